@@ -9,8 +9,11 @@ use ratatui::layout::Rect;
 use ratatui::style::{Color, Style};
 use ratatui::DefaultTerminal;
 
+use atlas_core::error::ReportStyle;
+use atlas_core::vm::exec::CollapseOrder;
 use atlas_core::vm::heap::{HeapScope, TermPtr};
 use atlas_core::vm::printer::Printer;
+use atlas_core::vm::type_eval::TypeOutcome;
 
 use crate::eval::{self, EvalEvent, EvalState};
 use crate::explorer::{ExplorerState, RootEntry};
@@ -91,6 +94,14 @@ impl<'a, 'h> CommandContext<'a, 'h> {
                     self.app.eval_line(&expr, true);
                 }
             }
+            Some("collapse") => {
+                let args = cmd.strip_prefix("collapse").unwrap_or("");
+                self.app.collapse_line(args);
+            }
+            Some("type") => {
+                let expr = cmd.strip_prefix("type").unwrap_or("");
+                self.app.type_line(expr);
+            }
             Some("source") => {
                 let path = cmd.strip_prefix("source").unwrap_or("").trim();
                 if path.is_empty() {
@@ -104,10 +115,11 @@ impl<'a, 'h> CommandContext<'a, 'h> {
                 Some(name) => self.open_panel(name),
             },
             Some("abort") => self.app.abort_eval(),
+            Some("gc") => self.app.collect_garbage(),
             Some("help") => self.open_dialogue(DialogueSpec {
                 title: "Help",
                 title_style: Style::new().fg(Color::Rgb(255, 165, 0)),
-                height: 17,
+                height: 20,
                 draw: draw_help_dialogue,
                 handle_key: ignore_dialogue_key,
             }),
@@ -295,11 +307,11 @@ impl<'h> App<'h> {
                         app.push(OutKind::Info, &line);
                     }
                 }
-                SubmitResult::Error { message, output } => {
+                SubmitResult::Error { error, output } => {
                     for line in output {
                         app.push(OutKind::Info, &line);
                     }
-                    app.push(OutKind::Error, &format!("prelude error: {message}"));
+                    app.push(OutKind::Error, &format!("prelude error: {error}"));
                 }
                 SubmitResult::StartEval { root, output } => {
                     for line in output {
@@ -405,12 +417,144 @@ impl<'h> App<'h> {
                 }
                 self.refresh_explorer();
             }
-            SubmitResult::Error { message, output } => {
+            SubmitResult::Error { error, output } => {
                 // Stage dumps that succeeded before the failure (show_ast).
                 for block in output {
                     self.push(OutKind::Info, &block);
                 }
-                self.push(OutKind::Error, &format!("error: {message}"));
+                self.push(OutKind::Error, &error.render(ReportStyle::Color));
+            }
+        }
+    }
+
+    /// `/collapse [bfs|dfs [<limit>]] <expr>`: evaluate `expr` and list the
+    /// answers of its superpositions, one per line (see
+    /// [`Executor::collapse`](atlas_core::vm::exec::Executor::collapse)).
+    fn collapse_line(&mut self, args: &str) {
+        let (mut order, mut limit, mut expr) = (CollapseOrder::BreadthFirst, COLLAPSE_LIMIT, args);
+        let (word, rest) = split_word(args);
+        let named = match word {
+            "bfs" => Some(CollapseOrder::BreadthFirst),
+            "dfs" => Some(CollapseOrder::DepthFirst),
+            _ => None,
+        };
+        if let Some(named) = named {
+            order = named;
+            expr = rest;
+            let (word, rest) = split_word(rest);
+            if let Ok(n) = word.parse() {
+                limit = n;
+                expr = rest;
+            }
+        }
+        let expr = expr.trim();
+        if expr.is_empty() {
+            self.push(
+                OutKind::Error,
+                "usage: /collapse [bfs|dfs [<limit>]] <expr>",
+            );
+            return;
+        }
+        if self.eval.is_running() {
+            self.push(
+                OutKind::Error,
+                "an evaluation is already pending (/abort or Ctrl+C to cancel it)",
+            );
+            return;
+        }
+        match self.session.submit(self.mode, expr) {
+            SubmitResult::StartEval { root, output } => {
+                for block in output {
+                    self.push(OutKind::Info, &block);
+                }
+                match eval::collapse(&self.session, root, order, limit) {
+                    Ok(collapsed) => {
+                        let count = collapsed.answers.len();
+                        for answer in collapsed.answers {
+                            let text = self.pretty(&answer);
+                            self.push(OutKind::Output, &text);
+                            eval::erase(&self.session, answer);
+                        }
+                        let plural = if count == 1 { "" } else { "s" };
+                        self.push(
+                            OutKind::Info,
+                            &format!("({count} answer{plural}, {} interactions)", collapsed.steps),
+                        );
+                        let pending = match collapsed.pending {
+                            1 => "1 branch".to_string(),
+                            n => format!("{n} branches"),
+                        };
+                        if collapsed.exhausted {
+                            self.push(
+                                OutKind::Error,
+                                &format!("(budget exhausted; {pending} unexplored)"),
+                            );
+                        } else if collapsed.pending > 0 {
+                            self.push(
+                                OutKind::Info,
+                                &format!("(limit reached; {pending} unexplored)"),
+                            );
+                        }
+                    }
+                    Err(error) => self.push(OutKind::Error, &format!("error: {error}")),
+                }
+                self.refresh_explorer();
+            }
+            SubmitResult::Output(blocks) => {
+                for block in blocks {
+                    self.push(OutKind::Output, &block);
+                }
+                self.refresh_explorer();
+            }
+            SubmitResult::Error { error, output } => {
+                for block in output {
+                    self.push(OutKind::Info, &block);
+                }
+                self.push(OutKind::Error, &error.render(ReportStyle::Color));
+            }
+        }
+    }
+
+    /// `/type <expr>`: statically type-evaluate `expr` without reducing it
+    /// (see [`eval::type_of`]) and report the outcome.
+    fn type_line(&mut self, expr: &str) {
+        let expr = expr.trim();
+        if expr.is_empty() {
+            self.push(OutKind::Error, "usage: /type <expr>");
+            return;
+        }
+        if self.eval.is_running() {
+            self.push(
+                OutKind::Error,
+                "an evaluation is already pending (/abort or Ctrl+C to cancel it)",
+            );
+            return;
+        }
+        match self.session.submit(self.mode, expr) {
+            SubmitResult::StartEval { root, output } => {
+                for block in output {
+                    self.push(OutKind::Info, &block);
+                }
+                let outcome = eval::type_of(&self.session, root);
+                let kind = match outcome {
+                    TypeOutcome::Ok(_) => OutKind::Output,
+                    TypeOutcome::Exhausted => OutKind::Info,
+                    _ => OutKind::Error,
+                };
+                self.push(kind, &outcome.to_string());
+                self.refresh_explorer();
+            }
+            SubmitResult::Output(blocks) => {
+                for block in blocks {
+                    self.push(OutKind::Output, &block);
+                }
+                self.refresh_explorer();
+            }
+            SubmitResult::Error { error, output } => {
+                for block in output {
+                    self.push(OutKind::Info, &block);
+                }
+                self.push(OutKind::Error, &error.render(ReportStyle::Color));
             }
         }
     }
@@ -442,11 +586,11 @@ impl<'h> App<'h> {
                 self.push(OutKind::Info, &format!("sourced {}", path.display()));
                 self.refresh_explorer();
             }
-            SubmitResult::Error { message, output } => {
+            SubmitResult::Error { error, output } => {
                 for block in output {
                     self.push(OutKind::Info, &block);
                 }
-                self.push(OutKind::Error, &format!("error: {message}"));
+                self.push(OutKind::Error, &error.render(ReportStyle::Color));
             }
         }
     }
@@ -510,8 +654,8 @@ impl<'h> App<'h> {
                 self.set_last_result(partial);
                 self.refresh_explorer();
             }
-            EvalEvent::Error { message } => {
-                self.push(OutKind::Error, &format!("error: {message}"));
+            EvalEvent::Error { error } => {
+                self.push(OutKind::Error, &format!("error: {error}"));
                 self.refresh_explorer();
             }
         }
@@ -541,6 +685,31 @@ impl<'h> App<'h> {
         }
     }
 
+    /// `/gc`: reclaim whatever the locals, the last result and the pending
+    /// evaluation no longer reach, leaks found by the explorer included.
+    /// Commands run between slices, so a pending evaluation, stepped or
+    /// running, is at rest and its current term is just one more root.
+    fn collect_garbage(&mut self) {
+        self.leaked.clear();
+        // SAFETY: these are all the pointers the App holds into the heap, the
+        // explorer's forged leak pointers having just been forgotten, and
+        // reduction only runs inside `tick` and `step`, so a pending one is
+        // at a slice boundary.
+        let reclaimed = unsafe { eval::collect_garbage(&self.session, &self.roots()) };
+        self.push(OutKind::Info, &format!("reclaimed {reclaimed}"));
+        self.refresh_explorer();
+    }
+
+    /// Every pointer the App owns into the heap, the explorer's leak
+    /// pointers aside.
+    fn roots(&self) -> Vec<&TermPtr<'h>> {
+        let mut roots: Vec<&TermPtr<'h>> =
+            self.session.locals().into_iter().map(|(.., p)| p).collect();
+        roots.extend(self.last_result.as_ref());
+        roots.extend(self.eval.root_ptr());
+        roots
+    }
+
     // ================================================================
     // Heap explorer
     // ================================================================
@@ -555,10 +724,7 @@ impl<'h> App<'h> {
             // Forgetting the previously forged pointers reverts those subgraphs
             // to leaked, so the fresh scan re-finds them.
             self.leaked.clear();
-            let mut roots: Vec<&TermPtr<'h>> =
-                self.session.locals().into_iter().map(|(.., p)| p).collect();
-            roots.extend(self.last_result.as_ref());
-            roots.extend(self.eval.root_ptr());
+            let roots = self.roots();
             // SAFETY: `roots` is every externally held pointer into this heap:
             // the App owns them all (locals, last result, pending eval root),
             // and the previous leak pointers were just forgotten. Reduction is
@@ -817,6 +983,18 @@ impl<'h> App<'h> {
     }
 }
 
+/// Answers `/collapse` lists unless given a limit.
+const COLLAPSE_LIMIT: usize = 10;
+
+/// The first whitespace-separated word of `s` and the rest after it.
+fn split_word(s: &str) -> (&str, &str) {
+    let s = s.trim_start();
+    match s.split_once(char::is_whitespace) {
+        Some((word, rest)) => (word, rest),
+        None => (s, ""),
+    }
+}
+
 fn run_builtin(ctx: &mut CommandContext<'_, '_>, cmd: &str) {
     ctx.run_builtin(cmd);
 }
@@ -864,6 +1042,7 @@ fn complete_builtin(ctx: &CommandContext<'_, '_>, cmd: &str) -> Vec<Completion>
     let values: Vec<&str> = match name {
         "lang" => vec!["core", "atlas", "agent"],
         "show" => vec!["ast"],
+        "collapse" => vec!["bfs", "dfs"],
         "panel" => ctx.app.panels.iter().map(|panel| panel.name).collect(),
         _ => Vec::new(),
     };
@@ -922,6 +1101,20 @@ fn builtin_commands() -> Vec<CommandSpec> {
             execute: run_builtin,
             complete: complete_builtin,
         },
+        CommandSpec {
+            name: "collapse",
+            aliases: &[],
+            description: "list the answers of a superposed result",
+            execute: run_builtin,
+            complete: complete_builtin,
+        },
+        CommandSpec {
+            name: "type",
+            aliases: &[],
+            description: "statically type-evaluate an expression",
+            execute: run_builtin,
+            complete: complete_builtin,
+        },
         CommandSpec {
             name: "source",
             aliases: &[],
@@ -943,6 +1136,13 @@ fn builtin_commands() -> Vec<CommandSpec> {
             execute: run_builtin,
             complete: complete_builtin,
         },
+        CommandSpec {
+            name: "gc",
+            aliases: &[],
+            description: "reclaim heap garbage",
+            execute: run_builtin,
+            complete: complete_builtin,
+        },
         CommandSpec {
             name: "help",
             aliases: &[],
@@ -1022,6 +1222,7 @@ mod tests {
     use std::path::PathBuf;
 
     use atlas_core::vm::heap::Heap;
+    use atlas_core::vm::term::Term;
 
     use super::*;
     use crate::LangArg;
@@ -1033,6 +1234,7 @@ mod tests {
             strong: false,
             no_prelude,
             source: Vec::<PathBuf>::new(),
+            command: None,
         }
     }
 
@@ -1237,6 +1439,121 @@ mod tests {
         });
     }
 
+    #[test]
+    fn collapse_lists_each_answer_up_to_the_limit() {
+        let heap = Heap::new();
+        heap.with(|h| {
+            let mut app = App::new(h, &args(true));
+            let outputs = |app: &App| {
+                (app.transcript.iter())
+                    .filter(|line| line.kind == OutKind::Output)
+                    .map(|line| line.text.clone())
+                    .collect::<Vec<_>>()
+            };
+            app.submit_line("/collapse &{&{1, 2}, 3} * 10");
+            assert_eq!(outputs(&app), ["30", "10", "20"]);
+            app.transcript.clear();
+            app.submit_line("/collapse dfs 2 &{&{1, 2}, 3} * 10");
+            assert_eq!(outputs(&app), ["10", "20"]);
+            let last = app.transcript.last().unwrap();
+            assert_eq!(last.text, "(limit reached; 1 branch unexplored)");
+            app.submit_line("/collapse dfs");
+            let last = app.transcript.last().unwrap();
+            assert_eq!(last.kind, OutKind::Error);
+            assert!(last.text.starts_with("usage: /collapse"));
+        });
+    }
+
+    #[test]
+    fn type_reports_the_static_outcome_without_reducing() {
+        let heap = Heap::new();
+        heap.with(|h| {
+            let mut app = App::new(h, &args(true));
+            let last = |app: &App| {
+                let line = app.transcript.last().unwrap();
+                (line.kind, line.text.clone())
+            };
+            app.submit_line(r"/type \&f x -> f (f x)");
+            assert_eq!(
+                last(&app),
+                (OutKind::Output, "forall a. (a -> a) -> a -> a".into())
+            );
+            // Not run: the erased argument would never be reduced.
+            app.submit_line(r"/type (\_ -> 0) (1 + true)");
+            assert_eq!(
+                last(&app),
+                (
+                    OutKind::Error,
+                    "mismatch: expected `Int`, found `Bool`".into()
+                )
+            );
+            app.submit_line(
+                r"/type &List = fix (\L -> type { Nil, Cons(typeof 0, L) }); List::Cons 1 List::Nil",
+            );
+            assert_eq!(
+                last(&app),
+                (OutKind::Output, "mu a. type{Nil, Cons(Int, a)}".into())
+            );
+            assert!(!app.eval.is_active());
+            app.submit_line("/type");
+            assert_eq!(last(&app), (OutKind::Error, "usage: /type <expr>".into()));
+        });
+    }
+
+    #[test]
+    fn gc_reclaims_what_no_root_reaches() {
+        let heap = Heap::new();
+        heap.with(|h| {
+            let mut app = App::new(h, &args(true));
+            app.submit_line(r"(\x -> x * 2) 21");
+            while app.eval.is_active() {
+                app.tick();
+            }
+            // Nothing owns this node; the kept result survives the collection.
+            let _ = h.alloc(Term::Int(7));
+            app.submit_line("/gc");
+            assert_eq!(app.transcript.last().unwrap().text, "reclaimed nodes 1");
+            app.submit_line("/gc");
+            assert_eq!(app.transcript.last().unwrap().text, "reclaimed nothing");
+            let result = app.last_result.as_ref().unwrap();
+            assert_eq!(app.pretty(result), "42");
+        });
+    }
+
+    #[test]
+    fn gc_keeps_a_pending_evaluation_between_slices() {
+        let heap = Heap::new();
+        heap.with(|h| {
+            let mut app = App::new(h, &args(true));
+            app.submit_line("/budget 10000000");
+            app.submit_line(r"fix (\&f n -> ?{0 -> 0; &m -> m + f (m - 1)} n) 3000");
+            app.tick();
+            assert!(app.eval.is_active(), "the evaluation spans several slices");
+            let collect = |app: &mut App| {
+                let _ = h.alloc(Term::Int(7));
+                app.submit_line("/gc");
+                let text = &app.transcript.last().unwrap().text;
+                // Besides the node, the cells of substitutions already made.
+                assert!(text.starts_with("reclaimed nodes 1, "), "{text}");
+            };
+            // Between two slices of a running evaluation, and while paused.
+            collect(&mut app);
+            app.tick();
+            app.eval.set_paused(true);
+            collect(&mut app);
+            app.eval.set_paused(false);
+            while app.eval.is_active() {
+                app.tick();
+            }
+            let result = app.last_result.as_ref().unwrap();
+            assert_eq!(app.pretty(result), "4501500");
+            // What the finished evaluation left behind, `fix`'s cycle included.
+            app.submit_line("/gc");
+            let text = &app.transcript.last().unwrap().text;
+            assert!(text.starts_with("reclaimed nodes "), "{text}");
+        });
+    }
+
     #[test]
     fn registered_panels_are_openable_and_receive_keys() {
         let heap = Heap::new();
@@ -0,0 +1,478 @@
+//! The non-interactive subcommands (`atlas run`, `atlas eval`): one submission
+//! through a [`Session`], reduced to completion without a terminal, after the
+//! prelude and any `--source` files, as the interactive session loads them.
+//! The normal form goes to stdout — as printer text, or with `--format json`
+//! as one JSON object holding its [readback](atlas_core::vm::json), the
+//! evaluation's metadata and the submission's notes (`output`). Diagnostics go
+//! to stderr as plain text, those notes (pattern warnings, a module's exports)
+//! included, and any failure — a parse error, an `Err` in the result (in one of
+//! its fields, too), a failed primitive, an exhausted budget — makes the exit
+//! status non-zero.
+
+use std::io::Write;
+use std::path::Path;
+use std::process::ExitCode;
+use std::time::Instant;
+
+use atlas_core::error::ReportStyle;
+use atlas_core::vm::exec::{Counted, ExecPolicy, Executor, FiniteBudget};
+use atlas_core::vm::heap::{HeapScope, TermPtr};
+use atlas_core::vm::json::{to_json, EvalMetadata};
+use atlas_core::vm::printer::Printer;
+use atlas_core::vm::term::Term;
+use serde_json::{json, Value};
+
+use crate::eval;
+use crate::session::{Session, SubmitError, SubmitResult};
+use crate::{Args, Command, Format};
+
+/// Run `command` against stdout/stderr.
+pub fn run<'h>(h: &'h HeapScope<'h>, args: &Args, command: &Command) -> ExitCode {
+    let stdout = std::io::stdout();
+    let stderr = std::io::stderr();
+    let ok = execute(h, args, command, &mut stdout.lock(), &mut stderr.lock());
+    if ok {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// Run `command`, writing its output to `out` and diagnostics to `err`.
+/// Returns whether it succeeded. Write errors (a closed pipe) are ignored: the
+/// exit status still reports how the evaluation went.
+fn execute<'h>(
+    h: &'h HeapScope<'h>,
+    args: &Args,
+    command: &Command,
+    out: &mut impl Write,
+    err: &mut impl Write,
+) -> bool {
+    let format = command.format();
+    let mut session = Session::new(h, args.budget, args.strong);
+    if !args.no_prelude {
+        match session.load_prelude() {
+            SubmitResult::Output(_) => {}
+            SubmitResult::Error { error, .. } => {
+                let _ = writeln!(err, "prelude {}", error.render(ReportStyle::Plain));
+                write_failure(format, out, &format!("prelude error: {error}"));
+                return false;
+            }
+            SubmitResult::StartEval { root, .. } => {
+                eval::erase(&session, root);
+                let message = "prelude error: expected declarations, got expression";
+                let _ = writeln!(err, "{message}");
+                write_failure(format, out, message);
+                return false;
+            }
+        }
+    }
+    for path in &args.source {
+        if !preload(&mut session, path, format, out, err) {
+            return false;
+        }
+    }
+    let submitted = match command {
+        Command::Run { file, .. } => session.source_file(file),
+        Command::Eval { expr, .. } => session.submit(args.lang.into(), expr),
+    };
+    match submitted {
+        SubmitResult::Output(blocks) => {
+            match format {
+                Format::Text => {
+                    for block in blocks {
+                        let _ = writeln!(out, "{block}");
+                    }
+                }
+                Format::Json => {
+                    let report = json!({ "result": null, "error": null, "output": blocks });
+                    let _ = writeln!(out, "{report}");
+                }
+            }
+            true
+        }
+        SubmitResult::Error { error, output } => {
+            forward(&output, err);
+            report_error(&error, format, out, err);
+            false
+        }
+        SubmitResult::StartEval { root, output } => {
+            forward(&output, err);
+            evaluate(&session, root, &output, format, out, err)
+        }
+    }
+}
+
+/// Pass a submission's notes on to stderr.
+fn forward(output: &[String], err: &mut impl Write) {
+    for block in output {
+        let _ = writeln!(err, "{block}");
+    }
+}
+
+/// Source a `--source` file before the command, as the interactive session
+/// does at startup: a core file's expression is reduced under the session's
+/// budget, and its result dropped. Returns whether that went through.
+fn preload<'h>(
+    session: &mut Session<'h>,
+    path: &Path,
+    format: Format,
+    out: &mut impl Write,
+    err: &mut impl Write,
+) -> bool {
+    let root = match session.source_file(path) {
+        SubmitResult::Output(output) => {
+            forward(&output, err);
+            return true;
+        }
+        SubmitResult::Error { error, output } => {
+            forward(&output, err);
+            report_error(&error, format, out, err);
+            return false;
+        }
+        SubmitResult::StartEval { root, output } => {
+            forward(&output, err);
+            root
+        }
+    };
+    let h = session.h;
+    let exec = Executor::with_extensions(h, FiniteBudget::new(session.budget), &session.extensions);
+    let result = if session.strong {
+        session.runtime.block_on(exec.normalize_at(root))
+    } else {
+        session.runtime.block_on(exec.whnf_at(root))
+    };
+    let error = match exec.take_extension_error() {
+        Some(e) => Some(e.to_string()),
+        None => err_message(h, &result),
+    };
+    exec.erase(h.pull(result));
+    match error {
+        Some(error) => {
+            let message = format!("{}: {error}", path.display());
+            let _ = writeln!(err, "error: {message}");
+            write_failure(format, out, &message);
+            false
+        }
+        None => true,
+    }
+}
+
+/// Report a submission that failed before evaluating.
+fn report_error(error: &SubmitError, format: Format, out: &mut impl Write, err: &mut impl Write) {
+    let _ = write!(err, "{}", error.render(ReportStyle::Plain));
+    if !matches!(error, SubmitError::Parse { .. } | SubmitError::Type { .. }) {
+        let _ = writeln!(err);
+    }
+    write_failure(format, out, &error.to_string());
+}
+
+/// In JSON mode, a failure before evaluation still answers with an object.
+fn write_failure(format: Format, out: &mut impl Write, message: &str) {
+    if format == Format::Json {
+        let _ = writeln!(out, "{}", json!({ "result": null, "error": message }));
+    }
+}
+
+/// Reduce `root` under the session's budget and strength, then print it, with
+/// the `output` of submitting it in the JSON report.
+fn evaluate<'h>(
+    session: &Session<'h>,
+    root: TermPtr<'h>,
+    output: &[String],
+    format: Format,
+    out: &mut impl Write,
+    err: &mut impl Write,
+) -> bool {
+    let h = session.h;
+    let policy = Counted::new(FiniteBudget::new(session.budget));
+    let exec = Executor::with_extensions(h, policy, &session.extensions);
+    let started = Instant::now();
+    let result = if session.strong {
+        session.runtime.block_on(exec.normalize_at(root))
+    } else {
+        session.runtime.block_on(exec.whnf_at(root))
+    };
+    let elapsed = started.elapsed();
+    let extension_error = exec.take_extension_error();
+    let (result, exhausted) = if extension_error.is_none() && !exec.policy.should_continue() {
+        stopped_short(session, result)
+    } else {
+        (result, false)
+    };
+    // Only a normal form is read back: a failed primitive abandons reduction
+    // midway, and so does running out of budget.
+    let normal = extension_error.is_none() && !exhausted;
+    let error = match &extension_error {
+        Some(e) => Some(e.to_string()),
+        None if !normal => Some(format!(
+            "no normal form within {} interactions",
+            session.budget
+        )),
+        None => err_message(h, &result),
+    };
+    if let Some(error) = &error {
+        let _ = writeln!(err, "error: {error}");
+    }
+    match format {
+        Format::Text => {
+            if error.is_none() {
+                let _ = writeln!(out, "{}", Printer::new(h).pretty(&result));
+            }
+        }
+        Format::Json => {
+            let metadata = EvalMetadata {
+                policy: &exec.policy,
+                extension_error: extension_error.as_ref(),
+                elapsed,
+            };
+            let mut report = metadata.to_json();
+            report["result"] = if normal {
+                to_json(h, &result)
+            } else {
+                Value::Null
+            };
+            report["error"] = error.clone().into();
+            report["output"] = json!(output);
+            let _ = writeln!(out, "{report}");
+        }
+    }
+    exec.erase(h.pull(result));
+    error.is_none()
+}
+
+/// Whether the reduction of `root`, having used up its budget, stopped short of
+/// the normal form rather than reaching it with its last interaction: it did
+/// if one more interaction fires. That one is not counted.
+fn stopped_short<'h>(session: &Session<'h>, root: TermPtr<'h>) -> (TermPtr<'h>, bool) {
+    let probe = Executor::with_extensions(session.h, FiniteBudget::new(1), &session.extensions);
+    let root = if session.strong {
+        session.runtime.block_on(probe.normalize_at(root))
+    } else {
+        session.runtime.block_on(probe.whnf_at(root))
+    };
+    let failed = probe.take_extension_error().is_some();
+    (root, failed || probe.policy.interactions() > 0)
+}
+
+/// If `result` holds an `Err` — as itself, a constructor's field, a side of a
+/// superposition or an argument of a partial application — what went wrong in
+/// the first one. Walks with a stack of its own, so a long list costs no
+/// native stack.
+fn err_message<'h>(h: &'h HeapScope<'h>, result: &TermPtr<'h>) -> Option<String> {
+    let mut pending = vec![result.addr()];
+    while let Some(addr) = pending.pop() {
+        let view = h.view_at(addr);
+        let fields = match &*view {
+            Term::Err {
+                backtrace: Some(trace),
+                ..
+            } => return Some(h.trace_get(trace).to_string()),
+            Term::Err { .. } => return Some("the result is an error".to_string()),
+            Term::Ctn { values, .. } | Term::Partial { args: values, .. } => values,
+            Term::Sup { ptr, .. } => {
+                let (left, right) = h.sup_addrs(ptr);
+                pending.extend([right, left]);
+                continue;
+            }
+            _ => continue,
+        };
+        pending.extend(
+            (0..h.pack_len(fields))
+                .rev()
+                .map(|i| h.pack_addr(fields, i)),
+        );
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LangArg;
+    use atlas_core::vm::heap::Heap;
+    use std::path::PathBuf;
+
+    fn args(budget: u64, no_prelude: bool) -> Args {
+        Args {
+            lang: LangArg::Core,
+            budget,
+            strong: false,
+            no_prelude,
+            source: Vec::new(),
+            command: None,
+        }
+    }
+
+    /// Run `command`, returning whether it succeeded, its stdout and stderr.
+    fn batch(args: &Args, command: Command) -> (bool, String, String) {
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        let ok = Heap::new().with(|h| execute(h, args, &command, &mut out, &mut err));
+        (
+            ok,
+            String::from_utf8(out).unwrap(),
+            String::from_utf8(err).unwrap(),
+        )
+    }
+
+    fn eval(expr: &str) -> Command {
+        Command::Eval {
+            expr: expr.to_string(),
+            format: Format::Text,
+        }
+    }
+
+    #[test]
+    fn eval_prints_the_normal_form() {
+        let (ok, out, err) = batch(&args(10_000, false), eval("fib 5"));
+        assert!(ok, "{err}");
+        assert_eq!(out, "8\n");
+        assert!(err.is_empty());
+    }
+
+    #[test]
+    fn failures_exit_non_zero_with_a_diagnostic() {
+        // A parse error, reported against the input.
+        let (ok, out, err) = batch(&args(1_000, true), eval("1 +"));
+        assert!(!ok);
+        assert!(out.is_empty());
+        assert!(err.contains("unexpected end of input"), "{err}");
+        // An `Err` result.
+        let (ok, out, err) = batch(&args(1_000, true), eval("1 / 0"));
+        assert!(!ok);
+        assert!(out.is_empty());
+        assert!(err.starts_with("error: "), "{err}");
+        // Budget exhaustion.
+        let (ok, _, err) = batch(&args(2, true), eval(r"(\x -> x + 1) ((\y -> y * 2) 3)"));
+        assert!(!ok);
+        assert!(err.contains("no normal form"), "{err}");
+        // The prelude is not loaded.
+        let (ok, _, err) = batch(&args(1_000, true), eval("fib 5"));
+        assert!(!ok);
+        assert!(err.contains("fib"), "{err}");
+    }
+
+    #[test]
+    fn an_err_nested_in_the_result_fails() {
+        let strong = Args {
+            strong: true,
+            ..args(1_000, true)
+        };
+        let tree = "type{Leaf, Node(type(), type())}";
+        let (ok, out, err) = batch(&strong, eval(&format!("{tree}::Node 1 (1 / 0)")));
+        assert!(!ok);
+        assert!(out.is_empty());
+        assert!(err.contains("division by zero"), "{err}");
+        // Deeper, and in a superposition.
+        let nested = format!("{tree}::Node 1 ({tree}::Node &{{2, 3 % 0}} 4)");
+        let (ok, _, err) = batch(&strong, eval(&nested));
+        assert!(!ok);
+        assert!(err.contains("division by zero in `%`"), "{err}");
+        let (ok, out, err) = batch(&strong, eval(&format!("{tree}::Node 1 2")));
+        assert!(ok, "{err}");
+        assert_eq!(out, "Node{1, 2}\n");
+    }
+
+    #[test]
+    fn a_budget_used_up_by_the_last_interaction_suffices() {
+        let expr = r"(\x -> x + 1) ((\y -> y * 2) 3)";
+        let json = Command::Eval {
+            expr: expr.to_string(),
+            format: Format::Json,
+        };
+        let (_, out, _) = batch(&args(1_000, true), json);
+        let report: Value = serde_json::from_str(&out).unwrap();
+        let total = report["interactions"]["total"].as_u64().unwrap();
+        let (ok, out, err) = batch(&args(total, true), eval(expr));
+        assert!(ok, "{err}");
+        assert_eq!(out, "7\n");
+        let (ok, _, err) = batch(&args(total - 1, true), eval(expr));
+        assert!(!ok);
+        assert!(err.contains("no normal form"), "{err}");
+    }
+
+    #[test]
+    fn run_evaluates_a_core_file() {
+        let dir = std::env::temp_dir().join(format!("atlas-batch-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file: PathBuf = dir.join("main.atc");
+        std::fs::write(&file, "&double = \\&x -> x + x;\ndouble 21\n").unwrap();
+        let (ok, out, err) = batch(
+            &args(1_000, true),
+            Command::Run {
+                file,
+                format: Format::Text,
+            },
+        );
+        assert!(ok, "{err}");
+        assert_eq!(out, "42\n");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn source_files_load_before_the_command() {
+        let dir = std::env::temp_dir().join(format!("atlas-batch-source-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let lib: PathBuf = dir.join("lib.atc");
+        std::fs::write(&lib, "&triple = \\&x -> x * 3;\n").unwrap();
+        let mut with_lib = args(1_000, true);
+        with_lib.source = vec![lib];
+        let (ok, out, err) = batch(&with_lib, eval("triple 14"));
+        assert!(ok, "{err}");
+        assert_eq!(out, "42\n");
+        // A source that fails stops the command.
+        with_lib.source.push(dir.join("missing.atc"));
+        let (ok, out, err) = batch(&with_lib, eval("triple 14"));
+        assert!(!ok);
+        assert!(out.is_empty());
+        assert!(err.contains("missing.atc"), "{err}");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn submission_notes_go_to_stderr() {
+        let dir = std::env::temp_dir().join(format!("atlas-batch-notes-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file: PathBuf = dir.join("main.at");
+        std::fs::write(&file, "pub f x = match x { 1 => 2 }\nmain = f 1\n").unwrap();
+        let warning = "warning: patterns are not exhaustive: `_` not covered";
+        let run = |format| Command::Run {
+            file: file.clone(),
+            format,
+        };
+        let (ok, out, err) = batch(&args(1_000, true), run(Format::Text));
+        assert!(ok, "{err}");
+        assert_eq!(out, "2\n");
+        assert_eq!(err, format!("{warning}\nexported f\n"));
+        let (ok, out, _) = batch(&args(1_000, true), run(Format::Json));
+        assert!(ok);
+        let report: Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(report["output"], json!([warning, "exported f"]));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn json_format_reports_result_and_metadata() {
+        let command = Command::Eval {
+            expr: r"(\x -> x + 1) 2".to_string(),
+            format: Format::Json,
+        };
+        let (ok, out, err) = batch(&args(1_000, true), command);
+        assert!(ok, "{err}");
+        let report: Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(report["result"], json!(3));
+        assert_eq!(report["error"], Value::Null);
+        assert_eq!(report["interactions"]["AppLam"], json!(1));
+        assert!(report["elapsed_ms"].is_number());
+
+        let command = Command::Eval {
+            expr: "1 / 0".to_string(),
+            format: Format::Json,
+        };
+        let (ok, out, _) = batch(&args(1_000, true), command);
+        assert!(!ok);
+        let report: Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(report["result"]["error"], json!("division by zero in `/`"));
+        assert!(report["error"].is_string());
+    }
+}
@@ -4,8 +4,12 @@
 use std::collections::VecDeque;
 use std::sync::Mutex;
 
-use atlas_core::vm::exec::{ExecPolicy, Executor, FiniteBudget, InteractionType, UnlimitedBudget};
-use atlas_core::vm::heap::TermPtr;
+use atlas_core::error::PrimError;
+use atlas_core::vm::exec::{
+    CollapseOrder, ExecPolicy, Executor, FiniteBudget, InteractionType, UnlimitedBudget,
+};
+use atlas_core::vm::heap::{Reclaimed, TermPtr};
+use atlas_core::vm::type_eval::{TypeEvaluator, TypeOutcome};
 
 use crate::session::Session;
 
@@ -79,8 +83,9 @@ pub enum EvalEvent<'h> {
         partial: TermPtr<'h>,
         steps: u64,
     },
+    /// A primitive failed; the evaluation is abandoned.
     Error {
-        message: String,
+        error: PrimError,
     },
 }
 
@@ -137,9 +142,9 @@ impl<'h> EvalState<'h> {
         let root = run.root.take().expect("running eval has a root");
         let (root, policy) = match reduce(session, root, run.strong, FiniteBudget::new(slice)) {
             Ok(result) => result,
-            Err(message) => {
+            Err(error) => {
                 *self = EvalState::Idle;
-                return Some(EvalEvent::Error { message });
+                return Some(EvalEvent::Error { error });
             }
         };
         let interactions = policy.interactions();
@@ -173,9 +178,9 @@ impl<'h> EvalState<'h> {
         let root = run.root.take().expect("running eval has a root");
         let (root, policy) = match reduce(session, root, run.strong, StepPolicy::default()) {
             Ok(result) => result,
-            Err(message) => {
+            Err(error) => {
                 *self = EvalState::Idle;
-                return Some(EvalEvent::Error { message });
+                return Some(EvalEvent::Error { error });
             }
         };
         let steps = run.steps;
@@ -226,7 +231,7 @@ fn reduce<'h, P: ExecPolicy>(
     root: TermPtr<'h>,
     strong: bool,
     policy: P,
-) -> Result<(TermPtr<'h>, P), String> {
+) -> Result<(TermPtr<'h>, P), PrimError> {
     let exec = Executor::with_extensions(session.h, policy, &session.extensions);
     let root = if strong {
         session.runtime.block_on(exec.normalize_at(root))
@@ -240,12 +245,80 @@ fn reduce<'h, P: ExecPolicy>(
     Ok((root, exec.policy))
 }
 
+/// What [`collapse`] found.
+pub struct Collapsed<'h> {
+    /// The answers, in the order they were found.
+    pub answers: Vec<TermPtr<'h>>,
+    pub steps: u64,
+    /// Branches left unexplored, by the limit or the budget.
+    pub pending: usize,
+    /// The budget ran out before the answers did.
+    pub exhausted: bool,
+}
+
+/// Enumerate up to `limit` answers of `root`'s superpositions (see
+/// [`Executor::collapse`]) in one go, within the session's budget.
+pub fn collapse<'h>(
+    session: &Session<'h>,
+    root: TermPtr<'h>,
+    order: CollapseOrder,
+    limit: usize,
+) -> Result<Collapsed<'h>, PrimError> {
+    let exec = Executor::with_extensions(
+        session.h,
+        FiniteBudget::new(session.budget),
+        &session.extensions,
+    );
+    let mut answers = Vec::new();
+    let mut collapse = exec.collapse(root).order(order).limit(limit);
+    while let Some(answer) = session.runtime.block_on(collapse.next()) {
+        answers.push(answer);
+    }
+    let pending = collapse.pending();
+    drop(collapse);
+    if let Some(error) = exec.take_extension_error() {
+        for answer in answers {
+            exec.erase(session.h.pull(answer));
+        }
+        return Err(error);
+    }
+    Ok(Collapsed {
+        answers,
+        steps: exec.policy.interactions(),
+        pending,
+        exhausted: !exec.policy.should_continue(),
+    })
+}
+
+/// Statically type-evaluate `root` (see [`TypeEvaluator`]) within the
+/// session's budget, then reclaim it: the graph is only read, never reduced.
+pub fn type_of<'h>(session: &Session<'h>, root: TermPtr<'h>) -> TypeOutcome {
+    let outcome = TypeEvaluator::new(session.h)
+        .with_extensions(&session.extensions)
+        .with_budget(session.budget)
+        .evaluate(&root);
+    erase(session, root);
+    outcome
+}
+
 /// Reclaim a term (an aborted partial result, a replaced `last_result`, …).
 pub fn erase<'h>(session: &Session<'h>, ptr: TermPtr<'h>) {
     let exec = Executor::with_extensions(session.h, UnlimitedBudget, &session.extensions);
     exec.erase(session.h.pull(ptr));
 }
 
+/// Reclaim everything in the session's heap that `roots` do not reach (see
+/// [`Executor::collect_garbage`]).
+///
+/// # Safety
+///
+/// `roots` must be every pointer the REPL holds into the heap, and no
+/// evaluation may be mid-slice.
+pub unsafe fn collect_garbage<'h>(session: &Session<'h>, roots: &[&TermPtr<'h>]) -> Reclaimed {
+    let exec = Executor::with_extensions(session.h, UnlimitedBudget, &session.extensions);
+    unsafe { exec.collect_garbage(roots) }
+}
+
 /// Reduce `root` to completion under the session's budget/strength (tests and
 /// preload use this; the interactive path goes through [`EvalState`]).
 #[cfg(test)]
@@ -291,7 +364,7 @@ mod tests {
                         break result;
                     }
                     EvalEvent::BudgetExhausted { .. } => panic!("budget exhausted"),
-                    EvalEvent::Error { message } => panic!("evaluation failed: {message}"),
+                    EvalEvent::Error { error } => panic!("evaluation failed: {error}"),
                 }
             };
             assert!(stepped > 0, "at least one interaction fires");
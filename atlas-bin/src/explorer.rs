@@ -3,7 +3,7 @@
 
 use std::collections::{HashMap, HashSet, VecDeque};
 
-use atlas_core::vm::heap::{Addr, ArenaKind, HeapScope, TermPtr, TypeInfo};
+use atlas_core::vm::heap::{Addr, ArenaKind, HeapScope, Layout, TermPtr};
 use atlas_core::vm::term::Term;
 
 /// A top-level graph root: a name plus the live pointer it borrows.
@@ -216,6 +216,7 @@ fn node_children<'h>(h: &'h HeapScope<'h>, addr: Addr) -> Vec<(String, Addr)> {
             vec![("lhs".into(), lhs.addr()), ("rhs".into(), rhs.addr())]
         }
         Term::Uop { val, .. } => vec![("val".into(), val.addr())],
+        Term::Prj { val, ty } => vec![("val".into(), val.addr()), ("ty".into(), ty.addr())],
         Term::Var { .. }
         | Term::VarId(_)
         | Term::Wld
@@ -231,13 +232,21 @@ fn node_children<'h>(h: &'h HeapScope<'h>, addr: Addr) -> Vec<(String, Addr)> {
 }
 
 fn type_children<'h>(h: &'h HeapScope<'h>, ty_addr: Addr) -> Vec<(String, Addr)> {
-    match h.type_info_at(ty_addr) {
-        TypeInfo::Product { fields, .. } => fields
+    let info = h.type_info_at(ty_addr);
+    let mut out: Vec<(String, Addr)> = match &info.layout {
+        Layout::Any | Layout::Atom(_) | Layout::Sort(_) => vec![],
+        Layout::Pi { input, output, .. } => {
+            vec![
+                ("ty.in".to_string(), *input),
+                ("ty.out".to_string(), *output),
+            ]
+        }
+        Layout::Product(fields) => fields
             .iter()
             .enumerate()
             .map(|(i, &addr)| (format!("ty[{i}]"), addr))
             .collect(),
-        TypeInfo::Sum { variants, .. } => variants
+        Layout::Sum(variants) => variants
             .iter()
             .flat_map(|variant| {
                 let name = h.variant_name(variant.name).to_string();
@@ -249,7 +258,14 @@ fn type_children<'h>(h: &'h HeapScope<'h>, ty_addr: Addr) -> Vec<(String, Addr)>
                     .collect::<Vec<_>>()
             })
             .collect(),
-    }
+    };
+    out.extend(
+        (info.props.entries.iter()).map(|e| (format!("ty.{}", h.variant_name(e.name)), e.sig)),
+    );
+    out.extend(
+        (info.ops.entries.iter()).map(|e| (format!("ty({})", h.variant_name(e.name)), e.sig)),
+    );
+    out
 }
 
 fn interaction_hint(source: &str, target: &str) -> Option<&'static str> {
@@ -314,9 +330,14 @@ fn summarize<'h>(h: &'h HeapScope<'h>, addr: Addr) -> String {
         }
         Term::Bop { op, .. } => format!("Bop {op:?}"),
         Term::Uop { op, .. } => format!("Uop {op:?}"),
+        Term::Prj { .. } => "Prj".into(),
         Term::And { .. } => "And".into(),
         Term::Or { .. } => "Or".into(),
         Term::Wld => "Wld".into(),
+        Term::Err {
+            backtrace: Some(trace),
+            ..
+        } => format!("Err {}", truncate(h.trace_get(trace).fault.to_string())),
         Term::Err { .. } => "Err".into(),
         Term::Int(value) => format!("Int {value}"),
         Term::Float(value) => format!("Float {value}"),
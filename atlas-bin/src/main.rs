@@ -1,16 +1,19 @@
 //! The `atlas` terminal application: a ratatui REPL over both atlas languages
 //! (core evaluates; the surface language parses to an AST for now), with a
-//! collapsible heap-explorer / reduction-stepper side panel.
+//! collapsible heap-explorer / reduction-stepper side panel. The `run` and
+//! `eval` subcommands evaluate without a terminal, for scripts and CI.
 
 mod app;
+mod batch;
 mod eval;
 mod explorer;
 mod input;
 mod session;
 mod ui;
 
-use clap::{Parser, ValueEnum};
+use clap::{Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
+use std::process::ExitCode;
 
 use atlas_core::vm::heap::Heap;
 use session::LangMode;
@@ -36,38 +39,75 @@ impl From<LangArg> for LangMode {
 #[command(name = "atlas", about = "The Atlas interactive terminal")]
 pub struct Args {
     /// Startup language mode (switch at runtime with /lang).
-    #[arg(long, value_enum, default_value_t = LangArg::Core)]
+    #[arg(long, global = true, value_enum, default_value_t = LangArg::Core)]
     lang: LangArg,
 
     /// Reduction budget: the maximum number of interactions per evaluation.
-    #[arg(long, default_value_t = 1_000_000)]
+    #[arg(long, global = true, default_value_t = 1_000_000)]
     budget: u64,
 
     /// Start with strong normalization (reduce under binders) enabled.
-    #[arg(long)]
+    #[arg(long, global = true)]
     strong: bool,
 
     /// Do not load the embedded core prelude at startup.
-    #[arg(long)]
+    #[arg(long, global = true)]
     no_prelude: bool,
 
     /// Source a file at startup (repeatable): `.atc` (core) is evaluated into
-    /// the session locals, `.at` (atlas) is parsed.
+    /// the session locals, `.at` (atlas) binds its `pub` declarations.
     #[arg(long, short = 's', value_name = "FILE")]
     source: Vec<PathBuf>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// Evaluate without the terminal UI: print the normal form to stdout, and exit
+/// non-zero if there is none.
+#[derive(Subcommand)]
+pub enum Command {
+    /// Evaluate a file: `.atc` (core), or `.at` (atlas), whose `main` is run.
+    Run {
+        file: PathBuf,
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+    },
+    /// Evaluate an expression in the `--lang` language.
+    Eval {
+        #[arg(short = 'e', long = "expr", value_name = "EXPR")]
+        expr: String,
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+    },
+}
+
+impl Command {
+    pub fn format(&self) -> Format {
+        match self {
+            Command::Run { format, .. } | Command::Eval { format, .. } => *format,
+        }
+    }
 }
 
-fn main() -> std::io::Result<()> {
+/// How a batch subcommand prints its result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// The normal form as the REPL prints it.
+    Text,
+    /// One JSON object: the result's readback, the error if there is one, and
+    /// the interaction counts and time it took. Without `--strong` only the
+    /// result's head is reduced, so its fields may read back as redexes.
+    Json,
+}
+
+fn main() -> std::io::Result<ExitCode> {
     let args = Args::parse();
-    // Reduction is driven by recursive `async` combinators, so its stack use
-    // grows with term/dup-chain depth. Run the whole app on a thread with a
-    // large stack so deep-but-finite reductions don't overflow.
-    std::thread::Builder::new()
-        .stack_size(512 * 1024 * 1024)
-        .spawn(move || run(args))
-        .expect("failed to spawn UI thread")
-        .join()
-        .expect("UI thread panicked")
+    if let Some(command) = &args.command {
+        let heap = Heap::new();
+        return Ok(heap.with(|h| batch::run(h, &args, command)));
+    }
+    run(args).map(|()| ExitCode::SUCCESS)
 }
 
 fn run(args: Args) -> std::io::Result<()> {
@@ -3,19 +3,26 @@
 //! by [`SubmitResult`] so the TUI (and tests) can render outputs themselves.
 
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
-use atlas_core::core::ast::{desugar_open, Binding, Node};
+use atlas_core::core::ast::{desugar_open, desugar_open_spanned, Binding, Node};
 use atlas_core::core::expr::Expr;
-use atlas_core::core::parse::{parse_repl, ReplInput};
-use atlas_core::extension::{CombinedExtensions, Extensions};
+use atlas_core::core::parse::{parse_repl_spanned, ReplInput};
+use atlas_core::core::span::NodeSpans;
+use atlas_core::error::{Error, ParseError, ReportStyle, TypeError};
+use atlas_core::extension::{CombinedExtensions, Extensions, TypeExtensions};
 use atlas_core::vm::heap::{HeapScope, TermPtr};
 use atlas_core::vm::printer::Printer;
 use atlas_io::IoExtensions;
+use atlas_lang::ast::ExprSpans;
+use atlas_lang::check::{DataType, Scheme, Types};
+use atlas_lang::lower::{qualify, Globals};
 use atlas_wasm::WasmExtensions;
 
 const PRELUDE: &str = include_str!("prelude.atc");
 
-pub type ReplExtensions = CombinedExtensions<IoExtensions, WasmExtensions>;
+pub type ReplExtensions =
+    CombinedExtensions<TypeExtensions, CombinedExtensions<IoExtensions, WasmExtensions>>;
 
 /// Which language the REPL interprets a line as.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -107,6 +114,132 @@ impl<'h> Locals<'h> {
     }
 }
 
+/// A trait's methods, as in [`Globals::traits`].
+type TraitMethods = Vec<(String, bool)>;
+
+/// What the private declarations of a module being bound displaced, oldest
+/// first: locals, entries of the atlas tables (variants' bindings, field
+/// lists, traits, methods, bounds) and of the checked types, each by name with
+/// what it held before if anything, and whatever was bound under the path of
+/// a private `mod`.
+#[derive(Default)]
+struct Hidden<'h> {
+    locals: Vec<(String, Option<Local<'h>>)>,
+    ctors: Vec<(String, Option<String>)>,
+    fields: Vec<(String, Option<Vec<String>>)>,
+    traits: Vec<(String, Option<TraitMethods>)>,
+    methods: Vec<(String, Option<String>)>,
+    bounds: Vec<(String, Option<Vec<Option<String>>>)>,
+    values: Vec<(String, Option<Scheme>)>,
+    data: Vec<(String, Option<DataType>)>,
+    modules: Vec<(String, ModuleScope<'h>)>,
+}
+
+impl<'h> Hidden<'h> {
+    fn extend(&mut self, later: Hidden<'h>) {
+        self.locals.extend(later.locals);
+        self.ctors.extend(later.ctors);
+        self.fields.extend(later.fields);
+        self.traits.extend(later.traits);
+        self.methods.extend(later.methods);
+        self.bounds.extend(later.bounds);
+        self.values.extend(later.values);
+        self.data.extend(later.data);
+        self.modules.extend(later.modules);
+    }
+
+    /// Stop hiding the names `bound` displaced, returning the locals that
+    /// were to be put back under them.
+    fn forget(&mut self, bound: &Hidden<'h>) -> Vec<Local<'h>> {
+        fn keep<V, W>(entries: &mut Vec<(String, V)>, bound: &[(String, W)]) {
+            entries.retain(|(name, _)| bound.iter().all(|(b, _)| b != name));
+        }
+        keep(&mut self.ctors, &bound.ctors);
+        keep(&mut self.fields, &bound.fields);
+        keep(&mut self.traits, &bound.traits);
+        keep(&mut self.methods, &bound.methods);
+        keep(&mut self.bounds, &bound.bounds);
+        keep(&mut self.values, &bound.values);
+        keep(&mut self.data, &bound.data);
+        let names = &bound.locals;
+        self.locals
+            .extract_if(.., |(name, _)| names.iter().any(|(n, _)| n == name))
+            .filter_map(|(_, local)| local)
+            .collect()
+    }
+}
+
+/// Set `key` in `map` to `value`, or remove it; returns the key with what it
+/// held before.
+fn replace<V>(map: &mut HashMap<String, V>, key: String, value: Option<V>) -> (String, Option<V>) {
+    let before = match value {
+        Some(value) => map.insert(key.clone(), value),
+        None => map.remove(&key),
+    };
+    (key, before)
+}
+
+/// Undo a run of [`replace`]s, newest first, so a key replaced twice ends up
+/// with what it first held.
+fn restore<V>(map: &mut HashMap<String, V>, displaced: Vec<(String, Option<V>)>) {
+    for (key, before) in displaced.into_iter().rev() {
+        replace(map, key, before);
+    }
+}
+
+/// Everything bound under one atlas module path: the table entries of the
+/// module and its submodules, and the locals, atlas table entries and checked
+/// types named under it.
+#[derive(Default)]
+struct ModuleScope<'h> {
+    modules: Vec<(String, HashMap<String, bool>)>,
+    locals: Vec<(String, Local<'h>)>,
+    ctors: Vec<(String, String)>,
+    fields: Vec<(String, Vec<String>)>,
+    traits: Vec<(String, TraitMethods)>,
+    methods: Vec<(String, String)>,
+    bounds: Vec<(String, Vec<Option<String>>)>,
+    values: Vec<(String, Scheme)>,
+    data: Vec<(String, DataType)>,
+}
+
+/// An atlas source being bound, which its type errors are reported against:
+/// the name it goes by (as for [`SubmitError::Parse`]), its text, and where
+/// its expressions are in it.
+struct AtlasSource<'s> {
+    name: &'s str,
+    text: &'s str,
+    spans: &'s ExprSpans<'s>,
+}
+
+impl AtlasSource<'_> {
+    fn error(&self, error: TypeError) -> SubmitError {
+        SubmitError::Type {
+            name: self.name.to_string(),
+            source: self.text.to_string(),
+            error: Box::new(error),
+        }
+    }
+}
+
+/// The names an atlas declaration binds.
+fn decl_names<'a>(decl: &'a atlas_lang::ast::Declaration<'a>) -> Vec<&'a str> {
+    use atlas_lang::ast::{Declaration, Pattern};
+    match decl {
+        Declaration::Let(d) => match d.pattern {
+            Pattern::Identifier(name) => vec![name],
+            _ => Vec::new(),
+        },
+        Declaration::Fn(d) => vec![d.name],
+        Declaration::Rec(d) => d.fns.iter().map(|f| f.name).collect(),
+        Declaration::Enum(d) => vec![d.name],
+        Declaration::Struct(d) => vec![d.name],
+        Declaration::Trait(d) => vec![d.name],
+        Declaration::Mod(d) => vec![d.name],
+        Declaration::Alias(_) | Declaration::Impl(_) => Vec::new(),
+    }
+}
+
 /// The outcome of submitting one line to the session.
 pub enum SubmitResult<'h> {
     /// A core expression was lowered; the caller should start evaluating it.
@@ -122,11 +255,80 @@ pub enum SubmitResult<'h> {
     /// step (when `show_ast` is enabled), so a desugar/lowering error still
     /// shows the stages that succeeded.
     Error {
-        message: String,
+        error: SubmitError,
         output: Vec<String>,
     },
 }
 
+/// Why a submission failed.
+#[derive(Debug)]
+pub enum SubmitError {
+    /// The input does not parse. Keeps the source and the name it goes by (a
+    /// path, `<repl>`, ...) so the errors can be rendered against it.
+    Parse {
+        name: String,
+        source: String,
+        error: ParseError,
+    },
+    /// The input parses but is ill-typed. Keeps its source like a parse
+    /// error, to point into it.
+    Type {
+        name: String,
+        source: String,
+        error: Box<TypeError>,
+    },
+    /// A later stage of the core or atlas pipeline rejected the input.
+    Pipeline(Error),
+    /// The session itself cannot take it: an unreadable file, a binding form
+    /// the REPL does not support, ...
+    Session(String),
+}
+
+impl From<Error> for SubmitError {
+    fn from(e: Error) -> Self {
+        SubmitError::Pipeline(e)
+    }
+}
+
+impl std::fmt::Display for SubmitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SubmitError::Parse { error, .. } => write!(f, "{error}"),
+            SubmitError::Type { error, .. } => write!(f, "{error}"),
+            SubmitError::Pipeline(e) => write!(f, "{e}"),
+            SubmitError::Session(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl SubmitError {
+    fn parse(name: &str, source: &str, error: ParseError) -> Self {
+        SubmitError::Parse {
+            name: name.to_string(),
+            source: source.to_string(),
+            error,
+        }
+    }
+
+    /// The error as transcript text: parse and type errors as source
+    /// reports, anything else on one `error:` line.
+    pub fn render(&self, style: ReportStyle) -> String {
+        match self {
+            SubmitError::Parse {
+                name,
+                source,
+                error,
+            } => error.render(name, source, style),
+            SubmitError::Type {
+                name,
+                source,
+                error,
+            } => error.render(name, source, style),
+            _ => format!("error: {self}"),
+        }
+    }
+}
+
 pub struct Session<'h> {
     pub h: &'h HeapScope<'h>,
     pub runtime: tokio::runtime::Runtime,
@@ -139,9 +341,15 @@ pub struct Session<'h> {
     pub strong: bool,
     /// Dump the AST of each submitted line (both languages; `/show ast`).
     pub show_ast: bool,
-    /// Atlas enum variants in scope: variant name -> the local binding of the
-    /// enum type it constructs (fed to the atlas lowering as its ctor map).
-    atlas_ctors: HashMap<String, String>,
+    /// Atlas items in scope for the lowering: enum variants (each naming the
+    /// local binding of the enum it constructs), structs and struct-like
+    /// variants with their field names, the declared modules, and traits with
+    /// their methods and the generic fns they bound. Items of a
+    /// module are bound as locals under their qualified names (`geo::area`).
+    atlas: Globals,
+    /// The types of the atlas locals and of the enums and structs declared,
+    /// which the checker sees lines against. Keyed like `atlas`.
+    types: Types,
 }
 
 impl<'h> Session<'h> {
@@ -155,12 +363,16 @@ impl<'h> Session<'h> {
         Session {
             h,
             runtime,
-            extensions: ReplExtensions::new(IoExtensions, WasmExtensions::default()),
+            extensions: ReplExtensions::new(
+                TypeExtensions,
+                CombinedExtensions::new(IoExtensions, WasmExtensions::default()),
+            ),
             locals: Locals::new(),
             budget,
             strong,
             show_ast: false,
-            atlas_ctors: HashMap::new(),
+            atlas: Globals::default(),
+            types: Types::default(),
         }
     }
 
@@ -169,128 +381,495 @@ impl<'h> Session<'h> {
     /// (dumping ASTs along the way when `show_ast` is set).
     pub fn submit(&mut self, mode: LangMode, line: &str) -> SubmitResult<'h> {
         match mode {
-            LangMode::Core => self.submit_core(line),
+            LangMode::Core => self.submit_core("<repl>", line),
             LangMode::Atlas => self.submit_atlas(line),
             LangMode::Agent => SubmitResult::Output(Vec::new()),
         }
     }
 
     pub fn load_prelude(&mut self) -> SubmitResult<'h> {
-        self.submit_core(PRELUDE)
+        self.submit_core("<prelude>", PRELUDE)
     }
 
-    fn submit_core(&mut self, line: &str) -> SubmitResult<'h> {
-        match parse_repl(line) {
-            Ok(ReplInput::Expr(node)) => {
+    fn submit_core(&mut self, name: &str, line: &str) -> SubmitResult<'h> {
+        match parse_repl_spanned(line) {
+            Ok((ReplInput::Expr(node), log)) => {
                 let mut output = Vec::new();
-                match self.lower_input(&node, &mut output) {
+                let spans = NodeSpans::new([&node], log);
+                match self.lower_input(&node, Some(&spans), &mut output) {
                     Ok(root) => SubmitResult::StartEval { root, output },
-                    Err(message) => SubmitResult::Error { message, output },
+                    Err(e) => SubmitResult::Error {
+                        error: e.into(),
+                        output,
+                    },
                 }
             }
-            Ok(ReplInput::Decl(bindings)) => self.bind_decl(bindings),
-            Err(message) => SubmitResult::Error {
-                message,
+            Ok((ReplInput::Decl(bindings), _)) => self.bind_decl(bindings),
+            Err(e) => SubmitResult::Error {
+                error: SubmitError::parse(name, line, e),
                 output: Vec::new(),
             },
         }
     }
 
-    /// Parse one atlas line: lower an expression to the core IR for evaluation,
-    /// or bind a declaration (`let` / `fn` / `enum`) as a session local.
+    /// Parse one atlas line: type check it, then lower an expression to the
+    /// core IR for evaluation, or bind a declaration (`let` / `fn` / `enum` /
+    /// `struct`) as a session local.
     fn submit_atlas(&mut self, line: &str) -> SubmitResult<'h> {
-        match atlas_lang::parser::parse_repl(line) {
-            Ok(atlas_lang::ast::ReplInput::Expr(e)) => {
+        let (input, log) = match atlas_lang::parser::parse_repl_spanned(line) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                return SubmitResult::Error {
+                    error: SubmitError::parse("<repl>", line, e),
+                    output: Vec::new(),
+                }
+            }
+        };
+        let spans = ExprSpans::of_repl(&input, log);
+        let source = AtlasSource {
+            name: "<repl>",
+            text: line,
+            spans: &spans,
+        };
+        match &input {
+            atlas_lang::ast::ReplInput::Expr(e) => {
                 let mut output = Vec::new();
                 if self.show_ast {
                     output.push(format!("{e:#?}"));
                 }
-                let lowered = atlas_lang::lower::lower_expr_open(&e, &self.atlas_ctors)
+                let inferred =
+                    match atlas_lang::check::check_expr(e, &self.atlas, &self.types, "", &spans) {
+                        Ok(inferred) => inferred,
+                        Err(error) => {
+                            return SubmitResult::Error {
+                                error: source.error(error),
+                                output,
+                            }
+                        }
+                    };
+                let mut warnings = Vec::new();
+                let lowered = atlas_lang::lower::lower_expr_open(
+                    e,
+                    &self.atlas,
+                    &inferred,
+                    "",
+                    &mut warnings,
+                );
+                output.extend(warnings.iter().map(|w| format!("warning: {w}")));
+                let lowered = lowered
+                    .map_err(Error::from)
                     .and_then(|expr| self.lower_core(&expr, &mut output));
                 match lowered {
                     Ok(root) => SubmitResult::StartEval { root, output },
-                    Err(message) => SubmitResult::Error { message, output },
+                    Err(e) => SubmitResult::Error {
+                        error: e.into(),
+                        output,
+                    },
                 }
             }
-            Ok(atlas_lang::ast::ReplInput::Declaration(decl)) => {
+            atlas_lang::ast::ReplInput::Declaration(decl) => {
                 let mut output = Vec::new();
                 if self.show_ast {
                     output.push(format!("{decl:#?}"));
                 }
-                match self.bind_atlas_decl(&decl, &mut output) {
+                match self.bind_atlas_decl(decl, "", &source, &mut Vec::new(), &mut output, None) {
                     Ok(()) => SubmitResult::Output(output),
-                    Err(message) => SubmitResult::Error { message, output },
+                    Err(error) => SubmitResult::Error { error, output },
                 }
             }
-            Err(message) => SubmitResult::Error {
-                message,
-                output: Vec::new(),
-            },
         }
     }
 
-    /// Lower one atlas declaration and store it as a local (lazily, like core
-    /// bindings). Atlas has no affine/auto-dup annotation, so every binding is
-    /// auto-dup (usable any number of times). Enum declarations additionally
-    /// register their variant names so later lines construct the bound type.
+    /// Type check and lower one atlas declaration of `module` (`""` at the top
+    /// level), read from `source`, and store it as a local (lazily, like core
+    /// bindings), with its type for the lines after it. Atlas has no
+    /// affine/auto-dup annotation, so every binding is auto-dup (usable any
+    /// number of times). Enum declarations additionally register their variant
+    /// names so later lines construct the bound type, and structs (and
+    /// struct-like variants) their field names. A trait registers its methods
+    /// and binds its default methods too, and a generic fn its bounds. A `rec`
+    /// group binds its tuple, then each of its functions as a field of it. An
+    /// impl binds its dictionary under its global name. A `mod` binds its items in
+    /// turn; see [`Self::bind_atlas_mod`].
+    ///
+    /// With `hidden`, a private declaration records the locals and table
+    /// entries it displaces there, to be put back when its module is done.
     fn bind_atlas_decl(
         &mut self,
         decl: &atlas_lang::ast::Declaration,
+        module: &str,
+        source: &AtlasSource,
+        files: &mut Vec<PathBuf>,
         output: &mut Vec<String>,
-    ) -> Result<(), String> {
-        let lowered = match atlas_lang::lower::lower_decl_open(decl, &self.atlas_ctors)? {
+        hidden: Option<&mut Hidden<'h>>,
+    ) -> Result<(), SubmitError> {
+        if let atlas_lang::ast::Declaration::Mod(m) = decl {
+            return self.bind_atlas_mod(m, module, source, files, output, hidden);
+        }
+        let checked =
+            atlas_lang::check::check_decl(decl, &self.atlas, &self.types, module, source.spans)
+                .map_err(|e| source.error(e))?;
+        let mut warnings = Vec::new();
+        let lowered = atlas_lang::lower::lower_decl_open(
+            decl,
+            &self.atlas,
+            &checked.inferred,
+            module,
+            &mut warnings,
+        );
+        output.extend(warnings.iter().map(|w| format!("warning: {w}")));
+        let lowered = match lowered.map_err(Error::from)? {
             Some(lowered) => lowered,
             // a `let _ = ..;`: the value is dropped
             None => return Ok(()),
         };
         let ptr = self.lower_core(&lowered.expr, output)?;
-        self.locals
-            .bind(lowered.name.clone(), LocalKind::AutoDup, ptr);
-        for variant in lowered.variants {
-            self.atlas_ctors.insert(variant, lowered.name.clone());
+        let mut locals = vec![lowered.name];
+        locals.extend(lowered.also.iter().map(|(name, _)| name.clone()));
+        // An impl's dictionary is global, whatever module declares it.
+        let is_impl = matches!(decl, atlas_lang::ast::Declaration::Impl(_));
+        let is_trait = matches!(decl, atlas_lang::ast::Declaration::Trait(_));
+        if let Some(items) = self.atlas.modules.get_mut(module) {
+            let methods = lowered.methods.iter().map(|(m, _)| m);
+            for item in lowered.variants.iter().chain(methods) {
+                items.insert(item.clone(), decl.is_pub());
+            }
+            if !is_impl {
+                items.insert(locals[0].clone(), decl.is_pub());
+            }
+        }
+        for name in locals.iter_mut().skip(is_impl as usize) {
+            *name = qualify(module, name);
+        }
+        let name = locals[0].clone();
+
+        // The rest are lowered once the first is bound, as a `rec` group's
+        // functions are projections of its tuple.
+        let mut displaced = Hidden::default();
+        let mut schemes: HashMap<_, _> = checked
+            .values
+            .into_iter()
+            .map(|(name, scheme)| (qualify(module, &name), scheme))
+            .collect();
+        let mut ptr = Some(ptr);
+        for (i, local) in locals.into_iter().enumerate() {
+            let ptr = match ptr.take() {
+                Some(ptr) => ptr,
+                None => self.lower_core(&lowered.also[i - 1].1, output)?,
+            };
+            displaced
+                .locals
+                .push((local.clone(), self.locals.map.remove(&local)));
+            let scheme = schemes.remove(&local);
+            displaced
+                .values
+                .push(replace(&mut self.types.values, local.clone(), scheme));
+            self.locals.bind(local, LocalKind::AutoDup, ptr);
+        }
+        // What is left are a trait's methods.
+        for (method, scheme) in schemes {
+            displaced
+                .values
+                .push(replace(&mut self.types.values, method, Some(scheme)));
+        }
+        for (ty, data) in checked.data {
+            displaced.data.push(replace(
+                &mut self.types.data,
+                qualify(module, &ty),
+                Some(data),
+            ));
+        }
+        for variant in &lowered.variants {
+            let owner = Some(name.clone());
+            displaced.ctors.push(replace(
+                &mut self.atlas.ctors,
+                qualify(module, variant),
+                owner,
+            ));
+        }
+        for (ctor, fields) in lowered.fields {
+            displaced.fields.push(replace(
+                &mut self.atlas.fields,
+                qualify(module, &ctor),
+                Some(fields),
+            ));
+        }
+        if is_trait {
+            for (method, _) in &lowered.methods {
+                let qualified = qualify(module, method);
+                displaced.methods.push(replace(
+                    &mut self.atlas.methods,
+                    qualified,
+                    Some(name.clone()),
+                ));
+            }
+            let methods = Some(lowered.methods);
+            displaced
+                .traits
+                .push(replace(&mut self.atlas.traits, name.clone(), methods));
+        } else if !is_impl {
+            // A later binding of a method's name shadows the method.
+            displaced
+                .methods
+                .push(replace(&mut self.atlas.methods, name.clone(), None));
+        }
+        if !is_impl {
+            let bounds = (!lowered.bounds.is_empty()).then_some(lowered.bounds);
+            displaced
+                .bounds
+                .push(replace(&mut self.atlas.bounds, name, bounds));
+        }
+        match hidden {
+            Some(hidden) if !decl.is_pub() && !is_impl => hidden.extend(displaced),
+            Some(hidden) => {
+                // Exported over an earlier private binding of the same name:
+                // the module's own binding stays visible.
+                for local in hidden.forget(&displaced) {
+                    crate::eval::erase(self, local.ptr);
+                }
+            }
+            None => {}
         }
         Ok(())
     }
 
+    /// Bind a `mod` declared in `parent`: its items in order, each under the
+    /// module's path. The body of `mod foo;` is the file `foo.at` beside the
+    /// innermost file being sourced (last in `files`), or in the working
+    /// directory at the REPL. Declaring a module again replaces all of it.
+    ///
+    /// With `hidden`, a private module records what it displaced there, to be
+    /// put back (and the module itself dropped) when the file is done.
+    fn bind_atlas_mod(
+        &mut self,
+        m: &atlas_lang::ast::ModDecl,
+        parent: &str,
+        source: &AtlasSource,
+        files: &mut Vec<PathBuf>,
+        output: &mut Vec<String>,
+        hidden: Option<&mut Hidden<'h>>,
+    ) -> Result<(), SubmitError> {
+        let public = matches!(m.modifier, Some(atlas_lang::ast::Modifier::Pub));
+        let path = qualify(parent, m.name);
+        let displaced = self.take_module(&path);
+        match hidden {
+            Some(hidden) if !public => hidden.modules.push((path.clone(), displaced)),
+            Some(hidden) => {
+                // Exported over an earlier private module of the same name.
+                for (_, scope) in hidden.modules.extract_if(.., |(p, _)| *p == path) {
+                    self.drop_module(scope);
+                }
+                self.drop_module(displaced);
+            }
+            None => self.drop_module(displaced),
+        }
+        if let Some(items) = self.atlas.modules.get_mut(parent) {
+            items.insert(m.name.to_string(), public);
+        }
+        self.atlas.modules.insert(path.clone(), HashMap::new());
+        let Some(body) = &m.value else {
+            let dir = files
+                .last()
+                .and_then(|f| f.parent())
+                .unwrap_or(Path::new(""));
+            return self.bind_atlas_file(&dir.join(format!("{}.at", m.name)), &path, files, output);
+        };
+        for decl in &body.decls {
+            self.bind_atlas_decl(decl, &path, source, files, output, None)?;
+        }
+        Ok(())
+    }
+
+    /// Bind the items of the module file `file` under `path`, refusing a file
+    /// that is already being sourced.
+    fn bind_atlas_file(
+        &mut self,
+        file: &Path,
+        path: &str,
+        files: &mut Vec<PathBuf>,
+        output: &mut Vec<String>,
+    ) -> Result<(), SubmitError> {
+        let src = std::fs::read_to_string(file)
+            .map_err(|e| SubmitError::Session(format!("cannot read {}: {e}", file.display())))?;
+        let canonical = std::fs::canonicalize(file).unwrap_or_else(|_| file.to_path_buf());
+        if files.contains(&canonical) {
+            return Err(SubmitError::Session(format!(
+                "module `{path}` includes its own file {}",
+                file.display()
+            )));
+        }
+        let name = file.display().to_string();
+        let (body, log) = atlas_lang::parser::parse_module_spanned(&src)
+            .map_err(|e| SubmitError::parse(&name, &src, e))?;
+        let spans = ExprSpans::of_module(&body, log);
+        let source = AtlasSource {
+            name: &name,
+            text: &src,
+            spans: &spans,
+        };
+        files.push(canonical);
+        let mut bound = Ok(());
+        for decl in &body.decls {
+            bound = self.bind_atlas_decl(decl, path, &source, files, output, None);
+            if bound.is_err() {
+                break;
+            }
+        }
+        files.pop();
+        bound
+    }
+
+    /// Take everything bound under the module `path` out of scope.
+    fn take_module(&mut self, path: &str) -> ModuleScope<'h> {
+        let prefix = format!("{path}::");
+        let under = |name: &String| name.starts_with(&prefix);
+        ModuleScope {
+            modules: self
+                .atlas
+                .modules
+                .extract_if(|m, _| m == path || under(m))
+                .collect(),
+            locals: self.locals.map.extract_if(|n, _| under(n)).collect(),
+            ctors: self.atlas.ctors.extract_if(|v, _| under(v)).collect(),
+            fields: self.atlas.fields.extract_if(|c, _| under(c)).collect(),
+            traits: self.atlas.traits.extract_if(|t, _| under(t)).collect(),
+            methods: self.atlas.methods.extract_if(|m, _| under(m)).collect(),
+            bounds: self.atlas.bounds.extract_if(|f, _| under(f)).collect(),
+            values: self.types.values.extract_if(|v, _| under(v)).collect(),
+            data: self.types.data.extract_if(|t, _| under(t)).collect(),
+        }
+    }
+
+    /// Put back what [`Self::take_module`] took from `path`, dropping whatever
+    /// was bound there since.
+    fn put_module(&mut self, path: &str, scope: ModuleScope<'h>) {
+        let since = self.take_module(path);
+        self.drop_module(since);
+        self.atlas.modules.extend(scope.modules);
+        self.locals.map.extend(scope.locals);
+        self.atlas.ctors.extend(scope.ctors);
+        self.atlas.fields.extend(scope.fields);
+        self.atlas.traits.extend(scope.traits);
+        self.atlas.methods.extend(scope.methods);
+        self.atlas.bounds.extend(scope.bounds);
+        self.types.values.extend(scope.values);
+        self.types.data.extend(scope.data);
+    }
+
+    fn drop_module(&self, scope: ModuleScope<'h>) {
+        for (_, local) in scope.locals {
+            crate::eval::erase(self, local.ptr);
+        }
+    }
+
+    /// Bind an atlas module's declarations in order, each seeing the ones
+    /// before it, then put back whatever its private (non-`pub`) declarations
+    /// displaced: only the `pub` ones stay in scope. If the module declares a
+    /// `main`, its value is returned for evaluation.
+    fn bind_atlas_module(
+        &mut self,
+        module: &atlas_lang::ast::Module,
+        file: &Path,
+        source: &AtlasSource,
+        output: &mut Vec<String>,
+    ) -> Result<Option<TermPtr<'h>>, SubmitError> {
+        let mut hidden = Hidden::default();
+        let mut files = vec![std::fs::canonicalize(file).unwrap_or_else(|_| file.to_path_buf())];
+        let mut bound = Ok(());
+        for decl in &module.decls {
+            bound = self.bind_atlas_decl(decl, "", source, &mut files, output, Some(&mut hidden));
+            if bound.is_err() {
+                break;
+            }
+        }
+        let main = match bound {
+            Ok(()) if module.decls.iter().any(|d| decl_names(d).contains(&"main")) => {
+                self.locals.use_name("main", self.h)
+            }
+            _ => None,
+        };
+        for (path, displaced) in hidden.modules.into_iter().rev() {
+            self.put_module(&path, displaced);
+        }
+        // Newest first, so a name hidden twice ends up with what the module
+        // first displaced.
+        for (name, shadowed) in hidden.locals.into_iter().rev() {
+            if let Some(local) = self.locals.map.remove(&name) {
+                crate::eval::erase(self, local.ptr);
+            }
+            if let Some(local) = shadowed {
+                self.locals.map.insert(name, local);
+            }
+        }
+        restore(&mut self.atlas.ctors, hidden.ctors);
+        restore(&mut self.atlas.fields, hidden.fields);
+        restore(&mut self.atlas.traits, hidden.traits);
+        restore(&mut self.atlas.methods, hidden.methods);
+        restore(&mut self.atlas.bounds, hidden.bounds);
+        restore(&mut self.types.values, hidden.values);
+        restore(&mut self.types.data, hidden.data);
+        bound.map(|()| main)
+    }
+
     /// Source a file into the session, dispatching on its extension: `.atc` is
     /// core input (a run of `lhs = rhs;` bindings and/or a trailing expression,
-    /// exactly like a REPL line), `.at` is an atlas module (parse only, since
-    /// atlas evaluation is not implemented yet).
+    /// exactly like a REPL line), `.at` is an atlas module whose `pub`
+    /// declarations become locals (see [`Self::bind_atlas_module`]), and whose
+    /// `main`, if it has one, is evaluated.
     pub fn source_file(&mut self, path: &std::path::Path) -> SubmitResult<'h> {
         let src = match std::fs::read_to_string(path) {
             Ok(src) => src,
             Err(e) => {
                 return SubmitResult::Error {
-                    message: format!("cannot read {}: {e}", path.display()),
+                    error: SubmitError::Session(format!("cannot read {}: {e}", path.display())),
                     output: Vec::new(),
                 };
             }
         };
         match path.extension().and_then(|e| e.to_str()) {
-            Some("atc") => self.submit_core(&src),
-            Some("at") => match atlas_lang::parser::parse_module(&src) {
-                Ok(module) => {
+            Some("atc") => self.submit_core(&path.display().to_string(), &src),
+            Some("at") => match atlas_lang::parser::parse_module_spanned(&src) {
+                Ok((module, log)) => {
+                    let name = path.display().to_string();
+                    let spans = ExprSpans::of_module(&module, log);
+                    let source = AtlasSource {
+                        name: &name,
+                        text: &src,
+                        spans: &spans,
+                    };
                     let mut output = Vec::new();
                     if self.show_ast {
                         output.push(format!("{module:#?}"));
                     }
-                    output.push(format!(
-                        "parsed {} declaration{} (atlas evaluation not yet implemented)",
-                        module.decls.len(),
-                        if module.decls.len() == 1 { "" } else { "s" },
-                    ));
-                    SubmitResult::Output(output)
+                    let exported: Vec<_> = module
+                        .decls
+                        .iter()
+                        .filter(|d| d.is_pub())
+                        .flat_map(decl_names)
+                        .collect();
+                    match self.bind_atlas_module(&module, path, &source, &mut output) {
+                        Ok(main) => {
+                            if !exported.is_empty() {
+                                output.push(format!("exported {}", exported.join(", ")));
+                            }
+                            match main {
+                                Some(root) => SubmitResult::StartEval { root, output },
+                                None => SubmitResult::Output(output),
+                            }
+                        }
+                        Err(error) => SubmitResult::Error { error, output },
+                    }
                 }
-                Err(message) => SubmitResult::Error {
-                    message,
+                Err(e) => SubmitResult::Error {
+                    error: SubmitError::parse(&path.display().to_string(), &src, e),
                     output: Vec::new(),
                 },
             },
             _ => SubmitResult::Error {
-                message: format!(
+                error: SubmitError::Session(format!(
                     "{}: unknown extension (expected .atc for core or .at for atlas)",
                     path.display()
-                ),
+                )),
                 output: Vec::new(),
             },
         }
@@ -301,15 +880,27 @@ impl<'h> Session<'h> {
     /// stage is dumped into `output` as it completes — the parsed node, the
     /// desugared core expression, and the transpiled heap term — so a failing
     /// step still leaves the earlier stages visible.
-    fn lower_input(
+    ///
+    /// With `spans`, the lowered nodes record the byte ranges of this input
+    /// they came from, replacing those of any earlier input. Only an
+    /// expression being evaluated is lowered with spans: a local outlives the
+    /// line that bound it, so its spans would index the wrong source.
+    fn lower_input<'n>(
         &mut self,
-        node: &Node,
+        node: &'n Node<'n>,
+        spans: Option<&NodeSpans<'n>>,
         output: &mut Vec<String>,
-    ) -> Result<TermPtr<'h>, String> {
+    ) -> Result<TermPtr<'h>, Error> {
         if self.show_ast {
             output.push(format!("{node:#?}"));
         }
-        let expr = desugar_open(node)?;
+        let expr = match spans {
+            Some(spans) => {
+                self.h.clear_spans();
+                desugar_open_spanned(node, spans)?
+            }
+            None => desugar_open(node)?,
+        };
         self.lower_core(&expr, output)
     }
 
@@ -317,7 +908,7 @@ impl<'h> Session<'h> {
     /// names against the locals in scope (the shared back half of both the core
     /// and atlas pipelines). When `show_ast` is set, the desugared expression
     /// and the transpiled heap term are dumped into `output`.
-    fn lower_core(&mut self, expr: &Expr, output: &mut Vec<String>) -> Result<TermPtr<'h>, String> {
+    fn lower_core(&mut self, expr: &Expr, output: &mut Vec<String>) -> Result<TermPtr<'h>, Error> {
         if self.show_ast {
             output.push(format!("desugared:\n{expr}"));
         }
@@ -349,21 +940,34 @@ impl<'h> Session<'h> {
                 ),
                 Binding::Hole => {
                     return SubmitResult::Error {
-                        message: "`_` bindings are not supported in the REPL".to_string(),
+                        error: SubmitError::Session(
+                            "`_` bindings are not supported in the REPL".to_string(),
+                        ),
                         output,
                     };
                 }
                 Binding::Dup { .. } => {
                     return SubmitResult::Error {
-                        message: "explicit dup bindings (&L{..}) are not supported in the REPL"
-                            .to_string(),
+                        error: SubmitError::Session(
+                            "explicit dup bindings (&L{..}) are not supported in the REPL"
+                                .to_string(),
+                        ),
                         output,
                     };
                 }
             };
-            match self.lower_input(&value, &mut output) {
-                Ok(ptr) => self.locals.bind(name, kind, ptr),
-                Err(message) => return SubmitResult::Error { message, output },
+            match self.lower_input(&value, None, &mut output) {
+                Ok(ptr) => {
+                    // Core bindings are untyped: atlas lines see them as open.
+                    self.types.values.remove(&name);
+                    self.locals.bind(name, kind, ptr)
+                }
+                Err(e) => {
+                    return SubmitResult::Error {
+                        error: e.into(),
+                        output,
+                    }
+                }
             }
         }
         SubmitResult::Output(output)
@@ -386,6 +990,7 @@ impl<'h> Session<'h> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use atlas_core::error::DesugarErrorKind;
     use atlas_core::vm::heap::Heap;
     use atlas_core::vm::printer::Printer;
 
@@ -398,7 +1003,21 @@ mod tests {
                 out
             }
             SubmitResult::Output(lines) => lines.join("\n"),
-            SubmitResult::Error { message, .. } => panic!("submit error: {message}"),
+            SubmitResult::Error { error, .. } => panic!("submit error: {error}"),
+        }
+    }
+
+    /// Like [`eval_to_string`], for an atlas line; errors come back rendered.
+    fn eval_atlas(session: &mut Session, line: &str) -> Result<String, String> {
+        match session.submit(LangMode::Atlas, line) {
+            SubmitResult::StartEval { root, .. } => {
+                let root = crate::eval::run_to_completion(session, root);
+                let out = Printer::new(session.h).pretty(&root).to_string();
+                crate::eval::erase(session, root);
+                Ok(out)
+            }
+            SubmitResult::Output(lines) => Ok(lines.join("\n")),
+            SubmitResult::Error { error, .. } => Err(error.to_string()),
         }
     }
 
@@ -444,7 +1063,7 @@ mod tests {
             let mut session = Session::new(h, 100_000, false);
             match session.load_prelude() {
                 SubmitResult::Output(lines) => assert!(lines.is_empty(), "prelude is silent"),
-                SubmitResult::Error { message, .. } => panic!("prelude error: {message}"),
+                SubmitResult::Error { error, .. } => panic!("prelude error: {error}"),
                 SubmitResult::StartEval { .. } => panic!("prelude must only contain bindings"),
             }
             assert!(session.locals().iter().any(|(name, ..)| *name == "fib"));
@@ -452,6 +1071,26 @@ mod tests {
         });
     }
 
+    #[test]
+    fn parse_errors_render_against_their_source() {
+        let heap = Heap::new();
+        heap.with(|h| {
+            let mut session = Session::new(h, 1_000, false);
+            let SubmitResult::Error { error, .. } = session.submit(LangMode::Core, "[1, 2") else {
+                panic!("expected a parse error")
+            };
+            let report = error.render(ReportStyle::Plain);
+            assert!(report.contains("<repl>:1:6"), "{report}");
+            assert!(report.contains(" 1 │ [1, 2"), "{report}");
+            assert!(report.contains("while parsing this list"), "{report}");
+            let SubmitResult::Error { error, .. } = session.submit(LangMode::Atlas, "let = 1")
+            else {
+                panic!("expected a parse error")
+            };
+            assert!(error.render(ReportStyle::Plain).contains("let declaration"));
+        });
+    }
+
     #[test]
     fn atlas_mode_silent_unless_show_ast() {
         let heap = Heap::new();
@@ -459,7 +1098,7 @@ mod tests {
             let mut session = Session::new(h, 1_000, false);
             match session.submit(LangMode::Atlas, "let a = 1") {
                 SubmitResult::Output(lines) => assert!(lines.is_empty(), "silent by default"),
-                SubmitResult::Error { message, .. } => panic!("parse error: {message}"),
+                SubmitResult::Error { error, .. } => panic!("parse error: {error}"),
                 SubmitResult::StartEval { .. } => panic!("atlas mode must not evaluate"),
             }
             session.show_ast = true;
@@ -467,12 +1106,447 @@ mod tests {
                 SubmitResult::Output(lines) => {
                     assert!(lines[0].contains("Let"), "AST dump: {}", lines[0])
                 }
-                SubmitResult::Error { message, .. } => panic!("parse error: {message}"),
+                SubmitResult::Error { error, .. } => panic!("parse error: {error}"),
                 SubmitResult::StartEval { .. } => panic!("atlas mode must not evaluate"),
             }
         });
     }
 
+    #[test]
+    fn atlas_modules_export_only_pub_declarations() {
+        let dir = std::env::temp_dir().join(format!("atlas-module-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let lib = dir.join("lib.at");
+        std::fs::write(
+            &lib,
+            "let base = 40\npub enum Shape { Dot, Line }\n\
+             pub bump n = n + base\nenum Hidden { Secret }\n",
+        )
+        .unwrap();
+        let app = dir.join("app.at");
+        std::fs::write(&app, "let two = 2\nmain = bump two\n").unwrap();
+
+        let heap = Heap::new();
+        heap.with(|h| {
+            let mut session = Session::new(h, 10_000, false);
+            session.submit(LangMode::Core, "base = 1;");
+            match session.source_file(&lib) {
+                SubmitResult::Output(lines) => assert_eq!(lines, ["exported Shape, bump"]),
+                SubmitResult::Error { error, .. } => panic!("module error: {error}"),
+                SubmitResult::StartEval { .. } => panic!("a library has no main"),
+            }
+            // Later declarations saw the private `base`, but the session's own
+            // binding is back in scope.
+            assert_eq!(eval_to_string(&mut session, "bump 2"), "42");
+            assert_eq!(eval_to_string(&mut session, "base"), "1");
+            assert!(session.atlas.ctors.contains_key("Line"));
+            assert!(!session.atlas.ctors.contains_key("Secret"));
+            assert!(matches!(
+                session.submit(LangMode::Atlas, "let s = Secret"),
+                SubmitResult::Error { .. }
+            ));
+            // A module with a `main` evaluates it.
+            match session.source_file(&app) {
+                SubmitResult::StartEval { root, .. } => {
+                    let root = crate::eval::run_to_completion(&session, root);
+                    assert_eq!(Printer::new(h).pretty(&root).to_string(), "42");
+                    crate::eval::erase(&session, root);
+                }
+                _ => panic!("expected main to evaluate"),
+            }
+            assert!(!session.locals().iter().any(|(name, ..)| *name == "two"));
+        });
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn atlas_tuples_evaluate() {
+        let dir = std::env::temp_dir().join(format!("atlas-tuple-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("tuples.at");
+        std::fs::write(
+            &file,
+            "swap (a, b) = (b, a)\n\
+             main = {\nlet p = (1, (2, 3))\nlet (x, y) = swap p.1\nx * 100 + y * 10 + p.0\n}\n",
+        )
+        .unwrap();
+        let heap = Heap::new();
+        heap.with(|h| {
+            let mut session = Session::new(h, 10_000, false);
+            match session.source_file(&file) {
+                SubmitResult::StartEval { root, .. } => {
+                    let root = crate::eval::run_to_completion(&session, root);
+                    assert_eq!(Printer::new(h).pretty(&root).to_string(), "321");
+                    crate::eval::erase(&session, root);
+                }
+                SubmitResult::Error { error, .. } => panic!("module error: {error}"),
+                SubmitResult::Output(_) => panic!("expected main to evaluate"),
+            }
+        });
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn atlas_projections_of_arguments_evaluate() {
+        let heap = Heap::new();
+        heap.with(|h| {
+            let mut session = Session::new(h, 10_000, false);
+            let decl = "struct P { x: Int, y: Int }";
+            assert_eq!(eval_atlas(&mut session, decl), Ok(String::new()));
+            // The arity of `t` and the struct of `q` are known from the calls.
+            let block = "{\nf t = t.0 + t.1\nh q = q.y\nsw t = (t.1, t.0)\n\
+                         let s = sw (f (3, 4), h (P { x: 1, y: 9 }))\ns.0 * 100 + s.1\n}";
+            assert_eq!(eval_atlas(&mut session, block), Ok("907".to_string()));
+            assert_eq!(
+                eval_atlas(&mut session, "let f t = t.0 in f (1, 2)"),
+                Ok("1".to_string())
+            );
+        });
+    }
+
+    #[test]
+    fn atlas_structs_evaluate() {
+        let dir = std::env::temp_dir().join(format!("atlas-struct-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("structs.at");
+        std::fs::write(
+            &file,
+            "pub struct Point { x: Int, y: Int }\n\
+             enum Shape { Dot, Rect { w: Int, h: Int } }\n\
+             area s = match s { Rect { h, w } => w * h, Dot => 0 }\n\
+             main = {\nlet p = Point { y: 3, x: 4 }\narea (Rect { w: p.x, h: p.y }) + area Dot\n}\n",
+        )
+        .unwrap();
+        let heap = Heap::new();
+        heap.with(|h| {
+            let mut session = Session::new(h, 10_000, false);
+            match session.source_file(&file) {
+                SubmitResult::StartEval { root, .. } => {
+                    let root = crate::eval::run_to_completion(&session, root);
+                    assert_eq!(Printer::new(h).pretty(&root).to_string(), "12");
+                    crate::eval::erase(&session, root);
+                }
+                SubmitResult::Error { error, .. } => panic!("module error: {error}"),
+                SubmitResult::Output(_) => panic!("expected main to evaluate"),
+            }
+            // The exported struct's fields stay known; the private enum's don't.
+            assert_eq!(session.atlas.fields["Point"], ["x", "y"]);
+            assert!(!session.atlas.fields.contains_key("Rect"));
+        });
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn atlas_nested_patterns_evaluate() {
+        let dir = std::env::temp_dir().join(format!("atlas-pattern-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("patterns.at");
+        std::fs::write(
+            &file,
+            "enum Opt { Nothing, Just(Int) }\n\
+             pick p = match p { (Just(x), _) => x, (_, Just(0)) => 5, (_, Just(y)) => y, \
+               (Nothing, Nothing) => 0, _ => 9 }\n\
+             main = {\nlet (Just(a), b) = (Just(4), 2)\npick (Nothing, Just(0)) * 100 + a * 10 + b\n}\n",
+        )
+        .unwrap();
+        let heap = Heap::new();
+        heap.with(|h| {
+            let mut session = Session::new(h, 10_000, false);
+            match session.source_file(&file) {
+                SubmitResult::StartEval { root, output } => {
+                    assert_eq!(
+                        output,
+                        [
+                            "warning: unreachable pattern `_`: earlier patterns match everything it does",
+                            "warning: patterns are not exhaustive: `(Nothing, _)` not covered",
+                        ]
+                    );
+                    let root = crate::eval::run_to_completion(&session, root);
+                    assert_eq!(Printer::new(h).pretty(&root).to_string(), "542");
+                    crate::eval::erase(&session, root);
+                }
+                SubmitResult::Error { error, .. } => panic!("module error: {error}"),
+                SubmitResult::Output(_) => panic!("expected main to evaluate"),
+            }
+        });
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn atlas_recursive_enums_match_nested_patterns() {
+        let heap = Heap::new();
+        heap.with(|h| {
+            let mut session = Session::new(h, 100_000, false);
+            for decl in [
+                "enum Opt { Nothing, Some(a) }",
+                "enum L { Nil, Cons(Int, L) }",
+                "len l = match l { Nil => 0, Cons(_, t) => 1 + len t }",
+            ] {
+                assert_eq!(eval_atlas(&mut session, decl), Ok(String::new()), "{decl}");
+            }
+            // The warning comes with the declaration's output.
+            let second = "second o = match o { Some(Cons(_, Cons(y, _))) => y, Some(Nil) => 0 }";
+            assert_eq!(
+                eval_atlas(&mut session, second),
+                Ok("warning: patterns are not exhaustive: `Some(Cons(_, Nil))`, `Nothing` not covered".to_string())
+            );
+            let list = "Cons(1, Cons(2, Cons(3, Nil)))";
+            let line = format!("second (Some({list})) * 10 + len {list} + second (Some(Nil))");
+            assert_eq!(eval_atlas(&mut session, &line), Ok("23".to_string()));
+        });
+    }
+
+    #[test]
+    fn atlas_modules_span_files_and_hide_private_items() {
+        let dir = std::env::temp_dir().join(format!("atlas-mod-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("util.at"), "pub let three = 3\n").unwrap();
+        let main = dir.join("main.at");
+        std::fs::write(
+            &main,
+            "mod util;\n\
+             pub mod geo {\n\
+             pub enum Shape { Dot, Circle(Int) }\n\
+             let scale = 10\n\
+             pub area s = match s { Circle(r) => r * scale, Dot => 0 }\n\
+             pub mod inner {\npub twice n = n + n\npub thrice n = n * 3\n}\n\
+             }\n\
+             main = geo::area (geo::Circle(util::three)) + geo::inner::twice 1\n",
+        )
+        .unwrap();
+        let looping = dir.join("looping.at");
+        std::fs::write(&looping, "mod looping;\n").unwrap();
+
+        let heap = Heap::new();
+        heap.with(|h| {
+            let mut session = Session::new(h, 10_000, false);
+            let ok = |s: &str| Ok(s.to_string());
+            let err = |s: &str| Err(s.to_string());
+            let m = "mod m {\npub x = 1\ny = 2\n}";
+            assert_eq!(eval_atlas(&mut session, m), ok(""));
+            assert_eq!(eval_atlas(&mut session, "m::x + 1"), ok("2"));
+            assert_eq!(eval_atlas(&mut session, "m::y"), err("`m::y` is private"));
+            let unbound = err("unbound variable `m::z`");
+            assert_eq!(eval_atlas(&mut session, "m::z"), unbound);
+
+            match session.source_file(&main) {
+                SubmitResult::StartEval { root, output } => {
+                    assert_eq!(output, ["exported geo"]);
+                    let root = crate::eval::run_to_completion(&session, root);
+                    assert_eq!(Printer::new(h).pretty(&root).to_string(), "32");
+                    crate::eval::erase(&session, root);
+                }
+                SubmitResult::Error { error, .. } => panic!("module error: {error}"),
+                SubmitResult::Output(_) => panic!("expected main to evaluate"),
+            }
+            // The exported module stays bound, private items and all, but
+            // paths only reach its `pub` ones; the private module is gone.
+            assert!(session.locals.map.contains_key("geo::scale"));
+            assert!(!session.locals.map.contains_key("util::three"));
+            assert_eq!(eval_atlas(&mut session, "geo::inner::thrice 4"), ok("12"));
+            let private = err("`geo::scale` is private");
+            assert_eq!(eval_atlas(&mut session, "geo::scale"), private);
+            let unbound = err("unbound variable `util::three`");
+            assert_eq!(eval_atlas(&mut session, "util::three"), unbound);
+            let SubmitResult::Error { error, .. } = session.source_file(&looping) else {
+                panic!("a module including itself must fail");
+            };
+            assert!(
+                error.to_string().contains("includes its own file"),
+                "{error}"
+            );
+        });
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn atlas_traits_dispatch_through_dictionaries() {
+        // Each check gets a fresh session and returns its last line's result.
+        let run = |lines: &[&str]| {
+            let heap = Heap::new();
+            heap.with(|h| {
+                let mut session = Session::new(h, 100_000, false);
+                for decl in [
+                    "trait Eq {\neq a b\nne a b = !(eq a b)\n}",
+                    "enum Color { Red, Green }",
+                    "struct P { x: Int, y: Int }",
+                    "impl Eq for Color {\neq a b = match (a, b) { (Red, Red) => true, (Green, Green) => true, _ => false }\n}",
+                    "impl Eq for Int {\neq a b = a == b\n}",
+                    "impl Eq for P {\neq a (b: P) = a.x == b.x && a.y == b.y\n}",
+                    "all_eq (x: Eq) y z = eq x y && eq x z",
+                ] {
+                    assert_eq!(eval_atlas(&mut session, decl), Ok(String::new()), "{decl}");
+                }
+                lines
+                    .iter()
+                    .map(|line| eval_atlas(&mut session, line))
+                    .last()
+                    .unwrap()
+            })
+        };
+        let ok = |s: &str| Ok(s.to_string());
+        assert_eq!(run(&["eq Red Green"]), ok("false"));
+        // A dictionary whose method matches serves later evaluations too.
+        assert_eq!(run(&["eq Red Green", "eq Green Green"]), ok("true"));
+        // The default `ne` dispatches `eq` on its receiver's dictionary.
+        assert_eq!(run(&["ne Green Red"]), ok("true"));
+        assert_eq!(run(&["ne 1 1"]), ok("false"));
+        assert_eq!(run(&["(4 + 1).eq 5"]), ok("true"));
+        assert_eq!(run(&["all_eq 3 3 3"]), ok("true"));
+        let c = "{\nlet c = Green\nall_eq c Green Red\n}";
+        assert_eq!(run(&[c]), ok("false"));
+        let p = "eq P { x: 1, y: 2 } P { y: 2, x: 1 }";
+        assert_eq!(run(&[p]), ok("true"));
+        // A fresh impl replaces the dictionary later calls pick up.
+        let always = "impl Eq for Color {\neq a b = true\n}";
+        assert_eq!(run(&[always, "ne Red Green"]), ok("false"));
+
+        let missing = "impl Eq for Bool {\nne a b = a\n}";
+        let error = run(&[missing]).unwrap_err();
+        assert!(error.contains("missing the method `eq`"), "{error}");
+        let error = run(&["eq 1.5 2.5"]).unwrap_err();
+        assert!(error.contains("impl Eq for Float"), "{error}");
+        let error = run(&["f x = eq x 1"]).unwrap_err();
+        assert!(error.contains("annotate its type"), "{error}");
+        // A call result or a variable dispatches on its checked type.
+        let inc = "inc n = n + 1";
+        assert_eq!(run(&[inc, "eq (inc 1) 2"]), ok("true"));
+        assert_eq!(run(&[inc, "let v = inc 4", "eq v 5"]), ok("true"));
+        assert_eq!(run(&[inc, "{\nlet c = inc 2\nne c 3\n}"]), ok("false"));
+        let pick = "pick b = if b { Red } else { Green }";
+        assert_eq!(run(&[pick, "eq (pick true) Red"]), ok("true"));
+        assert_eq!(run(&[pick, "all_eq (pick false) Green Red"]), ok("false"));
+    }
+
+    #[test]
+    fn atlas_rec_groups_recurse_mutually() {
+        let group = "rec {\neven n = if n == 0 { true } else { odd (n - 1) }\nodd n = if n == 0 { false } else { even (n - 1) }\n}";
+        // A fresh session per check, each going back and forth many times.
+        let run = |line: &str| {
+            let heap = Heap::new();
+            heap.with(|h| {
+                let mut session = Session::new(h, 100_000, false);
+                assert_eq!(eval_atlas(&mut session, group), Ok(String::new()));
+                eval_atlas(&mut session, line)
+            })
+        };
+        let ok = |s: &str| Ok(s.to_string());
+        assert_eq!(run("odd 7"), ok("true"));
+        assert_eq!(run("even 10"), ok("true"));
+        assert_eq!(run("odd 2"), ok("false"));
+        assert_eq!(run("even 3"), ok("false"));
+        assert_eq!(run("even 0"), ok("true"));
+        // The same group inside a block, with a value depending on it.
+        let block = format!("{{\n{group}\nlet x = 9\nodd x\n}}");
+        assert_eq!(run(&block), ok("true"));
+        let block = format!("{{\n{group}\nif odd 5 {{ even 8 }} else {{ false }}\n}}");
+        assert_eq!(run(&block), ok("true"));
+    }
+
+    #[test]
+    fn atlas_dense_rec_groups_recurse() {
+        // Six members, each calling every one of them: `ai n` adds `i` and
+        // goes on with the member `(n + i) % 6`, down to `n == 0`.
+        let arms = (0..6)
+            .map(|j| format!("{j} => a{j} (n - 1)"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let members = (0..6)
+            .map(|i| {
+                format!(
+                    "a{i} n = if n == 0 {{ {i} }} else {{ {i} + match (n + {i}) % 6 {{ {arms}, _ => 0 }} }}"
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let group = format!("rec {{\n{members}\n}}");
+        for strong in [false, true] {
+            let heap = Heap::new();
+            heap.with(|h| {
+                let mut session = Session::new(h, 1_000_000, strong);
+                assert_eq!(eval_atlas(&mut session, &group), Ok(String::new()));
+                for (line, value) in [("a0 10", "25"), ("a3 7", "20"), ("a5 0", "5")] {
+                    assert_eq!(eval_atlas(&mut session, line), Ok(value.into()), "{line}");
+                }
+            })
+        }
+    }
+
+    #[test]
+    fn atlas_closures_and_pipelines_evaluate() {
+        let heap = Heap::new();
+        heap.with(|h| {
+            let mut session = Session::new(h, 100_000, false);
+            for decl in ["twice f x = f (f x)", "add a b = a + b", "let k = 10"] {
+                assert_eq!(eval_atlas(&mut session, decl), Ok(String::new()), "{decl}");
+            }
+            assert_eq!(
+                eval_atlas(&mut session, "twice (|x| x * 3) 2"),
+                Ok("18".into())
+            );
+            // A closure captures the session's bindings and its block's.
+            assert_eq!(
+                eval_atlas(&mut session, "1 |> (|x| x + k)"),
+                Ok("11".into())
+            );
+            let block = "{\nlet n = 4\n(|x, (a, b)| x * n + a - b) 1 (5, 2)\n}";
+            assert_eq!(eval_atlas(&mut session, block), Ok("7".into()));
+            // Partial application feeds a pipeline.
+            assert_eq!(
+                eval_atlas(&mut session, "3 |> add 4 |> twice (add 1)"),
+                Ok("9".into())
+            );
+        });
+    }
+
+    #[test]
+    fn atlas_type_errors_stop_lines_before_lowering() {
+        let heap = Heap::new();
+        heap.with(|h| {
+            let mut session = Session::new(h, 100_000, false);
+            match session.submit(LangMode::Atlas, "2 * (1 + true)") {
+                SubmitResult::Error { error, .. } => {
+                    assert!(matches!(error, SubmitError::Type { .. }), "{error:?}");
+                    let report = error.render(ReportStyle::Plain);
+                    assert!(report.contains("expected `Int`, found `Bool`"), "{report}");
+                    assert!(report.contains("<repl>:1:10"), "{report}");
+                }
+                _ => panic!("an ill-typed line was accepted"),
+            }
+            for decl in [
+                "add a b = a + b",
+                "id x = x",
+                "struct Box { v: a }",
+                "let b = Box { v: 3 }",
+            ] {
+                assert_eq!(eval_atlas(&mut session, decl), Ok(String::new()), "{decl}");
+            }
+            assert_eq!(
+                eval_atlas(&mut session, "add 1 true"),
+                Err("expected `Int`, found `Bool`".into())
+            );
+            // `id` is generic, and a generic struct's field has the type it was
+            // built with.
+            assert_eq!(
+                eval_atlas(
+                    &mut session,
+                    "match b { Box { v } => if id true { id v + 1 } else { 0 } }"
+                ),
+                Ok("4".into())
+            );
+            assert_eq!(
+                eval_atlas(&mut session, "b.v && true"),
+                Err("expected `Bool`, found `Int`".into())
+            );
+            // A declaration that does not check binds nothing.
+            assert!(eval_atlas(&mut session, "let bad = add 1 true").is_err());
+            assert_eq!(
+                eval_atlas(&mut session, "bad"),
+                Err("unbound variable `bad`".into())
+            );
+        });
+    }
+
     #[test]
     fn agent_mode_ignores_input() {
         let heap = Heap::new();
@@ -492,7 +1566,7 @@ mod tests {
         let atc = dir.join("defs.atc");
         std::fs::write(&atc, "a = 1 + 1;\n&double = \\&x -> x + x;\n").unwrap();
         let at = dir.join("mod.at");
-        std::fs::write(&at, "let a = 1\nlet b = 2\n").unwrap();
+        std::fs::write(&at, "pub let a = 10\nlet b = 2\n").unwrap();
         let bogus = dir.join("noext");
         std::fs::write(&bogus, "").unwrap();
 
@@ -506,13 +1580,12 @@ mod tests {
             }
             assert_eq!(session.locals().len(), 2);
             assert_eq!(eval_to_string(&mut session, "double a"), "4");
-            // .at: parse-only, reports the declaration count.
+            // .at: the pub declarations land in the locals.
             match session.source_file(&at) {
-                SubmitResult::Output(lines) => {
-                    assert!(lines[0].contains("2 declarations"), "got: {}", lines[0])
-                }
-                _ => panic!("expected atlas parse output"),
+                SubmitResult::Output(lines) => assert_eq!(lines, ["exported a"]),
+                _ => panic!("expected atlas module output"),
             }
+            assert_eq!(eval_to_string(&mut session, "a * 2"), "20");
             // unknown extension: an error.
             assert!(matches!(
                 session.source_file(&bogus),
@@ -596,8 +1669,15 @@ mod tests {
             // Desugar fails (affine `x` used twice): the parsed AST dump — the
             // stage that succeeded — must still come back with the error.
             match session.submit(LangMode::Core, "(\\x -> x + x) 1") {
-                SubmitResult::Error { message, output } => {
-                    assert!(message.contains("affine variable"), "got: {message}");
+                SubmitResult::Error { error, output } => {
+                    assert!(
+                        matches!(
+                            &error,
+                            SubmitError::Pipeline(Error::Desugar(e))
+                                if matches!(e.kind, DesugarErrorKind::AffineReuse { .. })
+                        ),
+                        "got: {error}"
+                    );
                     assert_eq!(output.len(), 1);
                     assert!(output[0].contains("Lambda"), "Node dump: {}", output[0]);
                 }
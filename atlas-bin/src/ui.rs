@@ -122,6 +122,65 @@ fn line_style(kind: OutKind) -> Style {
     }
 }
 
+/// A transcript line that may carry ANSI SGR colour codes (the diagnostics
+/// ariadne renders), as spans over the line's `base` style. Only colours and
+/// bold are honoured; any other escape is dropped.
+fn ansi_line(text: &str, base: Style) -> Line<'static> {
+    if !text.contains('\x1b') {
+        return Line::styled(text.to_string(), base);
+    }
+    let mut spans = Vec::new();
+    let mut style = base;
+    let mut rest = text;
+    while let Some(start) = rest.find('\x1b') {
+        if start > 0 {
+            spans.push(Span::styled(rest[..start].to_string(), style));
+        }
+        rest = &rest[start + 1..];
+        let Some(params) = rest.strip_prefix('[') else {
+            continue;
+        };
+        let Some(end) = params.find(|c: char| c.is_ascii_alphabetic()) else {
+            rest = "";
+            break;
+        };
+        if params.as_bytes()[end] == b'm' {
+            style = sgr(&params[..end], style, base);
+        }
+        rest = &params[end + 1..];
+    }
+    if !rest.is_empty() {
+        spans.push(Span::styled(rest.to_string(), style));
+    }
+    Line::from(spans)
+}
+
+/// Apply the `;`-separated SGR parameters `params` to `style`.
+fn sgr(params: &str, mut style: Style, base: Style) -> Style {
+    let mut codes = params
+        .split(';')
+        .map(|code| code.parse::<u8>().unwrap_or(0));
+    while let Some(code) = codes.next() {
+        style = match code {
+            0 => base,
+            1 => style.add_modifier(Modifier::BOLD),
+            22 => style.remove_modifier(Modifier::BOLD),
+            30..=37 => style.fg(Color::Indexed(code - 30)),
+            90..=97 => style.fg(Color::Indexed(code - 90 + 8)),
+            38 => match (codes.next(), codes.next()) {
+                (Some(5), Some(index)) => style.fg(Color::Indexed(index)),
+                _ => style,
+            },
+            39 => Style {
+                fg: base.fg,
+                ..style
+            },
+            _ => style,
+        };
+    }
+    style
+}
+
 fn draw_transcript(f: &mut Frame, app: &mut App, area: Rect) {
     let height = area.height as usize;
     app.transcript_height = height;
@@ -138,7 +197,7 @@ fn draw_transcript(f: &mut Frame, app: &mut App, area: Rect) {
         .iter()
         .skip(app.scroll.offset)
         .take(height)
-        .map(|out| Line::styled(out.text.clone(), line_style(out.kind)))
+        .map(|out| ansi_line(&out.text, line_style(out.kind)))
         .collect();
     f.render_widget(Paragraph::new(lines), area);
 }
@@ -398,6 +457,26 @@ mod tests {
         terminal
     }
 
+    #[test]
+    fn ansi_colours_become_span_styles() {
+        let base = Style::new().fg(Color::Red);
+        let line = ansi_line("\x1b[31mError:\x1b[0m at \x1b[38;5;246m1:4\x1b[0m", base);
+        let spans: Vec<_> = line
+            .spans
+            .iter()
+            .map(|span| (span.content.as_ref(), span.style.fg))
+            .collect();
+        assert_eq!(
+            spans,
+            [
+                ("Error:", Some(Color::Indexed(1))),
+                (" at ", Some(Color::Red)),
+                ("1:4", Some(Color::Indexed(246))),
+            ]
+        );
+        assert_eq!(ansi_line("plain", base).style, base);
+    }
+
     #[test]
     fn prompt_uses_mode_rules_marker_and_empty_lower_row() {
         Heap::new().with(|h| {
@@ -407,6 +486,7 @@ mod tests {
                 strong: false,
                 no_prelude: true,
                 source: Vec::new(),
+                command: None,
             };
             let mut app = App::new(h, &args);
             app.input.replace_line("test".to_string());
@@ -433,6 +513,7 @@ mod tests {
                 strong: false,
                 no_prelude: true,
                 source: Vec::new(),
+                command: None,
             };
             let mut app = App::new(h, &args);
             app.completions = vec![
@@ -469,6 +550,7 @@ mod tests {
                 strong: false,
                 no_prelude: true,
                 source: Vec::new(),
+                command: None,
             };
             let mut app = App::new(h, &args);
             app.panel_open = true;
@@ -489,6 +571,7 @@ mod tests {
                 strong: false,
                 no_prelude: true,
                 source: Vec::new(),
+                command: None,
             };
             let mut app = App::new(h, &args);
             let terminal = render(&mut app, 40, 12);
@@ -513,6 +596,7 @@ mod tests {
                 strong: false,
                 no_prelude: true,
                 source: Vec::new(),
+                command: None,
             };
             let mut app = App::new(h, &args);
             app.dialogue = Some(DialogueSpec {
@@ -545,23 +629,24 @@ mod tests {
                 strong: false,
                 no_prelude: true,
                 source: Vec::new(),
+                command: None,
             };
             let mut app = App::new(h, &args);
             app.submit_line("/help");
-            let terminal = render(&mut app, 40, 24);
+            let terminal = render(&mut app, 40, 25);
             let buffer = terminal.backend().buffer();
 
-            assert_eq!(buffer[(4, 2)].symbol(), "H");
-            assert_eq!(buffer[(4, 2)].fg, Color::Rgb(255, 165, 0));
-            assert_eq!(buffer[(0, 2)].fg, Color::Reset);
-            assert_eq!(buffer[(0, 3)].symbol(), " ");
-            assert_eq!(buffer[(1, 4)].symbol(), "C");
-            assert_eq!(buffer[(1, 4)].fg, Color::Yellow);
-            assert_eq!(buffer[(0, 16)].symbol(), " ");
-            assert_eq!(buffer[(1, 17)].symbol(), "K");
-            assert_eq!(buffer[(1, 17)].fg, Color::Blue);
-            assert_eq!(buffer[(3, 18)].fg, Color::Reset);
-            assert_eq!(buffer[(0, 19)].symbol(), " ");
+            assert_eq!(buffer[(4, 0)].symbol(), "H");
+            assert_eq!(buffer[(4, 0)].fg, Color::Rgb(255, 165, 0));
+            assert_eq!(buffer[(0, 0)].fg, Color::Reset);
+            assert_eq!(buffer[(0, 1)].symbol(), " ");
+            assert_eq!(buffer[(1, 2)].symbol(), "C");
+            assert_eq!(buffer[(1, 2)].fg, Color::Yellow);
+            assert_eq!(buffer[(0, 17)].symbol(), " ");
+            assert_eq!(buffer[(1, 18)].symbol(), "K");
+            assert_eq!(buffer[(1, 18)].fg, Color::Blue);
+            assert_eq!(buffer[(3, 19)].fg, Color::Reset);
+            assert_eq!(buffer[(0, 20)].symbol(), " ");
         });
     }
 }
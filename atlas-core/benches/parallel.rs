@@ -0,0 +1,52 @@
+//! Wall-clock time of a wide fold — a construction whose fields are
+//! independent folds — normalized sequentially and by the parallel driver.
+//!
+//! `cargo bench -p atlas-core --bench parallel [-- <fields> <n> <workers>..]`
+//! prints one line per run; with as many workers and cores as fields, the
+//! parallel runs should take about `1 / fields` of the sequential one.
+
+use atlas_core::extension::NoExtensions;
+use atlas_core::vm::{run, run_parallel_with};
+use std::time::{Duration, Instant};
+
+/// `fields` folds of `0 + 1 + .. + n`, each with its own copy of the loop so
+/// that no two share a dup and every one can be forked.
+fn wide_fold(fields: usize, n: u64) -> String {
+    let ty = vec!["type ()"; fields].join(", ");
+    let fold = format!(r"((fix \&f x -> ?{{0 -> 0; &x -> (f (x - 1)) + x}} x) {n})");
+    format!("W = type ({ty}); W::New {}", vec![fold; fields].join(" "))
+}
+
+/// The best of three runs.
+fn time(f: impl Fn() -> String) -> (Duration, String) {
+    (0..3)
+        .map(|_| {
+            let start = Instant::now();
+            let out = f();
+            (start.elapsed(), out)
+        })
+        .min_by_key(|(elapsed, _)| *elapsed)
+        .unwrap()
+}
+
+fn main() {
+    let args: Vec<usize> = std::env::args()
+        .skip(1)
+        .filter_map(|a| a.parse().ok())
+        .collect();
+    let fields = args.first().copied().unwrap_or(4);
+    let n = args.get(1).copied().unwrap_or(20_000) as u64;
+    let workers = match args.get(2..) {
+        Some(w) if !w.is_empty() => w.to_vec(),
+        _ => vec![1, 2, fields],
+    };
+    let src = wide_fold(fields, n);
+    let (sequential, expected) = time(|| run(&src).unwrap());
+    println!("{fields} folds of {n}: sequential {sequential:?}");
+    for w in workers {
+        let (elapsed, out) = time(|| run_parallel_with(&src, &NoExtensions, w).unwrap());
+        assert_eq!(out, expected);
+        let speedup = sequential.as_secs_f64() / elapsed.as_secs_f64();
+        println!("{fields} folds of {n}: {w} workers {elapsed:?} ({speedup:.2}x)");
+    }
+}
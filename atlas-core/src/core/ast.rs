@@ -5,7 +5,9 @@ use std::rc::Rc;
 use ordered_float::OrderedFloat;
 
 use crate::core::expr::{DeBruijn, Expr, Pat, TypeDefKind, Value};
-use crate::vm::term::{BinaryOp, UnaryOp};
+use crate::core::span::NodeSpans;
+use crate::error::{DesugarError, DesugarErrorKind};
+use crate::vm::term::{BinaryOp, Sort, UnaryOp};
 
 #[rustfmt::skip]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -15,6 +17,7 @@ pub enum InfixOp {
     And, Or, Xor,
     Shl, Shr, Eq, Neq,
     Lt, Lte, Gt, Gte, Cons,
+    Conv,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -97,6 +100,14 @@ pub enum Node<'src> {
     Infix { left: Box<Node<'src>>, op: InfixOp, right: Box<Node<'src>>, },
     /// prefix unary operation: Oper Node
     Unary { op: UnaryOp, expr: Box<Node<'src>>, },
+    /// lazy projection: `Node ":>" Node`, asserting the value's type when demanded
+    Project { value: Box<Node<'src>>, ty: Box<Node<'src>>, },
+    /// dependent product: `"pi" "(" Binding ":" Node ")" "->" Node`, where
+    /// `output` may mention the binder. Braces `{x: A}` mark an erased
+    /// (grade zero) argument.
+    Pi { binder: Binding<'src>, erased: bool, input: Box<Node<'src>>, output: Box<Node<'src>>, },
+    /// a sort: `Prop` or a universe `Type_i`
+    Sort(Sort),
     /// wildcard: `*`
     Wild,
     /// the Y-combinator: `fix f` reduces to `f (fix f)`.
@@ -108,24 +119,32 @@ pub enum Node<'src> {
 // ========================================================================
 
 /// Lower a surface AST node into a desugared [`Expr`].
-pub fn desugar<'n>(node: &'n Node<'n>) -> Result<Expr, String> {
-    let mut d = Desugar {
-        depth: 0,
-        env: HashMap::new(),
-        allow_free: false,
-    };
-    d.go(node)
+pub fn desugar<'n>(node: &'n Node<'n>) -> Result<Expr, DesugarError> {
+    Desugar::new(false, None).go(node)
 }
 
 /// Like [`desugar`], but unbound names lower to [`Expr::Free`] (resolved later,
 /// e.g. against a REPL's local bindings) instead of erroring on the spot.
-pub fn desugar_open<'n>(node: &'n Node<'n>) -> Result<Expr, String> {
-    let mut d = Desugar {
-        depth: 0,
-        env: HashMap::new(),
-        allow_free: true,
-    };
-    d.go(node)
+pub fn desugar_open<'n>(node: &'n Node<'n>) -> Result<Expr, DesugarError> {
+    Desugar::new(true, None).go(node)
+}
+
+/// Like [`desugar`], but keep the source positions `spans` has for the tree:
+/// the expression of each spanned node is wrapped in an [`Expr::Spanned`], and
+/// an error carries the span of the innermost node it is about.
+pub fn desugar_spanned<'n>(
+    node: &'n Node<'n>,
+    spans: &NodeSpans<'n>,
+) -> Result<Expr, DesugarError> {
+    Desugar::new(false, Some(spans)).go(node)
+}
+
+/// [`desugar_open`] with the source positions of [`desugar_spanned`].
+pub fn desugar_open_spanned<'n>(
+    node: &'n Node<'n>,
+    spans: &NodeSpans<'n>,
+) -> Result<Expr, DesugarError> {
+    Desugar::new(true, Some(spans)).go(node)
 }
 
 /// What a source name resolves to during desugaring.
@@ -150,15 +169,42 @@ struct DupDesugar {
     used: usize,
 }
 
-struct Desugar<'n> {
+struct Desugar<'n, 's> {
     depth: usize,
     env: HashMap<&'n str, BindingDesugar<'n>>,
     /// When set, an unbound name lowers to [`Expr::Free`] rather than erroring.
     allow_free: bool,
+    spans: Option<&'s NodeSpans<'n>>,
 }
 
-impl<'n> Desugar<'n> {
-    fn go(&mut self, node: &'n Node<'n>) -> Result<Expr, String> {
+impl<'n, 's> Desugar<'n, 's> {
+    fn new(allow_free: bool, spans: Option<&'s NodeSpans<'n>>) -> Self {
+        Desugar {
+            depth: 0,
+            env: HashMap::new(),
+            allow_free,
+            spans,
+        }
+    }
+
+    fn span(&self, node: &'n Node<'n>) -> Option<std::ops::Range<usize>> {
+        self.spans.and_then(|spans| spans.get(node))
+    }
+
+    fn go(&mut self, node: &'n Node<'n>) -> Result<Expr, DesugarError> {
+        let Some(span) = self.span(node) else {
+            return self.go_node(node);
+        };
+        match self.go_node(node) {
+            Ok(body) => Ok(Expr::Spanned {
+                span,
+                body: Box::new(body),
+            }),
+            Err(e) => Err(e.or_span(span)),
+        }
+    }
+
+    fn go_node(&mut self, node: &'n Node<'n>) -> Result<Expr, DesugarError> {
         match node {
             Node::Lit { val } => Ok(self.lit(val)),
             Node::List { elems } => self.list(elems),
@@ -171,7 +217,9 @@ impl<'n> Desugar<'n> {
             Node::Fix => desugar(&y_combinator()),
             Node::Sup { nodes } => {
                 if nodes.len() != 2 {
-                    return Err("superposition must have exactly two elements".into());
+                    return Err(DesugarError::malformed(
+                        "superposition must have exactly two elements",
+                    ));
                 }
                 let left = self.go(&nodes[0])?;
                 let right = self.go(&nodes[1])?;
@@ -186,19 +234,22 @@ impl<'n> Desugar<'n> {
                     fs.push(self.go(n)?);
                 }
                 Ok(Expr::TypeDef {
+                    name: None,
                     kind: TypeDefKind::Product(fs),
                 })
             }
             Node::SumType { variants } => {
                 if variants.is_empty() {
-                    return Err(
-                        "a variant type `type { .. }` must have at least one variant".into(),
-                    );
+                    return Err(DesugarError::malformed(
+                        "a variant type `type { .. }` must have at least one variant",
+                    ));
                 }
                 let mut vs = Vec::with_capacity(variants.len());
                 for (name, args) in variants {
                     if *name == "New" {
-                        return Err("`New` is reserved as the product constructor and cannot be used as a variant name".into());
+                        return Err(DesugarError::malformed(
+                            "`New` is reserved as the product constructor and cannot be used as a variant name",
+                        ));
                     }
                     let mut a = Vec::with_capacity(args.len());
                     for arg in args {
@@ -207,6 +258,7 @@ impl<'n> Desugar<'n> {
                     vs.push((name.to_string(), a));
                 }
                 Ok(Expr::TypeDef {
+                    name: None,
                     kind: TypeDefKind::Sum(vs),
                 })
             }
@@ -216,12 +268,23 @@ impl<'n> Desugar<'n> {
             }),
             Node::App { func, args } => {
                 let mut f = self.go(func)?;
-                for arg in args {
+                let start = self.span(func).map(|span| span.start);
+                for (i, arg) in args.iter().enumerate() {
                     let x = self.go(arg)?;
                     f = Expr::App {
                         func: Box::new(f),
                         arg: Box::new(x),
                     };
+                    // A partial application `f a` of `f a b` spans `f` through
+                    // `a`; `go` spans the whole application.
+                    if i + 1 < args.len()
+                        && let (Some(start), Some(end)) = (start, self.span(arg))
+                    {
+                        f = Expr::Spanned {
+                            span: start..end.end,
+                            body: Box::new(f),
+                        };
+                    }
                 }
                 Ok(f)
             }
@@ -241,7 +304,24 @@ impl<'n> Desugar<'n> {
                 op: *op,
                 val: Box::new(self.go(expr)?),
             }),
+            Node::Project { value, ty } => Ok(Expr::Project {
+                value: Box::new(self.go(value)?),
+                ty: Box::new(self.go(ty)?),
+            }),
             Node::Lambda { binders, body } => self.lam(binders, body),
+            // The codomain is a function of the argument, so it lowers to a
+            // lambda over the binder.
+            Node::Pi {
+                binder,
+                erased,
+                input,
+                output,
+            } => Ok(Expr::Pi {
+                erased: *erased,
+                input: Box::new(self.go(input)?),
+                output: Box::new(self.lam(std::slice::from_ref(binder), output)?),
+            }),
+            Node::Sort(sort) => Ok(Expr::Sort(*sort)),
             Node::Let { bindings, body } => self.lets(bindings, 0, body),
             Node::Match { cases, default } => {
                 let mut compiled = Vec::with_capacity(cases.len());
@@ -258,7 +338,9 @@ impl<'n> Desugar<'n> {
                     match pat {
                         Pattern::Default | Pattern::Bind(_) | Pattern::BindDup(_) => {
                             if def.is_some() {
-                                return Err("match has more than one default branch".into());
+                                return Err(DesugarError::malformed(
+                                    "match has more than one default branch",
+                                ));
                             }
                             def = Some(match pat {
                                 Pattern::Default => self.default_lam(None, body)?,
@@ -284,7 +366,7 @@ impl<'n> Desugar<'n> {
         Expr::Value(lit_value(lit))
     }
 
-    fn list(&mut self, elems: &'n [Node<'n>]) -> Result<Expr, String> {
+    fn list(&mut self, elems: &'n [Node<'n>]) -> Result<Expr, DesugarError> {
         let mut acc = nil();
         for e in elems.iter().rev() {
             let head = self.go(e)?;
@@ -294,7 +376,7 @@ impl<'n> Desugar<'n> {
     }
 
     /// Resolve a variable use (de Bruijn), driving auto-dup and let inlining.
-    fn use_var(&mut self, name: &'n str) -> Result<Expr, String> {
+    fn use_var(&mut self, name: &'n str) -> Result<Expr, DesugarError> {
         enum What<'n> {
             Lam(usize),
             ClonedSingle(usize),
@@ -308,7 +390,9 @@ impl<'n> Desugar<'n> {
                 if self.allow_free {
                     return Ok(Expr::Free(name.to_string()));
                 }
-                return Err(format!("unbound variable `{name}`"));
+                return Err(DesugarError::new(DesugarErrorKind::UnboundVariable(
+                    name.to_string(),
+                )));
             }
             Some(BindingDesugar::Lam(d)) => What::Lam(*d),
             Some(BindingDesugar::Cloned(c)) => {
@@ -353,7 +437,7 @@ impl<'n> Desugar<'n> {
     }
 
     #[rustfmt::skip]
-    fn lam(&mut self, binders: &'n [Binding<'n>], body: &'n Node<'n>) -> Result<Expr, String> {
+    fn lam(&mut self, binders: &'n [Binding<'n>], body: &'n Node<'n>) -> Result<Expr, DesugarError> {
         let (binder, rest) = match binders.split_first() {
             Some(x) => x,
             None => return self.go(body),
@@ -371,9 +455,10 @@ impl<'n> Desugar<'n> {
             Binding::Var { name, auto_dup: false } => {
                 let n = count_in_rest(rest, body, name);
                 if n > 1 {
-                    return Err(format!(
-                        "affine variable `{name}` used {n} times; use `&{name}`"
-                    ));
+                    return Err(DesugarError::new(DesugarErrorKind::AffineReuse {
+                        name: name.to_string(),
+                        uses: n,
+                    }));
                 }
                 self.depth += 1;
                 if n == 0 {
@@ -405,7 +490,11 @@ impl<'n> Desugar<'n> {
     /// `x ->` arm) binds the scrutinee as `name`. Mirrors the single-binder
     /// Use/Lam collapse in [`Self::lam`]: an unused binder is an erasing `Use`, a
     /// single use is a plain `Lam`, and more than one use is an affine error.
-    fn default_lam(&mut self, name: Option<&'n str>, body: &'n Node<'n>) -> Result<Expr, String> {
+    fn default_lam(
+        &mut self,
+        name: Option<&'n str>,
+        body: &'n Node<'n>,
+    ) -> Result<Expr, DesugarError> {
         let name = match name {
             None => {
                 // erasing binder: a lambda whose argument is ignored
@@ -420,9 +509,10 @@ impl<'n> Desugar<'n> {
         };
         let n = count_node(body, name);
         if n > 1 {
-            return Err(format!(
-                "affine variable `{name}` used {n} times; use `&{name}`"
-            ));
+            return Err(DesugarError::new(DesugarErrorKind::AffineReuse {
+                name: name.to_string(),
+                uses: n,
+            }));
         }
         self.depth += 1;
         if n == 0 {
@@ -452,7 +542,7 @@ impl<'n> Desugar<'n> {
         names: &[&'n str],
         rest: &'n [Binding<'n>],
         body: &'n Node<'n>,
-    ) -> Result<Expr, String> {
+    ) -> Result<Expr, DesugarError> {
         let count: usize = names.iter().map(|n| count_in_rest(rest, body, n)).sum();
         if count == 0 {
             // unused binder: an erasing lambda
@@ -526,7 +616,7 @@ impl<'n> Desugar<'n> {
         bindings: &'n [(Binding<'n>, Node<'n>)],
         idx: usize,
         body: &'n Node<'n>,
-    ) -> Result<Expr, String> {
+    ) -> Result<Expr, DesugarError> {
         if idx >= bindings.len() {
             return self.go(body);
         }
@@ -542,9 +632,10 @@ impl<'n> Desugar<'n> {
             } => {
                 let n = count_seq(&bindings[idx + 1..], body, name);
                 if n > 1 {
-                    return Err(format!(
-                        "affine variable `{name}` used {n} times; use `&{name}`"
-                    ));
+                    return Err(DesugarError::new(DesugarErrorKind::AffineReuse {
+                        name: name.to_string(),
+                        uses: n,
+                    }));
                 }
                 let prev = self.env.insert(name, BindingDesugar::Let(val));
                 let r = self.lets(bindings, idx + 1, body);
@@ -570,7 +661,7 @@ impl<'n> Desugar<'n> {
         bindings: &'n [(Binding<'n>, Node<'n>)],
         idx: usize,
         body: &'n Node<'n>,
-    ) -> Result<Expr, String> {
+    ) -> Result<Expr, DesugarError> {
         let rest = &bindings[idx + 1..];
         let count: usize = names.iter().map(|n| count_seq(rest, body, n)).sum();
         if count == 0 {
@@ -651,6 +742,7 @@ impl TryInto<BinaryOp> for &InfixOp {
             InfixOp::Shr => BinaryOp::Shr,
             InfixOp::Eq => BinaryOp::Eq,
             InfixOp::Neq => BinaryOp::Neq,
+            InfixOp::Conv => BinaryOp::Conv,
             InfixOp::Lt => BinaryOp::Lt,
             InfixOp::Lte => BinaryOp::Lte,
             InfixOp::Gt => BinaryOp::Gt,
@@ -733,14 +825,16 @@ fn y_combinator() -> Node<'static> {
     }
 }
 
-fn pat_key(pat: &Pattern) -> Result<Pat, String> {
+fn pat_key(pat: &Pattern) -> Result<Pat, DesugarError> {
     Ok(match pat {
         Pattern::Ctr(name) => Pat::Ctr(name.to_string()),
         Pattern::Nil => Pat::Ctr("Nil".into()),
         Pattern::Cons => Pat::Ctr("Cons".into()),
         Pattern::Lit(lit) => Pat::Val(lit_value(lit)),
         Pattern::Default | Pattern::Bind(_) | Pattern::BindDup(_) => {
-            return Err("`_`/identifier pattern is handled as the default".into());
+            return Err(DesugarError::malformed(
+                "`_`/identifier pattern is handled as the default",
+            ));
         }
     })
 }
@@ -783,7 +877,12 @@ fn count_seq(bindings: &[(Binding, Node)], body: &Node, name: &str) -> usize {
 fn count_node(node: &Node, name: &str) -> usize {
     match node {
         Node::Var { name: n } => (*n == name) as usize,
-        Node::Lit { .. } | Node::Wild | Node::Erase | Node::Fix | Node::Primitive { .. } => 0,
+        Node::Lit { .. }
+        | Node::Wild
+        | Node::Erase
+        | Node::Fix
+        | Node::Sort(_)
+        | Node::Primitive { .. } => 0,
         Node::List { elems } => elems.iter().map(|e| count_node(e, name)).sum(),
         Node::Sup { nodes, .. } => nodes.iter().map(|e| count_node(e, name)).sum(),
         Node::ProductType { fields } => fields.iter().map(|e| count_node(e, name)).sum(),
@@ -797,6 +896,7 @@ fn count_node(node: &Node, name: &str) -> usize {
         }
         Node::Infix { left, right, .. } => count_node(left, name) + count_node(right, name),
         Node::Unary { expr, .. } => count_node(expr, name),
+        Node::Project { value, ty } => count_node(value, name) + count_node(ty, name),
         Node::Lambda { binders, body } => {
             if binders_bind(binders, name) {
                 0
@@ -804,6 +904,19 @@ fn count_node(node: &Node, name: &str) -> usize {
                 count_node(body, name)
             }
         }
+        Node::Pi {
+            binder,
+            input,
+            output,
+            ..
+        } => {
+            let output = if binders_bind(std::slice::from_ref(binder), name) {
+                0
+            } else {
+                count_node(output, name)
+            };
+            count_node(input, name) + output
+        }
         Node::Let { bindings, body } => count_seq(bindings, body, name),
         Node::Match { cases, default } => {
             cases
@@ -976,7 +1089,54 @@ mod tests {
     fn match_binding_default_affine_error() {
         // Using the default binder twice is an affine violation, like a lambda.
         let node = crate::core::parse::parse(r"?{1 -> 0; x -> x + x}").unwrap();
-        assert!(desugar(&node).is_err());
+        assert_eq!(
+            desugar(&node).unwrap_err().kind,
+            DesugarErrorKind::AffineReuse {
+                name: "x".into(),
+                uses: 2
+            }
+        );
+    }
+
+    /// Desugar `src` (open) with its parsed spans.
+    fn de_spanned(src: &str) -> Result<Expr, DesugarError> {
+        let (node, log) = crate::core::parse::parse_spanned(src).unwrap();
+        desugar_open_spanned(&node, &NodeSpans::new([&node], log))
+    }
+
+    #[test]
+    fn spans_do_not_change_the_desugaring() {
+        for src in [
+            r"(\&x y -> x + x * y) 2 3",
+            r"a = [1, 2]; ?{Cons h t -> h; _ -> 0} a",
+            r"&{\x -> x, type{A, B}::B}",
+        ] {
+            assert_eq!(de_spanned(src).unwrap().to_string(), de(src).to_string());
+        }
+    }
+
+    #[test]
+    fn partial_applications_are_spanned() {
+        let Ok(Expr::Spanned { span, body }) = de_spanned("f 1 2") else {
+            panic!()
+        };
+        assert_eq!(span, 0..5);
+        let Expr::App { func, .. } = *body else {
+            panic!("{body:?}")
+        };
+        assert!(matches!(*func, Expr::Spanned { span: ref s, .. } if *s == (0..3)));
+    }
+
+    #[test]
+    fn desugar_errors_point_at_their_node() {
+        // (Closed, so that `y` is unbound.)
+        let (node, log) = crate::core::parse::parse_spanned(r"1 + (y * 2)").unwrap();
+        let e = desugar_spanned(&node, &NodeSpans::new([&node], log)).unwrap_err();
+        assert_eq!(e.kind, DesugarErrorKind::UnboundVariable("y".into()));
+        assert_eq!(e.span, Some(5..6));
+        let e = de_spanned(r"2 * (\x -> x x)").unwrap_err();
+        assert_eq!(e.name(), Some("x"));
+        assert_eq!(e.span, Some(5..14));
     }
 
     #[test]
@@ -12,10 +12,15 @@
 //! - cloned binders (`\&x`) are made **explicit** as binary dup chains, and
 //!   cloned lets are fresh re-instantiations,
 //! - list / string / char / cons sugar is fully desugared into constructors.
+//!
+//! Source positions are opt-in: the spanned desugarings wrap the expression of
+//! each surface node in an [`Expr::Spanned`], which only lowering looks at.
+
+use std::ops::Range;
 
 use ordered_float::OrderedFloat;
 
-use crate::vm::term::{BinaryOp, UnaryOp};
+use crate::vm::term::{BinaryOp, Sort, UnaryOp};
 
 /// A builtin scalar / boxed value, mirroring the primitive leaves of
 /// [`vm::term::Term`](crate::vm::term::Term). Numbers, floats, chars and bools
@@ -109,7 +114,10 @@ pub enum Expr {
         variant: Option<String>,
     },
     /// a type declaration `type { .. }`, evaluating to a fresh type value.
+    /// `name` is the one a surface declaration gave it, which readback labels
+    /// its values by; core's own `type { .. }` is anonymous.
     TypeDef {
+        name: Option<String>,
         kind: TypeDefKind,
     },
     /// pattern match / numeric switch / use. The `default`, when present, is a
@@ -131,4 +139,37 @@ pub enum Expr {
         op: UnaryOp,
         val: Box<Expr>,
     },
+    /// a dependent product `pi (x: input) -> ..`. `output` is a function from
+    /// the argument to the codomain; `erased` marks a grade-zero argument.
+    Pi {
+        erased: bool,
+        input: Box<Expr>,
+        output: Box<Expr>,
+    },
+    /// a sort: `Prop` or a universe `Type_i`.
+    Sort(Sort),
+    /// a lazy projection `value :> ty`: checks `value`'s runtime type against
+    /// the type expression `ty` only when the result is demanded.
+    Project {
+        value: Box<Expr>,
+        ty: Box<Expr>,
+    },
+    /// `body`, as desugared from the source byte range `span`. Semantically
+    /// transparent: lowering records the span against the node `body` lowers to
+    /// (see [`HeapScope::span_of`](crate::vm::heap::HeapScope::span_of)).
+    Spanned {
+        span: Range<usize>,
+        body: Box<Expr>,
+    },
+}
+
+impl Expr {
+    /// This expression with any [`Expr::Spanned`] wrappers peeled off.
+    pub fn unspanned(&self) -> &Expr {
+        let mut e = self;
+        while let Expr::Spanned { body, .. } = e {
+            e = body;
+        }
+        e
+    }
 }
@@ -2,3 +2,4 @@ pub mod ast;
 pub mod expr;
 pub mod parse;
 pub mod printer;
+pub mod span;
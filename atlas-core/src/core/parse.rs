@@ -1,5 +1,5 @@
 use chumsky::extra;
-use chumsky::input::{MappedInput, Stream, ValueInput};
+use chumsky::input::{MapExtra, MappedInput, Stream, ValueInput};
 use chumsky::pratt::*;
 use chumsky::prelude::*;
 use chumsky::span::SimpleSpan;
@@ -7,9 +7,26 @@ use logos::Logos;
 use ordered_float::OrderedFloat;
 
 use crate::core::ast::{Binding, InfixOp, Literal, Node, Pattern};
-use crate::vm::term::UnaryOp;
+use crate::core::span::SpanLog;
+use crate::error::ParseError;
+use crate::vm::term::{Sort, UnaryOp};
 
-type ParserError<'tokens, 'src> = extra::Err<Rich<'tokens, Token<'src>>>;
+type ParserError<'tokens, 'src> = extra::Full<Rich<'tokens, Token<'src>>, SpanLog, ()>;
+
+/// Record the span of a node just built, returning the node. Every parser that
+/// builds a [`Node`] (rather than passing one through) goes through this, so
+/// that the [`SpanLog`] lines up with the tree (see [`crate::core::span`]).
+fn spanned<'tokens, 'src: 'tokens, I>(
+    node: Node<'src>,
+    e: &mut MapExtra<'tokens, '_, I, ParserError<'tokens, 'src>>,
+) -> Node<'src>
+where
+    I: ValueInput<'tokens, Token = Token<'src>, Span = SimpleSpan>,
+{
+    let span = e.span().into_range();
+    e.state().push(span);
+    node
+}
 
 pub fn literal<'tokens, 'src: 'tokens, I>()
 -> impl Parser<'tokens, I, Literal<'src>, ParserError<'tokens, 'src>> + Clone
@@ -23,6 +40,7 @@ where
         Token::Char(c) => Literal::Char(c),
         Token::String(s) => Literal::String(s),
     }
+    .labelled("literal")
 }
 
 pub fn infix_op<'tokens, 'src: 'tokens, I>()
@@ -57,7 +75,8 @@ where
             Token::Identifier(name) => Binding::Var { name, auto_dup: false },
             Token::Constructor(name) => Binding::Var { name, auto_dup: false },
             Token::Underscore => Binding::Hole
-        },
+        }
+        .labelled("binder"),
         // &{a b c} explicit dup (all names share one duplication)
         just(Token::Ampersand)
             .ignore_then(just(Token::LBrace))
@@ -92,14 +111,16 @@ where
             .ignore_then(term.clone())
             .then_ignore(just(Token::RParen));
         // literals: 123, 'a', "foo"
-        let lit = literal().map(|lit| Node::Lit { val: lit });
-        let wild = just(Token::Underscore).map(|_| Node::Wild);
+        let lit = literal().map_with(|lit, e| spanned(Node::Lit { val: lit }, e));
+        let wild = just(Token::Underscore).map_with(|_, e| spanned(Node::Wild, e));
         // variables: x, Foo (uppercase names are ordinary variables now), %name
         let var = select! {
             Token::Identifier(name) => Node::Var { name },
             Token::Constructor(name) => Node::Var { name },
             Token::PriId(name) => Node::Primitive { name }
-        };
+        }
+        .labelled("name")
+        .map_with(spanned);
         // type declaration: the delimiter signals the kind.
         //   product/tuple: `type ( typeExpr,* )` — bare field type-expressions
         //   sum/enum:      `type { Variant,* }` — a variant is a bare `Name`
@@ -110,7 +131,7 @@ where
             .allow_trailing()
             .collect::<Vec<_>>()
             .delimited_by(just(Token::LParen), just(Token::RParen))
-            .map(|fields| Node::ProductType { fields });
+            .map_with(|fields, e| spanned(Node::ProductType { fields }, e));
         let variant = select! { Token::Constructor(name) => name }
             .then(
                 term.clone()
@@ -126,8 +147,11 @@ where
             .allow_trailing()
             .collect::<Vec<_>>()
             .delimited_by(just(Token::LBrace), just(Token::RBrace))
-            .map(|variants| Node::SumType { variants });
-        let type_decl = just(Token::TypeKw).ignore_then(choice((product_type, sum_type)));
+            .map_with(|variants, e| spanned(Node::SumType { variants }, e));
+        let type_decl = just(Token::TypeKw)
+            .ignore_then(choice((product_type, sum_type)))
+            .labelled("type")
+            .as_context();
         // lambda: \ Binding* . term
         // where Binding = x | &x | &{a b c} | _
         let binding = binding();
@@ -135,16 +159,19 @@ where
             .ignore_then(binding.clone().repeated().at_least(1).collect::<Vec<_>>())
             .then_ignore(just(Token::Arrow))
             .then(term.clone())
-            .map(|(binders, body)| {
+            .map_with(|(binders, body), e| {
                 if binders.len() > 0 {
-                    Node::Lambda {
+                    let lambda = Node::Lambda {
                         binders,
                         body: Box::new(body),
-                    }
+                    };
+                    spanned(lambda, e)
                 } else {
                     body
                 }
-            });
+            })
+            .labelled("lambda")
+            .as_context();
         // match: ?{ pattern binders* -> term; ... ; _ -> term }
         // The default branch is written `_ -> term` (erasing), `x -> term` (a
         // lowercase identifier binding the whole scrutinee), or `&x -> term`
@@ -166,22 +193,27 @@ where
             select! { Token::Identifier(name) => Pattern::Bind(name) },
             just(Token::Ampersand)
                 .ignore_then(select! { Token::Identifier(name) => Pattern::BindDup(name) }),
-        ));
+        ))
+        .labelled("pattern");
         let cases = pattern
             .then(binding.clone().repeated().collect::<Vec<_>>())
             .then_ignore(just(Token::Arrow))
             .then(term.clone())
-            .map(|((pat, binders), body)| {
+            .map_with(|((pat, binders), body), e| {
                 let body = if binders.is_empty() {
                     body
                 } else {
-                    Node::Lambda {
+                    // The binders' lambda spans the whole case.
+                    let lambda = Node::Lambda {
                         binders,
                         body: Box::new(body),
-                    }
+                    };
+                    spanned(lambda, e)
                 };
                 (pat, body)
             })
+            .labelled("match case")
+            .as_context()
             .separated_by(just(Token::Semicolon))
             .collect::<Vec<_>>();
         let mat = just(Token::Question)
@@ -189,16 +221,19 @@ where
             .ignore_then(cases)
             .then(term.clone().or_not())
             .then_ignore(just(Token::RBrace))
-            .map(|(cases, default)| {
-                if cases.is_empty() && default.is_none() {
+            .map_with(|(cases, default), e| {
+                let node = if cases.is_empty() && default.is_none() {
                     Node::Erase
                 } else {
                     Node::Match {
                         cases,
                         default: default.map(Box::new),
                     }
-                }
-            });
+                };
+                spanned(node, e)
+            })
+            .labelled("match")
+            .as_context();
         // explicit list constructor [node, node, ...]
         // gets desugared to #Con{node, #Con{node, #Con{node, #Nil}}}
         let list = just(Token::LBracket)
@@ -208,7 +243,9 @@ where
                     .collect::<Vec<_>>(),
             )
             .then_ignore(just(Token::RBracket))
-            .map(|elems| Node::List { elems });
+            .map_with(|elems, e| spanned(Node::List { elems }, e))
+            .labelled("list")
+            .as_context();
         // sup: `&{a, b}` (and `&{}` for erasure)
         let sup = just(Token::Ampersand)
             .ignore_then(just(Token::LBrace))
@@ -219,29 +256,95 @@ where
                     .collect::<Vec<_>>(),
             )
             .then_ignore(just(Token::RBrace))
-            .map(|nodes| {
-                if nodes.is_empty() {
+            .map_with(|nodes, e| {
+                let node = if nodes.is_empty() {
                     Node::Erase
                 } else {
                     Node::Sup { nodes }
-                }
-            });
+                };
+                spanned(node, e)
+            })
+            .labelled("superposition")
+            .as_context();
         // fix: the Y-combinator atom. `fix f` reduces to `f (fix f)`.
-        let fix = just(Token::Fix).map(|_| Node::Fix);
+        let fix = just(Token::Fix).map_with(|_, e| spanned(Node::Fix, e));
+        // sorts: `Prop` and the universes `Type_0`, `Type_1`, ..
+        let sort = select! {
+            Token::Prop => Sort::Prop,
+            Token::Universe(level) => Sort::Type(level),
+        }
+        .map_with(|sort, e| spanned(Node::Sort(sort), e));
+        // dependent product: pi (x: A) {y: B} -> C
+        // Each group is one binder; `{..}` marks an erased argument. Groups
+        // nest to the right, so later inputs may mention earlier binders.
+        let pi_group = |open, close, erased| {
+            binding
+                .clone()
+                .then_ignore(just(Token::Colon))
+                .then(term.clone())
+                .delimited_by(just(open), just(close))
+                .map_with(
+                    move |(binder, input), e: &mut MapExtra<'tokens, '_, I, ParserError<'tokens, 'src>>| {
+                        (binder, erased, input, e.span().start)
+                    },
+                )
+        };
+        let pi = just(Token::Pi)
+            .ignore_then(
+                choice((
+                    pi_group(Token::LParen, Token::RParen, false),
+                    pi_group(Token::LBrace, Token::RBrace, true),
+                ))
+                .repeated()
+                .at_least(1)
+                .collect::<Vec<_>>(),
+            )
+            .then_ignore(just(Token::Arrow))
+            .then(term.clone())
+            .map_with(|(groups, output), e| {
+                // An inner product spans its own binder group through the end.
+                let end = e.span().into_range().end;
+                let mut groups = groups.into_iter();
+                let (binder, erased, input, _) = groups.next().expect("at least one group");
+                let inner = groups
+                    .rev()
+                    .fold(output, |output, (binder, erased, input, start)| {
+                        let pi = Node::Pi {
+                            binder,
+                            erased,
+                            input: Box::new(input),
+                            output: Box::new(output),
+                        };
+                        e.state().push(start..end);
+                        pi
+                    });
+                let pi = Node::Pi {
+                    binder,
+                    erased,
+                    input: Box::new(input),
+                    output: Box::new(inner),
+                };
+                spanned(pi, e)
+            })
+            .labelled("pi")
+            .as_context();
         // All atoms
         let atom = choice((
-            group, lit, wild, type_decl, var, list, mat, sup, lambda, fix,
+            group, lit, wild, type_decl, var, list, mat, sup, lambda, fix, sort, pi,
         ));
         // Postfix variant selector: `atom :: Name` (binds tighter than application).
-        let selected = atom.foldl(
+        let selected = atom.foldl_with(
             just(Token::ColonColon)
-                .ignore_then(select! { Token::Constructor(name) => name })
+                .ignore_then(select! { Token::Constructor(name) => name }.labelled("constructor"))
                 .repeated(),
-            |ty, name| Node::Ctr {
-                ty: Box::new(ty),
-                // `::New` is the product constructor (no variant); any other
-                // name selects a sum variant.
-                variant: (name != "New").then_some(name),
+            |ty, name, e| {
+                let ctr = Node::Ctr {
+                    ty: Box::new(ty),
+                    // `::New` is the product constructor (no variant); any other
+                    // name selects a sum variant.
+                    variant: (name != "New").then_some(name),
+                };
+                spanned(ctr, e)
             },
         );
         // Handle either atom or application
@@ -249,36 +352,51 @@ where
             .repeated()
             .at_least(1)
             .collect::<Vec<_>>()
-            .map(|mut atoms| {
+            .map_with(|mut atoms, e| {
                 let start = atoms.remove(0);
                 match atoms.len() {
                     0 => start,
-                    _ => Node::App {
-                        func: Box::new(start),
-                        args: atoms,
-                    },
+                    _ => {
+                        let app = Node::App {
+                            func: Box::new(start),
+                            args: atoms,
+                        };
+                        spanned(app, e)
+                    }
                 }
             });
         let infix_op = |prec: u16, tok: Token<'src>, op: InfixOp| {
-            infix(left(prec), just(tok), move |l, _, r, _| Node::Infix {
-                left: Box::new(l),
-                op,
-                right: Box::new(r),
+            infix(left(prec), just(tok), move |l, _, r, e| {
+                let infix = Node::Infix {
+                    left: Box::new(l),
+                    op,
+                    right: Box::new(r),
+                };
+                spanned(infix, e)
             })
         };
         let top_level = app.pratt((
             // prefix unary operators bind tighter than any infix operator
-            prefix(10, just(Token::Minus), |_, e, _| Node::Unary {
-                op: UnaryOp::Neg,
-                expr: Box::new(e),
+            prefix(10, just(Token::Minus), |_, operand, e| {
+                let unary = Node::Unary {
+                    op: UnaryOp::Neg,
+                    expr: Box::new(operand),
+                };
+                spanned(unary, e)
             }),
-            prefix(10, just(Token::Tilde), |_, e, _| Node::Unary {
-                op: UnaryOp::Not,
-                expr: Box::new(e),
+            prefix(10, just(Token::Tilde), |_, operand, e| {
+                let unary = Node::Unary {
+                    op: UnaryOp::Not,
+                    expr: Box::new(operand),
+                };
+                spanned(unary, e)
             }),
-            prefix(10, just(Token::TypeOf), |_, e, _| Node::Unary {
-                op: UnaryOp::TypeOf,
-                expr: Box::new(e),
+            prefix(10, just(Token::TypeOf), |_, operand, e| {
+                let unary = Node::Unary {
+                    op: UnaryOp::TypeOf,
+                    expr: Box::new(operand),
+                };
+                spanned(unary, e)
             }),
             infix_op(9, Token::Cons, InfixOp::Cons),
             // `^ is the xor operator
@@ -297,8 +415,17 @@ where
             infix_op(4, Token::Gte, InfixOp::Gte),
             infix_op(3, Token::EqEq, InfixOp::Eq),
             infix_op(3, Token::Neq, InfixOp::Neq),
+            infix_op(3, Token::EqEqEq, InfixOp::Conv),
             infix_op(2, Token::AndAnd, InfixOp::And),
             infix_op(1, Token::OrOr, InfixOp::Or),
+            // projection binds loosest: `x + 1 :> Int` checks the sum.
+            infix(left(0), just(Token::ColonGt), |value, _, ty, e| {
+                let project = Node::Project {
+                    value: Box::new(value),
+                    ty: Box::new(ty),
+                };
+                spanned(project, e)
+            }),
         ));
         let let_binding = binding
             .then_ignore(just(Token::Equals))
@@ -308,12 +435,13 @@ where
             .repeated()
             .collect::<Vec<_>>()
             .then(top_level.clone())
-            .map(|(bindings, body)| {
+            .map_with(|(bindings, body), e| {
                 if bindings.len() > 0 {
-                    Node::Let {
+                    let lets = Node::Let {
                         bindings,
                         body: Box::new(body),
-                    }
+                    };
+                    spanned(lets, e)
                 } else {
                     body
                 }
@@ -322,15 +450,21 @@ where
 }
 
 /// Parse a single source expression into an AST [`Node`].
-pub fn parse<'src>(input: &'src str) -> Result<Node<'src>, String> {
+pub fn parse<'src>(input: &'src str) -> Result<Node<'src>, ParseError> {
+    parse_spanned(input).map(|(node, _)| node)
+}
+
+/// Like [`parse`], but also return the spans of the tree's nodes, for
+/// [`NodeSpans`](crate::core::span::NodeSpans).
+pub fn parse_spanned<'src>(input: &'src str) -> Result<(Node<'src>, SpanLog), ParseError> {
     let lexer = Lexer::new(input);
     let stream = lexer.into_stream();
-    expr().parse(stream).into_result().map_err(|errs| {
-        errs.iter()
-            .map(|e| e.to_string())
-            .collect::<Vec<_>>()
-            .join("\n")
-    })
+    let mut log = SpanLog::default();
+    let node = expr()
+        .parse_with_state(stream, &mut log)
+        .into_result()
+        .map_err(ParseError::from_rich)?;
+    Ok((node, log))
 }
 
 /// A single REPL entry: either a bare expression to evaluate, or one or more
@@ -365,15 +499,23 @@ where
 }
 
 /// Parse a single REPL entry into a [`ReplInput`].
-pub fn parse_repl<'src>(input: &'src str) -> Result<ReplInput<'src>, String> {
+pub fn parse_repl<'src>(input: &'src str) -> Result<ReplInput<'src>, ParseError> {
+    parse_repl_spanned(input).map(|(input, _)| input)
+}
+
+/// Like [`parse_repl`], but also return the spans of the entry's nodes. The
+/// roots of a [`ReplInput::Decl`] are its right-hand sides, in order.
+pub fn parse_repl_spanned<'src>(
+    input: &'src str,
+) -> Result<(ReplInput<'src>, SpanLog), ParseError> {
     let lexer = Lexer::new(input);
     let stream = lexer.into_stream();
-    repl_input().parse(stream).into_result().map_err(|errs| {
-        errs.iter()
-            .map(|e| e.to_string())
-            .collect::<Vec<_>>()
-            .join("\n")
-    })
+    let mut log = SpanLog::default();
+    let input = repl_input()
+        .parse_with_state(stream, &mut log)
+        .into_result()
+        .map_err(ParseError::from_rich)?;
+    Ok((input, log))
 }
 
 #[derive(Logos, Debug, PartialEq, Eq, Clone)]
@@ -405,6 +547,11 @@ pub enum Token<'src> {
     #[token("typeof")] TypeOf,
     #[token("type")]   TypeKw,
     #[token("fix")]    Fix,
+    #[token("pi")]     Pi,
+    #[token("Prop")]   Prop,
+    // A universe `Type_i` must beat the constructor regex it also matches.
+    #[regex(r"Type_[0-9]+", |lex| lex.slice()[5..].parse().map_err(|_| ()), priority = 10)]
+    Universe(u32),
 
     #[token("%")] Percent,
     #[token("&")] Ampersand,
@@ -415,6 +562,7 @@ pub enum Token<'src> {
     #[token(",")] Comma,
     #[token("\\")] Backslash,
     #[token("::")] ColonColon,
+    #[token(":>")] ColonGt,
     #[token(":")] Colon,
     #[token(".")] Dot,
     #[token("(")] LParen,
@@ -684,6 +832,56 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_project() {
+        // `:>` binds looser than any operator, and associates to the left.
+        assert_eq!(
+            parse("x + 1 :> Int :> T"),
+            Ok(Node::Project {
+                value: Box::new(Node::Project {
+                    value: Box::new(Node::Infix {
+                        left: Box::new(Node::Var { name: "x" }),
+                        op: InfixOp::Add,
+                        right: Box::new(Node::Lit {
+                            val: Literal::Integer(1)
+                        }),
+                    }),
+                    ty: Box::new(Node::Var { name: "Int" }),
+                }),
+                ty: Box::new(Node::Var { name: "T" }),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_pi_and_sorts() {
+        assert_eq!(parse("Prop"), Ok(Node::Sort(Sort::Prop)));
+        assert_eq!(parse("Type_2"), Ok(Node::Sort(Sort::Type(2))));
+        // Other names that merely start with `Type` are still variables.
+        assert_eq!(parse("Type_x"), Ok(Node::Var { name: "Type_x" }));
+        // Binder groups nest to the right; braces mark an erased argument.
+        assert_eq!(
+            parse("pi {&A: Type_0} (x: A) -> A"),
+            Ok(Node::Pi {
+                binder: Binding::Var {
+                    name: "A",
+                    auto_dup: true
+                },
+                erased: true,
+                input: Box::new(Node::Sort(Sort::Type(0))),
+                output: Box::new(Node::Pi {
+                    binder: Binding::Var {
+                        name: "x",
+                        auto_dup: false
+                    },
+                    erased: false,
+                    input: Box::new(Node::Var { name: "A" }),
+                    output: Box::new(Node::Var { name: "A" }),
+                }),
+            })
+        );
+    }
+
     #[test]
     fn test_parse_match() {
         assert_eq!(
@@ -857,6 +1055,37 @@ mod tests {
             })
         );
     }
+
+    #[test]
+    fn syntax_errors_record_what_was_expected_and_where() {
+        let e = super::parse_repl(r"?{Foo a -> }").unwrap_err();
+        let [e] = &e.errors[..] else { panic!("{e:?}") };
+        assert_eq!(e.span, 11..12);
+        assert_eq!(e.found.as_deref(), Some("}"));
+        assert!(e.expected.iter().any(|x| x == "lambda"), "{e:?}");
+        assert_eq!(
+            e.contexts,
+            [
+                ("match case".to_string(), 2..10),
+                ("match".to_string(), 0..10)
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_errors_render_as_reports() {
+        use crate::error::ReportStyle;
+        let src = r"\x -> (x";
+        let e = super::parse_repl(src).unwrap_err();
+        let plain = e.render("<repl>", src, ReportStyle::Plain);
+        assert!(plain.contains("<repl>:1:9"), "{plain}");
+        assert!(plain.contains("unexpected end of input"), "{plain}");
+        assert!(plain.contains("while parsing this lambda"), "{plain}");
+        assert!(plain.contains("expected one of"), "{plain}");
+        assert!(!plain.contains('\x1b'), "{plain}");
+        let color = e.render("<repl>", src, ReportStyle::Color);
+        assert!(color.contains("\x1b["), "{color}");
+    }
 }
 
 impl<'src> std::fmt::Display for Token<'src> {
@@ -879,7 +1108,11 @@ impl<'src> std::fmt::Display for Token<'src> {
             Token::TypeOf => write!(f, "typeof"),
             Token::TypeKw => write!(f, "type"),
             Token::Fix => write!(f, "fix"),
+            Token::Pi => write!(f, "pi"),
+            Token::Prop => write!(f, "Prop"),
+            Token::Universe(level) => write!(f, "Type_{}", level),
             Token::ColonColon => write!(f, "::"),
+            Token::ColonGt => write!(f, ":>"),
             Token::Colon => write!(f, ":"),
             Token::Dot => write!(f, "."),
             Token::LParen => write!(f, "("),
@@ -121,7 +121,7 @@ impl Namer {
                 // between `=` and `;`, so a `Lam`/`Use` value needs no parens
                 // (render as tail); a nested `Dup` value does, to keep its own `;`
                 // unambiguous.
-                let val_tail = !matches!(val.as_ref(), Expr::Dup { .. });
+                let val_tail = !matches!(val.unspanned(), Expr::Dup { .. });
                 write!(f, "&{{{name}}} = ")?;
                 self.go(f, val, val_tail)?;
                 write!(f, ";{}", if tail { '\n' } else { ' ' })?;
@@ -182,6 +182,59 @@ impl Namer {
                 self.go(f, val, false)?;
                 write!(f, ")")
             }
+            Expr::Pi {
+                erased,
+                input,
+                output,
+            } => {
+                if !tail {
+                    write!(f, "(")?;
+                }
+                let (open, close) = if *erased { ('{', '}') } else { ('(', ')') };
+                let r = match output.unspanned() {
+                    // Print the codomain's lambda as the binder itself.
+                    Expr::Lam { body } => {
+                        let name = self.fresh();
+                        write!(f, "pi {open}{name}: ")?;
+                        self.go(f, input, false)?;
+                        write!(f, "{close} -> ")?;
+                        self.env.push(Binder::Lam(name));
+                        let r = self.go(f, body, true);
+                        self.env.pop();
+                        r
+                    }
+                    Expr::Use { body } => {
+                        write!(f, "pi {open}_: ")?;
+                        self.go(f, input, false)?;
+                        write!(f, "{close} -> ")?;
+                        self.env.push(Binder::Erased);
+                        let r = self.go(f, body, true);
+                        self.env.pop();
+                        r
+                    }
+                    _ => {
+                        let name = self.fresh();
+                        write!(f, "pi {open}{name}: ")?;
+                        self.go(f, input, false)?;
+                        write!(f, "{close} -> (")?;
+                        self.go(f, output, false)?;
+                        write!(f, " {name})")
+                    }
+                };
+                r?;
+                if !tail {
+                    write!(f, ")")?;
+                }
+                Ok(())
+            }
+            Expr::Sort(sort) => write!(f, "{sort}"),
+            Expr::Project { value, ty } => {
+                write!(f, "(")?;
+                self.go(f, value, false)?;
+                write!(f, " :> ")?;
+                self.go(f, ty, false)?;
+                write!(f, ")")
+            }
             Expr::Ctr { ty, variant } => {
                 self.go(f, ty, false)?;
                 match variant {
@@ -189,7 +242,7 @@ impl Namer {
                     None => write!(f, "::New"),
                 }
             }
-            Expr::TypeDef { kind } => match kind {
+            Expr::TypeDef { kind, .. } => match kind {
                 TypeDefKind::Product(fields) => {
                     write!(f, "type(")?;
                     for (i, t) in fields.iter().enumerate() {
@@ -221,6 +274,7 @@ impl Namer {
                     write!(f, "}}")
                 }
             },
+            Expr::Spanned { body, .. } => self.go(f, body, tail),
             Expr::Mat { cases, default } => {
                 write!(f, "?{{")?;
                 let mut first = true;
@@ -244,7 +298,7 @@ impl Namer {
                     // The default is a lambda applied to the scrutinee: an erasing
                     // `Use` prints as `_ -> body`, a `Lam` binds a fresh name, and
                     // anything else is the bare use-form `?{ term }`.
-                    match d.as_ref() {
+                    match d.unspanned() {
                         Expr::Use { body } => {
                             write!(f, "_ -> ")?;
                             self.env.push(Binder::Erased);
@@ -305,6 +359,10 @@ mod tests {
     fn basic_shapes() {
         assert_eq!(pp(r"\x -> x + 1"), "\\a -> (a + 1)");
         assert_eq!(pp(r"?{1 -> 100; 2 -> 200}"), "?{1 -> 100; 2 -> 200}");
+        assert_eq!(
+            pp(r"pi {A: Type_0} (_: Prop) -> A"),
+            "pi {a: Type_0} -> pi (_: Prop) -> a"
+        );
     }
 
     #[test]
@@ -0,0 +1,235 @@
+//! Source spans of the surface AST, kept beside the tree rather than in it.
+//!
+//! The parser records the byte range of every [`Node`] it builds into a
+//! [`SpanLog`], in the order it finishes them: children before their parent,
+//! siblings left to right. Once the tree has stopped moving, [`NodeSpans`]
+//! replays that log over a post-order walk of the tree to key each span by its
+//! node's address, which is how [`desugar_spanned`](crate::core::ast::desugar_spanned)
+//! looks them up.
+
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::ops::Range;
+
+use chumsky::input::{Checkpoint, Cursor, Input};
+use chumsky::inspector::Inspector;
+
+use crate::core::ast::Node;
+
+/// The spans of the nodes a parse built, in post-order.
+///
+/// Used as the parser's state: a branch the parser backtracks out of is
+/// rewound out of the log along with the input, so only the nodes of the final
+/// tree remain.
+#[derive(Debug, Clone, Default)]
+pub struct SpanLog {
+    spans: Vec<Range<usize>>,
+}
+
+impl SpanLog {
+    /// Record the span of a node just built.
+    pub fn push(&mut self, span: Range<usize>) {
+        self.spans.push(span);
+    }
+
+    pub fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    /// The recorded spans, in the order they were pushed.
+    pub fn into_spans(self) -> Vec<Range<usize>> {
+        self.spans
+    }
+}
+
+impl<'src, I: Input<'src>> Inspector<'src, I> for SpanLog {
+    type Checkpoint = usize;
+
+    fn on_token(&mut self, _: &I::Token) {}
+
+    fn on_save<'parse>(&self, _: &Cursor<'src, 'parse, I>) -> usize {
+        self.spans.len()
+    }
+
+    fn on_rewind<'parse>(&mut self, marker: &Checkpoint<'src, 'parse, I, usize>) {
+        self.spans.truncate(*marker.inspector());
+    }
+}
+
+/// The source byte range of each node of a parsed tree, by node address.
+///
+/// Borrows the tree, so it cannot be moved (and its nodes' addresses
+/// invalidated) while the spans are in use.
+#[derive(Debug, Default)]
+pub struct NodeSpans<'n> {
+    spans: HashMap<*const Node<'n>, Range<usize>>,
+    _tree: PhantomData<&'n Node<'n>>,
+}
+
+impl<'n> NodeSpans<'n> {
+    /// Key the spans `log` recorded while parsing `roots` (in source order) by
+    /// the nodes they belong to.
+    pub fn new(roots: impl IntoIterator<Item = &'n Node<'n>>, log: SpanLog) -> Self {
+        let mut spans = HashMap::with_capacity(log.len());
+        let mut log = log.spans.into_iter();
+        // Post-order with children in source order, the order the parser built
+        // them. `true` marks a node whose children have all been visited.
+        let roots: Vec<_> = roots.into_iter().collect();
+        let mut stack: Vec<(&'n Node<'n>, bool)> =
+            roots.into_iter().rev().map(|n| (n, false)).collect();
+        let mut children = Vec::new();
+        while let Some((node, done)) = stack.pop() {
+            if done {
+                let span = log.next();
+                debug_assert!(span.is_some(), "fewer spans recorded than nodes built");
+                if let Some(span) = span {
+                    spans.insert(node as *const Node<'n>, span);
+                }
+                continue;
+            }
+            stack.push((node, true));
+            children.clear();
+            node_children(node, &mut children);
+            stack.extend(children.drain(..).rev().map(|c| (c, false)));
+        }
+        debug_assert!(log.next().is_none(), "more spans recorded than nodes built");
+        NodeSpans {
+            spans,
+            _tree: PhantomData,
+        }
+    }
+
+    /// The source byte range `node` was parsed from.
+    pub fn get(&self, node: &Node<'n>) -> Option<Range<usize>> {
+        self.spans.get(&(node as *const Node<'n>)).cloned()
+    }
+}
+
+/// The direct children of `node`, in source order.
+fn node_children<'n>(node: &'n Node<'n>, out: &mut Vec<&'n Node<'n>>) {
+    match node {
+        Node::Lit { .. }
+        | Node::Var { .. }
+        | Node::Primitive { .. }
+        | Node::Erase
+        | Node::Wild
+        | Node::Sort(_)
+        | Node::Fix => {}
+        Node::List { elems: nodes } | Node::Sup { nodes } | Node::ProductType { fields: nodes } => {
+            out.extend(nodes)
+        }
+        Node::SumType { variants } => out.extend(variants.iter().flat_map(|(_, args)| args)),
+        Node::Let { bindings, body } => {
+            out.extend(bindings.iter().map(|(_, val)| val));
+            out.push(body);
+        }
+        Node::Lambda { body, .. } => out.push(body),
+        Node::Pi { input, output, .. } => {
+            out.push(input);
+            out.push(output);
+        }
+        Node::Ctr { ty, .. } => out.push(ty),
+        Node::Match { cases, default } => {
+            out.extend(cases.iter().map(|(_, body)| body));
+            out.extend(default.as_deref());
+        }
+        Node::App { func, args } => {
+            out.push(func);
+            out.extend(args);
+        }
+        Node::Infix { left, right, .. } => {
+            out.push(left);
+            out.push(right);
+        }
+        Node::Unary { expr, .. } => out.push(expr),
+        Node::Project { value, ty } => {
+            out.push(value);
+            out.push(ty);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NodeSpans;
+    use crate::core::ast::Node;
+    use crate::core::parse::{ReplInput, parse_repl_spanned, parse_spanned};
+
+    /// The source text of `node`.
+    fn text<'s>(src: &'s str, spans: &NodeSpans, node: &Node) -> &'s str {
+        &src[spans.get(node).expect("node has no span")]
+    }
+
+    #[test]
+    fn every_node_gets_its_own_span() {
+        let src = r"f (1 + x) [y, 2]";
+        let (node, log) = parse_spanned(src).unwrap();
+        let spans = NodeSpans::new([&node], log);
+        let Node::App { func, args } = &node else {
+            panic!("{node:?}")
+        };
+        assert_eq!(text(src, &spans, &node), src);
+        assert_eq!(text(src, &spans, func), "f");
+        // The parentheses only group: the infix is their content.
+        assert_eq!(text(src, &spans, &args[0]), "1 + x");
+        let Node::Infix { left, right, .. } = &args[0] else {
+            panic!("{:?}", args[0])
+        };
+        assert_eq!(text(src, &spans, left), "1");
+        assert_eq!(text(src, &spans, right), "x");
+        let Node::List { elems } = &args[1] else {
+            panic!("{:?}", args[1])
+        };
+        assert_eq!(text(src, &spans, &args[1]), "[y, 2]");
+        assert_eq!(text(src, &spans, &elems[1]), "2");
+    }
+
+    #[test]
+    fn inner_products_span_from_their_binder() {
+        let src = r"pi (&A: Type_0) (x: A) -> A";
+        let (node, log) = parse_spanned(src).unwrap();
+        let spans = NodeSpans::new([&node], log);
+        let Node::Pi { output, .. } = &node else {
+            panic!("{node:?}")
+        };
+        assert_eq!(text(src, &spans, &node), src);
+        assert_eq!(text(src, &spans, output), "(x: A) -> A");
+    }
+
+    #[test]
+    fn backtracked_nodes_leave_no_span() {
+        // `x = 1;` first parses as the start of a declaration, then the whole
+        // line is reparsed as a `let` expression.
+        let src = r"x = 1 + 2; ?{Foo a -> a; _ -> x}";
+        let (input, log) = parse_repl_spanned(src).unwrap();
+        let ReplInput::Expr(node) = &input else {
+            panic!("{input:?}")
+        };
+        let spans = NodeSpans::new([node], log);
+        let Node::Let { bindings, body } = node else {
+            panic!("{node:?}")
+        };
+        assert_eq!(text(src, &spans, &bindings[0].1), "1 + 2");
+        let Node::Match { cases, .. } = &**body else {
+            panic!("{body:?}")
+        };
+        // A case's binders make a lambda spanning the case.
+        assert_eq!(text(src, &spans, &cases[0].1), "Foo a -> a");
+    }
+
+    #[test]
+    fn declarations_are_spanned_in_order() {
+        let src = r"a = 1; b = \x -> x";
+        let (input, log) = parse_repl_spanned(src).unwrap();
+        let ReplInput::Decl(bindings) = &input else {
+            panic!("{input:?}")
+        };
+        let spans = NodeSpans::new(bindings.iter().map(|(_, value)| value), log);
+        assert_eq!(text(src, &spans, &bindings[0].1), "1");
+        assert_eq!(text(src, &spans, &bindings[1].1), r"\x -> x");
+    }
+}
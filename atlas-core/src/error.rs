@@ -0,0 +1,517 @@
+//! The errors of the source-to-normal-form pipeline.
+//!
+//! Each stage — parsing, desugaring (and type checking, for languages that
+//! have one), lowering onto the heap, running host primitives, reducing under
+//! a budget — fails with its own structured error,
+//! and [`Error`] is their union, so callers can branch on what went wrong and
+//! render it (with its source span, where one is known) instead of matching on
+//! message text.
+
+use crate::vm::term::PrimId;
+use ariadne::{Color, Config, IndexType, Label, Report, ReportKind, Source};
+use chumsky::error::Rich;
+use std::fmt;
+use std::ops::Range;
+
+/// Anything that can stop a source program from reaching its normal form.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    Parse(ParseError),
+    Desugar(DesugarError),
+    Type(TypeError),
+    Lower(LowerError),
+    Primitive(PrimError),
+    /// Reduction stopped after `budget` interactions without reaching normal
+    /// form.
+    BudgetExhausted {
+        budget: u64,
+    },
+    /// The async runtime reduction is driven on could not be started.
+    Runtime(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Parse(e) => write!(f, "{e}"),
+            Error::Desugar(e) => write!(f, "{e}"),
+            Error::Type(e) => write!(f, "{e}"),
+            Error::Lower(e) => write!(f, "{e}"),
+            Error::Primitive(e) => write!(f, "{e}"),
+            Error::BudgetExhausted { budget } => {
+                write!(f, "no normal form within {budget} interactions")
+            }
+            Error::Runtime(message) => write!(f, "cannot start the runtime: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<ParseError> for Error {
+    fn from(e: ParseError) -> Self {
+        Error::Parse(e)
+    }
+}
+
+impl From<DesugarError> for Error {
+    fn from(e: DesugarError) -> Self {
+        Error::Desugar(e)
+    }
+}
+
+impl From<TypeError> for Error {
+    fn from(e: TypeError) -> Self {
+        Error::Type(e)
+    }
+}
+
+impl From<LowerError> for Error {
+    fn from(e: LowerError) -> Self {
+        Error::Lower(e)
+    }
+}
+
+impl From<PrimError> for Error {
+    fn from(e: PrimError) -> Self {
+        Error::Primitive(e)
+    }
+}
+
+// ========================================================================
+// Parsing
+// ========================================================================
+
+/// The source does not parse. Holds every syntax error the parser recovered
+/// to report, in source order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub errors: Vec<SyntaxError>,
+}
+
+/// One syntax error: what the parser expected and found, at a byte range of
+/// the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+    pub span: Range<usize>,
+    pub message: String,
+    /// The token found at `span`; `None` at the end of the input, and for an
+    /// error the parser raised itself rather than on an unexpected token.
+    pub found: Option<String>,
+    /// What would have been accepted at `span`, deduplicated.
+    pub expected: Vec<String>,
+    /// The labelled constructs the parser was inside, innermost first, with
+    /// the span each started at.
+    pub contexts: Vec<(String, Range<usize>)>,
+}
+
+/// How [`ParseError::render`] styles a report: with ANSI colour for a
+/// terminal, or plain for logs and batch tools.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportStyle {
+    Plain,
+    Color,
+}
+
+impl ParseError {
+    /// Collect the errors of a chumsky parse over a token stream whose spans
+    /// are byte offsets into the source, as a logos lexer's are.
+    pub fn from_rich<'a, T: fmt::Display + 'a>(
+        errors: impl IntoIterator<Item = Rich<'a, T>>,
+    ) -> Self {
+        ParseError {
+            errors: errors
+                .into_iter()
+                .map(|e| {
+                    let mut expected = Vec::new();
+                    for pattern in e.expected() {
+                        let pattern = pattern.to_string();
+                        if !expected.contains(&pattern) {
+                            expected.push(pattern);
+                        }
+                    }
+                    SyntaxError {
+                        span: e.span().into_range(),
+                        message: e.to_string(),
+                        found: e.found().map(|t| t.to_string()),
+                        expected,
+                        contexts: e
+                            .contexts()
+                            .map(|(label, span)| (label.to_string(), span.into_range()))
+                            .collect(),
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// Render every error as an ariadne report over `source`, which `name`
+    /// (a path, or `<repl>`) identifies in the report headers.
+    pub fn render(&self, name: &str, source: &str, style: ReportStyle) -> String {
+        let mut out = Vec::new();
+        let mut cache = (name, Source::from(source));
+        for e in &self.errors {
+            e.report(name, style)
+                .write(&mut cache, &mut out)
+                .expect("writing to a Vec cannot fail");
+        }
+        String::from_utf8_lossy(&out).into_owned()
+    }
+}
+
+impl SyntaxError {
+    /// What the parser ran into, as the report's headline.
+    fn unexpected(&self) -> String {
+        match &self.found {
+            Some(found) => format!("unexpected `{found}`"),
+            None if self.expected.is_empty() => self.message.clone(),
+            None => "unexpected end of input".to_string(),
+        }
+    }
+
+    fn report<'a>(
+        &self,
+        name: &'a str,
+        style: ReportStyle,
+    ) -> Report<'static, (&'a str, Range<usize>)> {
+        let config = Config::new()
+            .with_index_type(IndexType::Byte)
+            .with_color(style == ReportStyle::Color);
+        let headline = self.unexpected();
+        let mut report = Report::build(ReportKind::Error, (name, self.span.clone()))
+            .with_config(config)
+            .with_message(&headline)
+            .with_label(
+                Label::new((name, self.span.clone()))
+                    .with_message(headline)
+                    .with_color(Color::Red)
+                    .with_order(0),
+            );
+        for (i, (context, span)) in self.contexts.iter().enumerate() {
+            report.add_label(
+                Label::new((name, span.clone()))
+                    .with_message(format!("while parsing this {context}"))
+                    .with_color(Color::Blue)
+                    .with_order(i as i32 + 1),
+            );
+        }
+        match self.expected.as_slice() {
+            [] => {}
+            [one] => report.set_note(format!("expected {one}")),
+            many => report.set_note(format!("expected one of {}", many.join(", "))),
+        }
+        report.finish()
+    }
+}
+
+/// One error per line.
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, e) in self.errors.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{e}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+// ========================================================================
+// Desugaring
+// ========================================================================
+
+/// A parsed program that has no core form: an unbound name, an affine variable
+/// used twice, a construct the lowering does not support yet, ...
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DesugarError {
+    pub kind: DesugarErrorKind,
+    /// The source byte range of the offending node, when desugared with spans.
+    pub span: Option<Range<usize>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DesugarErrorKind {
+    /// A variable with no binding in scope.
+    UnboundVariable(String),
+    /// A constructor name no type in scope declares.
+    UnboundConstructor(String),
+    /// An affine variable used more than once.
+    AffineReuse { name: String, uses: usize },
+    /// A path to an item its module does not export (`pub`) to the use site.
+    Private(String),
+    /// Valid syntax the lowering does not handle yet.
+    Unsupported(String),
+    /// Syntax that parses but is not a well-formed program.
+    Malformed(String),
+}
+
+impl DesugarError {
+    pub fn new(kind: DesugarErrorKind) -> Self {
+        DesugarError { kind, span: None }
+    }
+
+    pub fn unsupported(message: impl Into<String>) -> Self {
+        DesugarError::new(DesugarErrorKind::Unsupported(message.into()))
+    }
+
+    pub fn malformed(message: impl Into<String>) -> Self {
+        DesugarError::new(DesugarErrorKind::Malformed(message.into()))
+    }
+
+    /// Point the error at `span`, unless it already points at a narrower one.
+    pub fn or_span(mut self, span: Range<usize>) -> Self {
+        self.span.get_or_insert(span);
+        self
+    }
+
+    /// The name the error is about, if it is about one.
+    pub fn name(&self) -> Option<&str> {
+        match &self.kind {
+            DesugarErrorKind::UnboundVariable(name)
+            | DesugarErrorKind::UnboundConstructor(name)
+            | DesugarErrorKind::AffineReuse { name, .. }
+            | DesugarErrorKind::Private(name) => Some(name),
+            DesugarErrorKind::Unsupported(_) | DesugarErrorKind::Malformed(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for DesugarError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            DesugarErrorKind::UnboundVariable(name) => write!(f, "unbound variable `{name}`"),
+            DesugarErrorKind::UnboundConstructor(name) => {
+                write!(f, "unbound constructor `{name}`")
+            }
+            DesugarErrorKind::AffineReuse { name, uses } => {
+                write!(
+                    f,
+                    "affine variable `{name}` used {uses} times; use `&{name}`"
+                )
+            }
+            DesugarErrorKind::Private(path) => write!(f, "`{path}` is private"),
+            DesugarErrorKind::Unsupported(message) | DesugarErrorKind::Malformed(message) => {
+                write!(f, "{message}")
+            }
+        }
+    }
+}
+
+// ========================================================================
+// Typing
+// ========================================================================
+
+/// A program that desugars but is ill-typed: a value of one type where another
+/// is required, a field its type does not have, ... Types are carried as their
+/// source-language rendering, since each front end has its own.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeError {
+    pub kind: TypeErrorKind,
+    /// The source byte range of the innermost expression the error is about,
+    /// when checked with spans.
+    pub span: Option<Range<usize>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeErrorKind {
+    /// A value of type `found` where one of type `expected` is required.
+    Mismatch { expected: String, found: String },
+    /// A type that would have to contain itself, as `x` does in `x x`.
+    Infinite { var: String, ty: String },
+    /// A value of type `found` called as a function.
+    NotAFunction { found: String },
+    /// A constructor given, or matched with, the wrong number of fields.
+    Arity {
+        ctor: String,
+        expected: usize,
+        found: usize,
+    },
+    /// `.field` of a type with no such field.
+    NoField { ty: String, field: String },
+    /// An annotation naming no type in scope.
+    UnknownType(String),
+}
+
+impl TypeError {
+    pub fn new(kind: TypeErrorKind) -> Self {
+        TypeError { kind, span: None }
+    }
+
+    /// Point the error at `span`, unless it already points at a narrower one.
+    pub fn or_span(mut self, span: Range<usize>) -> Self {
+        self.span.get_or_insert(span);
+        self
+    }
+
+    /// Render the error as an ariadne report over `source`, labelled at its
+    /// span, which `name` identifies as in [`ParseError::render`]. Without a
+    /// span there is nothing to point at, and the report is the message alone,
+    /// on an `error:` line.
+    pub fn render(&self, name: &str, source: &str, style: ReportStyle) -> String {
+        let Some(span) = self.span.clone() else {
+            return format!("error: {self}\n");
+        };
+        let config = Config::new()
+            .with_index_type(IndexType::Byte)
+            .with_color(style == ReportStyle::Color);
+        let label = match &self.kind {
+            TypeErrorKind::Mismatch { found, .. } | TypeErrorKind::NotAFunction { found } => {
+                format!("this has type `{found}`")
+            }
+            _ => self.to_string(),
+        };
+        let mut out = Vec::new();
+        Report::build(ReportKind::Error, (name, span.clone()))
+            .with_config(config)
+            .with_message(self.to_string())
+            .with_label(
+                Label::new((name, span))
+                    .with_message(label)
+                    .with_color(Color::Red),
+            )
+            .finish()
+            .write((name, Source::from(source)), &mut out)
+            .expect("writing to a Vec cannot fail");
+        String::from_utf8_lossy(&out).into_owned()
+    }
+}
+
+impl fmt::Display for TypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            TypeErrorKind::Mismatch { expected, found } => {
+                write!(f, "expected `{expected}`, found `{found}`")
+            }
+            TypeErrorKind::Infinite { var, ty } => {
+                write!(
+                    f,
+                    "the type `{var}` would have to contain itself, as `{ty}`"
+                )
+            }
+            TypeErrorKind::NotAFunction { found } => {
+                write!(f, "a value of type `{found}` is not a function")
+            }
+            TypeErrorKind::Arity {
+                ctor,
+                expected,
+                found,
+            } => write!(
+                f,
+                "`{ctor}` has {expected} field{}, but {found} {} given",
+                if *expected == 1 { "" } else { "s" },
+                if *found == 1 { "is" } else { "are" }
+            ),
+            TypeErrorKind::NoField { ty, field } => {
+                write!(f, "`{ty}` has no field `.{field}`")
+            }
+            TypeErrorKind::UnknownType(name) => write!(f, "unknown type `{name}`"),
+        }
+    }
+}
+
+// ========================================================================
+// Lowering
+// ========================================================================
+
+/// A desugared expression that cannot be placed on the heap.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LowerError {
+    /// `%name` is not a primitive of the extension set.
+    UnknownPrimitive(String),
+    /// A free name that no local resolves.
+    UnboundVariable(String),
+    /// A de Bruijn index out of range or naming the wrong kind of binder. The
+    /// desugarer never produces one; hand-built expressions can.
+    IllScoped(String),
+}
+
+impl fmt::Display for LowerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LowerError::UnknownPrimitive(name) => write!(f, "unknown primitive %{name}"),
+            LowerError::UnboundVariable(name) => write!(f, "unbound variable `{name}`"),
+            LowerError::IllScoped(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+// ========================================================================
+// Primitives
+// ========================================================================
+
+/// A host primitive failed. `id` is the primitive as the executor's extension
+/// set resolved it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrimError {
+    pub id: PrimId,
+    pub message: String,
+}
+
+impl PrimError {
+    pub fn new(id: PrimId, message: impl Into<String>) -> Self {
+        PrimError {
+            id,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for PrimError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+// ========================================================================
+// Heap images
+// ========================================================================
+
+/// A heap image that cannot be written or read back (see
+/// [`vm::image`](crate::vm::image)).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageError {
+    /// The bytes do not start with the image magic number.
+    NotAnImage,
+    /// The image was written in a format version this build does not read.
+    UnsupportedVersion(u32),
+    /// The image is truncated, or its records do not describe a well-formed
+    /// heap region.
+    Malformed(String),
+    /// A duplication in the region is being forced: the heap is not idle.
+    InFlight,
+    /// The saving extension set has no name for a primitive in the region.
+    UnnamedPrimitive(PrimId),
+    /// The loading extension set has no primitive of this name.
+    UnknownPrimitive(String),
+    /// An exported term has a variable whose lambda is not part of it.
+    FreeVariable,
+    /// A root to capture is another root, or part of one: restoring would
+    /// give its node two owners.
+    SharedRoot,
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageError::NotAnImage => write!(f, "not a heap image"),
+            ImageError::UnsupportedVersion(version) => {
+                write!(f, "unsupported heap image version {version}")
+            }
+            ImageError::Malformed(message) => write!(f, "malformed heap image: {message}"),
+            ImageError::InFlight => write!(f, "the heap is mid-reduction"),
+            ImageError::UnnamedPrimitive(id) => {
+                write!(f, "primitive {} has no name to save it by", id.get())
+            }
+            ImageError::UnknownPrimitive(name) => write!(f, "unknown primitive %{name}"),
+            ImageError::FreeVariable => write!(f, "the term is not closed"),
+            ImageError::SharedRoot => write!(f, "a root is shared with another root"),
+        }
+    }
+}
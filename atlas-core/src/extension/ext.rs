@@ -5,23 +5,27 @@ use std::future::Future;
 use std::pin::Pin;
 
 use super::handle::Handle;
+use crate::error::PrimError;
 use crate::vm::exec::{ExecPolicy, Executor};
 use crate::vm::term::PrimId;
 
 /// A boxed primitive-application future. The primitive forces the argument
 /// handles it needs (through the [`Executor`]) and yields the result handle, which
 /// re-enters reduction. It borrows the executor/extension for `'a`; the result
-/// node lives in scope `'h`.
-pub type PrimReduce<'a, 'h> = Pin<Box<dyn Future<Output = Result<Handle<'h>, String>> + 'a>>;
+/// node lives in scope `'h`. `Send` so a primitive can fire on any worker of a
+/// parallel executor.
+pub type PrimReduce<'a, 'h> =
+    Pin<Box<dyn Future<Output = Result<Handle<'h>, PrimError>> + Send + 'a>>;
 
 /// Translates and runs host-provided primitive functions (`%name`). `apply`
 /// receives the still-unforced argument [`Handle`]s together with the
 /// [`Executor`]; it forces (to WHNF) the inputs it needs itself (e.g. via
-/// `exec.whnf_at`), and returns the result handle or an evaluation error.
+/// `exec.whnf_at`), and returns the result handle or a [`PrimError`] naming
+/// the `id` it was applied as.
 /// Arguments it does not consume
 /// may simply be dropped — the executor reclaims them via
 /// [`erase_dropped_handles`](Executor::erase_dropped_handles).
-pub trait Extensions: Sized {
+pub trait Extensions: Sized + Sync {
     fn resolve(&self, name: &str) -> Option<PrimId>;
     fn arity(&self, id: PrimId) -> usize;
     fn name(&self, id: PrimId) -> Option<Cow<'_, str>>;
@@ -117,11 +121,13 @@ impl<L: Extensions, R: Extensions> Extensions for CombinedExtensions<L, R> {
         id: PrimId,
         args: Vec<Handle<'h>>,
     ) -> PrimReduce<'a, 'h> {
-        if id.get() & 1 == 0 {
+        let reduce = if id.get() & 1 == 0 {
             self.left.apply(exec, PrimId::new(id.get() >> 1), args)
         } else {
             self.right.apply(exec, PrimId::new(id.get() >> 1), args)
-        }
+        };
+        // The child reports its own ID for the primitive; report the combined one.
+        Box::pin(async move { reduce.await.map_err(|e| PrimError { id, ..e }) })
     }
 }
 
@@ -160,6 +166,7 @@ mod tests {
         fn resolve(&self, name: &str) -> Option<PrimId> {
             match name {
                 "right" | "same" => Some(PrimId::new(0)),
+                "oops" => Some(PrimId::new(1)),
                 _ => None,
             }
         }
@@ -172,10 +179,15 @@ mod tests {
         fn apply<'a, 'e, 'h, P: ExecPolicy, X: Extensions>(
             &'a self,
             exec: &'a Executor<'e, 'h, P, X>,
-            _: PrimId,
+            id: PrimId,
             _: Vec<Handle<'h>>,
         ) -> PrimReduce<'a, 'h> {
-            Box::pin(async move { Ok(Handle::new(exec.heap.alloc(Term::Int(2)), exec.heap)) })
+            Box::pin(async move {
+                match id.get() {
+                    0 => Ok(Handle::new(exec.heap.alloc(Term::Int(2)), exec.heap)),
+                    _ => Err(PrimError::new(id, "oops")),
+                }
+            })
         }
     }
 
@@ -194,4 +206,16 @@ mod tests {
         let nested = CombinedExtensions::new(CombinedExtensions::new(Left, Right), Left);
         assert_eq!(crate::vm::run_with("%right", &nested).unwrap(), "2");
     }
+
+    #[test]
+    fn combined_extensions_report_the_combined_id() {
+        let nested = CombinedExtensions::new(CombinedExtensions::new(Left, Right), Left);
+        match crate::vm::run_with("%oops", &nested) {
+            Err(crate::Error::Primitive(e)) => {
+                assert_eq!(e.id, nested.resolve("oops").unwrap());
+                assert_eq!(e.message, "oops");
+            }
+            other => panic!("expected a primitive error, got {other:?}"),
+        }
+    }
 }
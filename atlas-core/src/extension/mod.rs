@@ -8,7 +8,10 @@
 mod ext;
 mod handle;
 mod term;
+mod types;
 
+pub use crate::error::PrimError;
 pub use ext::{CombinedExtensions, Extensions, NoExtensions, PrimReduce};
 pub use handle::{Handle, TermPtrLike};
 pub use term::Term;
+pub use types::TypeExtensions;
@@ -0,0 +1,339 @@
+//! [`TypeExtensions`]: primitives that build and compare structural type
+//! records (`docs/chapters/06-structural-types.typ`).
+
+use std::borrow::Cow;
+
+use super::ext::{Extensions, PrimReduce};
+use super::handle::Handle;
+use crate::error::PrimError;
+use crate::vm::exec::{Clash, ExecPolicy, Executor, Lattice, Sat};
+use crate::vm::heap::{Boxed, Entry, Row, TermPtr, TypeInfo, TypePtr};
+use crate::vm::term::{PrimId, Term};
+
+/// The primitives, indexed by [`PrimId`]: name and arity.
+const PRIMITIVES: [(&str, usize); 7] = [
+    ("type_any", 0),
+    ("type_prop", 3),
+    ("type_op", 3),
+    ("type_sat", 2),
+    ("type_meet", 2),
+    ("type_join", 2),
+    ("prop", 2),
+];
+
+/// Structural type primitives, so a library can state a requirement such as
+/// "any value with a `len` property" without a nominal type.
+///
+/// - `%type_any` is `Any`: no layout and open, empty capability rows.
+/// - `%type_prop name sig t` and `%type_op name sig t` are `t` with the
+///   property / operator `name` (a `String`) given the signature `sig`,
+///   replacing an entry of that name. `sig` stays lazy.
+/// - `%type_sat actual expected` is whether `actual` satisfies `expected`.
+/// - `%type_meet a b` is the consistent meet (failing when there is none) and
+///   `%type_join a b` the join.
+/// - `%prop name v` is the property access `v.name`, for type evaluation's
+///   T-PROPERTY. Rows hold signatures but no implementations, so at run time
+///   it always fails.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TypeExtensions;
+
+impl Extensions for TypeExtensions {
+    fn resolve(&self, name: &str) -> Option<PrimId> {
+        (PRIMITIVES.iter())
+            .position(|&(n, _)| n == name)
+            .map(|i| PrimId::new(i as u64))
+    }
+
+    fn arity(&self, id: PrimId) -> usize {
+        PRIMITIVES[id.get() as usize].1
+    }
+
+    fn name(&self, id: PrimId) -> Option<Cow<'_, str>> {
+        (PRIMITIVES.get(id.get() as usize)).map(|&(name, _)| Cow::Borrowed(name))
+    }
+
+    fn apply<'a, 'e, 'h, P: ExecPolicy, X: Extensions>(
+        &'a self,
+        exec: &'a Executor<'e, 'h, P, X>,
+        id: PrimId,
+        args: Vec<Handle<'h>>,
+    ) -> PrimReduce<'a, 'h> {
+        Box::pin(async move {
+            let name = PRIMITIVES[id.get() as usize].0;
+            let term = apply(exec, name, args)
+                .await
+                .map_err(|message| PrimError::new(id, message))?;
+            Ok(Handle::new(exec.heap.alloc(term), exec.heap))
+        })
+    }
+}
+
+/// Run the primitive `name` on its (unforced) arguments.
+async fn apply<'e, 'h, P: ExecPolicy, X: Extensions>(
+    exec: &Executor<'e, 'h, P, X>,
+    name: &str,
+    args: Vec<Handle<'h>>,
+) -> Result<Term<'h>, String> {
+    let mut args = args.into_iter();
+    let mut arg = || args.next().expect("primitive arity");
+    match name {
+        "type_any" => Ok(Term::Type(exec.heap.alloc_type(TypeInfo::any()))),
+        "type_prop" | "type_op" => {
+            let (key, sig, ty) = (arg(), arg(), arg());
+            with_entry(exec, name, key, sig, ty).await
+        }
+        "prop" => {
+            let key = exec.whnf_at(arg()).await;
+            let name = match &*key.view() {
+                Term::Box(value) => match exec.heap.value_get(value) {
+                    Boxed::Str(name) => name.clone(),
+                    _ => return Err("%prop expects its name to be a String".into()),
+                },
+                _ => return Err("%prop expects its name to be a String".into()),
+            };
+            Err(format!(
+                "%prop: `.{name}` has a signature but no implementation"
+            ))
+        }
+        "type_sat" => {
+            let (actual, expected) = force_types(exec, name, arg(), arg()).await?;
+            let (sat, actual, expected) = exec.satisfies(actual, expected).await;
+            exec.erase(Term::Type(actual));
+            exec.erase(Term::Type(expected));
+            match sat {
+                Sat::Holds => Ok(Term::Bool(true)),
+                Sat::Fails => Ok(Term::Bool(false)),
+                Sat::Stuck => Err(format!("%{name} is stuck on a sub-type")),
+            }
+        }
+        _ => {
+            let op = match name {
+                "type_meet" => Lattice::Meet,
+                _ => Lattice::Join,
+            };
+            let (a, b) = force_types(exec, name, arg(), arg()).await?;
+            match exec.combine_types(a, b, op).await {
+                Ok(t) => Ok(Term::Type(t)),
+                Err(Clash::Inconsistent) if op == Lattice::Meet => {
+                    Err(format!("%{name}: the types have no consistent meet"))
+                }
+                Err(Clash::Inconsistent) => {
+                    Err(format!("%{name} expects each shared entry to be a type"))
+                }
+                Err(Clash::Stuck) => Err(format!("%{name} is stuck on a sub-type")),
+            }
+        }
+    }
+}
+
+/// Force `arg` to a type value.
+async fn force_type<'e, 'h, P: ExecPolicy, X: Extensions>(
+    exec: &Executor<'e, 'h, P, X>,
+    name: &str,
+    arg: Handle<'h>,
+) -> Result<TypePtr<'h>, String> {
+    let arg = exec.whnf_at(arg).await;
+    if !matches!(&*arg.view(), Term::Type(_)) {
+        return Err(format!("%{name} expects a type"));
+    }
+    let Term::Type(t) = exec.heap.pull(arg.into_term_ptr()) else {
+        unreachable!()
+    };
+    Ok(t)
+}
+
+/// Force both of a binary primitive's operands to type values.
+async fn force_types<'e, 'h, P: ExecPolicy, X: Extensions>(
+    exec: &Executor<'e, 'h, P, X>,
+    name: &str,
+    a: Handle<'h>,
+    b: Handle<'h>,
+) -> Result<(TypePtr<'h>, TypePtr<'h>), String> {
+    let a = force_type(exec, name, a).await?;
+    match force_type(exec, name, b).await {
+        Ok(b) => Ok((a, b)),
+        Err(message) => {
+            exec.erase(Term::Type(a));
+            Err(message)
+        }
+    }
+}
+
+/// `%type_prop` / `%type_op`: add (or replace) the entry `key: sig` in the
+/// property or operator row of `ty`.
+async fn with_entry<'e, 'h, P: ExecPolicy, X: Extensions>(
+    exec: &Executor<'e, 'h, P, X>,
+    name: &str,
+    key: Handle<'h>,
+    sig: Handle<'h>,
+    ty: Handle<'h>,
+) -> Result<Term<'h>, String> {
+    let key = exec.whnf_at(key).await;
+    let key = match &*key.view() {
+        Term::Box(value) => match exec.heap.value_get(value) {
+            Boxed::Str(key) => exec.heap.intern_variant(key),
+            _ => return Err(format!("%{name} expects its name to be a String")),
+        },
+        _ => return Err(format!("%{name} expects its name to be a String")),
+    };
+    let ty = force_type(exec, name, ty).await?;
+    let mut info = exec.heap.free_type(ty);
+    let row: &mut Row = match name {
+        "type_prop" => &mut info.props,
+        _ => &mut info.ops,
+    };
+    let sig = sig.into_term_ptr().into_addr();
+    match row.entries.iter_mut().find(|e| e.name == key) {
+        Some(entry) => {
+            let old = std::mem::replace(&mut entry.sig, sig);
+            exec.erase(exec.heap.pull(unsafe { TermPtr::forge(old) }));
+        }
+        None => row.entries.push(Entry { name: key, sig }),
+    }
+    Ok(Term::Type(exec.heap.alloc_type(info)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(source: &str) -> Result<String, crate::Error> {
+        crate::vm::run_with(source, &TypeExtensions)
+    }
+
+    /// Run `source`, which must fail in a primitive, and return the message.
+    fn prim_error(source: &str) -> String {
+        match run(source) {
+            Err(crate::Error::Primitive(error)) => error.message,
+            other => panic!("expected a primitive error, got {other:?}"),
+        }
+    }
+
+    const HAS_LEN: &str = r#"&HasLen = %type_prop "len" (typeof 0) %type_any; "#;
+
+    #[test]
+    fn records_print_their_rows() {
+        assert_eq!(run("%type_any").unwrap(), "type * {..}");
+        // Signatures are lazy, like fields.
+        assert_eq!(
+            run(&format!("{HAS_LEN}HasLen")).unwrap(),
+            "type * {.len: (typeof 0), ..}"
+        );
+        assert_eq!(
+            run(r#"%type_op "+" (typeof 0) (typeof 0)"#).unwrap(),
+            "Int {(+): (typeof 0)}"
+        );
+        // A later entry of the same name replaces the earlier one.
+        assert_eq!(
+            run(r#"%type_prop "len" (typeof 0) (%type_prop "len" (typeof true) (type ()))"#)
+                .unwrap(),
+            "type() {.len: (typeof 0)}"
+        );
+    }
+
+    #[test]
+    fn satisfaction_is_width_based() {
+        let vec = r#"&Vec = %type_prop "len" (typeof 0) (%type_prop "push" (typeof 0) (type (typeof 0))); "#;
+        for (source, sat) in [
+            (format!("{HAS_LEN}{vec}%type_sat Vec HasLen"), "true"),
+            (format!("{HAS_LEN}%type_sat (type (typeof 0)) HasLen"), "false"),
+            (format!("{HAS_LEN}{vec}%type_sat HasLen Vec"), "false"),
+            (format!("{HAS_LEN}%type_sat (typeof 0) %type_any"), "true"),
+            ("%type_sat %type_any (typeof 0)".to_string(), "false"),
+            (
+                r#"%type_sat (%type_prop "len" (typeof true) %type_any) (%type_prop "len" (typeof 0) %type_any)"#.to_string(),
+                "false",
+            ),
+        ] {
+            assert_eq!(run(&source).unwrap(), sat, "{source}");
+        }
+        // Projection uses the same relation.
+        assert_eq!(
+            run(&format!("{HAS_LEN}{vec}Vec::New 3 :> HasLen")).unwrap(),
+            "<type>{3}"
+        );
+    }
+
+    #[test]
+    fn properties_have_no_implementation() {
+        assert_eq!(
+            prim_error(r#"%prop "len" ((%type_prop "len" (typeof 0) (type (typeof 0)))::New 3)"#),
+            "%prop: `.len` has a signature but no implementation"
+        );
+        assert_eq!(
+            prim_error("%prop 1 2"),
+            "%prop expects its name to be a String"
+        );
+    }
+
+    #[test]
+    fn meet_and_join() {
+        let size = r#"&HasSize = %type_prop "size" (typeof 0) (type (typeof 0)); "#;
+        assert_eq!(
+            run(&format!("{HAS_LEN}{size}%type_meet HasLen HasSize")).unwrap(),
+            "type((typeof 0)) {.len: (typeof 0), .size: (typeof 0)}"
+        );
+        assert_eq!(
+            run(&format!("{HAS_LEN}{size}%type_join HasLen HasSize")).unwrap(),
+            "type * {..}"
+        );
+        assert_eq!(
+            run(r"%type_join (type { A, B(typeof 0) }) (type { B(typeof 0), C })").unwrap(),
+            "type{A, B(Int), C}"
+        );
+        assert_eq!(
+            run(r"%type_meet (type { A, B(typeof 0) }) (type { B(typeof 0), C })").unwrap(),
+            "type{B(Int)}"
+        );
+        assert_eq!(
+            prim_error(r"%type_meet (typeof 0) (typeof true)"),
+            "%type_meet: the types have no consistent meet"
+        );
+        assert_eq!(
+            prim_error(
+                r#"%type_meet (%type_prop "len" (typeof 0) %type_any) (%type_prop "len" (typeof true) %type_any)"#
+            ),
+            "%type_meet: the types have no consistent meet"
+        );
+    }
+
+    #[test]
+    fn products_and_sorts() {
+        let int = "(typeof 0)";
+        for (source, sat) in [
+            // Covariant in the codomain, contravariant in the input.
+            (
+                format!("%type_sat (pi (x: {int}) -> {int}) (pi (x: {int}) -> %type_any)"),
+                "true",
+            ),
+            (
+                format!("%type_sat (pi (x: {int}) -> %type_any) (pi (x: {int}) -> {int})"),
+                "false",
+            ),
+            (
+                format!("%type_sat (pi (x: %type_any) -> {int}) (pi (x: {int}) -> {int})"),
+                "true",
+            ),
+            (
+                format!("%type_sat (pi (x: {int}) -> {int}) (pi (x: %type_any) -> {int})"),
+                "false",
+            ),
+            // The grades must agree.
+            (
+                format!("%type_sat (pi {{x: {int}}} -> {int}) (pi (x: {int}) -> {int})"),
+                "false",
+            ),
+            // Dependent codomains compare at a shared argument.
+            (
+                "%type_sat (pi (&A: Type_0) (x: A) -> A) (pi (&B: Type_0) (y: B) -> B)".into(),
+                "true",
+            ),
+            ("%type_sat Prop Type_1".into(), "true"),
+            ("%type_sat Type_1 Type_0".into(), "false"),
+        ] {
+            assert_eq!(run(&source).unwrap(), sat, "{source}");
+        }
+        assert_eq!(run("%type_meet Type_0 Prop").unwrap(), "Prop");
+        assert_eq!(run("%type_join Type_0 Type_2").unwrap(), "Type_2");
+    }
+}
@@ -1,4 +1,7 @@
 pub mod core;
+pub mod error;
 pub mod extension;
 pub mod util;
 pub mod vm;
+
+pub use error::Error;
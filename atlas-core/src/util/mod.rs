@@ -3,9 +3,11 @@ mod mutex;
 mod single_mutex;
 pub mod slab;
 mod u56;
+mod work_stack;
 
 pub use memo_map::MemoMap;
 pub use mutex::{AsyncMutex, AsyncMutexGuard, LockKey, OwnedAsyncMutexGuard, RecursiveLock};
 pub use single_mutex::{SingleMutex, SingleMutexGuard};
 pub use slab::{ShardedSlab, SlabScope};
 pub use u56::U56;
+pub use work_stack::{Deferred, WorkStack};
@@ -176,6 +176,10 @@ pub struct UniqueSlot<'sh, K, V> {
     _brand: Brand<'sh>,
 }
 
+// SAFETY: a `UniqueSlot` is the sole access to its value (like a `&mut V`), so
+// it may move to another thread whenever the value itself may.
+unsafe impl<'sh, K: Send, V: Send> Send for UniqueSlot<'sh, K, V> {}
+
 impl<'sh, K, V> Deref for UniqueSlot<'sh, K, V> {
     type Target = V;
 
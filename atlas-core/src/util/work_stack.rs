@@ -0,0 +1,343 @@
+//! Stack-safe driving of recursive futures.
+//!
+//! A recursive `async` computation boxes each recursive call, so its *state*
+//! lives on the heap, but polling still nests: polling a future polls the one it
+//! awaits, which polls the one it awaits, and so on down the whole chain. Deep
+//! enough chains overflow the native stack however small each frame is.
+//!
+//! A [`WorkStack`] bounds that nesting. Recursive calls are wrapped in
+//! [`Deferred`]; one polled more than [`MAX_NESTING`] levels below the
+//! innermost running [`WorkStack`] moves its future onto that work stack
+//! instead of polling it in place, and waits for the result. The work stack
+//! polls its frames from the top level, innermost first, so the native stack
+//! holds at most `MAX_NESTING` levels at a time and the rest of the chain is
+//! bounded only by the heap. Futures run in the same order as they would
+//! nested; only the stack they are polled from changes.
+
+use std::cell::{Cell, RefCell};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+/// How many [`Deferred`] levels are polled in place before the next one moves
+/// onto the work stack.
+pub const MAX_NESTING: usize = 16;
+
+/// A future moved onto a work stack, its output already routed to the
+/// [`Deferred`] that moved it. Stored as `'static`; see [`Deferred::poll`].
+type Frame = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+
+thread_local! {
+    /// The frames of the [`WorkStack`] being polled on this thread, if any.
+    static FRAMES: Cell<*const RefCell<Vec<Frame>>> = const { Cell::new(std::ptr::null()) };
+    /// How many [`Deferred`] levels deep the current poll is below it.
+    static NESTING: Cell<usize> = const { Cell::new(0) };
+    /// Set once a future has moved onto the work stack, until the work stack
+    /// polls it: every [`Deferred`] polled meanwhile (a sibling branch of a
+    /// `join!` above it, say) waits, so futures still run in nested order.
+    static YIELDING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// A recursive step of a computation driven by a [`WorkStack`]. Outside of one
+/// it simply polls the wrapped future.
+pub struct Deferred<F: Future> {
+    state: State<F>,
+}
+
+enum State<F: Future> {
+    /// Not polled yet, so still free to move.
+    Fresh(F),
+    InPlace(F),
+    Moved(Arc<Mutex<Slot<F::Output>>>),
+    Done,
+}
+
+/// Where a moved future's output lands, and who to wake when it does.
+struct Slot<T> {
+    value: Option<T>,
+    waker: Option<Waker>,
+}
+
+impl<F: Future> Deferred<F> {
+    pub fn new(future: F) -> Self {
+        Deferred {
+            state: State::Fresh(future),
+        }
+    }
+}
+
+impl<F: Future + Send> Future for Deferred<F>
+where
+    F::Output: Send,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        // SAFETY: the future is only moved out of `Fresh`, before it has been
+        // polled, and stays pinned in `InPlace` from its first poll on.
+        let this = unsafe { self.get_unchecked_mut() };
+        if YIELDING.get() {
+            return Poll::Pending;
+        }
+        let nesting = NESTING.get();
+        if let State::Fresh(_) = this.state {
+            let frames = FRAMES.get();
+            let State::Fresh(future) = std::mem::replace(&mut this.state, State::Done) else {
+                unreachable!()
+            };
+            if nesting < MAX_NESTING || frames.is_null() {
+                this.state = State::InPlace(future);
+            } else {
+                let slot = Arc::new(Mutex::new(Slot {
+                    value: None,
+                    waker: None,
+                }));
+                let out = slot.clone();
+                let frame: Pin<Box<dyn Future<Output = ()> + Send + '_>> = Box::pin(async move {
+                    let value = future.await;
+                    let mut out = out.lock().unwrap();
+                    out.value = Some(value);
+                    if let Some(waker) = out.waker.take() {
+                        waker.wake();
+                    }
+                });
+                // SAFETY: the frame may borrow from its environment, and is
+                // owned by the work stack from here on. That work stack is
+                // polling this very future, so it owns it (directly or through
+                // another frame) and is itself bounded by the same borrows: the
+                // frame is dropped before they end.
+                let frame = unsafe {
+                    std::mem::transmute::<Pin<Box<dyn Future<Output = ()> + Send + '_>>, Frame>(
+                        frame,
+                    )
+                };
+                // SAFETY: `FRAMES` is only set while its work stack is being
+                // polled, which it is (this poll runs inside it), and that work
+                // stack borrows none of its frames meanwhile.
+                unsafe { (*frames).borrow_mut().push(frame) };
+                YIELDING.set(true);
+                this.state = State::Moved(slot);
+            }
+        }
+        match &mut this.state {
+            State::InPlace(future) => {
+                NESTING.set(nesting + 1);
+                // SAFETY: see above.
+                let poll = unsafe { Pin::new_unchecked(future) }.poll(cx);
+                NESTING.set(nesting);
+                if poll.is_ready() {
+                    this.state = State::Done;
+                }
+                poll
+            }
+            // The work stack polls the moved frame first, and this future again
+            // once it has finished; the waker is left with the frame all the
+            // same, so a pending result is never one nobody will wake.
+            State::Moved(slot) => {
+                let value = {
+                    let mut slot = slot.lock().unwrap();
+                    let Some(value) = slot.value.take() else {
+                        slot.waker = Some(cx.waker().clone());
+                        return Poll::Pending;
+                    };
+                    value
+                };
+                this.state = State::Done;
+                Poll::Ready(value)
+            }
+            State::Fresh(_) | State::Done => panic!("`Deferred` polled after completion"),
+        }
+    }
+}
+
+/// Drives a recursive computation with its native-stack nesting bounded (see
+/// the [module docs](self)). Inside another running work stack it defers to
+/// that one and polls `root` in place.
+pub struct WorkStack<'s, T> {
+    root: Pin<Box<dyn Future<Output = T> + Send + 's>>,
+    frames: RefCell<Vec<Frame>>,
+}
+
+impl<'s, T> WorkStack<'s, T> {
+    pub fn drive(root: Pin<Box<dyn Future<Output = T> + Send + 's>>) -> Self {
+        WorkStack {
+            root,
+            frames: RefCell::new(Vec::new()),
+        }
+    }
+}
+
+/// Whether polling a frame moved it along: it finished, or moved work onto the
+/// stack above it.
+#[derive(PartialEq, Eq)]
+enum Step {
+    Progress,
+    Blocked,
+}
+
+// The frames are only ever reached through a shared `&RefCell`: the futures
+// polled below push onto them through `FRAMES`, so no `&mut` to them (and no
+// `&mut WorkStack` method) may be live while those futures run.
+
+/// Poll the frame at `index`, keeping its place on the stack unless it
+/// finished.
+fn poll_frame(frames: &RefCell<Vec<Frame>>, index: usize, cx: &mut Context<'_>) -> Step {
+    let mut frame = frames.borrow_mut().remove(index);
+    let below = frames.borrow().len();
+    NESTING.set(0);
+    YIELDING.set(false);
+    if frame.as_mut().poll(cx).is_ready() {
+        return Step::Progress;
+    }
+    let mut frames = frames.borrow_mut();
+    let pushed = frames.len() > below;
+    frames.insert(index, frame);
+    if pushed {
+        Step::Progress
+    } else {
+        Step::Blocked
+    }
+}
+
+/// Poll the root future, returning its output once it has finished.
+fn poll_root<T>(
+    root: Pin<&mut (dyn Future<Output = T> + Send + '_)>,
+    frames: &RefCell<Vec<Frame>>,
+    cx: &mut Context<'_>,
+) -> Result<T, Step> {
+    let below = frames.borrow().len();
+    NESTING.set(0);
+    YIELDING.set(false);
+    match root.poll(cx) {
+        Poll::Ready(value) => Ok(value),
+        Poll::Pending if frames.borrow().len() > below => Err(Step::Progress),
+        Poll::Pending => Err(Step::Blocked),
+    }
+}
+
+/// Installs a work stack as this thread's innermost one for the duration of a
+/// poll, restoring the previous one on exit (including by unwinding).
+struct Enter {
+    frames: *const RefCell<Vec<Frame>>,
+    nesting: usize,
+    yielding: bool,
+}
+
+impl Enter {
+    fn new(frames: &RefCell<Vec<Frame>>) -> Self {
+        Enter {
+            frames: FRAMES.replace(frames),
+            nesting: NESTING.get(),
+            yielding: YIELDING.get(),
+        }
+    }
+}
+
+impl Drop for Enter {
+    fn drop(&mut self) {
+        FRAMES.set(self.frames);
+        NESTING.set(self.nesting);
+        YIELDING.set(self.yielding);
+    }
+}
+
+impl<T> Future for WorkStack<'_, T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let this = self.get_mut();
+        if !FRAMES.get().is_null() {
+            let nesting = NESTING.get();
+            NESTING.set(nesting + 1);
+            let poll = this.root.as_mut().poll(cx);
+            NESTING.set(nesting);
+            return poll;
+        }
+        let (root, frames) = (&mut this.root, &this.frames);
+        let _enter = Enter::new(frames);
+        loop {
+            // The innermost frame is the one the rest are waiting on.
+            let len = frames.borrow().len();
+            if len == 0 {
+                match poll_root(root.as_mut(), frames, cx) {
+                    Ok(value) => return Poll::Ready(value),
+                    Err(Step::Progress) => continue,
+                    Err(Step::Blocked) => return Poll::Pending,
+                }
+            }
+            if poll_frame(frames, len - 1, cx) == Step::Progress {
+                continue;
+            }
+            // It is blocked on something else, e.g. a lock held by a frame
+            // below (interleaved branches of a `join!`): give every other
+            // frame a turn before yielding.
+            if (0..len - 1)
+                .rev()
+                .any(|i| poll_frame(frames, i, cx) == Step::Progress)
+            {
+                continue;
+            }
+            // Every frame is blocked, having left `cx`'s waker with whatever
+            // will unblock it; the root may still move on.
+            match poll_root(root.as_mut(), frames, cx) {
+                Ok(value) => return Poll::Ready(value),
+                Err(Step::Progress) => continue,
+                Err(Step::Blocked) => return Poll::Pending,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deep enough to overflow a small native stack, shallow enough for Miri.
+    const DEEP: u64 = if cfg!(miri) { 200 } else { 1_000_000 };
+
+    fn depth(n: u64) -> Pin<Box<dyn Future<Output = u64> + Send>> {
+        Box::pin(Deferred::new(async move {
+            if n == 0 { 0 } else { depth(n - 1).await + 1 }
+        }))
+    }
+
+    fn block_on<T>(future: impl Future<Output = T>) -> T {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn deep_recursion_runs_on_a_small_stack() {
+        std::thread::Builder::new()
+            .stack_size(256 * 1024)
+            .spawn(|| assert_eq!(block_on(WorkStack::drive(depth(DEEP))), DEEP))
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+
+    #[test]
+    fn nested_work_stacks_share_the_outer_one() {
+        let n = DEEP.min(10_000);
+        let inner = async move { WorkStack::drive(depth(n)).await + 1 };
+        assert_eq!(block_on(WorkStack::drive(Box::pin(inner))), n + 1);
+    }
+
+    #[test]
+    fn interleaved_branches_wait_on_each_other() {
+        // One branch waits for a value the other only sends from deep down.
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let send = Deferred::new(async move {
+            depth(DEEP.min(1_000)).await;
+            tx.send(7).unwrap();
+        });
+        let recv = Deferred::new(async move { rx.await.unwrap() });
+        let (_, got) = block_on(WorkStack::drive(Box::pin(async {
+            tokio::join!(send, recv)
+        })));
+        assert_eq!(got, 7);
+    }
+}
@@ -1,27 +1,51 @@
 //! The [`Executor`]: interaction-calculus evaluation over a branded [`HeapScope`].
 //!
-//! v1 is a synchronous, single-task evaluator over the affine heap model. It
-//! covers the affine core (APP-LAM / APP-USE / APP-ERA, binary ops, constructors
-//! as data, and full normalization). The duplication / superposition / match
-//! interactions and the parallel async driver are deferred to a later increment.
-
+//! By default an executor is a single-task evaluator: independent redexes (the
+//! two operands of a binary op, say) interleave on the calling task. A
+//! [parallel](Executor::parallel) executor instead forks independent subterms
+//! onto the worker threads of a multi-threaded tokio runtime while normalizing,
+//! and the operands of a binary op while reducing to weak head normal form.
+//! The sharded heap arenas, the two-party dup-cell locks and the heap's claim
+//! protocol for back-references keep the shared graph consistent.
+
+use crate::error::PrimError;
 use crate::extension::{Extensions, Handle, NoExtensions, TermPtrLike};
+use crate::util::{Deferred, WorkStack};
 use crate::vm::heap::{
-    Addr, Boxed, DupDrop, DupPtr, HeapScope, MatchData, MatchPtr, Spine, SupPtr, TermPtr, TypeInfo,
-    TypePtr, ValuePtr, Variant,
+    Addr, ArenaKind, Boxed, DupDrop, DupPtr, HeapScope, Layout, MatchData, MatchPtr, Reclaimed,
+    Row, Spine, SupPtr, TermPtr, TypeInfo, TypePtr, ValuePtr, VarPtr,
 };
-use crate::vm::term::{BinaryOp, LabelId, PrimId, Term, UnaryOp, VariantId};
+use crate::vm::term::{BinaryOp, LabelId, PrimId, Sort, Term, UnaryOp, VariantId};
+use crate::vm::trace::{Fault, Frame, Head, Trace};
 use ordered_float::OrderedFloat;
+use std::collections::VecDeque;
 use std::future::Future;
+use std::marker::PhantomData;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::sync::Mutex;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use tokio::runtime::RuntimeFlavor;
+use tokio::task::JoinHandle;
 
 /// A boxed reduction future. Boxed so the (mutually) recursive async reduction
-/// methods can call one another; the parallel driver will later add a `Send`
-/// bound here.
-type Reduce<'s, T> = Pin<Box<dyn Future<Output = T> + 's>>;
+/// methods can call one another, and [deferred](Deferred) so that, driven by a
+/// [`WorkStack`], deep recursion is bounded by the heap rather than the native
+/// stack; `Send` so the parallel driver can hand one to another worker thread.
+type Reduce<'s, T> = Pin<Box<dyn Future<Output = T> + Send + 's>>;
+
+/// The result of a projection, or its value and expected type handed back
+/// when it is stuck (see [`Executor::project`]).
+type Projected<'h> = Result<Term<'h>, (TermPtr<'h>, TermPtr<'h>)>;
+
+/// A pending step of [`Executor::erase`].
+enum Erase<'h> {
+    Term(Term<'h>),
+    Ptr(TermPtr<'h>),
+    /// Release a lambda's binder once its body is erased.
+    Binder(VarPtr<'h>),
+    Type(TypePtr<'h>),
+}
 
 enum DupForce<'h> {
     Term(Term<'h>),
@@ -33,16 +57,34 @@ enum DupForce<'h> {
 #[rustfmt::skip]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum InteractionType {
-    AppLam, AppUse, AppEra, AppErr, AppSup, AppMat, AppPri, AppCtr,
+    AppLam, AppUse, AppEra, AppErr, AppSup, AppMat, AppPri, AppCtr, MatSup,
     TypeDef, Variant,
-    DupLam, DupSup, DupCtr, DupType, DupApp, DupBop, DupUop, DupMat, DupNum, DupWld, DupVar, DupUse, DupPri, DupVal, DupErase, DupCollapse,
+    DupLam, DupSup, DupCtr, DupType, DupApp, DupBop, DupUop, DupPrj, DupMat, DupNum, DupWld, DupVar, DupUse, DupPri, DupVal, DupErase, DupCollapse,
     BopVal, BopSup,
     UopVal, UopSup,
+    PrjVal, PrjSup,
+}
+
+impl InteractionType {
+    /// Every kind of interaction, in declaration order.
+    #[rustfmt::skip]
+    pub const ALL: [InteractionType; 34] = {
+        use InteractionType::*;
+        [
+            AppLam, AppUse, AppEra, AppErr, AppSup, AppMat, AppPri, AppCtr, MatSup,
+            TypeDef, Variant,
+            DupLam, DupSup, DupCtr, DupType, DupApp, DupBop, DupUop, DupPrj, DupMat, DupNum, DupWld, DupVar, DupUse, DupPri, DupVal, DupErase, DupCollapse,
+            BopVal, BopSup,
+            UopVal, UopSup,
+            PrjVal, PrjSup,
+        ]
+    };
 }
 
 /// Controls how an [`Executor`] accounts for reduction steps and decides when to
-/// stop. Taken through `&self` (atomics) so it can be shared.
-pub trait ExecPolicy {
+/// stop. Taken through `&self` (atomics) so it can be shared, including across
+/// the worker threads of a [parallel](Executor::parallel) executor.
+pub trait ExecPolicy: Send + Sync {
     fn next_step(&self, interaction: InteractionType);
     fn should_continue(&self) -> bool;
 }
@@ -88,8 +130,61 @@ impl ExecPolicy for FiniteBudget {
     }
 }
 
+/// A policy wrapper that counts the interactions of each kind, leaving when to
+/// stop to the policy it wraps.
+pub struct Counted<P> {
+    pub inner: P,
+    counts: [AtomicU64; InteractionType::ALL.len()],
+}
+
+impl<P> Counted<P> {
+    pub fn new(inner: P) -> Self {
+        Counted {
+            inner,
+            counts: std::array::from_fn(|_| AtomicU64::new(0)),
+        }
+    }
+
+    /// The interactions of kind `interaction` performed so far.
+    pub fn count(&self, interaction: InteractionType) -> u64 {
+        self.counts[interaction as usize].load(Ordering::Relaxed)
+    }
+
+    /// The interactions performed so far, of any kind.
+    pub fn total(&self) -> u64 {
+        self.counts.iter().map(|c| c.load(Ordering::Relaxed)).sum()
+    }
+
+    /// The kinds of interaction performed at least once, with their counts.
+    pub fn counts(&self) -> impl Iterator<Item = (InteractionType, u64)> + '_ {
+        InteractionType::ALL
+            .into_iter()
+            .map(|interaction| (interaction, self.count(interaction)))
+            .filter(|&(_, count)| count > 0)
+    }
+}
+
+impl<P: ExecPolicy> ExecPolicy for Counted<P> {
+    #[inline]
+    fn next_step(&self, interaction: InteractionType) {
+        self.counts[interaction as usize].fetch_add(1, Ordering::Relaxed);
+        self.inner.next_step(interaction);
+    }
+    #[inline]
+    fn should_continue(&self) -> bool {
+        self.inner.should_continue()
+    }
+}
+
 const NO_EXTENSIONS: &NoExtensions = &NoExtensions;
 
+/// How many nodes of a subterm are scanned to decide whether it may be forked;
+/// a larger one is normalized on the calling task.
+const ISOLATION_SCAN_LIMIT: usize = 1 << 12;
+
+/// The fewest nodes a subterm needs to be worth a task of its own.
+const FORK_MIN_NODES: usize = 16;
+
 /// Drives reduction over a branded [`HeapScope`]. The scope borrow's lifetime is
 /// tied to the brand (`&'h HeapScope<'h>`), so [`Handle`]s minted for extensions
 /// carry a single lifetime; the extension set is borrowed separately for `'e`.
@@ -97,7 +192,20 @@ pub struct Executor<'e, 'h, P: ExecPolicy, X: Extensions = NoExtensions> {
     pub heap: &'h HeapScope<'h>,
     pub extensions: &'e X,
     pub policy: P,
-    extension_error: Mutex<Option<String>>,
+    extension_error: Mutex<Option<PrimError>>,
+    /// Fork capacity when running [parallel](Executor::parallel).
+    forks: Option<Forks>,
+    /// The next rigid witness tag (see [`Executor::witness`]).
+    witnesses: AtomicU64,
+}
+
+/// Fork accounting for a parallel executor: how many redexes have been forked
+/// onto tasks of their own and not yet joined, how many may be at once, and
+/// how many have been forked in all.
+struct Forks {
+    live: AtomicUsize,
+    max: usize,
+    total: AtomicUsize,
 }
 
 impl<'e, 'h, P: ExecPolicy> Executor<'e, 'h, P, NoExtensions> {
@@ -107,6 +215,8 @@ impl<'e, 'h, P: ExecPolicy> Executor<'e, 'h, P, NoExtensions> {
             extensions: NO_EXTENSIONS,
             policy,
             extension_error: Mutex::new(None),
+            forks: None,
+            witnesses: AtomicU64::new(0),
         }
     }
 }
@@ -118,19 +228,65 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
             extensions,
             policy,
             extension_error: Mutex::new(None),
+            forks: None,
+            witnesses: AtomicU64::new(0),
         }
     }
 
+    /// While normalizing, reduce independent subterms — the fields of a
+    /// construction, the two sides of a superposition — as separate tasks,
+    /// with at most `max_forks` of them in flight. Only a subterm sharing no
+    /// dup, superposition or free variable with the rest of the graph is
+    /// forked: where two subterms share a dup, which of them forces it first
+    /// decides how the duplicand is split, and not every split reaches the
+    /// same normal form. A subterm of fewer than a few nodes isn't worth a
+    /// task and isn't forked, and neither are the operands of a binary op,
+    /// whose checks would cost more than most of them take to reduce. Forking
+    /// only happens on a tokio runtime with more than one worker; on any other
+    /// the executor behaves exactly as a sequential one.
+    pub fn parallel(mut self, max_forks: usize) -> Self {
+        self.forks = Some(Forks {
+            live: AtomicUsize::new(0),
+            max: max_forks,
+            total: AtomicUsize::new(0),
+        });
+        self
+    }
+
+    /// How many reductions this executor has forked onto tasks of their own
+    /// so far (always 0 unless it is [parallel](Executor::parallel)).
+    pub fn forked(&self) -> usize {
+        self.forks
+            .as_ref()
+            .map_or(0, |f| f.total.load(Ordering::Acquire))
+    }
+
     /// Return and clear the first error raised by an extension primitive.
-    pub fn take_extension_error(&self) -> Option<String> {
+    pub fn take_extension_error(&self) -> Option<PrimError> {
         self.extension_error.lock().unwrap().take()
     }
 
     // ====================================================================
-    // Erase: recursively reclaim a term and everything reachable from it.
+    // Erase: reclaim a term and everything reachable from it.
     // ====================================================================
 
+    /// Reclaim `term` and everything it owns. Depth-first, children in order,
+    /// on an explicit stack so deep terms cannot overflow the native one.
     pub fn erase(&self, term: Term<'h>) {
+        let mut work = vec![Erase::Term(term)];
+        while let Some(item) = work.pop() {
+            match item {
+                Erase::Term(term) => self.erase_step(term, &mut work),
+                Erase::Ptr(ptr) => self.erase_step(self.heap.pull(ptr), &mut work),
+                Erase::Binder(var) => self.heap.drop_binder(var),
+                Erase::Type(ty) => self.erase_type(ty, &mut work),
+            }
+        }
+    }
+
+    /// Reclaim `term`'s own storage, pushing what it owns onto `work` (last
+    /// to be erased first).
+    fn erase_step(&self, term: Term<'h>, work: &mut Vec<Erase<'h>>) {
         match term {
             Term::App { func, arg }
             | Term::And {
@@ -140,41 +296,45 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
             | Term::Or {
                 lhs: func,
                 rhs: arg,
-            } => {
-                self.erase(self.heap.pull(func));
-                self.erase(self.heap.pull(arg));
-            }
-            Term::Bop { lhs, rhs, .. } => {
-                self.erase(self.heap.pull(lhs));
-                self.erase(self.heap.pull(rhs));
-            }
-            Term::Uop { val, .. } => {
-                self.erase(self.heap.pull(val));
-            }
-            Term::Lam { body, .. } => {
-                // The binder slot is owned by the body's variable occurrence, so
-                // erasing the body reclaims it exactly once.
-                self.erase(self.heap.pull(self.heap.into_body(body)));
-            }
-            Term::Use { body } => {
-                self.erase(self.heap.pull(body));
+            } => work.extend([Erase::Ptr(arg), Erase::Ptr(func)]),
+            Term::Bop { lhs, rhs, .. } => work.extend([Erase::Ptr(rhs), Erase::Ptr(lhs)]),
+            Term::Uop { val, .. } => work.push(Erase::Ptr(val)),
+            // An unforced projection is never checked.
+            Term::Prj { val, ty } => work.extend([Erase::Ptr(ty), Erase::Ptr(val)]),
+            Term::Lam { var, body } => {
+                // Erasing the body erases the variable occurrence with it, unless
+                // the occurrence escaped the body; the binder is released last.
+                work.push(Erase::Binder(var));
+                work.push(Erase::Ptr(self.heap.into_body(body)));
             }
+            Term::Use { body } => work.push(Erase::Ptr(body)),
             Term::Ctn { ty, values, .. } => {
-                for f in self.heap.into_fields(values) {
-                    self.erase(self.heap.pull(f));
-                }
-                self.erase_type(ty);
+                work.push(Erase::Type(ty));
+                work.extend(
+                    self.heap
+                        .into_fields(values)
+                        .into_iter()
+                        .rev()
+                        .map(Erase::Ptr),
+                );
             }
             Term::Partial { func, args, .. } => {
-                self.erase(self.heap.pull(func));
-                for f in self.heap.into_fields(args) {
-                    self.erase(self.heap.pull(f));
-                }
+                work.extend(
+                    self.heap
+                        .into_fields(args)
+                        .into_iter()
+                        .rev()
+                        .map(Erase::Ptr),
+                );
+                work.push(Erase::Ptr(func));
             }
             Term::Box(v) => self.heap.value_drop(v),
-            Term::Ctr { ty, .. } => self.erase(self.heap.pull(ty)),
+            Term::Err {
+                backtrace: Some(t), ..
+            } => self.heap.trace_drop(t),
+            Term::Ctr { ty, .. } => work.push(Erase::Ptr(ty)),
             // A type value owns its (lazy) sub-type children.
-            Term::Type(t) => self.erase_type(t),
+            Term::Type(t) => work.push(Erase::Type(t)),
             // Leaves and (v1-)inert heads.
             Term::Var { cell } => self.heap.drop_var(cell),
             Term::Wld
@@ -189,15 +349,14 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
             // A dup projection: dropping one side rewrites the surviving
             // projection's parent directly; dropping both reclaims the duplicand.
             Term::Dup { ptr, .. } => match self.heap.dup_drop_side(ptr) {
-                DupDrop::Recorded { dead } => self.erase_all(dead),
-                DupDrop::Reclaim(p) => self.erase(self.heap.pull(p)),
+                DupDrop::Recorded { dead } => work.extend(dead.into_iter().rev().map(Erase::Ptr)),
+                DupDrop::Reclaim(p) => work.push(Erase::Ptr(p)),
             },
             // A superposition owns its cell (`SupPtr` is affine): reclaim both
             // branches.
             Term::Sup { ptr, .. } => {
                 let (a, b) = self.heap.free_sup(ptr);
-                self.erase(self.heap.pull(a));
-                self.erase(self.heap.pull(b));
+                work.extend([Erase::Ptr(b), Erase::Ptr(a)]);
             }
             // A match table: same reclaim pattern as `fire_mat` — copy the
             // case/default addresses out, free the table, erase every key,
@@ -208,32 +367,25 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
                     (data.cases.clone(), data.default)
                 };
                 self.heap.free_match(matches);
-                for (key, branch) in cases {
-                    self.erase(self.heap.pull(unsafe { TermPtr::forge(key) }));
-                    self.erase(self.heap.pull(unsafe { TermPtr::forge(branch) }));
-                }
-                if let Some(d) = default {
-                    self.erase(self.heap.pull(unsafe { TermPtr::forge(d) }));
-                }
+                let owned = cases
+                    .into_iter()
+                    .flat_map(|(key, branch)| [key, branch])
+                    .chain(default);
+                let owned: Vec<_> = owned
+                    .map(|a| Erase::Ptr(unsafe { TermPtr::forge(a) }))
+                    .collect();
+                work.extend(owned.into_iter().rev());
             }
         }
     }
 
-    /// Reclaim a type value, erasing its owned (lazy) sub-type child nodes.
-    fn erase_type(&self, ty: TypePtr<'h>) {
-        let erase_children = |this: &Self, addrs: Vec<Addr>| {
-            for a in addrs {
-                this.erase(this.heap.pull(unsafe { TermPtr::forge(a) }));
-            }
-        };
-        match self.heap.free_type(ty) {
-            TypeInfo::Product { fields, .. } => erase_children(self, fields),
-            TypeInfo::Sum { variants, .. } => {
-                for v in variants {
-                    erase_children(self, v.args);
-                }
-            }
-        }
+    /// Reclaim a type value, pushing its owned (lazy) sub-type child nodes.
+    fn erase_type(&self, ty: TypePtr<'h>, work: &mut Vec<Erase<'h>>) {
+        work.extend(
+            (self.heap.free_type(ty).children().into_iter())
+                .rev()
+                .map(|a| Erase::Ptr(unsafe { TermPtr::forge(a) })),
+        );
     }
 
     /// Erase a batch of owned pointers (dead sup components handed back by the
@@ -264,6 +416,151 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
         }
     }
 
+    /// Collect the heap's garbage: erase every term `roots` do not reach,
+    /// leaked cycles included, along with the dropped handles, then free the
+    /// cells no remaining node holds. The erasure is the ordinary one, so a
+    /// live duplication or lambda sharing a cell with the garbage sees its
+    /// other half dropped, exactly as if the garbage had been erased when it
+    /// was lost.
+    ///
+    /// # Safety
+    ///
+    /// As for [`HeapScope::find_leaked_roots`]: `roots` must include every
+    /// [`TermPtr`] into the heap held outside it, no other pointer into the
+    /// heap (a dup, variable or type cell, say) may be held outside a node,
+    /// and reduction must be idle.
+    pub unsafe fn collect_garbage(&self, roots: &[&TermPtr<'h>]) -> Reclaimed {
+        let kinds = ArenaKind::ALL
+            .into_iter()
+            .filter(|&k| k != ArenaKind::Names);
+        let before: Vec<_> = kinds.clone().map(|k| self.heap.arena_len(k)).collect();
+        self.erase_all(unsafe { self.heap.take_garbage(roots) });
+        loop {
+            let batch = self.heap.take_dropped();
+            if batch.is_empty() {
+                break;
+            }
+            self.erase_all(batch);
+        }
+        unsafe { self.heap.sweep_cells() };
+        Reclaimed(
+            (kinds.zip(before))
+                .map(|(k, n)| (k, n.saturating_sub(self.heap.arena_len(k))))
+                .filter(|&(_, n)| n > 0)
+                .collect(),
+        )
+    }
+
+    // ====================================================================
+    // Fork / join
+    // ====================================================================
+
+    /// Spawn `job` as a task of its own if this executor is parallel, has fork
+    /// capacity left, and runs on a multi-threaded runtime; otherwise hand it
+    /// back to be driven on the calling task.
+    fn fork<'s, T: Send + 's>(
+        &'s self,
+        job: Reduce<'s, T>,
+    ) -> Result<ScopedTask<'s, T>, Reduce<'s, T>> {
+        let Some(forks) = &self.forks else {
+            return Err(job);
+        };
+        let Ok(live) = forks
+            .live
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                (n < forks.max).then_some(n + 1)
+            })
+        else {
+            return Err(job);
+        };
+        forks.total.fetch_add(1, Ordering::AcqRel);
+        if live == 0 {
+            // The first fork: from here until the last one is joined, the heap
+            // is shared between threads.
+            self.heap.begin_parallel_section();
+        }
+        let result = Arc::new(Mutex::new(None));
+        let slot = result.clone();
+        let task = WorkStack::drive(Box::pin(async move {
+            let value = job.await;
+            *slot.lock().unwrap() = Some(value);
+        }));
+        // SAFETY: the task borrows `'s`, which `tokio::spawn` cannot express.
+        // `ScopedTask` never lets that borrow end while the task may still run:
+        // it is either joined to completion or, when dropped early, aborts the
+        // task and waits until tokio has dropped it.
+        let task =
+            unsafe { std::mem::transmute::<WorkStack<'s, ()>, WorkStack<'static, ()>>(task) };
+        Ok(ScopedTask {
+            task: Some(tokio::spawn(task)),
+            result,
+            _scope: PhantomData,
+        })
+    }
+
+    /// Wait for a forked reduction and release its fork. Once the last fork is
+    /// joined the caller runs alone again and closes the heap's parallel section.
+    async fn join_fork<'s, T>(&'s self, task: ScopedTask<'s, T>) -> T {
+        let value = task.join().await;
+        let forks = self
+            .forks
+            .as_ref()
+            .expect("joined a fork on a sequential executor");
+        if forks.live.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.heap.end_parallel_section();
+        }
+        value
+    }
+
+    /// Whether `ptr` may be forked: fork capacity is left, other workers
+    /// could take it, and it is [isolated](HeapScope::isolated_size) and big
+    /// enough. The cheap checks go first, so a sequential run, or one with
+    /// every fork taken, scans nothing.
+    fn forkable(&self, ptr: &TermPtr<'h>) -> bool {
+        let capacity = self
+            .forks
+            .as_ref()
+            .is_some_and(|f| f.live.load(Ordering::Acquire) < f.max);
+        capacity
+            && tokio::runtime::Handle::try_current().is_ok_and(|rt| {
+                rt.runtime_flavor() == RuntimeFlavor::MultiThread && rt.metrics().num_workers() > 1
+            })
+            && self
+                .heap
+                .isolated_size(ptr, ISOLATION_SCAN_LIMIT)
+                .is_some_and(|n| n >= FORK_MIN_NODES)
+    }
+
+    /// Normalize a batch of subterms, returning the results in order. Every
+    /// one but the last is forked if it is [forkable](Self::forkable); the
+    /// rest run one after another on this task.
+    async fn normalize_all(&self, ptrs: Vec<TermPtr<'h>>) -> Vec<TermPtr<'h>> {
+        let n = ptrs.len();
+        let pending: Vec<_> = ptrs
+            .into_iter()
+            .enumerate()
+            .map(|(i, ptr)| {
+                let forkable = i + 1 < n && self.forkable(&ptr);
+                let job = self.sub_normalize_at(ptr);
+                if forkable { self.fork(job) } else { Err(job) }
+            })
+            .collect();
+        let mut results = Vec::with_capacity(n);
+        for job in pending {
+            results.push(match job {
+                Ok(task) => self.join_fork(task).await,
+                Err(job) => job.await,
+            });
+        }
+        results
+    }
+
+    /// [`normalize_all`](Self::normalize_all) for two subterms.
+    async fn normalize_pair(&self, a: TermPtr<'h>, b: TermPtr<'h>) -> (TermPtr<'h>, TermPtr<'h>) {
+        let mut results = self.normalize_all(vec![a, b]).await.into_iter();
+        (results.next().unwrap(), results.next().unwrap())
+    }
+
     // ====================================================================
     // WHNF
     // ====================================================================
@@ -271,21 +568,46 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
     /// Reduce `x` (a [`TermPtr`] or [`Handle`]) to weak head normal form,
     /// returning the same kind of pointer naming the result node.
     pub async fn whnf_at<T: TermPtrLike<'h>>(&self, x: T) -> T {
-        let r = self.whnf_at_ptr(x.into_ptr()).await;
-        T::from_ptr(r, self.heap)
+        let ptr = x.into_ptr();
+        let r = WorkStack::drive(Box::pin(async {
+            let r = self.whnf_at_ptr(ptr).await;
+            self.settle().await;
+            r
+        }));
+        T::from_ptr(r.await, self.heap)
     }
 
     /// Reduce `x` (a [`TermPtr`] or [`Handle`]) to full normal form, returning the
     /// same kind of pointer naming the result node.
     pub async fn normalize_at<T: TermPtrLike<'h>>(&self, x: T) -> T {
-        let r = self.normalize_at_ptr(x.into_ptr()).await;
-        T::from_ptr(r, self.heap)
+        let ptr = x.into_ptr();
+        let r = WorkStack::drive(Box::pin(async {
+            let r = self.normalize_at_ptr(ptr).await;
+            self.settle().await;
+            r
+        }));
+        T::from_ptr(r.await, self.heap)
+    }
+
+    /// Erase the superposition branches a label cleanup killed while the
+    /// superposition was lifted out; they are queued as dropped until the
+    /// reduction finishes.
+    async fn settle(&self) {
+        if self
+            .forks
+            .as_ref()
+            .is_some_and(|f| f.live.load(Ordering::Acquire) != 0)
+        {
+            // Called from inside a forked task.
+            return;
+        }
+        self.erase_dropped_handles().await;
     }
 
     /// The boxed form of [`whnf_at_ptr`](Self::whnf_at_ptr), for use at recursive
     /// call sites (an `async fn` cannot directly recurse into itself).
     pub fn sub_whnf_at(&self, ptr: TermPtr<'h>) -> Reduce<'_, TermPtr<'h>> {
-        Box::pin(self.whnf_at_ptr(ptr))
+        Box::pin(Deferred::new(self.whnf_at_ptr(ptr)))
     }
 
     /// Reduce the node at `ptr` to weak head normal form in place, returning a
@@ -300,7 +622,7 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
                 // Budget spent: write the head back and fold the spine.
                 let (mut s, mut t) = (slot, term);
                 loop {
-                    match spine.unwind(s, t) {
+                    match spine.unwind(self.heap, s, t) {
                         Ok((ps, pt)) => {
                             s = ps;
                             t = pt;
@@ -328,7 +650,10 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
                             unreachable!()
                         };
                         let arg_term = self.heap.pull(arg);
-                        let body_ptr = self.heap.substitute(var, body, arg_term);
+                        let (body_ptr, unused) = self.heap.substitute(var, body, arg_term);
+                        if let Some(arg) = unused {
+                            self.erase(arg);
+                        }
                         self.heap.remove_slot(app_slot);
                         self.heap.remove_slot(slot);
                         self.policy.next_step(InteractionType::AppLam);
@@ -385,10 +710,11 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
                         self.erase(self.heap.pull(arg));
                         self.heap.remove_slot(app_slot);
                         self.policy.next_step(InteractionType::AppErr);
-                        term = Term::Err {
+                        let err = Term::Err {
                             immediate,
                             backtrace,
-                        }; // reuse `slot`
+                        };
+                        term = self.propagate(err, Frame::Applied); // reuse `slot`
                         continue;
                     }
                     term = Term::Err {
@@ -397,10 +723,17 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
                     };
                 }
                 Term::Bop { op, lhs, rhs } => {
-                    // Reduce both operands concurrently.
-                    let (nl, nr) = tokio::join!(self.sub_whnf_at(lhs), self.sub_whnf_at(rhs));
+                    // Reduce both operands concurrently. They are not forked
+                    // (see `parallel`), so they interleave on this task, the
+                    // left one polled first.
+                    let (nl, nr) =
+                        tokio::join!(biased; self.sub_whnf_at(lhs), self.sub_whnf_at(rhs));
                     let (nl, nr) = if self.policy.should_continue() {
-                        match self.combine_bop(op, nl, nr) {
+                        let combined = match op {
+                            BinaryOp::Conv => self.combine_conv(nl, nr, slot.addr()).await,
+                            _ => self.combine_bop(op, nl, nr, slot.addr()),
+                        };
+                        match combined {
                             Ok(t) => {
                                 term = t; // reuse `slot`
                                 continue;
@@ -420,7 +753,13 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
                 Term::Uop { op, val } => {
                     let nv = self.sub_whnf_at(val).await;
                     if self.policy.should_continue() {
-                        match self.combine_uop(op, nv) {
+                        // The type of a type value is its sort, which may force
+                        // parts of the type.
+                        let combined = match (op, &*self.heap.view(&nv)) {
+                            (UnaryOp::TypeOf, Term::Type(_)) => self.sort_uop(nv).await,
+                            _ => self.combine_uop(op, nv, slot.addr()),
+                        };
+                        match combined {
                             Ok(t) => {
                                 term = t; // reuse `slot`
                                 continue;
@@ -431,12 +770,29 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
                         term = Term::Uop { op, val: nv };
                     }
                 }
+                Term::Prj { val, ty } => {
+                    // Only the value is demanded here; the expected type is
+                    // forced by the check itself, once the value has a head.
+                    let nv = self.sub_whnf_at(val).await;
+                    if self.policy.should_continue() {
+                        match self.project(nv, ty, slot.addr()).await {
+                            Ok(t) => {
+                                term = t; // reuse `slot`
+                                continue;
+                            }
+                            Err((val, ty)) => term = Term::Prj { val, ty },
+                        }
+                    } else {
+                        term = Term::Prj { val: nv, ty };
+                    }
+                }
                 Term::Dup { label, ptr: dp } => {
-                    let cur = unsafe { slot.unchanged() };
+                    // Write the projection back first: the other side rewrites
+                    // it in place if it fires the dup.
+                    let cur = self.heap.finish_slot(slot, Term::Dup { label, ptr: dp });
                     match self.force_dup(label, dp).await {
                         DupForce::Term(t) => {
-                            let (s, _) = self.heap.term(cur);
-                            slot = s;
+                            slot = self.heap.slot(cur);
                             term = t;
                             continue;
                         }
@@ -449,9 +805,13 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
                         // Stuck: a dup over an unsubstituted binder. Leave the
                         // `Dup` as an inert head and unwind.
                         DupForce::Stuck => {
-                            let (s, _) = self.heap.term(cur);
+                            let (s, t) = self.heap.term(cur);
                             slot = s;
-                            term = Term::Dup { label, ptr: dp };
+                            term = t;
+                            // The other side may have fired it meanwhile.
+                            if !matches!(term, Term::Dup { ptr, .. } if ptr == dp) {
+                                continue;
+                            }
                         }
                     }
                 }
@@ -475,12 +835,31 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
                             unreachable!()
                         };
                         let na = self.sub_whnf_at(arg).await;
+                        // MAT-ERR: an `Err` scrutinee erases the match and bubbles
+                        // up, as it does through an application.
+                        if matches!(&*self.heap.view(&na), Term::Err { .. }) {
+                            self.heap.remove_slot(app_slot);
+                            self.erase(Term::Mat { matches });
+                            self.policy.next_step(InteractionType::AppMat);
+                            term = self.propagate(self.heap.pull(na), Frame::Matched);
+                            continue;
+                        }
+                        // MAT-SUP: a superposed scrutinee is matched side by side.
+                        if matches!(&*self.heap.view(&na), Term::Sup { .. }) {
+                            self.heap.remove_slot(app_slot);
+                            let Term::Sup { label, ptr: sup } = self.heap.pull(na) else {
+                                unreachable!()
+                            };
+                            self.policy.next_step(InteractionType::MatSup);
+                            term = self.mat_sup(label, sup, matches); // reuse `slot`
+                            continue;
+                        }
                         // A concrete scrutinee fires the match (consuming `na`); an
                         // as-yet-inert head leaves the match stuck.
                         if is_matchable(&self.heap.view(&na)) {
                             self.heap.remove_slot(app_slot);
                             let scrut = self.heap.pull(na);
-                            term = self.fire_mat(matches, scrut).await;
+                            term = self.fire_mat(matches, scrut, slot.addr()).await;
                             continue;
                         } else {
                             // `func` is the null placeholder from `pop`; thread it
@@ -496,7 +875,7 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
                     let arity = self.extensions.arity(id);
                     if arity == 0 {
                         // a nullary primitive is a constant: fire immediately.
-                        let result = self.fire_prim(id, vec![]).await;
+                        let result = self.fire_prim(id, vec![], slot.addr()).await;
                         self.heap.remove_slot(slot);
                         let (s, t) = self.heap.term(result);
                         slot = s;
@@ -521,9 +900,14 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
                     // one must first turn it into a constructor with `::New` (product)
                     // or `::Variant` (sum). Applying a type to an argument is an error.
                     if matches!(spine.peek(), Some(Term::App { .. })) {
+                        let ty = Head::Type(self.heap.type_name(t.addr()));
                         self.erase(Term::Type(t));
                         self.policy.next_step(InteractionType::AppCtr);
-                        term = err_term();
+                        term = self.fail(
+                            Fault::TypeApplied { ty },
+                            InteractionType::AppCtr,
+                            slot.addr(),
+                        );
                         continue;
                     }
                     term = Term::Type(t);
@@ -543,7 +927,7 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
                         fields.push(arg);
                         if fields.len() == arity as usize {
                             self.policy.next_step(InteractionType::AppCtr);
-                            let result = self.complete_partial(func, fields).await;
+                            let result = self.complete_partial(func, fields, slot.addr()).await;
                             self.heap.remove_slot(slot); // drop the spent Partial node
                             let (s, t) = self.heap.term(result);
                             slot = s;
@@ -600,17 +984,28 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
                                     Some(_) => term = Term::Ctr { ty: nt, variant },
                                     None => {
                                         // unknown variant / constructor-type mismatch.
+                                        let fault = Fault::NoVariant {
+                                            ty: self.head(&self.heap.view(&nt)),
+                                            variant: variant
+                                                .map(|v| Arc::from(self.heap.variant_name(v))),
+                                        };
                                         self.erase(self.heap.pull(nt));
                                         self.policy.next_step(InteractionType::Variant);
-                                        term = err_term();
+                                        term =
+                                            self.fail(fault, InteractionType::Variant, slot.addr());
                                     }
                                 }
                             }
                             ArgClass::Stuck => term = Term::Ctr { ty: nt, variant },
                             ArgClass::Err => {
+                                let head = self.head(&self.heap.view(&nt));
                                 self.erase(self.heap.pull(nt));
                                 self.policy.next_step(InteractionType::Variant);
-                                term = err_term();
+                                term = self.fail(
+                                    Fault::NotAType { head },
+                                    InteractionType::Variant,
+                                    slot.addr(),
+                                );
                             }
                         }
                     }
@@ -622,7 +1017,7 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
             // The head is inert/stuck: fold it back up the spine, restoring each
             // parent's continuation from the slot just finalized.
             loop {
-                match spine.unwind(slot, term) {
+                match spine.unwind(self.heap, slot, term) {
                     Ok((cslot, cterm)) => {
                         slot = cslot;
                         term = cterm;
@@ -636,12 +1031,13 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
     /// Combine a binary op whose operands `la`/`ra` are already in WHNF. On a
     /// reduction the operand nodes are consumed and the result term returned as
     /// `Ok`; otherwise the operands are handed back as `Err` so the caller can
-    /// rebuild a stuck `Bop`.
+    /// rebuild a stuck `Bop`. `at` is the op's node, which a failure is traced to.
     fn combine_bop(
         &self,
         op: BinaryOp,
         la: TermPtr<'h>,
         ra: TermPtr<'h>,
+        at: Addr,
     ) -> Result<Term<'h>, (TermPtr<'h>, TermPtr<'h>)> {
         // BOP-SUP: a superposed operand distributes the op over both branches.
         if matches!(&*self.heap.view(&la), Term::Sup { .. }) {
@@ -650,12 +1046,24 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
         if matches!(&*self.heap.view(&ra), Term::Sup { .. }) {
             return Ok(self.bop_sup_right(op, la, ra));
         }
+        // BOP-ERR: an `Err` operand (the left one, if both are) bubbles up,
+        // erasing the other operand.
+        let err_left = match (&*self.heap.view(&la), &*self.heap.view(&ra)) {
+            (Term::Err { .. }, _) => Some(true),
+            (_, Term::Err { .. }) => Some(false),
+            _ => None,
+        };
+        if let Some(left) = err_left {
+            let (err, other) = if left { (la, ra) } else { (ra, la) };
+            self.erase(self.heap.pull(other));
+            self.policy.next_step(InteractionType::BopVal);
+            return Ok(self.propagate(self.heap.pull(err), Frame::Binary(op)));
+        }
         // BOP-VAL: both operands must be concrete primitive leaves. A type
         // mismatch or an op unsupported for the operand type reduces to `Err`;
         // if either operand is not yet a value, the op stays stuck and is rebuilt.
-        let result = match (&*self.heap.view(&la), &*self.heap.view(&ra)) {
-            // BOP-ERR: an `Err` operand bubbles up, erasing the other operand.
-            (Term::Err { .. }, _) | (_, Term::Err { .. }) => Some(err_term()),
+        let (lview, rview) = (self.heap.view(&la), self.heap.view(&ra));
+        let result = match (&*lview, &*rview) {
             (Term::Int(a), Term::Int(b)) => Some(apply_int(op, *a, *b)),
             (Term::Float(a), Term::Float(b)) => Some(apply_float(op, a.0, b.0)),
             // mixed Int/Float: promote the int and operate in float space.
@@ -666,14 +1074,27 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
             // strings / byte arrays: equality and concatenation.
             (Term::Box(a), Term::Box(b)) => Some(self.apply_box(op, a, b)),
             // both are values but of mismatched type: an invalid op.
-            (lt, rt) if is_value(lt) && is_value(rt) => Some(err_term()),
+            (lt, rt) if is_value(lt) && is_value(rt) => Some(Err(OpFailure::Unsupported)),
             // at least one operand is not a value yet: stay stuck.
             _ => None,
         };
+        let result = result.map(|r| {
+            r.unwrap_or_else(|failure| {
+                let fault = match failure {
+                    OpFailure::DivisionByZero => Fault::DivisionByZero { op },
+                    OpFailure::Unsupported => Fault::BadOperands {
+                        op,
+                        lhs: self.head(&lview),
+                        rhs: self.head(&rview),
+                    },
+                };
+                self.fail(fault, InteractionType::BopVal, at)
+            })
+        });
         match result {
             Some(t) => {
                 // Reclaim both operands (a no-op for scalar leaves; drops the
-                // payload of a boxed string/bytes operand or an `Err` child).
+                // payload of a boxed string/bytes operand).
                 self.erase(self.heap.pull(la));
                 self.erase(self.heap.pull(ra));
                 self.policy.next_step(InteractionType::BopVal);
@@ -685,10 +1106,15 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
 
     /// Apply a binary op to two boxed values (strings / byte arrays). Supports
     /// `==` / `!=` (yielding `Bool`) and `+` concatenation (yielding a fresh
-    /// boxed value). Mismatched box kinds or any other op yield `Err`.
-    fn apply_box(&self, op: BinaryOp, a: &ValuePtr<'h>, b: &ValuePtr<'h>) -> Term<'h> {
+    /// boxed value). Mismatched box kinds or any other op are unsupported.
+    fn apply_box(
+        &self,
+        op: BinaryOp,
+        a: &ValuePtr<'h>,
+        b: &ValuePtr<'h>,
+    ) -> Result<Term<'h>, OpFailure> {
         use BinaryOp::*;
-        match (op, self.heap.value_get(a), self.heap.value_get(b)) {
+        Ok(match (op, self.heap.value_get(a), self.heap.value_get(b)) {
             (Eq, Boxed::Str(x), Boxed::Str(y)) => Term::Bool(x == y),
             (Neq, Boxed::Str(x), Boxed::Str(y)) => Term::Bool(x != y),
             (Eq, Boxed::Bytes(x), Boxed::Bytes(y)) => Term::Bool(x == y),
@@ -703,15 +1129,15 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
                 v.extend_from_slice(y);
                 Term::Box(self.heap.value(Boxed::Bytes(Arc::from(v.as_slice()))))
             }
-            _ => err_term(),
-        }
+            _ => return Err(OpFailure::Unsupported),
+        })
     }
 
     /// Combine a unary op whose operand `va` is already in WHNF. On a reduction
     /// the operand node is consumed and the result term returned as `Ok`;
     /// otherwise the operand is handed back as `Err` so the caller can rebuild a
-    /// stuck `Uop`.
-    fn combine_uop(&self, op: UnaryOp, va: TermPtr<'h>) -> Result<Term<'h>, TermPtr<'h>> {
+    /// stuck `Uop`. `at` is the op's node, which a failure is traced to.
+    fn combine_uop(&self, op: UnaryOp, va: TermPtr<'h>, at: Addr) -> Result<Term<'h>, TermPtr<'h>> {
         // UOP-SUP: a superposed operand distributes the op over both branches.
         if matches!(&*self.heap.view(&va), Term::Sup { .. }) {
             return Ok(self.uop_sup(op, va));
@@ -732,11 +1158,12 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
             // Other operands map to a builtin/opaque type (or stay stuck / error).
             enum TyOf {
                 Builtin(&'static str),
+                Propagate,
                 Err,
                 Stuck,
             }
             let decision = match &*self.heap.view(&va) {
-                Term::Err { .. } => TyOf::Err,
+                Term::Err { .. } => TyOf::Propagate,
                 Term::Int(_) => TyOf::Builtin("Int"),
                 Term::Float(_) => TyOf::Builtin("Float"),
                 Term::Bool(_) => TyOf::Builtin("Bool"),
@@ -745,7 +1172,8 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
                     Boxed::Str(_) => TyOf::Builtin("String"),
                     Boxed::Bytes(_) => TyOf::Builtin("Bytes"),
                 },
-                Term::Type(_) => TyOf::Builtin("Type"),
+                // Handled by `sort_uop`, which may force parts of the type.
+                Term::Type(_) => TyOf::Stuck,
                 Term::Lam { .. }
                 | Term::Use { .. }
                 | Term::Pri(_)
@@ -759,31 +1187,46 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
             if let TyOf::Stuck = decision {
                 return Err(va);
             }
+            self.policy.next_step(InteractionType::UopVal);
             let ty = match decision {
                 TyOf::Builtin(name) => Term::Type(self.heap.builtin_type(name)),
-                TyOf::Err => err_term(),
+                TyOf::Propagate => return Ok(self.propagate(self.heap.pull(va), Frame::Unary(op))),
+                TyOf::Err => {
+                    let operand = self.head(&self.heap.view(&va));
+                    self.fail(
+                        Fault::BadOperand { op, operand },
+                        InteractionType::UopVal,
+                        at,
+                    )
+                }
                 TyOf::Stuck => unreachable!(),
             };
             self.erase(self.heap.pull(va));
-            self.policy.next_step(InteractionType::UopVal);
             return Ok(ty);
         }
+        // UOP-ERR: an `Err` operand bubbles up.
+        if matches!(&*self.heap.view(&va), Term::Err { .. }) {
+            self.policy.next_step(InteractionType::UopVal);
+            return Ok(self.propagate(self.heap.pull(va), Frame::Unary(op)));
+        }
         let result = match (op, &*self.heap.view(&va)) {
-            // UOP-ERR: an `Err` operand bubbles up.
-            (_, Term::Err { .. }) => Some(err_term()),
             (UnaryOp::Neg, Term::Int(a)) => Some(Term::Int(a.wrapping_neg())),
             (UnaryOp::Neg, Term::Float(a)) => Some(Term::Float(OrderedFloat(-a.0))),
             (UnaryOp::Not, Term::Bool(a)) => Some(Term::Bool(!a)),
             (UnaryOp::Not, Term::Int(a)) => Some(Term::Int(!a)),
             // operand is a value but the op is unsupported for its type.
-            (_, t) if is_value(t) => Some(err_term()),
+            (_, t) if is_value(t) => {
+                let operand = self.head(t);
+                let fault = Fault::BadOperand { op, operand };
+                Some(self.fail(fault, InteractionType::UopVal, at))
+            }
             // operand is not a value yet: stay stuck.
             _ => None,
         };
         match result {
             Some(t) => {
                 // Reclaim the operand (a no-op for scalar leaves; drops a boxed
-                // payload or an `Err` child).
+                // payload).
                 self.erase(self.heap.pull(va));
                 self.policy.next_step(InteractionType::UopVal);
                 Ok(t)
@@ -805,48 +1248,816 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
         self.sup_term(label, u0, u1)
     }
 
-    /// BOP-SUP (lhs superposed): `(&L{a,b} op r)` => `&L{(a op r0), (b op r1)}`.
-    fn bop_sup_left(&self, op: BinaryOp, la: TermPtr<'h>, ra: TermPtr<'h>) -> Term<'h> {
-        self.policy.next_step(InteractionType::BopSup);
-        let Term::Sup { label, ptr: sup } = self.heap.pull(la) else {
-            unreachable!("combine_bop checked lhs is a Sup")
-        };
-        let (a, b) = self.heap.free_sup(sup);
-        let rhs = self.heap.pull(ra);
-        let (d0, d1) = self.alloc_dup_c(rhs);
-        let b0 = self.heap.alloc(Term::Bop {
-            op,
-            lhs: a,
-            rhs: self.dp_node(label, d0),
-        });
-        let b1 = self.heap.alloc(Term::Bop {
-            op,
-            lhs: b,
-            rhs: self.dp_node(label, d1),
-        });
-        self.sup_term(label, b0, b1)
+    /// BOP-SUP (lhs superposed): `(&L{a,b} op r)` => `&L{(a op r0), (b op r1)}`.
+    fn bop_sup_left(&self, op: BinaryOp, la: TermPtr<'h>, ra: TermPtr<'h>) -> Term<'h> {
+        self.policy.next_step(InteractionType::BopSup);
+        let Term::Sup { label, ptr: sup } = self.heap.pull(la) else {
+            unreachable!("combine_bop checked lhs is a Sup")
+        };
+        let (a, b) = self.heap.free_sup(sup);
+        let rhs = self.heap.pull(ra);
+        let (d0, d1) = self.alloc_dup_c(rhs);
+        let b0 = self.heap.alloc(Term::Bop {
+            op,
+            lhs: a,
+            rhs: self.dp_node(label, d0),
+        });
+        let b1 = self.heap.alloc(Term::Bop {
+            op,
+            lhs: b,
+            rhs: self.dp_node(label, d1),
+        });
+        self.sup_term(label, b0, b1)
+    }
+
+    /// BOP-SUP (rhs superposed): `(l op &L{a,b})` => `&L{(l0 op a), (l1 op b)}`.
+    fn bop_sup_right(&self, op: BinaryOp, la: TermPtr<'h>, ra: TermPtr<'h>) -> Term<'h> {
+        self.policy.next_step(InteractionType::BopSup);
+        let Term::Sup { label, ptr: sup } = self.heap.pull(ra) else {
+            unreachable!("combine_bop checked rhs is a Sup")
+        };
+        let (a, b) = self.heap.free_sup(sup);
+        let lhs = self.heap.pull(la);
+        let (d0, d1) = self.alloc_dup_c(lhs);
+        let b0 = self.heap.alloc(Term::Bop {
+            op,
+            lhs: self.dp_node(label, d0),
+            rhs: a,
+        });
+        let b1 = self.heap.alloc(Term::Bop {
+            op,
+            lhs: self.dp_node(label, d1),
+            rhs: b,
+        });
+        self.sup_term(label, b0, b1)
+    }
+
+    // ====================================================================
+    // Projection
+    // ====================================================================
+
+    /// PROJECT: check the value `va` (in WHNF) against the expected type
+    /// expression `ty`. The value comes back unchanged when its descriptor
+    /// satisfies the type (PROJECT-OK) and an error replaces it when the two
+    /// are shown incompatible (PROJECT-FAIL). A value without a head yet, an
+    /// expected type that doesn't reduce to a type value, or a comparison stuck
+    /// on a sub-type leaves the projection stuck, handing the operands back as
+    /// `Err`. `at` is the projection's node, which a failure is traced to.
+    fn project(&self, va: TermPtr<'h>, ty: TermPtr<'h>, at: Addr) -> Reduce<'_, Projected<'h>> {
+        Box::pin(Deferred::new(async move {
+            // The value's descriptor: the type a construction carries, or the
+            // builtin record an atom or function determines.
+            enum Desc {
+                Atom(&'static str),
+                Data,
+                Sort,
+                Any,
+                Sup,
+                Err,
+                Stuck,
+                Other,
+            }
+            let desc = match &*self.heap.view(&va) {
+                Term::Sup { .. } => Desc::Sup,
+                Term::Err { .. } => Desc::Err,
+                Term::Ctn { .. } => Desc::Data,
+                Term::Int(_) => Desc::Atom("Int"),
+                Term::Float(_) => Desc::Atom("Float"),
+                Term::Bool(_) => Desc::Atom("Bool"),
+                Term::Char(_) => Desc::Atom("Char"),
+                Term::Box(b) => match self.heap.value_get(b) {
+                    Boxed::Str(_) => Desc::Atom("String"),
+                    Boxed::Bytes(_) => Desc::Atom("Bytes"),
+                },
+                Term::Type(_) => Desc::Sort,
+                Term::Lam { .. }
+                | Term::Use { .. }
+                | Term::Pri(_)
+                | Term::Mat { .. }
+                | Term::Ctr { .. }
+                | Term::Partial { .. } => Desc::Atom("Function"),
+                // A wildcard could be anything, so nothing can contradict it.
+                Term::Wld => Desc::Any,
+                Term::Var { .. }
+                | Term::Dup { .. }
+                | Term::App { .. }
+                | Term::Bop { .. }
+                | Term::Uop { .. }
+                | Term::Prj { .. }
+                | Term::And { .. }
+                | Term::Or { .. } => Desc::Stuck,
+                Term::VarId(_) | Term::Null => Desc::Other,
+            };
+            match desc {
+                Desc::Sup => return Ok(self.prj_sup(va, ty)),
+                // PROJECT-ERR: an `Err` value bubbles up; the type is never forced.
+                Desc::Err => {
+                    self.policy.next_step(InteractionType::PrjVal);
+                    self.erase(self.heap.pull(ty));
+                    return Ok(self.propagate(self.heap.pull(va), Frame::Projected));
+                }
+                Desc::Stuck => return Err((va, ty)),
+                _ => {}
+            }
+            let nt = self.sub_whnf_at(ty).await;
+            if !self.policy.should_continue() {
+                return Err((va, nt));
+            }
+            match self.classify_type_arg(&nt) {
+                ArgClass::Type => {}
+                ArgClass::Stuck => return Err((va, nt)),
+                ArgClass::Err => {
+                    self.policy.next_step(InteractionType::PrjVal);
+                    self.erase(self.heap.pull(va));
+                    if matches!(&*self.heap.view(&nt), Term::Err { .. }) {
+                        return Ok(self.propagate(self.heap.pull(nt), Frame::Projected));
+                    }
+                    let head = self.head(&self.heap.view(&nt));
+                    self.erase(self.heap.pull(nt));
+                    let fault = Fault::ProjectOnto { head };
+                    return Ok(self.fail(fault, InteractionType::PrjVal, at));
+                }
+            }
+            let Term::Type(expected) = self.heap.pull(nt) else {
+                unreachable!("classify_type_arg checked for a type value")
+            };
+            let value_head = self.head(&self.heap.view(&va));
+            let (sat, value, expected) = match desc {
+                Desc::Any => (Sat::Holds, self.heap.pull(va), expected),
+                Desc::Other => (Sat::Fails, self.heap.pull(va), expected),
+                Desc::Atom(name) => {
+                    let actual = self.heap.builtin_type(name);
+                    let (sat, actual, expected) = self.satisfies(actual, expected).await;
+                    self.erase(Term::Type(actual));
+                    (sat, self.heap.pull(va), expected)
+                }
+                Desc::Data => {
+                    let Term::Ctn { ty, arity, values } = self.heap.pull(va) else {
+                        unreachable!()
+                    };
+                    let (sat, ty, expected) = self.satisfies(ty, expected).await;
+                    (sat, Term::Ctn { ty, arity, values }, expected)
+                }
+                // A type value's descriptor is its sort.
+                Desc::Sort => {
+                    let Term::Type(ty) = self.heap.pull(va) else {
+                        unreachable!()
+                    };
+                    match self.sort_of(ty, Vec::new()).await {
+                        (Some(sort), ty) => {
+                            let actual = self.heap.alloc_type(TypeInfo::new(Layout::Sort(sort)));
+                            let (sat, actual, expected) = self.satisfies(actual, expected).await;
+                            self.erase(Term::Type(actual));
+                            (sat, Term::Type(ty), expected)
+                        }
+                        (None, ty) => (Sat::Stuck, Term::Type(ty), expected),
+                    }
+                }
+                Desc::Sup | Desc::Err | Desc::Stuck => unreachable!(),
+            };
+            match sat {
+                Sat::Holds => {
+                    self.policy.next_step(InteractionType::PrjVal);
+                    self.erase(Term::Type(expected));
+                    Ok(value)
+                }
+                Sat::Fails => {
+                    self.policy.next_step(InteractionType::PrjVal);
+                    let fault = Fault::Unsatisfied {
+                        value: value_head,
+                        expected: Head::Type(self.heap.type_name(expected.addr())),
+                    };
+                    self.erase(value);
+                    self.erase(Term::Type(expected));
+                    Ok(self.fail(fault, InteractionType::PrjVal, at))
+                }
+                Sat::Stuck => Err((
+                    self.heap.alloc(value),
+                    self.heap.alloc(Term::Type(expected)),
+                )),
+            }
+        }))
+    }
+
+    /// PROJECT-SUP: `&L{a,b} :> E` => `&L{a :> E0, b :> E1}`, with `E`
+    /// duplicated under the superposition's label so each alternative is
+    /// checked.
+    fn prj_sup(&self, va: TermPtr<'h>, ty: TermPtr<'h>) -> Term<'h> {
+        self.policy.next_step(InteractionType::PrjSup);
+        let Term::Sup { label, ptr: sup } = self.heap.pull(va) else {
+            unreachable!("project checked the value is a Sup")
+        };
+        let (a, b) = self.heap.free_sup(sup);
+        let (d0, d1) = self.alloc_dup_c(self.heap.pull(ty));
+        let p0 = self.heap.alloc(Term::Prj {
+            val: a,
+            ty: self.dp_node(label, d0),
+        });
+        let p1 = self.heap.alloc(Term::Prj {
+            val: b,
+            ty: self.dp_node(label, d1),
+        });
+        self.sup_term(label, p0, p1)
+    }
+
+    /// Whether the type `actual` satisfies `expected` (RECORD): the layouts
+    /// satisfy, and each capability row of `actual` has every entry the row of
+    /// `expected` names, with a satisfying signature. Both types are consumed
+    /// and handed back with the sub-types the check forced.
+    ///
+    /// Types are affine trees, so there is no back-edge for an active-pair
+    /// hypothesis to catch: a type a `fix` unfolds is compared as deep as the
+    /// budget allows.
+    pub(crate) fn satisfies(
+        &self,
+        actual: TypePtr<'h>,
+        expected: TypePtr<'h>,
+    ) -> Reduce<'_, (Sat, TypePtr<'h>, TypePtr<'h>)> {
+        Box::pin(Deferred::new(async move {
+            let (mut a, mut e) = (self.heap.free_type(actual), self.heap.free_type(expected));
+            let (mut sat, la, le) = self.satisfies_layout(a.layout, e.layout).await;
+            (a.layout, e.layout) = (la, le);
+            if sat != Sat::Fails {
+                let (s, pa, pe) = self.satisfies_row(a.props, e.props).await;
+                (a.props, e.props) = (pa, pe);
+                sat = sat.and(s);
+            }
+            if sat != Sat::Fails {
+                let (s, oa, oe) = self.satisfies_row(a.ops, e.ops).await;
+                (a.ops, e.ops) = (oa, oe);
+                sat = sat.and(s);
+            }
+            (sat, self.heap.alloc_type(a), self.heap.alloc_type(e))
+        }))
+    }
+
+    /// Layout satisfaction. Every layout satisfies `*`, and an atom only the
+    /// same atom. A product satisfies a product with no more fields when the
+    /// shared fields do, and a sum satisfies a sum that declares each of its
+    /// variants with satisfied arguments. A sort satisfies the sorts it is
+    /// within, and a dependent product one of the same grade whose input
+    /// satisfies its own (contravariantly) and whose codomain, at a shared
+    /// rigid argument, its codomain satisfies (PI-SAT).
+    fn satisfies_layout(
+        &self,
+        actual: Layout,
+        expected: Layout,
+    ) -> Reduce<'_, (Sat, Layout, Layout)> {
+        Box::pin(Deferred::new(async move {
+            match (actual, expected) {
+                (actual, Layout::Any) => (Sat::Holds, actual, Layout::Any),
+                (Layout::Atom(a), Layout::Atom(e)) => {
+                    let sat = if a == e { Sat::Holds } else { Sat::Fails };
+                    (sat, Layout::Atom(a), Layout::Atom(e))
+                }
+                (Layout::Product(fa), Layout::Product(fe)) => {
+                    let (sat, fa, fe) = if fa.len() < fe.len() || fa.is_empty() != fe.is_empty() {
+                        (Sat::Fails, fa, fe)
+                    } else {
+                        self.satisfies_fields(fa, fe).await
+                    };
+                    (sat, Layout::Product(fa), Layout::Product(fe))
+                }
+                (Layout::Sum(mut va), Layout::Sum(mut ve)) => {
+                    let mut sat = Sat::Holds;
+                    for v in va.iter_mut() {
+                        let Some(w) = ve.iter_mut().find(|w| w.name == v.name) else {
+                            sat = Sat::Fails;
+                            break;
+                        };
+                        if v.args.len() != w.args.len() {
+                            sat = Sat::Fails;
+                            break;
+                        }
+                        let (s, a, e) = self
+                            .satisfies_fields(
+                                std::mem::take(&mut v.args),
+                                std::mem::take(&mut w.args),
+                            )
+                            .await;
+                        (v.args, w.args) = (a, e);
+                        sat = sat.and(s);
+                        if sat == Sat::Fails {
+                            break;
+                        }
+                    }
+                    (sat, Layout::Sum(va), Layout::Sum(ve))
+                }
+                (Layout::Sort(a), Layout::Sort(e)) => {
+                    let sat = if a.within(e) { Sat::Holds } else { Sat::Fails };
+                    (sat, Layout::Sort(a), Layout::Sort(e))
+                }
+                (
+                    Layout::Pi {
+                        erased,
+                        input: ia,
+                        output: mut oa,
+                    },
+                    Layout::Pi {
+                        erased: ee,
+                        input: ie,
+                        output: mut oe,
+                    },
+                ) if erased == ee => {
+                    let (mut sat, ie, ia) = self.satisfies_fields(vec![ie], vec![ia]).await;
+                    if sat != Sat::Fails {
+                        let tag = self.witness_tag();
+                        let (ka, pa) = self.apply_shared(oa, &tag);
+                        let (ke, pe) = self.apply_shared(oe, &tag);
+                        (oa, oe) = (ka, ke);
+                        let (s, pa, pe) = (self
+                            .satisfies_fields(vec![pa.into_addr()], vec![pe.into_addr()]))
+                        .await;
+                        self.erase_addrs(pa);
+                        self.erase_addrs(pe);
+                        sat = sat.and(s);
+                    }
+                    let pi = |input, output| Layout::Pi {
+                        erased,
+                        input,
+                        output,
+                    };
+                    (sat, pi(ia[0], oa), pi(ie[0], oe))
+                }
+                (actual, expected) => (Sat::Fails, actual, expected),
+            }
+        }))
+    }
+
+    /// Width satisfaction of a capability row: every `expected` key is present
+    /// in `actual`, whose signature satisfies the expected one. Extra `actual`
+    /// entries are allowed, and the row tails are not compared.
+    fn satisfies_row(&self, mut actual: Row, mut expected: Row) -> Reduce<'_, (Sat, Row, Row)> {
+        Box::pin(Deferred::new(async move {
+            let mut at = Vec::with_capacity(expected.entries.len());
+            for e in &expected.entries {
+                match actual.entries.iter().position(|a| a.name == e.name) {
+                    Some(i) => at.push(i),
+                    None => return (Sat::Fails, actual, expected),
+                }
+            }
+            let sa = at.iter().map(|&i| actual.entries[i].sig).collect();
+            let se = expected.entries.iter().map(|e| e.sig).collect();
+            let (sat, sa, se) = self.satisfies_fields(sa, se).await;
+            for (&i, sig) in at.iter().zip(sa) {
+                actual.entries[i].sig = sig;
+            }
+            for (e, sig) in expected.entries.iter_mut().zip(se) {
+                e.sig = sig;
+            }
+            (sat, actual, expected)
+        }))
+    }
+
+    /// Check each lazy sub-type of `expected` against the one at the same
+    /// position of `actual`, forcing both to type values. Stops at the first
+    /// failing pair, leaving the rest unforced; an `actual` field past the end
+    /// of `expected` is never looked at. Returns the (relocated) field nodes.
+    fn satisfies_fields(
+        &self,
+        mut actual: Vec<Addr>,
+        mut expected: Vec<Addr>,
+    ) -> Reduce<'_, (Sat, Vec<Addr>, Vec<Addr>)> {
+        Box::pin(Deferred::new(async move {
+            let mut sat = Sat::Holds;
+            for i in 0..expected.len() {
+                let a = self.sub_whnf_at(unsafe { TermPtr::forge(actual[i]) }).await;
+                let e = self
+                    .sub_whnf_at(unsafe { TermPtr::forge(expected[i]) })
+                    .await;
+                if !self.policy.should_continue() {
+                    (actual[i], expected[i]) = (a.into_addr(), e.into_addr());
+                    sat = sat.and(Sat::Stuck);
+                    break;
+                }
+                let (s, a, e) = match (self.classify_type_arg(&a), self.classify_type_arg(&e)) {
+                    (ArgClass::Type, ArgClass::Type) => {
+                        let (Term::Type(ta), Term::Type(te)) =
+                            (self.heap.pull(a), self.heap.pull(e))
+                        else {
+                            unreachable!()
+                        };
+                        let (s, ta, te) = self.satisfies(ta, te).await;
+                        (
+                            s,
+                            self.heap.alloc(Term::Type(ta)),
+                            self.heap.alloc(Term::Type(te)),
+                        )
+                    }
+                    (ArgClass::Err, _) | (_, ArgClass::Err) => (Sat::Fails, a, e),
+                    _ => (Sat::Stuck, a, e),
+                };
+                (actual[i], expected[i]) = (a.into_addr(), e.into_addr());
+                sat = sat.and(s);
+                if sat == Sat::Fails {
+                    break;
+                }
+            }
+            (sat, actual, expected)
+        }))
+    }
+
+    // ====================================================================
+    // Meet / join
+    // ====================================================================
+
+    /// The meet `a ∧ b` or join `a ∨ b` of two type values. Meet unions
+    /// product fields and capability keys and intersects sum variants; join
+    /// keeps the common fields and keys, unions variants, and widens layouts
+    /// that disagree to `*`. Entries both sides declare are forced and combined
+    /// recursively; the rest move into the result unforced. Both types are
+    /// consumed, and erased on a [`Clash`].
+    pub(crate) fn combine_types(
+        &self,
+        a: TypePtr<'h>,
+        b: TypePtr<'h>,
+        op: Lattice,
+    ) -> Reduce<'_, Result<TypePtr<'h>, Clash>> {
+        Box::pin(Deferred::new(async move {
+            let (a, b) = (self.heap.free_type(a), self.heap.free_type(b));
+            let (mut info, partners, dropped) = match plan_combine(a, b, op) {
+                Ok(plan) => plan,
+                Err(all) => {
+                    self.erase_addrs(all);
+                    return Err(Clash::Inconsistent);
+                }
+            };
+            self.erase_addrs(dropped);
+            let mut failed = None;
+            for (i, (slot, partner)) in info.children_mut().into_iter().zip(partners).enumerate() {
+                let Some(b) = partner else { continue };
+                if failed.is_some() {
+                    self.erase_addrs(vec![b]);
+                    continue;
+                }
+                match self.combine_entry(*slot, b, op).await {
+                    Ok(c) => *slot = c,
+                    Err(clash) => failed = Some((i, clash)),
+                }
+            }
+            if let Some((i, clash)) = failed {
+                // The failed entry consumed both of its sides.
+                let mut rest = info.children();
+                rest.remove(i);
+                self.erase_addrs(rest);
+                return Err(clash);
+            }
+            Ok(self.heap.alloc_type(info))
+        }))
+    }
+
+    /// Force the two sides of a shared entry to type values and combine them,
+    /// returning the combined entry's node.
+    fn combine_entry(&self, a: Addr, b: Addr, op: Lattice) -> Reduce<'_, Result<Addr, Clash>> {
+        Box::pin(Deferred::new(async move {
+            let a = self.sub_whnf_at(unsafe { TermPtr::forge(a) }).await;
+            let b = self.sub_whnf_at(unsafe { TermPtr::forge(b) }).await;
+            let class = match self.policy.should_continue() {
+                true => (self.classify_type_arg(&a), self.classify_type_arg(&b)),
+                false => (ArgClass::Stuck, ArgClass::Stuck),
+            };
+            match class {
+                (ArgClass::Type, ArgClass::Type) => {
+                    let (Term::Type(ta), Term::Type(tb)) = (self.heap.pull(a), self.heap.pull(b))
+                    else {
+                        unreachable!()
+                    };
+                    let t = self.combine_types(ta, tb, op).await?;
+                    Ok(self.heap.alloc(Term::Type(t)).into_addr())
+                }
+                class => {
+                    self.erase_all(vec![a, b]);
+                    Err(match class {
+                        (ArgClass::Err, _) | (_, ArgClass::Err) => Clash::Inconsistent,
+                        _ => Clash::Stuck,
+                    })
+                }
+            }
+        }))
+    }
+
+    /// Erase a batch of owned child nodes by address.
+    fn erase_addrs(&self, addrs: Vec<Addr>) {
+        self.erase_all(
+            addrs
+                .into_iter()
+                .map(|a| unsafe { TermPtr::forge(a) })
+                .collect(),
+        );
+    }
+
+    // ====================================================================
+    // Universes / conversion
+    // ====================================================================
+
+    /// A fresh tag for [`Self::witness`].
+    fn witness_tag(&self) -> Arc<str> {
+        let n = self.witnesses.fetch_add(1, Ordering::Relaxed);
+        Arc::from(format!("?{n}"))
+    }
+
+    /// A rigid witness: an opaque atomic type value that stands for the
+    /// argument of a function or product while its result is inspected. Two
+    /// witnesses with the same tag are the same argument.
+    fn witness(&self, tag: &Arc<str>) -> TermPtr<'h> {
+        let ty = self
+            .heap
+            .alloc_type(TypeInfo::new(Layout::Atom(tag.clone())));
+        self.heap.alloc(Term::Type(ty))
+    }
+
+    /// Apply the function node `func` to the witness `tag` without consuming
+    /// it: returns the node to keep in its place and the application.
+    fn apply_shared(&self, func: Addr, tag: &Arc<str>) -> (Addr, TermPtr<'h>) {
+        let (probe, keep) = self.heap.dup_use(unsafe { TermPtr::forge(func) });
+        let arg = self.witness(tag);
+        let app = self.heap.alloc(Term::App { func: probe, arg });
+        (keep.into_addr(), app)
+    }
+
+    /// TYPEOF on a type value: its sort, as a type value. Stays stuck, handing
+    /// the operand back, while part of a product is.
+    fn sort_uop(&self, va: TermPtr<'h>) -> Reduce<'_, Result<Term<'h>, TermPtr<'h>>> {
+        Box::pin(Deferred::new(async move {
+            let Term::Type(ty) = self.heap.pull(va) else {
+                unreachable!("sort_uop is only called on a type value")
+            };
+            match self.sort_of(ty, Vec::new()).await {
+                (Some(sort), ty) => {
+                    self.policy.next_step(InteractionType::UopVal);
+                    self.erase(Term::Type(ty));
+                    let sort = self.heap.alloc_type(TypeInfo::new(Layout::Sort(sort)));
+                    Ok(Term::Type(sort))
+                }
+                (None, ty) => Err(self.heap.alloc(Term::Type(ty))),
+            }
+        }))
+    }
+
+    /// The sort the type value `ty` inhabits: `Prop : Type_0` and
+    /// `Type_i : Type_(i+1)`. A dependent product is in `Prop` when its
+    /// codomain is (PI-PROP), and otherwise in the larger universe of its
+    /// input's and its codomain's (PI-TYPE); the codomain is taken at a
+    /// witness whose sort `hyps` records. Records and atoms are in `Type_0`
+    /// without forcing their lazy fields, so a recursive record has a sort.
+    /// `None` when part of a product is stuck. The type is handed back.
+    pub(crate) fn sort_of(
+        &self,
+        ty: TypePtr<'h>,
+        hyps: Vec<(Arc<str>, Sort)>,
+    ) -> Reduce<'_, (Option<Sort>, TypePtr<'h>)> {
+        Box::pin(Deferred::new(async move {
+            let mut info = self.heap.free_type(ty);
+            let sort = match &mut info.layout {
+                Layout::Sort(sort) => Some(sort.succ()),
+                Layout::Atom(tag) => {
+                    Some((hyps.iter().find(|(t, _)| t == tag)).map_or(Sort::Type(0), |&(_, s)| s))
+                }
+                Layout::Any | Layout::Product(_) | Layout::Sum(_) => Some(Sort::Type(0)),
+                Layout::Pi { input, output, .. } => {
+                    let (domain, node) = self.sort_at(*input, hyps.clone()).await;
+                    *input = node;
+                    match domain {
+                        Some(domain) => {
+                            // The argument is a type of the input sort when the
+                            // input is a sort.
+                            let arg = match &*self.heap.view_at(node) {
+                                Term::Type(t) => match self.heap.type_info(t).layout {
+                                    Layout::Sort(sort) => sort,
+                                    _ => Sort::Type(0),
+                                },
+                                _ => Sort::Type(0),
+                            };
+                            let tag = self.witness_tag();
+                            let (keep, app) = self.apply_shared(*output, &tag);
+                            *output = keep;
+                            let mut hyps = hyps;
+                            hyps.push((tag, arg));
+                            let (codomain, node) = self.sort_at(app.into_addr(), hyps).await;
+                            self.erase_addrs(vec![node]);
+                            codomain.map(|codomain| match codomain {
+                                Sort::Prop => Sort::Prop,
+                                Sort::Type(j) => Sort::Type(domain.level().max(j)),
+                            })
+                        }
+                        None => None,
+                    }
+                }
+            };
+            (sort, self.heap.alloc_type(info))
+        }))
+    }
+
+    /// [`Self::sort_of`] for the lazy type node at `at`, forcing it first.
+    /// Returns the relocated node.
+    fn sort_at(&self, at: Addr, hyps: Vec<(Arc<str>, Sort)>) -> Reduce<'_, (Option<Sort>, Addr)> {
+        Box::pin(Deferred::new(async move {
+            let node = self.sub_whnf_at(unsafe { TermPtr::forge(at) }).await;
+            if !self.policy.should_continue()
+                || !matches!(self.classify_type_arg(&node), ArgClass::Type)
+            {
+                return (None, node.into_addr());
+            }
+            let Term::Type(ty) = self.heap.pull(node) else {
+                unreachable!()
+            };
+            let (sort, ty) = self.sort_of(ty, hyps).await;
+            (sort, self.heap.alloc(Term::Type(ty)).into_addr())
+        }))
+    }
+
+    /// Combine a conversion `la === ra` whose operands are in WHNF. A
+    /// superposed or `Err` operand distributes or bubbles up as for any
+    /// operator. Otherwise the operands are compared through shared copies, so
+    /// a comparison that gets stuck hands both back intact.
+    fn combine_conv(
+        &self,
+        la: TermPtr<'h>,
+        ra: TermPtr<'h>,
+        at: Addr,
+    ) -> Reduce<'_, Result<Term<'h>, (TermPtr<'h>, TermPtr<'h>)>> {
+        Box::pin(Deferred::new(async move {
+            let heads = [&la, &ra].map(|p| {
+                let head = self.heap.view(p);
+                (
+                    matches!(&*head, Term::Sup { .. } | Term::Err { .. }),
+                    undecided(&head),
+                )
+            });
+            if heads.iter().any(|&(split, _)| split) {
+                return self.combine_bop(BinaryOp::Conv, la, ra, at);
+            }
+            if heads.iter().any(|&(_, undecided)| undecided) {
+                return Err((la, ra));
+            }
+            let (pa, ka) = self.heap.dup_use(la);
+            let (pb, kb) = self.heap.dup_use(ra);
+            match self.convertible(pa, pb).await {
+                Sat::Stuck => Err((ka, kb)),
+                sat => {
+                    self.policy.next_step(InteractionType::BopVal);
+                    self.erase_all(vec![ka, kb]);
+                    Ok(Term::Bool(sat == Sat::Holds))
+                }
+            }
+        }))
+    }
+
+    /// CONV: whether `a` and `b` are definitionally equal, reducing each only
+    /// as far as the comparison demands. Leaves compare by value,
+    /// constructions and type values by their parts, and lambdas by applying
+    /// both to one rigid witness (there is no eta). Consumes both.
+    pub(crate) fn convertible(&self, a: TermPtr<'h>, b: TermPtr<'h>) -> Reduce<'_, Sat> {
+        Box::pin(Deferred::new(async move {
+            let a = self.sub_whnf_at(a).await;
+            let b = self.sub_whnf_at(b).await;
+            if !self.policy.should_continue() {
+                self.erase_all(vec![a, b]);
+                return Sat::Stuck;
+            }
+            enum Pair {
+                Leaf(bool),
+                Types,
+                Data,
+                Functions,
+                Neutral,
+                Stuck,
+            }
+            let pair = match (&*self.heap.view(&a), &*self.heap.view(&b)) {
+                (Term::Int(x), Term::Int(y)) => Pair::Leaf(x == y),
+                (Term::Float(x), Term::Float(y)) => Pair::Leaf(x == y),
+                (Term::Bool(x), Term::Bool(y)) => Pair::Leaf(x == y),
+                (Term::Char(x), Term::Char(y)) => Pair::Leaf(x == y),
+                (Term::VarId(x), Term::VarId(y)) => Pair::Leaf(x == y),
+                (Term::Pri(x), Term::Pri(y)) => Pair::Leaf(x == y),
+                (Term::Box(x), Term::Box(y)) => {
+                    Pair::Leaf(match (self.heap.value_get(x), self.heap.value_get(y)) {
+                        (Boxed::Str(x), Boxed::Str(y)) => x == y,
+                        (Boxed::Bytes(x), Boxed::Bytes(y)) => x == y,
+                        _ => false,
+                    })
+                }
+                (Term::Wld, Term::Wld) => Pair::Leaf(true),
+                (Term::Type(_), Term::Type(_)) => Pair::Types,
+                (Term::Ctn { .. }, Term::Ctn { .. }) => Pair::Data,
+                (Term::Lam { .. } | Term::Use { .. }, Term::Lam { .. } | Term::Use { .. }) => {
+                    Pair::Functions
+                }
+                // Operations stuck on a witness compare by congruence.
+                (Term::Bop { op: x, .. }, Term::Bop { op: y, .. }) if x == y => Pair::Neutral,
+                (Term::Uop { op: x, .. }, Term::Uop { op: y, .. }) if x == y => Pair::Neutral,
+                // A superposition is one of two values, not a value to compare.
+                (x, y)
+                    if undecided(x)
+                        || undecided(y)
+                        || matches!(x, Term::Sup { .. })
+                        || matches!(y, Term::Sup { .. }) =>
+                {
+                    Pair::Stuck
+                }
+                _ => Pair::Leaf(false),
+            };
+            match pair {
+                Pair::Leaf(same) => {
+                    self.erase_all(vec![a, b]);
+                    if same { Sat::Holds } else { Sat::Fails }
+                }
+                Pair::Stuck => {
+                    self.erase_all(vec![a, b]);
+                    Sat::Stuck
+                }
+                Pair::Types => {
+                    let (Term::Type(x), Term::Type(y)) = (self.heap.pull(a), self.heap.pull(b))
+                    else {
+                        unreachable!()
+                    };
+                    self.conv_types(x, y).await
+                }
+                Pair::Data => {
+                    let (
+                        Term::Ctn {
+                            ty: ta, values: va, ..
+                        },
+                        Term::Ctn {
+                            ty: tb, values: vb, ..
+                        },
+                    ) = (self.heap.pull(a), self.heap.pull(b))
+                    else {
+                        unreachable!()
+                    };
+                    let same_variant = self.heap.pack_name(&va) == self.heap.pack_name(&vb);
+                    let (fa, fb) = (self.heap.into_fields(va), self.heap.into_fields(vb));
+                    let (ta, tb) = (
+                        self.heap.alloc(Term::Type(ta)),
+                        self.heap.alloc(Term::Type(tb)),
+                    );
+                    if !same_variant || fa.len() != fb.len() {
+                        self.erase_all([vec![ta, tb], fa, fb].into_iter().flatten().collect());
+                        return Sat::Fails;
+                    }
+                    let pairs = std::iter::once((ta, tb)).chain(fa.into_iter().zip(fb));
+                    self.convertible_all(pairs.collect()).await
+                }
+                Pair::Neutral => {
+                    let pairs = match (self.heap.pull(a), self.heap.pull(b)) {
+                        (
+                            Term::Bop {
+                                lhs: la, rhs: ra, ..
+                            },
+                            Term::Bop {
+                                lhs: lb, rhs: rb, ..
+                            },
+                        ) => {
+                            vec![(la, lb), (ra, rb)]
+                        }
+                        (Term::Uop { val: x, .. }, Term::Uop { val: y, .. }) => vec![(x, y)],
+                        _ => unreachable!(),
+                    };
+                    self.convertible_all(pairs).await
+                }
+                Pair::Functions => {
+                    let tag = self.witness_tag();
+                    let (wa, wb) = (self.witness(&tag), self.witness(&tag));
+                    let a = self.heap.alloc(Term::App { func: a, arg: wa });
+                    let b = self.heap.alloc(Term::App { func: b, arg: wb });
+                    self.convertible(a, b).await
+                }
+            }
+        }))
+    }
+
+    /// CONV on two type values: the same shape (layout kind, atoms, sorts,
+    /// arities, capability keys and row tails) with convertible parts. The
+    /// codomains of two products are lambdas, so they compare as functions.
+    fn conv_types(&self, a: TypePtr<'h>, b: TypePtr<'h>) -> Reduce<'_, Sat> {
+        Box::pin(Deferred::new(async move {
+            let (a, mut b) = (self.heap.free_type(a), self.heap.free_type(b));
+            if !align_types(&a, &mut b) {
+                self.erase_addrs([a.children(), b.children()].concat());
+                return Sat::Fails;
+            }
+            let pairs = (a.children().into_iter().zip(b.children()))
+                .map(|(x, y)| unsafe { (TermPtr::forge(x), TermPtr::forge(y)) })
+                .collect();
+            self.convertible_all(pairs).await
+        }))
     }
 
-    /// BOP-SUP (rhs superposed): `(l op &L{a,b})` => `&L{(l0 op a), (l1 op b)}`.
-    fn bop_sup_right(&self, op: BinaryOp, la: TermPtr<'h>, ra: TermPtr<'h>) -> Term<'h> {
-        self.policy.next_step(InteractionType::BopSup);
-        let Term::Sup { label, ptr: sup } = self.heap.pull(ra) else {
-            unreachable!("combine_bop checked rhs is a Sup")
-        };
-        let (a, b) = self.heap.free_sup(sup);
-        let lhs = self.heap.pull(la);
-        let (d0, d1) = self.alloc_dup_c(lhs);
-        let b0 = self.heap.alloc(Term::Bop {
-            op,
-            lhs: self.dp_node(label, d0),
-            rhs: a,
-        });
-        let b1 = self.heap.alloc(Term::Bop {
-            op,
-            lhs: self.dp_node(label, d1),
-            rhs: b,
-        });
-        self.sup_term(label, b0, b1)
+    /// [`Self::convertible`] on each pair, stopping at the first that fails
+    /// (and erasing the rest).
+    fn convertible_all(&self, pairs: Vec<(TermPtr<'h>, TermPtr<'h>)>) -> Reduce<'_, Sat> {
+        Box::pin(Deferred::new(async move {
+            let mut sat = Sat::Holds;
+            let mut pairs = pairs.into_iter();
+            for (a, b) in pairs.by_ref() {
+                sat = sat.and(self.convertible(a, b).await);
+                if sat == Sat::Fails {
+                    break;
+                }
+            }
+            self.erase_all(pairs.flat_map(|(a, b)| [a, b]).collect());
+            sat
+        }))
     }
 
     // ====================================================================
@@ -874,68 +2085,30 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
     /// only to a product type (yielding its field count); a sum variant
     /// (`variant == Some(name)`) applies only to a sum type that declares `name`.
     fn ctor_arity(&self, t: &TypePtr<'h>, variant: Option<VariantId>) -> Option<usize> {
-        match (self.heap.type_info(t), variant) {
-            (TypeInfo::Product { fields, .. }, None) => Some(fields.len()),
-            (TypeInfo::Sum { variants, .. }, Some(name)) => variants
+        match (&self.heap.type_info(t).layout, variant) {
+            (Layout::Product(fields), None) => Some(fields.len()),
+            (Layout::Sum(variants), Some(name)) => variants
                 .iter()
                 .find(|v| v.name == name)
                 .map(|v| v.args.len()),
-            // `::New` on a sum, or a named variant on a product: mismatch.
+            // `::New` on a sum, a named variant on a product, or any constructor
+            // of a layout that has none: mismatch.
             _ => None,
         }
     }
 
-    /// Duplicate each sub-type child node of a type, returning the two projected
-    /// address lists (one per dup side). Mirrors DUP-CTR over a pack.
-    fn dup_arg_addrs(&self, label: LabelId, args: Vec<Addr>) -> (Vec<Addr>, Vec<Addr>) {
-        let mut a0 = Vec::with_capacity(args.len());
-        let mut a1 = Vec::with_capacity(args.len());
-        for a in args {
-            let field = self.heap.pull(unsafe { TermPtr::forge(a) });
-            let (d0, d1) = self.alloc_dup_c(field);
-            a0.push(self.dp_node(label, d0).into_addr());
-            a1.push(self.dp_node(label, d1).into_addr());
-        }
-        (a0, a1)
-    }
-
     /// Deep-duplicate an (affine) type value into two fresh type entries,
     /// distributing the dup into each lazy sub-type child (mirrors DUP-CTR).
     fn dup_type(&self, label: LabelId, ty: TypePtr<'h>) -> (TypePtr<'h>, TypePtr<'h>) {
-        match self.heap.free_type(ty) {
-            TypeInfo::Product { name, fields } => {
-                let (f0, f1) = self.dup_arg_addrs(label, fields);
-                (
-                    self.heap.alloc_type(TypeInfo::Product {
-                        name: name.clone(),
-                        fields: f0,
-                    }),
-                    self.heap.alloc_type(TypeInfo::Product { name, fields: f1 }),
-                )
-            }
-            TypeInfo::Sum { name, variants } => {
-                let mut v0 = Vec::with_capacity(variants.len());
-                let mut v1 = Vec::with_capacity(variants.len());
-                for v in variants {
-                    let (a0, a1) = self.dup_arg_addrs(label, v.args);
-                    v0.push(Variant {
-                        name: v.name,
-                        args: a0,
-                    });
-                    v1.push(Variant {
-                        name: v.name,
-                        args: a1,
-                    });
-                }
-                (
-                    self.heap.alloc_type(TypeInfo::Sum {
-                        name: name.clone(),
-                        variants: v0,
-                    }),
-                    self.heap.alloc_type(TypeInfo::Sum { name, variants: v1 }),
-                )
-            }
+        let mut t0 = self.heap.free_type(ty);
+        let mut t1 = t0.clone();
+        for (a0, a1) in t0.children_mut().into_iter().zip(t1.children_mut()) {
+            let field = self.heap.pull(unsafe { TermPtr::forge(*a0) });
+            let (d0, d1) = self.alloc_dup_c(field);
+            *a0 = self.dp_node(label, d0).into_addr();
+            *a1 = self.dp_node(label, d1).into_addr();
         }
+        (self.heap.alloc_type(t0), self.heap.alloc_type(t1))
     }
 
     /// Build a sup term over two owned component nodes.
@@ -950,7 +2123,7 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
     /// projection slot. Returns `None` when the dup is stuck (its value is an
     /// unsubstituted binder), leaving the cell untouched.
     fn force_dup(&self, label: LabelId, dp: DupPtr<'h>) -> Reduce<'_, DupForce<'h>> {
-        Box::pin(async move {
+        Box::pin(Deferred::new(async move {
             let mut guard = self.heap.dup_lock(dp).await;
             match self.heap.dup_take_value(&mut guard) {
                 Some(seed) => {
@@ -1011,8 +2184,11 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
                     // dropped: hand the reduced value through UNCOPIED (the whnf
                     // loop keeps reducing it) and reclaim the cell. Freeing from
                     // the winner cannot strand a waiter, since a dropped side
-                    // never arrives at the eval lock.
-                    if self.heap.dup_other_dropped(dp) {
+                    // never arrives at the eval lock. Labels are static, so
+                    // while any superposition of this label is live the value
+                    // may hold one only this dup would select from: copy it
+                    // then, and erase the dead copy below.
+                    if self.heap.dup_other_dropped(dp) && !self.heap.label_has_sups(label) {
                         self.policy.next_step(InteractionType::DupErase);
                         let mine = self.heap.pull(vp);
                         drop(guard);
@@ -1048,7 +2224,7 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
                     DupForce::Rewritten
                 }
             }
-        })
+        }))
     }
 
     /// Produce the two projections `(Dp0, Dp1)` of duplicating `head` (already in
@@ -1060,7 +2236,7 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
         _dp: DupPtr<'h>,
         head: Term<'h>,
     ) -> Reduce<'_, (Term<'h>, Term<'h>)> {
-        Box::pin(async move {
+        Box::pin(Deferred::new(async move {
             match head {
                 // copy leaves / atoms: duplicating a scalar value is a DUP-VAL.
                 Term::Int(n) => {
@@ -1196,6 +2372,24 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
                     };
                     (uop0, uop1)
                 }
+                Term::Prj { val, ty } => {
+                    // A stuck projection distributes the dup into the value and
+                    // the expected type alike, so both copies check the same thing.
+                    self.policy.next_step(InteractionType::DupPrj);
+                    let v = self.heap.pull(val);
+                    let t = self.heap.pull(ty);
+                    let (dv0, dv1) = self.alloc_dup_c(v);
+                    let (dt0, dt1) = self.alloc_dup_c(t);
+                    let prj0 = Term::Prj {
+                        val: self.dp_node(label, dv0),
+                        ty: self.dp_node(label, dt0),
+                    };
+                    let prj1 = Term::Prj {
+                        val: self.dp_node(label, dv1),
+                        ty: self.dp_node(label, dt1),
+                    };
+                    (prj0, prj1)
+                }
                 Term::Use { body } => {
                     self.policy.next_step(InteractionType::DupUse);
                     let b = self.heap.pull(body);
@@ -1228,13 +2422,16 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
                     // binder (component i feeds copy i). If one dup half is
                     // dropped we currently leak the unselected sup half.
                     let sup_ptr = self.heap.sup(occ0, occ1);
-                    self.heap.fill_binder(
+                    let unused = self.heap.fill_binder(
                         orig_binder,
                         Term::Sup {
                             label,
                             ptr: sup_ptr,
                         },
                     );
+                    if let Some(sup) = unused {
+                        self.erase(sup);
+                    }
                     (lam0, lam1)
                 }
                 Term::Ctn { ty, arity, values } => {
@@ -1271,13 +2468,24 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
                     self.policy.next_step(InteractionType::DupVal);
                     (Term::VarId(v), Term::VarId(v))
                 }
-                Term::Err { backtrace, .. } => {
+                Term::Err {
+                    immediate,
+                    backtrace,
+                } => {
                     // An error is a first-class eraser; duplicating it yields two
-                    // errors. Any (affine) backtrace cannot be shared, so it is
-                    // dropped here.
+                    // errors sharing its trace.
                     self.policy.next_step(InteractionType::DupVal);
-                    let _ = backtrace;
-                    (err_term(), err_term())
+                    let copy = backtrace.as_ref().map(|t| self.heap.trace_dup(t));
+                    (
+                        Term::Err {
+                            immediate,
+                            backtrace,
+                        },
+                        Term::Err {
+                            immediate,
+                            backtrace: copy,
+                        },
+                    )
                 }
                 Term::Mat { matches } => {
                     self.policy.next_step(InteractionType::DupMat);
@@ -1351,7 +2559,7 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
                 }
                 other => unreachable!("DUP of an unexpected head: {other:?}"),
             }
-        })
+        }))
     }
 
     /// APP-SUP: `(&L{f,g}) arg` => `!d&L=arg; &L{(f d0), (g d1)}`.
@@ -1370,12 +2578,31 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
         self.sup_term(label, fa, gb)
     }
 
+    /// MAT-SUP: `?{..} &L{a,b}` => `!m&L=?{..}; &L{(m0 a), (m1 b)}`.
+    fn mat_sup(&self, label: LabelId, sup: SupPtr<'h>, matches: MatchPtr<'h>) -> Term<'h> {
+        let (a, b) = self.heap.free_sup(sup);
+        let (m0, m1) = self.alloc_dup_c(Term::Mat { matches });
+        let ma = self.heap.alloc(Term::App {
+            func: self.dp_node(label, m0),
+            arg: a,
+        });
+        let mb = self.heap.alloc(Term::App {
+            func: self.dp_node(label, m1),
+            arg: b,
+        });
+        self.sup_term(label, ma, mb)
+    }
+
     /// Whether a WHNF pattern key matches the (already-WHNF) scrutinee. A
-    /// constructor scrutinee matches a `VarId` key naming the same variant; a
-    /// value scrutinee matches an equal value key.
+    /// constructor scrutinee matches a `VarId` key naming the same variant (a
+    /// product construction has none, and matches `New`, its constructor's
+    /// name); a value scrutinee matches an equal value key.
     fn key_matches(&self, scrut: &Term<'h>, key: &Term<'h>) -> bool {
         match (scrut, key) {
-            (Term::Ctn { values, .. }, Term::VarId(v)) => self.heap.pack_name(values) == Some(*v),
+            (Term::Ctn { values, .. }, Term::VarId(v)) => match self.heap.pack_name(values) {
+                Some(name) => name == *v,
+                None => self.heap.variant_name(*v) == "New",
+            },
             (Term::Int(a), Term::Int(b)) => a == b,
             (Term::Float(a), Term::Float(b)) => a == b,
             (Term::Bool(a), Term::Bool(b)) => a == b,
@@ -1397,7 +2624,7 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
     /// every other key and branch (and the match table) is reclaimed. With no
     /// covering case or default, the scrutinee is erased and the match reduces to
     /// a runtime [`Term::Err`].
-    async fn fire_mat(&self, matches: MatchPtr<'h>, scrut: Term<'h>) -> Term<'h> {
+    async fn fire_mat(&self, matches: MatchPtr<'h>, scrut: Term<'h>, at: Addr) -> Term<'h> {
         // Copy the case/default node addresses out, then free the table.
         let (cases, default) = {
             let data = self.heap.match_data(&matches);
@@ -1446,9 +2673,10 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
             (None, None) => {
                 // A concrete value reached the match but no case or default covers
                 // it: a runtime error.
-                self.erase(scrut);
                 self.policy.next_step(InteractionType::AppMat);
-                return err_term();
+                let scrutinee = self.head(&scrut);
+                self.erase(scrut);
+                return self.fail(Fault::NoMatch { scrutinee }, InteractionType::AppMat, at);
             }
         };
 
@@ -1478,7 +2706,7 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
     /// Apply a primitive to its (gathered, unforced) argument pointers: hand each
     /// as a [`Handle`] to the extension, which forces what it needs and returns a
     /// result; any argument it drops is reclaimed here.
-    async fn fire_prim(&self, id: PrimId, arg_ptrs: Vec<TermPtr<'h>>) -> TermPtr<'h> {
+    async fn fire_prim(&self, id: PrimId, arg_ptrs: Vec<TermPtr<'h>>, at: Addr) -> TermPtr<'h> {
         let args: Vec<Handle<'h>> = arg_ptrs
             .into_iter()
             .map(|p| Handle::new(p, self.heap))
@@ -1487,8 +2715,16 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
         let result = match self.extensions.apply(self, id, args).await {
             Ok(result) => result.into_term_ptr(),
             Err(error) => {
+                let fault = Fault::Primitive {
+                    name: match self.extensions.name(id) {
+                        Some(name) => Arc::from(name),
+                        None => Arc::from(id.get().to_string()),
+                    },
+                    message: Arc::from(error.message.as_str()),
+                };
                 self.extension_error.lock().unwrap().get_or_insert(error);
-                self.heap.alloc(Term::Wld)
+                self.heap
+                    .alloc(self.fail(fault, InteractionType::AppPri, at))
             }
         };
         self.erase_dropped_handles().await;
@@ -1497,8 +2733,14 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
 
     /// Complete a saturated [`Term::Partial`]: build the construction (for a
     /// constructor callable) or fire the primitive. `func` is the callable node and
-    /// `fields` the gathered (full) argument list. Returns the result node.
-    async fn complete_partial(&self, func: TermPtr<'h>, fields: Vec<TermPtr<'h>>) -> TermPtr<'h> {
+    /// `fields` the gathered (full) argument list. Returns the result node. `at`
+    /// is the partial's node, which a failure is traced to.
+    async fn complete_partial(
+        &self,
+        func: TermPtr<'h>,
+        fields: Vec<TermPtr<'h>>,
+        at: Addr,
+    ) -> TermPtr<'h> {
         let arity = fields.len() as u8;
         // `func` may be a dup projection (after duplicating a partial); force it.
         let nf = self.sub_whnf_at(func).await;
@@ -1515,21 +2757,27 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
                         })
                     }
                     other => {
+                        let head = self.head(&other);
                         self.erase(other);
                         for f in fields {
                             self.erase(self.heap.pull(f));
                         }
-                        self.heap.alloc(err_term())
+                        let fault = Fault::NotAType { head };
+                        self.heap
+                            .alloc(self.fail(fault, InteractionType::AppCtr, at))
                     }
                 }
             }
-            Term::Pri(id) => self.fire_prim(id, fields).await,
+            Term::Pri(id) => self.fire_prim(id, fields, at).await,
             other => {
+                let head = self.head(&other);
                 self.erase(other);
                 for f in fields {
                     self.erase(self.heap.pull(f));
                 }
-                self.heap.alloc(err_term())
+                let fault = Fault::NotCallable { head };
+                self.heap
+                    .alloc(self.fail(fault, InteractionType::AppCtr, at))
             }
         }
     }
@@ -1547,11 +2795,73 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
             | Term::App { .. }
             | Term::Bop { .. }
             | Term::Uop { .. }
+            | Term::Prj { .. }
             | Term::Partial { .. } => ArgClass::Stuck,
             _ => ArgClass::Err,
         }
     }
 
+    // ====================================================================
+    // Errors
+    // ====================================================================
+
+    /// Raise a runtime error: an `Err` whose backtrace records `fault`, the
+    /// failing `interaction`, and the source span of the redex node `at`.
+    fn fail(&self, fault: Fault, interaction: InteractionType, at: Addr) -> Term<'h> {
+        let trace = Trace {
+            span: self.heap.span_of(at),
+            ..Trace::new(fault, interaction)
+        };
+        Term::Err {
+            immediate: true,
+            backtrace: Some(self.heap.trace(trace)),
+        }
+    }
+
+    /// Record that the error `err` bubbled up through `frame`.
+    fn propagate(&self, err: Term<'h>, frame: Frame) -> Term<'h> {
+        match err {
+            Term::Err {
+                immediate,
+                backtrace,
+            } => Term::Err {
+                immediate,
+                backtrace: backtrace.map(|t| self.heap.trace_push(t, frame)),
+            },
+            other => other,
+        }
+    }
+
+    /// The head of an operand, as a trace reports it.
+    fn head(&self, term: &Term<'h>) -> Head {
+        match term {
+            Term::Int(n) => Head::Int(*n),
+            Term::Float(x) => Head::Float(x.0),
+            Term::Bool(b) => Head::Bool(*b),
+            Term::Char(c) => Head::Char(*c),
+            Term::Box(v) => match self.heap.value_get(v) {
+                Boxed::Str(_) => Head::Str,
+                Boxed::Bytes(_) => Head::Bytes,
+            },
+            Term::Type(t) => Head::Type(self.heap.type_name(t.addr())),
+            Term::Ctn { ty, values, .. } => Head::Ctn(match self.heap.pack_name(values) {
+                Some(v) => Some(Arc::from(self.heap.variant_name(v))),
+                None => self.heap.type_name(ty.addr()),
+            }),
+            Term::Lam { .. }
+            | Term::Use { .. }
+            | Term::Mat { .. }
+            | Term::Pri(_)
+            | Term::Ctr { .. }
+            | Term::Partial { .. } => Head::Function,
+            Term::Err { .. } => Head::Err,
+            Term::Wld => Head::Other("`*`"),
+            Term::VarId(_) => Head::Other("a variant tag"),
+            Term::Sup { .. } => Head::Other("a superposition"),
+            _ => Head::Other("an unreduced term"),
+        }
+    }
+
     // ====================================================================
     // Normalization
     // ====================================================================
@@ -1559,7 +2869,7 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
     /// The boxed form of [`normalize_at_ptr`](Self::normalize_at_ptr), for
     /// recursive call sites.
     pub fn sub_normalize_at(&self, ptr: TermPtr<'h>) -> Reduce<'_, TermPtr<'h>> {
-        Box::pin(self.normalize_at_ptr(ptr))
+        Box::pin(Deferred::new(self.normalize_at_ptr(ptr)))
     }
 
     /// Reduce the node at `ptr` to full normal form in place, returning a pointer
@@ -1594,8 +2904,7 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
             }
             Term::Sup { label, ptr } => {
                 let (a, b) = self.heap.sup_args(&ptr);
-                let na = self.sub_normalize_at(a).await;
-                let nb = self.sub_normalize_at(b).await;
+                let (na, nb) = self.normalize_pair(a, b).await;
                 self.heap.set_sup_args(&ptr, na, nb);
                 self.heap.finish_slot(slot, Term::Sup { label, ptr })
             }
@@ -1615,10 +2924,15 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
                 let nv = self.sub_normalize_at(val).await;
                 self.heap.finish_slot(slot, Term::Uop { op, val: nv })
             }
+            Term::Prj { val, ty } => {
+                let (nv, nt) = self.normalize_pair(val, ty).await;
+                self.heap.finish_slot(slot, Term::Prj { val: nv, ty: nt })
+            }
             Term::Ctn { ty, arity, values } => {
-                for i in 0..arity as usize {
-                    let f = self.heap.pack_field(&values, i);
-                    let nf = self.sub_normalize_at(f).await;
+                let fields = (0..arity as usize)
+                    .map(|i| self.heap.pack_field(&values, i))
+                    .collect();
+                for (i, nf) in self.normalize_all(fields).await.into_iter().enumerate() {
                     self.heap.set_pack_field(&values, i, nf);
                 }
                 // The type's sub-types are left lazy (not normalized).
@@ -1660,31 +2974,13 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
     /// sub-fields, rebuilding the (affine) type value. Genuine sub-type redexes
     /// are left untouched — only the substitution plumbing is settled.
     fn resolve_type_fields(&self, ty: TypePtr<'h>) -> Reduce<'_, TypePtr<'h>> {
-        Box::pin(async move {
-            match self.heap.free_type(ty) {
-                TypeInfo::Product { name, fields } => {
-                    let mut nf = Vec::with_capacity(fields.len());
-                    for a in fields {
-                        nf.push(self.resolve_lazy_field(a).await);
-                    }
-                    self.heap.alloc_type(TypeInfo::Product { name, fields: nf })
-                }
-                TypeInfo::Sum { name, variants } => {
-                    let mut nv = Vec::with_capacity(variants.len());
-                    for v in variants {
-                        let mut na = Vec::with_capacity(v.args.len());
-                        for a in v.args {
-                            na.push(self.resolve_lazy_field(a).await);
-                        }
-                        nv.push(Variant {
-                            name: v.name,
-                            args: na,
-                        });
-                    }
-                    self.heap.alloc_type(TypeInfo::Sum { name, variants: nv })
-                }
+        Box::pin(Deferred::new(async move {
+            let mut info = self.heap.free_type(ty);
+            for a in info.children_mut() {
+                *a = self.resolve_lazy_field(*a).await;
             }
-        })
+            self.heap.alloc_type(info)
+        }))
     }
 
     /// Settle one lazy sub-field address: fire a *ready* administrative `Dup`
@@ -1692,7 +2988,7 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
     /// recurse through `Sup`s and nested types, and otherwise leave the field as
     /// written. Returns the (possibly relocated) field address.
     fn resolve_lazy_field(&self, addr: Addr) -> Reduce<'_, Addr> {
-        Box::pin(async move {
+        Box::pin(Deferred::new(async move {
             let ptr = unsafe { TermPtr::forge(addr) };
             enum K<'h> {
                 Dup(DupPtr<'h>),
@@ -1739,7 +3035,7 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
                     self.heap.alloc(Term::Type(t)).into_addr()
                 }
             }
-        })
+        }))
     }
 
     /// Whether a `Dup`'s duplicand is settled enough to fire without reducing a
@@ -1750,21 +3046,309 @@ impl<'e, 'h, P: ExecPolicy, X: Extensions> Executor<'e, 'h, P, X> {
     fn dup_is_ready(&self, dp: &DupPtr<'h>) -> bool {
         // Follow a chain of dups iteratively (not recursively) so a deep chain
         // can't overflow the stack here.
-        let mut value = self.heap.dup_peek(dp);
+        // Each cell stays locked while its duplicand is inspected; one that is
+        // contended is being forced by another worker, so not ready yet.
+        let mut held = Vec::new();
+        let Some(guard) = self.heap.dup_try_lock(*dp) else {
+            return false;
+        };
+        let mut value = guard.value;
+        held.push(guard);
         loop {
             let Some(v) = value else { return true }; // fired dup
             match &*self.heap.view_at(v) {
                 Term::App { .. }
                 | Term::Bop { .. }
                 | Term::Uop { .. }
+                | Term::Prj { .. }
                 | Term::Use { .. }
                 | Term::Mat { .. }
                 | Term::Var { .. } => return false,
-                Term::Dup { ptr, .. } => value = self.heap.dup_peek(ptr),
+                Term::Dup { ptr, .. } => {
+                    let Some(guard) = self.heap.dup_try_lock(*ptr) else {
+                        return false;
+                    };
+                    value = guard.value;
+                    held.push(guard);
+                }
                 _ => return true,
             }
         }
     }
+    // ====================================================================
+    // Collapse
+    // ====================================================================
+
+    /// Enumerate the alternatives of the superpositions in `root`: a lazy
+    /// stream ([`Collapse::next`]) of its normal forms, one per consistent
+    /// choice of sides. Breadth-first and unlimited unless configured.
+    pub fn collapse(&self, root: TermPtr<'h>) -> Collapse<'_, 'e, 'h, P, X> {
+        Collapse {
+            exec: self,
+            order: CollapseOrder::default(),
+            limit: None,
+            frontier: VecDeque::from([Branch {
+                term: root,
+                chosen: Vec::new(),
+            }]),
+        }
+    }
+
+    /// Normalize `ptr` for [`Collapse`]: a superposition whose label `chosen`
+    /// already decides is replaced by that side, and the first undecided one
+    /// reached through constructor fields is lifted to the head, so a `Sup`
+    /// result is the next choice to make. Other heads are normalized as they
+    /// are; a superposition under a binder stays in place.
+    fn collapse_at<'s>(
+        &'s self,
+        ptr: TermPtr<'h>,
+        chosen: &'s [(LabelId, bool)],
+    ) -> Reduce<'s, TermPtr<'h>> {
+        Box::pin(Deferred::new(async move {
+            let mut ptr = ptr;
+            loop {
+                let p = self.whnf_at_ptr(ptr).await;
+                if !self.policy.should_continue() {
+                    return p;
+                }
+                let view = self.heap.view(&p);
+                let left = match &*view {
+                    Term::Sup { label, .. } => match chosen.iter().find(|(l, _)| l == label) {
+                        Some(&(_, left)) => left,
+                        None => return p,
+                    },
+                    Term::Ctn { .. } => return self.collapse_fields(p, chosen).await,
+                    _ => return self.sub_normalize_at(p).await,
+                };
+                let Term::Sup { ptr: sup, .. } = self.heap.pull(p) else {
+                    unreachable!()
+                };
+                let (a, b) = self.heap.free_sup(sup);
+                let (mine, other) = if left { (a, b) } else { (b, a) };
+                self.erase(self.heap.pull(other));
+                ptr = mine;
+            }
+        }))
+    }
+
+    /// [`collapse_at`](Self::collapse_at) a constructor's fields in order,
+    /// stopping at the first that is an undecided superposition to lift it
+    /// over the constructor (CTN-SUP): the other fields and the type are
+    /// duplicated under its label, so a field superposed on the same label
+    /// takes the same side.
+    async fn collapse_fields(&self, p: TermPtr<'h>, chosen: &[(LabelId, bool)]) -> TermPtr<'h> {
+        let (slot, term) = self.heap.term(p);
+        let Term::Ctn { ty, arity, values } = term else {
+            unreachable!()
+        };
+        let n = arity as usize;
+        for i in 0..n {
+            let field = self
+                .collapse_at(self.heap.pack_field(&values, i), chosen)
+                .await;
+            if !self.policy.should_continue()
+                || !matches!(&*self.heap.view(&field), Term::Sup { .. })
+            {
+                self.heap.set_pack_field(&values, i, field);
+                continue;
+            }
+            let Term::Sup { label, ptr: sup } = self.heap.pull(field) else {
+                unreachable!()
+            };
+            let (a, b) = self.heap.free_sup(sup);
+            let name = self.heap.pack_name(&values);
+            let mut f0 = Vec::with_capacity(n);
+            let mut f1 = Vec::with_capacity(n);
+            let mut sides = Some((a, b));
+            for j in 0..n {
+                if j == i {
+                    let (a, b) = sides.take().unwrap();
+                    f0.push(a);
+                    f1.push(b);
+                    continue;
+                }
+                let field = self.heap.pull(self.heap.pack_field(&values, j));
+                let (d0, d1) = self.alloc_dup_c(field);
+                f0.push(self.dp_node(label, d0));
+                f1.push(self.dp_node(label, d1));
+            }
+            self.heap.free_pack(values);
+            let (t0, t1) = self.dup_type(label, ty);
+            let c0 = self.heap.alloc(Term::Ctn {
+                ty: t0,
+                arity,
+                values: self.heap.alloc_pack(name, f0),
+            });
+            let c1 = self.heap.alloc(Term::Ctn {
+                ty: t1,
+                arity,
+                values: self.heap.alloc_pack(name, f1),
+            });
+            return self.heap.finish_slot(slot, self.sup_term(label, c0, c1));
+        }
+        self.heap.finish_slot(slot, Term::Ctn { ty, arity, values })
+    }
+}
+
+/// A reduction forked onto its own tokio task by [`Executor::fork`], borrowing
+/// the executor (and heap) for `'s`. Its result is read back from `result` once
+/// the task has finished.
+struct ScopedTask<'s, T> {
+    task: Option<JoinHandle<()>>,
+    result: Arc<Mutex<Option<T>>>,
+    _scope: PhantomData<&'s ()>,
+}
+
+impl<T> ScopedTask<'_, T> {
+    /// Wait for the task and take its result, resuming a panic raised in it.
+    async fn join(mut self) -> T {
+        let task = self.task.as_mut().expect("a scoped task is joined once");
+        let outcome = task.await;
+        self.task = None;
+        match outcome {
+            Ok(()) => {}
+            Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+            Err(e) => panic!("a forked reduction was cancelled: {e}"),
+        }
+        self.result
+            .lock()
+            .unwrap()
+            .take()
+            .expect("a finished scoped task stores its result")
+    }
+}
+
+impl<T> Drop for ScopedTask<'_, T> {
+    /// Dropped unjoined (the joining future was cancelled or is unwinding): the
+    /// task may still be using its `'s` borrow, so cancel it and block until
+    /// tokio has dropped it. `block_in_place` hands this worker's queue to
+    /// another thread meanwhile, in case the task is waiting in it.
+    fn drop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+            tokio::task::block_in_place(|| {
+                while !task.is_finished() {
+                    std::thread::yield_now();
+                }
+            });
+        }
+    }
+}
+
+/// The order [`Collapse`] explores the branches of a superposition in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CollapseOrder {
+    /// Shallow choices first: every answer behind `n` choices comes before
+    /// any behind `n + 1`, so an infinite search still reaches each answer.
+    #[default]
+    BreadthFirst,
+    /// The left side of each choice is exhausted before the right.
+    DepthFirst,
+}
+
+/// An unexplored branch of a [`Collapse`]: its term and the side each label
+/// on the way to it took.
+struct Branch<'h> {
+    term: TermPtr<'h>,
+    chosen: Vec<(LabelId, bool)>,
+}
+
+/// The answers of a superposed term, produced one at a time by
+/// [`next`](Collapse::next) (see [`Executor::collapse`]). A label is a single
+/// choice: once a branch has taken a side of `&L{..}` every other `&L{..}` in
+/// it takes the same side. Branches left unexplored are erased on drop.
+pub struct Collapse<'x, 'e, 'h, P: ExecPolicy, X: Extensions> {
+    exec: &'x Executor<'e, 'h, P, X>,
+    order: CollapseOrder,
+    /// Answers still to produce, if limited.
+    limit: Option<usize>,
+    frontier: VecDeque<Branch<'h>>,
+}
+
+impl<'h, P: ExecPolicy, X: Extensions> Collapse<'_, '_, 'h, P, X> {
+    pub fn order(mut self, order: CollapseOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Stop after `limit` answers.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// The number of branches not yet explored.
+    pub fn pending(&self) -> usize {
+        self.frontier.len()
+    }
+
+    /// Reduce branches until one is free of superpositions and return it, in
+    /// normal form. `None` once every branch is explored, the limit is
+    /// reached, or the executor's policy stops reduction (the branch it was
+    /// reducing then stays pending).
+    pub async fn next(&mut self) -> Option<TermPtr<'h>> {
+        let exec = self.exec;
+        if self.limit == Some(0) {
+            return None;
+        }
+        while let Some(Branch { term, chosen }) = match self.order {
+            CollapseOrder::BreadthFirst => self.frontier.pop_front(),
+            CollapseOrder::DepthFirst => self.frontier.pop_back(),
+        } {
+            let head = WorkStack::drive(Box::pin(async {
+                let r = exec.collapse_at(term, &chosen).await;
+                exec.settle().await;
+                r
+            }))
+            .await;
+            if !exec.policy.should_continue() {
+                let branch = Branch { term: head, chosen };
+                match self.order {
+                    CollapseOrder::BreadthFirst => self.frontier.push_front(branch),
+                    CollapseOrder::DepthFirst => self.frontier.push_back(branch),
+                }
+                return None;
+            }
+            if !matches!(&*exec.heap.view(&head), Term::Sup { .. }) {
+                if let Some(limit) = &mut self.limit {
+                    *limit -= 1;
+                }
+                return Some(head);
+            }
+            let Term::Sup { label, ptr } = exec.heap.pull(head) else {
+                unreachable!()
+            };
+            let (a, b) = exec.heap.free_sup(ptr);
+            let mut left = chosen.clone();
+            left.push((label, true));
+            let mut right = chosen;
+            right.push((label, false));
+            let branches = [
+                Branch {
+                    term: a,
+                    chosen: left,
+                },
+                Branch {
+                    term: b,
+                    chosen: right,
+                },
+            ];
+            // Either way the left branch is taken first.
+            match self.order {
+                CollapseOrder::BreadthFirst => self.frontier.extend(branches),
+                CollapseOrder::DepthFirst => self.frontier.extend(branches.into_iter().rev()),
+            }
+        }
+        None
+    }
+}
+
+impl<P: ExecPolicy, X: Extensions> Drop for Collapse<'_, '_, '_, P, X> {
+    fn drop(&mut self) {
+        for branch in self.frontier.drain(..) {
+            self.exec.erase(self.exec.heap.pull(branch.term));
+        }
+    }
 }
 
 /// Whether a WHNF scrutinee is a concrete value a match can fire on (a
@@ -1792,14 +3376,252 @@ enum ArgClass {
     Err,
 }
 
-/// A runtime error term, used for invalid operations (type mismatch, an op
-/// unsupported for the operand type, or division/modulo by zero). The backtrace
-/// is left unset for now.
-fn err_term<'h>() -> Term<'h> {
-    Term::Err {
-        immediate: true,
-        backtrace: None,
+/// The outcome of a projection's satisfaction check (see
+/// [`Executor::satisfies`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Sat {
+    Holds,
+    /// an incompatibility was demonstrated.
+    Fails,
+    /// a sub-type didn't reduce to a type value (or the budget ran out).
+    Stuck,
+}
+
+impl Sat {
+    /// Both checks: a failure anywhere wins over being stuck.
+    fn and(self, other: Sat) -> Sat {
+        match (self, other) {
+            (Sat::Fails, _) | (_, Sat::Fails) => Sat::Fails,
+            (Sat::Stuck, _) | (_, Sat::Stuck) => Sat::Stuck,
+            _ => Sat::Holds,
+        }
+    }
+}
+
+/// Whether `term` is a head [`Executor::convertible`] can't compare yet: a
+/// redex or binder still to be reduced or substituted, or a callable that is
+/// not a lambda.
+fn undecided(term: &Term) -> bool {
+    matches!(
+        term,
+        Term::Var { .. }
+            | Term::Dup { .. }
+            | Term::App { .. }
+            | Term::Bop { .. }
+            | Term::Uop { .. }
+            | Term::Prj { .. }
+            | Term::And { .. }
+            | Term::Or { .. }
+            | Term::Mat { .. }
+            | Term::Ctr { .. }
+            | Term::Partial { .. }
+    )
+}
+
+/// Whether `a` and `b` have the same shape for [`Executor::conv_types`].
+/// Reorders `b`'s variants and capability entries to `a`'s order, so that the
+/// two records' [`children`](TypeInfo::children) pair up.
+fn align_types(a: &TypeInfo, b: &mut TypeInfo) -> bool {
+    let layout = match (&a.layout, &mut b.layout) {
+        (Layout::Any, Layout::Any) => true,
+        (Layout::Atom(x), Layout::Atom(y)) => x == y,
+        (Layout::Sort(x), Layout::Sort(y)) => x == y,
+        (Layout::Product(x), Layout::Product(y)) => x.len() == y.len(),
+        (Layout::Sum(x), Layout::Sum(y)) => align(x, y, |v, w| {
+            v.name == w.name && v.args.len() == w.args.len()
+        }),
+        (Layout::Pi { erased: x, .. }, Layout::Pi { erased: y, .. }) => x == y,
+        _ => false,
+    };
+    layout
+        && [(&a.props, &mut b.props), (&a.ops, &mut b.ops)]
+            .into_iter()
+            .all(|(x, y)| {
+                x.open == y.open && align(&x.entries, &mut y.entries, |e, f| e.name == f.name)
+            })
+}
+
+/// Reorder `ys` to line up with `xs` under `same`, when it is a permutation.
+fn align<T>(xs: &[T], ys: &mut [T], same: impl Fn(&T, &T) -> bool) -> bool {
+    if xs.len() != ys.len() {
+        return false;
     }
+    for (i, x) in xs.iter().enumerate() {
+        match ys[i..].iter().position(|y| same(x, y)) {
+            Some(j) => ys.swap(i, i + j),
+            None => return false,
+        }
+    }
+    true
+}
+
+/// Which lattice operation [`Executor::combine_types`] computes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Lattice {
+    Meet,
+    Join,
+}
+
+/// Why [`Executor::combine_types`] has no result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Clash {
+    /// the requirements are incompatible (a meet with no consistent record).
+    Inconsistent,
+    /// a shared entry didn't reduce to a type value (or the budget ran out).
+    Stuck,
+}
+
+/// A combined record before its shared entries are combined: the record (a
+/// shared entry holding `a`'s node), the `b` partner of each of its
+/// [`children`](TypeInfo::children) in order, and the nodes left out of it.
+type Plan = (TypeInfo, Vec<Option<Addr>>, Vec<Addr>);
+
+/// The structural half of [`Executor::combine_types`], pairing the entries
+/// `a` and `b` share. A meet with no consistent layout hands back every node
+/// of both sides instead.
+fn plan_combine(a: TypeInfo, b: TypeInfo, op: Lattice) -> Result<Plan, Vec<Addr>> {
+    let mut all = a.children();
+    all.extend(b.children());
+    let mut partners = Vec::new();
+    let mut dropped = Vec::new();
+    let name = a.name.filter(|x| b.name.as_ref() == Some(x));
+    let layout = match (a.layout, b.layout, op) {
+        (Layout::Any, l, Lattice::Meet) | (l, Layout::Any, Lattice::Meet) => {
+            partners.extend(l.children().iter().map(|_| None));
+            l
+        }
+        (Layout::Atom(x), Layout::Atom(y), _) if x == y => Layout::Atom(x),
+        (Layout::Sort(x), Layout::Sort(y), op) => match (x.within(y), op) {
+            (true, Lattice::Meet) | (false, Lattice::Join) => Layout::Sort(x),
+            _ => Layout::Sort(y),
+        },
+        (Layout::Product(fa), Layout::Product(fb), op) if fa.is_empty() == fb.is_empty() => {
+            let mut fields = Vec::with_capacity(fa.len().max(fb.len()));
+            for i in 0..fa.len().max(fb.len()) {
+                match (fa.get(i), fb.get(i)) {
+                    (Some(&x), Some(&y)) => {
+                        fields.push(x);
+                        partners.push(Some(y));
+                    }
+                    (Some(&x), None) | (None, Some(&x)) if op == Lattice::Meet => {
+                        fields.push(x);
+                        partners.push(None);
+                    }
+                    (Some(&x), None) | (None, Some(&x)) => dropped.push(x),
+                    (None, None) => unreachable!(),
+                }
+            }
+            Layout::Product(fields)
+        }
+        (Layout::Sum(va), Layout::Sum(vb), op)
+            if op == Lattice::Meet
+                || va.iter().all(|v| {
+                    (vb.iter().find(|w| w.name == v.name))
+                        .is_none_or(|w| w.args.len() == v.args.len())
+                }) =>
+        {
+            let mut variants = Vec::new();
+            let mut used = vec![false; vb.len()];
+            for v in va {
+                match vb
+                    .iter()
+                    .position(|w| w.name == v.name && w.args.len() == v.args.len())
+                {
+                    Some(j) => {
+                        used[j] = true;
+                        partners.extend(vb[j].args.iter().map(|&y| Some(y)));
+                        variants.push(v);
+                    }
+                    None if op == Lattice::Join => {
+                        partners.extend(v.args.iter().map(|_| None));
+                        variants.push(v);
+                    }
+                    None => dropped.extend(v.args),
+                }
+            }
+            for (w, used) in vb.into_iter().zip(used) {
+                match (used, op) {
+                    (true, _) => {}
+                    (false, Lattice::Join) => {
+                        partners.extend(w.args.iter().map(|_| None));
+                        variants.push(w);
+                    }
+                    (false, Lattice::Meet) => dropped.extend(w.args),
+                }
+            }
+            if variants.is_empty() {
+                return Err(all);
+            }
+            Layout::Sum(variants)
+        }
+        (_, _, Lattice::Meet) => return Err(all),
+        // A join of layouts that disagree is unconstrained.
+        (la, lb, Lattice::Join) => {
+            dropped.extend(la.children());
+            dropped.extend(lb.children());
+            Layout::Any
+        }
+    };
+    let rows = [(a.props, b.props), (a.ops, b.ops)].map(|(ra, rb)| {
+        let mut row = Row {
+            entries: Vec::new(),
+            open: match op {
+                Lattice::Meet => ra.open && rb.open,
+                Lattice::Join => ra.open || rb.open,
+            },
+        };
+        let mut used = vec![false; rb.entries.len()];
+        for e in ra.entries {
+            match rb.entries.iter().position(|f| f.name == e.name) {
+                Some(j) => {
+                    used[j] = true;
+                    partners.push(Some(rb.entries[j].sig));
+                    row.entries.push(e);
+                }
+                None if op == Lattice::Meet => {
+                    partners.push(None);
+                    row.entries.push(e);
+                }
+                None => {
+                    dropped.push(e.sig);
+                    row.open = true;
+                }
+            }
+        }
+        for (f, used) in rb.entries.into_iter().zip(used) {
+            match (used, op) {
+                (true, _) => {}
+                (false, Lattice::Meet) => {
+                    partners.push(None);
+                    row.entries.push(f);
+                }
+                (false, Lattice::Join) => {
+                    dropped.push(f.sig);
+                    row.open = true;
+                }
+            }
+        }
+        row
+    });
+    let [props, ops] = rows;
+    Ok((
+        TypeInfo {
+            layout,
+            props,
+            ops,
+            name,
+        },
+        partners,
+        dropped,
+    ))
+}
+
+/// Why a builtin operator failed on two values; [`Executor::combine_bop`] turns
+/// it into a [`Fault`].
+enum OpFailure {
+    DivisionByZero,
+    /// Not defined for the operand types.
+    Unsupported,
 }
 
 /// Whether a WHNF term is a concrete primitive scalar leaf (the operands the
@@ -1825,17 +3647,17 @@ fn floor_div_i64(a: i64, b: i64) -> i64 {
 
 /// Apply a binary operator to two `Int`s. `/` is true division (always yields a
 /// `Float`); `~/` is floor division (`Int`). Comparisons yield `Bool`; div / mod
-/// by zero yields `Err`.
+/// by zero fails.
 #[rustfmt::skip]
-fn apply_int<'h>(op: BinaryOp, a: i64, b: i64) -> Term<'h> {
+fn apply_int<'h>(op: BinaryOp, a: i64, b: i64) -> Result<Term<'h>, OpFailure> {
     use BinaryOp::*;
-    match op {
+    Ok(match op {
         Add  => Term::Int(a.wrapping_add(b)),
         Sub  => Term::Int(a.wrapping_sub(b)),
         Mul  => Term::Int(a.wrapping_mul(b)),
-        Div  => if b != 0 { Term::Float(OrderedFloat(a as f64 / b as f64)) } else { err_term() },
-        IDiv => if b != 0 { Term::Int(floor_div_i64(a, b)) } else { err_term() },
-        Mod  => if b != 0 { Term::Int(a.wrapping_rem(b)) } else { err_term() },
+        Div  => if b != 0 { Term::Float(OrderedFloat(a as f64 / b as f64)) } else { return Err(OpFailure::DivisionByZero) },
+        IDiv => if b != 0 { Term::Int(floor_div_i64(a, b)) } else { return Err(OpFailure::DivisionByZero) },
+        Mod  => if b != 0 { Term::Int(a.wrapping_rem(b)) } else { return Err(OpFailure::DivisionByZero) },
         And  => Term::Int(a & b),
         Or   => Term::Int(a | b),
         Xor  => Term::Int(a ^ b),
@@ -1847,60 +3669,61 @@ fn apply_int<'h>(op: BinaryOp, a: i64, b: i64) -> Term<'h> {
         Lte  => Term::Bool(a <= b),
         Gt   => Term::Bool(a > b),
         Gte  => Term::Bool(a >= b),
-        Invalid => err_term(),
-    }
+        Conv | Invalid => return Err(OpFailure::Unsupported),
+    })
 }
 
 /// Apply a binary operator to two `f64`s (used for `Float op Float` and any mixed
-/// `Int`/`Float` after promoting the int). `/` and `~/` (floor) by zero yield
-/// `Err` rather than `inf`. Comparisons yield `Bool`; bitwise/shift ops yield `Err`.
+/// `Int`/`Float` after promoting the int). `/` and `~/` (floor) by zero fail
+/// rather than yield `inf`. Comparisons yield `Bool`; bitwise/shift ops are
+/// unsupported.
 #[rustfmt::skip]
-fn apply_float<'h>(op: BinaryOp, a: f64, b: f64) -> Term<'h> {
+fn apply_float<'h>(op: BinaryOp, a: f64, b: f64) -> Result<Term<'h>, OpFailure> {
     use BinaryOp::*;
-    match op {
+    Ok(match op {
         Add  => Term::Float(OrderedFloat(a + b)),
         Sub  => Term::Float(OrderedFloat(a - b)),
         Mul  => Term::Float(OrderedFloat(a * b)),
-        Div  => if b != 0.0 { Term::Float(OrderedFloat(a / b)) } else { err_term() },
-        IDiv => if b != 0.0 { Term::Float(OrderedFloat((a / b).floor())) } else { err_term() },
-        Mod  => if b != 0.0 { Term::Float(OrderedFloat(a % b)) } else { err_term() },
+        Div  => if b != 0.0 { Term::Float(OrderedFloat(a / b)) } else { return Err(OpFailure::DivisionByZero) },
+        IDiv => if b != 0.0 { Term::Float(OrderedFloat((a / b).floor())) } else { return Err(OpFailure::DivisionByZero) },
+        Mod  => if b != 0.0 { Term::Float(OrderedFloat(a % b)) } else { return Err(OpFailure::DivisionByZero) },
         Eq   => Term::Bool(a == b),
         Neq  => Term::Bool(a != b),
         Lt   => Term::Bool(a < b),
         Lte  => Term::Bool(a <= b),
         Gt   => Term::Bool(a > b),
         Gte  => Term::Bool(a >= b),
-        And | Or | Xor | Shl | Shr | Invalid => err_term(),
-    }
+        And | Or | Xor | Shl | Shr | Conv | Invalid => return Err(OpFailure::Unsupported),
+    })
 }
 
 /// Apply a binary operator to two `Bool`s. `&`/`|`/`^` are logical; `==`/`!=`
-/// compare. Arithmetic / shift ops yield `Err`.
+/// compare. Arithmetic / shift ops are unsupported.
 #[rustfmt::skip]
-fn apply_bool<'h>(op: BinaryOp, a: bool, b: bool) -> Term<'h> {
+fn apply_bool<'h>(op: BinaryOp, a: bool, b: bool) -> Result<Term<'h>, OpFailure> {
     use BinaryOp::*;
-    match op {
+    Ok(match op {
         And => Term::Bool(a && b),
         Or  => Term::Bool(a || b),
         Xor => Term::Bool(a ^ b),
         Eq  => Term::Bool(a == b),
         Neq => Term::Bool(a != b),
-        _ => err_term(),
-    }
+        _ => return Err(OpFailure::Unsupported),
+    })
 }
 
 /// Apply a binary operator to two `char`s. Only comparisons are supported (they
-/// yield `Bool`); everything else yields `Err`.
+/// yield `Bool`); everything else is unsupported.
 #[rustfmt::skip]
-fn apply_char<'h>(op: BinaryOp, a: char, b: char) -> Term<'h> {
+fn apply_char<'h>(op: BinaryOp, a: char, b: char) -> Result<Term<'h>, OpFailure> {
     use BinaryOp::*;
-    match op {
+    Ok(match op {
         Eq  => Term::Bool(a == b),
         Neq => Term::Bool(a != b),
         Lt  => Term::Bool(a < b),
         Lte => Term::Bool(a <= b),
         Gt  => Term::Bool(a > b),
         Gte => Term::Bool(a >= b),
-        _ => err_term(),
-    }
+        _ => return Err(OpFailure::Unsupported),
+    })
 }
@@ -1,11 +1,14 @@
-use super::term::{Brand, LabelId, Node, PrimId, Term, VariantId};
+use super::image::PortableTerm;
+use super::term::{Brand, LabelId, Node, PrimId, Sort, Term, VariantId};
+use super::trace::{Frame, Trace};
 use crate::core::expr::{Expr, Pat, Value as CoreValue};
+use crate::error::{ImageError, LowerError};
 use crate::util::slab::{ShardedSlab, SharedKey, UniqueKey, UniqueSlot};
 use crate::util::{SingleMutex, SingleMutexGuard, U56};
 use std::collections::{HashMap, HashSet};
 use std::marker::PhantomData;
-use std::ops::Deref;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::ops::{Deref, Range};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 
 pub struct Heap {
@@ -23,13 +26,33 @@ pub struct Heap {
     // A bidirectional string interner backing variant names and label ids: the
     // slab maps an address back to its string, the map a string to its address.
     names: ShardedSlab<Addr, Arc<str>>,
+    // Runtime error traces behind an `Err`'s backtrace. Shared behind an `Arc`
+    // so duplicating an error does not copy its trace.
+    traces: ShardedSlab<Addr, Arc<Trace>>,
     interner: Mutex<HashMap<Arc<str>, Addr>>,
     // Match tables, referenced by a shared `MatchPtr`.
     matches: ShardedSlab<Addr, MatchData>,
     labels: Mutex<HashMap<LabelId, LabelState>>,
+    // Serializes label cleanups, so concurrent drops of two dups sharing a
+    // label can neither both miss the cleanup nor both perform it.
+    label_cleanup: Mutex<()>,
     // Addresses of nodes whose owning `extension::Handle` was dropped rather than
     // explicitly consumed. Reclaimed by `Executor::erase_dropped_handles`.
     dropped: Mutex<Vec<Addr>>,
+    // While reductions run on several threads (see
+    // `HeapScope::begin_parallel_section`), a thread may read a `Dup` or `Var`
+    // node just before another fires or substitutes it and frees its cell.
+    // Such frees are parked here until the section ends (dups deduplicated, as
+    // a late reader frees the same cell again).
+    parallel: AtomicUsize,
+    retired_dups: Mutex<HashSet<Addr>>,
+    retired_vars: Mutex<Vec<Addr>>,
+    // The source byte range each lowered node was desugared from, for nodes
+    // lowered from a spanned `Expr`. An entry lives as long as its node:
+    // reclaiming the node drops it. `spanned` is set once any entry exists, so
+    // reclaiming nodes of an unspanned program never takes the lock.
+    spans: Mutex<HashMap<Addr, Range<usize>>>,
+    spanned: AtomicBool,
 }
 
 /// A boxed heap value, referenced by a [`ValuePtr`]: payloads too large to pack
@@ -50,24 +73,56 @@ pub struct Pack {
     pub data: Box<[Addr]>,
 }
 
-/// A first-class type object, behind an (affine) [`TypePtr`]. Either a product (an
-/// ordered list of field types) or a sum (named variants). Field/argument types are
-/// **owned, possibly-unevaluated** child nodes in `nodes` (so a type is a value with
-/// lazy structure). `name` is set for builtin/named types and `None` for anonymous
-/// `type { .. }` values.
+/// A first-class type object, behind an (affine) [`TypePtr`]: a structural record
+/// `Record(L, P, O)` of a [`Layout`], a property row and an operator row
+/// (`docs/chapters/06-structural-types.typ`). Field, argument and signature types
+/// are **owned, possibly-unevaluated** child nodes in `nodes` (so a type is a
+/// value with lazy structure).
 #[derive(Debug, Clone)]
-pub enum TypeInfo {
-    Product {
-        name: Option<Arc<str>>,
-        fields: Vec<Addr>,
-    },
-    Sum {
-        name: Option<Arc<str>>,
-        variants: Vec<Variant>,
+pub struct TypeInfo {
+    pub layout: Layout,
+    pub props: Row,
+    pub ops: Row,
+    /// The name a declaration gave the type, if any.
+    pub name: Option<Arc<str>>,
+}
+
+/// The layout component of a [`TypeInfo`].
+#[derive(Debug, Clone)]
+pub enum Layout {
+    /// `*`: no layout requirement. Distinct from the empty product, which
+    /// describes values with zero stored fields.
+    Any,
+    /// An atomic builtin layout (`Int`, `String`, `Type`, ...), by tag.
+    Atom(Arc<str>),
+    /// An ordered list of field types.
+    Product(Vec<Addr>),
+    /// Named variants.
+    Sum(Vec<Variant>),
+    /// A dependent product: the input type, and the codomain as a (lazy)
+    /// function of the argument. `erased` marks a grade-zero argument.
+    Pi {
+        erased: bool,
+        input: Addr,
+        output: Addr,
     },
+    /// A sort: `Prop` or a universe `Type_i`.
+    Sort(Sort),
+}
+
+impl Layout {
+    /// The owned child nodes: field types, or each variant's argument types.
+    pub fn children(&self) -> Vec<Addr> {
+        match self {
+            Layout::Any | Layout::Atom(_) | Layout::Sort(_) => Vec::new(),
+            Layout::Product(fields) => fields.clone(),
+            Layout::Sum(variants) => variants.iter().flat_map(|v| v.args.clone()).collect(),
+            Layout::Pi { input, output, .. } => vec![*input, *output],
+        }
+    }
 }
 
-/// One variant of a [`TypeInfo::Sum`]: a name plus its argument types (owned child
+/// One variant of a [`Layout::Sum`]: a name plus its argument types (owned child
 /// nodes). The argument count is the variant's arity.
 #[derive(Debug, Clone)]
 pub struct Variant {
@@ -75,13 +130,78 @@ pub struct Variant {
     pub args: Vec<Addr>,
 }
 
+/// A property or operator row: signatures by name, and whether the row is
+/// open (has a row variable tail) or closed.
+#[derive(Debug, Clone, Default)]
+pub struct Row {
+    pub entries: Vec<Entry>,
+    pub open: bool,
+}
+
+/// One capability of a [`Row`]. Only the signature is stored: satisfaction
+/// compares signatures, and the core has no property access that would call
+/// an implementation.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub name: VariantId,
+    pub sig: Addr,
+}
+
 impl TypeInfo {
-    /// The display name (builtin/named types only).
+    /// A record with `layout` and closed, empty capability rows: what a
+    /// `type (..)` / `type { .. }` declaration or a builtin type is.
+    pub fn new(layout: Layout) -> Self {
+        TypeInfo {
+            layout,
+            props: Row::default(),
+            ops: Row::default(),
+            name: None,
+        }
+    }
+
+    /// `Any`: no layout, and open, empty rows. It makes no guarantee.
+    pub fn any() -> Self {
+        let open = Row {
+            entries: Vec::new(),
+            open: true,
+        };
+        TypeInfo {
+            layout: Layout::Any,
+            props: open.clone(),
+            ops: open,
+            name: None,
+        }
+    }
+
+    /// The display name: a builtin atomic type's tag, or a declared name.
     pub fn name(&self) -> Option<&Arc<str>> {
-        match self {
-            TypeInfo::Product { name, .. } | TypeInfo::Sum { name, .. } => name.as_ref(),
+        match &self.layout {
+            Layout::Atom(tag) => Some(tag),
+            _ => self.name.as_ref(),
         }
     }
+
+    /// Every owned child node: the layout's, then the property and operator
+    /// signatures.
+    pub fn children(&self) -> Vec<Addr> {
+        let mut out = self.layout.children();
+        out.extend(self.props.entries.iter().map(|e| e.sig));
+        out.extend(self.ops.entries.iter().map(|e| e.sig));
+        out
+    }
+
+    /// [`Self::children`], for rewriting each child address in place.
+    pub fn children_mut(&mut self) -> Vec<&mut Addr> {
+        let mut out: Vec<&mut Addr> = match &mut self.layout {
+            Layout::Any | Layout::Atom(_) | Layout::Sort(_) => Vec::new(),
+            Layout::Product(fields) => fields.iter_mut().collect(),
+            Layout::Sum(variants) => variants.iter_mut().flat_map(|v| &mut v.args).collect(),
+            Layout::Pi { input, output, .. } => vec![input, output],
+        };
+        out.extend(self.props.entries.iter_mut().map(|e| &mut e.sig));
+        out.extend(self.ops.entries.iter_mut().map(|e| &mut e.sig));
+        out
+    }
 }
 
 /// A lowering-time environment entry, indexed by de Bruijn level.
@@ -166,9 +286,28 @@ pub struct DupEval {
 }
 
 pub struct VarCell {
+    /// The occurrence's node address plus one, or [`VAR_CLAIMED`] while the
+    /// occurrence is lifted out of its node or being substituted, or
+    /// [`VAR_SUBSTITUTED`] once substitution has overwritten it, or
+    /// [`VAR_ERASED`] once the occurrence is erased ahead of its lambda.
     addr: AtomicU64,
 }
 
+const VAR_CLAIMED: u64 = 0;
+const VAR_SUBSTITUTED: u64 = u64::MAX;
+const VAR_ERASED: u64 = u64::MAX - 1;
+/// Flag bit: the lambda was erased while its occurrence lives on.
+const VAR_UNBOUND: u64 = 1 << 62;
+
+/// The outcome of writing into a dup projection's parent on its behalf.
+enum SideWrite<'h> {
+    Written,
+    /// The side was dropped; the term is handed back.
+    Dropped(Term<'h>),
+    /// The projection is lifted out of its node (see `HeapScope::detach`).
+    InFlight(Term<'h>),
+}
+
 /// What [`HeapScope::dup_drop_side`] tells `erase` to do next.
 pub enum DupDrop<'h> {
     /// The drop was handled by rewriting the surviving parent (or was a stale
@@ -190,6 +329,25 @@ struct SupCell {
 struct LabelState {
     dups: HashSet<Addr>,
     sups: HashMap<Addr, Addr>,
+    // Superpositions lifted out of their parent node and not yet written back
+    // (see `HeapScope::detach`), and those a label cleanup is rewriting.
+    moving: HashSet<Addr>,
+    collapsing: HashSet<Addr>,
+    // The side a label cleanup dropped while some superposition of the label
+    // was in flight: that one collapses as soon as it is written back.
+    collapsed: Option<bool>,
+    // The dups that kept the last cleanup from collapsing the label, checked
+    // first so a long-lived one doesn't cost a scan of every dup each time.
+    blockers: Vec<Addr>,
+}
+
+impl LabelState {
+    fn is_idle(&self) -> bool {
+        self.dups.is_empty()
+            && self.sups.is_empty()
+            && self.moving.is_empty()
+            && self.collapsing.is_empty()
+    }
 }
 
 // The heap only contains functions for branding.
@@ -205,10 +363,17 @@ impl Heap {
             packs: ShardedSlab::new(),
             types: ShardedSlab::new(),
             names: ShardedSlab::new(),
+            traces: ShardedSlab::new(),
             interner: Mutex::new(HashMap::new()),
             matches: ShardedSlab::new(),
             labels: Mutex::new(HashMap::new()),
+            label_cleanup: Mutex::new(()),
             dropped: Mutex::new(Vec::new()),
+            parallel: AtomicUsize::new(0),
+            retired_dups: Mutex::new(HashSet::new()),
+            retired_vars: Mutex::new(Vec::new()),
+            spans: Mutex::new(HashMap::new()),
+            spanned: AtomicBool::new(false),
         }
     }
 
@@ -245,10 +410,11 @@ pub enum ArenaKind {
     Types,
     Names,
     Matches,
+    Traces,
 }
 
 impl ArenaKind {
-    pub const ALL: [ArenaKind; 9] = [
+    pub const ALL: [ArenaKind; 10] = [
         ArenaKind::Nodes,
         ArenaKind::Dups,
         ArenaKind::Sups,
@@ -258,6 +424,7 @@ impl ArenaKind {
         ArenaKind::Types,
         ArenaKind::Names,
         ArenaKind::Matches,
+        ArenaKind::Traces,
     ];
 
     pub fn label(self) -> &'static str {
@@ -271,10 +438,41 @@ impl ArenaKind {
             ArenaKind::Types => "types",
             ArenaKind::Names => "names",
             ArenaKind::Matches => "matches",
+            ArenaKind::Traces => "traces",
         }
     }
 }
 
+/// What a garbage collection freed: the number of slots of each arena it
+/// shrank (see [`Executor::collect_garbage`](crate::vm::exec::Executor::collect_garbage)).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Reclaimed(pub Vec<(ArenaKind, usize)>);
+
+impl Reclaimed {
+    /// The slots freed from `kind`.
+    pub fn count(&self, kind: ArenaKind) -> usize {
+        (self.0.iter())
+            .find(|&&(k, _)| k == kind)
+            .map_or(0, |&(_, n)| n)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl std::fmt::Display for Reclaimed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return write!(f, "nothing");
+        }
+        let counts: Vec<_> = (self.0.iter())
+            .map(|(kind, n)| format!("{} {n}", kind.label()))
+            .collect();
+        write!(f, "{}", counts.join(", "))
+    }
+}
+
 /// An affine pointer to a heap node. A `TermPtr` is normally a live `UniqueKey`,
 /// but it can also be *null* (`None`): a placeholder that names no slot. Null
 /// pointers are safe to construct and hold (e.g. the [`Spine`] swaps one in for a
@@ -427,6 +625,7 @@ impl<'h> HeapScope<'h> {
     pub fn term(&self, ptr: TermPtr<'h>) -> (TermSlot<'h>, Term<'h>) {
         let nodes = unsafe { self.heap.nodes.forge_brand() };
         let addr = ptr.addr();
+        self.detach(addr);
         let slot = nodes.get_unique(ptr.key());
         let term = unsafe { slot.deref().unpack() };
         (TermSlot { addr, slot }, term)
@@ -479,12 +678,14 @@ impl<'h> HeapScope<'h> {
     /// Reclaim a node, returning its raw packed contents.
     pub fn remove(&self, ptr: TermPtr<'h>) -> Node {
         let nodes = unsafe { self.heap.nodes.forge_brand() };
+        self.forget_span(ptr.addr());
         nodes.remove(ptr.key())
     }
 
     /// Reclaim the node behind a held slot.
     pub fn remove_slot(&self, slot: TermSlot<'h>) -> Node {
         let nodes = unsafe { self.heap.nodes.forge_brand() };
+        self.forget_span(slot.addr);
         nodes.remove(slot.slot.finished())
     }
 
@@ -492,21 +693,64 @@ impl<'h> HeapScope<'h> {
     /// as the parent if the written term is a dup projection or superposition.
     pub fn finish_slot(&self, slot: TermSlot<'h>, term: Term<'h>) -> TermPtr<'h> {
         let addr = slot.addr;
-        self.register_written_term(addr, &term);
-        slot.finished(term)
+        // Write before registering: once registered, other workers may write
+        // through the back-reference, and that write must not be clobbered.
+        let ptr = slot.finished(term);
+        self.register_written_term(addr, &self.view_at(addr));
+        ptr
+    }
+
+    /// Open the slot of a node whose contents were already consumed (a fired
+    /// dup projection, say), to overwrite it.
+    pub fn slot(&self, ptr: TermPtr<'h>) -> TermSlot<'h> {
+        let nodes = unsafe { self.heap.nodes.forge_brand() };
+        let addr = ptr.addr();
+        TermSlot {
+            addr,
+            slot: nodes.get_unique(ptr.key()),
+        }
     }
 
     /// Consume a node: free its slot and return its unpacked term.
     pub fn pull(&self, ptr: TermPtr<'h>) -> Term<'h> {
+        self.detach(ptr.addr());
         // SAFETY: the node was a live `nodes` slot for scope `'h`.
         unsafe { self.remove(ptr).unpack() }
     }
 
     fn swap_node(&self, addr: Addr, term: Term<'h>) {
+        self.swap_node_raw(addr, &term);
+        self.register_written_term(addr, &term);
+    }
+
+    /// Overwrite the node at `addr` without registering the written term.
+    fn swap_node_raw(&self, addr: Addr, term: &Term<'h>) {
         let nodes = unsafe { self.heap.nodes.forge_brand() };
         let key = unsafe { SharedKey::forge(addr) };
         nodes.get(&key).swap(term.pack());
-        self.register_written_term(addr, &term);
+    }
+
+    /// Claim the back-reference of the term at `addr` before it is lifted out
+    /// of its node: a variable's occurrence address, a dup projection's parent,
+    /// or a superposition's parent. Until the term is written into a node again
+    /// (which registers it anew), no other thread writes through that reference;
+    /// if such a write lands first, the node is re-read.
+    fn detach(&self, addr: Addr) {
+        let mut spins = 0usize;
+        loop {
+            let claimed = match &*self.view_at(addr) {
+                Term::Var { cell } => self.claim_var(*cell).is_some(),
+                Term::Dup { ptr, .. } => self.claim_dup_side(*ptr, addr),
+                Term::Sup { label, ptr } => self.claim_sup(*label, ptr.addr()),
+                _ => true,
+            };
+            if claimed {
+                return;
+            }
+            spins += 1;
+            debug_assert!(spins < 1_000_000, "node rewritten but never settled");
+            std::hint::spin_loop();
+        }
     }
 
     /// Move `src` into an existing heap node. If `src` is a `Var`, this updates
@@ -527,6 +771,18 @@ impl<'h> HeapScope<'h> {
         }
     }
 
+    /// Claim dup side `dp` lifted out of `parent`: false if the other side has
+    /// rewritten that node in the meantime (or has yet to register what it wrote).
+    fn claim_dup_side(&self, dp: DupPtr<'h>, parent: Addr) -> bool {
+        let mut meta = self.dup_entry(dp).meta.lock().unwrap();
+        let side = DupMeta::side_index(dp.side());
+        if meta.parents[side] != Some(parent) {
+            return false;
+        }
+        meta.parents[side] = None;
+        true
+    }
+
     fn register_dup_parent(&self, label: LabelId, dp: DupPtr<'h>, parent: Addr) {
         let mut meta = self.dup_entry(dp).meta.lock().unwrap();
         match meta.label {
@@ -552,21 +808,72 @@ impl<'h> HeapScope<'h> {
             slot.label = Some(label);
             let _ = slot.finished();
         }
-        self.heap
-            .labels
-            .lock()
-            .unwrap()
-            .entry(label)
-            .or_default()
-            .sups
-            .insert(sup, parent);
+        let collapse = {
+            let mut labels = self.heap.labels.lock().unwrap();
+            let state = labels.entry(label).or_default();
+            state.moving.remove(&sup);
+            match state.collapsed {
+                Some(dropped) => {
+                    state.collapsing.insert(sup);
+                    Some(dropped)
+                }
+                None => {
+                    state.sups.insert(sup, parent);
+                    None
+                }
+            }
+        };
+        if let Some(dropped) = collapse {
+            let dead = self.collapse_sup(label, sup, parent, dropped);
+            self.register_dropped(dead);
+        }
+    }
+
+    /// Replace the superposition `sup` in `parent` by its surviving branch,
+    /// returning the other one. The caller has moved `sup` into the label's
+    /// `collapsing` set, which keeps other threads off `parent` meanwhile.
+    fn collapse_sup(&self, label: LabelId, sup: Addr, parent: Addr, dropped: bool) -> TermPtr<'h> {
+        let (left, right) = self.free_sup(unsafe { SupPtr::forge(sup) });
+        let (keep, dead) = if dropped {
+            (right, left)
+        } else {
+            (left, right)
+        };
+        self.relocate(keep, parent);
+        let mut labels = self.heap.labels.lock().unwrap();
+        if let Some(state) = labels.get_mut(&label) {
+            state.collapsing.remove(&sup);
+            if state.is_idle() {
+                labels.remove(&label);
+            }
+        }
+        dead
+    }
+
+    /// Claim superposition `sup` lifted out of its parent node: false while a
+    /// label cleanup is rewriting that node.
+    fn claim_sup(&self, label: LabelId, sup: Addr) -> bool {
+        let mut labels = self.heap.labels.lock().unwrap();
+        let Some(state) = labels.get_mut(&label) else {
+            return true;
+        };
+        // Collapsing: cleanup is rewriting the parent. Moving: the sup was
+        // written here but its new parent is not registered yet.
+        if state.collapsing.contains(&sup) || state.moving.contains(&sup) {
+            return false;
+        }
+        if state.sups.remove(&sup).is_some() {
+            state.moving.insert(sup);
+        }
+        true
     }
 
     fn unregister_sup_parent(&self, label: LabelId, sup: Addr) {
         let mut labels = self.heap.labels.lock().unwrap();
         if let Some(state) = labels.get_mut(&label) {
             state.sups.remove(&sup);
-            if state.dups.is_empty() && state.sups.is_empty() {
+            state.moving.remove(&sup);
+            if state.is_idle() {
                 labels.remove(&label);
             }
         }
@@ -652,9 +959,13 @@ impl<'h> HeapScope<'h> {
         types.get(&unsafe { SharedKey::forge(addr) })
     }
 
-    /// A type's display name (builtin/named types only).
+    /// A type's display name (builtin types and sorts only).
     pub fn type_name(&self, addr: Addr) -> Option<Arc<str>> {
-        self.type_info_at(addr).name().cloned()
+        let info = self.type_info_at(addr);
+        match info.layout {
+            Layout::Sort(sort) => Some(Arc::from(sort.to_string())),
+            _ => info.name().cloned(),
+        }
     }
 
     /// Reclaim a type value, returning its [`TypeInfo`] so the caller can erase its
@@ -664,14 +975,10 @@ impl<'h> HeapScope<'h> {
         types.remove(ptr.0)
     }
 
-    /// A fresh opaque named type (e.g. `Int`, `Float`, `Type`), used by `typeof`
-    /// on primitive leaves. Proper builtin type definitions will come from a
-    /// prelude; for now these are empty named products.
+    /// A fresh builtin type (e.g. `Int`, `Float`, `Type`) with an atomic layout,
+    /// used by `typeof` on primitive leaves.
     pub fn builtin_type(&self, name: &str) -> TypePtr<'h> {
-        self.alloc_type(TypeInfo::Product {
-            name: Some(Arc::from(name)),
-            fields: vec![],
-        })
+        self.alloc_type(TypeInfo::new(Layout::Atom(Arc::from(name))))
     }
 
     // ====================================================================
@@ -778,6 +1085,80 @@ impl<'h> HeapScope<'h> {
         let _ = values.remove(ptr.0);
     }
 
+    // ====================================================================
+    // Source spans
+    // ====================================================================
+
+    /// The source byte range the node at `addr` was lowered from, if it was
+    /// lowered from an [`Expr::Spanned`]. A node rewritten in place by an
+    /// interaction keeps the span: it still holds (a reduct of) that source. A
+    /// term moved to another node (an argument substituted for its variable,
+    /// say) leaves its span behind.
+    pub fn span_of(&self, addr: Addr) -> Option<Range<usize>> {
+        if !self.heap.spanned.load(Ordering::Acquire) {
+            return None;
+        }
+        self.heap.spans.lock().unwrap().get(&addr).cloned()
+    }
+
+    /// Forget every recorded span, e.g. before lowering a program whose spans
+    /// index a different source than the live nodes' do.
+    pub fn clear_spans(&self) {
+        self.heap.spans.lock().unwrap().clear();
+    }
+
+    /// Record `span` for the node at `addr`, unless a narrower spanned
+    /// expression lowered to the same node already did.
+    fn record_span(&self, addr: Addr, span: &Range<usize>) {
+        self.heap.spanned.store(true, Ordering::Release);
+        let mut spans = self.heap.spans.lock().unwrap();
+        spans.entry(addr).or_insert_with(|| span.clone());
+    }
+
+    fn forget_span(&self, addr: Addr) {
+        if self.heap.spanned.load(Ordering::Acquire) {
+            self.heap.spans.lock().unwrap().remove(&addr);
+        }
+    }
+
+    // ====================================================================
+    // Traces (runtime error backtraces)
+    // ====================================================================
+
+    pub fn trace(&self, trace: Trace) -> TracePtr<'h> {
+        let traces = unsafe { self.heap.traces.forge_brand() };
+        TracePtr(traces.insert_unique(Arc::new(trace)))
+    }
+
+    pub fn trace_get(&self, ptr: &TracePtr<'h>) -> &'h Trace {
+        let traces = unsafe { self.heap.traces.forge_brand() };
+        let key = unsafe { SharedKey::forge(ptr.addr()) };
+        traces.get(&key)
+    }
+
+    /// Duplicate a trace: share it (an `Arc` bump) from a fresh entry, so each
+    /// copy of the error owns an affine handle.
+    pub fn trace_dup(&self, ptr: &TracePtr<'h>) -> TracePtr<'h> {
+        let traces = unsafe { self.heap.traces.forge_brand() };
+        let key = unsafe { SharedKey::forge(ptr.addr()) };
+        let trace = traces.get(&key).clone();
+        TracePtr(traces.insert_unique(trace))
+    }
+
+    /// Record that the error owning `ptr` bubbled up through `frame`. Copies the
+    /// trace first if a duplicate still shares it.
+    pub fn trace_push(&self, ptr: TracePtr<'h>, frame: Frame) -> TracePtr<'h> {
+        let traces = unsafe { self.heap.traces.forge_brand() };
+        let mut slot = traces.get_unique(ptr.0);
+        Arc::make_mut(&mut slot).frames.push(frame);
+        TracePtr(slot.finished())
+    }
+
+    pub fn trace_drop(&self, ptr: TracePtr<'h>) {
+        let traces = unsafe { self.heap.traces.forge_brand() };
+        let _ = traces.remove(ptr.0);
+    }
+
     // ====================================================================
     // Superpositions
     // ====================================================================
@@ -843,23 +1224,68 @@ impl<'h> HeapScope<'h> {
     }
 
     pub fn var_addr(&self, ptr: VarPtr<'h>) -> Addr {
-        let addr = self.var_cell(ptr).addr.load(Ordering::Acquire);
-        debug_assert_ne!(addr, 0, "variable address is locked by substitution");
+        let addr = self.var_cell(ptr).addr.load(Ordering::Acquire) & !VAR_UNBOUND;
+        debug_assert_ne!(
+            addr, VAR_CLAIMED,
+            "variable address is locked by substitution"
+        );
         Addr::new(addr - 1)
     }
 
     fn set_var_addr(&self, ptr: VarPtr<'h>, addr: Addr) {
-        self.var_cell(ptr)
+        let _ = self
+            .var_cell(ptr)
             .addr
-            .store(addr.to_u64() + 1, Ordering::Release);
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |cur| {
+                Some((cur & VAR_UNBOUND) | (addr.to_u64() + 1))
+            });
+    }
+
+    /// Claim the variable's occurrence address, waiting out another claim (a
+    /// substitution in progress, or the occurrence in flight on another thread),
+    /// and leave the cell claimed. `None` once the variable has been substituted
+    /// or its occurrence erased.
+    fn claim_var(&self, ptr: VarPtr<'h>) -> Option<Addr> {
+        let cell = self.var_cell(ptr);
+        let mut spins = 0usize;
+        loop {
+            let cur = cell.addr.load(Ordering::Acquire);
+            match cur {
+                VAR_SUBSTITUTED | VAR_ERASED => return None,
+                _ if cur & !VAR_UNBOUND == VAR_CLAIMED => {
+                    spins += 1;
+                    debug_assert!(spins < 1_000_000, "variable occurrence claimed forever");
+                    std::hint::spin_loop();
+                }
+                _ => {
+                    let claimed = cur & VAR_UNBOUND;
+                    if cell
+                        .addr
+                        .compare_exchange_weak(cur, claimed, Ordering::AcqRel, Ordering::Acquire)
+                        .is_ok()
+                    {
+                        return Some(Addr::new((cur & !VAR_UNBOUND) - 1));
+                    }
+                }
+            }
+        }
     }
 
-    fn take_var_addr(&self, ptr: VarPtr<'h>) -> Addr {
-        let prev = self.var_cell(ptr).addr.swap(0, Ordering::AcqRel);
-        if prev == 0 {
-            panic!("attempted to substitute a Var while substitution was already in progress");
+    /// Claim the occurrence for substitution: `Err(())` if it has been erased,
+    /// in which case the cell is released and the argument is garbage.
+    fn claim_binder(&self, var: VarPtr<'h>) -> Result<Addr, ()> {
+        match self.claim_var(var) {
+            Some(addr) => Ok(addr),
+            None => {
+                debug_assert_eq!(
+                    self.var_cell(var).addr.load(Ordering::Acquire),
+                    VAR_ERASED,
+                    "attempted to substitute a Var that was already substituted"
+                );
+                self.release_var(var);
+                Err(())
+            }
         }
-        Addr::new(prev - 1)
     }
 
     fn free_var(&self, ptr: VarPtr<'h>) {
@@ -867,17 +1293,78 @@ impl<'h> HeapScope<'h> {
         let _ = vars.remove(unsafe { UniqueKey::forge(ptr.addr()) });
     }
 
+    /// Free a varcell no one will claim again. Inside a [parallel
+    /// section](Self::begin_parallel_section) it is only retired, as a thread
+    /// that read the occurrence just before it was overwritten may still look
+    /// at the cell.
+    fn release_var(&self, ptr: VarPtr<'h>) {
+        if self.in_parallel_section() {
+            self.heap.retired_vars.lock().unwrap().push(ptr.addr());
+        } else {
+            self.free_var(ptr);
+        }
+    }
+
+    /// Erase a variable occurrence (already claimed by the `pull` that lifted it
+    /// out). Its lambda may still be applied later, so the cell stays until
+    /// then and the argument is erased; if the lambda is gone the cell is freed.
     pub fn drop_var(&self, ptr: VarPtr<'h>) {
-        self.free_var(ptr);
+        let cell = self.var_cell(ptr);
+        let prev = cell
+            .addr
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |cur| {
+                (cur == VAR_CLAIMED).then_some(VAR_ERASED)
+            });
+        if prev.is_err() {
+            debug_assert_eq!(
+                cell.addr.load(Ordering::Acquire),
+                VAR_UNBOUND,
+                "dropped a variable occurrence that was not claimed"
+            );
+            self.release_var(ptr);
+        }
+    }
+
+    /// Release the binder of an erased lambda: free the varcell if its
+    /// occurrence is already erased, or mark it unbound so that erasing the
+    /// (scope-escaped) occurrence later frees it.
+    pub fn drop_binder(&self, ptr: VarPtr<'h>) {
+        let prev =
+            self.var_cell(ptr)
+                .addr
+                .fetch_update(Ordering::AcqRel, Ordering::Acquire, |cur| {
+                    (cur != VAR_ERASED && cur != VAR_SUBSTITUTED).then_some(cur | VAR_UNBOUND)
+                });
+        match prev {
+            Err(VAR_ERASED) => self.release_var(ptr),
+            Err(_) => debug_assert!(false, "dropped the binder of a substituted variable"),
+            Ok(_) => {}
+        }
     }
 
     /// APP-LAM's substitution step: claim the lambda's varcell, lock the current
     /// variable occurrence, write `arg` into that occurrence, then free the cell.
-    pub fn substitute(&self, var: VarPtr<'h>, body: TermPtr<'h>, arg: Term<'h>) -> TermPtr<'h> {
-        let addr = self.take_var_addr(var);
-        self.swap_node(addr, arg);
-        self.free_var(var);
-        body
+    /// If the occurrence was erased, the argument is handed back for erasure.
+    pub fn substitute(
+        &self,
+        var: VarPtr<'h>,
+        body: TermPtr<'h>,
+        arg: Term<'h>,
+    ) -> (TermPtr<'h>, Option<Term<'h>>) {
+        // A lambda applied to its own (scope-escaping) variable: the occurrence
+        // is the argument itself, already lifted out, so there is nothing to
+        // write.
+        if !matches!(arg, Term::Var { cell } if cell == var) {
+            let Ok(addr) = self.claim_binder(var) else {
+                return (body, Some(arg));
+            };
+            self.swap_node(addr, arg);
+        }
+        self.var_cell(var)
+            .addr
+            .store(VAR_SUBSTITUTED, Ordering::Release);
+        self.release_var(var);
+        (body, None)
     }
 
     /// Open a lambda without substituting. The returned varcell is a shared
@@ -897,23 +1384,33 @@ impl<'h> HeapScope<'h> {
     /// Allocate a fresh lambda binder and initialize its varcell to the variable
     /// occurrence's node address.
     pub fn fresh_binder(&self) -> (VarPtr<'h>, TermPtr<'h>) {
-        let vars = unsafe { self.heap.vars.forge_brand() };
-        let cell = VarPtr(vars.insert(VarCell {
-            addr: AtomicU64::new(0),
-        }));
+        let cell = self.alloc_var();
         let occ = self.alloc(Term::Var { cell });
-        self.set_var_addr(cell, occ.addr());
         (cell, occ)
     }
 
+    /// Allocate a varcell with no occurrence yet: it reads as claimed until a
+    /// `Var` naming it is written into a node, which records the address.
+    pub fn alloc_var(&self) -> VarPtr<'h> {
+        let vars = unsafe { self.heap.vars.forge_brand() };
+        VarPtr(vars.insert(VarCell {
+            addr: AtomicU64::new(VAR_CLAIMED),
+        }))
+    }
+
     /// Overwrite the current variable occurrence named by `var` without freeing
     /// the varcell. Used by DUP-LAM to install the shared binder sup in the
     /// original lambda's binder occurrence while copied lambdas retain their own
-    /// varcells.
-    pub fn fill_binder(&self, var: VarPtr<'h>, term: Term<'h>) {
-        let addr = self.var_cell(var).addr.load(Ordering::Acquire);
-        debug_assert_ne!(addr, 0, "cannot fill a variable during substitution");
-        self.swap_node(Addr::new(addr - 1), term);
+    /// varcells. Hands `term` back if the occurrence was erased.
+    pub fn fill_binder(&self, var: VarPtr<'h>, term: Term<'h>) -> Option<Term<'h>> {
+        let Ok(addr) = self.claim_binder(var) else {
+            return Some(term);
+        };
+        self.swap_node(addr, term);
+        self.var_cell(var)
+            .addr
+            .store(VAR_SUBSTITUTED, Ordering::Release);
+        None
     }
 
     // ====================================================================
@@ -978,6 +1475,12 @@ impl<'h> HeapScope<'h> {
         self.auto_label(dp.addr())
     }
 
+    /// A fresh label for a superposition family, minted from the cell `ptr`
+    /// as a source superposition's is.
+    pub fn sup_auto_label(&self, ptr: &SupPtr<'h>) -> LabelId {
+        self.sup_label(ptr.addr())
+    }
+
     /// Acquire the duplication cell's eval lock (blocking the other branch until
     /// it is released). The caller inspects `value`: `Some` ⇒ this branch must
     /// reduce and fire it (holding the lock throughout); `None` ⇒ already fired,
@@ -1021,17 +1524,43 @@ impl<'h> HeapScope<'h> {
         _eval: &SingleMutexGuard<'h, DupEval>,
         other: Term<'h>,
     ) -> Result<bool, Term<'h>> {
+        let other_side = unsafe { DupPtr::forge(dp.addr(), !dp.side()) };
+        let mut other = other;
+        let mut spins = 0usize;
+        loop {
+            match self.write_dup_side(other_side, other) {
+                SideWrite::Written => return Ok(self.dup_entry(dp).eval.has_waiter()),
+                SideWrite::Dropped(term) => return Err(term),
+                // Another worker has lifted the projection out of its node;
+                // wait for it to land somewhere.
+                SideWrite::InFlight(term) => {
+                    other = term;
+                    spins += 1;
+                    debug_assert!(spins < 1_000_000, "dup projection never landed");
+                    std::hint::spin_loop();
+                }
+            }
+        }
+    }
+
+    /// Write `term` into the parent node of dup side `dp` on that side's behalf.
+    fn write_dup_side(&self, dp: DupPtr<'h>, term: Term<'h>) -> SideWrite<'h> {
         let mut meta = self.dup_entry(dp).meta.lock().unwrap();
-        if meta.dropped[DupMeta::side_index(!dp.side())] {
-            return Err(other);
+        let side = DupMeta::side_index(dp.side());
+        if meta.dropped[side] {
+            return SideWrite::Dropped(term);
         }
-        let other_parent = meta
-            .parent(!dp.side())
-            .expect("dup side parent was never registered");
+        let Some(parent) = meta.parent(dp.side()) else {
+            return SideWrite::InFlight(term);
+        };
+        // Overwrite under the meta lock, so a concurrent `detach` of the parent
+        // either claims the projection first or sees the rewrite.
+        self.swap_node_raw(parent, &term);
+        meta.parents[side] = None;
         meta.fired = true;
         drop(meta);
-        self.swap_node(other_parent, other);
-        Ok(self.dup_entry(dp).eval.has_waiter())
+        self.register_written_term(parent, &term);
+        SideWrite::Written
     }
 
     /// Whether the OTHER side of `dp` has been dropped (erased). Brief meta
@@ -1041,6 +1570,16 @@ impl<'h> HeapScope<'h> {
         meta.dropped[DupMeta::side_index(!dp.side())]
     }
 
+    /// Whether any superposition of `label` is live, settled or in flight.
+    pub fn label_has_sups(&self, label: LabelId) -> bool {
+        self.heap
+            .labels
+            .lock()
+            .unwrap()
+            .get(&label)
+            .is_some_and(|state| !state.sups.is_empty() || !state.moving.is_empty())
+    }
+
     /// Drop side `dp.side()` of a dup. If the other projection is still live,
     /// rewrite its parent to the inner duplicand and free the dup cell.
     pub fn dup_drop_side(&self, dp: DupPtr<'h>) -> DupDrop<'h> {
@@ -1057,35 +1596,42 @@ impl<'h> HeapScope<'h> {
             .label
             .expect("dup parent was never registered before drop");
         if !meta.dropped[other] {
-            let has_sups = self
-                .heap
-                .labels
-                .lock()
-                .unwrap()
-                .get(&label)
-                .is_some_and(|state| !state.sups.is_empty());
-            if has_sups {
+            if self.label_has_sups(label) {
                 drop(meta);
                 return DupDrop::Recorded {
                     dead: self.try_label_cleanup(label),
                 };
             }
-            let survivor_parent = meta
-                .parent(!dp.side())
-                .expect("dup side parent was never registered");
+            // The other side may be forcing the duplicand right now; it will see
+            // this drop when it fires (or gets stuck) and erase its copy itself.
+            let Some(mut eval) = entry.eval.try_lock() else {
+                return DupDrop::Recorded { dead: Vec::new() };
+            };
             drop(meta);
-            let mut eval = entry
-                .eval
-                .try_lock()
-                .expect("cannot erase one dup side while the duplicand is being forced");
             let addr = eval
                 .value
                 .take()
                 .expect("unfired dup with one side dropped has no duplicand");
-            drop(eval);
-            self.relocate(unsafe { TermPtr::forge(addr) }, survivor_parent);
-            self.free_dup(dp);
-            return DupDrop::Recorded { dead: Vec::new() };
+            let survivor = unsafe { DupPtr::forge(dp.addr(), !dp.side()) };
+            return match self.write_dup_side(survivor, self.pull(unsafe { TermPtr::forge(addr) })) {
+                SideWrite::Written => {
+                    drop(eval);
+                    self.free_dup(dp);
+                    DupDrop::Recorded { dead: Vec::new() }
+                }
+                // The survivor is in flight on another worker: leave the
+                // duplicand for it to force, passing it through uncopied.
+                SideWrite::InFlight(value) => {
+                    eval.value = Some(self.alloc(value).into_addr());
+                    DupDrop::Recorded { dead: Vec::new() }
+                }
+                // The survivor was dropped meanwhile, and its dropper waits on
+                // `eval` to free the cell: the duplicand is ours to reclaim.
+                SideWrite::Dropped(value) => {
+                    drop(eval);
+                    DupDrop::Reclaim(self.alloc(value))
+                }
+            };
         }
         drop(meta);
         let mut spins = 0usize;
@@ -1100,43 +1646,52 @@ impl<'h> HeapScope<'h> {
             );
             std::hint::spin_loop();
         };
-        let addr = eval
-            .value
-            .take()
-            .expect("unfired dup with both sides dropped has no duplicand");
+        let value = eval.value.take();
         drop(eval);
         self.free_dup(dp);
-        DupDrop::Reclaim(unsafe { TermPtr::forge(addr) })
+        match value {
+            Some(addr) => DupDrop::Reclaim(unsafe { TermPtr::forge(addr) }),
+            // The first dropper found this side gone mid-rewrite and reclaims
+            // the duplicand itself.
+            None => DupDrop::Recorded { dead: Vec::new() },
+        }
     }
 
+    /// Collapse every superposition of `label` to the one side all its live
+    /// dups still want. A source superposition's alternatives are the result's
+    /// own, and a dup of its label (copying a function or match for them) that
+    /// drops a side says nothing about which alternative survives, so those
+    /// labels are left alone.
     fn try_label_cleanup(&self, label: LabelId) -> Vec<TermPtr<'h>> {
-        let (dup_addrs, sup_entries) = {
+        if self.is_sup_label(label) {
+            return Vec::new();
+        }
+        let _serial = self.heap.label_cleanup.lock().unwrap();
+        let blockers = {
             let labels = self.heap.labels.lock().unwrap();
             let Some(state) = labels.get(&label) else {
                 return Vec::new();
             };
-            if state.dups.is_empty() || state.sups.is_empty() {
+            if state.dups.is_empty() || (state.sups.is_empty() && state.moving.is_empty()) {
                 return Vec::new();
             }
-            (
-                state.dups.iter().copied().collect::<Vec<_>>(),
-                state
-                    .sups
-                    .iter()
-                    .map(|(&sup, &parent)| (sup, parent))
-                    .collect::<Vec<_>>(),
-            )
+            let live = state.blockers.iter().filter(|dup| state.dups.contains(dup));
+            live.copied().collect::<Vec<_>>()
+        };
+        if self.dups_dropped(&blockers).0 == [false, false] {
+            return Vec::new();
+        }
+        let dup_addrs = {
+            let labels = self.heap.labels.lock().unwrap();
+            let Some(state) = labels.get(&label) else {
+                return Vec::new();
+            };
+            state.dups.iter().copied().collect::<Vec<_>>()
         };
 
-        let mut all_dropped = [true, true];
-        for dup in &dup_addrs {
-            let dp = unsafe { DupPtr::forge(*dup, true) };
-            let meta = self.dup_entry(dp).meta.lock().unwrap();
-            if meta.fired {
-                continue;
-            }
-            all_dropped[0] &= meta.dropped[0];
-            all_dropped[1] &= meta.dropped[1];
+        let (all_dropped, blockers) = self.dups_dropped(&dup_addrs);
+        if let Some(state) = self.heap.labels.lock().unwrap().get_mut(&label) {
+            state.blockers = blockers;
         }
         let dropped = match (all_dropped[0], all_dropped[1]) {
             (true, false) => true,
@@ -1145,44 +1700,57 @@ impl<'h> HeapScope<'h> {
             (false, false) => return Vec::new(),
         };
 
-        let mut evals = Vec::with_capacity(dup_addrs.len());
-        for dup in &dup_addrs {
-            let dp = unsafe { DupPtr::forge(*dup, dropped) };
-            let Some(eval) = self.dup_try_lock(dp) else {
+        // Claim every superposition of the label for rewriting. One lifted out
+        // of its node can't be rewritten now; it collapses once written back.
+        let sup_entries = {
+            let mut labels = self.heap.labels.lock().unwrap();
+            let Some(state) = labels.get_mut(&label) else {
                 return Vec::new();
             };
-            evals.push((*dup, eval));
-        }
+            if !state.moving.is_empty() {
+                state.collapsed = Some(dropped);
+            }
+            let sups: Vec<_> = state.sups.drain().collect();
+            state.collapsing.extend(sups.iter().map(|&(sup, _)| sup));
+            sups
+        };
 
+        // Collapse the superpositions first: one may be a dup's duplicand,
+        // which the dups below then hand through already collapsed.
         let mut dead = Vec::new();
-        for (dup, mut eval) in evals {
-            let dp = unsafe { DupPtr::forge(dup, dropped) };
-            let survivor_parent = {
-                let meta = self.dup_entry(dp).meta.lock().unwrap();
-                if meta.fired {
-                    continue;
-                }
-                meta.parent(!dropped)
-                    .expect("dup side parent was never registered")
+        for &(sup, parent) in &sup_entries {
+            dead.push(self.collapse_sup(label, sup, parent, dropped));
+        }
+
+        // A dup whose cell is locked is being forced; its side is already marked
+        // dropped, so the forcer hands the duplicand through uncopied itself.
+        for dup in &dup_addrs {
+            let dp = unsafe { DupPtr::forge(*dup, dropped) };
+            let Some(mut eval) = self.dup_try_lock(dp) else {
+                continue;
             };
+            let meta = self.dup_entry(dp).meta.lock().unwrap();
+            if meta.fired || !meta.dropped[DupMeta::side_index(dropped)] {
+                continue;
+            }
+            drop(meta);
             let Some(addr) = eval.value.take() else {
                 continue;
             };
-            drop(eval);
-            self.relocate(unsafe { TermPtr::forge(addr) }, survivor_parent);
-            self.free_dup(dp);
-        }
-
-        for (sup_addr, parent) in sup_entries {
-            let sup = unsafe { SupPtr::forge(sup_addr) };
-            let (left, right) = self.free_sup(sup);
-            let (keep, drop) = if dropped {
-                (right, left)
-            } else {
-                (left, right)
-            };
-            self.relocate(keep, parent);
-            dead.push(drop);
+            let survivor = unsafe { DupPtr::forge(*dup, !dropped) };
+            match self.write_dup_side(survivor, self.pull(unsafe { TermPtr::forge(addr) })) {
+                SideWrite::Written => {
+                    drop(eval);
+                    self.free_dup(dp);
+                }
+                SideWrite::InFlight(value) => {
+                    eval.value = Some(self.alloc(value).into_addr());
+                }
+                SideWrite::Dropped(value) => {
+                    drop(eval);
+                    dead.push(self.alloc(value));
+                }
+            }
         }
         dead
     }
@@ -1214,10 +1782,41 @@ impl<'h> HeapScope<'h> {
         (addr, self.view_addr(dp, addr))
     }
 
+    /// Which sides every unfired dup of `dups` has dropped, stopping at the
+    /// first pair of dups that leaves neither side, which are returned.
+    fn dups_dropped(&self, dups: &[Addr]) -> ([bool; 2], Vec<Addr>) {
+        let mut all_dropped = [true, true];
+        let mut kept = [None, None];
+        for &dup in dups {
+            let dp = unsafe { DupPtr::forge(dup, true) };
+            let meta = self.dup_entry(dp).meta.lock().unwrap();
+            if meta.fired {
+                continue;
+            }
+            for side in 0..2 {
+                if !meta.dropped[side] && kept[side].is_none() {
+                    kept[side] = Some(dup);
+                }
+                all_dropped[side] &= meta.dropped[side];
+            }
+            if all_dropped == [false, false] {
+                break;
+            }
+        }
+        let mut blockers: Vec<_> = kept.into_iter().flatten().collect();
+        blockers.dedup();
+        if all_dropped != [false, false] {
+            blockers.clear();
+        }
+        (all_dropped, blockers)
+    }
+
     /// Reclaim a fully-consumed dup cell: by the loser after projecting, by the
     /// winner after a drop-elision, by [`dup_drop_side`] when a drop kills the
     /// cell, or by [`alloc_dup_collapsing`] after absorbing it. The caller must
-    /// hold no guards into the entry.
+    /// hold no guards into the entry. Inside a
+    /// [parallel section](Self::begin_parallel_section) the cell is only
+    /// retired, and freeing it twice is harmless.
     pub fn free_dup(&self, dp: DupPtr<'h>) {
         let label = {
             let meta = self.dup_entry(dp).meta.lock().unwrap();
@@ -1227,15 +1826,157 @@ impl<'h> HeapScope<'h> {
             let mut labels = self.heap.labels.lock().unwrap();
             if let Some(state) = labels.get_mut(&label) {
                 state.dups.remove(&dp.addr());
-                if state.dups.is_empty() && state.sups.is_empty() {
+                if state.is_idle() {
                     labels.remove(&label);
                 }
             }
         }
+        if self.in_parallel_section() {
+            self.heap.retired_dups.lock().unwrap().insert(dp.addr());
+            return;
+        }
         let dups = unsafe { self.heap.dups.forge_brand() };
         let _ = dups.remove(unsafe { UniqueKey::forge(dp.addr()) });
     }
 
+    /// Enter a section in which reductions may run on several threads at once.
+    /// Until the matching [`end_parallel_section`](Self::end_parallel_section),
+    /// dup and variable cells are retired rather than freed, since another
+    /// thread may be about to lock one it has just read a projection or an
+    /// occurrence of. Sections nest.
+    pub fn begin_parallel_section(&self) {
+        self.heap.parallel.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Leave a [parallel section](Self::begin_parallel_section). Leaving the
+    /// outermost one frees every retired cell, so the caller must then be the
+    /// only thread touching the heap.
+    pub fn end_parallel_section(&self) {
+        if self.heap.parallel.fetch_sub(1, Ordering::AcqRel) != 1 {
+            return;
+        }
+        let dups = unsafe { self.heap.dups.forge_brand() };
+        for addr in std::mem::take(&mut *self.heap.retired_dups.lock().unwrap()) {
+            let _ = dups.remove(unsafe { UniqueKey::forge(addr) });
+        }
+        let vars = unsafe { self.heap.vars.forge_brand() };
+        for addr in std::mem::take(&mut *self.heap.retired_vars.lock().unwrap()) {
+            let _ = vars.remove(unsafe { UniqueKey::forge(addr) });
+        }
+    }
+
+    /// How many nodes the subterm at `ptr` has, if it can be reduced without
+    /// touching any node outside it: it holds no superposition, every variable
+    /// in it is bound by a lambda in it, and every dup reachable from it has
+    /// its live projections, its duplicand and the other dups of its label in
+    /// it too. Only the first `limit` nodes are scanned; a larger subterm
+    /// counts as not isolated.
+    pub fn isolated_size(&self, ptr: &TermPtr<'h>, limit: usize) -> Option<usize> {
+        let mut stack = vec![ptr.addr()];
+        let mut binders = HashSet::new();
+        let mut vars = Vec::new();
+        // Per dup cell: its label, live projections, and projections seen.
+        let mut dups: HashMap<Addr, (Option<LabelId>, usize, usize)> = HashMap::new();
+        let mut seen = 0;
+        while let Some(addr) = stack.pop() {
+            seen += 1;
+            if seen > limit {
+                return None;
+            }
+            match &*self.view_at(addr) {
+                Term::Sup { .. } => return None,
+                Term::Var { cell } => vars.push(cell.addr()),
+                Term::Dup { ptr: dp, .. } => {
+                    if let Some(entry) = dups.get_mut(&dp.addr()) {
+                        entry.2 += 1;
+                        continue;
+                    }
+                    let cell = self.dup_entry(*dp);
+                    let (label, live) = {
+                        let meta = cell.meta.lock().unwrap();
+                        if meta.fired {
+                            return None;
+                        }
+                        (meta.label, meta.dropped.iter().filter(|d| !**d).count())
+                    };
+                    let eval = cell.eval.try_lock()?;
+                    stack.extend(eval.value);
+                    dups.insert(dp.addr(), (label, live, 1));
+                }
+                Term::Lam { var, body } => {
+                    binders.insert(var.addr());
+                    stack.push(body.addr());
+                }
+                Term::App { func: a, arg: b }
+                | Term::Bop { lhs: a, rhs: b, .. }
+                | Term::Prj { val: a, ty: b }
+                | Term::And { lhs: a, rhs: b }
+                | Term::Or { lhs: a, rhs: b } => stack.extend([a.addr(), b.addr()]),
+                Term::Use { body: a } | Term::Uop { val: a, .. } | Term::Ctr { ty: a, .. } => {
+                    stack.push(a.addr())
+                }
+                Term::Partial { func, args, .. } => {
+                    stack.push(func.addr());
+                    stack.extend(
+                        (0..self.pack_len(args)).map(|i| self.pack_field(args, i).into_addr()),
+                    );
+                }
+                Term::Ctn { ty, values, .. } => {
+                    stack.extend(self.type_info(ty).children());
+                    stack.extend(
+                        (0..self.pack_len(values)).map(|i| self.pack_field(values, i).into_addr()),
+                    );
+                }
+                Term::Type(ty) => stack.extend(self.type_info(ty).children()),
+                Term::Mat { matches } => {
+                    let data = self.match_data(matches);
+                    stack.extend(data.cases.iter().flat_map(|&(k, v)| [k, v]));
+                    stack.extend(data.default);
+                }
+                _ => {}
+            }
+        }
+        if !vars.iter().all(|v| binders.contains(v))
+            || dups.values().any(|&(_, live, seen)| seen != live)
+        {
+            return None;
+        }
+        let labels = self.heap.labels.lock().unwrap();
+        let isolated = dups.values().filter_map(|&(label, ..)| label).all(|label| {
+            labels.get(&label).is_none_or(|state| {
+                state.sups.is_empty()
+                    && state.moving.is_empty()
+                    && state.collapsing.is_empty()
+                    && state.dups.iter().all(|d| dups.contains_key(d))
+            })
+        });
+        isolated.then_some(seen)
+    }
+
+    fn in_parallel_section(&self) -> bool {
+        self.heap.parallel.load(Ordering::Acquire) > 0
+    }
+
+    // ====================================================================
+    // Copying between heaps
+    // ====================================================================
+
+    /// Copy the closed term at `ptr`, with its duplications, superpositions
+    /// and types and the sharing between them, into a [`PortableTerm`] that
+    /// outlives this heap's brand. `ptr` itself is only read. Fails with
+    /// [`ImageError::FreeVariable`] if an occurrence's lambda is outside the
+    /// term, and with [`ImageError::InFlight`] while a duplication in it is
+    /// being forced.
+    pub fn export(&self, ptr: &TermPtr<'h>) -> Result<PortableTerm, ImageError> {
+        PortableTerm::capture(self, ptr)
+    }
+
+    /// Allocate a copy of `term` in this heap. Every import mints fresh
+    /// labels, so two copies in one heap never annihilate each other.
+    pub fn import(&self, term: &PortableTerm) -> TermPtr<'h> {
+        term.restore(self)
+    }
+
     // ====================================================================
     // Lowering: desugared core `Expr` -> heap term graph
     // ====================================================================
@@ -1249,7 +1990,7 @@ impl<'h> HeapScope<'h> {
         expr: &Expr,
         resolve: &dyn Fn(&str) -> Option<PrimId>,
         local: &mut dyn FnMut(&str) -> Option<TermPtr<'h>>,
-    ) -> Result<TermPtr<'h>, String> {
+    ) -> Result<TermPtr<'h>, LowerError> {
         let mut env: Vec<LowerFrame> = Vec::new();
         self.lower_env(expr, &mut env, resolve, local)
     }
@@ -1261,6 +2002,19 @@ impl<'h> HeapScope<'h> {
         LabelId::from_u56(self.intern_name(&format!("&auto#{}", unique.to_u64())))
     }
 
+    /// Mint the label of a source superposition `&{a, b}` from its cell
+    /// address. Sup cells and dup cells live in separate arenas, so this is
+    /// kept apart from [`auto_label`](Self::auto_label): a dup sharing the
+    /// cell's address must not annihilate with it.
+    fn sup_label(&self, unique: Addr) -> LabelId {
+        LabelId::from_u56(self.intern_name(&format!("&sup#{}", unique.to_u64())))
+    }
+
+    /// Whether `label` was minted by [`sup_label`](Self::sup_label).
+    fn is_sup_label(&self, label: LabelId) -> bool {
+        self.name_of(U56::new(label.get())).starts_with("&sup#")
+    }
+
     /// Lower a builtin [`CoreValue`] into a heap term: scalars become value
     /// leaves; strings and byte arrays become boxed heap [`Boxed`] values.
     fn lower_value(&self, v: &CoreValue) -> TermPtr<'h> {
@@ -1286,37 +2040,51 @@ impl<'h> HeapScope<'h> {
         env: &mut Vec<LowerFrame>,
         resolve: &dyn Fn(&str) -> Option<PrimId>,
         local: &mut dyn FnMut(&str) -> Option<TermPtr<'h>>,
-    ) -> Result<TermPtr<'h>, String> {
+    ) -> Result<TermPtr<'h>, LowerError> {
         Ok(match expr {
             Expr::Value(v) => self.lower_value(v),
             Expr::Wld => self.alloc(Term::Wld),
             Expr::Era => self.alloc(Term::Wld),
             Expr::Pri(name) => match resolve(name) {
                 Some(id) => self.alloc(Term::Pri(id)),
-                None => return Err(format!("unknown primitive %{name}")),
+                None => return Err(LowerError::UnknownPrimitive(name.clone())),
             },
             Expr::Var(db) => match self.frame(env, db.0)? {
                 LowerFrame::Binder(addr) => unsafe { TermPtr::forge(addr) },
-                _ => return Err("variable does not refer to a lambda binder".into()),
+                _ => {
+                    return Err(LowerError::IllScoped(
+                        "variable does not refer to a lambda binder".into(),
+                    ));
+                }
             },
             Expr::Dp0(db) => match self.frame(env, db.0)? {
                 LowerFrame::Dup { key, label, .. } => self.alloc(Term::Dup {
                     label,
                     ptr: unsafe { DupPtr::forge(key, true) },
                 }),
-                _ => return Err("Dp0 does not refer to a duplication".into()),
+                _ => {
+                    return Err(LowerError::IllScoped(
+                        "Dp0 does not refer to a duplication".into(),
+                    ));
+                }
             },
             Expr::Dp1(db) => match self.frame(env, db.0)? {
                 LowerFrame::Dup { key, label, .. } => self.alloc(Term::Dup {
                     label,
                     ptr: unsafe { DupPtr::forge(key, false) },
                 }),
-                _ => return Err("Dp1 does not refer to a duplication".into()),
+                _ => {
+                    return Err(LowerError::IllScoped(
+                        "Dp1 does not refer to a duplication".into(),
+                    ));
+                }
             },
             Expr::Ref(db) => {
                 let i = db.0 as usize;
                 if i >= env.len() {
-                    return Err(format!("de Bruijn index {i} out of range"));
+                    return Err(LowerError::IllScoped(format!(
+                        "de Bruijn index {i} out of range"
+                    )));
                 }
                 let frame = env.len() - 1 - i;
                 match &mut env[frame] {
@@ -1324,7 +2092,11 @@ impl<'h> HeapScope<'h> {
                         let side = match *refs {
                             0 => true,
                             1 => false,
-                            _ => return Err("binary duplication used more than twice".into()),
+                            _ => {
+                                return Err(LowerError::IllScoped(
+                                    "binary duplication used more than twice".into(),
+                                ));
+                            }
                         };
                         *refs += 1;
                         self.alloc(Term::Dup {
@@ -1332,7 +2104,11 @@ impl<'h> HeapScope<'h> {
                             ptr: unsafe { DupPtr::forge(*key, side) },
                         })
                     }
-                    _ => return Err("Ref does not refer to a duplication".into()),
+                    _ => {
+                        return Err(LowerError::IllScoped(
+                            "Ref does not refer to a duplication".into(),
+                        ));
+                    }
                 }
             }
             Expr::App { func, arg } => {
@@ -1353,6 +2129,11 @@ impl<'h> HeapScope<'h> {
                 let v = self.lower_env(val, env, resolve, local)?;
                 self.alloc(Term::Uop { op: *op, val: v })
             }
+            Expr::Project { value, ty } => {
+                let v = self.lower_env(value, env, resolve, local)?;
+                let t = self.lower_env(ty, env, resolve, local)?;
+                self.alloc(Term::Prj { val: v, ty: t })
+            }
             Expr::Lam { body } => {
                 let (var, occ) = self.fresh_binder();
                 env.push(LowerFrame::Binder(occ.into_addr()));
@@ -1372,7 +2153,7 @@ impl<'h> HeapScope<'h> {
                 let a = self.lower_env(left, env, resolve, local)?;
                 let b = self.lower_env(right, env, resolve, local)?;
                 let ptr = self.sup(a, b);
-                let label = self.auto_label(ptr.addr());
+                let label = self.sup_label(ptr.addr());
                 self.alloc(Term::Sup { label, ptr })
             }
             Expr::Dup { val, body } => {
@@ -1401,16 +2182,19 @@ impl<'h> HeapScope<'h> {
                     variant: variant.as_ref().map(|n| self.intern_variant(n)),
                 })
             }
-            Expr::TypeDef { kind } => {
+            Expr::TypeDef {
+                name: declared,
+                kind,
+            } => {
                 // A `type { .. }` lowers directly to a fresh type *value* whose
                 // field/arg sub-types are owned, *unevaluated* child nodes.
-                let info = match kind {
+                let layout = match kind {
                     crate::core::expr::TypeDefKind::Product(members) => {
                         let mut fields = Vec::with_capacity(members.len());
                         for m in members {
                             fields.push(self.lower_env(m, env, resolve, local)?.into_addr());
                         }
-                        TypeInfo::Product { name: None, fields }
+                        Layout::Product(fields)
                     }
                     crate::core::expr::TypeDefKind::Sum(variants) => {
                         let mut vs = Vec::with_capacity(variants.len());
@@ -1424,14 +2208,30 @@ impl<'h> HeapScope<'h> {
                                 args: aa,
                             });
                         }
-                        TypeInfo::Sum {
-                            name: None,
-                            variants: vs,
-                        }
+                        Layout::Sum(vs)
                     }
                 };
+                let mut info = TypeInfo::new(layout);
+                info.name = declared.as_deref().map(Arc::from);
                 self.alloc(Term::Type(self.alloc_type(info)))
             }
+            Expr::Pi {
+                erased,
+                input,
+                output,
+            } => {
+                let input = self.lower_env(input, env, resolve, local)?.into_addr();
+                let output = self.lower_env(output, env, resolve, local)?.into_addr();
+                let layout = Layout::Pi {
+                    erased: *erased,
+                    input,
+                    output,
+                };
+                self.alloc(Term::Type(self.alloc_type(TypeInfo::new(layout))))
+            }
+            Expr::Sort(sort) => self.alloc(Term::Type(
+                self.alloc_type(TypeInfo::new(Layout::Sort(*sort))),
+            )),
             Expr::Mat { cases, default } => {
                 let mut compiled = Vec::with_capacity(cases.len());
                 for (pat, body) in cases {
@@ -1458,15 +2258,26 @@ impl<'h> HeapScope<'h> {
             }
             Expr::Free(name) => match local(name) {
                 Some(ptr) => ptr,
-                None => return Err(format!("unbound variable `{name}`")),
+                None => return Err(LowerError::UnboundVariable(name.clone())),
             },
+            Expr::Spanned { span, body } => {
+                let ptr = self.lower_env(body, env, resolve, local)?;
+                // A variable lowers to a node that is not its own: a lambda
+                // binder's occurrence, or a REPL local's existing root.
+                if !matches!(body.unspanned(), Expr::Var(_) | Expr::Free(_)) {
+                    self.record_span(ptr.addr(), span);
+                }
+                ptr
+            }
         })
     }
 
-    fn frame(&self, env: &[LowerFrame], i: u64) -> Result<LowerFrame, String> {
+    fn frame(&self, env: &[LowerFrame], i: u64) -> Result<LowerFrame, LowerError> {
         let i = i as usize;
         if i >= env.len() {
-            return Err(format!("de Bruijn index {i} out of range"));
+            return Err(LowerError::IllScoped(format!(
+                "de Bruijn index {i} out of range"
+            )));
         }
         Ok(env[env.len() - 1 - i])
     }
@@ -1488,6 +2299,7 @@ impl<'h> HeapScope<'h> {
             ArenaKind::Types => self.heap.types.len(),
             ArenaKind::Names => self.heap.names.len(),
             ArenaKind::Matches => self.heap.matches.len(),
+            ArenaKind::Traces => self.heap.traces.len(),
         }
     }
 
@@ -1527,8 +2339,9 @@ impl<'h> HeapScope<'h> {
                 out.extend([lhs.addr(), rhs.addr()])
             }
             Term::Uop { val, .. } => out.push(val.addr()),
-            // Leaves. `Err` backtraces are currently always `None` and ignored
-            // by erasure; `Box` points into the values arena, not `nodes`.
+            Term::Prj { val, ty } => out.extend([val.addr(), ty.addr()]),
+            // Leaves. An `Err` backtrace points into the traces arena and a
+            // `Box` into the values arena, not `nodes`.
             Term::Var { .. }
             | Term::VarId(_)
             | Term::Wld
@@ -1545,12 +2358,7 @@ impl<'h> HeapScope<'h> {
 
     /// Append a [`TypeInfo`]'s field / variant-argument node addresses to `out`.
     fn type_children(&self, ty_addr: Addr, out: &mut Vec<Addr>) {
-        match self.type_info_at(ty_addr) {
-            TypeInfo::Product { fields, .. } => out.extend(fields.iter().copied()),
-            TypeInfo::Sum { variants, .. } => {
-                out.extend(variants.iter().flat_map(|v| v.args.iter().copied()))
-            }
-        }
+        out.extend(self.type_info_at(ty_addr).children());
     }
 
     /// Mark every node reachable from `roots` into `marked`, restricted to
@@ -1576,24 +2384,9 @@ impl<'h> HeapScope<'h> {
         }
     }
 
-    /// Find the terms unreachable from `roots` and return forged owning
-    /// pointers to the roots of each leaked subgraph. Every leaked node is
-    /// reachable from the returned pointers: subgraphs with no in-edge get
-    /// their in-degree-0 head; a leaked *cycle* has none, so an arbitrary
-    /// member is elected to represent it (erasing a cycle representative is
-    /// not supported — other cycle members still reference it).
-    ///
-    /// # Safety
-    ///
-    /// `roots` must include *every* externally held [`TermPtr`] into this heap
-    /// (REPL locals, a pending evaluation root, the last result, pointers
-    /// previously returned by this function, ...). Reduction must be idle (the
-    /// readback precondition). Under that assumption, any live node not
-    /// reachable from `roots` has no owner, so forging a pointer to it cannot
-    /// alias an existing one.
-    pub unsafe fn find_leaked_roots(&self, roots: &[&TermPtr<'h>]) -> Vec<TermPtr<'h>> {
-        // Everything reachable from the external roots is owned. The dropped
-        // queue owns its addresses until reclaimed, so it counts as a root.
+    /// The live nodes not reachable from `roots`. The dropped queue owns its
+    /// addresses until reclaimed, so it counts as a root.
+    fn unreachable_nodes(&self, roots: &[&TermPtr<'h>]) -> HashSet<Addr> {
         let mut reachable = HashSet::new();
         let external = roots
             .iter()
@@ -1603,25 +2396,29 @@ impl<'h> HeapScope<'h> {
             .collect::<Vec<_>>();
         self.mark_reachable(external, None, &mut reachable);
 
-        let unreachable: HashSet<Addr> = self
-            .heap
+        self.heap
             .nodes
             .live_keys()
             .into_iter()
             .filter(|a| !reachable.contains(a))
-            .collect();
+            .collect()
+    }
 
+    /// The heads of the subgraphs `unreachable` splits into: its members no
+    /// other member references, then, to cover any cycles, the smallest
+    /// member each time one is left uncovered. With `cut`, that member is
+    /// [evicted](Self::evict) first, so the head elected for it is a fresh
+    /// node nothing else references and its old address is left a `Null`.
+    fn leaked_heads(&self, unreachable: &mut HashSet<Addr>, cut: bool) -> Vec<Addr> {
         // Reference edges among the unreachable set (children of reachable
         // nodes are themselves reachable, so only intra-set edges matter).
         let mut referenced = HashSet::new();
         let mut children = Vec::new();
-        for &addr in &unreachable {
+        for &addr in &*unreachable {
             self.node_children(addr, &mut children);
             referenced.extend(children.drain(..));
         }
 
-        // In-degree-0 members head the leaked subgraphs; cover any remaining
-        // cycles by electing their smallest uncovered member.
         let mut heads: Vec<Addr> = unreachable
             .iter()
             .copied()
@@ -1629,23 +2426,163 @@ impl<'h> HeapScope<'h> {
             .collect();
         heads.sort_unstable();
         let mut covered = HashSet::new();
-        self.mark_reachable(heads.iter().copied(), Some(&unreachable), &mut covered);
+        self.mark_reachable(heads.iter().copied(), Some(unreachable), &mut covered);
         while covered.len() < unreachable.len() {
-            let rep = unreachable
+            let mut rep = unreachable
                 .iter()
                 .copied()
                 .filter(|a| !covered.contains(a))
                 .min()
                 .expect("uncovered leaked node");
+            if cut {
+                covered.insert(rep);
+                rep = self.evict(rep);
+                unreachable.insert(rep);
+            }
             heads.push(rep);
-            self.mark_reachable([rep], Some(&unreachable), &mut covered);
+            self.mark_reachable([rep], Some(unreachable), &mut covered);
         }
-
         heads
+    }
+
+    /// Move the term at `addr` into a fresh node, leaving a `Null` behind for
+    /// whatever still references `addr`. Readback-only, like
+    /// [`node_children`](Self::node_children).
+    fn evict(&self, addr: Addr) -> Addr {
+        // SAFETY: the node is leaked, so no pointer to it is held outside the
+        // heap; the one forged here is handed straight back as a slot.
+        let (slot, term) = self.term(unsafe { TermPtr::forge(addr) });
+        let _ = self.finish_slot(slot, Term::Null);
+        self.alloc(term).into_addr()
+    }
+
+    /// Find the terms unreachable from `roots` and return forged owning
+    /// pointers to the roots of each leaked subgraph. Every leaked node is
+    /// reachable from the returned pointers: subgraphs with no in-edge get
+    /// their in-degree-0 head; a leaked *cycle* has none, so an arbitrary
+    /// member is elected to represent it (erasing a cycle representative is
+    /// not supported — other cycle members still reference it; see
+    /// [`take_garbage`](Self::take_garbage) for a version that cuts cycles).
+    ///
+    /// # Safety
+    ///
+    /// `roots` must include *every* externally held [`TermPtr`] into this heap
+    /// (REPL locals, a pending evaluation root, the last result, pointers
+    /// previously returned by this function, ...). Reduction must be idle (the
+    /// readback precondition). Under that assumption, any live node not
+    /// reachable from `roots` has no owner, so forging a pointer to it cannot
+    /// alias an existing one.
+    pub unsafe fn find_leaked_roots(&self, roots: &[&TermPtr<'h>]) -> Vec<TermPtr<'h>> {
+        let mut unreachable = self.unreachable_nodes(roots);
+        self.leaked_heads(&mut unreachable, false)
             .into_iter()
             .map(|addr| unsafe { TermPtr::forge(addr) })
             .collect()
     }
+
+    /// Like [`find_leaked_roots`](Self::find_leaked_roots), but first cut
+    /// every leaked cycle, so that erasing the returned pointers reclaims all
+    /// of it. A leaked node mostly reaches itself through a duplication whose
+    /// duplicand is leaked too, so each such cell has its duplicand swapped
+    /// for a `Null` and the duplicand is returned as a subgraph of its own.
+    /// Its projections then erase as they would have: both are leaked, so the
+    /// duplicand was never going to be read. A cycle left after that, one
+    /// reduction tied through owning edges alone (a `fix` unrolled into
+    /// itself, say), is cut by evicting the member elected to head it.
+    ///
+    /// # Safety
+    ///
+    /// As for [`find_leaked_roots`](Self::find_leaked_roots).
+    pub unsafe fn take_garbage(&self, roots: &[&TermPtr<'h>]) -> Vec<TermPtr<'h>> {
+        let mut unreachable = self.unreachable_nodes(roots);
+        for &addr in &unreachable {
+            let Term::Dup { ptr, .. } = *self.view_at(addr) else {
+                continue;
+            };
+            // A projection left in the heap must not be locked at readback.
+            let mut eval = self
+                .dup_try_lock(ptr)
+                .expect("dup cell uncontended at readback");
+            // Once cut, the cell's `Null` is not in the set, so the other
+            // projection leaves it alone.
+            if eval.value.is_some_and(|value| unreachable.contains(&value)) {
+                eval.value = Some(self.alloc(Term::Null).into_addr());
+            }
+        }
+        self.leaked_heads(&mut unreachable, true)
+            .into_iter()
+            .map(|addr| unsafe { TermPtr::forge(addr) })
+            .collect()
+    }
+
+    /// Append the cells the node at `addr` holds to `out`, by arena.
+    fn node_cells(&self, addr: Addr, out: &mut Vec<(ArenaKind, Addr)>) {
+        match &*self.view_at(addr) {
+            Term::Var { cell: var } | Term::Lam { var, .. } => {
+                out.push((ArenaKind::Vars, var.addr()))
+            }
+            Term::Dup { ptr, .. } => out.push((ArenaKind::Dups, ptr.addr())),
+            Term::Sup { ptr, .. } => out.push((ArenaKind::Sups, ptr.addr())),
+            Term::Ctn { ty, values, .. } => out.extend([
+                (ArenaKind::Types, ty.addr()),
+                (ArenaKind::Packs, values.addr()),
+            ]),
+            Term::Partial { args, .. } => out.push((ArenaKind::Packs, args.addr())),
+            Term::Type(ty) => out.push((ArenaKind::Types, ty.addr())),
+            Term::Mat { matches } => out.push((ArenaKind::Matches, matches.addr())),
+            Term::Box(value) => out.push((ArenaKind::Values, value.addr())),
+            Term::Err {
+                backtrace: Some(trace),
+                ..
+            } => out.push((ArenaKind::Traces, trace.addr())),
+            _ => {}
+        }
+    }
+
+    /// Free every cell no live node holds: what is left of a cell's owner
+    /// once [`take_garbage`](Self::take_garbage)'s subgraphs are erased, or
+    /// a cell that never had one. Names are interned for good, and are kept.
+    ///
+    /// # Safety
+    ///
+    /// Reduction must be idle, and no cell pointer may be held outside a node.
+    pub unsafe fn sweep_cells(&self) {
+        let mut held = HashSet::new();
+        let mut cells = Vec::new();
+        for addr in self.heap.nodes.live_keys() {
+            self.node_cells(addr, &mut cells);
+            held.extend(cells.drain(..));
+        }
+        let orphans = |kind: ArenaKind, keys: Vec<Addr>| {
+            let held = &held;
+            keys.into_iter()
+                .filter(move |&addr| !held.contains(&(kind, addr)))
+        };
+        for addr in orphans(ArenaKind::Dups, self.heap.dups.live_keys()) {
+            self.free_dup(unsafe { DupPtr::forge(addr, true) });
+        }
+        for addr in orphans(ArenaKind::Sups, self.heap.sups.live_keys()) {
+            let _ = self.free_sup(unsafe { SupPtr::forge(addr) });
+        }
+        for addr in orphans(ArenaKind::Vars, self.heap.vars.live_keys()) {
+            self.free_var(unsafe { VarPtr::forge(addr) });
+        }
+        for addr in orphans(ArenaKind::Values, self.heap.values.live_keys()) {
+            self.value_drop(unsafe { ValuePtr::forge(addr) });
+        }
+        for addr in orphans(ArenaKind::Packs, self.heap.packs.live_keys()) {
+            let _ = self.free_pack(unsafe { PackPtr::forge(addr) });
+        }
+        for addr in orphans(ArenaKind::Types, self.heap.types.live_keys()) {
+            let _ = self.free_type(unsafe { TypePtr::forge(addr) });
+        }
+        for addr in orphans(ArenaKind::Matches, self.heap.matches.live_keys()) {
+            self.free_match(unsafe { MatchPtr::forge(addr) });
+        }
+        for addr in orphans(ArenaKind::Traces, self.heap.traces.live_keys()) {
+            self.trace_drop(unsafe { TracePtr::forge(addr) });
+        }
+    }
 }
 
 // Contains the spine of a reduction.
@@ -1711,10 +2648,11 @@ impl<'h> Spine<'h> {
     /// `Err(root)` when the spine is empty (the head is the final result).
     pub fn unwind(
         &mut self,
+        heap: &HeapScope<'h>,
         slot: TermSlot<'h>,
         term: Term<'h>,
     ) -> Result<(TermSlot<'h>, Term<'h>), TermPtr<'h>> {
-        let child = slot.finished(term);
+        let child = heap.finish_slot(slot, term);
         match self.terms.pop() {
             None => Err(child),
             Some((pslot, Term::App { func, arg })) => {
@@ -1799,6 +2737,39 @@ mod tests {
         });
     }
 
+    #[test]
+    fn lowered_nodes_carry_their_spans_until_reclaimed() {
+        let heap = Heap::new();
+        heap.with(|h| {
+            let spanned = |span, body| Expr::Spanned {
+                span,
+                body: Box::new(body),
+            };
+            // `1 + x`, with `x` a REPL local whose node is not this source's.
+            let expr = spanned(
+                0..5,
+                Expr::Bop {
+                    op: crate::vm::term::BinaryOp::Add,
+                    left: Box::new(spanned(0..1, Expr::Value(CoreValue::Int(1)))),
+                    right: Box::new(spanned(4..5, Expr::Free("x".into()))),
+                },
+            );
+            let mut x = Some(h.alloc(Term::Int(2)));
+            let x_addr = x.as_ref().unwrap().addr();
+            let root = h.lower(&expr, &|_| None, &mut |_| x.take()).unwrap();
+            assert_eq!(h.span_of(root.addr()), Some(0..5));
+            assert_eq!(h.span_of(x_addr), None);
+            let Term::Bop { lhs, rhs, .. } = h.pull(root) else {
+                panic!()
+            };
+            assert_eq!(h.span_of(lhs.addr()), Some(0..1));
+            let lhs_addr = lhs.addr();
+            h.remove(lhs);
+            h.remove(rhs);
+            assert_eq!(h.span_of(lhs_addr), None);
+        });
+    }
+
     #[test]
     fn pack_field_access() {
         let heap = Heap::new();
@@ -0,0 +1,1600 @@
+//! Heap images: the region of a heap reachable from a set of roots, as data
+//! that can be written to bytes and rebuilt into another heap.
+//!
+//! [`Image::capture`] walks the region like the printer does, only viewing
+//! the graph, and renumbers everything it meets: nodes by their index in the
+//! image's node table, duplication and variable cells by their own tables,
+//! and labels by family. [`Image::restore`] allocates the region afresh, so
+//! one image can be restored any number of times, into any heap.
+//!
+//! Some things are not carried over:
+//!
+//! - each label family gets a fresh label when restored, so it cannot
+//!   annihilate with a family already in the heap (or another restore of the
+//!   same image);
+//! - a duplication whose other projection is outside the region is restored
+//!   with that projection dropped, and likewise a lambda whose occurrence is
+//!   outside is restored as if the occurrence were erased, and an occurrence
+//!   whose lambda is outside as unbound;
+//! - an `Err` loses its backtrace, and nodes lose their source spans, which
+//!   index a source the image does not hold.
+//!
+//! # Format
+//!
+//! [`Image::encode`] writes, after the magic `ATLASIMG` and the format
+//! [`VERSION`] (a little-endian `u32`), a sequence of tables. Counts, indices
+//! and lengths are LEB128 varints; ints and floats are 8 little-endian bytes.
+//!
+//! 1. the interned names (variant names), then the primitive names, each a
+//!    length-prefixed UTF-8 string. Primitives are saved by name, since ids
+//!    belong to an extension set, and resolved by the reader's;
+//! 2. the number of variable cells and of label families;
+//! 3. the duplication cells, each the index of its duplicand;
+//! 4. the nodes, each a record kind byte and its fields;
+//! 5. the roots.
+//!
+//! [`Image::decode`] checks that every index is in range and every node has
+//! exactly one owner before anything is allocated.
+//!
+//! A [`PortableTerm`] is an image of one closed term kept in memory, for
+//! copying a term between heaps without going through bytes.
+
+use crate::error::ImageError;
+use crate::extension::Extensions;
+use crate::vm::heap::{
+    Addr, Boxed, DupDrop, DupPtr, Entry, HeapScope, Layout, MatchData, Row, TermPtr, TypeInfo,
+    Variant,
+};
+use crate::vm::term::{BinaryOp, LabelId, PrimId, Sort, Term, UnaryOp, VariantId};
+use ordered_float::OrderedFloat;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+const MAGIC: &[u8; 8] = b"ATLASIMG";
+
+/// The image format version [`Image::encode`] writes and [`Image::decode`]
+/// reads.
+pub const VERSION: u32 = 2;
+
+/// A captured heap region. See the [module docs](self).
+#[derive(Debug, Clone, Default)]
+pub struct Image {
+    nodes: Vec<Record>,
+    /// The duplicand of each duplication cell, by node index.
+    dups: Vec<usize>,
+    vars: usize,
+    labels: usize,
+    names: Vec<Arc<str>>,
+    roots: Vec<usize>,
+}
+
+/// One node, with its children by node index. A type record is a
+/// [`TypeInfo`] whose child addresses are node indices and whose variant
+/// ids index the image's names.
+#[derive(Debug, Clone)]
+enum Record {
+    App {
+        func: usize,
+        arg: usize,
+    },
+    Var {
+        var: usize,
+    },
+    Lam {
+        var: usize,
+        body: usize,
+    },
+    Use {
+        body: usize,
+    },
+    Dup {
+        label: usize,
+        dup: usize,
+        side: bool,
+    },
+    Sup {
+        label: usize,
+        left: usize,
+        right: usize,
+    },
+    Ctn {
+        ty: TypeInfo,
+        arity: u8,
+        values: PackRecord,
+    },
+    Partial {
+        func: usize,
+        arity: u8,
+        args: PackRecord,
+    },
+    Ctr {
+        ty: usize,
+        variant: Option<usize>,
+    },
+    VarId(usize),
+    Mat {
+        cases: Vec<(usize, usize)>,
+        default: Option<usize>,
+    },
+    Bop {
+        op: BinaryOp,
+        lhs: usize,
+        rhs: usize,
+    },
+    Uop {
+        op: UnaryOp,
+        val: usize,
+    },
+    And {
+        lhs: usize,
+        rhs: usize,
+    },
+    Or {
+        lhs: usize,
+        rhs: usize,
+    },
+    Prj {
+        val: usize,
+        ty: usize,
+    },
+    Wld,
+    Err {
+        immediate: bool,
+    },
+    Int(i64),
+    Float(OrderedFloat<f64>),
+    Char(char),
+    Bool(bool),
+    Box(Boxed),
+    Type(TypeInfo),
+    Pri(PrimId),
+    Null,
+}
+
+/// A pack: its variant (a name index) and field nodes.
+#[derive(Debug, Clone)]
+struct PackRecord {
+    name: Option<usize>,
+    fields: Vec<usize>,
+}
+
+impl Record {
+    /// The nodes this record owns, in the order they are written.
+    fn children(&self) -> Vec<usize> {
+        match self {
+            Record::App { func: a, arg: b }
+            | Record::Sup {
+                left: a, right: b, ..
+            }
+            | Record::Bop { lhs: a, rhs: b, .. }
+            | Record::And { lhs: a, rhs: b }
+            | Record::Or { lhs: a, rhs: b }
+            | Record::Prj { val: a, ty: b } => vec![*a, *b],
+            Record::Lam { body: a, .. }
+            | Record::Use { body: a }
+            | Record::Ctr { ty: a, .. }
+            | Record::Uop { val: a, .. } => vec![*a],
+            Record::Ctn { ty, values, .. } => {
+                let mut out = type_children(ty);
+                out.extend(&values.fields);
+                out
+            }
+            Record::Partial { func, args, .. } => {
+                let mut out = vec![*func];
+                out.extend(&args.fields);
+                out
+            }
+            Record::Type(ty) => type_children(ty),
+            Record::Mat { cases, default } => {
+                let mut out: Vec<usize> = cases.iter().flat_map(|&(k, b)| [k, b]).collect();
+                out.extend(*default);
+                out
+            }
+            Record::Var { .. }
+            | Record::Dup { .. }
+            | Record::VarId(_)
+            | Record::Wld
+            | Record::Err { .. }
+            | Record::Int(_)
+            | Record::Float(_)
+            | Record::Char(_)
+            | Record::Bool(_)
+            | Record::Box(_)
+            | Record::Pri(_)
+            | Record::Null => Vec::new(),
+        }
+    }
+}
+
+fn type_children(ty: &TypeInfo) -> Vec<usize> {
+    ty.children().iter().map(|a| a.to_u64() as usize).collect()
+}
+
+/// The variant names a [`TypeInfo`] mentions, for renaming each in place.
+fn type_names_mut(info: &mut TypeInfo) -> Vec<&mut VariantId> {
+    let mut out: Vec<&mut VariantId> = match &mut info.layout {
+        Layout::Sum(variants) => variants.iter_mut().map(|v| &mut v.name).collect(),
+        _ => Vec::new(),
+    };
+    out.extend(info.props.entries.iter_mut().map(|e| &mut e.name));
+    out.extend(info.ops.entries.iter_mut().map(|e| &mut e.name));
+    out
+}
+
+fn index_addr(i: usize) -> Addr {
+    Addr::new(i as u64)
+}
+
+// ========================================================================
+// Capture
+// ========================================================================
+
+impl Image {
+    /// Capture the region reachable from `roots`. Readback-only: reduction
+    /// must be idle, and fails with [`ImageError::InFlight`] on a duplication
+    /// it finds being forced. Each root must own its node: a root given twice,
+    /// or inside another root, fails with [`ImageError::SharedRoot`].
+    pub fn capture<'h>(heap: &HeapScope<'h>, roots: &[&TermPtr<'h>]) -> Result<Image, ImageError> {
+        let mut capture = Capture {
+            heap,
+            image: Image::default(),
+            nodes: HashMap::new(),
+            dups: HashMap::new(),
+            vars: HashMap::new(),
+            labels: HashMap::new(),
+            names: HashMap::new(),
+            queue: VecDeque::new(),
+        };
+        for root in roots {
+            let root = capture.node(root.addr());
+            capture.image.roots.push(root);
+        }
+        // Indices are handed out in discovery order and records written in
+        // queue order, so each record lands at its node's index.
+        while let Some(addr) = capture.queue.pop_front() {
+            let record = capture.record(addr)?;
+            capture.image.nodes.push(record);
+        }
+        capture.image.vars = capture.vars.len();
+        capture.image.labels = capture.labels.len();
+        capture.image.check_roots()?;
+        Ok(capture.image)
+    }
+
+    /// Fail if a root's node has another owner: a record, a duplication or
+    /// an earlier root.
+    fn check_roots(&self) -> Result<(), ImageError> {
+        let mut owned = vec![false; self.nodes.len()];
+        let children = self.nodes.iter().flat_map(Record::children);
+        for i in children.chain(self.dups.iter().copied()) {
+            owned[i] = true;
+        }
+        for &root in &self.roots {
+            if std::mem::replace(&mut owned[root], true) {
+                return Err(ImageError::SharedRoot);
+            }
+        }
+        Ok(())
+    }
+
+    /// The number of roots, in the order [`restore`](Self::restore) returns
+    /// them.
+    pub fn root_count(&self) -> usize {
+        self.roots.len()
+    }
+
+    /// The number of nodes in the region.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+}
+
+struct Capture<'a, 'h> {
+    heap: &'a HeapScope<'h>,
+    image: Image,
+    nodes: HashMap<Addr, usize>,
+    dups: HashMap<Addr, usize>,
+    vars: HashMap<Addr, usize>,
+    labels: HashMap<LabelId, usize>,
+    names: HashMap<VariantId, usize>,
+    queue: VecDeque<Addr>,
+}
+
+/// The index of `key` in `map`, numbering it next if it is new.
+fn number<K: std::hash::Hash + Eq>(map: &mut HashMap<K, usize>, key: K) -> usize {
+    let next = map.len();
+    *map.entry(key).or_insert(next)
+}
+
+impl<'h> Capture<'_, 'h> {
+    /// The index of the node at `addr`, queueing it if it is new.
+    fn node(&mut self, addr: Addr) -> usize {
+        let next = self.nodes.len();
+        *self.nodes.entry(addr).or_insert_with(|| {
+            self.queue.push_back(addr);
+            next
+        })
+    }
+
+    fn name(&mut self, id: VariantId) -> usize {
+        let next = self.names.len();
+        *self.names.entry(id).or_insert_with(|| {
+            self.image.names.push(Arc::from(self.heap.variant_name(id)));
+            next
+        })
+    }
+
+    fn type_info(&mut self, info: &TypeInfo) -> TypeInfo {
+        let mut info = info.clone();
+        for child in info.children_mut() {
+            *child = index_addr(self.node(*child));
+        }
+        for name in type_names_mut(&mut info) {
+            *name = VariantId::from_u56(index_addr(self.name(*name)));
+        }
+        info
+    }
+
+    fn pack(&mut self, name: Option<VariantId>, fields: Vec<Addr>) -> PackRecord {
+        PackRecord {
+            name: name.map(|id| self.name(id)),
+            fields: fields.into_iter().map(|a| self.node(a)).collect(),
+        }
+    }
+
+    fn record(&mut self, addr: Addr) -> Result<Record, ImageError> {
+        let heap = self.heap;
+        let view = heap.view_at(addr);
+        Ok(match &*view {
+            Term::App { func, arg } => Record::App {
+                func: self.node(func.addr()),
+                arg: self.node(arg.addr()),
+            },
+            Term::Var { cell } => Record::Var {
+                var: number(&mut self.vars, cell.addr()),
+            },
+            Term::Lam { var, body } => Record::Lam {
+                var: number(&mut self.vars, var.addr()),
+                body: self.node(body.addr()),
+            },
+            Term::Use { body } => Record::Use {
+                body: self.node(body.addr()),
+            },
+            Term::Dup { label, ptr } => {
+                let dup = match self.dups.get(&ptr.addr()) {
+                    Some(&dup) => dup,
+                    None => {
+                        let value = heap.dup_pending_value(*ptr).ok_or(ImageError::InFlight)?;
+                        let dup = number(&mut self.dups, ptr.addr());
+                        let value = self.node(value);
+                        self.image.dups.push(value);
+                        dup
+                    }
+                };
+                Record::Dup {
+                    label: number(&mut self.labels, *label),
+                    dup,
+                    side: ptr.side(),
+                }
+            }
+            Term::Sup { label, ptr } => {
+                let (left, right) = heap.sup_addrs(ptr);
+                Record::Sup {
+                    label: number(&mut self.labels, *label),
+                    left: self.node(left),
+                    right: self.node(right),
+                }
+            }
+            Term::Ctn { ty, arity, values } => {
+                let fields = (0..heap.pack_len(values))
+                    .map(|i| heap.pack_addr(values, i))
+                    .collect();
+                Record::Ctn {
+                    ty: self.type_info(heap.type_info(ty)),
+                    arity: *arity,
+                    values: self.pack(heap.pack_name(values), fields),
+                }
+            }
+            Term::Partial { func, arity, args } => {
+                let fields = (0..heap.pack_len(args))
+                    .map(|i| heap.pack_addr(args, i))
+                    .collect();
+                Record::Partial {
+                    func: self.node(func.addr()),
+                    arity: *arity,
+                    args: self.pack(heap.pack_name(args), fields),
+                }
+            }
+            Term::Ctr { ty, variant } => Record::Ctr {
+                ty: self.node(ty.addr()),
+                variant: variant.map(|id| self.name(id)),
+            },
+            Term::VarId(id) => Record::VarId(self.name(*id)),
+            Term::Mat { matches } => {
+                let data = heap.match_data(matches);
+                Record::Mat {
+                    cases: (data.cases.iter())
+                        .map(|&(key, branch)| (self.node(key), self.node(branch)))
+                        .collect(),
+                    default: data.default.map(|d| self.node(d)),
+                }
+            }
+            Term::Bop { op, lhs, rhs } => Record::Bop {
+                op: *op,
+                lhs: self.node(lhs.addr()),
+                rhs: self.node(rhs.addr()),
+            },
+            Term::Uop { op, val } => Record::Uop {
+                op: *op,
+                val: self.node(val.addr()),
+            },
+            Term::And { lhs, rhs } => Record::And {
+                lhs: self.node(lhs.addr()),
+                rhs: self.node(rhs.addr()),
+            },
+            Term::Or { lhs, rhs } => Record::Or {
+                lhs: self.node(lhs.addr()),
+                rhs: self.node(rhs.addr()),
+            },
+            Term::Prj { val, ty } => Record::Prj {
+                val: self.node(val.addr()),
+                ty: self.node(ty.addr()),
+            },
+            Term::Wld => Record::Wld,
+            Term::Err { immediate, .. } => Record::Err {
+                immediate: *immediate,
+            },
+            Term::Int(n) => Record::Int(*n),
+            Term::Float(x) => Record::Float(*x),
+            Term::Char(c) => Record::Char(*c),
+            Term::Bool(b) => Record::Bool(*b),
+            Term::Box(value) => Record::Box(heap.value_get(value).clone()),
+            Term::Type(ty) => Record::Type(self.type_info(heap.type_info(ty))),
+            Term::Pri(id) => Record::Pri(*id),
+            Term::Null => Record::Null,
+        })
+    }
+}
+
+// ========================================================================
+// Restore
+// ========================================================================
+
+impl Image {
+    /// Rebuild the region into `heap`, returning a pointer to each root.
+    ///
+    /// A duplication restored with one projection dropped is handed to
+    /// [`HeapScope::dup_drop_side`]; whatever that leaves to erase is queued
+    /// as dropped handles, for the next executor to reclaim.
+    pub fn restore<'h>(&self, heap: &HeapScope<'h>) -> Vec<TermPtr<'h>> {
+        // Reserve every node first, so a record can name nodes after it.
+        let addrs: Vec<Addr> = (self.nodes.iter())
+            .map(|_| heap.alloc(Term::Null).into_addr())
+            .collect();
+        let node = |i: usize| unsafe { TermPtr::forge(addrs[i]) };
+        let vars: Vec<_> = (0..self.vars).map(|_| heap.alloc_var()).collect();
+        let dups: Vec<_> = (self.dups.iter())
+            .map(|&value| heap.alloc_dup_at(addrs[value]).0.addr())
+            .collect();
+        let names: Vec<VariantId> = (self.names.iter())
+            .map(|name| heap.intern_variant(name))
+            .collect();
+        let mut labels: Vec<Option<LabelId>> = vec![None; self.labels];
+
+        let type_info = |ty: &TypeInfo| {
+            let mut ty = ty.clone();
+            for child in ty.children_mut() {
+                *child = addrs[child.to_u64() as usize];
+            }
+            for name in type_names_mut(&mut ty) {
+                *name = names[name.addr().to_u64() as usize];
+            }
+            heap.alloc_type(ty)
+        };
+        let pack = |pack: &PackRecord| {
+            let fields = pack.fields.iter().map(|&i| node(i)).collect();
+            heap.alloc_pack(pack.name.map(|n| names[n]), fields)
+        };
+
+        // Which lambdas and occurrences, and which projections, came along.
+        let mut binders = vec![[false; 2]; self.vars];
+        let mut sides = vec![[false; 2]; self.dups.len()];
+        for (i, record) in self.nodes.iter().enumerate() {
+            let term = match record {
+                &Record::App { func, arg } => Term::App {
+                    func: node(func),
+                    arg: node(arg),
+                },
+                &Record::Var { var } => {
+                    binders[var][1] = true;
+                    Term::Var { cell: vars[var] }
+                }
+                &Record::Lam { var, body } => {
+                    binders[var][0] = true;
+                    Term::Lam {
+                        var: vars[var],
+                        body: node(body),
+                    }
+                }
+                &Record::Use { body } => Term::Use { body: node(body) },
+                &Record::Dup { label, dup, side } => {
+                    sides[dup][usize::from(!side)] = true;
+                    let ptr = unsafe { DupPtr::forge(dups[dup], side) };
+                    Term::Dup {
+                        label: *labels[label].get_or_insert_with(|| heap.dup_auto_label(ptr)),
+                        ptr,
+                    }
+                }
+                &Record::Sup { label, left, right } => {
+                    let ptr = heap.sup(node(left), node(right));
+                    Term::Sup {
+                        label: *labels[label].get_or_insert_with(|| heap.sup_auto_label(&ptr)),
+                        ptr,
+                    }
+                }
+                Record::Ctn { ty, arity, values } => Term::Ctn {
+                    ty: type_info(ty),
+                    arity: *arity,
+                    values: pack(values),
+                },
+                Record::Partial { func, arity, args } => Term::Partial {
+                    func: node(*func),
+                    arity: *arity,
+                    args: pack(args),
+                },
+                Record::Ctr { ty, variant } => Term::Ctr {
+                    ty: node(*ty),
+                    variant: variant.map(|n| names[n]),
+                },
+                &Record::VarId(name) => Term::VarId(names[name]),
+                Record::Mat { cases, default } => Term::Mat {
+                    matches: heap.alloc_match(MatchData {
+                        cases: (cases.iter())
+                            .map(|&(key, branch)| (addrs[key], addrs[branch]))
+                            .collect(),
+                        default: default.map(|d| addrs[d]),
+                    }),
+                },
+                &Record::Bop { op, lhs, rhs } => Term::Bop {
+                    op,
+                    lhs: node(lhs),
+                    rhs: node(rhs),
+                },
+                &Record::Uop { op, val } => Term::Uop { op, val: node(val) },
+                &Record::And { lhs, rhs } => Term::And {
+                    lhs: node(lhs),
+                    rhs: node(rhs),
+                },
+                &Record::Or { lhs, rhs } => Term::Or {
+                    lhs: node(lhs),
+                    rhs: node(rhs),
+                },
+                &Record::Prj { val, ty } => Term::Prj {
+                    val: node(val),
+                    ty: node(ty),
+                },
+                Record::Wld => Term::Wld,
+                &Record::Err { immediate } => Term::Err {
+                    immediate,
+                    backtrace: None,
+                },
+                &Record::Int(n) => Term::Int(n),
+                &Record::Float(x) => Term::Float(x),
+                &Record::Char(c) => Term::Char(c),
+                &Record::Bool(b) => Term::Bool(b),
+                Record::Box(value) => Term::Box(heap.value(value.clone())),
+                Record::Type(ty) => Term::Type(type_info(ty)),
+                &Record::Pri(id) => Term::Pri(id),
+                Record::Null => Term::Null,
+            };
+            let _ = heap.finish_slot(heap.slot(node(i)), term);
+        }
+
+        for (&var, [lam, occurrence]) in vars.iter().zip(binders) {
+            if !occurrence {
+                heap.drop_var(var);
+            } else if !lam {
+                heap.drop_binder(var);
+            }
+        }
+        for (&dup, present) in dups.iter().zip(sides) {
+            for side in [true, false] {
+                if present[usize::from(!side)] {
+                    continue;
+                }
+                match heap.dup_drop_side(unsafe { DupPtr::forge(dup, side) }) {
+                    DupDrop::Recorded { dead } => {
+                        dead.into_iter().for_each(|d| heap.register_dropped(d))
+                    }
+                    DupDrop::Reclaim(dead) => heap.register_dropped(dead),
+                }
+            }
+        }
+        self.roots.iter().map(|&root| node(root)).collect()
+    }
+}
+
+// ========================================================================
+// Portable terms
+// ========================================================================
+
+/// A closed term copied out of one heap, to be copied into any number of
+/// others: see [`HeapScope::export`] and [`HeapScope::import`]. Unlike an
+/// encoded image, it keeps primitives by id, so every heap it is imported
+/// into must be reduced under the extension set it was exported from.
+#[derive(Debug, Clone)]
+pub struct PortableTerm {
+    image: Image,
+}
+
+impl PortableTerm {
+    pub(crate) fn capture<'h>(
+        heap: &HeapScope<'h>,
+        root: &TermPtr<'h>,
+    ) -> Result<PortableTerm, ImageError> {
+        let image = Image::capture(heap, &[root])?;
+        let mut bound = vec![false; image.vars];
+        for record in &image.nodes {
+            if let &Record::Lam { var, .. } = record {
+                bound[var] = true;
+            }
+        }
+        let free = (image.nodes.iter())
+            .any(|record| matches!(*record, Record::Var { var } if !bound[var]));
+        if free {
+            return Err(ImageError::FreeVariable);
+        }
+        Ok(PortableTerm { image })
+    }
+
+    pub(crate) fn restore<'h>(&self, heap: &HeapScope<'h>) -> TermPtr<'h> {
+        let mut roots = self.image.restore(heap);
+        roots.pop().expect("a portable term has one root")
+    }
+
+    /// The number of nodes each import allocates.
+    pub fn node_count(&self) -> usize {
+        self.image.node_count()
+    }
+}
+
+// ========================================================================
+// Encoding
+// ========================================================================
+
+// Record kinds. The numbering is part of the format: extend it, and bump
+// `VERSION` on any other change.
+const APP: u8 = 0;
+const VAR: u8 = 1;
+const LAM: u8 = 2;
+const USE: u8 = 3;
+const DUP: u8 = 4;
+const SUP: u8 = 5;
+const CTN: u8 = 6;
+const PARTIAL: u8 = 7;
+const CTR: u8 = 8;
+const VAR_ID: u8 = 9;
+const MAT: u8 = 10;
+const BOP: u8 = 11;
+const UOP: u8 = 12;
+const AND: u8 = 13;
+const OR: u8 = 14;
+const PRJ: u8 = 15;
+const WLD: u8 = 16;
+const ERR: u8 = 17;
+const INT: u8 = 18;
+const FLOAT: u8 = 19;
+const CHAR: u8 = 20;
+const BOOL: u8 = 21;
+const STR: u8 = 22;
+const BYTES: u8 = 23;
+const TYPE: u8 = 24;
+const PRI: u8 = 25;
+const NULL: u8 = 26;
+
+// Layout kinds.
+const ANY: u8 = 0;
+const ATOM: u8 = 1;
+const PRODUCT: u8 = 2;
+const SUM: u8 = 3;
+const PI: u8 = 4;
+const SORT: u8 = 5;
+
+#[derive(Default)]
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn byte(&mut self, b: u8) {
+        self.bytes.push(b);
+    }
+
+    fn uint(&mut self, mut n: u64) {
+        while n >= 0x80 {
+            self.bytes.push(n as u8 | 0x80);
+            n >>= 7;
+        }
+        self.bytes.push(n as u8);
+    }
+
+    fn index(&mut self, i: usize) {
+        self.uint(i as u64);
+    }
+
+    fn option(&mut self, i: Option<usize>) {
+        self.uint(i.map_or(0, |i| i as u64 + 1));
+    }
+
+    fn data(&mut self, data: &[u8]) {
+        self.index(data.len());
+        self.bytes.extend_from_slice(data);
+    }
+
+    fn type_info(&mut self, ty: &TypeInfo) {
+        let addr = |a: &Addr| a.to_u64() as usize;
+        let name = |id: &VariantId| id.addr().to_u64() as usize;
+        match &ty.layout {
+            Layout::Any => self.byte(ANY),
+            Layout::Atom(tag) => {
+                self.byte(ATOM);
+                self.data(tag.as_bytes());
+            }
+            Layout::Product(fields) => {
+                self.byte(PRODUCT);
+                self.index(fields.len());
+                fields.iter().for_each(|f| self.index(addr(f)));
+            }
+            Layout::Sum(variants) => {
+                self.byte(SUM);
+                self.index(variants.len());
+                for variant in variants {
+                    self.index(name(&variant.name));
+                    self.index(variant.args.len());
+                    variant.args.iter().for_each(|a| self.index(addr(a)));
+                }
+            }
+            Layout::Pi {
+                erased,
+                input,
+                output,
+            } => {
+                self.byte(PI);
+                self.byte(u8::from(*erased));
+                self.index(addr(input));
+                self.index(addr(output));
+            }
+            Layout::Sort(sort) => {
+                self.byte(SORT);
+                self.uint(match sort {
+                    Sort::Prop => 0,
+                    Sort::Type(i) => u64::from(*i) + 1,
+                });
+            }
+        }
+        for row in [&ty.props, &ty.ops] {
+            self.byte(u8::from(row.open));
+            self.index(row.entries.len());
+            for entry in &row.entries {
+                self.index(name(&entry.name));
+                self.index(addr(&entry.sig));
+            }
+        }
+        self.byte(u8::from(ty.name.is_some()));
+        if let Some(declared) = &ty.name {
+            self.data(declared.as_bytes());
+        }
+    }
+
+    fn pack(&mut self, pack: &PackRecord) {
+        self.option(pack.name);
+        self.index(pack.fields.len());
+        pack.fields.iter().for_each(|&f| self.index(f));
+    }
+}
+
+impl Image {
+    /// The image as bytes, naming its primitives by `ext`.
+    pub fn encode<X: Extensions>(&self, ext: &X) -> Result<Vec<u8>, ImageError> {
+        let mut prims: HashMap<PrimId, usize> = HashMap::new();
+        let mut prim_names = Vec::new();
+        for record in &self.nodes {
+            if let &Record::Pri(id) = record
+                && !prims.contains_key(&id)
+            {
+                let name = ext.name(id).ok_or(ImageError::UnnamedPrimitive(id))?;
+                prims.insert(id, prim_names.len());
+                prim_names.push(name);
+            }
+        }
+
+        let mut w = Writer::default();
+        w.bytes.extend_from_slice(MAGIC);
+        w.bytes.extend_from_slice(&VERSION.to_le_bytes());
+        w.index(self.names.len());
+        self.names.iter().for_each(|n| w.data(n.as_bytes()));
+        w.index(prim_names.len());
+        prim_names.iter().for_each(|n| w.data(n.as_bytes()));
+        w.index(self.vars);
+        w.index(self.labels);
+        w.index(self.dups.len());
+        self.dups.iter().for_each(|&d| w.index(d));
+        w.index(self.nodes.len());
+        for record in &self.nodes {
+            match record {
+                &Record::App { func, arg } => {
+                    w.byte(APP);
+                    w.index(func);
+                    w.index(arg);
+                }
+                &Record::Var { var } => {
+                    w.byte(VAR);
+                    w.index(var);
+                }
+                &Record::Lam { var, body } => {
+                    w.byte(LAM);
+                    w.index(var);
+                    w.index(body);
+                }
+                &Record::Use { body } => {
+                    w.byte(USE);
+                    w.index(body);
+                }
+                &Record::Dup { label, dup, side } => {
+                    w.byte(DUP);
+                    w.index(label);
+                    w.index(dup);
+                    w.byte(u8::from(side));
+                }
+                &Record::Sup { label, left, right } => {
+                    w.byte(SUP);
+                    w.index(label);
+                    w.index(left);
+                    w.index(right);
+                }
+                Record::Ctn { ty, arity, values } => {
+                    w.byte(CTN);
+                    w.type_info(ty);
+                    w.byte(*arity);
+                    w.pack(values);
+                }
+                Record::Partial { func, arity, args } => {
+                    w.byte(PARTIAL);
+                    w.index(*func);
+                    w.byte(*arity);
+                    w.pack(args);
+                }
+                &Record::Ctr { ty, variant } => {
+                    w.byte(CTR);
+                    w.index(ty);
+                    w.option(variant);
+                }
+                &Record::VarId(name) => {
+                    w.byte(VAR_ID);
+                    w.index(name);
+                }
+                Record::Mat { cases, default } => {
+                    w.byte(MAT);
+                    w.index(cases.len());
+                    for &(key, branch) in cases {
+                        w.index(key);
+                        w.index(branch);
+                    }
+                    w.option(*default);
+                }
+                &Record::Bop { op, lhs, rhs } => {
+                    w.byte(BOP);
+                    w.byte(op.into());
+                    w.index(lhs);
+                    w.index(rhs);
+                }
+                &Record::Uop { op, val } => {
+                    w.byte(UOP);
+                    w.byte(op.into());
+                    w.index(val);
+                }
+                &Record::And { lhs, rhs } => {
+                    w.byte(AND);
+                    w.index(lhs);
+                    w.index(rhs);
+                }
+                &Record::Or { lhs, rhs } => {
+                    w.byte(OR);
+                    w.index(lhs);
+                    w.index(rhs);
+                }
+                &Record::Prj { val, ty } => {
+                    w.byte(PRJ);
+                    w.index(val);
+                    w.index(ty);
+                }
+                Record::Wld => w.byte(WLD),
+                &Record::Err { immediate } => {
+                    w.byte(ERR);
+                    w.byte(u8::from(immediate));
+                }
+                &Record::Int(n) => {
+                    w.byte(INT);
+                    w.bytes.extend_from_slice(&n.to_le_bytes());
+                }
+                &Record::Float(x) => {
+                    w.byte(FLOAT);
+                    w.bytes
+                        .extend_from_slice(&x.into_inner().to_bits().to_le_bytes());
+                }
+                &Record::Char(c) => {
+                    w.byte(CHAR);
+                    w.uint(u64::from(c));
+                }
+                &Record::Bool(b) => {
+                    w.byte(BOOL);
+                    w.byte(u8::from(b));
+                }
+                Record::Box(Boxed::Str(s)) => {
+                    w.byte(STR);
+                    w.data(s.as_bytes());
+                }
+                Record::Box(Boxed::Bytes(b)) => {
+                    w.byte(BYTES);
+                    w.data(b);
+                }
+                Record::Type(ty) => {
+                    w.byte(TYPE);
+                    w.type_info(ty);
+                }
+                Record::Pri(id) => {
+                    w.byte(PRI);
+                    w.index(prims[id]);
+                }
+                Record::Null => w.byte(NULL),
+            }
+        }
+        w.index(self.roots.len());
+        self.roots.iter().for_each(|&r| w.index(r));
+        Ok(w.bytes)
+    }
+}
+
+// ========================================================================
+// Decoding
+// ========================================================================
+
+struct Reader<'b> {
+    bytes: &'b [u8],
+    pos: usize,
+}
+
+fn malformed(message: impl Into<String>) -> ImageError {
+    ImageError::Malformed(message.into())
+}
+
+impl<'b> Reader<'b> {
+    fn take(&mut self, n: usize) -> Result<&'b [u8], ImageError> {
+        let end = self
+            .pos
+            .checked_add(n)
+            .filter(|&end| end <= self.bytes.len());
+        let end = end.ok_or_else(|| malformed("unexpected end of image"))?;
+        let bytes = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, ImageError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn flag(&mut self) -> Result<bool, ImageError> {
+        match self.byte()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            b => Err(malformed(format!("bad flag {b}"))),
+        }
+    }
+
+    fn word(&mut self) -> Result<[u8; 8], ImageError> {
+        Ok(self.take(8)?.try_into().unwrap())
+    }
+
+    fn uint(&mut self) -> Result<u64, ImageError> {
+        let mut n = 0u64;
+        for shift in (0..64).step_by(7) {
+            let b = self.byte()?;
+            n |= u64::from(b & 0x7f) << shift;
+            if b & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+        Err(malformed("varint too long"))
+    }
+
+    /// A count or index. Range checks come later, in [`Image::validate`].
+    fn index(&mut self) -> Result<usize, ImageError> {
+        usize::try_from(self.uint()?).map_err(|_| malformed("index out of range"))
+    }
+
+    fn option(&mut self) -> Result<Option<usize>, ImageError> {
+        Ok(self.index()?.checked_sub(1))
+    }
+
+    fn data(&mut self) -> Result<&'b [u8], ImageError> {
+        let len = self.index()?;
+        self.take(len)
+    }
+
+    fn str(&mut self) -> Result<&'b str, ImageError> {
+        std::str::from_utf8(self.data()?).map_err(|_| malformed("a name is not UTF-8"))
+    }
+
+    fn indices(&mut self) -> Result<Vec<usize>, ImageError> {
+        let len = self.index()?;
+        (0..len).map(|_| self.index()).collect()
+    }
+
+    fn type_info(&mut self) -> Result<TypeInfo, ImageError> {
+        let addr = |i: usize| index_addr(i);
+        let name = |i: usize| VariantId::from_u56(index_addr(i));
+        let layout = match self.byte()? {
+            ANY => Layout::Any,
+            ATOM => Layout::Atom(Arc::from(self.str()?)),
+            PRODUCT => Layout::Product(self.indices()?.into_iter().map(addr).collect()),
+            SUM => {
+                let len = self.index()?;
+                let variants = (0..len).map(|_| {
+                    Ok(Variant {
+                        name: name(self.index()?),
+                        args: self.indices()?.into_iter().map(addr).collect(),
+                    })
+                });
+                Layout::Sum(variants.collect::<Result<_, ImageError>>()?)
+            }
+            PI => Layout::Pi {
+                erased: self.flag()?,
+                input: addr(self.index()?),
+                output: addr(self.index()?),
+            },
+            SORT => Layout::Sort(match self.uint()? {
+                0 => Sort::Prop,
+                i => Sort::Type(u32::try_from(i - 1).map_err(|_| malformed("universe too large"))?),
+            }),
+            kind => return Err(malformed(format!("unknown layout kind {kind}"))),
+        };
+        let mut rows = [Row::default(), Row::default()];
+        for row in &mut rows {
+            row.open = self.flag()?;
+            let len = self.index()?;
+            for _ in 0..len {
+                row.entries.push(Entry {
+                    name: name(self.index()?),
+                    sig: addr(self.index()?),
+                });
+            }
+        }
+        let [props, ops] = rows;
+        let declared = match self.flag()? {
+            true => Some(Arc::from(self.str()?)),
+            false => None,
+        };
+        Ok(TypeInfo {
+            layout,
+            props,
+            ops,
+            name: declared,
+        })
+    }
+
+    fn pack(&mut self) -> Result<PackRecord, ImageError> {
+        Ok(PackRecord {
+            name: self.option()?,
+            fields: self.indices()?,
+        })
+    }
+
+    fn record(&mut self, prims: &[PrimId]) -> Result<Record, ImageError> {
+        Ok(match self.byte()? {
+            APP => Record::App {
+                func: self.index()?,
+                arg: self.index()?,
+            },
+            VAR => Record::Var { var: self.index()? },
+            LAM => Record::Lam {
+                var: self.index()?,
+                body: self.index()?,
+            },
+            USE => Record::Use {
+                body: self.index()?,
+            },
+            DUP => Record::Dup {
+                label: self.index()?,
+                dup: self.index()?,
+                side: self.flag()?,
+            },
+            SUP => Record::Sup {
+                label: self.index()?,
+                left: self.index()?,
+                right: self.index()?,
+            },
+            CTN => Record::Ctn {
+                ty: self.type_info()?,
+                arity: self.byte()?,
+                values: self.pack()?,
+            },
+            PARTIAL => Record::Partial {
+                func: self.index()?,
+                arity: self.byte()?,
+                args: self.pack()?,
+            },
+            CTR => Record::Ctr {
+                ty: self.index()?,
+                variant: self.option()?,
+            },
+            VAR_ID => Record::VarId(self.index()?),
+            MAT => {
+                let len = self.index()?;
+                let cases = (0..len)
+                    .map(|_| Ok((self.index()?, self.index()?)))
+                    .collect::<Result<_, ImageError>>()?;
+                Record::Mat {
+                    cases,
+                    default: self.option()?,
+                }
+            }
+            BOP => Record::Bop {
+                op: BinaryOp::try_from(self.byte()?).map_err(|_| malformed("bad operator"))?,
+                lhs: self.index()?,
+                rhs: self.index()?,
+            },
+            UOP => Record::Uop {
+                op: UnaryOp::try_from(self.byte()?).map_err(|_| malformed("bad operator"))?,
+                val: self.index()?,
+            },
+            AND => Record::And {
+                lhs: self.index()?,
+                rhs: self.index()?,
+            },
+            OR => Record::Or {
+                lhs: self.index()?,
+                rhs: self.index()?,
+            },
+            PRJ => Record::Prj {
+                val: self.index()?,
+                ty: self.index()?,
+            },
+            WLD => Record::Wld,
+            ERR => Record::Err {
+                immediate: self.flag()?,
+            },
+            INT => Record::Int(i64::from_le_bytes(self.word()?)),
+            FLOAT => Record::Float(OrderedFloat(f64::from_bits(u64::from_le_bytes(
+                self.word()?,
+            )))),
+            CHAR => Record::Char(
+                (u32::try_from(self.uint()?).ok())
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| malformed("bad char"))?,
+            ),
+            BOOL => Record::Bool(self.flag()?),
+            STR => Record::Box(Boxed::Str(Arc::from(self.str()?))),
+            BYTES => Record::Box(Boxed::Bytes(Arc::from(self.data()?))),
+            TYPE => Record::Type(self.type_info()?),
+            PRI => Record::Pri(
+                *prims
+                    .get(self.index()?)
+                    .ok_or_else(|| malformed("primitive index out of range"))?,
+            ),
+            NULL => Record::Null,
+            kind => return Err(malformed(format!("unknown record kind {kind}"))),
+        })
+    }
+}
+
+impl Image {
+    /// Read an image written by [`encode`](Self::encode), resolving its
+    /// primitives by name in `ext`.
+    pub fn decode<X: Extensions>(bytes: &[u8], ext: &X) -> Result<Image, ImageError> {
+        let mut r = Reader { bytes, pos: 0 };
+        if r.take(MAGIC.len()).ok() != Some(&MAGIC[..]) {
+            return Err(ImageError::NotAnImage);
+        }
+        let version = u32::from_le_bytes(r.take(4)?.try_into().unwrap());
+        if version != VERSION {
+            return Err(ImageError::UnsupportedVersion(version));
+        }
+        let mut image = Image::default();
+        let len = r.index()?;
+        for _ in 0..len {
+            image.names.push(Arc::from(r.str()?));
+        }
+        let len = r.index()?;
+        let mut prims = Vec::new();
+        for _ in 0..len {
+            let name = r.str()?;
+            let id = ext.resolve(name);
+            prims.push(id.ok_or_else(|| ImageError::UnknownPrimitive(name.to_string()))?);
+        }
+        image.vars = r.index()?;
+        image.labels = r.index()?;
+        image.dups = r.indices()?;
+        let len = r.index()?;
+        for _ in 0..len {
+            image.nodes.push(r.record(&prims)?);
+        }
+        image.roots = r.indices()?;
+        if r.pos != bytes.len() {
+            return Err(malformed("trailing bytes"));
+        }
+        image.validate()?;
+        Ok(image)
+    }
+
+    /// Check that restoring cannot go wrong: every index is in range, every
+    /// node has exactly one owner, every cell is used as the heap uses it.
+    fn validate(&self) -> Result<(), ImageError> {
+        let n = self.nodes.len();
+        let in_range = |i: usize, len: usize, what: &str| {
+            if i < len {
+                Ok(())
+            } else {
+                Err(malformed(format!("{what} {i} out of range")))
+            }
+        };
+        let mut owners = vec![0usize; n];
+        let mut own = |i: usize| {
+            in_range(i, n, "node")?;
+            owners[i] += 1;
+            Ok::<_, ImageError>(())
+        };
+        let mut binders = vec![[0usize; 2]; self.vars];
+        let mut sides = vec![[0usize; 2]; self.dups.len()];
+        let mut dup_labels = vec![None; self.dups.len()];
+        for record in &self.nodes {
+            record.children().into_iter().try_for_each(&mut own)?;
+            let mut names: Vec<usize> = Vec::new();
+            match record {
+                &Record::Var { var } | &Record::Lam { var, .. } => {
+                    in_range(var, self.vars, "variable")?;
+                    binders[var][usize::from(matches!(record, Record::Var { .. }))] += 1;
+                }
+                &Record::Dup { label, dup, side } => {
+                    in_range(label, self.labels, "label")?;
+                    in_range(dup, self.dups.len(), "duplication")?;
+                    sides[dup][usize::from(!side)] += 1;
+                    if *dup_labels[dup].get_or_insert(label) != label {
+                        return Err(malformed(format!("duplication {dup} has two labels")));
+                    }
+                }
+                &Record::Sup { label, .. } => in_range(label, self.labels, "label")?,
+                Record::Ctn { ty, arity, values } => {
+                    if usize::from(*arity) != values.fields.len() {
+                        return Err(malformed("a construction's arity is not its field count"));
+                    }
+                    names.extend(type_name_indices(ty));
+                    names.extend(values.name);
+                }
+                Record::Partial { args, .. } => names.extend(args.name),
+                Record::Ctr { variant, .. } => names.extend(*variant),
+                &Record::VarId(name) => names.push(name),
+                Record::Type(ty) => names.extend(type_name_indices(ty)),
+                _ => {}
+            }
+            for name in names {
+                in_range(name, self.names.len(), "name")?;
+            }
+        }
+        self.dups.iter().copied().try_for_each(&mut own)?;
+        self.roots.iter().copied().try_for_each(&mut own)?;
+        if let Some(i) = owners.iter().position(|&o| o != 1) {
+            return Err(malformed(format!("node {i} has {} owners", owners[i])));
+        }
+        if let Some(var) = binders
+            .iter()
+            .position(|b| b.iter().any(|&c| c > 1) || b == &[0, 0])
+        {
+            return Err(malformed(format!("variable {var} is not one lambda's")));
+        }
+        if let Some(dup) = sides
+            .iter()
+            .position(|s| s.iter().any(|&c| c > 1) || s == &[0, 0])
+        {
+            return Err(malformed(format!("duplication {dup} has bad projections")));
+        }
+        Ok(())
+    }
+}
+
+fn type_name_indices(ty: &TypeInfo) -> Vec<usize> {
+    let mut ty = ty.clone();
+    type_names_mut(&mut ty)
+        .into_iter()
+        .map(|id| id.addr().to_u64() as usize)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Image, VERSION};
+    use crate::core::ast::desugar_open;
+    use crate::core::expr::{Expr, TypeDefKind, Value};
+    use crate::core::parse::parse;
+    use crate::error::ImageError;
+    use crate::extension::{Extensions, NoExtensions, TypeExtensions};
+    use crate::vm::exec::{Executor, FiniteBudget, UnlimitedBudget};
+    use crate::vm::heap::{ArenaKind, Heap, HeapScope, TermPtr};
+    use crate::vm::printer::Printer;
+    use crate::vm::term::Term;
+    use crate::vm::{run, run_with};
+
+    fn runtime() -> tokio::runtime::Runtime {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+    }
+
+    fn lower<'h, X: Extensions>(
+        h: &'h HeapScope<'h>,
+        src: &str,
+        ext: &X,
+        local: &mut dyn FnMut(&str) -> Option<TermPtr<'h>>,
+    ) -> TermPtr<'h> {
+        let expr = desugar_open(&parse(src).unwrap()).unwrap();
+        h.lower(&expr, &|n| ext.resolve(n), local).unwrap()
+    }
+
+    /// Lower `src`, take up to `budget` interactions towards its normal form,
+    /// and save what is left.
+    fn save<X: Extensions>(src: &str, ext: &X, budget: u64) -> Vec<u8> {
+        Heap::new().with(|h| {
+            let root = lower(h, src, ext, &mut |_| None);
+            let exec = Executor::with_extensions(h, FiniteBudget::new(budget), ext);
+            let root = runtime().block_on(exec.normalize_at(root));
+            Image::capture(h, &[&root]).unwrap().encode(ext).unwrap()
+        })
+    }
+
+    /// Load `bytes` into a fresh heap and print its normalized root, checking
+    /// that erasing it leaves the heap empty.
+    fn load<X: Extensions>(bytes: &[u8], ext: &X) -> String {
+        let image = Image::decode(bytes, ext).unwrap();
+        Heap::new().with(|h| {
+            let [root] = image.restore(h).try_into().unwrap();
+            let exec = Executor::with_extensions(h, UnlimitedBudget, ext);
+            let root = runtime().block_on(exec.normalize_at(root));
+            let printed = Printer::new(h).pretty(&root).to_string();
+            exec.erase(h.pull(root));
+            assert_eq!(h.arena_len(ArenaKind::Nodes), 0, "{printed}");
+            printed
+        })
+    }
+
+    const PROGRAMS: [&str; 7] = [
+        r"(\x -> x + 1) 2",
+        r"(\&x -> x * x) ((\y -> y + 1) 2)",
+        r"(\&f -> f (f 1)) (\x -> x * 2)",
+        r"(\&x -> x + x) &{1, 2}",
+        r#"?{1 -> "one"; _ -> "other"} (2 - 1)"#,
+        r"type{Leaf, Node(type(), type())}::Node 'a' (1.5 * 2.0)",
+        r"(\&f -> \x -> f (f x)) (\y -> y + 1)",
+    ];
+
+    #[test]
+    fn unevaluated_programs_round_trip() {
+        for src in PROGRAMS {
+            assert_eq!(
+                load(&save(src, &NoExtensions, 0), &NoExtensions),
+                run(src).unwrap(),
+                "{src}"
+            );
+        }
+    }
+
+    #[test]
+    fn interrupted_reductions_resume_after_a_reload() {
+        // Each budget stops reduction at a different point, with different
+        // duplications and superpositions in flight.
+        for src in PROGRAMS {
+            let expected = run(src).unwrap();
+            for budget in 1..12 {
+                let bytes = save(src, &NoExtensions, budget);
+                assert_eq!(
+                    load(&bytes, &NoExtensions),
+                    expected,
+                    "{src} after {budget}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn a_projection_saved_alone_gets_the_whole_duplicand() {
+        let bytes = Heap::new().with(|h| {
+            let value = lower(h, "2 * 3", &NoExtensions, &mut |_| None);
+            let (used, kept) = h.dup_use(value);
+            let bytes = Image::capture(h, &[&used])
+                .unwrap()
+                .encode(&NoExtensions)
+                .unwrap();
+            let exec = Executor::new(h, UnlimitedBudget);
+            exec.erase(h.pull(used));
+            exec.erase(h.pull(kept));
+            bytes
+        });
+        // The other projection stayed behind, so it is dropped on restore.
+        assert_eq!(load(&bytes, &NoExtensions), "6");
+    }
+
+    #[test]
+    fn declared_type_names_are_saved() {
+        // Core can't spell a named type: build the `Pair { a: 1, b: 2 }` an
+        // atlas `struct Pair` lowers to.
+        let unit = || Expr::TypeDef {
+            name: None,
+            kind: TypeDefKind::Product(Vec::new()),
+        };
+        let app = |func, arg| Expr::App {
+            func: Box::new(func),
+            arg: Box::new(Expr::Value(Value::Int(arg))),
+        };
+        let pair = Expr::Ctr {
+            ty: Box::new(Expr::TypeDef {
+                name: Some("Pair".into()),
+                kind: TypeDefKind::Product(vec![unit(), unit()]),
+            }),
+            variant: None,
+        };
+        let expr = app(app(pair, 1), 2);
+        let bytes = Heap::new().with(|h| {
+            let root = h.lower(&expr, &|_| None, &mut |_| None).unwrap();
+            let image = Image::capture(h, &[&root]).unwrap();
+            Executor::new(h, UnlimitedBudget).erase(h.pull(root));
+            image.encode(&NoExtensions).unwrap()
+        });
+        assert_eq!(load(&bytes, &NoExtensions), "Pair{1, 2}");
+    }
+
+    #[test]
+    fn roots_round_trip_unless_shared() {
+        let bytes = Heap::new().with(|h| {
+            let a = lower(h, r"(\x -> x) 1", &NoExtensions, &mut |_| None);
+            let b = lower(h, r"(\&x -> x + x) &{1, 2}", &NoExtensions, &mut |_| None);
+            let Term::App { func, .. } = &*h.view(&a) else {
+                panic!("expected an application")
+            };
+            // The same root twice, or a root inside another, would be owned
+            // twice once restored.
+            assert_eq!(
+                Image::capture(h, &[&a, &a]).unwrap_err(),
+                ImageError::SharedRoot
+            );
+            assert_eq!(
+                Image::capture(h, &[&a, func]).unwrap_err(),
+                ImageError::SharedRoot
+            );
+            let image = Image::capture(h, &[&b, &a]).unwrap();
+            let exec = Executor::new(h, UnlimitedBudget);
+            exec.erase(h.pull(a));
+            exec.erase(h.pull(b));
+            image.encode(&NoExtensions).unwrap()
+        });
+        let image = Image::decode(&bytes, &NoExtensions).unwrap();
+        Heap::new().with(|h| {
+            let exec = Executor::new(h, UnlimitedBudget);
+            let printed: Vec<String> = (image.restore(h).into_iter())
+                .map(|root| {
+                    let root = runtime().block_on(exec.normalize_at(root));
+                    let printed = Printer::new(h).pretty(&root).to_string();
+                    exec.erase(h.pull(root));
+                    printed
+                })
+                .collect();
+            assert_eq!(printed, ["&{2, 4}", "1"]);
+            assert_eq!(h.arena_len(ArenaKind::Nodes), 0);
+        });
+    }
+
+    #[test]
+    fn primitives_are_saved_by_name() {
+        let src = "%type_sat (typeof 0) %type_any";
+        let bytes = save(src, &TypeExtensions, 0);
+        assert_eq!(
+            load(&bytes, &TypeExtensions),
+            run_with(src, &TypeExtensions).unwrap()
+        );
+        assert_eq!(
+            Image::decode(&bytes, &NoExtensions).unwrap_err(),
+            ImageError::UnknownPrimitive("type_any".into())
+        );
+    }
+
+    #[test]
+    fn each_restore_gets_fresh_labels() {
+        // Restored twice into one heap, the two superpositions are
+        // independent choices, as two written in the source are.
+        let image = Image::decode(&save("&{1, 2}", &NoExtensions, 0), &NoExtensions).unwrap();
+        let printed = Heap::new().with(|h| {
+            let mut copies = [image.restore(h), image.restore(h)].map(|mut r| r.pop());
+            let root = lower(h, "a + b", &NoExtensions, &mut |name| match name {
+                "a" => copies[0].take(),
+                _ => copies[1].take(),
+            });
+            let exec = Executor::new(h, UnlimitedBudget);
+            let root = runtime().block_on(exec.normalize_at(root));
+            Printer::new(h).pretty(&root).to_string()
+        });
+        assert_eq!(printed, run("&{1, 2} + &{1, 2}").unwrap());
+    }
+
+    #[test]
+    fn an_exported_term_is_imported_into_other_heaps() {
+        // Partly reduced, so the library carries a duplication in flight.
+        let lib = r"(\&f -> \x -> f (f x)) (\y -> y * 2)";
+        let term = Heap::new().with(|h| {
+            let root = lower(h, lib, &NoExtensions, &mut |_| None);
+            let exec = Executor::new(h, FiniteBudget::new(3));
+            let root = runtime().block_on(exec.normalize_at(root));
+            let term = h.export(&root).unwrap();
+            exec.erase(h.pull(root));
+            term
+        });
+        std::thread::scope(|s| {
+            for arg in ["1", "5"] {
+                let term = &term;
+                s.spawn(move || {
+                    let printed = Heap::new().with(|h| {
+                        let mut lib = Some(h.import(term));
+                        let root =
+                            lower(h, &format!("lib {arg}"), &NoExtensions, &mut |_| lib.take());
+                        let exec = Executor::new(h, UnlimitedBudget);
+                        let root = runtime().block_on(exec.normalize_at(root));
+                        let printed = Printer::new(h).pretty(&root).to_string();
+                        exec.erase(h.pull(root));
+                        assert_eq!(h.arena_len(ArenaKind::Nodes), 0, "{printed}");
+                        printed
+                    });
+                    assert_eq!(printed, run(&format!("({lib}) {arg}")).unwrap());
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn open_terms_are_not_exported() {
+        Heap::new().with(|h| {
+            let root = lower(h, r"\x -> x + 1", &NoExtensions, &mut |_| None);
+            let Term::Lam { var, body } = h.pull(root) else {
+                panic!("not a lambda");
+            };
+            assert_eq!(h.export(&body).unwrap_err(), ImageError::FreeVariable);
+            let root = h.alloc(Term::Lam { var, body });
+            assert_eq!(h.export(&root).unwrap().node_count(), 4);
+            Executor::new(h, UnlimitedBudget).erase(h.pull(root));
+        });
+    }
+
+    #[test]
+    fn malformed_images_are_rejected() {
+        let decode = |bytes: &[u8]| Image::decode(bytes, &NoExtensions).unwrap_err();
+        assert_eq!(decode(b"not an image"), ImageError::NotAnImage);
+
+        let mut bytes = save(r"\x -> x", &NoExtensions, 0);
+        bytes[8..12].copy_from_slice(&(VERSION + 1).to_le_bytes());
+        assert_eq!(decode(&bytes), ImageError::UnsupportedVersion(VERSION + 1));
+
+        let bytes = save(r"\x -> x", &NoExtensions, 0);
+        assert!(matches!(
+            decode(&bytes[..bytes.len() - 1]),
+            ImageError::Malformed(_)
+        ));
+
+        // A root saved twice would be owned twice.
+        let bytes = Heap::new().with(|h| {
+            let root = lower(h, "1", &NoExtensions, &mut |_| None);
+            let mut image = Image::capture(h, &[&root]).unwrap();
+            image.roots.push(image.roots[0]);
+            image.encode(&NoExtensions).unwrap()
+        });
+        assert_eq!(
+            decode(&bytes),
+            ImageError::Malformed("node 0 has 2 owners".into())
+        );
+    }
+}
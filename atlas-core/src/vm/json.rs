@@ -0,0 +1,275 @@
+//! Readback of a normalized heap term as JSON, for scripts that want results as
+//! data rather than [`Printer`](crate::vm::printer::Printer) text.
+//!
+//! Values map onto their JSON counterparts: ints and finite floats are
+//! numbers, strings are strings, bools are bools, and a `Cons`/`Nil` chain is
+//! an array. Everything else is an object keyed by what it is:
+//!
+//! - `{"char": "a"}` and `{"bytes": [1, 2]}` for the scalars JSON has no form
+//!   for, and `{"float": "NaN"}` for a non-finite float;
+//! - `{"type": "Pair", "variant": null, "fields": [..]}` for any other
+//!   constructed value (either name is `null` when it has none);
+//! - `{"error": "division by zero in `/`"}` for an `Err`;
+//! - `{"opaque": "lambda"}` for a term that is not data — a lambda, a
+//!   superposition, a type, a primitive, or a redex left unreduced.
+//!
+//! Traversal only views the graph, like the printer's.
+
+use crate::error::PrimError;
+use crate::vm::exec::Counted;
+use crate::vm::heap::{Addr, Boxed, HeapScope, TermPtr};
+use crate::vm::term::Term;
+use serde_json::{Map, Value, json};
+use std::time::Duration;
+
+/// The JSON form of the term `root` points to.
+pub fn to_json<'h>(heap: &HeapScope<'h>, root: &TermPtr<'h>) -> Value {
+    value_at(heap, root.addr())
+}
+
+/// What it took to evaluate a term, reported beside its JSON value.
+pub struct EvalMetadata<'a, P> {
+    /// The policy reduction ran under, with its interaction counts.
+    pub policy: &'a Counted<P>,
+    /// The primitive failure that abandoned reduction, if one did.
+    pub extension_error: Option<&'a PrimError>,
+    pub elapsed: Duration,
+}
+
+impl<P> EvalMetadata<'_, P> {
+    /// `{"interactions": {"total": n, "AppLam": k, ..}, "extension_error": ..,
+    /// "elapsed_ms": ..}`, listing only the kinds of interaction that fired.
+    pub fn to_json(&self) -> Value {
+        let mut interactions = Map::new();
+        interactions.insert("total".to_string(), self.policy.total().into());
+        for (interaction, count) in self.policy.counts() {
+            interactions.insert(format!("{interaction:?}"), count.into());
+        }
+        json!({
+            "interactions": interactions,
+            "extension_error": self.extension_error.map(|e| e.to_string()),
+            "elapsed_ms": self.elapsed.as_secs_f64() * 1000.0,
+        })
+    }
+}
+
+/// The node at `addr` with any fired duplications it stands for looked
+/// through: a fired `Dup` projection is just a slot holding its side's value.
+fn resolve(heap: &HeapScope<'_>, mut addr: Addr) -> Addr {
+    loop {
+        let view = heap.view_at(addr);
+        match &*view {
+            Term::Dup { ptr, .. } if heap.dup_peek(ptr).is_none() => {
+                addr = heap.view_dup(ptr).0;
+            }
+            _ => return addr,
+        }
+    }
+}
+
+fn value_at(heap: &HeapScope<'_>, addr: Addr) -> Value {
+    let addr = resolve(heap, addr);
+    let view = heap.view_at(addr);
+    match &*view {
+        Term::Int(n) => (*n).into(),
+        Term::Float(x) if x.is_finite() => x.into_inner().into(),
+        Term::Float(x) => json!({ "float": x.to_string() }),
+        Term::Bool(b) => (*b).into(),
+        Term::Char(c) => json!({ "char": c.to_string() }),
+        Term::Box(v) => match heap.value_get(v) {
+            Boxed::Str(s) => Value::String(s.to_string()),
+            Boxed::Bytes(b) => json!({ "bytes": &b[..] }),
+        },
+        Term::Ctn { ty, arity, values } => {
+            let variant = heap.pack_name(values).map(|v| heap.variant_name(v));
+            if variant == Some("Cons") && *arity == 2 || variant == Some("Nil") && *arity == 0 {
+                return list_at(heap, addr);
+            }
+            let fields: Vec<Value> = (0..*arity as usize)
+                .map(|i| value_at(heap, heap.pack_addr(values, i)))
+                .collect();
+            json!({
+                "type": heap.type_name(ty.addr()).map(|name| name.to_string()),
+                "variant": variant,
+                "fields": fields,
+            })
+        }
+        Term::Err {
+            backtrace: Some(t), ..
+        } => json!({ "error": heap.trace_get(t).fault.to_string() }),
+        Term::Err { .. } => json!({ "error": null }),
+        term => json!({ "opaque": opaque_kind(term) }),
+    }
+}
+
+/// A `Cons`/`Nil` chain as an array. Walks the spine in a loop, so a long list
+/// costs no native stack; a spine that ends in anything but `Nil` gets it as
+/// its last element.
+fn list_at(heap: &HeapScope<'_>, mut addr: Addr) -> Value {
+    let mut items = Vec::new();
+    loop {
+        let view = heap.view_at(addr);
+        let next = match &*view {
+            Term::Ctn {
+                arity: 2, values, ..
+            } if heap.pack_name(values).map(|v| heap.variant_name(v)) == Some("Cons") => {
+                items.push(value_at(heap, heap.pack_addr(values, 0)));
+                heap.pack_addr(values, 1)
+            }
+            Term::Ctn {
+                arity: 0, values, ..
+            } if heap.pack_name(values).map(|v| heap.variant_name(v)) == Some("Nil") => {
+                return Value::Array(items);
+            }
+            _ => {
+                items.push(value_at(heap, addr));
+                return Value::Array(items);
+            }
+        };
+        addr = resolve(heap, next);
+    }
+}
+
+/// What a term that is not data is, for its `{"opaque": ..}` marker.
+fn opaque_kind(term: &Term<'_>) -> &'static str {
+    match term {
+        Term::Lam { .. } | Term::Use { .. } => "lambda",
+        Term::Sup { .. } => "superposition",
+        Term::Dup { .. } => "duplication",
+        Term::Type(_) => "type",
+        Term::Pri(_) => "primitive",
+        Term::Partial { .. } | Term::Ctr { .. } => "constructor",
+        Term::Mat { .. } => "match",
+        Term::Wld => "wildcard",
+        _ => "redex",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EvalMetadata, to_json};
+    use crate::core::ast::desugar;
+    use crate::core::expr::{Expr, TypeDefKind, Value as Lit};
+    use crate::core::parse::parse;
+    use crate::vm::exec::{Counted, Executor, InteractionType, UnlimitedBudget};
+    use crate::vm::heap::Heap;
+    use serde_json::{Value, json};
+    use std::time::Duration;
+
+    /// Normalize `src` and read it back as JSON.
+    fn eval_json(src: &str) -> Value {
+        eval_expr_json(&desugar(&parse(src).unwrap()).unwrap())
+    }
+
+    fn eval_expr_json(expr: &Expr) -> Value {
+        let heap = Heap::new();
+        heap.with(|h| {
+            let root = h.lower(expr, &|_| None, &mut |_| None).unwrap();
+            let exec = Executor::new(h, UnlimitedBudget);
+            let rt = tokio::runtime::Builder::new_current_thread()
+                .build()
+                .unwrap();
+            let root = rt.block_on(exec.normalize_at(root));
+            let value = to_json(h, &root);
+            exec.erase(h.pull(root));
+            value
+        })
+    }
+
+    #[test]
+    fn scalars_are_plain_json() {
+        assert_eq!(eval_json("1 + 2"), json!(3));
+        assert_eq!(eval_json("1.5"), json!(1.5));
+        assert_eq!(eval_json("1 < 2"), json!(true));
+        assert_eq!(eval_json(r#""hi""#), json!("hi"));
+        assert_eq!(eval_json("'a'"), json!({ "char": "a" }));
+    }
+
+    #[test]
+    fn lists_are_arrays() {
+        // List sugar needs the prelude's `List`; spell the variants out instead.
+        let list = r"&L = type{Nil, Cons(type(), type())};";
+        assert_eq!(
+            eval_json(&format!(
+                "{list} L::Cons 1 (L::Cons (2 * 3) (L::Cons (L::Cons true L::Nil) L::Nil))"
+            )),
+            json!([1, 6, [true]])
+        );
+        assert_eq!(eval_json(&format!("{list} L::Nil")), json!([]));
+    }
+
+    #[test]
+    fn constructors_carry_their_names() {
+        assert_eq!(
+            eval_json("type{Leaf, Node(type(), type())}::Node 1 2"),
+            json!({ "type": null, "variant": "Node", "fields": [1, 2] })
+        );
+        // A declared type, as the atlas `struct Pair { a: Int, b: Int }`
+        // lowers to, carries its name into its values.
+        let unit = || Expr::TypeDef {
+            name: None,
+            kind: TypeDefKind::Product(Vec::new()),
+        };
+        let app = |func, arg| Expr::App {
+            func: Box::new(func),
+            arg: Box::new(Expr::Value(Lit::Int(arg))),
+        };
+        let pair = Expr::Ctr {
+            ty: Box::new(Expr::TypeDef {
+                name: Some("Pair".into()),
+                kind: TypeDefKind::Product(vec![unit(), unit()]),
+            }),
+            variant: None,
+        };
+        assert_eq!(
+            eval_expr_json(&app(app(pair, 1), 2)),
+            json!({ "type": "Pair", "variant": null, "fields": [1, 2] })
+        );
+    }
+
+    #[test]
+    fn non_data_is_opaque() {
+        assert_eq!(eval_json(r"\x -> x"), json!({ "opaque": "lambda" }));
+        assert_eq!(eval_json("&{1, 2}"), json!({ "opaque": "superposition" }));
+        assert_eq!(
+            eval_json("1 / 0"),
+            json!({ "error": "division by zero in `/`" })
+        );
+    }
+
+    #[test]
+    fn metadata_counts_interactions_by_kind() {
+        let node = parse(r"(\x -> x + 1) 2").unwrap();
+        let expr = desugar(&node).unwrap();
+        let heap = Heap::new();
+        let value = heap.with(|h| {
+            let root = h.lower(&expr, &|_| None, &mut |_| None).unwrap();
+            let exec = Executor::new(h, Counted::new(UnlimitedBudget));
+            let rt = tokio::runtime::Builder::new_current_thread()
+                .build()
+                .unwrap();
+            let root = rt.block_on(exec.normalize_at(root));
+            exec.erase(h.pull(root));
+            let metadata = EvalMetadata {
+                policy: &exec.policy,
+                extension_error: None,
+                elapsed: Duration::from_millis(3),
+            };
+            assert_eq!(exec.policy.count(InteractionType::AppLam), 1);
+            metadata.to_json()
+        });
+        assert_eq!(value["interactions"]["AppLam"], json!(1));
+        assert_eq!(value["interactions"]["BopVal"], json!(1));
+        assert_eq!(value["interactions"]["total"], json!(2));
+        assert!(value["interactions"].get("DupLam").is_none());
+        assert_eq!(value["extension_error"], Value::Null);
+        assert_eq!(value["elapsed_ms"], json!(3.0));
+    }
+
+    #[test]
+    fn every_interaction_kind_is_listed_at_its_index() {
+        for (i, interaction) in InteractionType::ALL.into_iter().enumerate() {
+            assert_eq!(interaction as usize, i, "{interaction:?}");
+        }
+    }
+}
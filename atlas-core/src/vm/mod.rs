@@ -1,47 +1,129 @@
 pub mod exec;
 pub mod heap;
+pub mod image;
+pub mod json;
 pub mod printer;
 pub mod term;
+pub mod trace;
+pub mod type_eval;
 
-use crate::core::ast::desugar;
-use crate::core::parse::parse;
+use crate::Error;
+use crate::core::ast::desugar_spanned;
+use crate::core::parse::parse_spanned;
+use crate::core::span::NodeSpans;
 use crate::extension::{Extensions, NoExtensions};
-use exec::{Executor, UnlimitedBudget};
-use heap::Heap;
+use exec::{ExecPolicy, Executor, FiniteBudget, UnlimitedBudget};
+use heap::{Heap, HeapScope, TermPtr};
 use printer::Printer;
 
 /// Parse, desugar, lower, normalize, and pretty-print a source expression.
-pub fn run(src: &str) -> Result<String, String> {
+pub fn run(src: &str) -> Result<String, Error> {
     run_with(src, &NoExtensions)
 }
 
 /// Like [`run`], but with a host-provided primitive [`Extensions`] set.
-pub fn run_with<X: Extensions>(src: &str, ext: &X) -> Result<String, String> {
-    let node = parse(src)?;
-    let expr = desugar(&node)?;
-    let rt = tokio::runtime::Builder::new_current_thread()
+pub fn run_with<X: Extensions>(src: &str, ext: &X) -> Result<String, Error> {
+    run_on(&current_thread()?, src, ext, None, None)
+}
+
+/// Like [`run_with`], but give up with [`Error::BudgetExhausted`] once
+/// normalization has taken `budget` interactions.
+pub fn run_with_budget<X: Extensions>(src: &str, ext: &X, budget: u64) -> Result<String, Error> {
+    run_on(&current_thread()?, src, ext, Some(budget), None)
+}
+
+/// Like [`run_with`], but normalize on a multi-threaded runtime with `workers`
+/// worker threads, forking independent subterms onto them (see
+/// [`Executor::parallel`]).
+pub fn run_parallel_with<X: Extensions>(
+    src: &str,
+    ext: &X,
+    workers: usize,
+) -> Result<String, Error> {
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(workers)
         .enable_all()
         .build()
-        .map_err(|e| e.to_string())?;
+        .map_err(|e| Error::Runtime(e.to_string()))?;
+    // A few forks per worker keep every worker busy when some forked redexes
+    // turn out to be small.
+    run_on(&rt, src, ext, None, Some(4 * workers))
+}
+
+fn current_thread() -> Result<tokio::runtime::Runtime, Error> {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| Error::Runtime(e.to_string()))
+}
+
+fn run_on<X: Extensions>(
+    rt: &tokio::runtime::Runtime,
+    src: &str,
+    ext: &X,
+    budget: Option<u64>,
+    max_forks: Option<usize>,
+) -> Result<String, Error> {
+    let (node, log) = parse_spanned(src)?;
+    let spans = NodeSpans::new([&node], log);
+    let expr = desugar_spanned(&node, &spans)?;
     let heap = Heap::new();
     heap.with(|h| {
         let resolve = |n: &str| ext.resolve(n);
         // A closed program (run via `run_with`) has no free REPL locals.
         let root = h.lower(&expr, &resolve, &mut |_| None)?;
-        let exec = Executor::with_extensions(h, UnlimitedBudget, ext);
-        let result = rt.block_on(exec.normalize_at(root));
-        if let Some(error) = exec.take_extension_error() {
-            return Err(error);
+        match budget {
+            None => normalize(rt, h, root, ext, UnlimitedBudget, None, max_forks),
+            Some(budget) => normalize(
+                rt,
+                h,
+                root,
+                ext,
+                FiniteBudget::new(budget),
+                Some(budget),
+                max_forks,
+            ),
         }
-        Ok(format!("{}", Printer::new(h).pretty(&result)))
     })
 }
 
+/// Normalize `root` under `policy` and print it. `budget` is what `policy`
+/// limits reduction to, if anything: a policy that stops reduction has run out
+/// of it.
+fn normalize<'h, X: Extensions, P: ExecPolicy>(
+    rt: &tokio::runtime::Runtime,
+    h: &'h HeapScope<'h>,
+    root: TermPtr<'h>,
+    ext: &X,
+    policy: P,
+    budget: Option<u64>,
+    max_forks: Option<usize>,
+) -> Result<String, Error> {
+    let mut exec = Executor::with_extensions(h, policy, ext);
+    if let Some(max_forks) = max_forks {
+        exec = exec.parallel(max_forks);
+    }
+    let result = rt.block_on(exec.normalize_at(root));
+    if let Some(error) = exec.take_extension_error() {
+        return Err(error.into());
+    }
+    if let Some(budget) = budget
+        && !exec.policy.should_continue()
+    {
+        exec.erase(h.pull(result));
+        return Err(Error::BudgetExhausted { budget });
+    }
+    Ok(format!("{}", Printer::new(h).pretty(&result)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::exec::{ExecPolicy, Executor};
-    use super::{run, run_with};
-    use crate::extension::{Extensions, Handle, PrimReduce};
+    use super::{run, run_parallel_with, run_with, run_with_budget};
+    use crate::Error;
+    use crate::error::{DesugarError, DesugarErrorKind};
+    use crate::extension::NoExtensions;
+    use crate::extension::{Extensions, Handle, PrimError, PrimReduce};
     use crate::vm::term::{PrimId, Term};
     use std::borrow::Cow;
 
@@ -101,7 +183,7 @@ mod tests {
             Box::pin(async move {
                 let mut it = args.into_iter();
                 if id.get() == 4 {
-                    return Err("primitive failed".to_string());
+                    return Err(PrimError::new(id, "primitive failed"));
                 }
                 let result = match id.get() {
                     0 => {
@@ -149,8 +231,21 @@ mod tests {
     fn primitive_errors_reach_run_with() {
         assert_eq!(
             run_with(r"%fail", &Arith),
-            Err("primitive failed".to_string())
+            Err(Error::Primitive(PrimError::new(
+                PrimId::new(4),
+                "primitive failed"
+            )))
+        );
+    }
+
+    #[test]
+    fn budget_exhaustion_is_an_error() {
+        let countdown = r"(fix \&f x -> ?{0 -> 0; &n -> f (n - 1)} x) 100000";
+        assert_eq!(
+            run_with_budget(countdown, &NoExtensions, 1_000),
+            Err(Error::BudgetExhausted { budget: 1_000 })
         );
+        assert_eq!(run_with_budget("1 + 2", &NoExtensions, 1_000).unwrap(), "3");
     }
 
     #[test]
@@ -172,6 +267,95 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parallel_run_matches_sequential() {
+        let fib =
+            r"&fib = fix \&fib x -> ?{0 -> 1; 1 -> 1; &x -> (fib (x - 1)) + (fib (x - 2))} x; ";
+        for src in [
+            format!("{fib}fib 8"),
+            format!("{fib}Pair = type (type (), type (), type ()); Pair::New (fib 5) (fib 6) (fib 7)"),
+            format!("{fib}&{{fib 5, fib 6}} + 1"),
+            r"&x = \y -> (2 + 1) * y; (x 1) + (x 2) + (x 3)".to_string(),
+            r"(&{\x -> x, \y -> y}) 5".to_string(),
+            // Fields that share no dup with one another are forked.
+            r"Pair = type (type (), type ()); Pair::New ((\&x -> x * x) 7) ((\y -> y + 1) 2)".to_string(),
+            r"Pair = type (type (), type ()); Pair::New ((fix \&f x -> ?{0 -> 0; &x -> (f (x - 1)) + 1} x) 30) ((fix \&g x -> ?{0 -> 1; &x -> (g (x - 1)) * 2} x) 10)".to_string(),
+        ] {
+            assert_eq!(run_parallel_with(&src, &NoExtensions, 4), run(&src), "{src}");
+        }
+        assert_eq!(
+            run_parallel_with(r"%add (%mul 2 3) (%inc 3)", &Arith, 2).unwrap(),
+            "10"
+        );
+    }
+
+    #[test]
+    fn parallel_forks_construction_fields_not_op_operands() {
+        use super::exec::UnlimitedBudget;
+        use super::heap::Heap;
+        use super::printer::Printer;
+        use crate::core::ast::desugar;
+        use crate::core::parse::parse;
+
+        let op = r"((fix \&f x -> ?{0 -> 0; &x -> (f (x - 1)) + 1} x) 30) + ((fix \&g x -> ?{0 -> 1; &x -> (g (x - 1)) * 2} x) 10)";
+        let fields = r"W = type (type (), type ()); W::New ((fix \&f x -> ?{0 -> 0; &x -> (f (x - 1)) + 1} x) 30) ((fix \&g x -> ?{0 -> 1; &x -> (g (x - 1)) * 2} x) 10)";
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .enable_all()
+            .build()
+            .unwrap();
+        for (src, expected, forks) in [(op, "1054", false), (fields, "<type>{30, 1024}", true)] {
+            let expr = desugar(&parse(src).unwrap()).unwrap();
+            for parallel in [false, true] {
+                let heap = Heap::new();
+                heap.with(|h| {
+                    let root = h.lower(&expr, &|_| None, &mut |_| None).unwrap();
+                    let mut exec = Executor::new(h, UnlimitedBudget);
+                    if parallel {
+                        exec = exec.parallel(2);
+                    }
+                    let result = rt.block_on(exec.normalize_at(root));
+                    assert_eq!(format!("{}", Printer::new(h).pretty(&result)), expected);
+                    // The operands of a binary op are too small to pay for a
+                    // fork; the fields of a construction are not.
+                    assert_eq!(
+                        exec.forked() > 0,
+                        parallel && forks,
+                        "{src}, parallel: {parallel}"
+                    );
+                });
+            }
+        }
+    }
+
+    #[test]
+    fn parallel_run_propagates_forked_panics() {
+        struct Boom;
+        impl Extensions for Boom {
+            fn resolve(&self, name: &str) -> Option<PrimId> {
+                (name == "boom").then(|| PrimId::new(0))
+            }
+            fn arity(&self, _: PrimId) -> usize {
+                1
+            }
+            fn name(&self, _: PrimId) -> Option<Cow<'_, str>> {
+                Some(Cow::Borrowed("boom"))
+            }
+            fn apply<'a, 'e, 'h, P: ExecPolicy, X: Extensions>(
+                &'a self,
+                _: &'a Executor<'e, 'h, P, X>,
+                _: PrimId,
+                _: Vec<Handle<'h>>,
+            ) -> PrimReduce<'a, 'h> {
+                Box::pin(async { panic!("boom") })
+            }
+        }
+        let result = std::panic::catch_unwind(|| {
+            run_parallel_with(r"(%boom 1 + 2) + (%boom 3 + 4)", &Boom, 2)
+        });
+        assert!(result.is_err());
+    }
+
     #[test]
     fn auto_dup() {
         // `\&x -> x + x` duplicates its argument (the dup value is the binder,
@@ -290,12 +474,12 @@ mod tests {
         assert_eq!(run(r"6 / 2").unwrap(), "3.0");
         assert_eq!(run(r"7.0 / 2.0").unwrap(), "3.5");
         // division by zero is an Err, not `inf`.
-        assert_eq!(run(r"7 / 0").unwrap(), "<err>");
-        assert_eq!(run(r"7.0 / 0.0").unwrap(), "<err>");
+        assert_eq!(run(r"7 / 0").unwrap(), "<err: division by zero in `/`>");
+        assert_eq!(run(r"7.0 / 0.0").unwrap(), "<err: division by zero in `/`>");
         // `~/` is floor (integer) division.
         assert_eq!(run(r"7 ~/ 2").unwrap(), "3");
         assert_eq!(run(r"-7 ~/ 2").unwrap(), "-4");
-        assert_eq!(run(r"7 ~/ 0").unwrap(), "<err>");
+        assert_eq!(run(r"7 ~/ 0").unwrap(), "<err: division by zero in `~/`>");
         // `~/` with a float operand floor-divides and keeps a float.
         assert_eq!(run(r"7.0 ~/ 2").unwrap(), "3.0");
         // `//` remains a line comment, so the trailing text is ignored.
@@ -305,8 +489,14 @@ mod tests {
     #[test]
     fn uncovered_match_is_err() {
         // A concrete value with no covering case and no default is a runtime err.
-        assert_eq!(run(r"?{1 -> 100; 2 -> 200} 3").unwrap(), "<err>");
-        assert_eq!(run(r"?{true -> 1} false").unwrap(), "<err>");
+        assert_eq!(
+            run(r"?{1 -> 100; 2 -> 200} 3").unwrap(),
+            "<err: no case matches 3>"
+        );
+        assert_eq!(
+            run(r"?{true -> 1} false").unwrap(),
+            "<err: no case matches false>"
+        );
         // A default still covers the otherwise-uncovered scrutinee.
         assert_eq!(run(r"?{1 -> 100; _ -> 0} 3").unwrap(), "0");
     }
@@ -378,7 +568,7 @@ mod tests {
         // prefix binds tighter than infix: `-2 + 3` == `(-2) + 3`.
         assert_eq!(run(r"-2 + 3").unwrap(), "1");
         // unary not is unsupported on a char -> Err.
-        assert_eq!(run(r"~'a'").unwrap(), "<err>");
+        assert_eq!(run(r"~'a'").unwrap(), "<err: `~` is not defined for 'a'>");
     }
 
     #[test]
@@ -408,15 +598,24 @@ mod tests {
     #[test]
     fn invalid_ops_become_err() {
         // division / modulo / integer-division by zero.
-        assert_eq!(run(r"1 / 0").unwrap(), "<err>");
-        assert_eq!(run(r"1 % 0").unwrap(), "<err>");
-        assert_eq!(run(r"1 ~/ 0").unwrap(), "<err>");
+        assert_eq!(run(r"1 / 0").unwrap(), "<err: division by zero in `/`>");
+        assert_eq!(run(r"1 % 0").unwrap(), "<err: division by zero in `%`>");
+        assert_eq!(run(r"1 ~/ 0").unwrap(), "<err: division by zero in `~/`>");
         // type mismatch between concrete values (numeric vs non-numeric).
-        assert_eq!(run(r"1 + 'a'").unwrap(), "<err>");
+        assert_eq!(
+            run(r"1 + 'a'").unwrap(),
+            "<err: `+` is not defined for 1 and 'a'>"
+        );
         // op unsupported for the operand type.
-        assert_eq!(run(r"true + true").unwrap(), "<err>");
+        assert_eq!(
+            run(r"true + true").unwrap(),
+            "<err: `+` is not defined for true and true>"
+        );
         // bitwise ops are invalid on floats.
-        assert_eq!(run(r"1.0 && 2.0").unwrap(), "<err>");
+        assert_eq!(
+            run(r"1.0 && 2.0").unwrap(),
+            "<err: `&` is not defined for 1.0 and 2.0>"
+        );
     }
 
     #[test]
@@ -429,24 +628,42 @@ mod tests {
         assert_eq!(run(r#""foo" + "bar""#).unwrap(), r#""foobar""#);
         assert_eq!(run(r#""" + "x""#).unwrap(), r#""x""#);
         // an unsupported op on strings is an error.
-        assert_eq!(run(r#""a" - "b""#).unwrap(), "<err>");
+        assert_eq!(
+            run(r#""a" - "b""#).unwrap(),
+            "<err: `-` is not defined for a string and a string>"
+        );
         // a string compared against a scalar is a type mismatch -> error.
-        assert_eq!(run(r#""a" == 1"#).unwrap(), "<err>");
+        assert_eq!(
+            run(r#""a" == 1"#).unwrap(),
+            "<err: `==` is not defined for a string and 1>"
+        );
     }
 
     #[test]
     fn err_bubbles_up_when_forced() {
         // div-by-zero produces an Err that propagates through enclosing ops
         // instead of getting stuck.
-        assert_eq!(run(r"(1 / 0) + 5").unwrap(), "<err>");
-        assert_eq!(run(r"5 + (1 / 0)").unwrap(), "<err>");
-        assert_eq!(run(r"-(1 / 0)").unwrap(), "<err>");
-        assert_eq!(run(r"~(1 / 0)").unwrap(), "<err>");
+        assert_eq!(
+            run(r"(1 / 0) + 5").unwrap(),
+            "<err: division by zero in `/`>"
+        );
+        assert_eq!(
+            run(r"5 + (1 / 0)").unwrap(),
+            "<err: division by zero in `/`>"
+        );
+        assert_eq!(run(r"-(1 / 0)").unwrap(), "<err: division by zero in `/`>");
+        assert_eq!(run(r"~(1 / 0)").unwrap(), "<err: division by zero in `/`>");
         // applied as a function, an Err erases its argument and bubbles up.
-        assert_eq!(run(r"(1 / 0) 5").unwrap(), "<err>");
-        assert_eq!(run(r"(1 / 0) 5 6 7").unwrap(), "<err>");
+        assert_eq!(run(r"(1 / 0) 5").unwrap(), "<err: division by zero in `/`>");
+        assert_eq!(
+            run(r"(1 / 0) 5 6 7").unwrap(),
+            "<err: division by zero in `/`>"
+        );
         // nested: the Err threads all the way out.
-        assert_eq!(run(r"((1 / 0) 5) + 2").unwrap(), "<err>");
+        assert_eq!(
+            run(r"((1 / 0) 5) + 2").unwrap(),
+            "<err: division by zero in `/`>"
+        );
     }
 
     #[test]
@@ -465,9 +682,14 @@ mod tests {
         assert_eq!(run(r"3.14").unwrap(), "3.14");
         assert_eq!(run(r"3.0").unwrap(), "3.0");
         // a capitalized word is now an ordinary variable (here unbound), not a bool
-        assert!(run(r"True").is_err());
+        assert_eq!(
+            run(r"True"),
+            Err(Error::Desugar(
+                DesugarError::new(DesugarErrorKind::UnboundVariable("True".into())).or_span(0..4)
+            ))
+        );
         // `truex` is an ordinary identifier, not the `true` keyword
-        assert!(run(r"truex").is_err());
+        assert!(matches!(run(r"truex"), Err(Error::Desugar(e)) if e.name() == Some("truex")));
     }
 
     #[test]
@@ -489,6 +711,8 @@ mod tests {
 mod type_system_tests {
     use super::exec::{ExecPolicy, Executor, InteractionType};
     use super::run;
+    use crate::Error;
+    use crate::error::{DesugarError, DesugarErrorKind};
     use crate::vm::term::Term;
     use std::collections::HashMap;
     use std::sync::Mutex;
@@ -557,15 +781,36 @@ mod type_system_tests {
         // Applying a bare type value (without `::New`) is an error.
         assert_eq!(
             run(r"Foo = type (type (), type ()); Foo 1 2").unwrap(),
-            "<err>"
+            "<err: a type applied to an argument>"
+        );
+    }
+
+    #[test]
+    fn product_matches_on_new() {
+        // A product has no variant: a `New` case takes it apart, and any other
+        // variant name misses.
+        assert_eq!(
+            run(r"Foo = type (type (), type ()); ?{New a b -> a - b} (Foo::New 5 2)").unwrap(),
+            "3"
+        );
+        assert_eq!(
+            run(r"Foo = type (type ()); ?{Some a -> a; _ -> 0} (Foo::New 5)").unwrap(),
+            "0"
         );
     }
 
     #[test]
     fn new_is_reserved_as_variant_name() {
         // `New` names the product constructor, so it cannot be a sum variant.
-        assert!(run(r"type { New }").is_err());
-        assert!(run(r"type { New, Some(type ()) }").is_err());
+        for src in [r"type { New }", r"type { New, Some(type ()) }"] {
+            assert!(matches!(
+                run(src),
+                Err(Error::Desugar(DesugarError {
+                    kind: DesugarErrorKind::Malformed(_),
+                    ..
+                }))
+            ));
+        }
     }
 
     #[test]
@@ -614,6 +859,144 @@ mod type_system_tests {
         );
     }
 
+    #[test]
+    fn projections_check_the_demanded_value() {
+        // PROJECT-OK hands the value back unchanged.
+        assert_eq!(run(r"1 :> typeof 0").unwrap(), "1");
+        assert_eq!(run(r"((\x -> x) :> typeof (\y -> y)) 3").unwrap(), "3");
+        let opt = r"&Opt = \T -> type { Some(T), None }; ";
+        assert_eq!(
+            run(&format!("{opt}(Opt (typeof 0))::Some 7 :> Opt (typeof 0)")).unwrap(),
+            "Some{7}"
+        );
+        // A narrower sum and a wider product satisfy.
+        assert_eq!(
+            run(&format!(
+                "{opt}(type {{ Some(typeof 0) }})::Some 7 :> Opt (typeof 0)"
+            ))
+            .unwrap(),
+            "Some{7}"
+        );
+        assert_eq!(
+            run(r"(type (typeof 0, typeof true))::New 1 true :> type (typeof 0)").unwrap(),
+            "<type>{1, true}"
+        );
+        // PROJECT-FAIL, on the value's head or on a sub-type.
+        assert_eq!(
+            run(r"1 :> typeof true").unwrap(),
+            "<err: 1 does not satisfy type Bool>"
+        );
+        assert_eq!(
+            run(&format!(
+                "{opt}(Opt (typeof 0))::Some 7 :> Opt (typeof true)"
+            ))
+            .unwrap(),
+            "<err: Some{..} does not satisfy a type>"
+        );
+        assert_eq!(
+            run(r"1 :> 2").unwrap(),
+            "<err: projection onto 2, which is not a type>"
+        );
+    }
+
+    #[test]
+    fn projections_are_lazy() {
+        // An erased argument or an untaken branch is never checked.
+        assert_eq!(run(r"(\_ -> 5) (1 :> typeof true)").unwrap(), "5");
+        assert_eq!(
+            run(r"?{true -> 1; false -> 2 :> typeof true} true").unwrap(),
+            "1"
+        );
+        // A value with no head yet leaves the projection stuck.
+        assert_eq!(run(r"\x -> x :> typeof 0").unwrap(), r"\a -> (a :> Int)");
+        assert_eq!(run(r"(\T -> 1 :> T) (typeof 0)").unwrap(), "1");
+    }
+
+    #[test]
+    fn projections_distribute() {
+        // PROJECT-SUP checks every alternative.
+        assert_eq!(
+            run(r"&{1, true} :> typeof 0").unwrap(),
+            "&{1, <err: true does not satisfy type Int>}"
+        );
+        assert_eq!(run(r"(\&x -> x + x) (1 :> typeof 0)").unwrap(), "2");
+        // A stuck projection is duplicated value and type alike.
+        assert_eq!(
+            run(r"\y -> (\&x -> x + x) (y :> typeof 0)").unwrap(),
+            "&{a, b} = c;\n\\c -> ((a :> Int) + (b :> Int))"
+        );
+    }
+
+    #[test]
+    fn universes_are_cumulative() {
+        assert_eq!(run(r"typeof Prop").unwrap(), "Type_0");
+        assert_eq!(run(r"typeof Type_0").unwrap(), "Type_1");
+        assert_eq!(run(r"typeof (typeof 0)").unwrap(), "Type_0");
+        // PI-TYPE takes the larger universe; PI-PROP keeps a proposition
+        // impredicative.
+        assert_eq!(
+            run(r"typeof (pi (x: typeof 0) -> Type_2)").unwrap(),
+            "Type_3"
+        );
+        assert_eq!(
+            run(r"typeof (pi (&A: Type_1) (x: A) -> A)").unwrap(),
+            "Type_2"
+        );
+        assert_eq!(run(r"typeof (pi (&P: Prop) (p: P) -> P)").unwrap(), "Prop");
+        // A type projects onto every universe above its own.
+        assert_eq!(run(r"typeof 0 :> Type_0").unwrap(), "Int");
+        assert_eq!(run(r"Prop :> Type_0").unwrap(), "Prop");
+        assert_eq!(run(r"Type_0 :> Type_1").unwrap(), "Type_0");
+        assert_eq!(
+            run(r"Type_0 :> Type_0").unwrap(),
+            "<err: type Type_0 does not satisfy type Type_0>"
+        );
+        assert_eq!(
+            run(r"1 :> Type_0").unwrap(),
+            "<err: 1 does not satisfy type Type_0>"
+        );
+    }
+
+    #[test]
+    fn products_print_their_binder() {
+        assert_eq!(
+            run(r"pi (x: typeof 0) -> typeof 0").unwrap(),
+            "pi (_: (typeof 0)) -> (typeof 0)"
+        );
+        assert_eq!(
+            run(r"pi (&A: Type_0) {x: A} -> A").unwrap(),
+            "&{a, b} = c;\npi (c: Type_0) -> pi {_: a} -> b"
+        );
+    }
+
+    #[test]
+    fn conversion_compares_by_reduction() {
+        for (source, conv) in [
+            (r"(1 + 1) === 2", "true"),
+            (r"1 === true", "false"),
+            (r"Type_0 === Type_1", "false"),
+            (r"type { A, B } === type { B, A }", "true"),
+            (
+                r"(type (typeof 0))::New 1 === (type (typeof 0))::New 2",
+                "false",
+            ),
+            // Functions compare at a shared rigid argument, without eta.
+            (r"(\x -> x + 1) === (\y -> y + 1)", "true"),
+            (r"(\x -> x + 1) === (\y -> 1 + y)", "false"),
+            (r"(\x -> \y -> x) === (\x -> \y -> y)", "false"),
+            (
+                r"(pi (&A: Type_0) (x: A) -> A) === (pi (&B: Type_0) (y: B) -> B)",
+                "true",
+            ),
+            (r"(pi (A: Type_0) -> A) === (pi {A: Type_0} -> A)", "false"),
+        ] {
+            assert_eq!(run(source).unwrap(), conv, "{source}");
+        }
+        // A superposition distributes; an operand with no head stays stuck.
+        assert_eq!(run(r"&{1, 2} === 1").unwrap(), "&{true, false}");
+        assert_eq!(run(r"\x -> x === 1").unwrap(), r"\a -> (a === 1)");
+    }
+
     #[test]
     fn constructors_saturate() {
         // A constructor accepts exactly its declared field count; further
@@ -815,6 +1198,7 @@ mod dup_drop_tests {
     use super::exec::{ExecPolicy, Executor, InteractionType, UnlimitedBudget};
     use super::heap::{ArenaKind, DupDrop, Heap, MatchData};
     use super::printer::Printer;
+    use super::run;
     use crate::core::ast::desugar;
     use crate::core::parse::parse;
     use crate::vm::term::{BinaryOp, LabelId, Term};
@@ -1126,6 +1510,24 @@ mod dup_drop_tests {
         });
     }
 
+    #[test]
+    fn elided_copy_waits_for_same_label_sups() {
+        // Each copy of `f` drops the projections of `n` in the arms it does not
+        // take, while the copies' own superpositions of `n` (under the labels
+        // of `f`'s dups) are still live: forcing the surviving projection must
+        // copy the value and pick its side, not hand the superpositions through.
+        let f =
+            r"(\h &n -> (?{0 -> \k -> 0; 1 -> \k -> k (n - 1) + 1; _ -> \k -> k (n - 1) + 2} n) h)";
+        assert_eq!(
+            run(&format!(r"(\&f -> f (f (f (\_ -> 0))) 2) {f}")).unwrap(),
+            "3"
+        );
+        assert_eq!(
+            run(r"fix (\&f n -> ?{0 -> 0; 2 -> f 1; _ -> f 0} n) 2").unwrap(),
+            "0"
+        );
+    }
+
     #[test]
     fn label_cleanup_waits_for_all_live_dups() {
         let heap = Heap::new();
@@ -1242,3 +1644,482 @@ mod dup_drop_tests {
         });
     }
 }
+
+#[cfg(test)]
+mod collapse_tests {
+    use super::exec::{CollapseOrder, ExecPolicy, Executor, FiniteBudget, UnlimitedBudget};
+    use super::heap::{ArenaKind, Heap};
+    use super::printer::Printer;
+    use super::run;
+    use crate::core::ast::desugar;
+    use crate::core::parse::parse;
+
+    /// Collapse `src` under `policy`, printing up to `limit` answers, and check
+    /// that the answers and the unexplored branches are all reclaimed (unless
+    /// the policy interrupted a reduction, whose erasure is not leak-free).
+    fn collapse_with<P: ExecPolicy>(
+        src: &str,
+        order: CollapseOrder,
+        limit: usize,
+        policy: P,
+    ) -> (Vec<String>, usize) {
+        let expr = desugar(&parse(src).unwrap()).unwrap();
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let heap = Heap::new();
+        heap.with(|h| {
+            let root = h.lower(&expr, &|_| None, &mut |_| None).unwrap();
+            let exec = Executor::new(h, policy);
+            let mut answers = Vec::new();
+            let mut collapse = exec.collapse(root).order(order).limit(limit);
+            while let Some(answer) = rt.block_on(collapse.next()) {
+                answers.push(Printer::new(h).pretty(&answer).to_string());
+                exec.erase(h.pull(answer));
+            }
+            let pending = collapse.pending();
+            drop(collapse);
+            if exec.policy.should_continue() {
+                assert_eq!(h.arena_len(ArenaKind::Nodes), 0, "{src}");
+            }
+            (answers, pending)
+        })
+    }
+
+    fn collapse(src: &str, order: CollapseOrder) -> Vec<String> {
+        collapse_with(src, order, usize::MAX, UnlimitedBudget).0
+    }
+
+    #[test]
+    fn answers_come_one_per_branch() {
+        use CollapseOrder::*;
+        assert_eq!(collapse(r"&{1, 2} + 10", BreadthFirst), ["11", "12"]);
+        assert_eq!(collapse(r"\x -> x", BreadthFirst), [r"\a -> a"]);
+        // Breadth-first takes the shallow answer first.
+        assert_eq!(collapse(r"&{&{1, 2}, 3}", BreadthFirst), ["3", "1", "2"]);
+        assert_eq!(collapse(r"&{&{1, 2}, 3}", DepthFirst), ["1", "2", "3"]);
+    }
+
+    #[test]
+    fn copies_of_one_superposition_choose_together() {
+        // Both copies of `x` carry the superposition's label, so of the four
+        // combinations only two are consistent.
+        let src = r"Foo = type (type (), type ()); (\&x -> Foo::New x (x * 10)) &{1, 2}";
+        assert_eq!(run(src).unwrap(), "<type>{&{1, 2}, &{10, 20}}");
+        assert_eq!(
+            collapse(src, CollapseOrder::BreadthFirst),
+            ["<type>{1, 10}", "<type>{2, 20}"]
+        );
+    }
+
+    #[test]
+    fn matches_distribute_over_alternatives() {
+        let src = r"?{1 -> 10; _ -> 20} &{1, &{2, 1}}";
+        assert_eq!(run(src).unwrap(), "&{10, &{20, 10}}");
+        assert_eq!(collapse(src, CollapseOrder::DepthFirst), ["10", "20", "10"]);
+    }
+
+    #[test]
+    fn superpositions_lift_out_of_constructors() {
+        let foo = r"Foo = type (type (), type ()); ";
+        assert_eq!(
+            collapse(
+                &format!("{foo}Foo::New &{{1, 2}} &{{3, 4}}"),
+                CollapseOrder::BreadthFirst
+            ),
+            [
+                "<type>{1, 3}",
+                "<type>{1, 4}",
+                "<type>{2, 3}",
+                "<type>{2, 4}"
+            ]
+        );
+    }
+
+    #[test]
+    fn enumeration_stops_at_the_limit_or_budget() {
+        let (answers, pending) = collapse_with(
+            r"&{&{1, 2}, &{3, 4}}",
+            CollapseOrder::DepthFirst,
+            3,
+            UnlimitedBudget,
+        );
+        assert_eq!(answers, ["1", "2", "3"]);
+        assert_eq!(pending, 1);
+        // A branch the budget interrupts stays pending, after the answers
+        // found before it.
+        let countdown = r"(fix \&f x -> ?{0 -> 0; &n -> f (n - 1)} x) 100000";
+        let (answers, pending) = collapse_with(
+            &format!("&{{7, {countdown}}}"),
+            CollapseOrder::BreadthFirst,
+            usize::MAX,
+            FiniteBudget::new(1_000),
+        );
+        assert_eq!(answers, ["7"]);
+        assert_eq!(pending, 1);
+    }
+}
+
+#[cfg(test)]
+mod trace_tests {
+    use super::exec::{ExecPolicy, Executor, InteractionType, UnlimitedBudget};
+    use super::heap::{ArenaKind, Heap, HeapScope};
+    use super::trace::{Fault, Frame, Head, Trace};
+    use crate::core::ast::desugar_spanned;
+    use crate::core::parse::parse_spanned;
+    use crate::core::span::NodeSpans;
+    use crate::extension::{Extensions, Handle, PrimError, PrimReduce};
+    use crate::vm::term::{BinaryOp, PrimId, Term};
+    use std::borrow::Cow;
+
+    /// A single nullary primitive, `%boom`, that always fails.
+    struct Boom;
+
+    impl Extensions for Boom {
+        fn resolve(&self, name: &str) -> Option<PrimId> {
+            (name == "boom").then(|| PrimId::new(0))
+        }
+        fn arity(&self, _: PrimId) -> usize {
+            0
+        }
+        fn name(&self, _: PrimId) -> Option<Cow<'_, str>> {
+            Some(Cow::Borrowed("boom"))
+        }
+        fn apply<'a, 'e, 'h, P: ExecPolicy, X: Extensions>(
+            &'a self,
+            _: &'a Executor<'e, 'h, P, X>,
+            id: PrimId,
+            _: Vec<Handle<'h>>,
+        ) -> PrimReduce<'a, 'h> {
+            Box::pin(async move { Err(PrimError::new(id, "out of fuel")) })
+        }
+    }
+
+    /// Normalize `src` to an `Err` and return its trace, checking that erasing
+    /// the result reclaims the trace.
+    fn trace_of(src: &str) -> Trace {
+        let (node, log) = parse_spanned(src).unwrap();
+        let expr = desugar_spanned(&node, &NodeSpans::new([&node], log)).unwrap();
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let heap = Heap::new();
+        heap.with(|h: &HeapScope| {
+            let root = h.lower(&expr, &|n| Boom.resolve(n), &mut |_| None).unwrap();
+            let exec = Executor::with_extensions(h, UnlimitedBudget, &Boom);
+            let result = rt.block_on(exec.normalize_at(root));
+            let trace = match &*h.view(&result) {
+                Term::Err {
+                    backtrace: Some(t), ..
+                } => h.trace_get(t).clone(),
+                _ => panic!("{src} did not reduce to a traced error"),
+            };
+            exec.erase(h.pull(result));
+            assert_eq!(h.arena_len(ArenaKind::Traces), 0);
+            trace
+        })
+    }
+
+    #[test]
+    fn trace_records_the_failing_interaction() {
+        let trace = trace_of(r"1 + 'a'");
+        assert_eq!(
+            trace.fault,
+            Fault::BadOperands {
+                op: BinaryOp::Add,
+                lhs: Head::Int(1),
+                rhs: Head::Char('a'),
+            }
+        );
+        assert_eq!(trace.interaction, InteractionType::BopVal);
+        assert!(trace.frames.is_empty());
+    }
+
+    #[test]
+    fn trace_records_where_the_error_bubbled() {
+        let trace = trace_of(r"((1 / 0) 5) + 2");
+        assert_eq!(trace.fault, Fault::DivisionByZero { op: BinaryOp::Div });
+        assert_eq!(trace.frames, [Frame::Applied, Frame::Binary(BinaryOp::Add)]);
+        assert_eq!(
+            trace.to_string(),
+            "division by zero in `/` (BopVal at 2..7)\n  applied to an argument\n  in an operand of `+`"
+        );
+    }
+
+    #[test]
+    fn duplicated_errors_share_a_trace() {
+        // Both copies carry the trace; the one erased by `+` releases its own.
+        let trace = trace_of(r"(\&e -> e + e) (1 / 0)");
+        assert_eq!(trace.fault, Fault::DivisionByZero { op: BinaryOp::Div });
+        assert_eq!(trace.frames, [Frame::Binary(BinaryOp::Add)]);
+    }
+
+    #[test]
+    fn failed_primitives_are_traced() {
+        let trace = trace_of(r"%boom");
+        assert_eq!(
+            trace.fault,
+            Fault::Primitive {
+                name: "boom".into(),
+                message: "out of fuel".into(),
+            }
+        );
+        assert_eq!(trace.interaction, InteractionType::AppPri);
+    }
+
+    #[test]
+    fn traces_point_at_the_failing_source() {
+        assert_eq!(trace_of(r"1 + (10 / 0)").span, Some(5..11));
+        assert_eq!(trace_of(r"?{1 -> 0} 2").span, Some(0..9));
+        assert_eq!(trace_of(r"1 + %boom").span, Some(4..9));
+        // An error keeps the span of where it was raised as it bubbles up.
+        assert_eq!(trace_of(r"-((1 / 0) + 2)").span, Some(3..8));
+    }
+
+    #[test]
+    fn failed_projections_are_traced() {
+        let trace = trace_of(r"(1 :> typeof true) + 2");
+        assert_eq!(
+            trace.fault,
+            Fault::Unsatisfied {
+                value: Head::Int(1),
+                expected: Head::Type(Some("Bool".into())),
+            }
+        );
+        assert_eq!(trace.interaction, InteractionType::PrjVal);
+        assert_eq!(trace.span, Some(1..17));
+        assert_eq!(trace.frames, [Frame::Binary(BinaryOp::Add)]);
+    }
+
+    #[test]
+    fn matched_errors_keep_their_trace() {
+        // Even a default branch does not catch an error.
+        let trace = trace_of(r"?{1 -> 100; _ -> 0} (1 / 0)");
+        assert_eq!(trace.fault, Fault::DivisionByZero { op: BinaryOp::Div });
+        assert_eq!(trace.frames, [Frame::Matched]);
+    }
+}
+
+/// Deep terms reduce on a thread with a small native stack: reduction recursion
+/// is bounded by the heap (see [`crate::util::WorkStack`]), not the stack.
+#[cfg(test)]
+mod stack_depth_tests {
+    use super::exec::{Executor, UnlimitedBudget};
+    use super::heap::{ArenaKind, Heap, Layout, TypeInfo};
+    use crate::vm::term::{BinaryOp, Term};
+
+    const DEPTH: usize = 20_000;
+
+    /// Run `f` on a thread with the stack size of a typical embedder's thread.
+    fn on_small_stack(f: impl FnOnce() + Send + 'static) {
+        std::thread::Builder::new()
+            .stack_size(512 * 1024)
+            .spawn(f)
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+
+    fn block_on<T>(future: impl std::future::Future<Output = T>) -> T {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn deep_dup_chain() {
+        on_small_stack(|| {
+            let heap = Heap::new();
+            heap.with(|h| {
+                let mut cur = h.alloc(Term::Int(1));
+                let mut forced = None;
+                for _ in 0..DEPTH {
+                    let (use_node, keep_node) = h.dup_use(cur);
+                    forced = Some(use_node);
+                    cur = keep_node;
+                }
+                let exec = Executor::new(h, UnlimitedBudget);
+                let result = block_on(exec.whnf_at(forced.unwrap()));
+                assert_eq!(*h.view(&result), Term::Int(1));
+            });
+        });
+    }
+
+    #[test]
+    fn deep_operand_chain() {
+        on_small_stack(|| {
+            let heap = Heap::new();
+            heap.with(|h| {
+                // 1 + (1 + (... + 0))
+                let mut cur = h.alloc(Term::Int(0));
+                for _ in 0..DEPTH {
+                    let lhs = h.alloc(Term::Int(1));
+                    cur = h.alloc(Term::Bop {
+                        op: BinaryOp::Add,
+                        lhs,
+                        rhs: cur,
+                    });
+                }
+                let exec = Executor::new(h, UnlimitedBudget);
+                let result = block_on(exec.normalize_at(cur));
+                assert_eq!(*h.view(&result), Term::Int(DEPTH as i64));
+            });
+        });
+    }
+
+    #[test]
+    fn deep_construction() {
+        on_small_stack(|| {
+            let heap = Heap::new();
+            heap.with(|h| {
+                // A list-like nesting: each level holds `1 + 1` and the rest.
+                let mut cur = h.alloc(Term::Int(0));
+                for _ in 0..DEPTH {
+                    let lhs = h.alloc(Term::Int(1));
+                    let rhs = h.alloc(Term::Int(1));
+                    let head = h.alloc(Term::Bop {
+                        op: BinaryOp::Add,
+                        lhs,
+                        rhs,
+                    });
+                    let ty = h.alloc_type(TypeInfo::new(Layout::Product(Vec::new())));
+                    let values = h.alloc_pack(None, vec![head, cur]);
+                    cur = h.alloc(Term::Ctn {
+                        ty,
+                        arity: 2,
+                        values,
+                    });
+                }
+                let exec = Executor::new(h, UnlimitedBudget);
+                let result = block_on(exec.normalize_at(cur));
+                let Term::Ctn { values, .. } = &*h.view(&result) else {
+                    panic!("not a construction");
+                };
+                assert_eq!(*h.view(&h.pack_field(values, 0)), Term::Int(2));
+                exec.erase(h.pull(result));
+                assert_eq!(h.arena_len(ArenaKind::Nodes), 0);
+            });
+        });
+    }
+}
+
+/// Garbage collection: terms no root reaches are erased as they would have
+/// been when lost, cycles included, and cells no node holds are freed.
+#[cfg(test)]
+mod gc_tests {
+    use super::exec::{Executor, UnlimitedBudget};
+    use super::heap::{ArenaKind, Boxed, Heap, HeapScope, Layout, TermPtr, TypeInfo};
+    use super::printer::Printer;
+    use crate::core::ast::desugar;
+    use crate::core::parse::parse;
+    use crate::vm::term::Term;
+    use std::sync::Arc;
+
+    fn lower<'h>(h: &'h HeapScope<'h>, src: &str) -> TermPtr<'h> {
+        let expr = desugar(&parse(src).unwrap()).unwrap();
+        h.lower(&expr, &|_| None, &mut |_| None).unwrap()
+    }
+
+    fn normalize<'h>(exec: &Executor<'_, 'h, UnlimitedBudget>, root: TermPtr<'h>) -> String {
+        let root = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(exec.normalize_at(root));
+        let printed = Printer::new(exec.heap).pretty(&root).to_string();
+        exec.erase(exec.heap.pull(root));
+        printed
+    }
+
+    #[test]
+    fn unreachable_terms_are_reclaimed() {
+        Heap::new().with(|h| {
+            let root = lower(h, "1 + 2");
+            let _ = lower(h, r"(\x -> x + 1) 5");
+            let exec = Executor::new(h, UnlimitedBudget);
+            let reclaimed = unsafe { exec.collect_garbage(&[&root]) };
+            assert_eq!(reclaimed.count(ArenaKind::Nodes), 6);
+            assert_eq!(reclaimed.count(ArenaKind::Vars), 1);
+            assert_eq!(h.arena_len(ArenaKind::Nodes), 3);
+            assert_eq!(normalize(&exec, root), "3");
+            assert!(unsafe { exec.collect_garbage(&[]) }.is_empty());
+        });
+    }
+
+    #[test]
+    fn a_leaked_cycle_is_reclaimed() {
+        Heap::new().with(|h| {
+            // A duplicand holding one of its own projections: `v = n0 1`,
+            // with `n0` and `n1` the projections of a dup over `v`.
+            let v = h.alloc(Term::Null);
+            let (d0, d1) = h.alloc_dup_at(v.addr());
+            let label = h.dup_auto_label(d0);
+            let func = h.alloc(Term::Dup { label, ptr: d0 });
+            let arg = h.alloc(Term::Int(1));
+            let _ = h.finish_slot(h.slot(v), Term::App { func, arg });
+            let _ = h.alloc(Term::Dup { label, ptr: d1 });
+            assert_eq!(unsafe { h.find_leaked_roots(&[]) }.len(), 1);
+
+            let exec = Executor::new(h, UnlimitedBudget);
+            let reclaimed = unsafe { exec.collect_garbage(&[]) };
+            assert_eq!(reclaimed.count(ArenaKind::Dups), 1);
+            // Only the label's name is left: names are interned for good.
+            for kind in ArenaKind::ALL
+                .into_iter()
+                .filter(|&k| k != ArenaKind::Names)
+            {
+                assert_eq!(h.arena_len(kind), 0, "{}", kind.label());
+            }
+        });
+    }
+
+    #[test]
+    fn a_cycle_of_owning_edges_is_reclaimed() {
+        Heap::new().with(|h| {
+            // `v = (use v) 1`, tied through owning edges alone, as a `fix`
+            // unrolled into itself can leave it: no duplication to cut.
+            let v = h.alloc(Term::Null);
+            let func = h.alloc(Term::Null);
+            let func_addr = func.addr();
+            let arg = h.alloc(Term::Int(1));
+            let v_addr = v.addr();
+            let _ = h.finish_slot(h.slot(v), Term::App { func, arg });
+            let inner = unsafe { TermPtr::forge(func_addr) };
+            let back = unsafe { TermPtr::forge(v_addr) };
+            let _ = h.finish_slot(h.slot(inner), Term::Use { body: back });
+            assert_eq!(unsafe { h.find_leaked_roots(&[]) }.len(), 1);
+
+            let exec = Executor::new(h, UnlimitedBudget);
+            let reclaimed = unsafe { exec.collect_garbage(&[]) };
+            assert_eq!(reclaimed.to_string(), "nodes 3");
+            assert_eq!(h.arena_len(ArenaKind::Nodes), 0);
+        });
+    }
+
+    #[test]
+    fn a_lost_projection_is_dropped() {
+        Heap::new().with(|h| {
+            // The lost projection is dropped, so the kept one is handed the
+            // duplicand uncopied.
+            let (used, _) = h.dup_use(lower(h, "2 * 3"));
+            let exec = Executor::new(h, UnlimitedBudget);
+            let reclaimed = unsafe { exec.collect_garbage(&[&used]) };
+            assert_eq!(reclaimed.count(ArenaKind::Dups), 1);
+            assert!(matches!(*h.view(&used), Term::Bop { .. }));
+            assert_eq!(normalize(&exec, used), "6");
+            assert_eq!(h.arena_len(ArenaKind::Nodes), 0);
+        });
+    }
+
+    #[test]
+    fn cells_no_node_holds_are_freed() {
+        Heap::new().with(|h| {
+            let _ = h.alloc_type(TypeInfo::new(Layout::Atom(Arc::from("Int"))));
+            let _ = h.value(Boxed::Str(Arc::from("lost")));
+            let _ = h.alloc_var();
+            let exec = Executor::new(h, UnlimitedBudget);
+            let reclaimed = unsafe { exec.collect_garbage(&[]) };
+            assert_eq!(reclaimed.to_string(), "vars 1, values 1, types 1");
+        });
+    }
+}
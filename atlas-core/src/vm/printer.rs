@@ -6,7 +6,7 @@
 
 use crate::core::printer::fmt_float;
 use crate::util::MemoMap;
-use crate::vm::heap::{Addr, Boxed, HeapScope, TermPtr, TypeInfo};
+use crate::vm::heap::{Addr, Boxed, HeapScope, Layout, TermPtr, TypeInfo};
 use crate::vm::term::Term;
 use std::cell::{Cell, RefCell};
 use std::collections::HashSet;
@@ -154,6 +154,10 @@ impl<'a, 'h> Printer<'a, 'h> {
             Term::Uop { val, .. } => {
                 self.collect_ptr(val);
             }
+            Term::Prj { val, ty } => {
+                self.collect_ptr(val);
+                self.collect_ptr(ty);
+            }
             Term::Ctn { arity, values, .. } => {
                 for i in 0..*arity as usize {
                     self.collect(
@@ -169,23 +173,14 @@ impl<'a, 'h> Printer<'a, 'h> {
                 }
             }
             Term::Ctr { ty, .. } => self.collect_ptr(ty),
-            Term::Type(t) => match self.heap.type_info(t) {
-                // A type's (lazy, possibly-unevaluated) sub-type fields can still
-                // hold unfired dups — walk them so those dups are hoisted, not
-                // rendered as bare projection names.
-                TypeInfo::Product { fields, .. } => {
-                    for a in fields {
-                        self.collect(*a, &self.heap.view_at(*a));
-                    }
-                }
-                TypeInfo::Sum { variants, .. } => {
-                    for v in variants {
-                        for a in &v.args {
-                            self.collect(*a, &self.heap.view_at(*a));
-                        }
-                    }
+            // A type's (lazy, possibly-unevaluated) sub-type fields can still
+            // hold unfired dups — walk them so those dups are hoisted, not
+            // rendered as bare projection names.
+            Term::Type(t) => {
+                for a in self.heap.type_info(t).children() {
+                    self.collect(a, &self.heap.view_at(a));
                 }
-            },
+            }
             Term::Sup { ptr, .. } => {
                 let (la, ra) = self.heap.sup_addrs(ptr);
                 self.collect(la, &self.heap.view_sup(ptr, true));
@@ -277,6 +272,13 @@ impl<'a, 'h> Printer<'a, 'h> {
                 self.fmt_ptr(f, val, false)?;
                 write!(f, ")")
             }
+            Term::Prj { val, ty } => {
+                write!(f, "(")?;
+                self.fmt_ptr(f, val, false)?;
+                write!(f, " :> ")?;
+                self.fmt_ptr(f, ty, false)?;
+                write!(f, ")")
+            }
             Term::Ctn { ty, arity, values } => {
                 // Label by the variant name if there is one, else the type's name.
                 let nm = match self.heap.pack_name(values) {
@@ -359,16 +361,21 @@ impl<'a, 'h> Printer<'a, 'h> {
                 write!(f, "}}")
             }
             Term::Wld => write!(f, "*"),
+            // The fault that raised it; the interactions it bubbled up through
+            // are left to the trace's own `Display`.
+            Term::Err {
+                backtrace: Some(t), ..
+            } => write!(f, "<err: {}>", self.heap.trace_get(t).fault),
             Term::Err { .. } => write!(f, "<err>"),
             Term::Pri(id) => write!(f, "%{}", id.get()),
             Term::Type(t) => {
-                if let Some(n) = self.heap.type_name(t.addr()) {
-                    return write!(f, "{n}");
-                }
-                // Anonymous type: render its shape. Sub-types are lazy, so a field
-                // prints as its (unevaluated) term form.
-                match self.heap.type_info(t) {
-                    TypeInfo::Sum { variants, .. } => {
+                // Render the layout, then any capabilities. Sub-types are lazy, so
+                // a field or signature prints as its (unevaluated) term form.
+                let info = self.heap.type_info(t);
+                match &info.layout {
+                    Layout::Any => write!(f, "type *")?,
+                    Layout::Atom(name) => write!(f, "{name}")?,
+                    Layout::Sum(variants) => {
                         write!(f, "type{{")?;
                         for (i, v) in variants.iter().enumerate() {
                             if i > 0 {
@@ -386,9 +393,9 @@ impl<'a, 'h> Printer<'a, 'h> {
                                 write!(f, ")")?;
                             }
                         }
-                        write!(f, "}}")
+                        write!(f, "}}")?
                     }
-                    TypeInfo::Product { fields, .. } => {
+                    Layout::Product(fields) => {
                         write!(f, "type(")?;
                         for (i, a) in fields.iter().enumerate() {
                             if i > 0 {
@@ -396,9 +403,49 @@ impl<'a, 'h> Printer<'a, 'h> {
                             }
                             self.fmt_term(f, *a, &self.heap.view_at(*a), false)?;
                         }
-                        write!(f, ")")
+                        write!(f, ")")?
+                    }
+                    Layout::Sort(sort) => write!(f, "{sort}")?,
+                    Layout::Pi {
+                        erased,
+                        input,
+                        output,
+                    } => {
+                        if !tail {
+                            write!(f, "(")?;
+                        }
+                        let (open, close) = if *erased { ('{', '}') } else { ('(', ')') };
+                        // A codomain lambda prints as the binder itself; any other
+                        // codomain function is applied to `_`.
+                        let view = self.heap.view_at(*output);
+                        match &*view {
+                            Term::Lam { var, body } => {
+                                let name = self.var_name(self.heap.var_addr(*var));
+                                write!(f, "pi {open}{name}: ")?;
+                                self.fmt_term(f, *input, &self.heap.view_at(*input), false)?;
+                                write!(f, "{close} -> ")?;
+                                self.fmt_term(f, body.addr(), &self.heap.view_body(body), true)?;
+                            }
+                            Term::Use { body } => {
+                                write!(f, "pi {open}_: ")?;
+                                self.fmt_term(f, *input, &self.heap.view_at(*input), false)?;
+                                write!(f, "{close} -> ")?;
+                                self.fmt_ptr(f, body, true)?;
+                            }
+                            _ => {
+                                write!(f, "pi {open}_: ")?;
+                                self.fmt_term(f, *input, &self.heap.view_at(*input), false)?;
+                                write!(f, "{close} -> (")?;
+                                self.fmt_term(f, *output, &view, false)?;
+                                write!(f, " _)")?;
+                            }
+                        }
+                        if !tail {
+                            write!(f, ")")?;
+                        }
                     }
                 }
+                self.fmt_rows(f, info)
             }
             Term::VarId(v) => write!(f, "{}", self.heap.variant_name(*v)),
             // A constructor selector value: print its variant name, or `New` for
@@ -429,4 +476,36 @@ impl<'a, 'h> Printer<'a, 'h> {
             _ => write!(f, "<?>"),
         }
     }
+
+    /// Print a type's capability rows after its layout, as
+    /// ` {.prop: S, (op): S, ..}` (`..` for an open row); nothing when both rows
+    /// are closed and empty.
+    fn fmt_rows(&self, f: &mut fmt::Formatter<'_>, info: &TypeInfo) -> fmt::Result {
+        let (props, ops) = (&info.props, &info.ops);
+        if props.entries.is_empty() && ops.entries.is_empty() && !props.open && !ops.open {
+            return Ok(());
+        }
+        write!(f, " {{")?;
+        let entries =
+            (props.entries.iter().map(|e| (e, true))).chain(ops.entries.iter().map(|e| (e, false)));
+        for (i, (e, prop)) in entries.enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            let name = self.heap.variant_name(e.name);
+            if prop {
+                write!(f, ".{name}: ")?;
+            } else {
+                write!(f, "({name}): ")?;
+            }
+            self.fmt_term(f, e.sig, &self.heap.view_at(e.sig), false)?;
+        }
+        if props.open || ops.open {
+            if !props.entries.is_empty() || !ops.entries.is_empty() {
+                write!(f, ", ")?;
+            }
+            write!(f, "..")?;
+        }
+        write!(f, "}}")
+    }
 }
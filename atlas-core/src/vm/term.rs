@@ -20,7 +20,9 @@ pub type Brand<'h> = PhantomData<fn(&'h ()) -> &'h ()>;
 pub enum BinaryOp {
     Add, Sub, Mul, Div, Mod,
     Eq, Neq, Lt, Lte, Gt, Gte,
-    And, Or, Xor, Shl, Shr, IDiv, Invalid
+    And, Or, Xor, Shl, Shr, IDiv,
+    /// definitional equality (`===`), decided by the conversion checker.
+    Conv, Invalid
 }
 
 impl TryFrom<u8> for BinaryOp {
@@ -47,7 +49,8 @@ impl BinaryOp {
             Mod => "%", Eq => "==", Neq => "!=",
             Lt => "<",  Lte => "<=", Gt => ">", Gte => ">=",
             And => "&", Or => "|", Xor => "^",
-            Shl => "<<", Shr => ">>", IDiv => "~/", Invalid => "INVALID",
+            Shl => "<<", Shr => ">>", IDiv => "~/", Conv => "===",
+            Invalid => "INVALID",
         }
     }
 }
@@ -86,6 +89,50 @@ impl UnaryOp {
     }
 }
 
+/// A sort: `Prop` or the universe `Type_i`. Universes are cumulative, and
+/// `Prop` sits below `Type_0`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Sort {
+    Prop,
+    Type(u32),
+}
+
+impl Sort {
+    /// `level(Prop) = 0`, `level(Type_i) = i`.
+    pub fn level(self) -> u32 {
+        match self {
+            Sort::Prop => 0,
+            Sort::Type(i) => i,
+        }
+    }
+
+    /// The sort this one inhabits: `Prop : Type_0`, `Type_i : Type_(i+1)`.
+    pub fn succ(self) -> Sort {
+        match self {
+            Sort::Prop => Sort::Type(0),
+            Sort::Type(i) => Sort::Type(i + 1),
+        }
+    }
+
+    /// Cumulativity: whether a type in `self` may be used in `other`.
+    pub fn within(self, other: Sort) -> bool {
+        match (self, other) {
+            (Sort::Prop, _) => true,
+            (Sort::Type(_), Sort::Prop) => false,
+            (Sort::Type(i), Sort::Type(j)) => i <= j,
+        }
+    }
+}
+
+impl std::fmt::Display for Sort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Sort::Prop => write!(f, "Prop"),
+            Sort::Type(i) => write!(f, "Type_{i}"),
+        }
+    }
+}
+
 // --- newtypes ---
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -185,6 +232,9 @@ pub enum Term<'h> {
     /// short-circuit `and` / `or` node `[lhs, rhs]`
     And { lhs: TermPtr<'h>, rhs: TermPtr<'h> },
     Or { lhs: TermPtr<'h>, rhs: TermPtr<'h> },
+    /// a lazy projection `val :> ty`: demanded, it checks `val`'s runtime
+    /// descriptor against the type `ty` and yields `val` (or an error).
+    Prj { val: TermPtr<'h>, ty: TermPtr<'h> },
     /// wildcard (`*` / `_`): an inert atom. Could be anything!
     Wld,
     /// err: a first-class eraser; it annihilates whatever it interacts with.
@@ -232,6 +282,8 @@ pub enum Tag {
     Ctr,
     /// an inert constructor-variant match key
     VarId,
+    /// a lazy projection `val :> ty`
+    Prj,
     Invalid,
 }
 
@@ -378,7 +430,12 @@ impl Node {
     fn acquire_unpack_tag(&self) -> (Tag, bool) {
         loop {
             let tag = self.load_unlocked_tag();
-            if tag == Tag::Dp0 as u8 || tag == Tag::Dp1 as u8 || tag == Tag::Var as u8 {
+            // The tags `swap` may overwrite, so their payload is read locked.
+            if tag == Tag::Dp0 as u8
+                || tag == Tag::Dp1 as u8
+                || tag == Tag::Var as u8
+                || tag == Tag::Sup as u8
+            {
                 match self.tag.compare_exchange(
                     tag,
                     LOCKED_TAG,
@@ -518,6 +575,10 @@ impl Node {
                     variant: (valtag != 0).then(|| VariantId::from_u56(valext)),
                 },
                 Tag::VarId => Term::VarId(VariantId::from_u56(valext)),
+                Tag::Prj => Term::Prj {
+                    val: TermPtr::forge(ext),
+                    ty: TermPtr::forge(valext),
+                },
                 Tag::Mat => Term::Mat {
                     matches: MatchPtr::forge(ext),
                 },
@@ -541,7 +602,9 @@ impl Node {
                 Tag::Wld => Term::Wld,
                 Tag::Err => Term::Err {
                     immediate: ext.to_u64() != 0,
-                    backtrace: (valext.to_u64() != 0).then(|| TracePtr::forge(valext)),
+                    // `valtag` flags presence, as for `Ctr`'s variant: a trace may
+                    // live at address 0.
+                    backtrace: (valtag != 0).then(|| TracePtr::forge(valext)),
                 },
                 Tag::Pri => Term::Pri(PrimId(valext)),
                 Tag::Int => Term::Int(val as i64),
@@ -583,8 +646,15 @@ impl<'h> Term<'h> {
             Term::Uop { op, val } => Node::from_all(Tag::Uop, val.addr(), *op as u8, U56::new(0)),
             Term::And { lhs, rhs } => Node::from_tag_ext_valext(Tag::And, lhs.addr(), rhs.addr()),
             Term::Or { lhs, rhs } => Node::from_tag_ext_valext(Tag::Or, lhs.addr(), rhs.addr()),
+            Term::Prj { val, ty } => Node::from_tag_ext_valext(Tag::Prj, val.addr(), ty.addr()),
             Term::Wld => Node::from_tag(Tag::Wld),
-            Term::Err { immediate, backtrace } => Node::from_tag_ext_valext(Tag::Err, (*immediate as u32).into(), backtrace.as_ref().map_or(U56::new(0), |t| t.addr())),
+            Term::Err { immediate, backtrace } => {
+                let (flag, ve) = match backtrace {
+                    Some(t) => (1, t.addr()),
+                    None => (0, U56::new(0)),
+                };
+                Node::from_all(Tag::Err, (*immediate as u32).into(), flag, ve)
+            }
             Term::Pri(id)  => Node::from_tag_valext(Tag::Pri, id.0),
             Term::Int(val) => Node::from_tag_val(Tag::Int, *val as u64),
             Term::Float(val) => Node::from_tag_val(Tag::Float, val.into_inner().to_bits()),
@@ -711,6 +781,14 @@ mod tests {
         });
     }
 
+    #[test]
+    fn round_trip_prj() {
+        assert_round_trip(Term::Prj {
+            val: term_ptr(17),
+            ty: term_ptr(18),
+        });
+    }
+
     #[test]
     fn round_trip_err() {
         assert_round_trip(Term::Err {
@@ -721,6 +799,10 @@ mod tests {
             immediate: true,
             backtrace: Some(unsafe { TracePtr::forge(addr(201)) }),
         });
+        assert_round_trip(Term::Err {
+            immediate: true,
+            backtrace: Some(unsafe { TracePtr::forge(addr(0)) }),
+        });
         assert_round_trip(Term::Err {
             immediate: true,
             backtrace: None,
@@ -0,0 +1,173 @@
+//! Runtime error traces.
+//!
+//! A failed interaction reduces to an [`Err`](crate::vm::term::Term::Err) whose
+//! backtrace names a [`Trace`]: the [`Fault`] that raised it, the interaction
+//! that failed, and the interactions the error has since bubbled up through.
+
+use crate::core::printer::fmt_float;
+use crate::vm::exec::InteractionType;
+use crate::vm::term::{BinaryOp, UnaryOp};
+use std::fmt;
+use std::ops::Range;
+use std::sync::Arc;
+
+/// Why an [`Err`](crate::vm::term::Term::Err) was raised and how it got where it
+/// is. Stored behind a [`TracePtr`](crate::vm::heap::TracePtr).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trace {
+    pub fault: Fault,
+    /// The interaction that failed.
+    pub interaction: InteractionType,
+    /// The source byte range of the failing redex, when lowering recorded one.
+    pub span: Option<Range<usize>>,
+    /// The interactions the error bubbled up through, innermost first.
+    pub frames: Vec<Frame>,
+}
+
+impl Trace {
+    pub fn new(fault: Fault, interaction: InteractionType) -> Self {
+        Trace {
+            fault,
+            interaction,
+            span: None,
+            frames: Vec::new(),
+        }
+    }
+}
+
+/// What went wrong.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fault {
+    /// `/`, `~/` or `%` by zero.
+    DivisionByZero { op: BinaryOp },
+    /// A binary operator applied to operands it is not defined for.
+    BadOperands { op: BinaryOp, lhs: Head, rhs: Head },
+    /// A unary operator applied to an operand it is not defined for.
+    BadOperand { op: UnaryOp, operand: Head },
+    /// No case of a match covers the scrutinee, and it has no default.
+    NoMatch { scrutinee: Head },
+    /// A type value applied to an argument (it must become a constructor first).
+    TypeApplied { ty: Head },
+    /// A constructor's type operand is not a type.
+    NotAType { head: Head },
+    /// A constructor names a variant its type does not have.
+    NoVariant { ty: Head, variant: Option<Arc<str>> },
+    /// Arguments were gathered for something that is not callable.
+    NotCallable { head: Head },
+    /// A host primitive failed.
+    Primitive { name: Arc<str>, message: Arc<str> },
+    /// A projection's value does not satisfy the type it is projected onto.
+    Unsatisfied { value: Head, expected: Head },
+    /// A projection's expected type is not a type.
+    ProjectOnto { head: Head },
+}
+
+/// An interaction an error bubbled up through on its way out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frame {
+    /// An operand of a binary operator.
+    Binary(BinaryOp),
+    /// The operand of a unary operator.
+    Unary(UnaryOp),
+    /// Applied to an argument, which it erased.
+    Applied,
+    /// The scrutinee of a match.
+    Matched,
+    /// Projected onto a type (or, as that type, projected onto).
+    Projected,
+}
+
+/// The head of an operand a failing interaction saw, kept after the operand
+/// itself is erased.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Head {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Char(char),
+    Str,
+    Bytes,
+    /// A type value, by name if it has one.
+    Type(Option<Arc<str>>),
+    /// A construction, by variant (or type) name if it has one.
+    Ctn(Option<Arc<str>>),
+    /// A lambda, match, primitive, constructor or partial application.
+    Function,
+    Err,
+    /// Anything else, by term kind.
+    Other(&'static str),
+}
+
+impl fmt::Display for Head {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Head::Int(n) => write!(f, "{n}"),
+            Head::Float(x) => fmt_float(f, *x),
+            Head::Bool(b) => write!(f, "{b}"),
+            Head::Char(c) => write!(f, "{c:?}"),
+            Head::Str => write!(f, "a string"),
+            Head::Bytes => write!(f, "bytes"),
+            Head::Type(Some(name)) => write!(f, "type {name}"),
+            Head::Type(None) => write!(f, "a type"),
+            Head::Ctn(Some(name)) => write!(f, "{name}{{..}}"),
+            Head::Ctn(None) => write!(f, "a construction"),
+            Head::Function => write!(f, "a function"),
+            Head::Err => write!(f, "an error"),
+            Head::Other(kind) => write!(f, "{kind}"),
+        }
+    }
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fault::DivisionByZero { op } => write!(f, "division by zero in `{}`", op.symbol()),
+            Fault::BadOperands { op, lhs, rhs } => {
+                write!(f, "`{}` is not defined for {lhs} and {rhs}", op.symbol())
+            }
+            Fault::BadOperand { op, operand } => {
+                write!(f, "`{}` is not defined for {operand}", op.symbol())
+            }
+            Fault::NoMatch { scrutinee } => write!(f, "no case matches {scrutinee}"),
+            Fault::TypeApplied { ty } => write!(f, "{ty} applied to an argument"),
+            Fault::NotAType { head } => write!(f, "constructor of {head}, which is not a type"),
+            Fault::NoVariant { ty, variant } => match variant {
+                Some(v) => write!(f, "{ty} has no variant `{v}`"),
+                None => write!(f, "{ty} has no product constructor"),
+            },
+            Fault::NotCallable { head } => write!(f, "{head} is not callable"),
+            Fault::Primitive { name, message } => write!(f, "%{name} failed: {message}"),
+            Fault::Unsatisfied { value, expected } => {
+                write!(f, "{value} does not satisfy {expected}")
+            }
+            Fault::ProjectOnto { head } => write!(f, "projection onto {head}, which is not a type"),
+        }
+    }
+}
+
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Frame::Binary(op) => write!(f, "in an operand of `{}`", op.symbol()),
+            Frame::Unary(op) => write!(f, "in the operand of `{}`", op.symbol()),
+            Frame::Applied => write!(f, "applied to an argument"),
+            Frame::Matched => write!(f, "matched on"),
+            Frame::Projected => write!(f, "in a projection"),
+        }
+    }
+}
+
+/// The fault on the first line, then one line per frame.
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({:?}", self.fault, self.interaction)?;
+        if let Some(span) = &self.span {
+            write!(f, " at {}..{}", span.start, span.end)?;
+        }
+        write!(f, ")")?;
+        for frame in &self.frames {
+            write!(f, "\n  {frame}")?;
+        }
+        Ok(())
+    }
+}
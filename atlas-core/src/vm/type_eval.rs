@@ -0,0 +1,2348 @@
+//! The [`TypeEvaluator`]: static type evaluation of a lowered term graph, the
+//! second interpretation of a term next to the value reduction of the
+//! [`Executor`](crate::vm::exec::Executor) (`docs/chapters/08-type-evaluation.typ`).
+//!
+//! Where value reduction stops at weak-head normal form, type evaluation visits
+//! every statically reachable node: lambda bodies, erased arguments, both sides
+//! of a superposition and every branch of a match. Each node is assigned a
+//! [`StaticType`] by its type interaction (T-VAR, T-LAM, T-APP, ...), and the
+//! requirements between types are solved as they are emitted, by structural
+//! subsumption over inference metavariables. A metavariable left
+//! unconstrained at the lambda that introduced it is generalized there.
+//!
+//! The walk follows the borrowed [`HeapScope::view`] of each node, like the
+//! [`Printer`](crate::vm::printer::Printer): nothing is reduced or consumed.
+//! Type-level expressions (the type a constructor selects from, the fields of a
+//! `type { .. }`) are regularized by type-level beta alone, under an
+//! environment of the arguments substituted for their binders.
+//!
+//! Only the fragment the core represents is covered. Capability rows come from
+//! the primitives of [`TypeExtensions`](crate::extension::TypeExtensions), and
+//! `%prop` is the one property access (T-PROPERTY); every sort reads as the
+//! single universe `Type`. A `μ` type comes only from a type-level `fix`, so a
+//! term whose self-application would need one is not proven rather than
+//! mismatched. The REPL's `/type` command is the entry point outside tests.
+
+use crate::extension::Extensions;
+use crate::vm::heap::{Addr, Boxed, HeapScope, Layout, MatchData, TermPtr, TypeInfo};
+use crate::vm::term::{BinaryOp, PrimId, Term, UnaryOp, VariantId};
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// A type computed by static type evaluation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StaticType {
+    /// An inference metavariable that is not (yet) solved.
+    Meta(u32),
+    /// A variable bound by an enclosing [`StaticType::Forall`].
+    Gen(u32),
+    /// A builtin atomic type: `Int`, `Float`, `Char`, `Bool`, `String` or
+    /// `Bytes`.
+    Atom(&'static str),
+    /// Any other atomic layout, by its tag: the nominal name
+    /// [`HeapScope::type_name`] reports. Only the same tag satisfies it.
+    Named(Arc<str>),
+    /// `*`: no layout requirement, so every type satisfies it.
+    Any,
+    /// The type of first-class type values.
+    Type,
+    /// A product `π^q x:A. B` whose result does not depend on `x`. An `erased`
+    /// one is grade zero: it erases its argument rather than using it.
+    Pi {
+        erased: bool,
+        input: Box<StaticType>,
+        output: Box<StaticType>,
+    },
+    /// A product layout, with its fields in order.
+    Product { fields: Vec<StaticType> },
+    /// A sum layout. An `open` one (the input of a match with a default branch)
+    /// also accepts the variants it does not list.
+    Sum {
+        variants: Vec<(Arc<str>, Vec<StaticType>)>,
+        open: bool,
+    },
+    /// A layout with capability rows: the property and operator signatures,
+    /// by name, that its values provide besides the layout. Only built when a
+    /// row has an entry. The record T-PROPERTY requires of an unknown type is
+    /// open: its `rest` stands for the capabilities not required yet, a
+    /// metavariable until a later requirement solves it to their record.
+    Record {
+        layout: Box<StaticType>,
+        props: Vec<(Arc<str>, StaticType)>,
+        ops: Vec<(Arc<str>, StaticType)>,
+        rest: Option<Box<StaticType>>,
+    },
+    /// A recursive type `μ x. A`, read from a type-level `fix`: the
+    /// [`StaticType::Rec`] `x` in `A` stands for the whole.
+    Mu(u32, Box<StaticType>),
+    /// The variable of an enclosing [`StaticType::Mu`].
+    Rec(u32),
+    /// The join of the alternatives of a superposition or the branches of a
+    /// match: any one of them.
+    Join(Vec<StaticType>),
+    /// The type of an error, which satisfies every requirement.
+    Never,
+    /// A generalized type `π^0 a:Type. ..`, binding the [`StaticType::Gen`]
+    /// variables it lists.
+    Forall(Vec<u32>, Box<StaticType>),
+}
+
+impl StaticType {
+    fn pi(input: StaticType, output: StaticType) -> StaticType {
+        StaticType::Pi {
+            erased: false,
+            input: Box::new(input),
+            output: Box::new(output),
+        }
+    }
+
+    /// The join of `alts`, flattened, without duplicates and without `Never`,
+    /// which contributes nothing to a join.
+    fn join(alts: Vec<StaticType>) -> StaticType {
+        let mut flat: Vec<StaticType> = Vec::with_capacity(alts.len());
+        for alt in alts {
+            let nested = match alt {
+                StaticType::Join(nested) => nested,
+                StaticType::Never => continue,
+                alt => vec![alt],
+            };
+            for alt in nested {
+                if !flat.contains(&alt) {
+                    flat.push(alt);
+                }
+            }
+        }
+        match flat.len() {
+            0 => StaticType::Never,
+            1 => flat.pop().unwrap(),
+            _ => StaticType::Join(flat),
+        }
+    }
+
+    /// Float the quantifiers of a result out to the front, so a curried
+    /// lambda reads `forall a b. a -> b -> a` rather than
+    /// `forall a. a -> forall b. b -> a`. An input never mentions the
+    /// variables of its result's quantifier, so this is sound.
+    fn prenex(self) -> StaticType {
+        match self {
+            StaticType::Pi {
+                erased,
+                input,
+                output,
+            } => {
+                let pi = |output| StaticType::Pi {
+                    erased,
+                    input,
+                    output: Box::new(output),
+                };
+                match output.prenex() {
+                    StaticType::Forall(vars, body) => StaticType::Forall(vars, Box::new(pi(*body))),
+                    output => pi(output),
+                }
+            }
+            StaticType::Forall(mut vars, body) => match body.prenex() {
+                StaticType::Forall(inner, body) => {
+                    vars.extend(inner);
+                    StaticType::Forall(vars, body)
+                }
+                body => StaticType::Forall(vars, Box::new(body)),
+            },
+            ty => ty,
+        }
+    }
+
+    /// Write the type at precedence `prec`: 0 at the top or right of an
+    /// arrow, 1 in a join, 2 left of an arrow. Variables are named in order of
+    /// appearance; `names` holds each named so far.
+    fn write(&self, f: &mut fmt::Formatter<'_>, names: &mut Vec<TypeVar>, prec: u8) -> fmt::Result {
+        match self {
+            StaticType::Meta(v) => write_name(f, names, TypeVar::Meta(*v)),
+            StaticType::Gen(v) => write_name(f, names, TypeVar::Gen(*v)),
+            StaticType::Rec(v) => write_name(f, names, TypeVar::Rec(*v)),
+            StaticType::Atom(name) => f.write_str(name),
+            StaticType::Named(name) => f.write_str(name),
+            StaticType::Any => f.write_str("*"),
+            StaticType::Type => f.write_str("Type"),
+            StaticType::Never => f.write_str("Never"),
+            StaticType::Pi {
+                erased,
+                input,
+                output,
+            } => {
+                if prec > 0 {
+                    f.write_str("(")?;
+                }
+                if *erased {
+                    f.write_str("{")?;
+                    input.write(f, names, 0)?;
+                    f.write_str("}")?;
+                } else {
+                    input.write(f, names, 2)?;
+                }
+                f.write_str(" -> ")?;
+                output.write(f, names, 0)?;
+                if prec > 0 {
+                    f.write_str(")")?;
+                }
+                Ok(())
+            }
+            StaticType::Product { fields } => {
+                f.write_str("type(")?;
+                write_list(f, names, fields)?;
+                f.write_str(")")
+            }
+            StaticType::Sum { variants, open } => {
+                f.write_str("type{")?;
+                for (i, (name, args)) in variants.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    f.write_str(name)?;
+                    if !args.is_empty() {
+                        f.write_str("(")?;
+                        write_list(f, names, args)?;
+                        f.write_str(")")?;
+                    }
+                }
+                if *open {
+                    f.write_str(", ..")?;
+                }
+                f.write_str("}")
+            }
+            // Like the executor's printer: `layout {.prop: S, (op): S}`.
+            StaticType::Record {
+                layout,
+                props,
+                ops,
+                rest,
+            } => {
+                if prec > 1 {
+                    f.write_str("(")?;
+                }
+                layout.write(f, names, 2)?;
+                f.write_str(" {")?;
+                let entries =
+                    (props.iter().map(|e| (e, true))).chain(ops.iter().map(|e| (e, false)));
+                for (i, ((name, sig), prop)) in entries.enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    if prop {
+                        write!(f, ".{name}: ")?;
+                    } else {
+                        write!(f, "({name}): ")?;
+                    }
+                    sig.write(f, names, 0)?;
+                }
+                if let Some(rest) = rest {
+                    f.write_str(", ..")?;
+                    rest.write(f, names, 0)?;
+                }
+                f.write_str("}")?;
+                if prec > 1 {
+                    f.write_str(")")?;
+                }
+                Ok(())
+            }
+            StaticType::Mu(v, body) => {
+                if prec > 0 {
+                    f.write_str("(")?;
+                }
+                f.write_str("mu ")?;
+                write_name(f, names, TypeVar::Rec(*v))?;
+                f.write_str(". ")?;
+                body.write(f, names, 0)?;
+                if prec > 0 {
+                    f.write_str(")")?;
+                }
+                Ok(())
+            }
+            StaticType::Join(alts) => {
+                if prec > 1 {
+                    f.write_str("(")?;
+                }
+                for (i, alt) in alts.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" | ")?;
+                    }
+                    alt.write(f, names, 1)?;
+                }
+                if prec > 1 {
+                    f.write_str(")")?;
+                }
+                Ok(())
+            }
+            StaticType::Forall(vars, body) => {
+                if prec > 0 {
+                    f.write_str("(")?;
+                }
+                f.write_str("forall")?;
+                for v in vars {
+                    f.write_str(" ")?;
+                    write_name(f, names, TypeVar::Gen(*v))?;
+                }
+                f.write_str(". ")?;
+                body.write(f, names, 0)?;
+                if prec > 0 {
+                    f.write_str(")")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A variable of a [`StaticType`], as [`StaticType::write`] names it.
+#[derive(Clone, Copy, PartialEq)]
+enum TypeVar {
+    Meta(u32),
+    Gen(u32),
+    Rec(u32),
+}
+
+fn write_name(f: &mut fmt::Formatter<'_>, names: &mut Vec<TypeVar>, key: TypeVar) -> fmt::Result {
+    let n = match names.iter().position(|k| *k == key) {
+        Some(n) => n,
+        None => {
+            names.push(key);
+            names.len() - 1
+        }
+    };
+    let letter = (b'a' + (n % 26) as u8) as char;
+    if n < 26 {
+        write!(f, "{letter}")
+    } else {
+        write!(f, "{letter}{}", n / 26)
+    }
+}
+
+fn write_list(
+    f: &mut fmt::Formatter<'_>,
+    names: &mut Vec<TypeVar>,
+    tys: &[StaticType],
+) -> fmt::Result {
+    for (i, ty) in tys.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        ty.write(f, names, 0)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for StaticType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, &mut Vec::new(), 0)
+    }
+}
+
+/// What static type evaluation of a term concluded. Only
+/// [`TypeOutcome::Mismatch`] is a negative proof: the incomplete outcomes do
+/// not mean that any particular run of the term fails.
+///
+/// A `span` is the source range of the node the outcome was reached at, when
+/// the term was lowered from a spanned program.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeOutcome {
+    /// Every requirement was solved or generalized: the term has this type.
+    Ok(StaticType),
+    /// Regularization exposed incompatible rigid structure.
+    Mismatch {
+        expected: StaticType,
+        found: StaticType,
+        span: Option<Range<usize>>,
+    },
+    /// Not proven: a requirement stayed stuck on a value that is not known
+    /// statically, or on a metavariable that cannot be generalized.
+    Stuck {
+        reason: String,
+        span: Option<Range<usize>>,
+    },
+    /// The budget ran out before a required type structure was exposed.
+    Exhausted,
+    /// The term uses an extension that has no type interaction.
+    Unsupported {
+        what: String,
+        span: Option<Range<usize>>,
+    },
+}
+
+impl fmt::Display for TypeOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeOutcome::Ok(ty) => write!(f, "{ty}"),
+            TypeOutcome::Mismatch {
+                expected, found, ..
+            } => write!(f, "mismatch: expected `{expected}`, found `{found}`"),
+            TypeOutcome::Stuck { reason, .. } => write!(f, "not proven: {reason}"),
+            TypeOutcome::Exhausted => write!(f, "budget exhausted"),
+            TypeOutcome::Unsupported { what, .. } => write!(f, "unsupported: {what}"),
+        }
+    }
+}
+
+/// Why the walk stopped early. An incomplete outcome does not stop it (see
+/// [`TypeEvaluator::stuck`]), so a later mismatch is still found.
+/// The types of a mismatch are boxed, as every interaction returns a `Stop`.
+enum Stop {
+    Mismatch {
+        expected: Box<StaticType>,
+        found: Box<StaticType>,
+        at: Addr,
+    },
+    Exhausted,
+}
+
+#[derive(Debug, Clone)]
+enum Meta {
+    /// Unsolved, introduced under `level` lambdas.
+    Free {
+        level: u32,
+    },
+    Bound(StaticType),
+}
+
+/// A type-level term together with the arguments type-level beta substituted
+/// for the binders in scope of it.
+#[derive(Clone)]
+struct Closure {
+    at: Addr,
+    env: Env,
+}
+
+/// A persistent list of type-level bindings, keyed by the address of the bound
+/// variable's occurrence.
+#[derive(Clone, Default)]
+struct Env(Option<Rc<(Addr, Closure, Env)>>);
+
+impl Env {
+    fn bind(&self, var: Addr, value: Closure) -> Env {
+        Env(Some(Rc::new((var, value, self.clone()))))
+    }
+
+    /// Whether both are the same bindings, not merely equal ones.
+    fn same(&self, other: &Env) -> bool {
+        match (&self.0, &other.0) {
+            (Some(a), Some(b)) => Rc::ptr_eq(a, b),
+            (a, b) => a.is_none() && b.is_none(),
+        }
+    }
+
+    fn get(&self, var: Addr) -> Option<&Closure> {
+        let mut env = self;
+        while let Some(entry) = &env.0 {
+            if entry.0 == var {
+                return Some(&entry.1);
+            }
+            env = &entry.2;
+        }
+        None
+    }
+}
+
+/// Statically type-evaluates lowered terms over a [`HeapScope`] (see the
+/// module docs).
+pub struct TypeEvaluator<'a, 'h> {
+    heap: &'a HeapScope<'h>,
+    budget: Option<u64>,
+    steps: u64,
+    metas: Vec<Meta>,
+    /// The number of lambdas the walk is under.
+    level: u32,
+    /// The type of each lambda binder, by the address of its occurrence.
+    vars: HashMap<Addr, StaticType>,
+    /// The type of each duplicand, by its dup cell: both projections share it.
+    dups: HashMap<Addr, StaticType>,
+    /// The argument of each lambda applied in place, by the address of its
+    /// binder's occurrence, for the type-level uses of that binder.
+    defs: HashMap<Addr, Addr>,
+    /// The first stuck or unsupported requirement met.
+    incomplete: Option<TypeOutcome>,
+    /// Set when solving a requirement would need a recursive type.
+    cyclic: bool,
+    /// The type primitives (see [`Self::with_extensions`]) by their ids.
+    prims: HashMap<PrimId, &'static str>,
+    /// The number of [`StaticType::Rec`] variables introduced so far.
+    recs: u32,
+    /// The type-level `fix` applications being regularized, innermost last.
+    fixes: Vec<Fix>,
+    /// The pairs of recursive types being compared, innermost last.
+    active: Vec<(StaticType, StaticType)>,
+}
+
+/// A type-level `fix F a..` being regularized: `F` and the arguments `a..`,
+/// by their [`TypeEvaluator::origin`], and the [`StaticType::Rec`] that
+/// stands for it inside.
+struct Fix {
+    key: Vec<Closure>,
+    var: u32,
+    used: bool,
+}
+
+impl<'a, 'h> TypeEvaluator<'a, 'h> {
+    pub fn new(heap: &'a HeapScope<'h>) -> Self {
+        TypeEvaluator {
+            heap,
+            budget: None,
+            steps: 0,
+            metas: Vec::new(),
+            level: 0,
+            vars: HashMap::new(),
+            dups: HashMap::new(),
+            defs: HashMap::new(),
+            incomplete: None,
+            cyclic: false,
+            prims: HashMap::new(),
+            recs: 0,
+            fixes: Vec::new(),
+            active: Vec::new(),
+        }
+    }
+
+    /// Give up with [`TypeOutcome::Exhausted`] after `budget` type
+    /// interactions, for terms whose type-level computation diverges.
+    pub fn with_budget(mut self, budget: u64) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Give the type primitives of `extensions` (those of
+    /// [`TypeExtensions`](crate::extension::TypeExtensions)) their type
+    /// interactions: a signature each, type-level regularization of
+    /// `%type_any`, `%type_prop` and `%type_op` to the record they build, and
+    /// T-PROPERTY for `%prop`. Every other primitive is unsupported.
+    pub fn with_extensions<X: Extensions>(mut self, extensions: &X) -> Self {
+        for name in TYPE_PRIMITIVES {
+            if let Some(id) = extensions.resolve(name) {
+                self.prims.insert(id, name);
+            }
+        }
+        self
+    }
+
+    /// Type-evaluate the term at `root`. The graph is only read, so it is
+    /// left as it was.
+    pub fn evaluate(&mut self, root: &TermPtr<'h>) -> TypeOutcome {
+        self.steps = 0;
+        self.incomplete = None;
+        match self.eval(root.addr()) {
+            Ok(ty) => match self.incomplete.take() {
+                Some(outcome) => outcome,
+                None => TypeOutcome::Ok(self.generalize(&ty)),
+            },
+            Err(Stop::Mismatch {
+                expected,
+                found,
+                at,
+            }) => TypeOutcome::Mismatch {
+                expected: *expected,
+                found: *found,
+                span: self.heap.span_of(at),
+            },
+            Err(Stop::Exhausted) => TypeOutcome::Exhausted,
+        }
+    }
+
+    fn step(&mut self) -> Result<(), Stop> {
+        if self.budget.is_some_and(|budget| self.steps >= budget) {
+            return Err(Stop::Exhausted);
+        }
+        self.steps += 1;
+        Ok(())
+    }
+
+    fn fresh(&mut self) -> StaticType {
+        self.metas.push(Meta::Free { level: self.level });
+        StaticType::Meta(self.metas.len() as u32 - 1)
+    }
+
+    /// Record that the requirement at `at` is not proven, and go on with a
+    /// fresh metavariable for the type it would have had.
+    fn stuck(&mut self, at: Addr, reason: &str) -> StaticType {
+        if self.incomplete.is_none() {
+            self.incomplete = Some(TypeOutcome::Stuck {
+                reason: reason.to_string(),
+                span: self.heap.span_of(at),
+            });
+        }
+        self.fresh()
+    }
+
+    /// Like [`Self::stuck`], for a node with no type interaction.
+    fn unsupported(&mut self, at: Addr, what: String) -> StaticType {
+        if self.incomplete.is_none() {
+            self.incomplete = Some(TypeOutcome::Unsupported {
+                what,
+                span: self.heap.span_of(at),
+            });
+        }
+        self.fresh()
+    }
+
+    // ====================================================================
+    // Type interactions
+    // ====================================================================
+
+    fn eval(&mut self, at: Addr) -> Result<StaticType, Stop> {
+        self.step()?;
+        let heap = self.heap;
+        let view = heap.view_at(at);
+        Ok(match &*view {
+            // T-VAR
+            Term::Var { .. } => match self.vars.get(&at) {
+                Some(ty) => ty.clone(),
+                None => self.stuck(at, "a variable bound outside the term"),
+            },
+            // T-LAM: the binder gets a fresh metavariable, which the body
+            // constrains, and whatever is left unconstrained is generalized.
+            // T-FIX: `fix` is not typed through its self-application, which
+            // would need a recursive type, but by its signature.
+            Term::Lam { .. } if self.is_fix(at) => {
+                self.level += 1;
+                let a = self.fresh();
+                self.level -= 1;
+                self.generalize(&StaticType::pi(StaticType::pi(a.clone(), a.clone()), a))
+            }
+            Term::Lam { var, body } => {
+                let (var, body) = (heap.var_addr(*var), body.addr());
+                self.level += 1;
+                let input = self.fresh();
+                self.vars.insert(var, input.clone());
+                let output = self.eval(body);
+                self.level -= 1;
+                let ty = StaticType::pi(input, output?);
+                self.generalize(&ty)
+            }
+            Term::Use { body } => {
+                let body = body.addr();
+                self.level += 1;
+                let input = self.fresh();
+                let output = self.eval(body);
+                self.level -= 1;
+                let ty = StaticType::Pi {
+                    erased: true,
+                    input: Box::new(input),
+                    output: Box::new(output?),
+                };
+                self.generalize(&ty)
+            }
+            // T-PROPERTY, for `%prop name receiver`.
+            Term::App { func, arg } if self.property_name(func.addr()).is_some() => {
+                let (key, arg) = (self.property_name(func.addr()).unwrap(), arg.addr());
+                let name = self.eval(key)?;
+                self.require(&name, &StaticType::Atom("String"), key)?;
+                let receiver = self.eval(arg)?;
+                let key = Closure {
+                    at: key,
+                    env: Env::default(),
+                };
+                match self.literal(key) {
+                    Some(name) => self.property(at, receiver, name)?,
+                    None => self.stuck(at, "a property name that is not a literal"),
+                }
+            }
+            // T-APP, with T-ERASE: an erased argument is still evaluated.
+            Term::App { func, arg } => {
+                let (func, arg) = (func.addr(), arg.addr());
+                if let Term::Lam { var, .. } = &*heap.view_at(func) {
+                    self.defs.insert(heap.var_addr(*var), arg);
+                }
+                let input = self.eval(arg)?;
+                let f = match &*heap.view_at(func) {
+                    Term::Mat { matches } => {
+                        self.step()?;
+                        self.mat(heap.match_data(matches), Some(&input))?
+                    }
+                    _ => self.eval(func)?,
+                };
+                self.apply(at, f, input, arg)?
+            }
+            // T-DUP: both projections have the duplicand's type, and what
+            // each requires of it meets on that one type.
+            Term::Dup { ptr, .. } => {
+                let cell = ptr.addr();
+                match self.dups.get(&cell) {
+                    Some(ty) => ty.clone(),
+                    None => match heap.dup_peek(ptr) {
+                        Some(value) => {
+                            let ty = self.eval(value)?;
+                            self.dups.insert(cell, ty.clone());
+                            ty
+                        }
+                        None => self.unsupported(at, "a duplication that already fired".into()),
+                    },
+                }
+            }
+            // T-SUP
+            Term::Sup { ptr, .. } => {
+                let (left, right) = heap.sup_addrs(ptr);
+                let left = self.eval(left)?;
+                let right = self.eval(right)?;
+                StaticType::join(vec![left, right])
+            }
+            // T-TYPE: every field of a type value is itself a type.
+            Term::Type(t) => {
+                for field in heap.type_info(t).children() {
+                    let ty = self.eval(field)?;
+                    self.require(&ty, &StaticType::Type, field)?;
+                }
+                StaticType::Type
+            }
+            Term::Ctr { ty, variant } => {
+                let (ty, variant) = (ty.addr(), *variant);
+                let kind = self.eval(ty)?;
+                self.require(&kind, &StaticType::Type, ty)?;
+                let data = self.type_expr(ty, Env::default())?;
+                self.constructor(at, data, variant)?
+            }
+            Term::Ctn { ty, arity, values } => {
+                let data = self.type_info(at, heap.type_info(ty), &Env::default())?;
+                let ctor = self.constructor(at, data, heap.pack_name(values))?;
+                let fields: Vec<Addr> = (0..*arity as usize)
+                    .map(|i| heap.pack_addr(values, i))
+                    .collect();
+                let mut ty = ctor;
+                for field in fields {
+                    let input = self.eval(field)?;
+                    ty = self.apply(at, ty, input, field)?;
+                }
+                ty
+            }
+            Term::Mat { matches } => self.mat(heap.match_data(matches), None)?,
+            Term::Bop { op, lhs, rhs } => {
+                let (op, lhs, rhs) = (*op, lhs.addr(), rhs.addr());
+                let l = self.eval(lhs)?;
+                let r = self.eval(rhs)?;
+                self.operator(at, op, l, r)?
+            }
+            Term::And { lhs, rhs } | Term::Or { lhs, rhs } => {
+                let op = match &*view {
+                    Term::And { .. } => BinaryOp::And,
+                    _ => BinaryOp::Or,
+                };
+                let (lhs, rhs) = (lhs.addr(), rhs.addr());
+                let l = self.eval(lhs)?;
+                let r = self.eval(rhs)?;
+                self.operator(at, op, l, r)?
+            }
+            Term::Uop { op, val } => {
+                let (op, val) = (*op, val.addr());
+                let ty = self.eval(val)?;
+                self.unary(at, op, ty)?
+            }
+            // T-PROJECT: the result keeps the actual type, not the expected one.
+            Term::Prj { val, ty } => {
+                let (val, ty) = (val.addr(), ty.addr());
+                let actual = self.eval(val)?;
+                let kind = self.eval(ty)?;
+                self.require(&kind, &StaticType::Type, ty)?;
+                let expected = self.type_expr(ty, Env::default())?;
+                self.require(&actual, &expected, val)?;
+                actual
+            }
+            Term::Int(_) => StaticType::Atom("Int"),
+            Term::Float(_) => StaticType::Atom("Float"),
+            Term::Char(_) => StaticType::Atom("Char"),
+            Term::Bool(_) => StaticType::Atom("Bool"),
+            Term::Box(v) => match heap.value_get(v) {
+                Boxed::Str(_) => StaticType::Atom("String"),
+                Boxed::Bytes(_) => StaticType::Atom("Bytes"),
+            },
+            Term::Err { .. } => StaticType::Never,
+            Term::Wld => self.fresh(),
+            Term::Pri(id) => match self.prims.get(id) {
+                Some(&"prop") => {
+                    self.unsupported(at, "a property access without a receiver".into())
+                }
+                Some(name) => primitive_type(name),
+                None => self.unsupported(
+                    at,
+                    format!("primitive {} has no static signature", id.get()),
+                ),
+            },
+            Term::Partial { .. } => self.unsupported(at, "a partial application".into()),
+            Term::VarId(_) => self.unsupported(at, "a bare match key".into()),
+            Term::Null => self.unsupported(at, "an empty slot".into()),
+        })
+    }
+
+    /// Apply a function of type `f` to an argument of type `input` (at
+    /// `arg`), for the application at `at`.
+    fn apply(
+        &mut self,
+        at: Addr,
+        f: StaticType,
+        input: StaticType,
+        arg: Addr,
+    ) -> Result<StaticType, Stop> {
+        match self.resolve(&f) {
+            ty @ StaticType::Forall(..) => {
+                let f = self.instantiate(&ty);
+                self.apply(at, f, input, arg)
+            }
+            StaticType::Pi {
+                input: expected,
+                output,
+                ..
+            } => {
+                self.require(&input, &expected, arg)?;
+                Ok(*output)
+            }
+            // A superposition of functions: apply each alternative.
+            StaticType::Join(alts) => {
+                let mut outputs = Vec::with_capacity(alts.len());
+                for alt in alts {
+                    outputs.push(self.apply(at, alt, input.clone(), arg)?);
+                }
+                Ok(StaticType::join(outputs))
+            }
+            StaticType::Never => Ok(StaticType::Never),
+            StaticType::Meta(_) => {
+                let output = self.fresh();
+                self.require(&f, &StaticType::pi(input, output.clone()), at)?;
+                Ok(output)
+            }
+            found => {
+                let output = self.fresh();
+                Err(Stop::Mismatch {
+                    expected: Box::new(self.zonk(&StaticType::pi(input, output))),
+                    found: Box::new(self.zonk(&found)),
+                    at,
+                })
+            }
+        }
+    }
+
+    /// The name node of `%prop name`, when `func` is that partial application.
+    fn property_name(&self, func: Addr) -> Option<Addr> {
+        let heap = self.heap;
+        match &*heap.view_at(func) {
+            Term::App { func, arg } => match &*heap.view_at(func.addr()) {
+                Term::Pri(id) if self.prims.get(id) == Some(&"prop") => Some(arg.addr()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// T-PROPERTY: the property `name` of a receiver of type `ty` requires
+    /// an open record with that property, whose fresh tail keeps the
+    /// receiver's other capabilities, and has its signature.
+    fn property(&mut self, at: Addr, ty: StaticType, name: Arc<str>) -> Result<StaticType, Stop> {
+        match self.resolve(&ty) {
+            StaticType::Never => Ok(StaticType::Never),
+            StaticType::Join(alts) => {
+                let mut sigs = Vec::with_capacity(alts.len());
+                for alt in alts {
+                    sigs.push(self.property(at, alt, name.clone())?);
+                }
+                Ok(StaticType::join(sigs))
+            }
+            ty => {
+                let sig = self.fresh();
+                let expected = StaticType::Record {
+                    layout: Box::new(self.fresh()),
+                    props: vec![(name, sig.clone())],
+                    ops: Vec::new(),
+                    rest: Some(Box::new(self.fresh())),
+                };
+                self.require(&ty, &expected, at)?;
+                Ok(sig)
+            }
+        }
+    }
+
+    /// The type of constructor `variant` of data type `data` (the product
+    /// constructor when `None`): its fields, curried, to `data`.
+    fn constructor(
+        &mut self,
+        at: Addr,
+        data: StaticType,
+        variant: Option<VariantId>,
+    ) -> Result<StaticType, Stop> {
+        let heap = self.heap;
+        let fields = match (&self.layout(&data), variant) {
+            (StaticType::Product { fields }, None) => Some(fields.clone()),
+            (StaticType::Sum { variants, .. }, Some(v)) => {
+                let name = heap.variant_name(v);
+                variants
+                    .iter()
+                    .find(|(n, _)| **n == *name)
+                    .map(|(_, args)| args.clone())
+            }
+            // The data type is not known: that is recorded already.
+            (StaticType::Meta(_), _) => return Ok(self.fresh()),
+            _ => None,
+        };
+        let Some(fields) = fields else {
+            // A variant the closed sum does not have, or `::New` of a sum.
+            let found = match variant {
+                Some(v) => StaticType::Sum {
+                    variants: vec![(Arc::from(heap.variant_name(v)), Vec::new())],
+                    open: false,
+                },
+                None => StaticType::Product { fields: Vec::new() },
+            };
+            return Err(Stop::Mismatch {
+                expected: Box::new(self.zonk(&data)),
+                found: Box::new(found),
+                at,
+            });
+        };
+        Ok(fields
+            .into_iter()
+            .rev()
+            .fold(data, |output, input| StaticType::pi(input, output)))
+    }
+
+    /// A match as a function. Every branch is evaluated; when the `scrutinee`
+    /// it is applied to is known to be of a closed sum, only the branches of
+    /// that sum's variants join into the result.
+    fn mat(
+        &mut self,
+        data: &MatchData,
+        scrutinee: Option<&StaticType>,
+    ) -> Result<StaticType, Stop> {
+        let heap = self.heap;
+        let known = match scrutinee.map(|s| self.layout(s)) {
+            Some(StaticType::Sum {
+                variants,
+                open: false,
+                ..
+            }) => Some(variants),
+            _ => None,
+        };
+        let mut keys = Vec::new();
+        let mut variants = Vec::new();
+        let mut outputs = Vec::new();
+        for &(key, branch) in &data.cases {
+            let ty = self.eval(branch)?;
+            let name = match &*heap.view_at(key) {
+                Term::VarId(v) => Arc::<str>::from(heap.variant_name(*v)),
+                _ => {
+                    keys.push(self.eval(key)?);
+                    outputs.push(ty);
+                    continue;
+                }
+            };
+            // A branch is applied to the variant's fields.
+            let fields = known
+                .as_ref()
+                .and_then(|known| known.iter().find(|(n, _)| *n == name));
+            let arity = match fields {
+                Some((_, args)) => args.len(),
+                None => self.binders(branch),
+            };
+            let (args, output) = self.peel(ty, arity, branch)?;
+            if known.is_none() || fields.is_some() {
+                outputs.push(output);
+            }
+            variants.push((name, args));
+        }
+        if !variants.is_empty() {
+            keys.push(StaticType::Sum {
+                variants,
+                open: data.default.is_some(),
+            });
+        }
+        let mut input = StaticType::join(keys);
+        if let Some(default) = data.default {
+            // The default branch takes the whole scrutinee.
+            let ty = self.eval(default)?;
+            let (mut args, output) = self.peel(ty, 1, default)?;
+            let arg = args.pop().unwrap();
+            if input == StaticType::Never {
+                input = arg;
+            } else {
+                self.require(&input, &arg, default)?;
+            }
+            outputs.push(output);
+        }
+        Ok(StaticType::pi(input, StaticType::join(outputs)))
+    }
+
+    /// Split `arity` inputs off the function type `ty` of the node at `at`.
+    fn peel(
+        &mut self,
+        ty: StaticType,
+        arity: usize,
+        at: Addr,
+    ) -> Result<(Vec<StaticType>, StaticType), Stop> {
+        let mut args = Vec::with_capacity(arity);
+        let mut ty = ty;
+        for _ in 0..arity {
+            let input = self.fresh();
+            let output = self.fresh();
+            self.require(&ty, &StaticType::pi(input.clone(), output.clone()), at)?;
+            args.push(input);
+            ty = output;
+        }
+        Ok((args, ty))
+    }
+
+    /// How many lambdas the node at `at` starts with.
+    fn binders(&self, mut at: Addr) -> usize {
+        let mut n = 0;
+        loop {
+            match &*self.heap.view_at(at) {
+                Term::Lam { body, .. } | Term::Use { body } => {
+                    n += 1;
+                    at = body.addr();
+                }
+                _ => return n,
+            }
+        }
+    }
+
+    /// T-OPERATOR over the builtin atomic types, following the executor's
+    /// run-time rules.
+    fn operator(
+        &mut self,
+        at: Addr,
+        op: BinaryOp,
+        l: StaticType,
+        r: StaticType,
+    ) -> Result<StaticType, Stop> {
+        let (l, r) = (self.resolve(&l), self.resolve(&r));
+        match (&l, &r) {
+            (StaticType::Never, _) | (_, StaticType::Never) => return Ok(StaticType::Never),
+            // Conversion compares any two terms.
+            _ if op == BinaryOp::Conv => return Ok(StaticType::Atom("Bool")),
+            (StaticType::Join(alts), _) => {
+                let mut outputs = Vec::with_capacity(alts.len());
+                for alt in alts {
+                    outputs.push(self.operator(at, op, alt.clone(), r.clone())?);
+                }
+                return Ok(StaticType::join(outputs));
+            }
+            (_, StaticType::Join(alts)) => {
+                let mut outputs = Vec::with_capacity(alts.len());
+                for alt in alts {
+                    outputs.push(self.operator(at, op, l.clone(), alt.clone())?);
+                }
+                return Ok(StaticType::join(outputs));
+            }
+            // With one operand known the other is required to match it; with
+            // neither, only equality is decided without knowing which.
+            (StaticType::Meta(_), StaticType::Meta(_)) => {
+                self.require(&r, &l, at)?;
+                return Ok(match op {
+                    BinaryOp::Eq | BinaryOp::Neq => StaticType::Atom("Bool"),
+                    _ => self.stuck(at, "an operator on operands of unknown type"),
+                });
+            }
+            (StaticType::Meta(_), _) => {
+                self.require(&l, &r, at)?;
+                return self.operator(at, op, r.clone(), r);
+            }
+            (_, StaticType::Meta(_)) => {
+                self.require(&r, &l, at)?;
+                return self.operator(at, op, l.clone(), l);
+            }
+            _ => {}
+        }
+        if let (StaticType::Atom(a), StaticType::Atom(b)) = (&l, &r)
+            && let Some(ty) = operator_type(op, a, b)
+        {
+            return Ok(StaticType::Atom(ty));
+        }
+        // Blame the right operand when the left one supports the operator.
+        let (expected, found) = match &l {
+            StaticType::Atom(a) if operator_type(op, a, a).is_some() => (l, r),
+            _ => (StaticType::Atom(operand_type(op)), l),
+        };
+        Err(Stop::Mismatch {
+            expected: Box::new(self.zonk(&expected)),
+            found: Box::new(self.zonk(&found)),
+            at,
+        })
+    }
+
+    fn unary(&mut self, at: Addr, op: UnaryOp, ty: StaticType) -> Result<StaticType, Stop> {
+        if let UnaryOp::TypeOf = op {
+            return Ok(StaticType::Type);
+        }
+        Ok(match (op, self.resolve(&ty)) {
+            (_, StaticType::Never) => StaticType::Never,
+            (UnaryOp::Not, StaticType::Atom(a @ ("Bool" | "Int")))
+            | (UnaryOp::Neg, StaticType::Atom(a @ ("Int" | "Float"))) => StaticType::Atom(a),
+            (_, StaticType::Meta(_)) => self.stuck(at, "an operator on an operand of unknown type"),
+            (_, found) => {
+                let expected = StaticType::Atom(match op {
+                    UnaryOp::Not => "Bool",
+                    _ => "Int",
+                });
+                return Err(Stop::Mismatch {
+                    expected: Box::new(expected),
+                    found: Box::new(self.zonk(&found)),
+                    at,
+                });
+            }
+        })
+    }
+
+    // ====================================================================
+    // Type-level regularization
+    // ====================================================================
+
+    /// Regularize the type-level term at `at` until it exposes a type value,
+    /// and read that back as a [`StaticType`]. Reduction here is type-level
+    /// beta only (plus `typeof`, which reflects a static type); a term that
+    /// needs anything else is not proven.
+    fn type_expr(&mut self, at: Addr, env: Env) -> Result<StaticType, Stop> {
+        self.regularize(Closure { at, env }, Vec::new())
+    }
+
+    /// [`Self::type_expr`] of `term` applied to `args`, the first argument
+    /// last, each with the application node that supplied it.
+    fn regularize(
+        &mut self,
+        mut term: Closure,
+        mut args: Vec<(Addr, Closure)>,
+    ) -> Result<StaticType, Stop> {
+        let heap = self.heap;
+        loop {
+            self.step()?;
+            match &*heap.view_at(term.at) {
+                Term::App { func, arg } => {
+                    let arg = Closure {
+                        at: arg.addr(),
+                        env: term.env.clone(),
+                    };
+                    args.push((term.at, arg));
+                    term.at = func.addr();
+                }
+                Term::Lam { .. } if !args.is_empty() && self.is_fix(term.at) => {
+                    let (app, f) = args.pop().unwrap();
+                    return self.fixpoint(app, f, args);
+                }
+                Term::Lam { var, body } if !args.is_empty() => {
+                    let (_, arg) = args.pop().unwrap();
+                    term.env = term.env.bind(heap.var_addr(*var), arg);
+                    term.at = body.addr();
+                }
+                Term::Use { body } if !args.is_empty() => {
+                    args.pop();
+                    term.at = body.addr();
+                }
+                Term::Var { .. } => match term.env.get(term.at) {
+                    Some(value) => term = value.clone(),
+                    None => match self.defs.get(&term.at) {
+                        Some(&value) => {
+                            term = Closure {
+                                at: value,
+                                env: Env::default(),
+                            }
+                        }
+                        None => return Ok(self.stuck(term.at, "a type that depends on a value")),
+                    },
+                },
+                Term::Dup { ptr, .. } => match heap.dup_peek(ptr) {
+                    Some(value) => term.at = value,
+                    None => return Ok(self.stuck(term.at, "a type that depends on a value")),
+                },
+                Term::Type(t) if args.is_empty() => {
+                    return self.type_info(term.at, heap.type_info(t), &term.env);
+                }
+                Term::Uop {
+                    op: UnaryOp::TypeOf,
+                    val,
+                } if args.is_empty() => return self.eval(val.addr()),
+                Term::Pri(id) if self.prims.contains_key(id) => {
+                    let name = self.prims[id];
+                    let args = args.into_iter().map(|(_, arg)| arg).collect();
+                    return self.type_primitive(term.at, name, args);
+                }
+                _ => return Ok(self.stuck(term.at, "a type expression that is not a type")),
+            }
+        }
+    }
+
+    /// Regularize `fix f` (the application at `app`) applied to `args` to a
+    /// recursive type. `f` is applied to the `fix f` it is the fixpoint of,
+    /// and when that regularizes to the same `fix f` with the same arguments
+    /// again, a [`StaticType::Rec`] stands for it. Arguments that grow on
+    /// each unfolding would never meet again, so that is not proven.
+    fn fixpoint(
+        &mut self,
+        app: Addr,
+        f: Closure,
+        mut args: Vec<(Addr, Closure)>,
+    ) -> Result<StaticType, Stop> {
+        let key: Vec<Closure> = std::iter::once(&f)
+            .chain(args.iter().rev().map(|(_, arg)| arg))
+            .map(|c| self.origin(c.clone()))
+            .collect();
+        let same = |a: &[Closure], b: &[Closure]| {
+            a.len() == b.len()
+                && a.iter()
+                    .zip(b)
+                    .all(|(a, b)| a.at == b.at && a.env.same(&b.env))
+        };
+        if let Some(fix) = self.fixes.iter_mut().find(|fix| same(&fix.key, &key)) {
+            fix.used = true;
+            return Ok(StaticType::Rec(fix.var));
+        }
+        let nested = self
+            .fixes
+            .iter()
+            .filter(|fix| same(&fix.key[..1], &key[..1]));
+        if nested.count() >= FIX_NESTING {
+            return Ok(self.stuck(app, "a recursive type that is not regular"));
+        }
+        let var = self.recs;
+        self.recs += 1;
+        self.fixes.push(Fix {
+            key,
+            var,
+            used: false,
+        });
+        let whole = Closure {
+            at: app,
+            env: f.env.clone(),
+        };
+        args.push((app, whole));
+        let body = self.regularize(f, args);
+        let fix = self.fixes.pop().unwrap();
+        Ok(match body? {
+            StaticType::Rec(_) => self.stuck(app, "a recursive type that is only itself"),
+            body if fix.used => StaticType::Mu(var, Box::new(body)),
+            body => body,
+        })
+    }
+
+    /// The term the type-level term `term` stands for, following its bindings
+    /// and duplications like [`Self::type_expr`], but no application.
+    fn origin(&self, mut term: Closure) -> Closure {
+        let heap = self.heap;
+        loop {
+            match &*heap.view_at(term.at) {
+                Term::Var { .. } => match term.env.get(term.at) {
+                    Some(value) => term = value.clone(),
+                    None => match self.defs.get(&term.at) {
+                        Some(&at) => {
+                            term = Closure {
+                                at,
+                                env: Env::default(),
+                            }
+                        }
+                        None => return term,
+                    },
+                },
+                Term::Dup { ptr, .. } => match heap.dup_peek(ptr) {
+                    Some(value) => term.at = value,
+                    None => return term,
+                },
+                _ => return term,
+            }
+        }
+    }
+
+    /// Whether the lambda at `at` is `fix`, the fixpoint combinator
+    /// `λf. (λx. f (x x)) (λx. f (x x))` with its binders duplicated.
+    fn is_fix(&self, at: Addr) -> bool {
+        let heap = self.heap;
+        let Term::Lam { var, body } = &*heap.view_at(at) else {
+            return false;
+        };
+        let f = heap.var_addr(*var);
+        let half = |at: Addr| match &*heap.view_at(at) {
+            Term::Lam { var, body } => {
+                let x = heap.var_addr(*var);
+                match &*heap.view_at(body.addr()) {
+                    Term::App { func, arg } => {
+                        self.copy_of(func.addr(), f)
+                            && match &*heap.view_at(arg.addr()) {
+                                Term::App { func, arg } => {
+                                    self.copy_of(func.addr(), x) && self.copy_of(arg.addr(), x)
+                                }
+                                _ => false,
+                            }
+                    }
+                    _ => false,
+                }
+            }
+            _ => false,
+        };
+        match &*heap.view_at(body.addr()) {
+            Term::App { func, arg } => half(func.addr()) && half(arg.addr()),
+            _ => false,
+        }
+    }
+
+    /// Whether the node at `at` is an occurrence of the binder whose
+    /// occurrence is at `var`, or a duplication of it.
+    fn copy_of(&self, at: Addr, var: Addr) -> bool {
+        match &*self.heap.view_at(at) {
+            Term::Dup { ptr, .. } => self.heap.dup_peek(ptr) == Some(var),
+            Term::Var { .. } => at == var,
+            _ => false,
+        }
+    }
+
+    /// Regularize the type primitive `name` applied to `args` (the first
+    /// argument last) to the type it builds. Meets and joins are not
+    /// computed statically.
+    fn type_primitive(
+        &mut self,
+        at: Addr,
+        name: &str,
+        mut args: Vec<Closure>,
+    ) -> Result<StaticType, Stop> {
+        match (name, args.len()) {
+            ("type_any", 0) => Ok(StaticType::Any),
+            ("type_prop" | "type_op", 3) => {
+                let (key, sig, ty) = (
+                    args.pop().unwrap(),
+                    args.pop().unwrap(),
+                    args.pop().unwrap(),
+                );
+                let Some(key) = self.literal(key) else {
+                    return Ok(self.stuck(at, "a capability name that is not a literal"));
+                };
+                let sig = self.type_expr(sig.at, sig.env)?;
+                let ty = self.type_expr(ty.at, ty.env)?;
+                Ok(with_entry(ty, name == "type_prop", key, sig))
+            }
+            _ => Ok(self.stuck(at, "a type expression that is not a type")),
+        }
+    }
+
+    /// The string literal the type-level term `term` stands for, following
+    /// its bindings like [`Self::type_expr`].
+    fn literal(&self, mut term: Closure) -> Option<Arc<str>> {
+        let heap = self.heap;
+        loop {
+            match &*heap.view_at(term.at) {
+                Term::Var { .. } => match term.env.get(term.at) {
+                    Some(value) => term = value.clone(),
+                    None => {
+                        term = Closure {
+                            at: *self.defs.get(&term.at)?,
+                            env: Env::default(),
+                        }
+                    }
+                },
+                Term::Dup { ptr, .. } => term.at = heap.dup_peek(ptr)?,
+                Term::Box(v) => {
+                    return match heap.value_get(v) {
+                        Boxed::Str(s) => Some(s.clone()),
+                        Boxed::Bytes(_) => None,
+                    };
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    /// Read the type value at `at` back, regularizing its fields and
+    /// capability signatures under `env`. Row tails are not read: satisfaction
+    /// only asks for the entries a requirement names.
+    fn type_info(&mut self, at: Addr, info: &TypeInfo, env: &Env) -> Result<StaticType, Stop> {
+        let layout = match &info.layout {
+            Layout::Any => StaticType::Any,
+            // The inhabitants of a sort are types.
+            Layout::Sort(_) => StaticType::Type,
+            Layout::Pi {
+                erased,
+                input,
+                output,
+            } => {
+                // Only a codomain that ignores its argument has a static form.
+                let body = match &*self.heap.view_at(*output) {
+                    Term::Use { body } => Some(body.addr()),
+                    _ => None,
+                };
+                match body {
+                    Some(body) => StaticType::Pi {
+                        erased: *erased,
+                        input: Box::new(self.type_expr(*input, env.clone())?),
+                        output: Box::new(self.type_expr(body, env.clone())?),
+                    },
+                    None => self.unsupported(at, "a dependent product".into()),
+                }
+            }
+            Layout::Atom(name) => match builtin(name) {
+                Some(ty) => ty,
+                None => StaticType::Named(name.clone()),
+            },
+            Layout::Product(fields) => {
+                let mut tys = Vec::with_capacity(fields.len());
+                for &field in fields {
+                    tys.push(self.type_expr(field, env.clone())?);
+                }
+                StaticType::Product { fields: tys }
+            }
+            Layout::Sum(variants) => {
+                let mut tys = Vec::with_capacity(variants.len());
+                for v in variants {
+                    let mut args = Vec::with_capacity(v.args.len());
+                    for &arg in &v.args {
+                        args.push(self.type_expr(arg, env.clone())?);
+                    }
+                    tys.push((Arc::from(self.heap.variant_name(v.name)), args));
+                }
+                StaticType::Sum {
+                    variants: tys,
+                    open: false,
+                }
+            }
+        };
+        let mut ty = layout;
+        for (row, prop) in [(&info.props, true), (&info.ops, false)] {
+            for entry in &row.entries {
+                let sig = self.type_expr(entry.sig, env.clone())?;
+                let name = Arc::from(self.heap.variant_name(entry.name));
+                ty = with_entry(ty, prop, name, sig);
+            }
+        }
+        Ok(ty)
+    }
+
+    // ====================================================================
+    // Constraint solving
+    // ====================================================================
+
+    /// Require `actual ⊆ expected` of the node at `at`.
+    fn require(
+        &mut self,
+        actual: &StaticType,
+        expected: &StaticType,
+        at: Addr,
+    ) -> Result<(), Stop> {
+        if !self.sub(actual, expected) {
+            return Err(Stop::Mismatch {
+                expected: Box::new(self.zonk(expected)),
+                found: Box::new(self.zonk(actual)),
+                at,
+            });
+        }
+        if std::mem::take(&mut self.cyclic) {
+            self.stuck(at, "a recursive type");
+        }
+        Ok(())
+    }
+
+    /// Structural satisfaction `actual ⊆ expected`, solving metavariables on
+    /// the way. Products are checked in width (extra trailing fields are
+    /// allowed, but the empty product stays distinct), sums by their variants,
+    /// and functions contravariantly in their input. Capability rows are
+    /// checked entry by entry, as `%type_sat` does: each entry `expected`
+    /// names must be present with a satisfying signature, or be added to the
+    /// open tail of `actual`. Recursive types are compared coinductively: a
+    /// pair met again while it is being compared holds.
+    fn sub(&mut self, actual: &StaticType, expected: &StaticType) -> bool {
+        use StaticType::*;
+        let (a, e) = (self.resolve(actual), self.resolve(expected));
+        match (&a, &e) {
+            (Never, _) | (_, Any) => true,
+            (Meta(x), Meta(y)) if x == y => true,
+            (Forall(..), _) => {
+                let a = self.instantiate(&a);
+                self.sub(&a, &e)
+            }
+            (_, Forall(..)) => {
+                let e = self.instantiate(&e);
+                self.sub(&a, &e)
+            }
+            (Meta(x), _) => self.bind(*x, &e),
+            (_, Meta(y)) => self.bind(*y, &a),
+            (Join(alts), _) => alts.iter().all(|alt| self.sub(alt, &e)),
+            (_, Join(alts)) => alts.iter().any(|alt| self.attempt(&a, alt)),
+            (Mu(..), _) | (_, Mu(..)) => {
+                let pair = (self.zonk(&a), self.zonk(&e));
+                if self.active.contains(&pair) {
+                    return true;
+                }
+                let (ua, ue) = (unfold(&pair.0), unfold(&pair.1));
+                self.active.push(pair);
+                let holds = self.sub(&ua, &ue);
+                self.active.pop();
+                holds
+            }
+            (Record { .. }, _) | (_, Record { .. }) => {
+                let (actual, expected) = (rows(self.zonk(&a)), rows(self.zonk(&e)));
+                if !self.sub(&actual.layout, &expected.layout) {
+                    return false;
+                }
+                let missing = |row: &Entries, wanted: &Entries| -> Entries {
+                    (wanted.iter())
+                        .filter(|(name, _)| !row.iter().any(|(n, _)| n == name))
+                        .cloned()
+                        .collect()
+                };
+                let props = missing(&actual.props, &expected.props);
+                let ops = missing(&actual.ops, &expected.ops);
+                let actual = match actual.rest {
+                    // An open record takes on the entries it lacks.
+                    Some(Meta(rest)) if !props.is_empty() || !ops.is_empty() => {
+                        let tail = Record {
+                            layout: Box::new(Any),
+                            props,
+                            ops,
+                            rest: Some(Box::new(self.fresh())),
+                        };
+                        self.bind(rest, &tail);
+                        rows(self.zonk(&a))
+                    }
+                    _ => actual,
+                };
+                self.sub_row(&actual.props, &expected.props)
+                    && self.sub_row(&actual.ops, &expected.ops)
+            }
+            (Rec(x), Rec(y)) => x == y,
+            (Atom(x), Atom(y)) => x == y,
+            (Named(x), Named(y)) => x == y,
+            (Type, Type) => true,
+            (
+                Pi {
+                    erased: ea,
+                    input: ia,
+                    output: oa,
+                },
+                Pi {
+                    erased: ee,
+                    input: ie,
+                    output: oe,
+                },
+            ) => (*ea || !*ee) && self.sub(ie, ia) && self.sub(oa, oe),
+            (Product { fields: fa }, Product { fields: fe }) => {
+                fa.len() >= fe.len()
+                    && fa.is_empty() == fe.is_empty()
+                    && fa.iter().zip(fe).all(|(a, e)| self.sub(a, e))
+            }
+            (
+                Sum { variants: va, .. },
+                Sum {
+                    variants: ve, open, ..
+                },
+            ) => va
+                .iter()
+                .all(|(name, args)| match ve.iter().find(|(n, _)| n == name) {
+                    Some((_, expected)) => {
+                        args.len() == expected.len()
+                            && args.iter().zip(expected).all(|(a, e)| self.sub(a, e))
+                    }
+                    None => *open,
+                }),
+            _ => false,
+        }
+    }
+
+    /// Row satisfaction: every entry of `expected` is in `actual`, with a
+    /// signature that satisfies its own. Other entries of `actual` are
+    /// allowed.
+    fn sub_row(
+        &mut self,
+        actual: &[(Arc<str>, StaticType)],
+        expected: &[(Arc<str>, StaticType)],
+    ) -> bool {
+        expected
+            .iter()
+            .all(|(name, e)| match actual.iter().find(|(n, _)| n == name) {
+                Some((_, a)) => self.sub(a, e),
+                None => false,
+            })
+    }
+
+    /// [`Self::sub`], undoing whatever it solved if it fails.
+    fn attempt(&mut self, actual: &StaticType, expected: &StaticType) -> bool {
+        let (metas, cyclic) = (self.metas.clone(), self.cyclic);
+        if self.sub(actual, expected) {
+            return true;
+        }
+        self.metas = metas;
+        self.cyclic = cyclic;
+        false
+    }
+
+    fn bind(&mut self, var: u32, ty: &StaticType) -> bool {
+        let ty = self.zonk(ty);
+        if occurs(var, &ty) {
+            // Only a recursive type would solve this.
+            self.cyclic = true;
+            return true;
+        }
+        let Meta::Free { level } = self.metas[var as usize] else {
+            unreachable!("bound metavariable {var} was resolved");
+        };
+        self.lower(&ty, level);
+        self.metas[var as usize] = Meta::Bound(ty);
+        true
+    }
+
+    /// Move the metavariables of `ty` out to `level`: it is about to be known
+    /// there, so it cannot be generalized any deeper.
+    fn lower(&mut self, ty: &StaticType, level: u32) {
+        let mut free = Vec::new();
+        metas(ty, &mut free);
+        for v in free {
+            if let Meta::Free { level: l } = &mut self.metas[v as usize] {
+                *l = (*l).min(level);
+            }
+        }
+    }
+
+    /// `ty` with its solved metavariables at the head followed.
+    fn resolve(&self, ty: &StaticType) -> StaticType {
+        let mut ty = ty;
+        while let StaticType::Meta(v) = ty {
+            match &self.metas[*v as usize] {
+                Meta::Bound(t) => ty = t,
+                Meta::Free { .. } => break,
+            }
+        }
+        ty.clone()
+    }
+
+    /// The layout of `ty` at the head, without its capability rows and with a
+    /// recursive type unfolded.
+    fn layout(&self, ty: &StaticType) -> StaticType {
+        match self.resolve(ty) {
+            StaticType::Record { layout, .. } => self.layout(&layout),
+            ty @ StaticType::Mu(..) => self.layout(&unfold(&ty)),
+            ty => ty,
+        }
+    }
+
+    /// `ty` with every solved metavariable in it replaced by its solution.
+    fn zonk(&self, ty: &StaticType) -> StaticType {
+        match self.resolve(ty) {
+            StaticType::Pi {
+                erased,
+                input,
+                output,
+            } => StaticType::Pi {
+                erased,
+                input: Box::new(self.zonk(&input)),
+                output: Box::new(self.zonk(&output)),
+            },
+            StaticType::Product { fields } => StaticType::Product {
+                fields: fields.iter().map(|f| self.zonk(f)).collect(),
+            },
+            StaticType::Sum { variants, open } => StaticType::Sum {
+                variants: variants
+                    .into_iter()
+                    .map(|(n, args)| (n, args.iter().map(|a| self.zonk(a)).collect()))
+                    .collect(),
+                open,
+            },
+            StaticType::Record {
+                layout,
+                mut props,
+                mut ops,
+                rest,
+            } => {
+                // A solved tail contributes its entries, and its own tail.
+                let mut rest = rest.map(|rest| self.zonk(&rest));
+                if let Some(StaticType::Record {
+                    props: more_props,
+                    ops: more_ops,
+                    rest: more,
+                    ..
+                }) = rest
+                {
+                    props.extend(more_props);
+                    ops.extend(more_ops);
+                    rest = more.map(|rest| *rest);
+                }
+                let row = |row: Vec<(Arc<str>, StaticType)>| {
+                    row.into_iter()
+                        .map(|(n, sig)| (n, self.zonk(&sig)))
+                        .collect()
+                };
+                StaticType::Record {
+                    layout: Box::new(self.zonk(&layout)),
+                    props: row(props),
+                    ops: row(ops),
+                    rest: rest.map(Box::new),
+                }
+            }
+            StaticType::Mu(v, body) => StaticType::Mu(v, Box::new(self.zonk(&body))),
+            StaticType::Join(alts) => StaticType::join(alts.iter().map(|a| self.zonk(a)).collect()),
+            StaticType::Forall(vars, body) => StaticType::Forall(vars, Box::new(self.zonk(&body))),
+            ty => ty,
+        }
+    }
+
+    fn instantiate(&mut self, ty: &StaticType) -> StaticType {
+        let StaticType::Forall(vars, body) = ty else {
+            return ty.clone();
+        };
+        let fresh: Vec<(u32, StaticType)> = vars.iter().map(|&v| (v, self.fresh())).collect();
+        substitute(body, &|g| {
+            fresh.iter().find(|(v, _)| *v == g).map(|(_, m)| m.clone())
+        })
+    }
+
+    /// Generalize the metavariables of `ty` introduced under more lambdas
+    /// than the walk is under now.
+    fn generalize(&self, ty: &StaticType) -> StaticType {
+        let ty = self.zonk(ty);
+        let mut free = Vec::new();
+        metas(&ty, &mut free);
+        free.retain(
+            |&v| matches!(self.metas[v as usize], Meta::Free { level } if level > self.level),
+        );
+        if free.is_empty() {
+            return ty.prenex();
+        }
+        let body = substitute_metas(&ty, &free);
+        StaticType::Forall(free, Box::new(body)).prenex()
+    }
+}
+
+/// The primitives of [`TypeExtensions`](crate::extension::TypeExtensions),
+/// which type evaluation gives a signature (see [`primitive_type`]) or, for
+/// `prop`, T-PROPERTY.
+const TYPE_PRIMITIVES: [&str; 7] = [
+    "type_any",
+    "type_prop",
+    "type_op",
+    "type_sat",
+    "type_meet",
+    "type_join",
+    "prop",
+];
+
+/// How many unfoldings of one type-level `fix` may be regularized inside one
+/// another, with different arguments, before it is taken not to be regular.
+const FIX_NESTING: usize = 16;
+
+/// The signature of the type primitive `name`.
+fn primitive_type(name: &str) -> StaticType {
+    let ty = || StaticType::Type;
+    match name {
+        "type_any" => ty(),
+        "type_prop" | "type_op" => StaticType::pi(
+            StaticType::Atom("String"),
+            StaticType::pi(ty(), StaticType::pi(ty(), ty())),
+        ),
+        "type_sat" => StaticType::pi(ty(), StaticType::pi(ty(), StaticType::Atom("Bool"))),
+        _ => StaticType::pi(ty(), StaticType::pi(ty(), ty())),
+    }
+}
+
+/// The builtin type a runtime-named type value (see
+/// [`HeapScope::builtin_type`]) stands for.
+fn builtin(name: &str) -> Option<StaticType> {
+    Some(match name {
+        "Int" => StaticType::Atom("Int"),
+        "Float" => StaticType::Atom("Float"),
+        "Char" => StaticType::Atom("Char"),
+        "Bool" => StaticType::Atom("Bool"),
+        "String" => StaticType::Atom("String"),
+        "Bytes" => StaticType::Atom("Bytes"),
+        "Type" => StaticType::Type,
+        _ => return None,
+    })
+}
+
+/// The result type of `op` on operands of atomic types `l` and `r`, if the
+/// executor defines it.
+fn operator_type(op: BinaryOp, l: &str, r: &str) -> Option<&'static str> {
+    use BinaryOp::*;
+    let compare = matches!(op, Eq | Neq | Lt | Lte | Gt | Gte);
+    match (l, r) {
+        ("Int", "Int") => match op {
+            Invalid | Conv => None,
+            Div => Some("Float"),
+            _ if compare => Some("Bool"),
+            _ => Some("Int"),
+        },
+        ("Int" | "Float", "Int" | "Float") => match op {
+            Add | Sub | Mul | Div | IDiv | Mod => Some("Float"),
+            _ if compare => Some("Bool"),
+            _ => None,
+        },
+        ("Bool", "Bool") => matches!(op, And | Or | Xor | Eq | Neq).then_some("Bool"),
+        ("Char", "Char") => compare.then_some("Bool"),
+        ("String", "String") | ("Bytes", "Bytes") => match op {
+            Eq | Neq => Some("Bool"),
+            Add if l == "String" => Some("String"),
+            Add => Some("Bytes"),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The operand type reported when neither operand supports `op`.
+fn operand_type(op: BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::And | BinaryOp::Or | BinaryOp::Xor => "Bool",
+        _ => "Int",
+    }
+}
+
+/// The entries of a capability row: signatures by name.
+type Entries = Vec<(Arc<str>, StaticType)>;
+
+/// `ty` with the property (or operator) `name` given the signature `sig`,
+/// replacing an entry of that name, as `%type_prop` and `%type_op` do.
+fn with_entry(ty: StaticType, prop: bool, name: Arc<str>, sig: StaticType) -> StaticType {
+    let Rows {
+        layout,
+        mut props,
+        mut ops,
+        rest,
+    } = rows(ty);
+    let row = if prop { &mut props } else { &mut ops };
+    match row.iter_mut().find(|(n, _)| *n == name) {
+        Some(entry) => entry.1 = sig,
+        None => row.push((name, sig)),
+    }
+    StaticType::Record {
+        layout: Box::new(layout),
+        props,
+        ops,
+        rest: rest.map(Box::new),
+    }
+}
+
+/// A type split into its layout and capability rows.
+struct Rows {
+    layout: StaticType,
+    props: Entries,
+    ops: Entries,
+    rest: Option<StaticType>,
+}
+
+/// The layout and capability rows of `ty`, whose rows are empty unless it is
+/// a [`StaticType::Record`].
+fn rows(ty: StaticType) -> Rows {
+    match ty {
+        StaticType::Record {
+            layout,
+            props,
+            ops,
+            rest,
+        } => Rows {
+            layout: *layout,
+            props,
+            ops,
+            rest: rest.map(|rest| *rest),
+        },
+        ty => Rows {
+            layout: ty,
+            props: Vec::new(),
+            ops: Vec::new(),
+            rest: None,
+        },
+    }
+}
+
+/// One unfolding of the recursive type `ty`: its body, with the whole in
+/// place of its variable.
+fn unfold(ty: &StaticType) -> StaticType {
+    let StaticType::Mu(v, body) = ty else {
+        return ty.clone();
+    };
+    map(body, &|t| match t {
+        StaticType::Rec(x) if x == v => Some(ty.clone()),
+        _ => None,
+    })
+}
+
+fn occurs(var: u32, ty: &StaticType) -> bool {
+    let mut free = Vec::new();
+    metas(ty, &mut free);
+    free.contains(&var)
+}
+
+/// The metavariables of an already zonked `ty`, in order of appearance.
+fn metas(ty: &StaticType, out: &mut Vec<u32>) {
+    match ty {
+        StaticType::Meta(v) => {
+            if !out.contains(v) {
+                out.push(*v);
+            }
+        }
+        StaticType::Pi { input, output, .. } => {
+            metas(input, out);
+            metas(output, out);
+        }
+        StaticType::Product { fields: tys } | StaticType::Join(tys) => {
+            for ty in tys {
+                metas(ty, out);
+            }
+        }
+        StaticType::Sum { variants, .. } => {
+            for (_, args) in variants {
+                for ty in args {
+                    metas(ty, out);
+                }
+            }
+        }
+        StaticType::Record {
+            layout,
+            props,
+            ops,
+            rest,
+        } => {
+            metas(layout, out);
+            for (_, sig) in props.iter().chain(ops) {
+                metas(sig, out);
+            }
+            if let Some(rest) = rest {
+                metas(rest, out);
+            }
+        }
+        StaticType::Forall(_, body) | StaticType::Mu(_, body) => metas(body, out),
+        StaticType::Gen(_)
+        | StaticType::Rec(_)
+        | StaticType::Atom(_)
+        | StaticType::Named(_)
+        | StaticType::Any
+        | StaticType::Type
+        | StaticType::Never => {}
+    }
+}
+
+/// `ty` with each `Gen` variable that `sub` maps replaced.
+fn substitute(ty: &StaticType, sub: &dyn Fn(u32) -> Option<StaticType>) -> StaticType {
+    map(ty, &|t| match t {
+        StaticType::Gen(v) => sub(*v),
+        _ => None,
+    })
+}
+
+/// `ty` with the metavariables `vars` turned into `Gen` variables.
+fn substitute_metas(ty: &StaticType, vars: &[u32]) -> StaticType {
+    map(ty, &|t| match t {
+        StaticType::Meta(v) if vars.contains(v) => Some(StaticType::Gen(*v)),
+        _ => None,
+    })
+}
+
+/// Rebuild `ty`, replacing every node that `leaf` maps.
+fn map(ty: &StaticType, leaf: &dyn Fn(&StaticType) -> Option<StaticType>) -> StaticType {
+    if let Some(t) = leaf(ty) {
+        return t;
+    }
+    let all = |tys: &[StaticType]| tys.iter().map(|t| map(t, leaf)).collect();
+    match ty {
+        StaticType::Pi {
+            erased,
+            input,
+            output,
+        } => StaticType::Pi {
+            erased: *erased,
+            input: Box::new(map(input, leaf)),
+            output: Box::new(map(output, leaf)),
+        },
+        StaticType::Product { fields } => StaticType::Product {
+            fields: all(fields),
+        },
+        StaticType::Sum { variants, open } => StaticType::Sum {
+            variants: variants
+                .iter()
+                .map(|(n, args)| (n.clone(), all(args)))
+                .collect(),
+            open: *open,
+        },
+        StaticType::Record {
+            layout,
+            props,
+            ops,
+            rest,
+        } => {
+            let row = |row: &[(Arc<str>, StaticType)]| {
+                row.iter()
+                    .map(|(n, sig)| (n.clone(), map(sig, leaf)))
+                    .collect()
+            };
+            StaticType::Record {
+                layout: Box::new(map(layout, leaf)),
+                props: row(props),
+                ops: row(ops),
+                rest: rest.as_ref().map(|rest| Box::new(map(rest, leaf))),
+            }
+        }
+        StaticType::Mu(v, body) => StaticType::Mu(*v, Box::new(map(body, leaf))),
+        StaticType::Join(alts) => StaticType::Join(all(alts)),
+        StaticType::Forall(vars, body) => {
+            StaticType::Forall(vars.clone(), Box::new(map(body, leaf)))
+        }
+        ty => ty.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Env, StaticType, TYPE_PRIMITIVES, TypeEvaluator, TypeOutcome, with_entry};
+    use crate::core::ast::desugar_spanned;
+    use crate::core::parse::parse_spanned;
+    use crate::core::span::NodeSpans;
+    use crate::extension::{Extensions, TypeExtensions};
+    use crate::vm::heap::{Entry, Heap, Layout, TypeInfo};
+    use crate::vm::run;
+    use crate::vm::term::{PrimId, Term};
+    use std::sync::Arc;
+
+    /// Lower `src` (resolving the type primitives, and every other primitive
+    /// to one without a signature) and type-evaluate it under `budget`.
+    fn outcome_with(src: &str, budget: Option<u64>) -> TypeOutcome {
+        let (node, log) = parse_spanned(src).unwrap();
+        let spans = NodeSpans::new([&node], log);
+        let expr = desugar_spanned(&node, &spans).unwrap();
+        let heap = Heap::new();
+        heap.with(|h| {
+            let root = h
+                .lower(
+                    &expr,
+                    &|name| {
+                        let other = PrimId::new(TYPE_PRIMITIVES.len() as u64);
+                        Some(TypeExtensions.resolve(name).unwrap_or(other))
+                    },
+                    &mut |_| None,
+                )
+                .unwrap();
+            let mut types = TypeEvaluator::new(h).with_extensions(&TypeExtensions);
+            if let Some(budget) = budget {
+                types = types.with_budget(budget);
+            }
+            types.evaluate(&root)
+        })
+    }
+
+    fn outcome(src: &str) -> TypeOutcome {
+        outcome_with(src, None)
+    }
+
+    fn type_of(src: &str) -> String {
+        match outcome(src) {
+            TypeOutcome::Ok(ty) => ty.to_string(),
+            other => panic!("{src}: {other}"),
+        }
+    }
+
+    #[test]
+    fn sharing_and_superposition() {
+        // Equal labels: both projections of `&{1, 2}` are `Int`.
+        assert_eq!(type_of(r"&{a b} = &{1, 2}; a + b"), "Int");
+        // A duplicated lambda is shared, not copied, and used at two types.
+        assert_eq!(
+            type_of(r"&f = \y -> y; ?{true -> f 1; false -> 2} (f true)"),
+            "Int"
+        );
+        // T-SUP: no alternative is selected.
+        assert_eq!(type_of(r"&{1, true}"), "Int | Bool");
+        assert_eq!(type_of(r"(&{\x -> x, \y -> y + 1}) 5"), "Int");
+    }
+
+    #[test]
+    fn the_body_of_a_lambda_is_type_evaluated() {
+        // In runtime WHNF already, but type evaluation visits the body.
+        assert_eq!(type_of(r"\x z -> (\y -> y) z"), "forall a b. {a} -> b -> b");
+        assert_eq!(
+            outcome(r"\x -> (\y -> y + 1) true"),
+            TypeOutcome::Mismatch {
+                expected: StaticType::Atom("Int"),
+                found: StaticType::Atom("Bool"),
+                span: Some(20..24),
+            }
+        );
+    }
+
+    #[test]
+    fn erased_arguments_are_checked_statically_only() {
+        let src = r"(\_ -> 0) (1 + true)";
+        assert_eq!(run(src).unwrap(), "0");
+        assert_eq!(
+            outcome(src),
+            TypeOutcome::Mismatch {
+                expected: StaticType::Atom("Int"),
+                found: StaticType::Atom("Bool"),
+                span: Some(11..19),
+            }
+        );
+    }
+
+    #[test]
+    fn unconstrained_binders_are_generalized_at_the_lambda() {
+        assert_eq!(type_of(r"\x -> x"), "forall a. a -> a");
+        assert_eq!(type_of(r"(\x -> x) 5"), "Int");
+        assert_eq!(type_of(r"id = \x -> x; id 'c'"), "Char");
+        assert_eq!(type_of(r"\_ -> 0"), "forall a. {a} -> Int");
+        // A binder the body constrains is not generalized.
+        assert_eq!(type_of(r"\x -> x + 1"), "Int -> Int");
+        assert_eq!(type_of(r"\&f x -> f (f x)"), "forall a. (a -> a) -> a -> a");
+    }
+
+    #[test]
+    fn sums_and_matches() {
+        let opt = r"Opt = \T -> type { Some(T), None }; ";
+        // Type-level beta regularizes the constructor's type.
+        assert_eq!(
+            type_of(&format!("{opt}(Opt (typeof 0))::Some 5")),
+            "type{Some(Int), None}"
+        );
+        assert_eq!(
+            type_of(&format!(
+                "{opt}?{{Some x -> x; None -> 0}} ((Opt (typeof 0))::Some 5)"
+            )),
+            "Int"
+        );
+        // A producer that may emit `None` does not satisfy a consumer that
+        // only handles `Some`...
+        assert!(matches!(
+            outcome(&format!("{opt}?{{Some x -> x}} ((Opt (typeof 0))::Some 5)")),
+            TypeOutcome::Mismatch { .. }
+        ));
+        // ...but the reverse holds, and the branch the closed sum excludes
+        // does not join into the result.
+        assert_eq!(
+            type_of(r"?{Some x -> x; None -> true} ((type { Some(typeof 0) })::Some 5)"),
+            "Int"
+        );
+        assert!(matches!(
+            outcome(&format!("{opt}(Opt (typeof 0))::Some true")),
+            TypeOutcome::Mismatch { .. }
+        ));
+        assert!(matches!(
+            outcome(&format!("{opt}(Opt (typeof 0))::Other")),
+            TypeOutcome::Mismatch { .. }
+        ));
+        assert_eq!(type_of(r"?{1 -> 100; x -> x + 1}"), "Int -> Int");
+    }
+
+    #[test]
+    fn projections_keep_the_actual_type() {
+        assert_eq!(type_of(r"1 :> typeof 0"), "Int");
+        // Satisfied by a narrower sum, whose type is kept.
+        assert_eq!(
+            type_of(r"(type { Some(typeof 0) })::Some 5 :> type { Some(typeof 0), None }"),
+            "type{Some(Int)}"
+        );
+        // Unlike at run time, a projection in an erased argument is checked.
+        let src = r"(\_ -> 0) (true :> typeof 0)";
+        assert_eq!(run(src).unwrap(), "0");
+        assert_eq!(
+            outcome(src),
+            TypeOutcome::Mismatch {
+                expected: StaticType::Atom("Int"),
+                found: StaticType::Atom("Bool"),
+                span: Some(11..15),
+            }
+        );
+    }
+
+    #[test]
+    fn capability_rows_are_checked_entry_by_entry() {
+        let defs = concat!(
+            r#"&HasLen = %type_prop "len" (typeof 0) %type_any; "#,
+            r#"&T = %type_prop "len" (typeof 0) (%type_prop "name" (typeof "") (type (typeof 0))); "#,
+        );
+        // Row width: extra entries are allowed, and the actual type is kept.
+        assert_eq!(
+            type_of(&format!("{defs}T::New 5 :> HasLen")),
+            "type(Int) {.name: String, .len: Int}"
+        );
+        assert_eq!(type_of(&format!("{defs}HasLen")), "Type");
+        let mismatch = |src: &str| match outcome(&format!("{defs}{src}")) {
+            TypeOutcome::Mismatch {
+                expected, found, ..
+            } => (expected.to_string(), found.to_string()),
+            other => panic!("{src}: {other}"),
+        };
+        // A missing entry, an entry whose signature does not satisfy, and a
+        // property required of the operator row each mismatch.
+        assert_eq!(
+            mismatch("(type (typeof 0))::New 5 :> HasLen"),
+            ("* {.len: Int}".into(), "type(Int)".into())
+        );
+        assert_eq!(
+            mismatch(r#"T::New 5 :> %type_prop "len" (typeof "") %type_any"#).0,
+            "* {.len: String}"
+        );
+        assert_eq!(
+            mismatch(r#"T::New 5 :> %type_op "len" (typeof 0) %type_any"#).0,
+            "* {(len): Int}"
+        );
+        // The layout is still checked beside the rows.
+        assert_eq!(
+            mismatch(r#"T::New 5 :> %type_prop "len" (typeof 0) (type (typeof true))"#).0,
+            "type(Bool) {.len: Int}"
+        );
+    }
+
+    #[test]
+    fn named_records_keep_their_name() {
+        let heap = Heap::new();
+        heap.with(|h| {
+            let mut types = TypeEvaluator::new(h);
+            let int = h.alloc(Term::Type(h.builtin_type("Int")));
+            // A record over the opaque atom `tag`, with the property `len`.
+            let mut named = |tag: &str| {
+                let mut info = TypeInfo::new(Layout::Atom(Arc::from(tag)));
+                info.props.entries.push(Entry {
+                    name: h.intern_variant("len"),
+                    sig: int.addr(),
+                });
+                match types.type_info(int.addr(), &info, &Env::default()) {
+                    Ok(ty) => ty,
+                    Err(_) => panic!("{tag} was not read"),
+                }
+            };
+            let (foo, other_foo, bar) = (named("Foo"), named("Foo"), named("Bar"));
+            assert_eq!(foo.to_string(), "Foo {.len: Int}");
+            assert!(types.sub(&foo, &other_foo));
+            assert!(!types.sub(&foo, &bar));
+            // Without its row, the name alone still tells them apart.
+            let name = |tag: &str| StaticType::Named(Arc::from(tag));
+            assert!(types.sub(&foo, &name("Foo")));
+            assert!(!types.sub(&name("Foo"), &foo));
+            assert!(!types.sub(&name("Foo"), &name("Bar")));
+            let empty = StaticType::Product { fields: Vec::new() };
+            assert!(!types.sub(&name("Foo"), &empty));
+            let bar_len = with_entry(name("Bar"), true, Arc::from("len"), StaticType::Atom("Int"));
+            assert_eq!(bar_len, bar);
+        });
+    }
+
+    #[test]
+    fn fix_has_its_signature() {
+        assert_eq!(type_of(r"fix"), "forall a. (a -> a) -> a");
+        // Its variable is instantiated at the top, where nothing generalizes.
+        assert_eq!(type_of(r"fix (\f x -> f x)"), "a -> b");
+        assert_eq!(
+            type_of(r"fix (\&f n -> ?{0 -> 1; &m -> m * f (m - 1)} n) 5"),
+            "Int"
+        );
+    }
+
+    #[test]
+    fn recursive_types_are_satisfied_coinductively() {
+        let defs = concat!(
+            r"&List = fix (\L -> type { Nil, Cons(typeof 0, L) }); ",
+            r"&Pairs = fix (\L -> type { Nil, Cons(typeof 0, type { Nil, Cons(typeof 0, L) }) }); ",
+            r"&Other = fix (\L -> type { Nil, Cons(typeof 0, type { Nil, Cons(typeof true, L) }) }); ",
+        );
+        let list = "mu a. type{Nil, Cons(Int, a)}";
+        // The tail is read independently of the list it is consed onto.
+        assert_eq!(
+            type_of(&format!("{defs}List::Cons 1 (List::Cons 2 List::Nil)")),
+            list
+        );
+        assert_eq!(type_of(&format!("{defs}List::Nil :> List")), list);
+        // Differently unrolled, but the same infinite type, either way round.
+        assert_eq!(
+            type_of(&format!("{defs}Pairs::Cons 1 Pairs::Nil :> List")),
+            "mu a. type{Nil, Cons(Int, type{Nil, Cons(Int, a)})}"
+        );
+        assert_eq!(type_of(&format!("{defs}List::Nil :> Pairs")), list);
+        // A tail that differs further down does not satisfy.
+        assert!(matches!(
+            outcome(&format!("{defs}Other::Nil :> List")),
+            TypeOutcome::Mismatch { .. }
+        ));
+        assert!(matches!(
+            outcome(&format!("{defs}List::Cons 1 (Other::Cons 2 Other::Nil)")),
+            TypeOutcome::Mismatch { .. }
+        ));
+        // A parameterized recursive type, at the same argument each time.
+        let poly = r"&List = fix (\L &A -> type { Nil, Cons(A, L A) }); ";
+        assert_eq!(
+            type_of(&format!(
+                "{poly}(List (typeof 0))::Cons 1 (List (typeof 0))::Nil"
+            )),
+            list
+        );
+        // Arguments that grow on each unfolding are never regular.
+        let nest = r"&Nest = fix (\N &A -> type { Leaf(A), Node(N (type (A, A))) }); ";
+        assert!(matches!(
+            outcome(&format!("{nest}(Nest (typeof 0))::Leaf 1")),
+            TypeOutcome::Stuck { .. }
+        ));
+    }
+
+    #[test]
+    fn properties_constrain_an_open_row() {
+        // The receiver is required to have `foo`, and nothing more.
+        assert_eq!(
+            type_of(r#"\x -> %prop "foo" x"#),
+            "forall a b c. (a {.foo: b, ..c}) -> b"
+        );
+        // The fresh tail keeps the capabilities a later access requires.
+        assert_eq!(
+            type_of(r#"\&x -> %prop "foo" x == %prop "bar" x"#),
+            "forall a b c. (a {.foo: b, .bar: b, ..c}) -> Bool"
+        );
+        let t = r#"&T = %type_prop "len" (typeof 0) (type (typeof 0)); "#;
+        assert_eq!(type_of(&format!(r#"{t}%prop "len" (T::New 5)"#)), "Int");
+        assert_eq!(
+            type_of(&format!(r#"{t}(\x -> %prop "len" x) (T::New 5)"#)),
+            "Int"
+        );
+        for src in [
+            format!(r#"{t}%prop "size" (T::New 5)"#),
+            r#"%prop "len" 5"#.to_string(),
+            format!(r#"{t}(\x -> %prop "len" x) 5"#),
+        ] {
+            assert!(
+                matches!(outcome(&src), TypeOutcome::Mismatch { .. }),
+                "{src}"
+            );
+        }
+    }
+
+    #[test]
+    fn width_variance_and_never() {
+        let heap = Heap::new();
+        heap.with(|h| {
+            use StaticType::*;
+            let mut types = TypeEvaluator::new(h);
+            let product = |fields| Product { fields };
+            let (int, string) = (Atom("Int"), Atom("String"));
+            // Width: a product with more fields satisfies one with fewer, but
+            // the empty product is a distinct layout.
+            let foo_bar = product(vec![int.clone(), string.clone()]);
+            assert!(types.sub(&foo_bar, &product(vec![int.clone()])));
+            assert!(!types.sub(&product(vec![int.clone()]), &foo_bar));
+            assert!(!types.sub(&product(vec![string.clone()]), &product(vec![int.clone()])));
+            assert!(!types.sub(&foo_bar, &product(vec![])));
+            // PI-SAT: a broader input and a narrower output satisfy.
+            let number = StaticType::join(vec![int.clone(), Atom("Float")]);
+            let actual = StaticType::pi(number.clone(), int.clone());
+            let expected = StaticType::pi(int.clone(), number.clone());
+            assert!(types.sub(&actual, &expected));
+            assert!(!types.sub(&expected, &actual));
+            // `Never` satisfies everything and adds nothing to a join.
+            assert!(types.sub(&Never, &int));
+            assert_eq!(StaticType::join(vec![Never, int.clone()]), int);
+        });
+    }
+
+    #[test]
+    fn incomplete_outcomes() {
+        // A constructor of a type only known at run time.
+        assert!(matches!(
+            outcome(r"\T -> T::Some 5"),
+            TypeOutcome::Stuck { .. }
+        ));
+        assert!(matches!(
+            outcome(r"\x y -> x + y"),
+            TypeOutcome::Stuck { .. }
+        ));
+        // Self-application needs a recursive type.
+        assert!(matches!(outcome(r"\&x -> x x"), TypeOutcome::Stuck { .. }));
+        assert!(matches!(
+            outcome(r"%add 1 2"),
+            TypeOutcome::Unsupported { .. }
+        ));
+        // A type-level computation that never exposes a type.
+        assert_eq!(
+            outcome_with(r"((\&x -> x x) (\&x -> x x))::New", Some(10_000)),
+            TypeOutcome::Exhausted
+        );
+        // A mismatch is still found after something that is not proven.
+        assert!(matches!(
+            outcome(r"(\T -> T::Some 5) (1 + true)"),
+            TypeOutcome::Mismatch { .. }
+        ));
+    }
+}
@@ -3,7 +3,7 @@
 use std::borrow::Cow;
 use std::sync::Arc;
 
-use atlas_core::extension::{Extensions, Handle, PrimReduce};
+use atlas_core::extension::{Extensions, Handle, PrimError, PrimReduce};
 use atlas_core::vm::exec::{ExecPolicy, Executor};
 use atlas_core::vm::heap::Boxed;
 use atlas_core::vm::term::{PrimId, Term};
@@ -88,58 +88,68 @@ impl Extensions for IoExtensions {
     ) -> PrimReduce<'a, 'h> {
         Box::pin(async move {
             if id.get() != FETCH_ID {
-                return Err("unknown atlas-io primitive".to_string());
+                return Err(PrimError::new(id, "unknown atlas-io primitive"));
             }
-            let mut args = args.into_iter();
-            let url = exec.whnf_at(args.next().expect("fetch URL argument")).await;
-            let hash = exec
-                .whnf_at(args.next().expect("fetch hash argument"))
-                .await;
-            let url = match &*url.view() {
-                Term::Box(value) => match exec.heap.value_get(value) {
-                    Boxed::Str(url) => url.clone(),
-                    _ => return Err("%fetch expects its URL to be a String".to_string()),
-                },
-                _ => return Err("%fetch expects its URL to be a String".to_string()),
-            };
-            let hash = match &*hash.view() {
-                Term::Box(value) => match exec.heap.value_get(value) {
-                    Boxed::Str(hash) => hash.clone(),
-                    _ => return Err("%fetch expects its hash to be a String".to_string()),
-                },
-                _ => return Err("%fetch expects its hash to be a String".to_string()),
-            };
-            let (algorithm, expected) = HashAlgorithm::parse(&hash)?;
-            let response = reqwest::get(url.as_ref())
+            fetch(exec, args)
                 .await
-                .map_err(|error| format!("%fetch could not fetch {url:?}: {error}"))?
-                .error_for_status()
-                .map_err(|error| format!("%fetch could not fetch {url:?}: {error}"))?;
-            let bytes = response
-                .bytes()
-                .await
-                .map_err(|error| format!("%fetch could not read {url:?}: {error}"))?;
-            let actual = algorithm.digest(&bytes);
-            if actual != expected {
-                return Err(format!(
-                    "%fetch hash mismatch for {url:?}: expected {hash}, got {}-{}",
-                    match algorithm {
-                        HashAlgorithm::Sha256 => "sha256",
-                        HashAlgorithm::Md5 => "md5",
-                    },
-                    hex::encode(actual)
-                ));
-            }
-            Ok(Handle::new(
-                exec.heap.alloc(Term::Box(
-                    exec.heap.value(Boxed::Bytes(Arc::from(bytes.as_ref()))),
-                )),
-                exec.heap,
-            ))
+                .map_err(|message| PrimError::new(id, message))
         })
     }
 }
 
+/// `%fetch url hash`: force both arguments, download, and verify.
+async fn fetch<'e, 'h, P: ExecPolicy, X: Extensions>(
+    exec: &Executor<'e, 'h, P, X>,
+    args: Vec<Handle<'h>>,
+) -> Result<Handle<'h>, String> {
+    let mut args = args.into_iter();
+    let url = exec.whnf_at(args.next().expect("fetch URL argument")).await;
+    let hash = exec
+        .whnf_at(args.next().expect("fetch hash argument"))
+        .await;
+    let url = match &*url.view() {
+        Term::Box(value) => match exec.heap.value_get(value) {
+            Boxed::Str(url) => url.clone(),
+            _ => return Err("%fetch expects its URL to be a String".to_string()),
+        },
+        _ => return Err("%fetch expects its URL to be a String".to_string()),
+    };
+    let hash = match &*hash.view() {
+        Term::Box(value) => match exec.heap.value_get(value) {
+            Boxed::Str(hash) => hash.clone(),
+            _ => return Err("%fetch expects its hash to be a String".to_string()),
+        },
+        _ => return Err("%fetch expects its hash to be a String".to_string()),
+    };
+    let (algorithm, expected) = HashAlgorithm::parse(&hash)?;
+    let response = reqwest::get(url.as_ref())
+        .await
+        .map_err(|error| format!("%fetch could not fetch {url:?}: {error}"))?
+        .error_for_status()
+        .map_err(|error| format!("%fetch could not fetch {url:?}: {error}"))?;
+    let bytes = response
+        .bytes()
+        .await
+        .map_err(|error| format!("%fetch could not read {url:?}: {error}"))?;
+    let actual = algorithm.digest(&bytes);
+    if actual != expected {
+        return Err(format!(
+            "%fetch hash mismatch for {url:?}: expected {hash}, got {}-{}",
+            match algorithm {
+                HashAlgorithm::Sha256 => "sha256",
+                HashAlgorithm::Md5 => "md5",
+            },
+            hex::encode(actual)
+        ));
+    }
+    Ok(Handle::new(
+        exec.heap.alloc(Term::Box(
+            exec.heap.value(Boxed::Bytes(Arc::from(bytes.as_ref()))),
+        )),
+        exec.heap,
+    ))
+}
+
 #[cfg(test)]
 mod tests {
     use std::io::{Read, Write};
@@ -178,6 +188,17 @@ mod tests {
         server.join().unwrap();
     }
 
+    /// Run `source`, which must fail in `%fetch`, and return the failure message.
+    fn fetch_error(source: &str) -> String {
+        match atlas_core::vm::run_with(source, &IoExtensions) {
+            Err(atlas_core::Error::Primitive(error)) => {
+                assert_eq!(Some(error.id), IoExtensions.resolve("fetch"));
+                error.message
+            }
+            other => panic!("expected %fetch to fail, got {other:?}"),
+        }
+    }
+
     #[test]
     fn fetches_md5_verified_bytes() {
         let (url, server) = serve("200 OK", b"hello");
@@ -193,20 +214,12 @@ mod tests {
     #[test]
     fn rejects_hash_mismatches_and_http_errors() {
         let (url, server) = serve("200 OK", b"hello");
-        let error = atlas_core::vm::run_with(
-            &format!(r#"%fetch {url:?} "sha256-{}""#, "00".repeat(32)),
-            &IoExtensions,
-        )
-        .unwrap_err();
+        let error = fetch_error(&format!(r#"%fetch {url:?} "sha256-{}""#, "00".repeat(32)));
         assert!(error.contains("hash mismatch"), "got: {error}");
         server.join().unwrap();
 
         let (url, server) = serve("404 Not Found", b"missing");
-        let error = atlas_core::vm::run_with(
-            &format!(r#"%fetch {url:?} "sha256-{}""#, "00".repeat(32)),
-            &IoExtensions,
-        )
-        .unwrap_err();
+        let error = fetch_error(&format!(r#"%fetch {url:?} "sha256-{}""#, "00".repeat(32)));
         assert!(error.contains("404"), "got: {error}");
         server.join().unwrap();
     }
@@ -227,7 +240,7 @@ mod tests {
             (r#"%fetch "http://localhost" "md5-00""#, "md5 requires 16"),
         ];
         for (source, expected) in cases {
-            let error = atlas_core::vm::run_with(source, &IoExtensions).unwrap_err();
+            let error = fetch_error(source);
             assert!(error.contains(expected), "got: {error}");
         }
     }
@@ -42,16 +42,36 @@ pub struct StructDecl<'src> {
     pub entries: Vec<(&'src str, Type<'src>)>,
 }
 
+/// A method signature of a trait: `eq a b`, or `ne a b = !(eq a b)` with a
+/// default body. The first argument is the receiver.
+#[derive(Debug, Clone)]
+pub struct TraitMethod<'src> {
+    pub name: &'src str,
+    pub args: Vec<Pattern<'src>>,
+    pub default: Option<ExprBlock<'src>>,
+}
+
 #[derive(Debug, Clone)]
 pub struct TraitDecl<'src> {
     pub modifier: Option<Modifier>,
     pub name: &'src str,
+    pub methods: Vec<TraitMethod<'src>>,
 }
 
+/// `impl Trait for Type { .. }`, whose methods are plain function declarations.
 #[derive(Debug, Clone)]
 pub struct ImplDecl<'src> {
     pub modifier: Option<Modifier>,
-    pub name: &'src str,
+    pub trait_name: &'src str,
+    pub ty: Type<'src>,
+    pub methods: Vec<FnDecl<'src>>,
+}
+
+/// `rec { .. }`: functions that may all call each other (and themselves).
+#[derive(Debug, Clone)]
+pub struct RecDecl<'src> {
+    pub modifier: Option<Modifier>,
+    pub fns: Vec<FnDecl<'src>>,
 }
 
 #[derive(Debug, Clone)]
@@ -65,7 +85,9 @@ pub struct AliasDecl<'src> {
 pub struct ModDecl<'src> {
     pub modifier: Option<Modifier>,
     pub name: &'src str,
-    pub value: Module<'src>,
+    /// The inline body of `mod foo { .. }`; `None` for `mod foo;`, whose body
+    /// is the file `foo.at` next to the declaring one.
+    pub value: Option<Module<'src>>,
 }
 
 #[derive(Debug, Clone)]
@@ -73,6 +95,7 @@ pub enum Declaration<'src> {
     Mod(ModDecl<'src>),
     Let(LetDecl<'src>),
     Fn(FnDecl<'src>),
+    Rec(RecDecl<'src>),
     // Types
     Alias(AliasDecl<'src>),
     Enum(EnumDecl<'src>),
@@ -81,6 +104,27 @@ pub enum Declaration<'src> {
     Impl(ImplDecl<'src>),
 }
 
+impl Declaration<'_> {
+    pub fn modifier(&self) -> Option<&Modifier> {
+        match self {
+            Declaration::Mod(d) => d.modifier.as_ref(),
+            Declaration::Let(d) => d.modifier.as_ref(),
+            Declaration::Fn(d) => d.modifier.as_ref(),
+            Declaration::Rec(d) => d.modifier.as_ref(),
+            Declaration::Alias(d) => d.modifier.as_ref(),
+            Declaration::Enum(d) => d.modifier.as_ref(),
+            Declaration::Struct(d) => d.modifier.as_ref(),
+            Declaration::Trait(d) => d.modifier.as_ref(),
+            Declaration::Impl(d) => d.modifier.as_ref(),
+        }
+    }
+
+    /// Whether the declaration is visible outside its module.
+    pub fn is_pub(&self) -> bool {
+        matches!(self.modifier(), Some(Modifier::Pub))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Module<'src> {
     pub decls: Vec<Declaration<'src>>,
@@ -62,6 +62,13 @@ pub struct ExprBlock<'src> {
     pub value: Option<Expr<'src>>,
 }
 
+/// `|x, y| e`. The body is kept as a block holding just `e`, like a fn's.
+#[derive(Debug, Clone)]
+pub struct Closure<'src> {
+    pub params: Vec<Pattern<'src>>,
+    pub body: ExprBlock<'src>,
+}
+
 #[derive(Debug, Clone)]
 pub enum Constructor<'src> {
     Struct(&'src str, Vec<(&'src str, Expr<'src>)>),
@@ -79,6 +86,7 @@ pub enum Expr<'src> {
     IfElse(Box<IfElse<'src>>),
     Match(Box<Match<'src>>),
     Block(Box<ExprBlock<'src>>),
+    Closure(Box<Closure<'src>>),
     Unary {
         op: UnaryOp,
         expr: Box<Expr<'src>>,
@@ -88,9 +96,12 @@ pub enum Expr<'src> {
         op: InfixOp,
         rhs: Box<Expr<'src>>,
     },
+    // foo.bar, or foo.0 for a tuple's first field
     Project(Box<Expr<'src>>, &'src str),
     // Something like foo::bar
     Scope(Vec<&'src str>),
     Index(Box<Expr<'src>>, Box<Expr<'src>>),
     Call(Box<Expr<'src>>, Vec<Expr<'src>>),
+    // `x |> f a`, which calls `f a x`
+    Pipe(Box<Expr<'src>>, Box<Expr<'src>>),
 }
@@ -1,9 +1,11 @@
 mod decl;
 mod expr;
+mod span;
 mod types;
 
 pub use decl::*;
 pub use expr::*;
+pub use span::ExprSpans;
 pub use types::*;
 
 #[derive(Debug, Clone)]
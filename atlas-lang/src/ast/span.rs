@@ -0,0 +1,215 @@
+//! Source spans of atlas expressions, kept beside the tree as core keeps its
+//! own (see [`atlas_core::core::span`]).
+//!
+//! The parser logs the byte range of every [`Expr`] it builds into a
+//! [`SpanLog`], children before their parent and siblings left to right.
+//! [`ExprSpans`] replays that log over a post-order walk of the parsed tree to
+//! key each span by its expression's address. Declarations and blocks have no
+//! spans of their own; the walk only passes through them.
+
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::ops::Range;
+
+use atlas_core::core::span::SpanLog;
+
+use super::{Constructor, Declaration, Expr, ExprBlock, Module, ReplInput};
+
+/// The source byte range of each expression of a parsed tree, by address.
+///
+/// Borrows the tree, so it cannot be moved (and its expressions' addresses
+/// invalidated) while the spans are in use.
+#[derive(Debug, Default)]
+pub struct ExprSpans<'n> {
+    spans: HashMap<*const Expr<'n>, Range<usize>>,
+    _tree: PhantomData<&'n Expr<'n>>,
+}
+
+/// A step of the walk: an item to visit, or an expression whose children have
+/// all been visited.
+enum Step<'n> {
+    Expr(&'n Expr<'n>),
+    Decl(&'n Declaration<'n>),
+    Done(&'n Expr<'n>),
+}
+
+impl<'n> ExprSpans<'n> {
+    /// The spans `log` recorded while parsing the expression `e`.
+    pub fn of_expr(e: &'n Expr<'n>, log: SpanLog) -> Self {
+        Self::new(vec![Step::Expr(e)], log)
+    }
+
+    /// The spans `log` recorded while parsing the module `m`.
+    pub fn of_module(m: &'n Module<'n>, log: SpanLog) -> Self {
+        Self::new(m.decls.iter().map(Step::Decl).collect(), log)
+    }
+
+    /// The spans `log` recorded while parsing the REPL entry `r`.
+    pub fn of_repl(r: &'n ReplInput<'n>, log: SpanLog) -> Self {
+        match r {
+            ReplInput::Expr(e) => Self::of_expr(e, log),
+            ReplInput::Declaration(d) => Self::new(vec![Step::Decl(d)], log),
+        }
+    }
+
+    fn new(roots: Vec<Step<'n>>, log: SpanLog) -> Self {
+        let mut log = log.into_spans().into_iter();
+        let mut spans = HashMap::with_capacity(log.len());
+        let mut stack: Vec<_> = roots.into_iter().rev().collect();
+        let mut children = Vec::new();
+        while let Some(step) = stack.pop() {
+            match step {
+                Step::Done(e) => {
+                    let span = log.next();
+                    debug_assert!(
+                        span.is_some(),
+                        "fewer spans recorded than expressions built"
+                    );
+                    if let Some(span) = span {
+                        spans.insert(e as *const Expr<'n>, span);
+                    }
+                    continue;
+                }
+                Step::Expr(e) => {
+                    stack.push(Step::Done(e));
+                    expr_children(e, &mut children);
+                }
+                Step::Decl(d) => decl_children(d, &mut children),
+            }
+            stack.extend(children.drain(..).rev());
+        }
+        debug_assert!(
+            log.next().is_none(),
+            "more spans recorded than expressions built"
+        );
+        ExprSpans {
+            spans,
+            _tree: PhantomData,
+        }
+    }
+
+    /// The source byte range `e` was parsed from.
+    pub fn get(&self, e: &Expr<'n>) -> Option<Range<usize>> {
+        self.spans.get(&(e as *const Expr<'n>)).cloned()
+    }
+}
+
+/// The direct sub-expressions and declarations of `e`, in source order.
+fn expr_children<'n>(e: &'n Expr<'n>, out: &mut Vec<Step<'n>>) {
+    match e {
+        Expr::Literal(_) | Expr::Identifier(_) | Expr::Scope(_) => {}
+        Expr::Constructor(c) => match c {
+            Constructor::Empty(_) => {}
+            Constructor::Tuple(_, args) => out.extend(args.iter().map(Step::Expr)),
+            Constructor::Struct(_, fields) => out.extend(fields.iter().map(|(_, e)| Step::Expr(e))),
+        },
+        Expr::Tuple(t) => out.extend(t.fields.iter().map(Step::Expr)),
+        Expr::List(l) => out.extend(l.elems.iter().map(Step::Expr)),
+        Expr::IfElse(b) => out.extend([&b.cond, &b.if_expr, &b.else_expr].map(Step::Expr)),
+        Expr::Match(m) => {
+            out.push(Step::Expr(&m.scrut));
+            out.extend(m.arms.iter().map(|arm| Step::Expr(&arm.body)));
+        }
+        Expr::Block(b) => block_children(b, out),
+        Expr::Closure(c) => block_children(&c.body, out),
+        Expr::Unary { expr, .. } | Expr::Project(expr, _) => out.push(Step::Expr(expr)),
+        Expr::Infix { lhs, rhs, .. } | Expr::Index(lhs, rhs) | Expr::Pipe(lhs, rhs) => {
+            out.extend([Step::Expr(lhs), Step::Expr(rhs)])
+        }
+        Expr::Call(f, args) => {
+            out.push(Step::Expr(f));
+            out.extend(args.iter().map(Step::Expr));
+        }
+    }
+}
+
+fn block_children<'n>(b: &'n ExprBlock<'n>, out: &mut Vec<Step<'n>>) {
+    out.extend(b.decls.iter().map(Step::Decl));
+    out.extend(b.value.iter().map(Step::Expr));
+}
+
+/// The expressions and nested declarations of `d`, in source order.
+fn decl_children<'n>(d: &'n Declaration<'n>, out: &mut Vec<Step<'n>>) {
+    match d {
+        Declaration::Mod(m) => {
+            out.extend(m.value.iter().flat_map(|m| &m.decls).map(Step::Decl));
+        }
+        Declaration::Let(l) => out.push(Step::Expr(&l.value)),
+        Declaration::Fn(f) => block_children(&f.body, out),
+        Declaration::Rec(r) => r.fns.iter().for_each(|f| block_children(&f.body, out)),
+        Declaration::Trait(t) => t
+            .methods
+            .iter()
+            .filter_map(|m| m.default.as_ref())
+            .for_each(|b| block_children(b, out)),
+        Declaration::Impl(i) => i.methods.iter().for_each(|f| block_children(&f.body, out)),
+        Declaration::Alias(_) | Declaration::Enum(_) | Declaration::Struct(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ExprSpans;
+    use crate::ast::{Declaration, Expr, ReplInput};
+    use crate::parser::{parse_expr_spanned, parse_module_spanned, parse_repl_spanned};
+
+    /// The source text of `e`.
+    fn text<'s>(src: &'s str, spans: &ExprSpans, e: &Expr) -> &'s str {
+        &src[spans.get(e).expect("expression has no span")]
+    }
+
+    #[test]
+    fn every_expression_gets_its_own_span() {
+        let src = "f (1 + x) [y, 2] |> g";
+        let (e, log) = parse_expr_spanned(src).unwrap();
+        let spans = ExprSpans::of_expr(&e, log);
+        assert_eq!(text(src, &spans, &e), src);
+        let Expr::Pipe(call, g) = &e else {
+            panic!("{e:?}")
+        };
+        assert_eq!(text(src, &spans, g), "g");
+        let Expr::Call(f, args) = &**call else {
+            panic!("{call:?}")
+        };
+        assert_eq!(text(src, &spans, f), "f");
+        // The parentheses only group: the infix is their content.
+        assert_eq!(text(src, &spans, &args[0]), "1 + x");
+        let Expr::Infix { lhs, rhs, .. } = &args[0] else {
+            panic!("{:?}", args[0])
+        };
+        assert_eq!(text(src, &spans, lhs), "1");
+        assert_eq!(text(src, &spans, rhs), "x");
+        assert_eq!(text(src, &spans, &args[1]), "[y, 2]");
+    }
+
+    #[test]
+    fn declarations_and_backtracking_keep_spans_in_line() {
+        // The first line is tried as a declaration before it parses as an
+        // expression.
+        let src = "{\nlet p = P { x, y: 2 }\n|a| a.x\n}";
+        let (input, log) = parse_repl_spanned(src).unwrap();
+        let spans = ExprSpans::of_repl(&input, log);
+        let ReplInput::Expr(Expr::Block(b)) = &input else {
+            panic!("{input:?}")
+        };
+        let Declaration::Let(p) = &b.decls[0] else {
+            panic!("{:?}", b.decls[0])
+        };
+        assert_eq!(text(src, &spans, &p.value), "P { x, y: 2 }");
+        let value = b.value.as_ref().unwrap();
+        assert_eq!(text(src, &spans, value), "|a| a.x");
+        let Expr::Closure(c) = value else {
+            panic!("{value:?}")
+        };
+        assert_eq!(text(src, &spans, c.body.value.as_ref().unwrap()), "a.x");
+
+        let src = "mod m {\nf x = -x\n}\nrec {\ng = (1, ())\n}";
+        let (m, log) = parse_module_spanned(src).unwrap();
+        let spans = ExprSpans::of_module(&m, log);
+        let Declaration::Rec(r) = &m.decls[1] else {
+            panic!("{:?}", m.decls[1])
+        };
+        let g = r.fns[0].body.value.as_ref().unwrap();
+        assert_eq!(text(src, &spans, g), "(1, ())");
+    }
+}
@@ -11,6 +11,8 @@ pub enum Pattern<'src> {
     Wildcard,
     Literal(Literal<'src>),
     Constructor(&'src str, Vec<Pattern<'src>>),
+    // Foo { x: p, y }, where `y` alone is short for `y: y`
+    Struct(&'src str, Vec<(&'src str, Pattern<'src>)>),
     Tuple(Vec<Pattern<'src>>),
     Typed(Box<Pattern<'src>>, Type<'src>),
 }
@@ -0,0 +1,1319 @@
+//! Type checking: Hindley–Milner inference over the atlas surface AST, run
+//! before lowering so an ill-typed program is rejected, pointing at the
+//! offending expression, instead of reducing to a stuck term.
+//!
+//! Every expression gets a type; a `let`, a fn and a `rec` group generalize
+//! theirs over whatever their values leave open, so `id x = x` can be used at
+//! `Int` and at `Bool` alike, while the binders of a fn's arguments, a closure
+//! and a `match` arm stay monomorphic within their scope.
+//!
+//! Annotations are optional. `(x: Int)` names a builtin scalar, a declared enum
+//! or struct (at fresh type arguments), or a trait, which only stands for
+//! whatever type is passed with its dictionary; a lowercase name is a type
+//! variable, the same one throughout its declaration. Enums and structs are
+//! generic over the lowercase names among their field types, in order of first
+//! appearance: `enum Opt { None, Some(a) }` is `Opt a`.
+//!
+//! Names that nothing in scope gives a type to (a core binding, say) are open:
+//! each use is a fresh type variable, as are type values (an enum's own name)
+//! and indexing. A projection `.f` of a value whose type is not known yet is
+//! settled once it is, by the end of the declaration: until then neither type
+//! is generalized, so `let f t = t.0 in f (1, 2)` gives `f` the one type
+//! `(Int, Int) -> Int`. A trait method takes and returns anything of its arity,
+//! since which impl it runs is settled by lowering's dispatch, from the type
+//! checking records for its receiver.
+//!
+//! Besides accepting or rejecting, checking records the type of each projected
+//! value and each argument, as an [`Inferred`], for lowering to find the arity
+//! of a tuple and the impl of a method by.
+
+use std::collections::HashMap;
+
+use atlas_core::error::{TypeError, TypeErrorKind};
+
+use crate::ast;
+use crate::ast::ExprSpans;
+use crate::lower::{
+    bare, enclosing, is_type_param, qualify, untyped, variant_name, Globals, Inferred,
+    InferredType, SCALAR_TYPES,
+};
+
+mod ty;
+
+use ty::{DataCtor, Type, Unifier};
+pub use ty::{DataType, Scheme};
+
+// ========================================================================
+// Public entry points
+// ========================================================================
+
+/// The types of the top-level items checked so far, for later ones to use.
+/// Like the [`Globals`] the lowering uses, it is keyed by qualified name.
+#[derive(Debug, Clone, Default)]
+pub struct Types {
+    /// value name -> its type scheme (a fn's, a `let`'s, a trait method's)
+    pub values: HashMap<String, Scheme>,
+    /// enum or struct name -> its declaration
+    pub data: HashMap<String, DataType>,
+}
+
+/// What a checked top-level declaration adds to [`Types`], by the names it
+/// declares them under; [`qualify`] them with its module, as for a
+/// [`crate::lower::LoweredDecl`]. `inferred` is for lowering the declaration.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CheckedDecl {
+    pub values: Vec<(String, Scheme)>,
+    pub data: Vec<(String, DataType)>,
+    pub inferred: Inferred,
+}
+
+/// Check an expression, as it appears in `module` (`""` at the top level),
+/// returning what lowering it needs to know. Errors point into the source
+/// `spans` index.
+pub fn check_expr<'a>(
+    e: &'a ast::Expr<'a>,
+    globals: &'a Globals,
+    types: &'a Types,
+    module: &'a str,
+    spans: &'a ExprSpans<'a>,
+) -> Result<Inferred, TypeError> {
+    let mut c = Check::new(globals, types, module, spans, 0);
+    c.expr(e)?;
+    c.finish()
+}
+
+/// Check a top-level declaration of `module`. A `mod` checks nothing itself:
+/// its items are checked one by one, in its path.
+pub fn check_decl<'a>(
+    d: &'a ast::Declaration<'a>,
+    globals: &'a Globals,
+    types: &'a Types,
+    module: &'a str,
+    spans: &'a ExprSpans<'a>,
+) -> Result<CheckedDecl, TypeError> {
+    // The declaration's own value is checked one `let` level in.
+    let mut c = Check::new(globals, types, module, spans, 1);
+    let mut checked = CheckedDecl::default();
+    match d {
+        ast::Declaration::Let(l) => {
+            let scheme = c.let_value(l)?;
+            if let ast::Pattern::Identifier(name) = untyped(&l.pattern) {
+                checked.values.push((name.to_string(), scheme));
+            }
+        }
+        ast::Declaration::Fn(f) => checked.values.push((f.name.to_string(), c.fn_decl(f)?)),
+        ast::Declaration::Rec(group) => {
+            let schemes = c.rec_group(group)?;
+            checked.values = schemes
+                .into_iter()
+                .map(|(f, s)| (f.to_string(), s))
+                .collect();
+        }
+        ast::Declaration::Enum(en) => {
+            let data = c.enum_type(en, &qualify(module, en.name))?;
+            checked.data.push((en.name.to_string(), data));
+        }
+        ast::Declaration::Struct(st) => {
+            let data = c.struct_type(st, &qualify(module, st.name))?;
+            checked.data.push((st.name.to_string(), data));
+        }
+        ast::Declaration::Trait(t) => {
+            checked.values = c.trait_methods(t)?;
+        }
+        ast::Declaration::Impl(i) => c.impl_methods(i)?,
+        ast::Declaration::Mod(_) | ast::Declaration::Alias(_) => {}
+    }
+    checked.inferred = c.finish()?;
+    Ok(checked)
+}
+
+// ========================================================================
+// The checking context
+// ========================================================================
+
+struct Check<'a> {
+    u: Unifier,
+    /// the values bound in scope, innermost last
+    env: Vec<(&'a str, Scheme)>,
+    /// the enums and structs declared in enclosing blocks, innermost last
+    data: Vec<(&'a str, DataType)>,
+    /// variant name -> the block-declared enum it constructs, innermost last
+    ctors: Vec<(&'a str, &'a str)>,
+    /// the type variables annotations in the current declaration name
+    tyvars: HashMap<&'a str, Type>,
+    globals: &'a Globals,
+    types: &'a Types,
+    /// the path of the module being checked (`""` at the top level)
+    module: &'a str,
+    spans: &'a ExprSpans<'a>,
+    /// the `let` level of the item being checked, where the types of a
+    /// pending projection stay until the item is done
+    item: usize,
+    /// projections `e.f` of values of no known type yet: `e`, `f`, its type
+    /// and the projection's
+    pending: Vec<(&'a ast::Expr<'a>, &'a str, Type, Type)>,
+    /// the projected values and the arguments of calls, with their types
+    seen: Vec<(&'a ast::Expr<'a>, Type)>,
+}
+
+/// A constructor, with the type it constructs and that type's declaration.
+struct Ctor<'c> {
+    ty: String,
+    data: &'c DataType,
+    ctor: &'c DataCtor,
+}
+
+/// How far the scopes of a [`Check`] reach, to go back to.
+struct Mark(usize, usize, usize);
+
+impl<'a> Check<'a> {
+    fn new(
+        globals: &'a Globals,
+        types: &'a Types,
+        module: &'a str,
+        spans: &'a ExprSpans<'a>,
+        item: usize,
+    ) -> Self {
+        Check {
+            u: Unifier::default(),
+            env: Vec::new(),
+            data: Vec::new(),
+            ctors: Vec::new(),
+            tyvars: HashMap::new(),
+            globals,
+            types,
+            module,
+            spans,
+            item,
+            pending: Vec::new(),
+            seen: Vec::new(),
+        }
+    }
+
+    /// Settle what projections are pending, as far as the types of their
+    /// values are known by now: before a `let` generalizes, and when the item
+    /// is done.
+    fn settle(&mut self) -> Result<(), TypeError> {
+        loop {
+            let pending = std::mem::take(&mut self.pending);
+            let before = pending.len();
+            for (e, field, t, result) in pending {
+                if let Type::Var(_) = self.u.head(&t) {
+                    self.pending.push((e, field, t, result));
+                    continue;
+                }
+                let found = self.field(&t, field).map_err(|err| self.at(err, e))?;
+                self.expect(&result, &found, None)
+                    .map_err(|err| self.at(err, e))?;
+            }
+            if self.pending.is_empty() || self.pending.len() == before {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Settle the pending projections and record what lowering needs.
+    fn finish(&mut self) -> Result<Inferred, TypeError> {
+        self.settle()?;
+        let mut inferred = Inferred::default();
+        for (e, t) in &self.seen {
+            match self.u.head(t) {
+                Type::Tuple(ts) => inferred.insert(e, InferredType::Tuple(ts.len())),
+                Type::Con(name, _) => inferred.insert(e, InferredType::Named(name)),
+                _ => {}
+            }
+        }
+        Ok(inferred)
+    }
+
+    fn mark(&self) -> Mark {
+        Mark(self.env.len(), self.data.len(), self.ctors.len())
+    }
+
+    fn reset(&mut self, Mark(env, data, ctors): Mark) {
+        self.env.truncate(env);
+        self.data.truncate(data);
+        self.ctors.truncate(ctors);
+    }
+
+    /// Require `found` (the type of `at`, if given) to be `expected`.
+    fn expect(
+        &mut self,
+        expected: &Type,
+        found: &Type,
+        at: Option<&'a ast::Expr<'a>>,
+    ) -> Result<(), TypeError> {
+        self.u.unify(expected, found).map_err(|kind| {
+            let e = TypeError::new(kind);
+            match at {
+                Some(at) => self.at(e, at),
+                None => e,
+            }
+        })
+    }
+
+    /// Point `err` at `e`, unless it points somewhere inside already.
+    fn at(&self, err: TypeError, e: &'a ast::Expr<'a>) -> TypeError {
+        match self.spans.get(e) {
+            Some(span) => err.or_span(span),
+            None => err,
+        }
+    }
+
+    // --- expressions ---
+
+    fn expr(&mut self, e: &'a ast::Expr<'a>) -> Result<Type, TypeError> {
+        self.infer(e).map_err(|err| self.at(err, e))
+    }
+
+    fn infer(&mut self, e: &'a ast::Expr<'a>) -> Result<Type, TypeError> {
+        Ok(match e {
+            ast::Expr::Literal(l) => lit_type(l),
+            ast::Expr::Identifier(name) => self.name(name),
+            ast::Expr::Scope(path) => self.global(&path.join("::")),
+            ast::Expr::Constructor(c) => self.constructor(c)?,
+            ast::Expr::Tuple(t) => {
+                let mut fields = Vec::with_capacity(t.fields.len());
+                for e in &t.fields {
+                    fields.push(self.expr(e)?);
+                }
+                Type::Tuple(fields)
+            }
+            ast::Expr::List(l) => {
+                let elem = self.u.fresh();
+                for e in &l.elems {
+                    let t = self.expr(e)?;
+                    self.expect(&elem, &t, Some(e))?;
+                }
+                Type::Con("List".to_string(), vec![elem])
+            }
+            ast::Expr::IfElse(b) => {
+                let cond = self.expr(&b.cond)?;
+                self.expect(&Type::con("Bool"), &cond, Some(&b.cond))?;
+                let then = self.expr(&b.if_expr)?;
+                let els = self.expr(&b.else_expr)?;
+                self.expect(&then, &els, Some(&b.else_expr))?;
+                then
+            }
+            ast::Expr::Match(m) => {
+                let scrut = self.expr(&m.scrut)?;
+                let result = self.u.fresh();
+                for arm in &m.arms {
+                    let mark = self.mark();
+                    let t = self
+                        .pattern(&arm.pattern, &scrut)
+                        .and_then(|()| self.expr(&arm.body));
+                    self.reset(mark);
+                    self.expect(&result, &t?, Some(&arm.body))?;
+                }
+                result
+            }
+            ast::Expr::Block(b) => self.block(b)?,
+            ast::Expr::Closure(c) => self.fn_type(&c.params, &c.body, None)?,
+            ast::Expr::Unary { op, expr } => {
+                let t = self.expr(expr)?;
+                if *op == ast::UnaryOp::Neg {
+                    self.numeric(&t, expr)?;
+                }
+                t
+            }
+            ast::Expr::Infix { lhs, op, rhs } => self.infix(lhs, *op, rhs)?,
+            ast::Expr::Call(f, args) => self.call(f, &args.iter().collect::<Vec<_>>())?,
+            // `x |> f a` is the call `f a x`, as lowering has it.
+            ast::Expr::Pipe(x, f) => match &**f {
+                ast::Expr::Call(f, args) => {
+                    let mut args: Vec<_> = args.iter().collect();
+                    args.push(x);
+                    self.call(f, &args)?
+                }
+                f => self.call(f, &[x])?,
+            },
+            ast::Expr::Project(e, field) => self.project(e, field)?,
+            ast::Expr::Index(a, i) => {
+                self.expr(a)?;
+                self.expr(i)?;
+                self.u.fresh()
+            }
+        })
+    }
+
+    /// The type of a use of `name`: a local, else an item of the innermost
+    /// enclosing module that has one, else open.
+    fn name(&mut self, name: &str) -> Type {
+        match self.env.iter().rev().find(|(n, _)| *n == name) {
+            Some((_, scheme)) => {
+                let scheme = scheme.clone();
+                self.u.instantiate(&scheme)
+            }
+            None => self.global(name),
+        }
+    }
+
+    /// The type of a use of the item `name` (bare, or a path).
+    fn global(&mut self, name: &str) -> Type {
+        match self.global_key(&self.types.values, name) {
+            Some(key) => {
+                let scheme = &self.types.values[key];
+                self.u.instantiate(scheme)
+            }
+            None => self.u.fresh(),
+        }
+    }
+
+    /// The key `known` holds the item `name` used here under, if any.
+    fn global_key<'m, V>(&self, known: &'m HashMap<String, V>, name: &str) -> Option<&'m str> {
+        enclosing(self.module)
+            .find_map(|m| known.get_key_value(&qualify(m, name)))
+            .map(|(k, _)| k.as_str())
+    }
+
+    /// Require `t` to be a number, as `-` does. An open `t` stays open.
+    fn numeric(&mut self, t: &Type, e: &'a ast::Expr<'a>) -> Result<(), TypeError> {
+        match self.u.head(t) {
+            Type::Con(name, _) if name == "Int" || name == "Float" => Ok(()),
+            Type::Var(_) => Ok(()),
+            _ => {
+                let [found] = self.u.show([t]);
+                Err(self.at(
+                    TypeError::new(TypeErrorKind::Mismatch {
+                        expected: "Int".to_string(),
+                        found,
+                    }),
+                    e,
+                ))
+            }
+        }
+    }
+
+    /// Arithmetic takes two operands of one type and gives that type back;
+    /// comparisons give a `Bool`, and `&&` and `||` take them too.
+    fn infix(
+        &mut self,
+        lhs: &'a ast::Expr<'a>,
+        op: ast::InfixOp,
+        rhs: &'a ast::Expr<'a>,
+    ) -> Result<Type, TypeError> {
+        use ast::InfixOp as I;
+        let l = self.expr(lhs)?;
+        let r = self.expr(rhs)?;
+        match op {
+            I::And | I::Or => {
+                let bool = Type::con("Bool");
+                self.expect(&bool, &l, Some(lhs))?;
+                self.expect(&bool, &r, Some(rhs))?;
+                Ok(bool)
+            }
+            I::Eq | I::Neq | I::Lt | I::Lte | I::Gt | I::Gte => {
+                self.expect(&l, &r, Some(rhs))?;
+                Ok(Type::con("Bool"))
+            }
+            I::Add | I::Sub | I::Mul | I::Div | I::Mod | I::Xor | I::Shl | I::Shr => {
+                self.expect(&l, &r, Some(rhs))?;
+                Ok(l)
+            }
+        }
+    }
+
+    /// `f a b ..`: each argument in turn must have the type `f` takes next.
+    fn call(
+        &mut self,
+        f: &'a ast::Expr<'a>,
+        args: &[&'a ast::Expr<'a>],
+    ) -> Result<Type, TypeError> {
+        let mut acc = self.expr(f)?;
+        for arg in args {
+            let x = self.expr(arg)?;
+            // A method, or a parameter bound by a trait, takes the impl for
+            // the argument's type.
+            self.seen.push((arg, x.clone()));
+            acc = self.apply(&acc, &x, arg)?;
+        }
+        Ok(acc)
+    }
+
+    /// The result of applying a function of type `f` to `arg`, of type `x`.
+    fn apply(&mut self, f: &Type, x: &Type, arg: &'a ast::Expr<'a>) -> Result<Type, TypeError> {
+        match self.u.head(f) {
+            Type::Fun(param, ret) => {
+                self.expect(&param, x, Some(arg))?;
+                Ok(*ret)
+            }
+            Type::Var(_) => {
+                let ret = self.u.fresh();
+                self.expect(f, &Type::fun(x.clone(), ret.clone()), None)?;
+                Ok(ret)
+            }
+            _ => {
+                let [found] = self.u.show([f]);
+                Err(TypeError::new(TypeErrorKind::NotAFunction { found }))
+            }
+        }
+    }
+
+    /// `e.0` of a tuple, `e.x` of a struct, or `e.m`, the call `m e` of a
+    /// method. Of a value of no known type yet, the projection is pending.
+    fn project(&mut self, e: &'a ast::Expr<'a>, field: &'a str) -> Result<Type, TypeError> {
+        let t = self.expr(e)?;
+        self.seen.push((e, t.clone()));
+        match self.u.head(&t) {
+            Type::Tuple(_) => self.field(&t, field),
+            Type::Con(..) => match self.field(&t, field) {
+                Err(_) if self.is_method(field) => self.apply_method(field, &t, e),
+                found => found,
+            },
+            _ if self.is_method(field) => self.apply_method(field, &t, e),
+            Type::Var(_) => {
+                let result = self.u.fresh();
+                self.u.pin(&t, self.item);
+                self.u.pin(&result, self.item);
+                self.pending.push((e, field, t, result.clone()));
+                Ok(result)
+            }
+            _ => Ok(self.u.fresh()),
+        }
+    }
+
+    /// The type of the field `field` of a tuple or struct of type `t`.
+    fn field(&mut self, t: &Type, field: &str) -> Result<Type, TypeError> {
+        let found = match self.u.head(t) {
+            Type::Tuple(ts) => field.parse::<usize>().ok().and_then(|i| ts.get(i)).cloned(),
+            Type::Con(name, args) => self.data_type(&name).and_then(|data| {
+                let ctor = data.ctors.first()?;
+                let i = ctor.names.iter().position(|n| n == field)?;
+                data.product.then(|| ctor.fields[i].subst(&args))
+            }),
+            _ => None,
+        };
+        found.ok_or_else(|| {
+            let [ty] = self.u.show([t]);
+            TypeError::new(TypeErrorKind::NoField {
+                ty,
+                field: field.to_string(),
+            })
+        })
+    }
+
+    /// Whether `name` is a trait method not shadowed by a local.
+    fn is_method(&self, name: &str) -> bool {
+        !self.env.iter().any(|(n, _)| *n == name)
+            && self.global_key(&self.globals.methods, name).is_some()
+    }
+
+    fn apply_method(
+        &mut self,
+        method: &str,
+        recv: &Type,
+        e: &'a ast::Expr<'a>,
+    ) -> Result<Type, TypeError> {
+        let f = self.global(method);
+        self.apply(&f, recv, e)
+    }
+
+    /// A constructor applied to some or all of its fields; the rest are still
+    /// to come, as with a call.
+    fn constructor(&mut self, c: &'a ast::Constructor<'a>) -> Result<Type, TypeError> {
+        let (name, args): (&str, &'a [ast::Expr<'a>]) = match c {
+            ast::Constructor::Empty(name) => (name, &[]),
+            ast::Constructor::Tuple(name, args) => (name, args.as_slice()),
+            ast::Constructor::Struct(name, given) => return self.struct_ctor(name, given),
+        };
+        let Some((result, fields)) = self.instantiate_ctor(name, args.len())? else {
+            // An enum's own name, or a constructor nothing in scope declares.
+            for arg in args {
+                self.expr(arg)?;
+            }
+            return Ok(self.u.fresh());
+        };
+        for (arg, field) in args.iter().zip(&fields) {
+            let x = self.expr(arg)?;
+            self.expect(field, &x, Some(arg))?;
+        }
+        Ok(fields[args.len()..]
+            .iter()
+            .rev()
+            .fold(result, |acc, field| Type::fun(field.clone(), acc)))
+    }
+
+    /// `Name { x: a, y: b }`: each field given must be one `Name` has, of its
+    /// type.
+    fn struct_ctor(
+        &mut self,
+        name: &str,
+        given: &'a [(&'a str, ast::Expr<'a>)],
+    ) -> Result<Type, TypeError> {
+        let Some(ctor) = self.ctor(name) else {
+            for (_, e) in given {
+                self.expr(e)?;
+            }
+            return Ok(self.u.fresh());
+        };
+        let names = ctor.ctor.names.clone();
+        let (result, fields) = self
+            .instantiate_ctor(name, 0)?
+            .expect("a constructor found once is found again");
+        for (field, e) in given {
+            let x = self.expr(e)?;
+            match names.iter().position(|n| n == field) {
+                Some(i) => self.expect(&fields[i], &x, Some(e))?,
+                None => {
+                    let [ty] = self.u.show([&result]);
+                    return Err(TypeError::new(TypeErrorKind::NoField {
+                        ty,
+                        field: field.to_string(),
+                    }));
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// The type the constructor `name` makes, and its fields', at fresh type
+    /// arguments; `given` fields are too many for it, when it has fewer.
+    fn instantiate_ctor(
+        &mut self,
+        name: &str,
+        given: usize,
+    ) -> Result<Option<(Type, Vec<Type>)>, TypeError> {
+        let Some(Ctor { ty, data, ctor }) = self.ctor(name) else {
+            return Ok(None);
+        };
+        if given > ctor.fields.len() {
+            return Err(TypeError::new(TypeErrorKind::Arity {
+                ctor: name.to_string(),
+                expected: ctor.fields.len(),
+                found: given,
+            }));
+        }
+        let (params, fields) = (data.params, ctor.fields.clone());
+        let args: Vec<_> = (0..params).map(|_| self.u.fresh()).collect();
+        let fields = fields.iter().map(|t| t.subst(&args)).collect();
+        Ok(Some((Type::Con(ty, args), fields)))
+    }
+
+    /// The constructor `name` (a variant, or a struct's own): declared in an
+    /// enclosing block, or an item.
+    fn ctor(&self, name: &str) -> Option<Ctor<'_>> {
+        let local = |ty: &str| {
+            let (_, data) = self.data.iter().rev().find(|(n, _)| *n == ty)?;
+            Some((ty.to_string(), data))
+        };
+        let (ty, data) = match self.ctors.iter().rev().find(|(v, _)| *v == name) {
+            Some((_, ty)) => local(ty)?,
+            None => match local(name) {
+                Some(found) => found,
+                None => match self.global_key(&self.globals.ctors, name) {
+                    Some(key) => {
+                        let ty = &self.globals.ctors[key];
+                        (ty.clone(), self.types.data.get(ty)?)
+                    }
+                    None => {
+                        let key = self.global_key(&self.types.data, name)?;
+                        (key.to_string(), &self.types.data[key])
+                    }
+                },
+            },
+        };
+        let ctor = data.ctor(bare(name))?;
+        Some(Ctor { ty, data, ctor })
+    }
+
+    /// The declaration of the enum or struct that `Con(name, ..)` names.
+    fn data_type(&self, name: &str) -> Option<&DataType> {
+        match self.data.iter().rev().find(|(n, _)| *n == name) {
+            Some((_, data)) => Some(data),
+            None => self.types.data.get(name),
+        }
+    }
+
+    // --- patterns ---
+
+    /// Check `p` matches values of type `ty`, binding what it names.
+    fn pattern(&mut self, p: &'a ast::Pattern<'a>, ty: &Type) -> Result<(), TypeError> {
+        match p {
+            ast::Pattern::Identifier(name) => self.env.push((name, Scheme::mono(ty.clone()))),
+            ast::Pattern::Wildcard => {}
+            ast::Pattern::Literal(l) => self.expect(ty, &lit_type(l), None)?,
+            ast::Pattern::Typed(p, ast::Type::Identifier(name)) => {
+                let t = self.annotation(name)?;
+                self.expect(&t, ty, None)?;
+                self.pattern(p, ty)?;
+            }
+            ast::Pattern::Tuple(ps) => {
+                let fields: Vec<_> = ps.iter().map(|_| self.u.fresh()).collect();
+                self.expect(&Type::Tuple(fields.clone()), ty, None)?;
+                for (p, t) in ps.iter().zip(&fields) {
+                    self.pattern(p, t)?;
+                }
+            }
+            ast::Pattern::Constructor(name, ps) => {
+                let Some((result, fields)) = self.instantiate_ctor(name, ps.len())? else {
+                    // A bare name nothing declares matches whatever carries it.
+                    for p in ps {
+                        let t = self.u.fresh();
+                        self.pattern(p, &t)?;
+                    }
+                    return Ok(());
+                };
+                if ps.len() != fields.len() {
+                    return Err(TypeError::new(TypeErrorKind::Arity {
+                        ctor: name.to_string(),
+                        expected: fields.len(),
+                        found: ps.len(),
+                    }));
+                }
+                self.expect(&result, ty, None)?;
+                for (p, t) in ps.iter().zip(&fields) {
+                    self.pattern(p, t)?;
+                }
+            }
+            ast::Pattern::Struct(name, given) => {
+                let Some(names) = self.ctor(name).map(|c| c.ctor.names.clone()) else {
+                    for (_, p) in given {
+                        let t = self.u.fresh();
+                        self.pattern(p, &t)?;
+                    }
+                    return Ok(());
+                };
+                let (result, fields) = self
+                    .instantiate_ctor(name, 0)?
+                    .expect("a constructor found once is found again");
+                self.expect(&result, ty, None)?;
+                for (field, p) in given {
+                    let Some(i) = names.iter().position(|n| n == field) else {
+                        let [ty] = self.u.show([&result]);
+                        return Err(TypeError::new(TypeErrorKind::NoField {
+                            ty,
+                            field: field.to_string(),
+                        }));
+                    };
+                    self.pattern(p, &fields[i])?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The type an annotation `(x: name)` names.
+    fn annotation(&mut self, name: &'a str) -> Result<Type, TypeError> {
+        if SCALAR_TYPES.contains(&name) {
+            return Ok(Type::con(name));
+        }
+        if is_type_param(name) {
+            return Ok(match self.tyvars.get(name) {
+                Some(t) => t.clone(),
+                None => {
+                    let t = self.u.fresh();
+                    self.tyvars.insert(name, t.clone());
+                    t
+                }
+            });
+        }
+        if self.global_key(&self.globals.traits, name).is_some() {
+            return Ok(self.u.fresh());
+        }
+        let key = match self.data.iter().rev().find(|(n, _)| *n == name) {
+            Some(_) => Some(name),
+            None => self.global_key(&self.types.data, name),
+        };
+        match key.and_then(|k| Some((k, self.data_type(k)?.params))) {
+            Some((key, params)) => {
+                let key = key.to_string();
+                let args = (0..params).map(|_| self.u.fresh()).collect();
+                Ok(Type::Con(key, args))
+            }
+            None => Err(TypeError::new(TypeErrorKind::UnknownType(name.to_string()))),
+        }
+    }
+
+    // --- blocks and declarations ---
+
+    fn block(&mut self, b: &'a ast::ExprBlock<'a>) -> Result<Type, TypeError> {
+        let mark = self.mark();
+        let t = self.block_in_scope(b);
+        self.reset(mark);
+        t
+    }
+
+    fn block_in_scope(&mut self, b: &'a ast::ExprBlock<'a>) -> Result<Type, TypeError> {
+        for d in &b.decls {
+            self.local_decl(d)?;
+        }
+        match &b.value {
+            Some(e) => self.expr(e),
+            // Not a program lowering accepts, but no type error either.
+            None => Ok(self.u.fresh()),
+        }
+    }
+
+    /// Check a declaration of a block, binding what it declares for the rest
+    /// of the block.
+    fn local_decl(&mut self, d: &'a ast::Declaration<'a>) -> Result<(), TypeError> {
+        match d {
+            ast::Declaration::Let(l) => {
+                let scheme = self.let_value(l)?;
+                match untyped(&l.pattern) {
+                    ast::Pattern::Identifier(name) => self.env.push((name, scheme)),
+                    ast::Pattern::Wildcard => {}
+                    p => {
+                        let t = self.u.instantiate(&scheme);
+                        self.pattern(p, &t)?;
+                    }
+                }
+            }
+            ast::Declaration::Fn(f) => {
+                let scheme = self.fn_decl(f)?;
+                self.env.push((f.name, scheme));
+            }
+            ast::Declaration::Rec(group) => {
+                let schemes = self.rec_group(group)?;
+                self.env.extend(schemes);
+            }
+            ast::Declaration::Enum(en) => {
+                let data = self.enum_type(en, en.name)?;
+                self.data.push((en.name, data));
+                for v in &en.variants {
+                    self.ctors.push((variant_name(v), en.name));
+                }
+            }
+            ast::Declaration::Struct(st) => {
+                let data = self.struct_type(st, st.name)?;
+                self.data.push((st.name, data));
+            }
+            // Lowering rejects these in a block.
+            ast::Declaration::Mod(_)
+            | ast::Declaration::Alias(_)
+            | ast::Declaration::Trait(_)
+            | ast::Declaration::Impl(_) => {}
+        }
+        Ok(())
+    }
+
+    /// The scheme of a `let`'s value, checked against its annotation if it has
+    /// one.
+    fn let_value(&mut self, l: &'a ast::LetDecl<'a>) -> Result<Scheme, TypeError> {
+        self.u.enter();
+        let t = self.expr(&l.value).and_then(|t| {
+            if let ast::Pattern::Typed(_, ast::Type::Identifier(name)) = &l.pattern {
+                let annotated = self.annotation(name)?;
+                self.expect(&annotated, &t, Some(&l.value))?;
+            }
+            Ok(t)
+        });
+        self.u.leave();
+        let t = t?;
+        self.settle()?;
+        Ok(self.u.generalize(&t))
+    }
+
+    /// The scheme of a fn, which sees itself (monomorphically) in its body.
+    fn fn_decl(&mut self, f: &'a ast::FnDecl<'a>) -> Result<Scheme, TypeError> {
+        let tyvars = std::mem::take(&mut self.tyvars);
+        self.u.enter();
+        let me = self.u.fresh();
+        let mark = self.mark();
+        self.env.push((f.name, Scheme::mono(me.clone())));
+        let t = self.fn_type(&f.args, &f.body, None);
+        self.reset(mark);
+        let t = t.and_then(|t| self.expect(&me, &t, None).map(|()| t));
+        self.u.leave();
+        self.tyvars = tyvars;
+        let t = t?;
+        self.settle()?;
+        Ok(self.u.generalize(&t))
+    }
+
+    /// The schemes of a `rec` group's functions, which see each other (and
+    /// themselves) monomorphically in their bodies.
+    fn rec_group(
+        &mut self,
+        group: &'a ast::RecDecl<'a>,
+    ) -> Result<Vec<(&'a str, Scheme)>, TypeError> {
+        self.u.enter();
+        let tys: Vec<_> = group.fns.iter().map(|_| self.u.fresh()).collect();
+        let mark = self.mark();
+        for (f, t) in group.fns.iter().zip(&tys) {
+            self.env.push((f.name, Scheme::mono(t.clone())));
+        }
+        let mut checked = Ok(());
+        for (f, t) in group.fns.iter().zip(&tys) {
+            let tyvars = std::mem::take(&mut self.tyvars);
+            checked = self
+                .fn_type(&f.args, &f.body, None)
+                .and_then(|ft| self.expect(t, &ft, None));
+            self.tyvars = tyvars;
+            if checked.is_err() {
+                break;
+            }
+        }
+        self.reset(mark);
+        self.u.leave();
+        checked?;
+        self.settle()?;
+        Ok(group
+            .fns
+            .iter()
+            .zip(&tys)
+            .map(|(f, t)| (f.name, self.u.generalize(t)))
+            .collect())
+    }
+
+    /// The type of a fn taking `args` and returning `body`. A method's first
+    /// argument, its receiver, has the type `receiver` where that is known.
+    fn fn_type(
+        &mut self,
+        args: &'a [ast::Pattern<'a>],
+        body: &'a ast::ExprBlock<'a>,
+        receiver: Option<Type>,
+    ) -> Result<Type, TypeError> {
+        let mark = self.mark();
+        let t = self.fn_type_in_scope(args, body, receiver);
+        self.reset(mark);
+        t
+    }
+
+    fn fn_type_in_scope(
+        &mut self,
+        args: &'a [ast::Pattern<'a>],
+        body: &'a ast::ExprBlock<'a>,
+        mut receiver: Option<Type>,
+    ) -> Result<Type, TypeError> {
+        let mut params = Vec::with_capacity(args.len());
+        for p in args {
+            let t = receiver.take().unwrap_or_else(|| self.u.fresh());
+            self.pattern(p, &t)?;
+            params.push(t);
+        }
+        let ret = self.block(body)?;
+        Ok(params
+            .into_iter()
+            .rev()
+            .fold(ret, |acc, p| Type::fun(p, acc)))
+    }
+
+    /// An enum's declaration, as the type named `ty`.
+    fn enum_type(&mut self, en: &'a ast::EnumDecl<'a>, ty: &str) -> Result<DataType, TypeError> {
+        let fields = |v: &'a ast::EnumVariant<'a>| -> Vec<&'a ast::Type<'a>> {
+            match v {
+                ast::EnumVariant::Empty(_) => Vec::new(),
+                ast::EnumVariant::Tuple(_, tys) => tys.iter().collect(),
+                ast::EnumVariant::Struct(_, entries) => entries.iter().map(|(_, t)| t).collect(),
+            }
+        };
+        let all: Vec<_> = en.variants.iter().flat_map(fields).collect();
+        let mut params = DataParams::new(en.name, ty, &all);
+        let mut ctors = Vec::with_capacity(en.variants.len());
+        for v in &en.variants {
+            let mut tys = Vec::new();
+            for t in fields(v) {
+                tys.push(params.field(self, t)?);
+            }
+            let names = match v {
+                ast::EnumVariant::Struct(_, entries) => {
+                    entries.iter().map(|(n, _)| n.to_string()).collect()
+                }
+                _ => Vec::new(),
+            };
+            ctors.push(DataCtor {
+                name: variant_name(v).to_string(),
+                fields: tys,
+                names,
+            });
+        }
+        Ok(DataType {
+            params: params.count,
+            ctors,
+            product: false,
+        })
+    }
+
+    /// A struct's declaration, as the type named `ty`.
+    fn struct_type(
+        &mut self,
+        st: &'a ast::StructDecl<'a>,
+        ty: &str,
+    ) -> Result<DataType, TypeError> {
+        let all: Vec<_> = st.entries.iter().map(|(_, t)| t).collect();
+        let mut params = DataParams::new(st.name, ty, &all);
+        let mut fields = Vec::with_capacity(all.len());
+        for t in all {
+            fields.push(params.field(self, t)?);
+        }
+        Ok(DataType {
+            params: params.count,
+            ctors: vec![DataCtor {
+                name: st.name.to_string(),
+                fields,
+                names: st.entries.iter().map(|(n, _)| n.to_string()).collect(),
+            }],
+            product: true,
+        })
+    }
+
+    /// A trait's methods, each taking anything of its arity. Its default
+    /// methods are checked against them.
+    fn trait_methods(
+        &mut self,
+        t: &'a ast::TraitDecl<'a>,
+    ) -> Result<Vec<(String, Scheme)>, TypeError> {
+        let methods: Vec<_> = t
+            .methods
+            .iter()
+            .map(|m| (m.name, method_scheme(m.args.len())))
+            .collect();
+        let mark = self.mark();
+        self.env.extend(methods.iter().cloned());
+        let mut checked = Ok(());
+        for m in &t.methods {
+            if let Some(body) = &m.default {
+                checked = self.method(&m.args, body, None);
+                if checked.is_err() {
+                    break;
+                }
+            }
+        }
+        self.reset(mark);
+        checked?;
+        Ok(methods
+            .into_iter()
+            .map(|(m, s)| (m.to_string(), s))
+            .collect())
+    }
+
+    /// Check the methods of an impl, whose receivers have the impl's type.
+    fn impl_methods(&mut self, i: &'a ast::ImplDecl<'a>) -> Result<(), TypeError> {
+        let ast::Type::Identifier(ty) = &i.ty;
+        for f in &i.methods {
+            let recv = self.annotation(ty)?;
+            self.method(&f.args, &f.body, Some(recv))?;
+        }
+        Ok(())
+    }
+
+    fn method(
+        &mut self,
+        args: &'a [ast::Pattern<'a>],
+        body: &'a ast::ExprBlock<'a>,
+        receiver: Option<Type>,
+    ) -> Result<(), TypeError> {
+        let tyvars = std::mem::take(&mut self.tyvars);
+        let t = self.fn_type(args, body, receiver);
+        self.tyvars = tyvars;
+        t.map(drop)
+    }
+}
+
+/// The type parameters of an enum or struct being declared: the lowercase
+/// names among its field types, in order of first appearance, then one for
+/// each parameter of a generic type its fields name, which it passes on.
+struct DataParams<'a> {
+    /// the declaration's own name, which its fields may use for itself
+    name: &'a str,
+    /// the name of the type it declares
+    ty: String,
+    named: Vec<&'a str>,
+    count: usize,
+}
+
+impl<'a> DataParams<'a> {
+    fn new(name: &'a str, ty: &str, fields: &[&'a ast::Type<'a>]) -> Self {
+        let mut named = Vec::new();
+        for ast::Type::Identifier(n) in fields {
+            if is_type_param(n) && !named.contains(n) {
+                named.push(*n);
+            }
+        }
+        DataParams {
+            name,
+            ty: ty.to_string(),
+            count: named.len(),
+            named,
+        }
+    }
+
+    fn field(&mut self, c: &mut Check<'a>, t: &'a ast::Type<'a>) -> Result<Type, TypeError> {
+        let ast::Type::Identifier(name) = t;
+        if let Some(i) = self.named.iter().position(|n| n == name) {
+            return Ok(Type::Gen(i));
+        }
+        if *name == self.name {
+            let own = (0..self.named.len()).map(Type::Gen).collect();
+            return Ok(Type::Con(self.ty.clone(), own));
+        }
+        match c.annotation(name)? {
+            Type::Con(key, args) => {
+                let start = self.count;
+                self.count += args.len();
+                Ok(Type::Con(key, (start..self.count).map(Type::Gen).collect()))
+            }
+            // A trait: whatever implements it.
+            _ => {
+                self.count += 1;
+                Ok(Type::Gen(self.count - 1))
+            }
+        }
+    }
+}
+
+// ========================================================================
+// Helpers
+// ========================================================================
+
+fn lit_type(l: &ast::Literal) -> Type {
+    match l {
+        ast::Literal::Integer(_) => Type::con("Int"),
+        ast::Literal::Float(_) => Type::con("Float"),
+        ast::Literal::Bool(_) => Type::con("Bool"),
+        ast::Literal::String(_) => Type::con("String"),
+        ast::Literal::Unit => Type::Tuple(Vec::new()),
+    }
+}
+
+/// `a1 -> .. -> an -> r`, for a method of `arity` arguments.
+fn method_scheme(arity: usize) -> Scheme {
+    let ty = (0..arity)
+        .rev()
+        .fold(Type::Gen(arity), |acc, i| Type::fun(Type::Gen(i), acc));
+    Scheme {
+        vars: arity + 1,
+        ty,
+    }
+}
+
+// ========================================================================
+// Tests
+// ========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_module_spanned;
+
+    /// Check the top-level declarations of `src` in order, each seeing the
+    /// ones before it, as a session binds them.
+    fn check(src: &str) -> Result<Types, TypeError> {
+        let (m, log) = parse_module_spanned(src).unwrap();
+        let spans = ExprSpans::of_module(&m, log);
+        let mut globals = Globals::default();
+        let mut types = Types::default();
+        for d in &m.decls {
+            let checked = check_decl(d, &globals, &types, "", &spans)?;
+            match d {
+                ast::Declaration::Enum(en) => {
+                    for v in &en.variants {
+                        let name = variant_name(v).to_string();
+                        globals.ctors.insert(name, en.name.to_string());
+                    }
+                }
+                ast::Declaration::Trait(t) => {
+                    let methods = t.methods.iter().map(|m| (m.name.to_string(), false));
+                    globals.traits.insert(t.name.to_string(), methods.collect());
+                    for m in &t.methods {
+                        globals
+                            .methods
+                            .insert(m.name.to_string(), t.name.to_string());
+                    }
+                }
+                _ => {}
+            }
+            types.values.extend(checked.values);
+            types.data.extend(checked.data);
+        }
+        Ok(types)
+    }
+
+    /// The scheme `src` gives `name`.
+    fn scheme(src: &str, name: &str) -> String {
+        match check(src) {
+            Ok(types) => types.values[name].to_string(),
+            Err(e) => panic!("{e}"),
+        }
+    }
+
+    /// The error checking `src` fails with, and the source it points at.
+    fn error(src: &str) -> (TypeErrorKind, &str) {
+        let e = check(src).expect_err("ill-typed");
+        let at = e.span.map_or("", |span| &src[span]);
+        (e.kind, at)
+    }
+
+    fn mismatch(expected: &str, found: &str) -> TypeErrorKind {
+        TypeErrorKind::Mismatch {
+            expected: expected.to_string(),
+            found: found.to_string(),
+        }
+    }
+
+    #[test]
+    fn literals_and_operators() {
+        assert_eq!(scheme("let x = 1 + 2 * 3", "x"), "Int");
+        assert_eq!(scheme("let b = 1 < 2 && !false", "b"), "Bool");
+        assert_eq!(
+            scheme("let s = if true { \"a\" } else { \"b\" }", "s"),
+            "String"
+        );
+        assert_eq!(scheme("let l = [1.5, 2.0]", "l"), "List Float");
+        assert_eq!(error("let x = 1 + true"), (mismatch("Int", "Bool"), "true"));
+        assert_eq!(
+            error("let x = if 1 { 2 } else { 3 }"),
+            (mismatch("Bool", "Int"), "1")
+        );
+        assert_eq!(
+            error("let x = if true { 2 } else { \"3\" }"),
+            (mismatch("Int", "String"), "\"3\"")
+        );
+        assert_eq!(error("let n = -true"), (mismatch("Int", "Bool"), "true"));
+    }
+
+    #[test]
+    fn projections_settle_once_the_value_is_known() {
+        let src = "g = {\nf t = t.0\nf (1, true)\n}";
+        assert_eq!(scheme(src, "g"), "Int");
+        let src = "struct P { x: Int }\ng = {\nf p = p.x\nf (P { x: 1 })\n}";
+        assert_eq!(scheme(src, "g"), "Int");
+        // Not generalized meanwhile, `f` takes the one type of tuple.
+        let (kind, at) = error("g = {\nf t = t.0\n(f (1, 2), f (true, 3))\n}");
+        assert_eq!(
+            (kind, at),
+            (mismatch("(Int, Int)", "(Bool, Int)"), "(true, 3)")
+        );
+        let (kind, at) = error("g = {\nf t = t.0\nf 3\n}");
+        let no_field = TypeErrorKind::NoField {
+            ty: "Int".to_string(),
+            field: "0".to_string(),
+        };
+        assert_eq!((kind, at), (no_field, "t"));
+        // A value of no known type at all is left to lowering.
+        assert_eq!(scheme("f t = t.0", "f"), "a -> b");
+    }
+
+    #[test]
+    fn lets_and_fns_are_polymorphic() {
+        let src = "id x = x\nlet both = (id 1, id true)\nconst x y = x";
+        assert_eq!(scheme(src, "id"), "a -> a");
+        assert_eq!(scheme(src, "both"), "(Int, Bool)");
+        assert_eq!(scheme(src, "const"), "a -> b -> a");
+        // Within its own body, a fn is not generic yet.
+        assert_eq!(
+            scheme("count n = if n == 0 { 0 } else { count (n - 1) }", "count"),
+            "Int -> Int"
+        );
+        // Nor is an argument.
+        let (kind, at) = error("twice f = (f 1, f true)");
+        assert_eq!((kind, at), (mismatch("Int", "Bool"), "true"));
+    }
+
+    #[test]
+    fn calls_point_at_the_argument() {
+        let src = "add a b = a + b * 2\nlet y = add 1 \"s\"";
+        assert_eq!(error(src), (mismatch("Int", "String"), "\"s\""));
+        let (kind, at) = error("let z = 1 2");
+        assert_eq!(
+            kind,
+            TypeErrorKind::NotAFunction {
+                found: "Int".to_string()
+            }
+        );
+        assert_eq!(at, "1 2");
+        let (kind, _) = error("selfapp f = f f");
+        assert!(matches!(kind, TypeErrorKind::Infinite { .. }), "{kind:?}");
+    }
+
+    #[test]
+    fn closures_pipelines_and_blocks() {
+        assert_eq!(scheme("let k = 1 |> |x| x == 2", "k"), "Bool");
+        assert_eq!(scheme("add a b = a + b\nlet k = 1 |> add 2", "k"), "Int");
+        assert_eq!(scheme("let inc = |x| x + 1", "inc"), "Int -> Int");
+        let src = "let v = {\nlet (a, b) = (1, true)\nsq x = x * x\nif b { sq a } else { 0 }\n}";
+        assert_eq!(scheme(src, "v"), "Int");
+        // The closure is checked before the value piped into it.
+        let src = "let w = 1 |> |x| {\nlet y = x\ny && true\n}";
+        assert_eq!(error(src), (mismatch("Bool", "Int"), "1"));
+    }
+
+    #[test]
+    fn annotations_constrain_and_name_variables() {
+        assert_eq!(scheme("f (x: Int) y = x", "f"), "Int -> a -> Int");
+        assert_eq!(
+            error("f (x: Int) = x\nlet b = f true"),
+            (mismatch("Int", "Bool"), "true")
+        );
+        // `a` is one variable throughout the declaration.
+        assert_eq!(
+            scheme("pair (x: a) (y: a) = (x, y)", "pair"),
+            "a -> a -> (a, a)"
+        );
+        assert_eq!(
+            error("pair (x: a) (y: a) = (x, y)\nlet p = pair 1 true"),
+            (mismatch("Int", "Bool"), "true")
+        );
+        let (kind, _) = error("g (x: Nope) = x");
+        assert_eq!(kind, TypeErrorKind::UnknownType("Nope".to_string()));
+    }
+
+    #[test]
+    fn data_types_are_generic_over_their_lowercase_fields() {
+        let src = "enum Opt { None, Some(a) }\n\
+                   struct Pair { fst: a, snd: b }\n\
+                   get d o = match o { Some(x) => x, None => d }\n\
+                   let p = Pair { fst: Some(1), snd: get 0 None }\n\
+                   let n = p.snd + 1\n\
+                   let wrap = Some";
+        assert_eq!(scheme(src, "get"), "a -> Opt a -> a");
+        assert_eq!(scheme(src, "p"), "Pair (Opt Int) Int");
+        assert_eq!(scheme(src, "n"), "Int");
+        assert_eq!(scheme(src, "wrap"), "a -> Opt a");
+
+        let src = "enum List { Nil, Cons(a, List) }\nlet l = Cons(1, Cons(2, Nil))";
+        assert_eq!(scheme(src, "l"), "List Int");
+        assert_eq!(
+            error("enum List { Nil, Cons(a, List) }\nlet l = Cons(1, Cons(true, Nil))"),
+            (mismatch("List Int", "List Bool"), "Cons(true, Nil)")
+        );
+
+        let src = "struct P { x: Int, y: Int }\nlet q = P { x: 1, y: 2 }\nlet r = q.z";
+        let (kind, _) = error(src);
+        assert_eq!(
+            kind,
+            TypeErrorKind::NoField {
+                ty: "P".to_string(),
+                field: "z".to_string()
+            }
+        );
+        let (kind, _) = error("enum E { A(Int) }\nf e = match e { A(x, y) => x }");
+        assert_eq!(
+            kind,
+            TypeErrorKind::Arity {
+                ctor: "A".to_string(),
+                expected: 1,
+                found: 2
+            }
+        );
+        let src = "enum C { Red, Green }\nis_red c = match c { Red => true, _ => false }\nlet t = is_red 1";
+        assert_eq!(error(src), (mismatch("C", "Int"), "1"));
+    }
+
+    #[test]
+    fn rec_groups_and_traits() {
+        let src = "rec {\n\
+                   even n = if n == 0 { true } else { odd (n - 1) }\n\
+                   odd n = if n == 0 { false } else { even (n - 1) }\n\
+                   }";
+        assert_eq!(scheme(src, "even"), "Int -> Bool");
+        assert_eq!(scheme(src, "odd"), "Int -> Bool");
+
+        let src = "trait Eq {\neq a b\nne a b = !(eq a b)\n}\n\
+                   impl Eq for Int {\neq a b = a == b\n}\n\
+                   let t = eq 1 2 && ne 1 2";
+        assert_eq!(scheme(src, "eq"), "a -> b -> c");
+        assert_eq!(scheme(src, "t"), "Bool");
+        // An impl's receiver has the impl's type.
+        let src = "trait Show {\nshow a\n}\nimpl Show for Int {\nshow a = a && true\n}";
+        assert_eq!(error(src), (mismatch("Bool", "Int"), "a"));
+    }
+
+    #[test]
+    fn unknown_names_are_open() {
+        assert_eq!(scheme("let u = mystery 1 + 2", "u"), "Int");
+        assert_eq!(scheme("let v = mystery", "v"), "a");
+    }
+}
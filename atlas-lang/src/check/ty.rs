@@ -0,0 +1,386 @@
+//! Types, type schemes, and the unifier that solves for type variables.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use atlas_core::error::TypeErrorKind;
+
+/// A type, possibly with variables in it.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Type {
+    /// A variable of the [`Unifier`], solved or not.
+    Var(usize),
+    /// The `i`th quantified variable of a [`Scheme`], or the `i`th parameter
+    /// of a [`DataType`].
+    Gen(usize),
+    /// A named type applied to its parameters: `Int`, `List a`, `Opt Int`.
+    Con(String, Vec<Type>),
+    Fun(Box<Type>, Box<Type>),
+    /// A tuple; the empty one is the unit type `()`.
+    Tuple(Vec<Type>),
+}
+
+impl Type {
+    pub(crate) fn con(name: &str) -> Self {
+        Type::Con(name.to_string(), Vec::new())
+    }
+
+    pub(crate) fn fun(arg: Type, ret: Type) -> Self {
+        Type::Fun(Box::new(arg), Box::new(ret))
+    }
+
+    /// The type with each `Gen(i)` replaced by `args[i]`.
+    pub(crate) fn subst(&self, args: &[Type]) -> Type {
+        match self {
+            Type::Gen(i) => args[*i].clone(),
+            Type::Var(_) => self.clone(),
+            Type::Con(name, ts) => {
+                Type::Con(name.clone(), ts.iter().map(|t| t.subst(args)).collect())
+            }
+            Type::Fun(a, r) => Type::fun(a.subst(args), r.subst(args)),
+            Type::Tuple(ts) => Type::Tuple(ts.iter().map(|t| t.subst(args)).collect()),
+        }
+    }
+}
+
+/// A type generalized over its `vars` quantified variables, `Gen(0)` to
+/// `Gen(vars - 1)`: `id x = x` has the scheme `a -> a`, and each use of `id`
+/// gets a fresh `a`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scheme {
+    pub(crate) vars: usize,
+    pub(crate) ty: Type,
+}
+
+impl Scheme {
+    /// A type with nothing quantified, as a lambda-bound name has.
+    pub(crate) fn mono(ty: Type) -> Self {
+        Scheme { vars: 0, ty }
+    }
+}
+
+/// Quantified variables print as letters, in order of appearance.
+impl fmt::Display for Scheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Names::default().show(&self.ty))
+    }
+}
+
+/// A declared enum or struct, whose field types refer to its parameters by
+/// `Gen` index.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataType {
+    pub(crate) params: usize,
+    pub(crate) ctors: Vec<DataCtor>,
+    /// Whether this is a struct, whose one constructor is named after it.
+    pub(crate) product: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DataCtor {
+    pub(crate) name: String,
+    pub(crate) fields: Vec<Type>,
+    /// The fields' names, for a struct or struct-like variant; empty otherwise.
+    pub(crate) names: Vec<String>,
+}
+
+impl DataType {
+    pub(crate) fn ctor(&self, name: &str) -> Option<&DataCtor> {
+        self.ctors.iter().find(|c| c.name == name)
+    }
+}
+
+/// Why two types don't unify.
+enum Clash {
+    /// Different constructors somewhere inside them.
+    Mismatch,
+    /// Solving this variable would make it contain itself.
+    Occurs(usize),
+}
+
+/// The type variables made while checking, each with its solution (once
+/// unification finds one) and the level of `let` nesting it was made at. A
+/// variable still unsolved when its `let` is done, and made inside it, is
+/// local to the `let`'s value and can be generalized.
+#[derive(Default)]
+pub(crate) struct Unifier {
+    vars: Vec<(Option<Type>, usize)>,
+    level: usize,
+}
+
+impl Unifier {
+    pub(crate) fn fresh(&mut self) -> Type {
+        self.vars.push((None, self.level));
+        Type::Var(self.vars.len() - 1)
+    }
+
+    /// Enter the value of a `let` (or a fn declaration).
+    pub(crate) fn enter(&mut self) {
+        self.level += 1;
+    }
+
+    pub(crate) fn leave(&mut self) {
+        self.level -= 1;
+    }
+
+    /// Keep the unsolved variables of `t` from being generalized by any `let`
+    /// nested deeper than `level`.
+    pub(crate) fn pin(&mut self, t: &Type, level: usize) {
+        match self.head(t) {
+            Type::Var(v) => {
+                let l = &mut self.vars[v].1;
+                *l = (*l).min(level);
+            }
+            Type::Con(_, ts) | Type::Tuple(ts) => ts.iter().for_each(|t| self.pin(t, level)),
+            Type::Fun(a, r) => {
+                self.pin(&a, level);
+                self.pin(&r, level);
+            }
+            Type::Gen(_) => {}
+        }
+    }
+
+    /// `t`, with solved variables at its head followed through.
+    pub(crate) fn head(&self, t: &Type) -> Type {
+        let mut t = t;
+        while let Type::Var(v) = t {
+            match &self.vars[*v].0 {
+                Some(solved) => t = solved,
+                None => break,
+            }
+        }
+        t.clone()
+    }
+
+    /// `t` with every solved variable replaced by its solution.
+    pub(crate) fn resolve(&self, t: &Type) -> Type {
+        match self.head(t) {
+            Type::Con(name, ts) => Type::Con(name, ts.iter().map(|t| self.resolve(t)).collect()),
+            Type::Fun(a, r) => Type::fun(self.resolve(&a), self.resolve(&r)),
+            Type::Tuple(ts) => Type::Tuple(ts.iter().map(|t| self.resolve(t)).collect()),
+            t => t,
+        }
+    }
+
+    /// Make `found` equal `expected`, or say why it can't be, in terms of the
+    /// two whole types.
+    pub(crate) fn unify(&mut self, expected: &Type, found: &Type) -> Result<(), TypeErrorKind> {
+        match self.unify_parts(expected, found) {
+            Ok(()) => Ok(()),
+            Err(Clash::Mismatch) => {
+                let [expected, found] = self.show([expected, found]);
+                Err(TypeErrorKind::Mismatch { expected, found })
+            }
+            Err(Clash::Occurs(v)) => {
+                let [var, ty] = self.show([&Type::Var(v), found]);
+                Err(TypeErrorKind::Infinite { var, ty })
+            }
+        }
+    }
+
+    fn unify_parts(&mut self, a: &Type, b: &Type) -> Result<(), Clash> {
+        match (self.head(a), self.head(b)) {
+            (Type::Var(x), Type::Var(y)) if x == y => Ok(()),
+            (Type::Var(v), t) | (t, Type::Var(v)) => self.solve(v, t),
+            (Type::Con(x, xs), Type::Con(y, ys)) if x == y && xs.len() == ys.len() => xs
+                .iter()
+                .zip(&ys)
+                .try_for_each(|(x, y)| self.unify_parts(x, y)),
+            (Type::Fun(a1, r1), Type::Fun(a2, r2)) => {
+                self.unify_parts(&a1, &a2)?;
+                self.unify_parts(&r1, &r2)
+            }
+            (Type::Tuple(xs), Type::Tuple(ys)) if xs.len() == ys.len() => xs
+                .iter()
+                .zip(&ys)
+                .try_for_each(|(x, y)| self.unify_parts(x, y)),
+            _ => Err(Clash::Mismatch),
+        }
+    }
+
+    /// Solve `v` as `t`. The variables of `t` sink to `v`'s level, since they
+    /// are now as widely shared as `v` is.
+    fn solve(&mut self, v: usize, t: Type) -> Result<(), Clash> {
+        let level = self.vars[v].1;
+        self.occurs(v, level, &t)?;
+        self.vars[v].0 = Some(t);
+        Ok(())
+    }
+
+    fn occurs(&mut self, v: usize, level: usize, t: &Type) -> Result<(), Clash> {
+        match self.head(t) {
+            Type::Var(w) if w == v => Err(Clash::Occurs(v)),
+            Type::Var(w) => {
+                let l = &mut self.vars[w].1;
+                *l = (*l).min(level);
+                Ok(())
+            }
+            Type::Con(_, ts) | Type::Tuple(ts) => {
+                ts.iter().try_for_each(|t| self.occurs(v, level, t))
+            }
+            Type::Fun(a, r) => {
+                self.occurs(v, level, &a)?;
+                self.occurs(v, level, &r)
+            }
+            Type::Gen(_) => Ok(()),
+        }
+    }
+
+    /// The scheme of a `let`-bound value of type `t`, quantified over the
+    /// variables made (and left unsolved) inside the `let`.
+    pub(crate) fn generalize(&self, t: &Type) -> Scheme {
+        let mut gens = HashMap::new();
+        let ty = self.generalize_in(t, &mut gens);
+        Scheme {
+            vars: gens.len(),
+            ty,
+        }
+    }
+
+    fn generalize_in(&self, t: &Type, gens: &mut HashMap<usize, usize>) -> Type {
+        match self.head(t) {
+            Type::Var(v) if self.vars[v].1 > self.level => {
+                let next = gens.len();
+                Type::Gen(*gens.entry(v).or_insert(next))
+            }
+            Type::Con(name, ts) => Type::Con(
+                name,
+                ts.iter().map(|t| self.generalize_in(t, gens)).collect(),
+            ),
+            Type::Fun(a, r) => {
+                Type::fun(self.generalize_in(&a, gens), self.generalize_in(&r, gens))
+            }
+            Type::Tuple(ts) => {
+                Type::Tuple(ts.iter().map(|t| self.generalize_in(t, gens)).collect())
+            }
+            t => t,
+        }
+    }
+
+    /// A use of a value of scheme `s`: its type, with fresh variables for the
+    /// quantified ones.
+    pub(crate) fn instantiate(&mut self, s: &Scheme) -> Type {
+        let args: Vec<_> = (0..s.vars).map(|_| self.fresh()).collect();
+        s.ty.subst(&args)
+    }
+
+    /// Render types for an error, naming their unsolved variables alike.
+    pub(crate) fn show<const N: usize>(&self, ts: [&Type; N]) -> [String; N] {
+        let mut names = Names::default();
+        ts.map(|t| names.show(&self.resolve(t)))
+    }
+}
+
+/// Letters for type variables, handed out in order of appearance.
+#[derive(Default)]
+struct Names {
+    vars: HashMap<(bool, usize), String>,
+}
+
+impl Names {
+    fn name(&mut self, key: (bool, usize)) -> String {
+        let next = self.vars.len();
+        self.vars
+            .entry(key)
+            .or_insert_with(|| {
+                let letter = (b'a' + (next % 26) as u8) as char;
+                match next / 26 {
+                    0 => letter.to_string(),
+                    n => format!("{letter}{n}"),
+                }
+            })
+            .clone()
+    }
+
+    /// `t`, with `Fun` associating to the right and applied types taking
+    /// parenthesized arguments.
+    fn show(&mut self, t: &Type) -> String {
+        match t {
+            Type::Var(v) => self.name((false, *v)),
+            Type::Gen(i) => self.name((true, *i)),
+            Type::Con(name, ts) => {
+                let mut s = name.clone();
+                for t in ts {
+                    let arg = self.show(t);
+                    match t {
+                        Type::Fun(..) => s.push_str(&format!(" ({arg})")),
+                        Type::Con(_, args) if !args.is_empty() => s.push_str(&format!(" ({arg})")),
+                        _ => s.push_str(&format!(" {arg}")),
+                    }
+                }
+                s
+            }
+            Type::Fun(a, r) => {
+                let arg = self.show(a);
+                let ret = self.show(r);
+                match **a {
+                    Type::Fun(..) => format!("({arg}) -> {ret}"),
+                    _ => format!("{arg} -> {ret}"),
+                }
+            }
+            Type::Tuple(ts) => {
+                let fields: Vec<_> = ts.iter().map(|t| self.show(t)).collect();
+                format!("({})", fields.join(", "))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unification_solves_and_generalizes() {
+        let mut u = Unifier::default();
+        u.enter();
+        let a = u.fresh();
+        let b = u.fresh();
+        let f = Type::fun(a.clone(), b.clone());
+        u.unify(&f, &Type::fun(Type::con("Int"), a.clone()))
+            .unwrap();
+        u.leave();
+        assert_eq!(u.resolve(&f), Type::fun(Type::con("Int"), Type::con("Int")));
+        let err = u.unify(&b, &Type::con("Bool")).unwrap_err();
+        assert_eq!(
+            err,
+            TypeErrorKind::Mismatch {
+                expected: "Int".to_string(),
+                found: "Bool".to_string()
+            }
+        );
+
+        u.enter();
+        let c = u.fresh();
+        let id = Type::fun(c.clone(), c);
+        u.leave();
+        let s = u.generalize(&id);
+        assert_eq!(s.to_string(), "a -> a");
+        let (x, y) = (u.instantiate(&s), u.instantiate(&s));
+        assert_ne!(x, y, "each use gets its own variables");
+    }
+
+    #[test]
+    fn a_variable_cannot_contain_itself() {
+        let mut u = Unifier::default();
+        let a = u.fresh();
+        let err = u
+            .unify(&a, &Type::fun(a.clone(), Type::con("Int")))
+            .unwrap_err();
+        assert!(matches!(err, TypeErrorKind::Infinite { .. }), "{err:?}");
+    }
+
+    #[test]
+    fn types_print_like_annotations() {
+        let mut u = Unifier::default();
+        let a = u.fresh();
+        let list = Type::Con("List".to_string(), vec![Type::con("Int")]);
+        let opt = Type::Con("Opt".to_string(), vec![list.clone()]);
+        let t = Type::fun(
+            Type::fun(a.clone(), a),
+            Type::Tuple(vec![opt, Type::Tuple(Vec::new())]),
+        );
+        let [shown] = u.show([&t]);
+        assert_eq!(shown, "(a -> a) -> (Opt (List Int), ())");
+    }
+}
@@ -10,7 +10,10 @@ pub enum Token<'src> {
     // Two alternatives so a lone `_` is the Underscore token, not an identifier.
     #[regex(r"([a-z][a-zA-Z0-9_]*)|([a-z_][a-zA-Z0-9_]+)")]
     Identifier(&'src str),
-    #[regex(r"[A-Z][a-zA-Z0-9_]*")]
+    // A type/ctor name may be module-qualified (`geo::Shape`) in one token, so
+    // everything that takes one takes a path too. Value paths (`geo::area`)
+    // stay separate tokens and are assembled by the parser.
+    #[regex(r"([a-z][a-zA-Z0-9_]*::)*[A-Z][a-zA-Z0-9_]*")]
     TypeIdentifier(&'src str),
 
     // Literals.
@@ -19,6 +22,10 @@ pub enum Token<'src> {
     Float(NotNan<f64>),
     #[regex(r"[0-9]+", |lex| lex.slice().parse::<u64>().map_err(|_| ()))]
     Integer(u64),
+    // A tuple field projection: `.0` in `t.0`. One token, so `t.0.1` is not
+    // read as `t` followed by the float `0.1`.
+    #[regex(r"\.[0-9]+", |lex| &lex.slice()[1..])]
+    FieldIndex(&'src str),
     #[regex(r#"("[^"]*")|('[^']*')"#, |lex| { let s = lex.slice(); &s[1..s.len()-1] })]
     String(&'src str),
     // Keyword tokens beat the identifier regex on equal length (literal > regex).
@@ -39,6 +46,7 @@ pub enum Token<'src> {
     #[token("type")]  Type,
     #[token("pub")]   Pub,
     #[token("in")]    In,
+    #[token("for")]   For,
     #[token("rec")]   Rec,
 
     // Delimiters / punctuation
@@ -57,6 +65,7 @@ pub enum Token<'src> {
     #[token("<=")] Lte,     #[token("<<")] Shl, #[token("<")] Lt,
     #[token(">=")] Gte,     #[token(">>")] Shr, #[token(">")] Gt,
     #[token("&&")] AndAnd,  #[token("||")] OrOr,
+    #[token("|>")] PipeGt,  #[token("|")] Pipe,
     #[token("+")] Plus,     #[token("-")] Minus,
     #[token("*")] Star,     #[token("/")] Slash,
     #[token("%")] Percent,  #[token("^")] Caret,
@@ -139,6 +148,7 @@ impl<'src> std::fmt::Display for Token<'src> {
             Identifier(s) | TypeIdentifier(s) | String(s) => write!(f, "{s}"),
             Integer(i) => write!(f, "{i}"),
             Float(x) => write!(f, "{x}"),
+            FieldIndex(i) => write!(f, ".{i}"),
             True => write!(f, "true"),
             False => write!(f, "false"),
             Let => write!(f, "let"),
@@ -154,6 +164,7 @@ impl<'src> std::fmt::Display for Token<'src> {
             Type => write!(f, "type"),
             Pub => write!(f, "pub"),
             In => write!(f, "in"),
+            For => write!(f, "for"),
             Rec => write!(f, "rec"),
             LBrace => write!(f, "{{"),
             RBrace => write!(f, "}}"),
@@ -181,6 +192,8 @@ impl<'src> std::fmt::Display for Token<'src> {
             Gt => write!(f, ">"),
             AndAnd => write!(f, "&&"),
             OrOr => write!(f, "||"),
+            PipeGt => write!(f, "|>"),
+            Pipe => write!(f, "|"),
             Plus => write!(f, "+"),
             Minus => write!(f, "-"),
             Star => write!(f, "*"),
@@ -208,4 +221,57 @@ mod test {
         assert_eq!(Token::Minus, lex.next().unwrap().unwrap());
         assert_eq!(Token::Integer(2), lex.next().unwrap().unwrap());
     }
+
+    #[test]
+    fn field_indices_are_not_floats() {
+        let tokens: Vec<_> = Token::lexer("t.0.12 1.5").map(|t| t.unwrap()).collect();
+        assert_eq!(
+            tokens,
+            [
+                Token::Identifier("t"),
+                Token::FieldIndex("0"),
+                Token::FieldIndex("12"),
+                Token::Float(1.5.try_into().unwrap()),
+            ]
+        );
+    }
+
+    #[test]
+    fn qualified_type_names_are_one_token() {
+        let tokens: Vec<_> = Token::lexer("geo::shapes::Circle geo::area a::B::c")
+            .map(|t| t.unwrap())
+            .collect();
+        assert_eq!(
+            tokens,
+            [
+                Token::TypeIdentifier("geo::shapes::Circle"),
+                Token::Identifier("geo"),
+                Token::ColonColon,
+                Token::Identifier("area"),
+                Token::TypeIdentifier("a::B"),
+                Token::ColonColon,
+                Token::Identifier("c"),
+            ]
+        );
+    }
+
+    #[test]
+    fn pipes_lex_apart() {
+        let tokens: Vec<_> = Token::lexer("|x| a || b |> f")
+            .map(|t| t.unwrap())
+            .collect();
+        assert_eq!(
+            tokens,
+            [
+                Token::Pipe,
+                Token::Identifier("x"),
+                Token::Pipe,
+                Token::Identifier("a"),
+                Token::OrOr,
+                Token::Identifier("b"),
+                Token::PipeGt,
+                Token::Identifier("f"),
+            ]
+        );
+    }
 }
@@ -0,0 +1,3424 @@
+//! Lowering: the atlas surface AST -> the desugared core IR
+//! ([`atlas_core::core::expr::Expr`]).
+//!
+//! Unlike the core surface language, atlas binders carry no affine / auto-dup /
+//! erased annotation. The classification is inferred here from the number of
+//! uses of each binder in its scope:
+//!
+//! - 0 uses: an erasing binder ([`Expr::Use`] for lambdas; `let` values are
+//!   dropped entirely),
+//! - 1 use: a plain linear binder ([`Expr::Lam`] + [`Expr::Var`]; `let` values
+//!   are inlined at their one use site),
+//! - `N >= 2` uses: a binary dup chain of `N-1` [`Expr::Dup`] levels, with use
+//!   sites lowered to [`Expr::Dp0`] / [`Expr::Dp1`] projections.
+//!
+//! A `let` is not recursive, but a `fn foo(..) { .. }` binds `foo` within its
+//! own body. When the body actually uses `foo`, the declaration lowers to a
+//! closed Y-combinator applied to `\foo -> \args.. -> body`; when it does not,
+//! it lowers to a plain lambda chain. Functions that call each other go in a
+//! `rec { even n = .. \n odd n = .. }` group, whose value is the fixpoint of
+//! `\self -> (even, odd)`: each use of a member, in the group or after it, is
+//! a projection of one use of the tuple, so the tuple's binder is classified
+//! by the members' uses all together. A member with no arguments is a
+//! recursive value, such as `ones = Cons(1, ones)`.
+//!
+//! A closure `|x, y| e` is the lambda chain of a fn with those arguments, and
+//! whatever else `e` names is captured simply by being in scope: each mention
+//! is one use of the outer binder, however often the closure is called. Calls
+//! are curried, so `add 1` is a function of the remaining argument, and the
+//! pipeline `xs |> map f` is the call `map f xs`.
+//!
+//! Tuples are anonymous products: `(a, b)` is the `New` constructor of a fresh
+//! `type (type (), type ())`, and a tuple pattern (in a `match` arm, a block's
+//! `let`, or a fn argument) is a match on the product's `New` case. A
+//! projection `t.0` is such a match too, so it needs `t`'s arity while
+//! lowering: it is read off tuple literals and the names `let`-bound to them,
+//! and otherwise taken from the type checking inferred for `t` (an
+//! [`Inferred`]), as for the argument `t` of `let f t = t.0 in f (1, 2)`.
+//!
+//! Structs are products too, with their fields in declared order: `P { y: b,
+//! x: a }` is `P::New a b` for `struct P { x: Int, y: Int }`, and `p.x` is a
+//! projection like `p.0`. Struct-like enum variants order their fields the same
+//! way. The builtin scalar types (`Int`, `Bool`, ..) have no type values of
+//! their own yet and lower to core's `type ()` placeholder, as do the type
+//! parameters of a generic struct or enum (`a` in `struct Box { v: a }`) and
+//! an enum's own name in its variants (`L` in `enum L { Nil, Cons(Int, L) }`).
+//!
+//! Patterns nest, wherever they appear: `match` arms, a block's `let`, and fn
+//! arguments all go through the pattern-match compiler in `pattern`, which
+//! builds a decision tree of `Mat`s. A match that misses some values, or has
+//! an arm it never reaches, still lowers, with a [`PatternWarning`].
+//!
+//! Modules only exist at the top level, where the caller binds each item under
+//! its qualified name (`geo::area`) and records it in [`Globals`]. Within a
+//! module, a name not bound locally is looked up in that module, then in each
+//! enclosing one, then at the top level. A path `geo::area` starts from the
+//! innermost module that declares `geo`. Each further step must be `pub`
+//! unless the use site is inside the module declaring it.
+//!
+//! Traits lower to explicit dictionary passing. `trait Eq { eq a b; ne a b =
+//! !(eq a b) }` is a product type with one field per method (the dictionary
+//! type, whose fields go by the method names), and its default methods are
+//! globals of their own (`Eq.ne`). `impl Eq for Color { .. }` binds the global
+//! `impl Eq for Color` to the dictionary: `Eq::New` applied to each method, or
+//! to the trait's default for those it leaves out. A method's first argument is
+//! its receiver, which it takes as a `(dictionary, value)` pair, so the calls
+//! in its body can dispatch on the receiver in turn; so does an argument bound
+//! by a trait, as in `all_eq (x: Eq) y = ..`, of a generic fn. Dispatch is
+//! static: a call `eq c d` pairs `c` with the impl for its type, unless `c` is
+//! itself bound by `Eq`. The type is read off `c`'s form where that tells (a
+//! literal, a constructor, a binder annotated `(c: Color)`, ..), and otherwise
+//! taken from the type checking inferred for it, as for `eq (inc 1) 2`.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use atlas_core::core::expr::{DeBruijn, Expr, Pat, TypeDefKind, Value};
+use atlas_core::error::{DesugarError, DesugarErrorKind};
+use atlas_core::vm::term as vm;
+use ordered_float::OrderedFloat;
+
+use crate::ast;
+
+mod pattern;
+
+pub use pattern::PatternWarning;
+use pattern::Scrutinee;
+
+// ========================================================================
+// Public entry points
+// ========================================================================
+
+/// What the top-level declarations bound so far leave for later ones to
+/// resolve against, seeded by the caller (e.g. a REPL session). Items declared
+/// in a module go by their qualified name (`geo::Circle`), in keys and values
+/// alike.
+#[derive(Debug, Clone, Default)]
+pub struct Globals {
+    /// variant name -> the (free) name of the enum binding it constructs
+    pub ctors: HashMap<String, String>,
+    /// struct or struct-like variant name -> its field names, in declared order
+    pub fields: HashMap<String, Vec<String>>,
+    /// module path -> the names declared in it, each with whether it is `pub`
+    pub modules: HashMap<String, HashMap<String, bool>>,
+    /// trait name -> its methods in declared order, each with whether it has a
+    /// default
+    pub traits: HashMap<String, Vec<(String, bool)>>,
+    /// method name -> the trait declaring it
+    pub methods: HashMap<String, String>,
+    /// generic fn name -> the trait bounding each argument, if any
+    pub bounds: HashMap<String, Vec<Option<String>>>,
+}
+
+/// What type checking found out about the expressions of one top-level
+/// declaration or REPL expression, for lowering to fall back on where an
+/// expression's form does not tell (see [`crate::check`]): the type of each
+/// projected value and each argument of a call, where that is a tuple or a
+/// named type. Expressions are told apart by address, so the table is only
+/// good for the syntax tree that was checked.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Inferred {
+    types: HashMap<*const (), InferredType>,
+}
+
+/// The type of an expression, as far as [`Inferred`] records it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InferredType {
+    /// a tuple of this arity
+    Tuple(usize),
+    /// a builtin scalar, enum or struct, by its global name (or its bare name,
+    /// for one declared in a block)
+    Named(String),
+}
+
+impl Inferred {
+    pub(crate) fn insert(&mut self, e: &ast::Expr, ty: InferredType) {
+        self.types.insert(e as *const ast::Expr as *const (), ty);
+    }
+
+    /// The recorded type of `e`, if any.
+    pub fn get(&self, e: &ast::Expr) -> Option<&InferredType> {
+        self.types.get(&(e as *const ast::Expr as *const ()))
+    }
+}
+
+/// `module` and the modules around it, innermost first, ending with the top
+/// level (`""`): where a bare name used in `module` is looked up.
+pub(crate) fn enclosing(module: &str) -> impl Iterator<Item = &str> {
+    std::iter::successors(Some(module), |m| {
+        (!m.is_empty()).then(|| m.rfind("::").map_or("", |i| &m[..i]))
+    })
+}
+
+/// The full name of the item `name` declared in `module` (`""` for the top
+/// level).
+pub fn qualify(module: &str, name: &str) -> String {
+    if module.is_empty() {
+        name.to_string()
+    } else {
+        format!("{module}::{name}")
+    }
+}
+
+/// The global an `impl trait_name for ty` binds its dictionary to.
+pub fn impl_name(trait_name: &str, ty: &str) -> String {
+    format!("impl {trait_name} for {ty}")
+}
+
+/// Lower a closed expression: unbound names are an error. Pattern warnings are
+/// dropped.
+pub fn lower_expr(e: &ast::Expr) -> Result<Expr, DesugarError> {
+    let globals = Globals::default();
+    let inferred = Inferred::default();
+    Lower::new(false, &globals, &inferred, "").expr(e)
+}
+
+/// Lower an open expression, as it appears in `module` (`""` at the top
+/// level): unbound names lower to [`Expr::Free`] of the global they resolve to
+/// (bound later, e.g. against a REPL's locals). What checking `e` `inferred`
+/// settles the shapes its form leaves open. Warnings about its pattern matches
+/// are appended to `warnings`.
+pub fn lower_expr_open<'a>(
+    e: &'a ast::Expr<'a>,
+    globals: &'a Globals,
+    inferred: &'a Inferred,
+    module: &'a str,
+    warnings: &mut Vec<PatternWarning>,
+) -> Result<Expr, DesugarError> {
+    let mut l = Lower::new(true, globals, inferred, module);
+    let lowered = l.expr(e)?;
+    warnings.append(&mut l.warnings);
+    Ok(lowered)
+}
+
+/// A lowered top-level declaration (a REPL line or one item of a `.at` module):
+/// the name to bind, its open-lowered value, and — for enum declarations — the
+/// variant names that should resolve to this binding from now on. `fields` lists
+/// the constructors with named fields it declares (a struct, or struct-like
+/// variants), each with its field names in declared order. A trait lists its
+/// `methods` (as in [`Globals::traits`]) and binds its defaults `also`, as
+/// `Trait.method`; a `rec` group binds the tuple of its functions (named
+/// `rec f g`), and `also` each function as a field of it. A generic fn lists
+/// the trait `bounds` of its arguments
+/// (empty when it has none), by their global names. All other names are as
+/// declared; [`qualify`] them with the module they were declared in — except
+/// an impl's, which is already the global [`impl_name`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoweredDecl {
+    pub name: String,
+    pub expr: Expr,
+    pub variants: Vec<String>,
+    pub fields: Vec<(String, Vec<String>)>,
+    pub methods: Vec<(String, bool)>,
+    pub bounds: Vec<Option<String>>,
+    pub also: Vec<(String, Expr)>,
+}
+
+impl LoweredDecl {
+    fn new(name: &str, expr: Expr) -> Self {
+        LoweredDecl {
+            name: name.to_string(),
+            expr,
+            variants: Vec::new(),
+            fields: Vec::new(),
+            methods: Vec::new(),
+            bounds: Vec::new(),
+            also: Vec::new(),
+        }
+    }
+}
+
+/// Lower a top-level declaration (open, like [`lower_expr_open`]). Returns
+/// `Ok(None)` for a `let _ = ..;` (the value is dropped). Top-level
+/// redefinitions are ordinary shadowing, so no shadow checks apply here — the
+/// caller just overwrites its binding and ctor maps. A `mod` has no value of
+/// its own: the caller lowers its items, each in the module's path.
+pub fn lower_decl_open<'a>(
+    d: &'a ast::Declaration<'a>,
+    globals: &'a Globals,
+    inferred: &'a Inferred,
+    module: &'a str,
+    warnings: &mut Vec<PatternWarning>,
+) -> Result<Option<LoweredDecl>, DesugarError> {
+    let mut l = Lower::new(true, globals, inferred, module);
+    let lowered = match d {
+        ast::Declaration::Let(decl) => match untyped(&decl.pattern) {
+            ast::Pattern::Identifier(name) => LoweredDecl::new(name, l.expr(&decl.value)?),
+            ast::Pattern::Wildcard => return Ok(None),
+            _ => {
+                return Err(DesugarError::unsupported(
+                    "a top-level `let` binds one name; destructure values inside a block",
+                ))
+            }
+        },
+        ast::Declaration::Fn(f) => {
+            let bounds = l.fn_bounds(&f.args)?;
+            LoweredDecl {
+                bounds: if bounds.iter().any(Option::is_some) {
+                    bounds.iter().map(|b| b.map(str::to_string)).collect()
+                } else {
+                    Vec::new()
+                },
+                ..LoweredDecl::new(f.name, l.fn_value(f)?)
+            }
+        }
+        ast::Declaration::Enum(en) => LoweredDecl {
+            variants: en
+                .variants
+                .iter()
+                .map(|v| variant_name(v).to_string())
+                .collect(),
+            fields: en
+                .variants
+                .iter()
+                .filter_map(|v| match v {
+                    ast::EnumVariant::Struct(name, entries) => {
+                        Some((name.to_string(), owned(field_names(entries))))
+                    }
+                    _ => None,
+                })
+                .collect(),
+            ..LoweredDecl::new(en.name, l.enum_value(en)?)
+        },
+        ast::Declaration::Struct(st) => LoweredDecl {
+            fields: vec![(st.name.to_string(), owned(field_names(&st.entries)))],
+            ..LoweredDecl::new(st.name, l.struct_value(st)?)
+        },
+        ast::Declaration::Rec(group) => {
+            for f in &group.fns {
+                if l.fn_shape(f)?.is_some() {
+                    return Err(DesugarError::unsupported(format!(
+                        "`{}` is in a top-level `rec` group, whose functions can't have trait-bound arguments yet",
+                        f.name
+                    )));
+                }
+            }
+            let name = format!(
+                "rec {}",
+                group
+                    .fns
+                    .iter()
+                    .map(|f| f.name)
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            );
+            let tuple = qualify(module, &name);
+            let arity = group.fns.len();
+            LoweredDecl {
+                also: group
+                    .fns
+                    .iter()
+                    .enumerate()
+                    .map(|(i, f)| {
+                        let field = field_branch(i, arity);
+                        (
+                            f.name.to_string(),
+                            product_match(Expr::Free(tuple.clone()), field),
+                        )
+                    })
+                    .collect(),
+                ..LoweredDecl::new(&name, l.rec_value(group)?)
+            }
+        }
+        ast::Declaration::Trait(t) => {
+            let (expr, also) = l.trait_value(t)?;
+            LoweredDecl {
+                fields: vec![(
+                    t.name.to_string(),
+                    t.methods.iter().map(|m| m.name.to_string()).collect(),
+                )],
+                methods: t
+                    .methods
+                    .iter()
+                    .map(|m| (m.name.to_string(), m.default.is_some()))
+                    .collect(),
+                also,
+                ..LoweredDecl::new(t.name, expr)
+            }
+        }
+        ast::Declaration::Impl(i) => {
+            let (name, expr) = l.impl_value(i)?;
+            LoweredDecl::new(&name, expr)
+        }
+        d => {
+            return Err(DesugarError::unsupported(format!(
+                "`{}` declarations are not yet supported in lowering",
+                decl_kind(d)
+            )))
+        }
+    };
+    warnings.append(&mut l.warnings);
+    Ok(Some(lowered))
+}
+
+// ========================================================================
+// The lowering context
+// ========================================================================
+
+/// What a bound name resolves to during lowering.
+#[derive(Clone)]
+enum Binding<'a> {
+    /// a `Lam` binder bound at this absolute depth (exactly one use)
+    Lam(usize),
+    /// a binder with `>= 2` uses, expanded into a binary dup chain
+    Cloned(Rc<RefCell<DupState>>),
+    /// a single-use `let`-like binding, re-lowered (inlined) at its use site
+    Inline(InlineVal<'a>),
+    /// a function of a `rec` group: this field (of so many) of the group's
+    /// tuple, which is bound as the inner binding
+    Field(Box<Binding<'a>>, usize, usize),
+}
+
+/// What a bound value is known to be: for projecting fields out of it, and
+/// for picking the impl of a trait method called on it.
+#[derive(Clone, Copy)]
+enum Shape<'a> {
+    /// a tuple of this arity
+    Tuple(usize),
+    /// a value of this struct
+    Struct(&'a str),
+    /// a value of this enum or scalar type
+    Of(&'a str),
+    /// a `(dictionary, value)` pair for this trait: a method's receiver, or an
+    /// argument the trait bounds. The receiver of an impl's method also knows
+    /// the type of its value.
+    Bound(&'a str, Option<&'a str>),
+    /// a fn with trait-bound arguments
+    Generic(&'a ast::FnDecl<'a>),
+}
+
+/// The (unlowered) value of an inlinable binding.
+#[derive(Clone, Copy)]
+enum InlineVal<'a> {
+    Expr(&'a ast::Expr<'a>),
+    Fn(&'a ast::FnDecl<'a>),
+    Enum(&'a ast::EnumDecl<'a>),
+    Struct(&'a ast::StructDecl<'a>),
+}
+
+struct DupState {
+    /// absolute depths of the `N-1` dup binders in the chain
+    dup_depths: Vec<usize>,
+    /// total number of uses
+    count: usize,
+    /// uses consumed so far
+    used: usize,
+}
+
+struct Lower<'a> {
+    /// binder depth: every `Lam`, `Use`, and `Dup` level counts
+    depth: usize,
+    /// bound value names (lowercase) and enum type names (uppercase)
+    env: HashMap<&'a str, Binding<'a>>,
+    /// variant name -> the enum binder (or free name) it constructs
+    ctors: HashMap<&'a str, &'a str>,
+    /// struct or struct-like variant name -> its field names, in declared order
+    fields: HashMap<&'a str, Vec<&'a str>>,
+    /// bound names known to hold a tuple, a struct, a value of some type, ..
+    shapes: HashMap<&'a str, Shape<'a>>,
+    /// trait name -> its methods, as in [`Globals::traits`]
+    traits: HashMap<&'a str, Vec<(&'a str, bool)>>,
+    /// method name -> the trait declaring it
+    methods: HashMap<&'a str, &'a str>,
+    /// generic fn name -> the trait bounding each argument, if any
+    bounds: HashMap<&'a str, Vec<Option<&'a str>>>,
+    /// the trait being declared, which its default methods know by its bare
+    /// name, or the enum, which its variants' fields do
+    declaring: Option<&'a str>,
+    /// the declared modules, as in [`Globals::modules`]
+    modules: &'a HashMap<String, HashMap<String, bool>>,
+    /// the path of the module being lowered (`""` at the top level)
+    module: &'a str,
+    /// when set, unbound names lower to [`Expr::Free`] rather than erroring
+    open: bool,
+    /// the types checking found, for what [`Self::shape`] can't tell
+    inferred: &'a Inferred,
+    /// collected while lowering pattern matches
+    warnings: Vec<PatternWarning>,
+}
+
+impl<'a> Lower<'a> {
+    fn new(open: bool, globals: &'a Globals, inferred: &'a Inferred, module: &'a str) -> Self {
+        Lower {
+            depth: 0,
+            env: HashMap::new(),
+            ctors: globals
+                .ctors
+                .iter()
+                .map(|(k, v)| (k.as_str(), v.as_str()))
+                .collect(),
+            fields: globals
+                .fields
+                .iter()
+                .map(|(k, v)| (k.as_str(), v.iter().map(String::as_str).collect()))
+                .collect(),
+            shapes: HashMap::new(),
+            traits: globals
+                .traits
+                .iter()
+                .map(|(k, v)| {
+                    (
+                        k.as_str(),
+                        v.iter().map(|(m, d)| (m.as_str(), *d)).collect(),
+                    )
+                })
+                .collect(),
+            methods: globals
+                .methods
+                .iter()
+                .map(|(k, v)| (k.as_str(), v.as_str()))
+                .collect(),
+            bounds: globals
+                .bounds
+                .iter()
+                .map(|(k, v)| (k.as_str(), v.iter().map(|b| b.as_deref()).collect()))
+                .collect(),
+            declaring: None,
+            modules: &globals.modules,
+            module,
+            open,
+            inferred,
+            warnings: Vec::new(),
+        }
+    }
+
+    // --- expressions ---
+
+    fn expr(&mut self, e: &'a ast::Expr<'a>) -> Result<Expr, DesugarError> {
+        match e {
+            ast::Expr::Literal(l) => Ok(Expr::Value(lit_value(l)?)),
+            ast::Expr::Identifier(name) => {
+                self.check_uncalled(e)?;
+                self.name_value(name)
+            }
+            ast::Expr::Constructor(c) => self.constructor(c),
+            ast::Expr::List(l) => {
+                let mut acc = nil();
+                for e in l.elems.iter().rev() {
+                    let head = self.expr(e)?;
+                    acc = cons(head, acc);
+                }
+                Ok(acc)
+            }
+            ast::Expr::IfElse(b) => self.if_else(b),
+            ast::Expr::Match(m) => self.match_expr(m),
+            ast::Expr::Block(b) => self.block(b),
+            ast::Expr::Closure(c) => self.closure(c),
+            ast::Expr::Unary { op, expr } => Ok(Expr::Uop {
+                op: unary_op(*op),
+                val: Box::new(self.expr(expr)?),
+            }),
+            ast::Expr::Infix { lhs, op, rhs } => Ok(Expr::Bop {
+                op: infix_op(*op),
+                left: Box::new(self.expr(lhs)?),
+                right: Box::new(self.expr(rhs)?),
+            }),
+            ast::Expr::Call(f, args) => self.call(f, &args.iter().collect::<Vec<_>>()),
+            // `x |> f a` is the call `f a x`, so a method still dispatches on
+            // its first argument.
+            ast::Expr::Pipe(x, f) => match &**f {
+                ast::Expr::Call(f, args) => {
+                    let mut args: Vec<_> = args.iter().collect();
+                    args.push(x);
+                    self.call(f, &args)
+                }
+                f => self.call(f, &[x]),
+            },
+            ast::Expr::Tuple(t) => {
+                let mut acc = Expr::Ctr {
+                    ty: Box::new(tuple_type(t.fields.len())),
+                    variant: None,
+                };
+                for e in &t.fields {
+                    let x = self.expr(e)?;
+                    acc = app(acc, x);
+                }
+                Ok(acc)
+            }
+            ast::Expr::Project(e, field) => self.project(e, field),
+            ast::Expr::Scope(path) => {
+                self.check_uncalled(e)?;
+                Ok(Expr::Free(self.resolve_path(path)?))
+            }
+            ast::Expr::Index(..) => Err(DesugarError::unsupported(
+                "indexing is not yet supported in lowering",
+            )),
+        }
+    }
+
+    /// Resolve a use of a bound name, driving dup projection and let inlining.
+    fn use_name(&mut self, name: &'a str) -> Result<Expr, DesugarError> {
+        let v = match self.env.get(name) {
+            None => {
+                if self.open {
+                    return Ok(Expr::Free(self.resolve_name(name)?));
+                }
+                return Err(DesugarError::new(DesugarErrorKind::UnboundVariable(
+                    name.to_string(),
+                )));
+            }
+            Some(Binding::Inline(v)) => *v,
+            Some(b) => return Ok(self.use_binding(b)),
+        };
+        // The bound value's scope excludes its own binder (recursion is not
+        // allowed), so a mention of `name` inside the value must resolve to the
+        // *outer* binding, not loop back to this one.
+        let prev = self.env.remove(name);
+        let prev_shape = self.shapes.remove(name);
+        let r = self.inline_value(v);
+        restore(&mut self.env, name, prev);
+        restore(&mut self.shapes, name, prev_shape);
+        r
+    }
+
+    /// One use of a lambda binder: its variable, or the next projection of its
+    /// dup chain.
+    fn use_binding(&self, b: &Binding<'a>) -> Expr {
+        let idx = |d: usize| DeBruijn((self.depth - 1 - d) as u64);
+        match b {
+            Binding::Lam(d) => Expr::Var(idx(*d)),
+            Binding::Cloned(c) => {
+                let mut c = c.borrow_mut();
+                let m = c.used;
+                c.used += 1;
+                // Each use selects one side of the binary dup chain: the m-th
+                // use takes the first projection of the m-th dup, and the last
+                // use takes the second projection of the final dup.
+                if m < c.count - 1 {
+                    Expr::Dp0(idx(c.dup_depths[m]))
+                } else {
+                    Expr::Dp1(idx(c.dup_depths[c.count - 2]))
+                }
+            }
+            Binding::Field(group, index, arity) => {
+                product_match(self.use_binding(group), field_branch(*index, *arity))
+            }
+            Binding::Inline(_) => unreachable!("inline bindings are re-lowered, not used"),
+        }
+    }
+
+    /// Lower the value of an inlinable binding at its (single) use site, or as
+    /// the shared value of a dup chain.
+    fn inline_value(&mut self, v: InlineVal<'a>) -> Result<Expr, DesugarError> {
+        match v {
+            InlineVal::Expr(e) => self.expr(e),
+            InlineVal::Fn(f) => self.fn_value(f),
+            InlineVal::Enum(en) => self.enum_value(en),
+            InlineVal::Struct(st) => self.struct_value(st),
+        }
+    }
+
+    /// A use of `name` as a plain value: an argument bound by a trait leaves
+    /// its dictionary behind.
+    fn name_value(&mut self, name: &'a str) -> Result<Expr, DesugarError> {
+        let bound =
+            self.env.contains_key(name) && matches!(self.shapes.get(name), Some(Shape::Bound(..)));
+        let v = self.use_name(name)?;
+        Ok(if bound {
+            product_match(v, field_branch(1, 2))
+        } else {
+            v
+        })
+    }
+
+    // --- calls and trait dispatch ---
+
+    /// `f a b ..`. A trait method dispatches on its first argument (see
+    /// [`Self::method_call`]); a generic fn gets each trait-bound argument
+    /// paired with its dictionary.
+    fn call(
+        &mut self,
+        f: &'a ast::Expr<'a>,
+        args: &[&'a ast::Expr<'a>],
+    ) -> Result<Expr, DesugarError> {
+        if let (Some((tr, method)), Some((recv, rest))) = (self.method_of(f)?, args.split_first()) {
+            let mut acc = self.method_call(tr, method, recv)?;
+            for arg in rest {
+                let x = self.expr(arg)?;
+                acc = app(acc, x);
+            }
+            return Ok(acc);
+        }
+        let bounds = self.bounds_of(f)?.unwrap_or_default();
+        if bounds.iter().skip(args.len()).any(Option::is_some) {
+            return Err(DesugarError::unsupported(format!(
+                "`{}` must be applied up to its last trait-bound argument",
+                callee(f)
+            )));
+        }
+        let mut acc = match f {
+            ast::Expr::Identifier(name) => self.name_value(name)?,
+            ast::Expr::Scope(path) => Expr::Free(self.resolve_path(path)?),
+            f => self.expr(f)?,
+        };
+        for (i, arg) in args.iter().enumerate() {
+            let x = match bounds.get(i).copied().flatten() {
+                Some(tr) => self.dict_arg(arg, tr)?,
+                None => self.expr(arg)?,
+            };
+            acc = app(acc, x);
+        }
+        Ok(acc)
+    }
+
+    /// A method or generic fn is only lowered as the head of a call, which
+    /// supplies its receiver or its trait-bound arguments.
+    fn check_uncalled(&self, e: &'a ast::Expr<'a>) -> Result<(), DesugarError> {
+        if let Some((tr, _)) = self.method_of(e)? {
+            return Err(DesugarError::unsupported(format!(
+                "`{m}` is a method of `{tr}`; call it on a receiver, or pass on a closure such as `|(x: Int)| {m} x`",
+                m = callee(e)
+            )));
+        }
+        if self.bounds_of(e)?.is_some() {
+            return Err(DesugarError::unsupported(format!(
+                "`{}` has trait-bound arguments; call it with them",
+                callee(e)
+            )));
+        }
+        Ok(())
+    }
+
+    /// The trait and method `f` names, when it is a method not shadowed by a
+    /// local.
+    fn method_of(&self, f: &ast::Expr) -> Result<Option<(&'a str, &'a str)>, DesugarError> {
+        let key = match f {
+            ast::Expr::Identifier(name) if !self.env.contains_key(name) => {
+                self.global_key(&self.methods, name)?
+            }
+            ast::Expr::Scope(path) => self.global_key(&self.methods, &path.join("::"))?,
+            _ => None,
+        };
+        Ok(key.map(|k| (self.methods[k], bare(k))))
+    }
+
+    /// The trait bounding each argument of `f`, when it names a generic fn.
+    fn bounds_of(
+        &self,
+        f: &'a ast::Expr<'a>,
+    ) -> Result<Option<Vec<Option<&'a str>>>, DesugarError> {
+        match f {
+            ast::Expr::Identifier(name) if self.env.contains_key(name) => {
+                match self.shapes.get(name) {
+                    Some(Shape::Generic(fd)) => Ok(Some(self.fn_bounds(&fd.args)?)),
+                    _ => Ok(None),
+                }
+            }
+            ast::Expr::Identifier(name) => Ok(self
+                .global_key(&self.bounds, name)?
+                .map(|k| self.bounds[k].clone())),
+            ast::Expr::Scope(path) => Ok(self
+                .global_key(&self.bounds, &path.join("::"))?
+                .map(|k| self.bounds[k].clone())),
+            _ => Ok(None),
+        }
+    }
+
+    /// The trait bounding each of `args`: those annotated with one, as in
+    /// `(x: Eq)`.
+    fn fn_bounds(
+        &self,
+        args: &'a [ast::Pattern<'a>],
+    ) -> Result<Vec<Option<&'a str>>, DesugarError> {
+        args.iter()
+            .map(|p| match self.annotated(p)? {
+                Some(Shape::Bound(tr, _)) => Ok(Some(tr)),
+                _ => Ok(None),
+            })
+            .collect()
+    }
+
+    /// `method recv ..`, for a method of `tr`: the receiver, paired with its
+    /// dictionary, is taken apart to select the method, which is applied to
+    /// the pair again. Both halves of the dictionary come from one `Dup`.
+    fn method_call(
+        &mut self,
+        tr: &'a str,
+        method: &str,
+        recv: &'a ast::Expr<'a>,
+    ) -> Result<Expr, DesugarError> {
+        let receiver = self.dict_arg(recv, tr)?;
+        let methods = &self.traits[tr];
+        let index = methods
+            .iter()
+            .position(|(m, _)| *m == method)
+            .expect("a method is declared by its trait");
+        // \dict \value -> dup dict; (dict0 .method) (dict1, value)
+        let select = product_match(Expr::Dp0(DeBruijn(0)), field_branch(index, methods.len()));
+        let branch = Expr::Lam {
+            body: Box::new(Expr::Lam {
+                body: Box::new(Expr::Dup {
+                    val: Box::new(Expr::Var(DeBruijn(1))),
+                    body: Box::new(app(
+                        select,
+                        pair(Expr::Dp1(DeBruijn(0)), Expr::Var(DeBruijn(1))),
+                    )),
+                }),
+            }),
+        };
+        Ok(product_match(receiver, branch))
+    }
+
+    /// `arg` as the `(dictionary, value)` pair a parameter bound by `tr`
+    /// takes: an argument already bound by `tr` passes its own on; anything
+    /// else goes with the impl of `tr` for its type.
+    fn dict_arg(&mut self, arg: &'a ast::Expr<'a>, tr: &'a str) -> Result<Expr, DesugarError> {
+        let name = match arg {
+            ast::Expr::Identifier(name) => Some(*name),
+            _ => None,
+        };
+        if let Some(name) = name.filter(|n| self.env.contains_key(n)) {
+            match self.shapes.get(name) {
+                Some(Shape::Bound(t, _)) if *t == tr => return self.use_name(name),
+                Some(Shape::Bound(t, _)) => {
+                    return Err(DesugarError::unsupported(format!(
+                        "`{name}` is only known to implement `{t}`, not `{tr}`"
+                    )))
+                }
+                _ => {}
+            }
+        }
+        let Some(ty) = self.shape(arg).and_then(|s| self.type_of(s)) else {
+            let what = name.map_or("this argument".to_string(), |n| format!("`{n}`"));
+            return Err(DesugarError::unsupported(format!(
+                "cannot tell which impl of `{tr}` {what} uses; annotate its type, as in `(x: Int)`"
+            )));
+        };
+        let dict = self.global(impl_name(&self.trait_global(tr), ty))?;
+        let value = self.expr(arg)?;
+        Ok(pair(dict, value))
+    }
+
+    /// The global name of the trait `tr` (a key of [`Self::traits`]).
+    fn trait_global(&self, tr: &str) -> String {
+        if self.declaring == Some(tr) {
+            qualify(self.module, tr)
+        } else {
+            tr.to_string()
+        }
+    }
+
+    /// The global `name`, which lowering does not see bound: free when open.
+    fn global(&self, name: String) -> Result<Expr, DesugarError> {
+        if self.open {
+            Ok(Expr::Free(name))
+        } else {
+            Err(DesugarError::new(DesugarErrorKind::UnboundVariable(name)))
+        }
+    }
+
+    fn constructor(&mut self, c: &'a ast::Constructor<'a>) -> Result<Expr, DesugarError> {
+        let (name, args): (&'a str, &'a [ast::Expr<'a>]) = match c {
+            ast::Constructor::Empty(name) => (name, &[]),
+            ast::Constructor::Tuple(name, args) => (name, args.as_slice()),
+            ast::Constructor::Struct(name, given) => return self.struct_ctor(name, given),
+        };
+        // Resolution: a bound (enum) name is the type value itself; a known
+        // variant name selects the constructor of its enum's binding; anything
+        // else is free (open mode) or unbound (closed mode).
+        let head = if self.env.contains_key(name) {
+            self.use_name(name)?
+        } else if let Some(key) = self.global_key(&self.ctors, name)? {
+            Expr::Ctr {
+                ty: Box::new(self.use_enum(self.ctors[key])?),
+                variant: Some(bare(key).to_string()),
+            }
+        } else if self.open {
+            Expr::Free(self.resolve_name(name)?)
+        } else {
+            return Err(DesugarError::new(DesugarErrorKind::UnboundConstructor(
+                name.to_string(),
+            )));
+        };
+        let mut acc = head;
+        for arg in args {
+            let x = self.expr(arg)?;
+            acc = app(acc, x);
+        }
+        Ok(acc)
+    }
+
+    /// `Name { x: a, y: b }`: the constructor of a struct (its product's `New`)
+    /// or of a struct-like variant, applied to the fields in declared order.
+    fn struct_ctor(
+        &mut self,
+        name: &'a str,
+        given: &'a [(&'a str, ast::Expr<'a>)],
+    ) -> Result<Expr, DesugarError> {
+        let fields = self.fields_of(name)?;
+        check_fields(name, &fields, given.iter().map(|(f, _)| *f))?;
+        let mut acc = match self.global_key(&self.ctors, name)? {
+            Some(key) => Expr::Ctr {
+                ty: Box::new(self.use_enum(self.ctors[key])?),
+                variant: Some(bare(key).to_string()),
+            },
+            None => Expr::Ctr {
+                ty: Box::new(self.use_name(name)?),
+                variant: None,
+            },
+        };
+        for field in fields {
+            let Some((_, e)) = given.iter().find(|(f, _)| *f == field) else {
+                return Err(DesugarError::malformed(format!(
+                    "`{name} {{ .. }}` is missing the field `{field}`"
+                )));
+            };
+            let x = self.expr(e)?;
+            acc = app(acc, x);
+        }
+        Ok(acc)
+    }
+
+    /// The field names of the struct or struct-like variant `name`.
+    fn fields_of(&self, name: &str) -> Result<Vec<&'a str>, DesugarError> {
+        if let Some(key) = self.global_key(&self.fields, name)? {
+            return Ok(self.fields[key].clone());
+        }
+        if self.global_key(&self.ctors, name)?.is_some() || self.env.contains_key(name) {
+            return Err(DesugarError::malformed(format!(
+                "`{name}` has no named fields"
+            )));
+        }
+        Err(DesugarError::new(DesugarErrorKind::UnboundConstructor(
+            name.to_string(),
+        )))
+    }
+
+    /// A use of the enum binding `ty` that a variant constructs: a local, or a
+    /// global, already resolved to its full name.
+    fn use_enum(&mut self, ty: &'a str) -> Result<Expr, DesugarError> {
+        if self.open && !self.env.contains_key(ty) {
+            Ok(Expr::Free(ty.to_string()))
+        } else {
+            self.use_name(ty)
+        }
+    }
+
+    // --- module paths ---
+
+    /// The modules around the use site, innermost first, ending with the top
+    /// level (`""`).
+    fn enclosing(&self) -> impl Iterator<Item = &'a str> {
+        enclosing(self.module)
+    }
+
+    /// Whether the use site is inside `module` (or is `module` itself).
+    fn within(&self, module: &str) -> bool {
+        self.module
+            .strip_prefix(module)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+    }
+
+    /// The global a name not bound in scope refers to: written bare, the item
+    /// of the innermost enclosing module that declares it (else the top-level
+    /// name); written `a::B`, the item the path leads to.
+    fn resolve_name(&self, name: &str) -> Result<String, DesugarError> {
+        if name.contains("::") {
+            return self.resolve_path(&name.split("::").collect::<Vec<_>>());
+        }
+        let module = self.enclosing().find(|m| {
+            self.modules
+                .get(*m)
+                .is_some_and(|items| items.contains_key(name))
+        });
+        Ok(module.map_or_else(|| name.to_string(), |m| qualify(m, name)))
+    }
+
+    /// The full name of the item at `a::b::x`, checking each step past `a` is
+    /// visible from the use site.
+    fn resolve_path(&self, path: &[&str]) -> Result<String, DesugarError> {
+        let unbound = || DesugarError::new(DesugarErrorKind::UnboundVariable(path.join("::")));
+        let (first, rest) = path.split_first().ok_or_else(unbound)?;
+        let mut at = self
+            .enclosing()
+            .map(|m| qualify(m, first))
+            .find(|m| self.modules.contains_key(m))
+            .ok_or_else(unbound)?;
+        for (i, step) in rest.iter().enumerate() {
+            let public = *self.modules[&at].get(*step).ok_or_else(unbound)?;
+            if !public && !self.within(&at) {
+                return Err(DesugarError::new(DesugarErrorKind::Private(qualify(
+                    &at, step,
+                ))));
+            }
+            at = qualify(&at, step);
+            if i + 1 < rest.len() && !self.modules.contains_key(&at) {
+                return Err(unbound());
+            }
+        }
+        Ok(at)
+    }
+
+    /// The key `known` (the ctor or fields map) holds the item `name` under,
+    /// if it has one. Items declared in a block go by their bare name.
+    fn global_key<V>(
+        &self,
+        known: &HashMap<&'a str, V>,
+        name: &str,
+    ) -> Result<Option<&'a str>, DesugarError> {
+        let key = |name: &str| known.get_key_value(name).map(|(k, _)| *k);
+        if name.contains("::") {
+            return Ok(key(&self.resolve_name(name)?));
+        }
+        Ok(self.enclosing().find_map(|m| key(&qualify(m, name))))
+    }
+
+    /// `if cond { a } else { b }` is a bool match applied to the condition; the
+    /// default arm erases the (false) scrutinee.
+    fn if_else(&mut self, b: &'a ast::IfElse<'a>) -> Result<Expr, DesugarError> {
+        let cond = self.expr(&b.cond)?;
+        let then = self.expr(&b.if_expr)?;
+        self.depth += 1; // the default's erasing binder
+        let els = self.expr(&b.else_expr);
+        self.depth -= 1;
+        Ok(Expr::App {
+            func: Box::new(Expr::Mat {
+                cases: vec![(Pat::Val(Value::Bool(true)), then)],
+                default: Some(Box::new(Expr::Use {
+                    body: Box::new(els?),
+                })),
+            }),
+            arg: Box::new(cond),
+        })
+    }
+
+    fn match_expr(&mut self, m: &'a ast::Match<'a>) -> Result<Expr, DesugarError> {
+        let scrut = self.expr(&m.scrut)?;
+        let pats: Vec<_> = m.arms.iter().map(|arm| &arm.pattern).collect();
+        self.match_patterns(
+            Scrutinee::Value(scrut),
+            &pats,
+            &|s, arm, name| s.count_expr(&m.arms[arm].body, name),
+            &mut |s, arm| s.expr(&m.arms[arm].body),
+        )
+    }
+
+    /// `e.0` or `e.x`: a match taking the tuple or struct apart, whose branch
+    /// keeps the one field. The branch needs a binder per field, so the value's
+    /// shape must be known here — see [`Self::shape`].
+    /// Where `e` is not a tuple or struct, `e.m` may also call the method `m`
+    /// on `e`, like `m e`.
+    fn project(&mut self, e: &'a ast::Expr<'a>, field: &str) -> Result<Expr, DesugarError> {
+        let shape = self.shape(e);
+        if !matches!(shape, Some(Shape::Tuple(_) | Shape::Struct(_))) {
+            if let Some(key) = self.global_key(&self.methods, field)? {
+                return self.method_call(self.methods[key], bare(key), e);
+            }
+        }
+        let (index, arity) = match shape {
+            Some(Shape::Tuple(arity)) => match field.parse::<usize>() {
+                Ok(index) if index < arity => (index, arity),
+                _ => {
+                    return Err(DesugarError::malformed(format!(
+                        "a {arity}-tuple has no field `.{field}`"
+                    )))
+                }
+            },
+            Some(Shape::Struct(name)) => {
+                let fields = self.fields_of(name)?;
+                match fields.iter().position(|f| *f == field) {
+                    Some(index) => (index, fields.len()),
+                    None => {
+                        return Err(DesugarError::malformed(format!(
+                            "struct `{name}` has no field `{field}`"
+                        )))
+                    }
+                }
+            }
+            _ => {
+                return Err(DesugarError::unsupported(format!(
+                    "`.{field}` needs a tuple or struct of known shape; destructure it with a pattern instead"
+                )))
+            }
+        };
+        let scrut = self.expr(e)?;
+        Ok(product_match(scrut, field_branch(index, arity)))
+    }
+
+    /// What `e` evaluates to, where that is evident from its form: a literal,
+    /// a constructed value, an operator's result, a name bound to one of those
+    /// (or annotated with its type), a field of one, or a block or `if` ending
+    /// in one. Anything else (a call, an unannotated argument) has the type
+    /// checking inferred for it, if it recorded one. An argument bound by a
+    /// trait is not a pair as far as its uses can see.
+    fn shape(&self, e: &'a ast::Expr<'a>) -> Option<Shape<'a>> {
+        if let ast::Expr::Identifier(name) = e {
+            if let Some(Shape::Bound(_, ty)) = self.shapes.get(name) {
+                return self.type_shape((*ty)?);
+            }
+        }
+        self.form_shape(e).or_else(|| self.inferred_shape(e))
+    }
+
+    /// The shape of a value of the type checking inferred for `e`.
+    fn inferred_shape(&self, e: &'a ast::Expr<'a>) -> Option<Shape<'a>> {
+        Some(match self.inferred.get(e)? {
+            InferredType::Tuple(arity) => Shape::Tuple(*arity),
+            InferredType::Named(ty) => {
+                let struct_like = self.fields.contains_key(ty.as_str());
+                if struct_like && !self.ctors.contains_key(ty.as_str()) {
+                    Shape::Struct(ty)
+                } else {
+                    Shape::Of(ty)
+                }
+            }
+        })
+    }
+
+    /// [`Self::shape`], as far as the form of `e` tells.
+    fn form_shape(&self, e: &'a ast::Expr<'a>) -> Option<Shape<'a>> {
+        let variant_of = |name| match self.global_key(&self.ctors, name) {
+            Ok(Some(key)) => Some(Shape::Of(self.ctors[key])),
+            _ => None,
+        };
+        match e {
+            ast::Expr::Literal(l) => scalar_type(l).map(Shape::Of),
+            ast::Expr::Tuple(t) => Some(Shape::Tuple(t.fields.len())),
+            ast::Expr::Constructor(ast::Constructor::Struct(name, _)) => {
+                variant_of(name).or(Some(Shape::Struct(name)))
+            }
+            ast::Expr::Constructor(
+                ast::Constructor::Empty(name) | ast::Constructor::Tuple(name, _),
+            ) => variant_of(name),
+            ast::Expr::Unary {
+                op: ast::UnaryOp::Not,
+                ..
+            } => Some(Shape::Of("Bool")),
+            ast::Expr::Unary { expr, .. } => self.shape(expr),
+            ast::Expr::Infix { lhs, op, .. } => match op {
+                ast::InfixOp::Eq
+                | ast::InfixOp::Neq
+                | ast::InfixOp::Lt
+                | ast::InfixOp::Lte
+                | ast::InfixOp::Gt
+                | ast::InfixOp::Gte => Some(Shape::Of("Bool")),
+                _ => self.shape(lhs).filter(|s| matches!(s, Shape::Of(_))),
+            },
+            ast::Expr::Identifier(name) => self.shapes.get(name).copied(),
+            ast::Expr::Block(b) if b.decls.is_empty() => self.shape(b.value.as_ref()?),
+            ast::Expr::IfElse(b) => self.shape(&b.if_expr).or_else(|| self.shape(&b.else_expr)),
+            ast::Expr::Project(inner, field) => self.shape(literal_field(inner, field)?),
+            _ => None,
+        }
+    }
+
+    /// The global name of the type a value of `shape` has, for naming its
+    /// impls.
+    fn type_of(&self, shape: Shape<'a>) -> Option<&'a str> {
+        match shape {
+            Shape::Struct(name) => self.global_key(&self.fields, name).ok().flatten(),
+            Shape::Of(ty) => Some(ty),
+            Shape::Tuple(_) | Shape::Bound(..) | Shape::Generic(_) => None,
+        }
+    }
+
+    /// The shape the annotation on a binder `(x: T)` gives it: bound by the
+    /// trait `T`, or a value of the type `T`. Types the lowering knows nothing
+    /// about give none.
+    fn annotated(&self, p: &ast::Pattern) -> Result<Option<Shape<'a>>, DesugarError> {
+        let ast::Pattern::Typed(_, ast::Type::Identifier(ty)) = p else {
+            return Ok(None);
+        };
+        if let Some(tr) = self.global_key(&self.traits, ty)? {
+            return Ok(Some(Shape::Bound(tr, None)));
+        }
+        Ok(self.type_shape(ty))
+    }
+
+    /// The shape of a value of the type `ty`: a scalar, a struct or an enum.
+    fn type_shape(&self, ty: &str) -> Option<Shape<'a>> {
+        if let Some(scalar) = SCALAR_TYPES.iter().find(|s| **s == ty) {
+            if !self.env.contains_key(ty) {
+                return Some(Shape::Of(scalar));
+            }
+        }
+        if let Ok(Some(key)) = self.global_key(&self.fields, ty) {
+            if !self.traits.contains_key(key) && !self.ctors.contains_key(key) {
+                return Some(Shape::Struct(key));
+            }
+        }
+        let full = if self.env.contains_key(ty) {
+            ty.to_string()
+        } else {
+            self.resolve_name(ty).ok()?
+        };
+        self.ctors
+            .values()
+            .find(|v| **v == full)
+            .map(|v| Shape::Of(v))
+    }
+
+    // --- blocks and declarations ---
+
+    fn block(&mut self, b: &'a ast::ExprBlock<'a>) -> Result<Expr, DesugarError> {
+        self.decls(&b.decls, 0, b.value.as_ref())
+    }
+
+    fn decls(
+        &mut self,
+        decls: &'a [ast::Declaration<'a>],
+        idx: usize,
+        value: Option<&'a ast::Expr<'a>>,
+    ) -> Result<Expr, DesugarError> {
+        let decl = match decls.get(idx) {
+            None => {
+                return match value {
+                    Some(e) => self.expr(e),
+                    None => Err(DesugarError::malformed("a block must end in an expression")),
+                };
+            }
+            Some(d) => d,
+        };
+        match decl {
+            ast::Declaration::Let(l) => match untyped(&l.pattern) {
+                ast::Pattern::Identifier(name) => {
+                    let n = self.count_decls(&decls[idx + 1..], value, name);
+                    self.let_binder(name, n, InlineVal::Expr(&l.value), |s| {
+                        s.decls(decls, idx + 1, value)
+                    })
+                }
+                // erased let: drop the value
+                ast::Pattern::Wildcard => self.decls(decls, idx + 1, value),
+                // a one-arm match, whose arm is the rest of the block
+                p => {
+                    let scrut = self.expr(&l.value)?;
+                    let rest = &decls[idx + 1..];
+                    self.match_patterns(
+                        Scrutinee::Value(scrut),
+                        &[p],
+                        &|s, _, name| s.count_decls(rest, value, name),
+                        &mut |s, _| s.decls(decls, idx + 1, value),
+                    )
+                }
+            },
+            ast::Declaration::Fn(f) => {
+                let n = self.count_decls(&decls[idx + 1..], value, f.name);
+                self.let_binder(f.name, n, InlineVal::Fn(f), |s| {
+                    s.decls(decls, idx + 1, value)
+                })
+            }
+            ast::Declaration::Rec(group) => {
+                let rest = &decls[idx + 1..];
+                let n = group
+                    .fns
+                    .iter()
+                    .map(|f| self.count_decls(rest, value, f.name))
+                    .sum();
+                let fix = self.rec_value(group)?;
+                let scope = self.binder(n, |s, b| {
+                    s.with_group(group, b, |s| s.decls(decls, idx + 1, value))
+                })?;
+                Ok(app(scope, fix))
+            }
+            ast::Declaration::Enum(en) => {
+                self.check_enum_shadowing(en)?;
+                // The variants resolve to this enum binding for the rest of the
+                // block (including any inline re-lowering of later bindings).
+                let prevs: Vec<_> = en
+                    .variants
+                    .iter()
+                    .map(|v| {
+                        let name = variant_name(v);
+                        (name, self.ctors.insert(name, en.name))
+                    })
+                    .collect();
+                let prev_fields: Vec<_> = en
+                    .variants
+                    .iter()
+                    .filter_map(|v| match v {
+                        ast::EnumVariant::Struct(name, entries) => {
+                            Some((*name, self.fields.insert(name, field_names(entries))))
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                let n = self.count_decls(&decls[idx + 1..], value, en.name);
+                let r = self.let_binder(en.name, n, InlineVal::Enum(en), |s| {
+                    s.decls(decls, idx + 1, value)
+                });
+                for (name, prev) in prev_fields.into_iter().rev() {
+                    restore(&mut self.fields, name, prev);
+                }
+                for (name, prev) in prevs.into_iter().rev() {
+                    restore(&mut self.ctors, name, prev);
+                }
+                r
+            }
+            ast::Declaration::Struct(st) => {
+                if self.env.contains_key(st.name) || self.fields.contains_key(st.name) {
+                    return Err(DesugarError::unsupported(format!(
+                        "struct `{}` shadows an existing binding; shadowing structs is not yet supported",
+                        st.name
+                    )));
+                }
+                let prev = self.fields.insert(st.name, field_names(&st.entries));
+                let n = self.count_decls(&decls[idx + 1..], value, st.name);
+                let r = self.let_binder(st.name, n, InlineVal::Struct(st), |s| {
+                    s.decls(decls, idx + 1, value)
+                });
+                restore(&mut self.fields, st.name, prev);
+                r
+            }
+            ast::Declaration::Mod(m) => Err(DesugarError::unsupported(format!(
+                "`mod {}` must be declared at the top level, not in a block",
+                m.name
+            ))),
+            ast::Declaration::Trait(_) | ast::Declaration::Impl(_) => {
+                Err(DesugarError::unsupported(format!(
+                    "`{}` declarations must be at the top level, not in a block",
+                    decl_kind(decl)
+                )))
+            }
+            d => Err(DesugarError::unsupported(format!(
+                "`{}` declarations are not yet supported in lowering",
+                decl_kind(d)
+            ))),
+        }
+    }
+
+    /// A single ctor map keyed by variant name can't express shadowing, so
+    /// redefining a live enum or variant name inside an expression is rejected
+    /// (first pass). Top-level (REPL) redefinition is fine — see
+    /// [`lower_decl_open`].
+    fn check_enum_shadowing(&self, en: &ast::EnumDecl) -> Result<(), DesugarError> {
+        if self.env.contains_key(en.name) || self.ctors.values().any(|&v| v == en.name) {
+            return Err(DesugarError::unsupported(format!(
+                "enum `{}` shadows an existing binding; shadowing enums is not yet supported",
+                en.name
+            )));
+        }
+        for v in &en.variants {
+            let name = variant_name(v);
+            if matches!(self.global_key(&self.ctors, name), Ok(Some(_)))
+                || self.env.contains_key(name)
+            {
+                return Err(DesugarError::unsupported(format!(
+                    "variant `{name}` shadows an existing binding; shadowing variants is not yet supported"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    // --- fn and enum values ---
+
+    /// The value of a `fn` declaration: a lambda chain over its arguments. The
+    /// function's own name is bound within its body; if the body actually uses
+    /// it, the whole chain is wrapped in `\foo -> ..` and applied to the
+    /// Y-combinator. See [`Self::rec_value`] for functions that call each
+    /// other.
+    fn fn_value(&mut self, f: &'a ast::FnDecl<'a>) -> Result<Expr, DesugarError> {
+        let rec = if f.args.iter().any(|p| pat_binds(p, f.name)) {
+            0
+        } else {
+            self.count_block(&f.body, f.name)
+        };
+        if rec == 0 {
+            return self.fn_args(&f.args, &f.body, 0, None);
+        }
+        let shape = self.fn_shape(f)?;
+        let wrapped =
+            self.lam_binder(f.name, rec, shape, |s| s.fn_args(&f.args, &f.body, 0, None))?;
+        Ok(app(y_combinator(), wrapped))
+    }
+
+    /// A `rec` group's value: the tuple of its functions, which see each other
+    /// through one binder of the tuple itself, tied by the Y-combinator.
+    fn rec_value(&mut self, group: &'a ast::RecDecl<'a>) -> Result<Expr, DesugarError> {
+        if group.fns.is_empty() {
+            return Err(DesugarError::malformed(
+                "a `rec` group needs at least one function",
+            ));
+        }
+        for (i, f) in group.fns.iter().enumerate() {
+            if group.fns[..i].iter().any(|g| g.name == f.name) {
+                return Err(DesugarError::malformed(format!(
+                    "`{}` is declared twice in one `rec` group",
+                    f.name
+                )));
+            }
+        }
+        let n = group
+            .fns
+            .iter()
+            .map(|g| {
+                group
+                    .fns
+                    .iter()
+                    .map(|f| count_member(self, f, g.name))
+                    .sum::<usize>()
+            })
+            .sum();
+        let tuple = |s: &mut Self| {
+            let mut acc = Expr::Ctr {
+                ty: Box::new(tuple_type(group.fns.len())),
+                variant: None,
+            };
+            for f in &group.fns {
+                let x = s.fn_args(&f.args, &f.body, 0, None)?;
+                acc = app(acc, x);
+            }
+            Ok(acc)
+        };
+        if n == 0 {
+            return tuple(self);
+        }
+        let wrapped = self.binder(n, |s, b| s.with_group(group, b, tuple))?;
+        Ok(app(y_combinator(), wrapped))
+    }
+
+    /// Run `f` with the functions of `group` bound to the fields of the
+    /// group's binding `b` (`None` when none of them is used).
+    fn with_group(
+        &mut self,
+        group: &'a ast::RecDecl<'a>,
+        b: Option<Binding<'a>>,
+        f: impl FnOnce(&mut Self) -> Result<Expr, DesugarError>,
+    ) -> Result<Expr, DesugarError> {
+        let Some(b) = b else {
+            return f(self);
+        };
+        let mut shapes = Vec::with_capacity(group.fns.len());
+        for g in &group.fns {
+            shapes.push(self.fn_shape(g)?);
+        }
+        let arity = group.fns.len();
+        let mut prevs = Vec::with_capacity(arity);
+        for (i, (g, shape)) in group.fns.iter().zip(shapes).enumerate() {
+            let field = Binding::Field(Box::new(b.clone()), i, arity);
+            prevs.push((
+                g.name,
+                self.env.insert(g.name, field),
+                self.bind_shape(g.name, shape),
+            ));
+        }
+        let r = f(self);
+        for (name, prev, prev_shape) in prevs.into_iter().rev() {
+            restore(&mut self.env, name, prev);
+            restore(&mut self.shapes, name, prev_shape);
+        }
+        r
+    }
+
+    /// A generic fn's shape, which calls to it need to pair up its arguments.
+    fn fn_shape(&self, f: &'a ast::FnDecl<'a>) -> Result<Option<Shape<'a>>, DesugarError> {
+        let generic = self.fn_bounds(&f.args)?.iter().any(Option::is_some);
+        Ok(generic.then_some(Shape::Generic(f)))
+    }
+
+    /// `|x, y| e`: the same lambda chain a fn with these arguments lowers to,
+    /// closing over whatever `e` uses from the enclosing scope. Nothing pairs
+    /// up the arguments of a call to it, so none may be bound by a trait.
+    fn closure(&mut self, c: &'a ast::Closure<'a>) -> Result<Expr, DesugarError> {
+        if self.fn_bounds(&c.params)?.iter().any(Option::is_some) {
+            return Err(DesugarError::unsupported(
+                "a closure's arguments can't be bound by a trait; declare a fn instead",
+            ));
+        }
+        self.fn_args(&c.params, &c.body, 0, None)
+    }
+
+    /// The lambda chain over `args` (from `idx` on) returning `body`. A
+    /// method's first argument, its receiver, has the shape `receiver`.
+    fn fn_args(
+        &mut self,
+        args: &'a [ast::Pattern<'a>],
+        body: &'a ast::ExprBlock<'a>,
+        idx: usize,
+        receiver: Option<Shape<'a>>,
+    ) -> Result<Expr, DesugarError> {
+        let pat = match args.get(idx) {
+            None => return self.block(body),
+            Some(p) => p,
+        };
+        let shape = match receiver {
+            Some(shape) if idx == 0 => Some(shape),
+            _ => self.annotated(pat)?,
+        };
+        match untyped(pat) {
+            ast::Pattern::Identifier(name) => {
+                let n = if pats_bind(&args[idx + 1..], name) {
+                    0
+                } else {
+                    self.count_block(body, name)
+                };
+                self.lam_binder(name, n, shape, |s| s.fn_args(args, body, idx + 1, receiver))
+            }
+            ast::Pattern::Wildcard => {
+                self.depth += 1;
+                let inner = self.fn_args(args, body, idx + 1, receiver);
+                self.depth -= 1;
+                Ok(Expr::Use {
+                    body: Box::new(inner?),
+                })
+            }
+            // A pair can't be matched as the value it carries.
+            _ if matches!(shape, Some(Shape::Bound(..))) => Err(DesugarError::unsupported(
+                "an argument bound by a trait must be a plain name",
+            )),
+            // `f (a, b) = ..`: a lambda whose argument is matched at once.
+            p => {
+                let later = &args[idx + 1..];
+                self.match_patterns(
+                    Scrutinee::Arg,
+                    &[p],
+                    &|s, _, name| {
+                        if pats_bind(later, name) {
+                            0
+                        } else {
+                            s.count_block(body, name)
+                        }
+                    },
+                    &mut |s, _| s.fn_args(args, body, idx + 1, receiver),
+                )
+            }
+        }
+    }
+
+    /// A trait's dictionary type, a product of its methods, and the values of
+    /// its default methods, each named `Trait.method`. The trait is in scope
+    /// for its defaults, which may call each other.
+    fn trait_value(
+        &mut self,
+        t: &'a ast::TraitDecl<'a>,
+    ) -> Result<(Expr, Vec<(String, Expr)>), DesugarError> {
+        for (i, m) in t.methods.iter().enumerate() {
+            if t.methods[..i].iter().any(|o| o.name == m.name) {
+                return Err(DesugarError::malformed(format!(
+                    "trait `{}` declares the method `{}` twice",
+                    t.name, m.name
+                )));
+            }
+        }
+        let methods = t.methods.iter().map(|m| (m.name, m.default.is_some()));
+        self.traits.insert(t.name, methods.collect());
+        self.fields
+            .insert(t.name, t.methods.iter().map(|m| m.name).collect());
+        for m in &t.methods {
+            self.methods.insert(m.name, t.name);
+        }
+        self.declaring = Some(t.name);
+        let mut defaults = Vec::new();
+        for m in &t.methods {
+            if let Some(body) = &m.default {
+                let value = self.method_value(t.name, None, m.name, &m.args, body)?;
+                defaults.push((format!("{}.{}", t.name, m.name), value));
+            } else {
+                self.check_receiver(t.name, m.name, &m.args)?;
+            }
+        }
+        let dictionary = Expr::TypeDef {
+            name: None,
+            kind: TypeDefKind::Product(t.methods.iter().map(|_| placeholder_type()).collect()),
+        };
+        Ok((dictionary, defaults))
+    }
+
+    /// An impl's dictionary: the trait's product constructor applied to each
+    /// method, the impl's own or else the trait's default. Returns it with the
+    /// global it is bound to.
+    fn impl_value(&mut self, i: &'a ast::ImplDecl<'a>) -> Result<(String, Expr), DesugarError> {
+        let tr = self
+            .global_key(&self.traits, i.trait_name)?
+            .ok_or_else(|| DesugarError::malformed(format!("`{}` is not a trait", i.trait_name)))?;
+        let ast::Type::Identifier(ty_name) = &i.ty;
+        let ty = self
+            .type_shape(ty_name)
+            .and_then(|s| self.type_of(s))
+            .ok_or_else(|| {
+                DesugarError::new(DesugarErrorKind::UnboundVariable(ty_name.to_string()))
+            })?;
+        let name = impl_name(tr, ty);
+        let methods = self.traits[tr].clone();
+        for (k, f) in i.methods.iter().enumerate() {
+            if !methods.iter().any(|(m, _)| *m == f.name) {
+                return Err(DesugarError::malformed(format!(
+                    "`{}` is not a method of `{tr}`",
+                    f.name
+                )));
+            }
+            if i.methods[..k].iter().any(|o| o.name == f.name) {
+                return Err(DesugarError::malformed(format!(
+                    "`{name}` defines `{}` twice",
+                    f.name
+                )));
+            }
+        }
+        let mut acc = Expr::Ctr {
+            ty: Box::new(self.use_enum(tr)?),
+            variant: None,
+        };
+        for (m, has_default) in methods {
+            let value = match i.methods.iter().find(|f| f.name == m) {
+                Some(f) => self.method_value(tr, Some(ty), m, &f.args, &f.body)?,
+                None if has_default => self.global(format!("{tr}.{m}"))?,
+                None => {
+                    return Err(DesugarError::malformed(format!(
+                        "`{name}` is missing the method `{m}`"
+                    )))
+                }
+            };
+            acc = app(acc, value);
+        }
+        Ok((name, acc))
+    }
+
+    /// A method of `tr`: a fn whose first argument, its receiver, is bound by
+    /// `tr`. In an impl, the receiver's value has the impl's type `ty`.
+    fn method_value(
+        &mut self,
+        tr: &'a str,
+        ty: Option<&'a str>,
+        method: &str,
+        args: &'a [ast::Pattern<'a>],
+        body: &'a ast::ExprBlock<'a>,
+    ) -> Result<Expr, DesugarError> {
+        self.check_receiver(tr, method, args)?;
+        self.fn_args(args, body, 0, Some(Shape::Bound(tr, ty)))
+    }
+
+    /// Check a method of `tr` has a receiver it can pass on.
+    fn check_receiver(
+        &self,
+        tr: &str,
+        method: &str,
+        args: &[ast::Pattern],
+    ) -> Result<(), DesugarError> {
+        match args.first().map(untyped) {
+            Some(ast::Pattern::Identifier(_) | ast::Pattern::Wildcard) => Ok(()),
+            Some(_) => Err(DesugarError::unsupported(format!(
+                "the receiver of `{tr}`'s `{method}` must be a plain name"
+            ))),
+            None => Err(DesugarError::malformed(format!(
+                "`{tr}`'s `{method}` needs a receiver, its first argument"
+            ))),
+        }
+    }
+
+    fn enum_value(&mut self, en: &'a ast::EnumDecl<'a>) -> Result<Expr, DesugarError> {
+        if en.variants.is_empty() {
+            return Err(DesugarError::malformed(format!(
+                "enum `{}` must have at least one variant",
+                en.name
+            )));
+        }
+        let declaring = self.declaring.replace(en.name);
+        let variants = self.variant_types(en);
+        self.declaring = declaring;
+        Ok(Expr::TypeDef {
+            name: Some(en.name.to_string()),
+            kind: TypeDefKind::Sum(variants?),
+        })
+    }
+
+    /// The variants of an enum, with their field types.
+    fn variant_types(
+        &mut self,
+        en: &'a ast::EnumDecl<'a>,
+    ) -> Result<Vec<(String, Vec<Expr>)>, DesugarError> {
+        let mut variants = Vec::with_capacity(en.variants.len());
+        for v in &en.variants {
+            let (name, args) = match v {
+                ast::EnumVariant::Empty(name) => (*name, Vec::new()),
+                ast::EnumVariant::Tuple(name, tys) => {
+                    let mut args = Vec::with_capacity(tys.len());
+                    for t in tys {
+                        args.push(self.lower_type(t)?);
+                    }
+                    (*name, args)
+                }
+                ast::EnumVariant::Struct(name, entries) => {
+                    (*name, self.field_types(name, entries)?)
+                }
+            };
+            if name == "New" {
+                return Err(DesugarError::malformed("`New` is reserved as the product constructor and cannot be used as a variant name"));
+            }
+            variants.push((name.to_string(), args));
+        }
+        Ok(variants)
+    }
+
+    /// A struct's value: its product type, fields in declared order.
+    fn struct_value(&mut self, st: &'a ast::StructDecl<'a>) -> Result<Expr, DesugarError> {
+        Ok(Expr::TypeDef {
+            name: Some(st.name.to_string()),
+            kind: TypeDefKind::Product(self.field_types(st.name, &st.entries)?),
+        })
+    }
+
+    /// The types of named fields, in order; `owner` names their struct or
+    /// variant in errors.
+    fn field_types(
+        &mut self,
+        owner: &str,
+        entries: &'a [(&'a str, ast::Type<'a>)],
+    ) -> Result<Vec<Expr>, DesugarError> {
+        let mut tys = Vec::with_capacity(entries.len());
+        for (i, (field, t)) in entries.iter().enumerate() {
+            if entries[..i].iter().any(|(f, _)| f == field) {
+                return Err(DesugarError::malformed(format!(
+                    "`{owner}` declares the field `{field}` twice"
+                )));
+            }
+            tys.push(self.lower_type(t)?);
+        }
+        Ok(tys)
+    }
+
+    /// A field's type. Type parameters, like the builtin scalars, have no
+    /// type values of their own and lower to the placeholder. So does the
+    /// enum being declared, in its own variants: core types have no binder
+    /// for a recursive type.
+    fn lower_type(&mut self, t: &'a ast::Type<'a>) -> Result<Expr, DesugarError> {
+        match t {
+            ast::Type::Identifier(name) if self.declaring == Some(*name) => Ok(placeholder_type()),
+            ast::Type::Identifier(name)
+                if SCALAR_TYPES.contains(name) && !self.env.contains_key(name) =>
+            {
+                Ok(placeholder_type())
+            }
+            ast::Type::Identifier(name) if is_type_param(name) => Ok(placeholder_type()),
+            ast::Type::Identifier(name) => self.use_name(name),
+        }
+    }
+
+    // --- binder machinery ---
+
+    /// Emit a lambda binder for `name` with `n` precomputed uses in the body
+    /// produced by `f`: an erasing `Use` (0), a plain `Lam` (1), or a `Lam`
+    /// over an `n-1`-long binary dup chain (>= 2).
+    fn lam_binder(
+        &mut self,
+        name: &'a str,
+        n: usize,
+        shape: Option<Shape<'a>>,
+        f: impl FnOnce(&mut Self) -> Result<Expr, DesugarError>,
+    ) -> Result<Expr, DesugarError> {
+        self.binder(n, |s, b| {
+            let Some(b) = b else {
+                return f(s);
+            };
+            let prev = s.env.insert(name, b);
+            let prev_shape = s.bind_shape(name, shape);
+            let r = f(s);
+            restore(&mut s.env, name, prev);
+            restore(&mut s.shapes, name, prev_shape);
+            r
+        })
+    }
+
+    /// A lambda binder with `n` uses, as for [`Self::lam_binder`], but not
+    /// bound to a name: `f` gets its binding (`None` when erased) to use.
+    fn binder(
+        &mut self,
+        n: usize,
+        f: impl FnOnce(&mut Self, Option<Binding<'a>>) -> Result<Expr, DesugarError>,
+    ) -> Result<Expr, DesugarError> {
+        if n == 0 {
+            self.depth += 1;
+            let inner = f(self, None);
+            self.depth -= 1;
+            return Ok(Expr::Use {
+                body: Box::new(inner?),
+            });
+        }
+        if n == 1 {
+            let lam_depth = self.depth;
+            self.depth += 1;
+            let inner = f(self, Some(Binding::Lam(lam_depth)));
+            self.depth -= 1;
+            return Ok(Expr::Lam {
+                body: Box::new(inner?),
+            });
+        }
+        // n >= 2: the lambda's own binder plus the dup chain over its argument.
+        self.depth += 1;
+        let base = self.depth;
+        let dup_depths: Vec<usize> = (0..n - 1).map(|j| base + j).collect();
+        self.depth += dup_depths.len();
+        let state = Rc::new(RefCell::new(DupState {
+            dup_depths: dup_depths.clone(),
+            count: n,
+            used: 0,
+        }));
+        let inner = f(self, Some(Binding::Cloned(state)));
+        self.depth -= 1 + dup_depths.len();
+
+        let mut e = inner?;
+        for j in (0..dup_depths.len()).rev() {
+            let val = if j == 0 {
+                Expr::Var(DeBruijn(0)) // the lambda's argument
+            } else {
+                Expr::Dp1(DeBruijn(0)) // fed by the enclosing dup
+            };
+            e = Expr::Dup {
+                val: Box::new(val),
+                body: Box::new(e),
+            };
+        }
+        Ok(Expr::Lam { body: Box::new(e) })
+    }
+
+    /// Emit a `let`-like binder for `name` with `n` precomputed uses in the
+    /// rest of the scope produced by `f`: dropped (0), inlined at the one use
+    /// site (1), or lowered once and shared through an `n-1` dup chain (>= 2).
+    fn let_binder(
+        &mut self,
+        name: &'a str,
+        n: usize,
+        val: InlineVal<'a>,
+        f: impl FnOnce(&mut Self) -> Result<Expr, DesugarError>,
+    ) -> Result<Expr, DesugarError> {
+        if n == 0 {
+            return f(self);
+        }
+        // The value's shape, read in the scope it is lowered in.
+        let shape = match val {
+            InlineVal::Expr(e) => self.shape(e),
+            InlineVal::Fn(f) => self.fn_shape(f)?,
+            InlineVal::Enum(_) | InlineVal::Struct(_) => None,
+        };
+        if n == 1 {
+            let prev_shape = self.bind_shape(name, shape);
+            let prev = self.env.insert(name, Binding::Inline(val));
+            let r = f(self);
+            restore(&mut self.env, name, prev);
+            restore(&mut self.shapes, name, prev_shape);
+            return r;
+        }
+        // n >= 2: the value is duplicated, not re-lowered, so lower it once
+        // here (in the scope outside the chain's dup binders).
+        let val_expr = self.inline_value(val)?;
+        let prev_shape = self.bind_shape(name, shape);
+        let base = self.depth;
+        let dup_depths: Vec<usize> = (0..n - 1).map(|j| base + j).collect();
+        self.depth += dup_depths.len();
+        let state = Rc::new(RefCell::new(DupState {
+            dup_depths: dup_depths.clone(),
+            count: n,
+            used: 0,
+        }));
+        let prev = self.env.insert(name, Binding::Cloned(state));
+        let inner = f(self);
+        restore(&mut self.env, name, prev);
+        restore(&mut self.shapes, name, prev_shape);
+        self.depth -= dup_depths.len();
+
+        let mut e = inner?;
+        let mut val_expr = Some(val_expr);
+        for j in (0..dup_depths.len()).rev() {
+            let val = if j == 0 {
+                val_expr.take().unwrap()
+            } else {
+                Expr::Dp1(DeBruijn(0))
+            };
+            e = Expr::Dup {
+                val: Box::new(val),
+                body: Box::new(e),
+            };
+        }
+        Ok(e)
+    }
+
+    /// Record (or forget) the shape of what `name` holds, returning what was
+    /// recorded before.
+    fn bind_shape(&mut self, name: &'a str, shape: Option<Shape<'a>>) -> Option<Shape<'a>> {
+        match shape {
+            Some(shape) => self.shapes.insert(name, shape),
+            None => self.shapes.remove(name),
+        }
+    }
+
+    // --- occurrence counting ---
+    //
+    // Counting runs over the *surface* AST before a binder is emitted, so the
+    // totals must agree exactly with the number of `use_name` resolutions the
+    // emission pass performs. Shadowing binders stop the count for the region
+    // they cover.
+
+    fn count_expr(&self, e: &ast::Expr, name: &str) -> usize {
+        match e {
+            ast::Expr::Literal(_) | ast::Expr::Scope(_) => 0,
+            ast::Expr::Identifier(n) => (*n == name) as usize,
+            ast::Expr::Constructor(c) => match c {
+                ast::Constructor::Empty(n) => self.ctor_counts(n, name),
+                ast::Constructor::Tuple(n, args) => {
+                    self.ctor_counts(n, name)
+                        + args.iter().map(|a| self.count_expr(a, name)).sum::<usize>()
+                }
+                ast::Constructor::Struct(n, fields) => {
+                    self.ctor_counts(n, name)
+                        + fields
+                            .iter()
+                            .map(|(_, e)| self.count_expr(e, name))
+                            .sum::<usize>()
+                }
+            },
+            ast::Expr::Tuple(t) => t.fields.iter().map(|e| self.count_expr(e, name)).sum(),
+            ast::Expr::List(l) => l.elems.iter().map(|e| self.count_expr(e, name)).sum(),
+            ast::Expr::IfElse(b) => {
+                self.count_expr(&b.cond, name)
+                    + self.count_expr(&b.if_expr, name)
+                    + self.count_expr(&b.else_expr, name)
+            }
+            // An arm's body is lowered once per path to it.
+            ast::Expr::Match(m) => {
+                let pats: Vec<_> = m.arms.iter().map(|arm| &arm.pattern).collect();
+                self.count_expr(&m.scrut, name)
+                    + m.arms
+                        .iter()
+                        .zip(self.arm_paths(&pats))
+                        .map(|(arm, paths)| {
+                            if pat_binds(&arm.pattern, name) {
+                                0
+                            } else {
+                                paths * self.count_expr(&arm.body, name)
+                            }
+                        })
+                        .sum::<usize>()
+            }
+            ast::Expr::Block(b) => self.count_block(b, name),
+            ast::Expr::Closure(c) => {
+                if pats_bind(&c.params, name) {
+                    0
+                } else {
+                    self.count_block(&c.body, name)
+                }
+            }
+            ast::Expr::Unary { expr, .. } => self.count_expr(expr, name),
+            ast::Expr::Infix { lhs, rhs, .. } => {
+                self.count_expr(lhs, name) + self.count_expr(rhs, name)
+            }
+            ast::Expr::Project(e, _) => self.count_expr(e, name),
+            ast::Expr::Index(e, i) => self.count_expr(e, name) + self.count_expr(i, name),
+            ast::Expr::Call(f, args) => {
+                self.count_expr(f, name)
+                    + args.iter().map(|a| self.count_expr(a, name)).sum::<usize>()
+            }
+            ast::Expr::Pipe(x, f) => self.count_expr(x, name) + self.count_expr(f, name),
+        }
+    }
+
+    /// How many uses of the binder `name` one constructor-head mention of `n`
+    /// contributes: a direct mention of the enum binding itself, or a variant
+    /// that resolves to it (a `Ctr` whose `ty` uses the binding).
+    fn ctor_counts(&self, n: &str, name: &str) -> usize {
+        let resolves =
+            matches!(self.global_key(&self.ctors, n), Ok(Some(k)) if self.ctors[k] == name);
+        (n == name || resolves) as usize
+    }
+
+    fn count_block(&self, b: &ast::ExprBlock, name: &str) -> usize {
+        self.count_decls(&b.decls, b.value.as_ref(), name)
+    }
+
+    /// Count uses of `name` across a run of declarations and the trailing block
+    /// value, stopping when a declaration rebinds `name`.
+    fn count_decls(
+        &self,
+        decls: &[ast::Declaration],
+        value: Option<&ast::Expr>,
+        name: &str,
+    ) -> usize {
+        let mut total = 0;
+        for d in decls {
+            match d {
+                ast::Declaration::Let(l) => {
+                    total += self.count_expr(&l.value, name);
+                    if pat_binds(&l.pattern, name) {
+                        return total;
+                    }
+                }
+                ast::Declaration::Fn(f) => {
+                    total += self.count_fn(f, name);
+                    if f.name == name {
+                        return total;
+                    }
+                }
+                ast::Declaration::Rec(group) => {
+                    if group.fns.iter().any(|f| f.name == name) {
+                        return total;
+                    }
+                    total += group
+                        .fns
+                        .iter()
+                        .map(|f| self.count_fn(f, name))
+                        .sum::<usize>();
+                }
+                ast::Declaration::Enum(en) => {
+                    total += self.count_enum(en, name);
+                    if en.name == name {
+                        return total;
+                    }
+                }
+                ast::Declaration::Struct(st) => {
+                    total += count_field_types(&st.entries, name);
+                    if st.name == name {
+                        return total;
+                    }
+                }
+                // Unsupported declarations error during emission; count nothing.
+                _ => {}
+            }
+        }
+        total + value.map_or(0, |v| self.count_expr(v, name))
+    }
+
+    /// Uses of an *outer* `name` inside a `fn` value. The function's own name
+    /// and its argument binders shadow.
+    fn count_fn(&self, f: &ast::FnDecl, name: &str) -> usize {
+        if f.name == name || f.args.iter().any(|p| pat_binds(p, name)) {
+            0
+        } else {
+            self.count_block(&f.body, name)
+        }
+    }
+
+    /// Uses of `name` in an enum declaration's variant field types.
+    fn count_enum(&self, en: &ast::EnumDecl, name: &str) -> usize {
+        en.variants
+            .iter()
+            .map(|v| match v {
+                ast::EnumVariant::Tuple(_, tys) => tys.iter().filter(|t| names(t, name)).count(),
+                ast::EnumVariant::Struct(_, fields) => count_field_types(fields, name),
+                ast::EnumVariant::Empty(_) => 0,
+            })
+            .sum()
+    }
+}
+
+// ========================================================================
+// Helpers
+// ========================================================================
+
+fn restore<'a, V>(map: &mut HashMap<&'a str, V>, name: &'a str, prev: Option<V>) {
+    match prev {
+        Some(v) => {
+            map.insert(name, v);
+        }
+        None => {
+            map.remove(name);
+        }
+    }
+}
+
+fn pat_binds(p: &ast::Pattern, name: &str) -> bool {
+    match p {
+        ast::Pattern::Identifier(n) => *n == name,
+        ast::Pattern::Wildcard | ast::Pattern::Literal(_) => false,
+        ast::Pattern::Constructor(_, subs) => pats_bind(subs, name),
+        ast::Pattern::Struct(_, fields) => fields.iter().any(|(_, p)| pat_binds(p, name)),
+        ast::Pattern::Tuple(subs) => pats_bind(subs, name),
+        ast::Pattern::Typed(inner, _) => pat_binds(inner, name),
+    }
+}
+
+fn pats_bind(ps: &[ast::Pattern], name: &str) -> bool {
+    ps.iter().any(|p| pat_binds(p, name))
+}
+
+/// `p` without its type ascriptions (types are not checked yet).
+pub(crate) fn untyped<'p, 'a>(p: &'p ast::Pattern<'a>) -> &'p ast::Pattern<'a> {
+    match p {
+        ast::Pattern::Typed(p, _) => untyped(p),
+        p => p,
+    }
+}
+
+/// Uses of a `rec` group's function `name` inside its member `f`, whose own
+/// arguments may shadow it.
+fn count_member(l: &Lower, f: &ast::FnDecl, name: &str) -> usize {
+    if pats_bind(&f.args, name) {
+        0
+    } else {
+        l.count_block(&f.body, name)
+    }
+}
+
+/// Uses of `name` among named fields' types.
+fn count_field_types(entries: &[(&str, ast::Type)], name: &str) -> usize {
+    entries.iter().filter(|(_, t)| names(t, name)).count()
+}
+
+/// Whether the field type `t` uses the binding `name`, as a type parameter
+/// does not.
+fn names(t: &ast::Type, name: &str) -> bool {
+    matches!(t, ast::Type::Identifier(n) if *n == name && !is_type_param(n))
+}
+
+/// Whether a type named `name` is a parameter (`a` in `struct Box { v: a }`)
+/// rather than a declared type: it starts with a lowercase letter.
+pub(crate) fn is_type_param(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_lowercase())
+}
+
+fn field_names<'a>(entries: &[(&'a str, ast::Type<'a>)]) -> Vec<&'a str> {
+    entries.iter().map(|(name, _)| *name).collect()
+}
+
+fn owned(names: Vec<&str>) -> Vec<String> {
+    names.into_iter().map(str::to_string).collect()
+}
+
+/// Check the fields named in `Name { .. }` (a construction or a pattern)
+/// against the ones `Name` declares: each must exist, and appear once.
+fn check_fields<'a>(
+    owner: &str,
+    declared: &[&str],
+    given: impl Iterator<Item = &'a str>,
+) -> Result<(), DesugarError> {
+    let mut seen = Vec::new();
+    for field in given {
+        if !declared.contains(&field) {
+            return Err(DesugarError::malformed(format!(
+                "`{owner}` has no field `{field}`"
+            )));
+        }
+        if seen.contains(&field) {
+            return Err(DesugarError::malformed(format!(
+                "the field `{field}` is given twice in `{owner} {{ .. }}`"
+            )));
+        }
+        seen.push(field);
+    }
+    Ok(())
+}
+
+/// The expression of field `field` of `e`, when `e` is a tuple or struct
+/// literal (possibly in a block without declarations).
+fn literal_field<'a>(e: &'a ast::Expr<'a>, field: &str) -> Option<&'a ast::Expr<'a>> {
+    match e {
+        ast::Expr::Block(b) if b.decls.is_empty() => literal_field(b.value.as_ref()?, field),
+        ast::Expr::Tuple(t) => t.fields.get(field.parse::<usize>().ok()?),
+        ast::Expr::Constructor(ast::Constructor::Struct(_, fields)) => {
+            fields.iter().find(|(f, _)| *f == field).map(|(_, e)| e)
+        }
+        _ => None,
+    }
+}
+
+/// The last segment of a possibly qualified name: what a `Ctr` or `Pat::Ctr`
+/// calls a variant.
+pub(crate) fn bare(name: &str) -> &str {
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+pub(crate) fn variant_name<'a>(v: &ast::EnumVariant<'a>) -> &'a str {
+    match v {
+        ast::EnumVariant::Tuple(n, _)
+        | ast::EnumVariant::Struct(n, _)
+        | ast::EnumVariant::Empty(n) => n,
+    }
+}
+
+fn decl_kind(d: &ast::Declaration) -> &'static str {
+    match d {
+        ast::Declaration::Mod(_) => "mod",
+        ast::Declaration::Let(_) => "let",
+        ast::Declaration::Fn(_) => "fn",
+        ast::Declaration::Rec(_) => "rec",
+        ast::Declaration::Alias(_) => "type",
+        ast::Declaration::Enum(_) => "enum",
+        ast::Declaration::Struct(_) => "struct",
+        ast::Declaration::Trait(_) => "trait",
+        ast::Declaration::Impl(_) => "impl",
+    }
+}
+
+/// The scalar type of a literal's value.
+fn scalar_type(l: &ast::Literal) -> Option<&'static str> {
+    match l {
+        ast::Literal::Integer(_) => Some("Int"),
+        ast::Literal::Float(_) => Some("Float"),
+        ast::Literal::Bool(_) => Some("Bool"),
+        ast::Literal::String(_) => Some("String"),
+        ast::Literal::Unit => None,
+    }
+}
+
+/// How `f`, the head of a call, reads in an error.
+fn callee(f: &ast::Expr) -> String {
+    match f {
+        ast::Expr::Identifier(name) => name.to_string(),
+        ast::Expr::Scope(path) => path.join("::"),
+        _ => "this".to_string(),
+    }
+}
+
+fn lit_value(l: &ast::Literal) -> Result<Value, DesugarError> {
+    Ok(match l {
+        ast::Literal::Integer(i) => Value::Int(*i),
+        ast::Literal::Float(x) => Value::Float(OrderedFloat(x.into_inner())),
+        ast::Literal::Bool(b) => Value::Bool(*b),
+        ast::Literal::String(s) => Value::Str((*s).to_string()),
+        ast::Literal::Unit => {
+            return Err(DesugarError::unsupported(
+                "`()` is not yet supported in lowering",
+            ))
+        }
+    })
+}
+
+fn infix_op(op: ast::InfixOp) -> vm::BinaryOp {
+    use ast::InfixOp as I;
+    use vm::BinaryOp as B;
+    match op {
+        I::Add => B::Add,
+        I::Sub => B::Sub,
+        I::Mul => B::Mul,
+        I::Div => B::Div,
+        I::Mod => B::Mod,
+        I::Eq => B::Eq,
+        I::Neq => B::Neq,
+        I::Lt => B::Lt,
+        I::Lte => B::Lte,
+        I::Gt => B::Gt,
+        I::Gte => B::Gte,
+        I::And => B::And,
+        I::Or => B::Or,
+        I::Xor => B::Xor,
+        I::Shl => B::Shl,
+        I::Shr => B::Shr,
+    }
+}
+
+fn unary_op(op: ast::UnaryOp) -> vm::UnaryOp {
+    match op {
+        ast::UnaryOp::Neg => vm::UnaryOp::Neg,
+        ast::UnaryOp::Not => vm::UnaryOp::Not,
+    }
+}
+
+/// The variant name a product's one constructor answers to in a match.
+const PRODUCT_CTOR: &str = "New";
+
+/// The builtin scalar types, which have no type values yet.
+pub(crate) const SCALAR_TYPES: [&str; 5] = ["Int", "Float", "Bool", "String", "Char"];
+
+/// `type ()`, core's placeholder for a field type that is not checked.
+fn placeholder_type() -> Expr {
+    Expr::TypeDef {
+        name: None,
+        kind: TypeDefKind::Product(Vec::new()),
+    }
+}
+
+/// The anonymous product type of an `arity`-tuple, its fields untyped.
+fn tuple_type(arity: usize) -> Expr {
+    Expr::TypeDef {
+        name: None,
+        kind: TypeDefKind::Product((0..arity).map(|_| placeholder_type()).collect()),
+    }
+}
+
+/// The branch of a product match with `arity` fields keeping the one at
+/// `index`.
+fn field_branch(index: usize, arity: usize) -> Expr {
+    let mut branch = Expr::Var(DeBruijn((arity - 1 - index) as u64));
+    for i in (0..arity).rev() {
+        let body = Box::new(branch);
+        branch = if i == index {
+            Expr::Lam { body }
+        } else {
+            Expr::Use { body }
+        };
+    }
+    branch
+}
+
+/// `(a, b)`.
+fn pair(a: Expr, b: Expr) -> Expr {
+    let new = Expr::Ctr {
+        ty: Box::new(tuple_type(2)),
+        variant: None,
+    };
+    app(app(new, a), b)
+}
+
+/// Take the product `scrut` apart with `branch`, a lambda over its fields.
+fn product_match(scrut: Expr, branch: Expr) -> Expr {
+    app(
+        Expr::Mat {
+            cases: vec![(Pat::Ctr(PRODUCT_CTOR.to_string()), branch)],
+            default: None,
+        },
+        scrut,
+    )
+}
+
+fn app(func: Expr, arg: Expr) -> Expr {
+    Expr::App {
+        func: Box::new(func),
+        arg: Box::new(arg),
+    }
+}
+
+/// The list element type used by list sugar: `(List Int)`, matching core's list
+/// desugaring. `List` and `Int` are free names resolved later by the prelude.
+fn list_variant(name: &str) -> Expr {
+    Expr::Ctr {
+        ty: Box::new(app(Expr::Free("List".into()), Expr::Free("Int".into()))),
+        variant: Some(name.into()),
+    }
+}
+
+fn nil() -> Expr {
+    list_variant("Nil")
+}
+
+fn cons(head: Expr, tail: Expr) -> Expr {
+    app(app(list_variant("Cons"), head), tail)
+}
+
+/// The Y-combinator `\&f -> (\&x -> f (x x)) (\&x -> f (x x))` as a closed
+/// core expression, identical to what core's desugarer produces for `fix`
+/// (verified by a test below): binder levels outside-in are `Lam f` (0),
+/// `Dup f` (1), and within each inner lambda `Lam x` (2) and `Dup x` (3), so
+/// use sites at depth 4 see `f`'s dup at index 2 and `x`'s at index 0.
+fn y_combinator() -> Expr {
+    let inner = |f_use: Expr| Expr::Lam {
+        body: Box::new(Expr::Dup {
+            val: Box::new(Expr::Var(DeBruijn(0))),
+            body: Box::new(app(
+                f_use,
+                app(Expr::Dp0(DeBruijn(0)), Expr::Dp1(DeBruijn(0))),
+            )),
+        }),
+    };
+    Expr::Lam {
+        body: Box::new(Expr::Dup {
+            val: Box::new(Expr::Var(DeBruijn(0))),
+            body: Box::new(app(
+                inner(Expr::Dp0(DeBruijn(2))),
+                inner(Expr::Dp1(DeBruijn(2))),
+            )),
+        }),
+    }
+}
+
+// ========================================================================
+// Tests
+// ========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::ExprSpans;
+    use crate::check::{check_expr, Types};
+    use crate::parser::{parse_expr, parse_expr_spanned, parse_repl};
+
+    /// Lower a closed expression from source.
+    fn de(src: &str) -> Expr {
+        let e = parse_expr(src).unwrap_or_else(|m| panic!("parse error in {src:?}: {m}"));
+        lower_expr(&e).unwrap_or_else(|m| panic!("lowering error in {src:?}: {m}"))
+    }
+
+    fn de_err(src: &str) -> DesugarErrorKind {
+        let e = parse_expr(src).unwrap_or_else(|m| panic!("parse error in {src:?}: {m}"));
+        lower_expr(&e)
+            .expect_err(&format!("expected a lowering error for {src:?}"))
+            .kind
+    }
+
+    /// Check `src`, then lower it with what checking inferred.
+    fn de_checked(src: &str) -> Expr {
+        let (e, log) = parse_expr_spanned(src).unwrap();
+        let spans = ExprSpans::of_expr(&e, log);
+        let (globals, types) = (Globals::default(), Types::default());
+        let inferred = check_expr(&e, &globals, &types, "", &spans)
+            .unwrap_or_else(|m| panic!("type error in {src:?}: {m}"));
+        lower_expr_open(&e, &globals, &inferred, "", &mut Vec::new())
+            .unwrap_or_else(|m| panic!("lowering error in {src:?}: {m}"))
+    }
+
+    fn de_open(src: &str) -> Expr {
+        let e = parse_expr(src).unwrap_or_else(|m| panic!("parse error in {src:?}: {m}"));
+        lower_expr_open(
+            &e,
+            &Globals::default(),
+            &Inferred::default(),
+            "",
+            &mut Vec::new(),
+        )
+        .unwrap_or_else(|m| panic!("lowering error in {src:?}: {m}"))
+    }
+
+    /// The pattern warnings lowering `src` (open) reports.
+    fn warnings(src: &str) -> Vec<String> {
+        let e = parse_expr(src).unwrap_or_else(|m| panic!("parse error in {src:?}: {m}"));
+        let mut warnings = Vec::new();
+        lower_expr_open(
+            &e,
+            &Globals::default(),
+            &Inferred::default(),
+            "",
+            &mut warnings,
+        )
+        .unwrap_or_else(|m| panic!("lowering error in {src:?}: {m}"));
+        warnings.iter().map(ToString::to_string).collect()
+    }
+
+    fn lam(body: Expr) -> Expr {
+        Expr::Lam {
+            body: Box::new(body),
+        }
+    }
+    fn use_(body: Expr) -> Expr {
+        Expr::Use {
+            body: Box::new(body),
+        }
+    }
+    fn dup(val: Expr, body: Expr) -> Expr {
+        Expr::Dup {
+            val: Box::new(val),
+            body: Box::new(body),
+        }
+    }
+    fn int(i: i64) -> Expr {
+        Expr::Value(Value::Int(i))
+    }
+    fn bop(op: vm::BinaryOp, l: Expr, r: Expr) -> Expr {
+        Expr::Bop {
+            op,
+            left: Box::new(l),
+            right: Box::new(r),
+        }
+    }
+    fn var(i: u64) -> Expr {
+        Expr::Var(DeBruijn(i))
+    }
+    fn dp0(i: u64) -> Expr {
+        Expr::Dp0(DeBruijn(i))
+    }
+    fn dp1(i: u64) -> Expr {
+        Expr::Dp1(DeBruijn(i))
+    }
+
+    #[test]
+    fn literals() {
+        assert_eq!(de("1"), int(1));
+        assert_eq!(de("true"), Expr::Value(Value::Bool(true)));
+        assert_eq!(de("1 + 2"), bop(vm::BinaryOp::Add, int(1), int(2)));
+    }
+
+    #[test]
+    fn single_use_let_inlines() {
+        assert_eq!(de("let x = 1 in x + 2"), de("1 + 2"));
+    }
+
+    #[test]
+    fn unused_let_drops_value() {
+        assert_eq!(de("let x = 1 in 2"), int(2));
+        assert_eq!(de("let _ = 1 in 2"), int(2));
+    }
+
+    #[test]
+    fn double_use_let_dups() {
+        assert_eq!(
+            de("let x = 1 in x + x"),
+            dup(int(1), bop(vm::BinaryOp::Add, dp0(0), dp1(0)))
+        );
+    }
+
+    #[test]
+    fn triple_use_let_chains_dups() {
+        assert_eq!(
+            de("let x = 1 in x + (x + x)"),
+            dup(
+                int(1),
+                dup(
+                    dp1(0),
+                    bop(
+                        vm::BinaryOp::Add,
+                        dp0(1),
+                        bop(vm::BinaryOp::Add, dp0(0), dp1(0))
+                    )
+                )
+            )
+        );
+    }
+
+    #[test]
+    fn fn_binders_classify_by_use_count() {
+        // unused arg: an erasing binder
+        assert_eq!(de("let f x = 1 in f"), use_(int(1)));
+        // single use: a plain linear binder
+        assert_eq!(de("let f x = x in f"), lam(var(0)));
+        // double use: a dup chain over the argument
+        assert_eq!(
+            de("let f x = x + x in f"),
+            lam(dup(var(0), bop(vm::BinaryOp::Add, dp0(0), dp1(0))))
+        );
+    }
+
+    #[test]
+    fn multi_arg_fn_nests_lambdas() {
+        assert_eq!(
+            de("let add a b = a + b in add"),
+            lam(lam(bop(vm::BinaryOp::Add, var(1), var(0))))
+        );
+        assert_eq!(de("let add a b = a + b in add 1 2"), {
+            app(
+                app(lam(lam(bop(vm::BinaryOp::Add, var(1), var(0)))), int(1)),
+                int(2),
+            )
+        });
+    }
+
+    #[test]
+    fn fn_used_twice_dups_the_lambda() {
+        assert_eq!(
+            de("let id x = x in id (id 1)"),
+            dup(lam(var(0)), app(dp0(0), app(dp1(0), int(1))))
+        );
+    }
+
+    #[test]
+    fn if_else_is_a_bool_match() {
+        assert_eq!(
+            de("if true { 1 } else { 2 }"),
+            Expr::App {
+                func: Box::new(Expr::Mat {
+                    cases: vec![(Pat::Val(Value::Bool(true)), int(1))],
+                    default: Some(Box::new(use_(int(2)))),
+                }),
+                arg: Box::new(Expr::Value(Value::Bool(true))),
+            }
+        );
+    }
+
+    #[test]
+    fn match_lowers_cases_and_defaults() {
+        // literal case + wildcard default
+        assert_eq!(
+            de("match 1 { 1 => 10, _ => 20 }"),
+            Expr::App {
+                func: Box::new(Expr::Mat {
+                    cases: vec![(Pat::Val(Value::Int(1)), int(10))],
+                    default: Some(Box::new(use_(int(20)))),
+                }),
+                arg: Box::new(int(1)),
+            }
+        );
+        // identifier default binds the scrutinee
+        assert_eq!(
+            de("match 1 { 2 => 0, x => x + 1 }"),
+            Expr::App {
+                func: Box::new(Expr::Mat {
+                    cases: vec![(Pat::Val(Value::Int(2)), int(0))],
+                    default: Some(Box::new(lam(bop(vm::BinaryOp::Add, var(0), int(1))))),
+                }),
+                arg: Box::new(int(1)),
+            }
+        );
+        // a second default is never reached
+        assert_eq!(de("match 1 { x => x, _ => 2 }"), app(lam(var(0)), int(1)));
+        assert_eq!(
+            warnings("match 1 { x => x, _ => 2 }"),
+            ["unreachable pattern `_`: earlier patterns match everything it does"]
+        );
+    }
+
+    #[test]
+    fn nested_patterns_compile_to_nested_matches() {
+        let mat = |cases: Vec<(&str, Expr)>, default: Option<Expr>, scrut| Expr::App {
+            func: Box::new(Expr::Mat {
+                cases: cases
+                    .into_iter()
+                    .map(|(c, e)| (Pat::Ctr(c.into()), e))
+                    .collect(),
+                default: default.map(Box::new),
+            }),
+            arg: Box::new(scrut),
+        };
+        // The `_` arm is reached from both levels' defaults, so its body is
+        // lowered twice.
+        assert_eq!(
+            de_open("match v { Some(Cons(x, _)) => x, _ => 0 }"),
+            mat(
+                vec![(
+                    "Some",
+                    lam(mat(
+                        vec![("Cons", lam(use_(var(1))))],
+                        Some(use_(int(0))),
+                        var(0)
+                    ))
+                )],
+                Some(use_(int(0))),
+                Expr::Free("v".into())
+            )
+        );
+        // A literal under a constructor; `n` names the whole value, so it is
+        // tested once and also used by the last arm: a dup.
+        assert_eq!(
+            de_open("match v { Some(0) => 1, n => n + 1 }"),
+            app(
+                lam(dup(
+                    var(0),
+                    mat(
+                        vec![(
+                            "Some",
+                            lam(Expr::App {
+                                func: Box::new(Expr::Mat {
+                                    cases: vec![(Pat::Val(Value::Int(0)), int(1))],
+                                    default: Some(Box::new(use_(bop(
+                                        vm::BinaryOp::Add,
+                                        dp1(2),
+                                        int(1)
+                                    )))),
+                                }),
+                                arg: Box::new(var(0)),
+                            })
+                        )],
+                        Some(lam(bop(vm::BinaryOp::Add, var(0), int(1)))),
+                        dp0(0)
+                    )
+                )),
+                Expr::Free("v".into())
+            )
+        );
+        // Type ascriptions are not checked yet, just skipped.
+        assert_eq!(de("let f (x: Int) = x in f"), lam(var(0)));
+        assert_eq!(
+            de_open("{\nlet Some((a, b): Pair) = v\na - b\n}"),
+            de_open("{\nlet Some((a, b)) = v\na - b\n}")
+        );
+    }
+
+    #[test]
+    fn pattern_warnings_name_what_is_missed() {
+        assert_eq!(
+            warnings("match p { (true, x) => x }"),
+            ["patterns are not exhaustive: `(false, _)` not covered"]
+        );
+        assert_eq!(
+            warnings("{\nenum T { A, B, C(Int) }\nmatch C(1) { A => 1, C(0) => 2 }\n}"),
+            ["patterns are not exhaustive: `C(_)`, `B` not covered"]
+        );
+        assert_eq!(
+            warnings("{\nenum T { A, B }\nlet A = B\n1\n}"),
+            ["patterns are not exhaustive: `B` not covered"]
+        );
+        assert_eq!(
+            warnings("match p { (_, 1) => 1, (0, 1) => 2, (0, n) => n, _ => 3 }"),
+            ["unreachable pattern `(0, 1)`: earlier patterns match everything it does"]
+        );
+        // Variants of no enum in scope are taken to be all there are.
+        assert!(warnings("match v { Some(x) => x, None => 0 }").is_empty());
+        assert!(matches!(
+            de_err("match 1 { Some(x) => x, Some(x, y) => y }"),
+            DesugarErrorKind::Malformed(_)
+        ));
+    }
+
+    #[test]
+    fn ctor_match_arm_binds_fields() {
+        let e = de_open("match v { Some(x) => x + 1, None => 0 }");
+        let expected = Expr::App {
+            func: Box::new(Expr::Mat {
+                cases: vec![
+                    (
+                        Pat::Ctr("Some".into()),
+                        lam(bop(vm::BinaryOp::Add, var(0), int(1))),
+                    ),
+                    (Pat::Ctr("None".into()), int(0)),
+                ],
+                default: None,
+            }),
+            arg: Box::new(Expr::Free("v".into())),
+        };
+        assert_eq!(e, expected);
+    }
+
+    #[test]
+    fn enum_decl_binds_type_and_variants() {
+        let color = Expr::TypeDef {
+            name: Some("Color".into()),
+            kind: TypeDefKind::Sum(vec![("Red".into(), vec![]), ("Green".into(), vec![])]),
+        };
+        // A single variant use inlines the type value into the Ctr.
+        assert_eq!(
+            de("{\nenum Color { Red, Green }\nRed\n}"),
+            Expr::Ctr {
+                ty: Box::new(color.clone()),
+                variant: Some("Red".into()),
+            }
+        );
+        // Two uses share one lowered TypeDef through a dup.
+        assert_eq!(
+            de("{\nenum Color { Red, Green }\nif true { Red } else { Green }\n}"),
+            Expr::App {
+                func: Box::new(Expr::Mat {
+                    cases: vec![(
+                        Pat::Val(Value::Bool(true)),
+                        Expr::Ctr {
+                            ty: Box::new(dp0(0)),
+                            variant: Some("Red".into()),
+                        }
+                    )],
+                    default: Some(Box::new(use_(Expr::Ctr {
+                        ty: Box::new(dp1(1)),
+                        variant: Some("Green".into()),
+                    }))),
+                }),
+                arg: Box::new(Expr::Value(Value::Bool(true))),
+            }
+            .pipe(|body| dup(color.clone(), body))
+        );
+        // The bare enum name is the type value itself.
+        assert_eq!(de("{\nenum Color { Red }\nColor\n}"), color_single());
+        // Variant argument types resolve as free names, but for the scalar
+        // types, which are placeholders.
+        assert_eq!(
+            de_open("{\nenum Opt { None, Some(Thing), Num(Int) }\nOpt\n}"),
+            Expr::TypeDef {
+                name: Some("Opt".into()),
+                kind: TypeDefKind::Sum(vec![
+                    ("None".into(), vec![]),
+                    ("Some".into(), vec![Expr::Free("Thing".into())]),
+                    ("Num".into(), vec![placeholder_type()]),
+                ]),
+            }
+        );
+        // As is the enum's own name, in its variants.
+        assert_eq!(
+            de("{\nenum L { Nil, Cons(Int, L) }\nL\n}"),
+            Expr::TypeDef {
+                name: Some("L".into()),
+                kind: TypeDefKind::Sum(vec![
+                    ("Nil".into(), vec![]),
+                    ("Cons".into(), vec![placeholder_type(), placeholder_type()]),
+                ]),
+            }
+        );
+    }
+
+    fn color_single() -> Expr {
+        Expr::TypeDef {
+            name: Some("Color".into()),
+            kind: TypeDefKind::Sum(vec![("Red".into(), vec![])]),
+        }
+    }
+
+    // tiny pipe helper so expected shapes read outside-in
+    trait Pipe: Sized {
+        fn pipe<R>(self, f: impl FnOnce(Self) -> R) -> R {
+            f(self)
+        }
+    }
+    impl Pipe for Expr {}
+
+    #[test]
+    fn ctor_args_apply() {
+        assert_eq!(
+            de("{\nenum Opt { None, Some }\nSome(1)\n}"),
+            app(
+                Expr::Ctr {
+                    ty: Box::new(Expr::TypeDef {
+                        name: Some("Opt".into()),
+                        kind: TypeDefKind::Sum(vec![
+                            ("None".into(), vec![]),
+                            ("Some".into(), vec![]),
+                        ]),
+                    }),
+                    variant: Some("Some".into()),
+                },
+                int(1)
+            )
+        );
+    }
+
+    #[test]
+    fn unknown_names_free_when_open_error_when_closed() {
+        assert_eq!(de_open("foo"), Expr::Free("foo".into()));
+        assert_eq!(de_open("Foo"), Expr::Free("Foo".into()));
+        assert_eq!(de_open("Foo(1)"), app(Expr::Free("Foo".into()), int(1)));
+        assert_eq!(
+            de_err("foo"),
+            DesugarErrorKind::UnboundVariable("foo".into())
+        );
+        assert_eq!(
+            de_err("Foo"),
+            DesugarErrorKind::UnboundConstructor("Foo".into())
+        );
+    }
+
+    #[test]
+    fn seeded_ctors_resolve_to_free_enum() {
+        let mut globals = Globals::default();
+        globals.ctors.insert("Red".to_string(), "Color".to_string());
+        let e = parse_expr("Red").unwrap();
+        assert_eq!(
+            lower_expr_open(&e, &globals, &Inferred::default(), "", &mut Vec::new()).unwrap(),
+            Expr::Ctr {
+                ty: Box::new(Expr::Free("Color".into())),
+                variant: Some("Red".into()),
+            }
+        );
+    }
+
+    #[test]
+    fn module_paths_resolve_with_visibility() {
+        let items = |names: &[(&str, bool)]| {
+            names
+                .iter()
+                .map(|&(n, public)| (n.to_string(), public))
+                .collect::<HashMap<_, _>>()
+        };
+        let globals = Globals {
+            ctors: HashMap::from([("geo::Circle".to_string(), "geo::Shape".to_string())]),
+            modules: HashMap::from([
+                (
+                    "geo".to_string(),
+                    items(&[
+                        ("area", true),
+                        ("secret", false),
+                        ("Shape", true),
+                        ("Circle", true),
+                        ("inner", false),
+                    ]),
+                ),
+                ("geo::inner".to_string(), items(&[("deep", true)])),
+            ]),
+            ..Globals::default()
+        };
+        let lower = |module: &str, src: &str| {
+            let e = parse_expr(src).unwrap();
+            lower_expr_open(&e, &globals, &Inferred::default(), module, &mut Vec::new())
+                .map_err(|e| e.kind)
+        };
+        let free = |name: &str| Ok(Expr::Free(name.into()));
+
+        assert_eq!(lower("", "geo::area"), free("geo::area"));
+        assert_eq!(lower("", "x"), free("x"));
+        assert_eq!(
+            lower("", "geo::secret"),
+            Err(DesugarErrorKind::Private("geo::secret".into()))
+        );
+        assert_eq!(
+            lower("", "geo::inner::deep"),
+            Err(DesugarErrorKind::Private("geo::inner".into()))
+        );
+        assert_eq!(
+            lower("", "geo::nope"),
+            Err(DesugarErrorKind::UnboundVariable("geo::nope".into()))
+        );
+        // Inside a module, its own items (private or not) go bare, and so do
+        // those of the modules around it.
+        assert_eq!(lower("geo", "secret"), free("geo::secret"));
+        assert_eq!(lower("geo", "inner::deep"), free("geo::inner::deep"));
+        assert_eq!(lower("geo::inner", "area"), free("geo::area"));
+        assert_eq!(lower("geo::inner", "geo::secret"), free("geo::secret"));
+
+        let circle = Expr::Ctr {
+            ty: Box::new(Expr::Free("geo::Shape".into())),
+            variant: Some("Circle".into()),
+        };
+        assert_eq!(lower("", "geo::Circle(1)"), Ok(app(circle.clone(), int(1))));
+        assert_eq!(lower("geo", "Circle(1)"), Ok(app(circle, int(1))));
+        assert_eq!(lower("", "geo::Shape"), free("geo::Shape"));
+        // A match on every variant of a module's enum is exhaustive.
+        let e = parse_expr("match s { geo::Circle(r) => r }").unwrap();
+        let mut warnings = Vec::new();
+        assert_eq!(
+            lower_expr_open(&e, &globals, &Inferred::default(), "", &mut warnings).unwrap(),
+            app(
+                Expr::Mat {
+                    cases: vec![(Pat::Ctr("Circle".into()), lam(var(0)))],
+                    default: None,
+                },
+                Expr::Free("s".into())
+            )
+        );
+        assert!(warnings.is_empty(), "{warnings:?}");
+
+        assert!(matches!(
+            de_err("{\nmod m {}\n1\n}"),
+            DesugarErrorKind::Unsupported(_)
+        ));
+    }
+
+    /// A match taking `scrut` apart with the tuple-case `branch`.
+    fn untuple(scrut: Expr, branch: Expr) -> Expr {
+        Expr::App {
+            func: Box::new(Expr::Mat {
+                cases: vec![(Pat::Ctr("New".into()), branch)],
+                default: None,
+            }),
+            arg: Box::new(scrut),
+        }
+    }
+
+    #[test]
+    fn tuples_are_anonymous_products() {
+        assert_eq!(de("(1, 2)"), pair(int(1), int(2)));
+        assert_eq!(
+            tuple_type(2),
+            atlas_core::core::ast::desugar(
+                &atlas_core::core::parse::parse("type (type (), type ())").unwrap()
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn tuple_patterns_bind_fields() {
+        let sub = |a, b| bop(vm::BinaryOp::Sub, a, b);
+        // match arm
+        assert_eq!(
+            de_open("match p { (a, b) => a - b }"),
+            untuple(Expr::Free("p".into()), lam(lam(sub(var(1), var(0)))))
+        );
+        // let in a block: the rest of the block is the branch
+        assert_eq!(
+            de("{\nlet (a, _) = (1, 2)\na + a\n}"),
+            untuple(
+                pair(int(1), int(2)),
+                Expr::Lam {
+                    body: Box::new(dup(
+                        var(0),
+                        Expr::Use {
+                            body: Box::new(bop(vm::BinaryOp::Add, dp0(1), dp1(1)))
+                        }
+                    ))
+                }
+            )
+        );
+        // fn argument: taken apart as soon as it is bound
+        assert_eq!(
+            de("let f (a, b) = a - b in f"),
+            lam(untuple(var(0), lam(lam(sub(var(1), var(0))))))
+        );
+        // a later binder of the same name shadows
+        assert_eq!(
+            de("let f (a, a) = a in f"),
+            lam(untuple(var(0), use_(lam(var(0)))))
+        );
+    }
+
+    #[test]
+    fn projection_takes_the_arity_it_is_known_by() {
+        // The branch keeps field 1 of 3 and erases the others.
+        let keep_second = use_(lam(use_(var(1))));
+        assert_eq!(
+            de("let t = (1, 2, 3) in t.1"),
+            untuple(
+                app(
+                    app(
+                        app(
+                            Expr::Ctr {
+                                ty: Box::new(tuple_type(3)),
+                                variant: None
+                            },
+                            int(1)
+                        ),
+                        int(2)
+                    ),
+                    int(3)
+                ),
+                keep_second
+            )
+        );
+        assert_eq!(
+            de("((1, 2), 3).0.1"),
+            untuple(
+                untuple(pair(pair(int(1), int(2)), int(3)), lam(use_(var(1)))),
+                use_(lam(var(0)))
+            )
+        );
+        // An argument's arity is what checking inferred from the calls.
+        assert_eq!(
+            de_checked("let f t = t.0 in f (1, 2)"),
+            app(
+                lam(untuple(var(0), lam(use_(var(1))))),
+                pair(int(1), int(2))
+            )
+        );
+        // Without a call to tell, it stays unknown.
+        assert!(matches!(
+            de_err("let f t = t.0 in f"),
+            DesugarErrorKind::Unsupported(_)
+        ));
+        // An out-of-range field is malformed.
+        assert!(matches!(de_err("(1, 2).2"), DesugarErrorKind::Malformed(_)));
+    }
+
+    /// `P::New` of a two-field struct declared inline (so inlined at its use).
+    fn new_pair_struct(a: Expr, b: Expr) -> Expr {
+        let ty = Expr::TypeDef {
+            name: Some("P".into()),
+            kind: TypeDefKind::Product(vec![placeholder_type(), placeholder_type()]),
+        };
+        let new = Expr::Ctr {
+            ty: Box::new(ty),
+            variant: None,
+        };
+        app(app(new, a), b)
+    }
+
+    #[test]
+    fn structs_construct_in_declared_order() {
+        let p = "struct P { x: Int, y: Bool }";
+        assert_eq!(
+            de(&format!("{{\n{p}\nP {{ y: true, x: 1 }}\n}}")),
+            new_pair_struct(int(1), Expr::Value(Value::Bool(true)))
+        );
+        // `x` alone is `x: x`.
+        assert_eq!(
+            de(&format!(
+                "let x = 1 in let y = false in {{\n{p}\nP {{ y, x }}\n}}"
+            )),
+            new_pair_struct(int(1), Expr::Value(Value::Bool(false)))
+        );
+        // Type parameters lower like the scalars, not to the values named
+        // like them.
+        assert_eq!(
+            de("let a = 1 in {\nstruct P { x: a, y: b }\nP { y: true, x: a }\n}"),
+            new_pair_struct(int(1), Expr::Value(Value::Bool(true)))
+        );
+        for body in [
+            "P { x: 1 }",
+            "P { x: 1, y: true, z: 2 }",
+            "P { x: 1, x: 2, y: true }",
+        ] {
+            assert!(
+                matches!(
+                    de_err(&format!("{{\n{p}\n{body}\n}}")),
+                    DesugarErrorKind::Malformed(_)
+                ),
+                "{body}"
+            );
+        }
+        assert!(matches!(
+            de_err("{\nstruct P { x: Int, x: Int }\nP\n}"),
+            DesugarErrorKind::Malformed(_)
+        ));
+    }
+
+    #[test]
+    fn struct_fields_project_by_name() {
+        assert_eq!(
+            de("{\nstruct P { x: Int, y: Int }\nlet p = P { y: 2, x: 1 }\np.y\n}"),
+            untuple(new_pair_struct(int(1), int(2)), use_(lam(var(0))))
+        );
+        assert!(matches!(
+            de_err("{\nstruct P { x: Int }\nlet p = P { x: 1 }\np.z\n}"),
+            DesugarErrorKind::Malformed(_)
+        ));
+    }
+
+    #[test]
+    fn struct_patterns_match_by_field_name() {
+        let globals = Globals {
+            ctors: HashMap::from([
+                ("Dot".to_string(), "Shape".to_string()),
+                ("Circle".to_string(), "Shape".to_string()),
+            ]),
+            fields: HashMap::from([
+                ("Circle".to_string(), vec!["r".to_string(), "c".to_string()]),
+                ("P".to_string(), vec!["x".to_string(), "y".to_string()]),
+            ]),
+            ..Globals::default()
+        };
+        let lower = |src: &str| {
+            let e = parse_expr(src).unwrap();
+            lower_expr_open(&e, &globals, &Inferred::default(), "", &mut Vec::new()).unwrap()
+        };
+        let matching = |cases| Expr::App {
+            func: Box::new(Expr::Mat {
+                cases,
+                default: None,
+            }),
+            arg: Box::new(Expr::Free("s".into())),
+        };
+        // A variant's case; a left-out field is not bound.
+        assert_eq!(
+            lower("match s { Circle { c } => c, Dot => 0 }"),
+            matching(vec![
+                (Pat::Ctr("Circle".into()), use_(lam(var(0)))),
+                (Pat::Ctr("Dot".into()), int(0)),
+            ])
+        );
+        // A struct's case is its product's.
+        assert_eq!(
+            lower("match s { P { y: b, x: a } => a - b }"),
+            matching(vec![(
+                Pat::Ctr("New".into()),
+                lam(lam(bop(vm::BinaryOp::Sub, var(1), var(0))))
+            )])
+        );
+        // Construction of a struct-like variant, in declared order.
+        assert_eq!(
+            lower("Circle { c: 1, r: 2 }"),
+            app(
+                app(
+                    Expr::Ctr {
+                        ty: Box::new(Expr::Free("Shape".into())),
+                        variant: Some("Circle".into()),
+                    },
+                    int(2)
+                ),
+                int(1)
+            )
+        );
+    }
+
+    #[test]
+    fn list_literal_matches_core_desugaring() {
+        let core_node = atlas_core::core::parse::parse("[1, 2]").unwrap();
+        let core_expr = atlas_core::core::ast::desugar_open(&core_node).unwrap();
+        assert_eq!(de_open("[1, 2]"), core_expr);
+    }
+
+    #[test]
+    fn y_combinator_matches_core_fix() {
+        let fix = atlas_core::core::ast::desugar(&atlas_core::core::ast::Node::Fix).unwrap();
+        assert_eq!(y_combinator(), fix);
+    }
+
+    #[test]
+    fn recursive_fn_wraps_in_y_combinator() {
+        // `f` used once in its own body: Y applied to \f -> \n -> f n
+        assert_eq!(
+            de("let f n = f n in f"),
+            app(y_combinator(), lam(lam(app(var(1), var(0)))))
+        );
+        // non-recursive fn: no Y
+        assert_eq!(de("let f n = n in f"), lam(var(0)));
+    }
+
+    #[test]
+    fn rec_groups_share_one_fixpoint() {
+        // `f` calls `g`: the tuple is Y (\self -> (\n -> self.1 n, \n -> n)),
+        // and `f` is used once after the group, as field 0 of the tuple.
+        let field = |t, i| product_match(t, field_branch(i, 2));
+        let fix = app(
+            y_combinator(),
+            lam(pair(lam(app(field(var(1), 1), var(0))), lam(var(0)))),
+        );
+        assert_eq!(
+            de("{\nrec {\nf n = g n\ng n = n\n}\nf\n}"),
+            app(lam(field(var(0), 0)), fix)
+        );
+        // No member used by another: no Y, and an unused group is dropped.
+        assert_eq!(
+            de("{\nrec {\nf n = n\n}\n1\n}"),
+            app(
+                use_(int(1)),
+                app(
+                    Expr::Ctr {
+                        ty: Box::new(tuple_type(1)),
+                        variant: None
+                    },
+                    lam(var(0))
+                )
+            )
+        );
+        // However densely the members call each other, the group is one Y.
+        let members = (0..6)
+            .map(|i| {
+                let calls = (0..6).map(|j| format!("a{j} n")).collect::<Vec<_>>();
+                format!("a{i} n = {}", calls.join(" + "))
+            })
+            .collect::<Vec<_>>();
+        let src = format!("{{\nrec {{\n{}\n}}\n1\n}}", members.join("\n"));
+        let Expr::App { func, arg } = de(&src) else {
+            panic!("expected the group applied to its body");
+        };
+        assert_eq!(*func, use_(int(1)));
+        assert!(matches!(*arg, Expr::App { func, .. } if *func == y_combinator()));
+        // An argument shadows a member within its own body.
+        assert_eq!(
+            de("{\nrec {\nf g = g\ng = 1\n}\n2\n}"),
+            app(use_(int(2)), pair(lam(var(0)), int(1)))
+        );
+        assert!(matches!(
+            de_err("{\nrec {\nf = 1\nf = 2\n}\nf\n}"),
+            DesugarErrorKind::Malformed(_)
+        ));
+        assert!(matches!(
+            de_err("{\nrec {}\n1\n}"),
+            DesugarErrorKind::Malformed(_)
+        ));
+
+        let d = match parse_repl("rec {\nf n = g n\ng n = f n\n}").unwrap() {
+            crate::ast::ReplInput::Declaration(d) => d,
+            other => panic!("expected a declaration, got {other:?}"),
+        };
+        let lowered = lower_decl_open(
+            &d,
+            &Globals::default(),
+            &Inferred::default(),
+            "m",
+            &mut Vec::new(),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(lowered.name, "rec f g");
+        let tuple = || Expr::Free("m::rec f g".into());
+        assert_eq!(
+            lowered.also,
+            vec![
+                ("f".to_string(), field(tuple(), 0)),
+                ("g".to_string(), field(tuple(), 1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn closures_lower_like_fns() {
+        let add = |l, r| bop(vm::BinaryOp::Add, l, r);
+        let mul = |l, r| bop(vm::BinaryOp::Mul, l, r);
+        assert_eq!(de("|x| x"), lam(var(0)));
+        assert_eq!(de("|x, _| x"), lam(use_(var(1))));
+        // Arguments are classified by their uses, patterns matched at once.
+        assert_eq!(de("|x| x * x"), lam(dup(var(0), mul(dp0(0), dp1(0)))));
+        assert_eq!(de("|(a, _)| a"), de("let f (a, _) = a in f"));
+        // Captures are uses of the enclosing binders.
+        assert_eq!(
+            de("let f y = |x| x + y in f"),
+            lam(lam(add(var(0), var(1))))
+        );
+        assert_eq!(
+            de("let f y = (|x| x + y, y) in f"),
+            lam(dup(var(0), pair(lam(add(var(0), dp0(1))), dp1(0))))
+        );
+        // An argument shadows a capture.
+        assert_eq!(de("let f x = |x| x in f"), use_(lam(var(0))));
+    }
+
+    #[test]
+    fn pipelines_and_partial_application_are_calls() {
+        let add = |l, r| bop(vm::BinaryOp::Add, l, r);
+        let inc = lam(add(var(0), int(1)));
+        assert_eq!(de("let f x = x + 1 in 2 |> f"), app(inc.clone(), int(2)));
+        assert_eq!(
+            de("2 |> (|x| x + 1) |> (|x| x + 1)"),
+            app(inc.clone(), app(inc, int(2)))
+        );
+        // `add 1` leaves a function of the other argument.
+        let plus = lam(lam(add(var(1), var(0))));
+        assert_eq!(
+            de("let add a b = a + b in 2 |> add 1"),
+            app(app(plus.clone(), int(1)), int(2))
+        );
+        assert_eq!(de("let add a b = a + b in add 1"), app(plus, int(1)));
+    }
+
+    #[test]
+    fn recursive_let_is_not_allowed() {
+        assert_eq!(
+            de_err("let x = x in x"),
+            DesugarErrorKind::UnboundVariable("x".into())
+        );
+    }
+
+    #[test]
+    fn shadowing_enums_rejected() {
+        for src in [
+            "{\nenum Color { Red }\nenum Color { Blue }\nColor\n}",
+            "{\nenum A { Red }\nenum B { Red }\nRed\n}",
+        ] {
+            assert!(matches!(de_err(src), DesugarErrorKind::Unsupported(_)));
+        }
+    }
+
+    #[test]
+    fn unsupported_constructs_error() {
+        for src in ["foo.bar", "()"] {
+            assert!(
+                matches!(de_err(src), DesugarErrorKind::Unsupported(_)),
+                "{src:?}"
+            );
+        }
+        assert!(matches!(
+            de_err("{\nlet x = 1\n}"),
+            DesugarErrorKind::Malformed(_)
+        ));
+    }
+
+    #[test]
+    fn traits_lower_to_dictionary_passing() {
+        let strings = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        let globals = Globals {
+            ctors: HashMap::from([("Red".to_string(), "Color".to_string())]),
+            fields: HashMap::from([
+                ("Eq".to_string(), strings(&["eq", "ne"])),
+                ("Show".to_string(), strings(&["show"])),
+            ]),
+            traits: HashMap::from([
+                (
+                    "Eq".to_string(),
+                    vec![("eq".to_string(), false), ("ne".to_string(), true)],
+                ),
+                ("Show".to_string(), vec![("show".to_string(), false)]),
+            ]),
+            methods: HashMap::from([
+                ("eq".to_string(), "Eq".to_string()),
+                ("ne".to_string(), "Eq".to_string()),
+                ("show".to_string(), "Show".to_string()),
+            ]),
+            bounds: HashMap::from([("all_eq".to_string(), vec![Some("Eq".to_string()), None])]),
+            ..Globals::default()
+        };
+        let lower = |src: &str| {
+            let e = parse_expr(src).unwrap();
+            lower_expr_open(&e, &globals, &Inferred::default(), "", &mut Vec::new())
+                .map_err(|e| e.kind)
+        };
+        let decl = |src: &str| {
+            let d = match parse_repl(src).unwrap() {
+                crate::ast::ReplInput::Declaration(d) => d,
+                other => panic!("expected a declaration, got {other:?}"),
+            };
+            lower_decl_open(&d, &globals, &Inferred::default(), "", &mut Vec::new())
+                .map(Option::unwrap)
+                .map_err(|e| e.kind)
+        };
+        // `m recv`: select `m` from one copy of the receiver's dictionary and
+        // apply it to the pair rebuilt around the other.
+        let call = |index: usize, receiver: Expr| {
+            let select = product_match(dp0(0), field_branch(index, 2));
+            let branch = lam(lam(dup(var(1), app(select, pair(dp1(0), var(1))))));
+            product_match(receiver, branch)
+        };
+        let dict = |ty: &str| Expr::Free(format!("impl Eq for {ty}"));
+
+        assert_eq!(
+            lower("eq 1 2"),
+            Ok(app(call(0, pair(dict("Int"), int(1))), int(2)))
+        );
+        assert_eq!(
+            lower("Red.ne Red"),
+            Ok(app(
+                call(1, pair(dict("Color"), lower("Red").unwrap())),
+                lower("Red").unwrap()
+            ))
+        );
+        assert_eq!(
+            lower("all_eq 1 2"),
+            Ok(app(
+                app(Expr::Free("all_eq".into()), pair(dict("Int"), int(1))),
+                int(2)
+            ))
+        );
+        // A local generic fn pairs up its argument the same way; its own
+        // argument is passed on as the pair it is.
+        let local = lam(app(call(0, var(0)), int(1)));
+        assert_eq!(
+            lower("{\nf (x: Eq) = eq x 1\nf 2\n}"),
+            Ok(app(local, pair(dict("Int"), int(2))))
+        );
+
+        let lowered = decl("trait Eq {\neq a b\nne a b = !(eq a b)\n}").unwrap();
+        assert_eq!(lowered.expr, tuple_type(2));
+        assert_eq!(
+            lowered.fields,
+            vec![("Eq".to_string(), strings(&["eq", "ne"]))]
+        );
+        assert_eq!(
+            lowered.methods,
+            vec![("eq".to_string(), false), ("ne".to_string(), true)]
+        );
+        let ne = lam(lam(Expr::Uop {
+            op: vm::UnaryOp::Not,
+            val: Box::new(app(call(0, var(1)), var(0))),
+        }));
+        assert_eq!(lowered.also, vec![("Eq.ne".to_string(), ne)]);
+
+        // An impl fills in the trait's default for what it leaves out; its
+        // methods see the receiver's value once it's unpaired.
+        let lowered = decl("impl Eq for Int {\neq a b = a == b\n}").unwrap();
+        assert_eq!(lowered.name, "impl Eq for Int");
+        let eq = lam(lam(bop(
+            vm::BinaryOp::Eq,
+            product_match(var(1), field_branch(1, 2)),
+            var(0),
+        )));
+        let new = Expr::Ctr {
+            ty: Box::new(Expr::Free("Eq".into())),
+            variant: None,
+        };
+        assert_eq!(lowered.expr, app(app(new, eq), Expr::Free("Eq.ne".into())));
+
+        let lowered = decl("all_eq (x: Eq) y = eq x y").unwrap();
+        assert_eq!(lowered.bounds, vec![Some("Eq".to_string()), None]);
+        assert!(decl("add a b = a + b").unwrap().bounds.is_empty());
+
+        let unsupported =
+            |r: Result<_, DesugarErrorKind>| matches!(r, Err(DesugarErrorKind::Unsupported(_)));
+        let malformed =
+            |r: Result<_, DesugarErrorKind>| matches!(r, Err(DesugarErrorKind::Malformed(_)));
+        // Methods and generic fns only lower as the head of a call.
+        assert!(unsupported(lower("eq")));
+        assert!(unsupported(lower("all_eq")));
+        // A closure can pass a method on, but can't take a trait-bound argument.
+        assert!(lower("|(x: Int)| eq x 1").is_ok());
+        assert!(unsupported(lower("|(x: Eq)| eq x 1")));
+        // The receiver's type must be known, and bound by the right trait.
+        assert!(unsupported(lower("eq x 1")));
+        assert!(unsupported(lower("{\nf (x: Show) = eq x 1\nf 2\n}")));
+        assert!(unsupported(lower("{\ntrait T {}\n1\n}")));
+        assert!(malformed(decl("impl Eq for Int {}")));
+        assert!(malformed(decl(
+            "impl Eq for Int {\neq a b = true\nshow a = 1\n}"
+        )));
+        assert!(malformed(decl("impl Nope for Int {}")));
+        assert_eq!(
+            decl("impl Eq for Nope {}"),
+            Err(DesugarErrorKind::UnboundVariable("Nope".into()))
+        );
+        assert!(malformed(decl("trait T {\nm\n}")));
+    }
+
+    #[test]
+    fn repl_decls_lower_open() {
+        let globals = Globals::default();
+        fn parse_decl(src: &str) -> crate::ast::Declaration<'_> {
+            match parse_repl(src).unwrap() {
+                crate::ast::ReplInput::Declaration(d) => d,
+                other => panic!("expected a declaration, got {other:?}"),
+            }
+        }
+
+        let d = parse_decl("add a b = a + b");
+        let lowered = lower_decl_open(&d, &globals, &Inferred::default(), "", &mut Vec::new())
+            .unwrap()
+            .unwrap();
+        assert_eq!(lowered.name, "add");
+        assert_eq!(
+            lowered.expr,
+            lam(lam(bop(vm::BinaryOp::Add, var(1), var(0))))
+        );
+        assert!(lowered.variants.is_empty());
+
+        let d = parse_decl("enum Color { Red, Green }");
+        let lowered = lower_decl_open(&d, &globals, &Inferred::default(), "", &mut Vec::new())
+            .unwrap()
+            .unwrap();
+        assert_eq!(lowered.name, "Color");
+        assert_eq!(
+            lowered.variants,
+            vec!["Red".to_string(), "Green".to_string()]
+        );
+
+        let d = parse_decl("struct P { x: Int, y: Int }");
+        let lowered = lower_decl_open(&d, &globals, &Inferred::default(), "", &mut Vec::new())
+            .unwrap()
+            .unwrap();
+        assert_eq!(lowered.name, "P");
+        assert_eq!(
+            lowered.fields,
+            vec![("P".to_string(), vec!["x".to_string(), "y".to_string()])]
+        );
+
+        let d = parse_decl("let _ = 1");
+        assert!(
+            lower_decl_open(&d, &globals, &Inferred::default(), "", &mut Vec::new())
+                .unwrap()
+                .is_none()
+        );
+    }
+}
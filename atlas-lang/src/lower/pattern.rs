@@ -0,0 +1,664 @@
+//! The pattern-match compiler: the arms of a `match` (or the one pattern of a
+//! block's `let` or a fn argument) lower to a decision tree of nested
+//! [`Expr::Mat`]s, each testing one sub-value of the scrutinee — an
+//! *occurrence* — against the constructors and literals its patterns mention.
+//! Columns are picked left to right, the first one the first remaining arm
+//! actually tests.
+//!
+//! Occurrences are ordinary binders, classified by use count like any other:
+//! the scrutinee (when it is a fn argument or tested more than once), the
+//! fields of a `Mat` case, and the default's rebinding of the value that failed
+//! every case. Their uses are the `Mat`s testing them and the uses of the
+//! pattern variables aliasing them. An arm reached along several paths has its
+//! body lowered once per path, so occurrence counting for names used in arm
+//! bodies goes through [`Lower::arm_paths`].
+
+use std::fmt;
+
+use atlas_core::core::expr::{Expr, Pat, Value};
+use atlas_core::error::{DesugarError, DesugarErrorKind};
+
+use super::{bare, check_fields, lit_value, restore, Binding, Lower, PRODUCT_CTOR};
+use crate::ast;
+
+/// A pattern match that lowers fine but is probably a mistake.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatternWarning {
+    /// Values like these (`_` standing for any value) match no pattern; the
+    /// lowered `Mat` has no case for them.
+    NonExhaustive(Vec<String>),
+    /// A pattern that only matches values an earlier one already matches.
+    Unreachable(String),
+}
+
+impl fmt::Display for PatternWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternWarning::NonExhaustive(missing) => {
+                let missing: Vec<_> = missing.iter().map(|m| format!("`{m}`")).collect();
+                write!(
+                    f,
+                    "patterns are not exhaustive: {} not covered",
+                    missing.join(", ")
+                )
+            }
+            PatternWarning::Unreachable(p) => write!(
+                f,
+                "unreachable pattern `{p}`: earlier patterns match everything it does"
+            ),
+        }
+    }
+}
+
+/// The match a lowered pattern tests: the scrutinee is either a lowered value,
+/// or the argument of a lambda wrapped around the match (a fn argument).
+pub(super) enum Scrutinee {
+    Value(Expr),
+    Arg,
+}
+
+/// A sub-value of the scrutinee; the scrutinee itself is `0`.
+type Occ = usize;
+
+/// What one `Mat` case tests for.
+#[derive(Debug, Clone, PartialEq)]
+enum Head<'a> {
+    Variant(&'a str),
+    /// a tuple's or struct's `New`
+    Product,
+    Lit(Value),
+}
+
+/// A pattern reduced to the tests it makes.
+#[derive(Debug, Clone)]
+enum Simple<'a> {
+    /// anything, binding the name if there is one
+    Any(Option<&'a str>),
+    Ctr(Head<'a>, Vec<Simple<'a>>),
+}
+
+/// One arm's row of the pattern matrix: a pattern per column still to test.
+struct Row<'a> {
+    pats: Vec<Simple<'a>>,
+    arm: usize,
+    /// names bound by the columns already tested, to what they hold
+    binds: Vec<(&'a str, Occ)>,
+}
+
+enum Tree<'a> {
+    /// the arm's body, with its pattern's names aliasing occurrences
+    Leaf {
+        arm: usize,
+        binds: Vec<(&'a str, Occ)>,
+    },
+    Switch(Switch<'a>),
+}
+
+/// A `Mat` on `occ`: a case per head, binding its fields as new occurrences,
+/// and a default rebinding the whole value as another. There is no default
+/// when the heads cover every value, or when no arm matches the rest.
+struct Switch<'a> {
+    occ: Occ,
+    cases: Vec<(Head<'a>, Vec<Occ>, Tree<'a>)>,
+    default: Option<(Occ, Box<Tree<'a>>)>,
+}
+
+struct Compiled<'a> {
+    tree: Tree<'a>,
+    /// number of occurrences, the scrutinee included
+    occs: usize,
+    /// per arm: whether any path leads to it
+    reached: Vec<bool>,
+    /// values no path matches
+    missing: Vec<String>,
+}
+
+/// What a path through the tree has learnt about one occurrence.
+enum Step<'a> {
+    /// it was taken apart by this case
+    Case(Head<'a>, Vec<Occ>),
+    /// it failed these cases (so the path is in the default)
+    Not(Vec<Head<'a>>),
+}
+
+struct Compiler<'l, 'a> {
+    lower: &'l Lower<'a>,
+    next: Occ,
+    reached: Vec<bool>,
+    missing: Vec<String>,
+    path: Vec<(Occ, Step<'a>)>,
+}
+
+impl<'a> Compiler<'_, 'a> {
+    fn fresh(&mut self) -> Occ {
+        self.next += 1;
+        self.next - 1
+    }
+
+    /// The decision tree for `rows` (never empty) over `occs`, one per column.
+    fn compile(&mut self, occs: Vec<Occ>, rows: Vec<Row<'a>>) -> Result<Tree<'a>, DesugarError> {
+        let Some(col) = rows[0]
+            .pats
+            .iter()
+            .position(|p| matches!(p, Simple::Ctr(..)))
+        else {
+            // The first row matches whatever reaches it.
+            let row = rows.into_iter().next().unwrap();
+            self.reached[row.arm] = true;
+            let mut binds = row.binds;
+            for (p, &occ) in row.pats.iter().zip(&occs) {
+                if let Simple::Any(Some(name)) = p {
+                    binds.push((*name, occ));
+                }
+            }
+            return Ok(Tree::Leaf {
+                arm: row.arm,
+                binds,
+            });
+        };
+        let occ = occs[col];
+        let mut heads: Vec<(Head<'a>, usize)> = Vec::new();
+        for row in &rows {
+            if let Simple::Ctr(head, subs) = &row.pats[col] {
+                match heads.iter().find(|(h, _)| h == head) {
+                    Some((_, arity)) if *arity != subs.len() => {
+                        return Err(DesugarError::malformed(format!(
+                            "`{}` is matched with {arity} and with {} fields",
+                            show_head(self.lower, head, &[]),
+                            subs.len()
+                        )));
+                    }
+                    Some(_) => {}
+                    None => heads.push((head.clone(), subs.len())),
+                }
+            }
+        }
+
+        let mut cases = Vec::with_capacity(heads.len());
+        for (head, arity) in &heads {
+            let fields: Vec<Occ> = (0..*arity).map(|_| self.fresh()).collect();
+            let sub_rows = rows
+                .iter()
+                .filter_map(|row| {
+                    let (subs, name) = match &row.pats[col] {
+                        Simple::Ctr(h, subs) if h == head => (subs.clone(), None),
+                        Simple::Ctr(..) => return None,
+                        Simple::Any(name) => (vec![Simple::Any(None); *arity], *name),
+                    };
+                    Some(Row {
+                        pats: splice(&row.pats, col, subs),
+                        arm: row.arm,
+                        binds: row
+                            .binds
+                            .iter()
+                            .copied()
+                            .chain(name.map(|n| (n, occ)))
+                            .collect(),
+                    })
+                })
+                .collect();
+            self.path
+                .push((occ, Step::Case(head.clone(), fields.clone())));
+            let tree = self.compile(splice(&occs, col, fields.clone()), sub_rows);
+            self.path.pop();
+            cases.push((head.clone(), fields, tree?));
+        }
+
+        let heads: Vec<Head<'a>> = heads.into_iter().map(|(h, _)| h).collect();
+        let complete = self.lower.complete(&heads);
+        if complete == Some(true) {
+            return Ok(Tree::Switch(Switch {
+                occ,
+                cases,
+                default: None,
+            }));
+        }
+        let rest: Vec<_> = rows
+            .into_iter()
+            .filter(|row| matches!(row.pats[col], Simple::Any(_)))
+            .collect();
+        self.path.push((occ, Step::Not(heads)));
+        let default = if rest.is_empty() {
+            // Unknown variants are trusted to be all there are.
+            if complete == Some(false) {
+                let w = self.witness(0);
+                self.missing.push(w);
+            }
+            Ok(None)
+        } else {
+            let d = self.fresh();
+            let rows = rest
+                .into_iter()
+                .map(|mut row| {
+                    if let Simple::Any(Some(name)) = row.pats.remove(col) {
+                        row.binds.push((name, d));
+                    }
+                    row
+                })
+                .collect();
+            let occs = splice(&occs, col, Vec::new());
+            self.compile(occs, rows).map(|t| Some((d, Box::new(t))))
+        };
+        self.path.pop();
+        Ok(Tree::Switch(Switch {
+            occ,
+            cases,
+            default: default?,
+        }))
+    }
+
+    /// An example of the values reaching the current path, as far as `occ` is
+    /// concerned.
+    fn witness(&self, occ: Occ) -> String {
+        match self.path.iter().rev().find(|(o, _)| *o == occ) {
+            None => "_".to_string(),
+            Some((_, Step::Case(head, fields))) => {
+                let fields: Vec<_> = fields.iter().map(|f| self.witness(*f)).collect();
+                show_head(self.lower, head, &fields)
+            }
+            Some((_, Step::Not(heads))) => self.lower.missing_head(heads),
+        }
+    }
+}
+
+/// State threaded through emitting one tree.
+struct Emit<'e, 'a> {
+    /// uses of each occurrence
+    counts: Vec<usize>,
+    /// each occurrence's binder, once emitted (none for an unused one)
+    occs: Vec<Option<Binding<'a>>>,
+    body: &'e mut dyn FnMut(&mut Lower<'a>, usize) -> Result<Expr, DesugarError>,
+}
+
+impl<'a> Lower<'a> {
+    /// Lower a match of `scrut` against `pats`, one per arm in order. `uses`
+    /// counts a name's uses in an arm's body, and `body` lowers it.
+    pub(super) fn match_patterns(
+        &mut self,
+        scrut: Scrutinee,
+        pats: &[&'a ast::Pattern<'a>],
+        uses: &dyn Fn(&Self, usize, &str) -> usize,
+        body: &mut dyn FnMut(&mut Self, usize) -> Result<Expr, DesugarError>,
+    ) -> Result<Expr, DesugarError> {
+        let compiled = self.compile_patterns(pats)?;
+        if !compiled.missing.is_empty() {
+            self.warn(PatternWarning::NonExhaustive(compiled.missing));
+        }
+        for (p, _) in pats.iter().zip(compiled.reached).filter(|(_, r)| !r) {
+            self.warn(PatternWarning::Unreachable(show_pattern(p)));
+        }
+        let tree = compiled.tree;
+        let mut em = Emit {
+            counts: vec![0; compiled.occs],
+            occs: vec![None; compiled.occs],
+            body,
+        };
+        self.count_occs(&tree, uses, &mut em.counts);
+        let n = em.counts[0];
+        match (scrut, &tree) {
+            // Tested once and used for nothing else: no binder needed.
+            (Scrutinee::Value(e), Tree::Switch(sw)) if n == 1 => {
+                self.emit_switch(sw, Some(e), &mut em)
+            }
+            (Scrutinee::Value(e), _) => {
+                let f = self.binder(n, |s, b| {
+                    em.occs[0] = b;
+                    s.emit_tree(&tree, &mut em)
+                })?;
+                Ok(Expr::App {
+                    func: Box::new(f),
+                    arg: Box::new(e),
+                })
+            }
+            (Scrutinee::Arg, _) => self.binder(n, |s, b| {
+                em.occs[0] = b;
+                s.emit_tree(&tree, &mut em)
+            }),
+        }
+    }
+
+    /// How many paths through the match of `pats` lead to each arm (`0` for
+    /// an unreachable one). Counts `1` each if the patterns don't compile:
+    /// lowering will report that.
+    pub(super) fn arm_paths(&self, pats: &[&'a ast::Pattern<'a>]) -> Vec<usize> {
+        let Ok(compiled) = self.compile_patterns(pats) else {
+            return vec![1; pats.len()];
+        };
+        let mut paths = vec![0; pats.len()];
+        count_leaves(&compiled.tree, &mut paths);
+        paths
+    }
+
+    fn warn(&mut self, w: PatternWarning) {
+        // An arm body lowered once per path repeats its own matches' warnings.
+        if !self.warnings.contains(&w) {
+            self.warnings.push(w);
+        }
+    }
+
+    fn compile_patterns(
+        &self,
+        pats: &[&'a ast::Pattern<'a>],
+    ) -> Result<Compiled<'a>, DesugarError> {
+        let mut c = Compiler {
+            lower: self,
+            next: 1,
+            reached: vec![false; pats.len()],
+            missing: Vec::new(),
+            path: Vec::new(),
+        };
+        let mut rows = Vec::with_capacity(pats.len());
+        for (arm, p) in pats.iter().enumerate() {
+            let mut p = self.simplify(p)?;
+            forget_shadowed(&mut p, &mut Vec::new());
+            rows.push(Row {
+                pats: vec![p],
+                arm,
+                binds: Vec::new(),
+            });
+        }
+        let tree = if rows.is_empty() {
+            c.missing.push("_".to_string());
+            Tree::Switch(Switch {
+                occ: 0,
+                cases: Vec::new(),
+                default: None,
+            })
+        } else {
+            c.compile(vec![0], rows)?
+        };
+        Ok(Compiled {
+            tree,
+            occs: c.next,
+            reached: c.reached,
+            missing: c.missing,
+        })
+    }
+
+    fn simplify(&self, p: &'a ast::Pattern<'a>) -> Result<Simple<'a>, DesugarError> {
+        let subs = |ps: &'a [ast::Pattern<'a>]| -> Result<Vec<_>, DesugarError> {
+            ps.iter().map(|p| self.simplify(p)).collect()
+        };
+        Ok(match p {
+            ast::Pattern::Identifier(name) => Simple::Any(Some(name)),
+            ast::Pattern::Wildcard => Simple::Any(None),
+            ast::Pattern::Typed(p, _) => self.simplify(p)?,
+            ast::Pattern::Literal(l) => Simple::Ctr(Head::Lit(lit_value(l)?), Vec::new()),
+            ast::Pattern::Constructor(name, ps) => {
+                Simple::Ctr(Head::Variant(self.variant_key(name)?), subs(ps)?)
+            }
+            ast::Pattern::Tuple(ps) => Simple::Ctr(Head::Product, subs(ps)?),
+            // A struct-like variant's fields, or a struct's (its product's), in
+            // declared order; a left-out field matches anything.
+            ast::Pattern::Struct(name, given) => {
+                let fields = self.fields_of(name)?;
+                check_fields(name, &fields, given.iter().map(|(f, _)| *f))?;
+                let mut ps = Vec::with_capacity(fields.len());
+                for field in &fields {
+                    ps.push(match given.iter().find(|(f, _)| f == field) {
+                        Some((_, p)) => self.simplify(p)?,
+                        None => Simple::Any(None),
+                    });
+                }
+                let head = match self.global_key(&self.ctors, name)? {
+                    Some(key) => Head::Variant(key),
+                    None => Head::Product,
+                };
+                Simple::Ctr(head, ps)
+            }
+        })
+    }
+
+    /// The variant a constructor pattern names, by its key in the ctor map. An
+    /// unknown bare name stays as written, to match whatever carries it.
+    fn variant_key(&self, name: &'a str) -> Result<&'a str, DesugarError> {
+        match self.global_key(&self.ctors, name)? {
+            Some(key) => Ok(key),
+            None if name.contains("::") => Err(DesugarError::new(
+                DesugarErrorKind::UnboundConstructor(name.to_string()),
+            )),
+            None => Ok(name),
+        }
+    }
+
+    /// Whether `heads` (distinct, at least one) cover every value of their
+    /// type; `None` if they include variants of no enum in scope.
+    fn complete(&self, heads: &[Head<'a>]) -> Option<bool> {
+        match &heads[0] {
+            Head::Product => Some(true),
+            Head::Lit(Value::Bool(_)) => Some(heads.len() == 2),
+            Head::Lit(_) => Some(false),
+            Head::Variant(v) => {
+                let ty = self.ctors.get(v)?;
+                for h in heads {
+                    match h {
+                        Head::Variant(v) if self.ctors.get(v) == Some(ty) => {}
+                        _ => return None,
+                    }
+                }
+                Some(self.ctors.values().filter(|t| *t == ty).count() == heads.len())
+            }
+        }
+    }
+
+    /// A value none of `heads` matches, for a witness.
+    fn missing_head(&self, heads: &[Head<'a>]) -> String {
+        match &heads[0] {
+            Head::Lit(Value::Bool(b)) => (!b).to_string(),
+            Head::Variant(v) => {
+                let Some(ty) = self.ctors.get(v) else {
+                    return "_".to_string();
+                };
+                // The ctor map is unordered: report the first by name.
+                let mut others: Vec<&str> = self
+                    .ctors
+                    .iter()
+                    .filter(|(n, t)| *t == ty && !heads.contains(&Head::Variant(n)))
+                    .map(|(n, _)| *n)
+                    .collect();
+                others.sort_unstable();
+                match others.first() {
+                    Some(n) if self.fields.contains_key(n) => format!("{n} {{ .. }}"),
+                    Some(n) => n.to_string(),
+                    None => "_".to_string(),
+                }
+            }
+            Head::Product | Head::Lit(_) => "_".to_string(),
+        }
+    }
+
+    fn count_occs(
+        &self,
+        tree: &Tree<'a>,
+        uses: &dyn Fn(&Self, usize, &str) -> usize,
+        counts: &mut [usize],
+    ) {
+        match tree {
+            Tree::Leaf { arm, binds } => {
+                for (name, occ) in binds {
+                    counts[*occ] += uses(self, *arm, name);
+                }
+            }
+            Tree::Switch(sw) => {
+                counts[sw.occ] += 1;
+                for (_, _, t) in &sw.cases {
+                    self.count_occs(t, uses, counts);
+                }
+                if let Some((_, t)) = &sw.default {
+                    self.count_occs(t, uses, counts);
+                }
+            }
+        }
+    }
+
+    fn emit_tree(&mut self, tree: &Tree<'a>, em: &mut Emit<'_, 'a>) -> Result<Expr, DesugarError> {
+        let (arm, binds) = match tree {
+            Tree::Switch(sw) => return self.emit_switch(sw, None, em),
+            Tree::Leaf { arm, binds } => (*arm, binds),
+        };
+        let mut prevs = Vec::with_capacity(binds.len());
+        for (name, occ) in binds {
+            // An occurrence with no binder has no uses, so neither has `name`.
+            if let Some(b) = &em.occs[*occ] {
+                let prev = self.env.insert(*name, b.clone());
+                prevs.push((*name, prev, self.bind_shape(name, None)));
+            }
+        }
+        let r = (em.body)(self, arm);
+        for (name, prev, prev_shape) in prevs.into_iter().rev() {
+            restore(&mut self.env, name, prev);
+            restore(&mut self.shapes, name, prev_shape);
+        }
+        r
+    }
+
+    /// The `Mat` for `sw`, applied to `scrut` or, without one, to a use of the
+    /// tested occurrence.
+    fn emit_switch(
+        &mut self,
+        sw: &Switch<'a>,
+        scrut: Option<Expr>,
+        em: &mut Emit<'_, 'a>,
+    ) -> Result<Expr, DesugarError> {
+        let scrut = match scrut {
+            Some(e) => e,
+            None => {
+                let b = em.occs[sw.occ].clone();
+                self.use_binding(&b.expect("a tested occurrence has a binder"))
+            }
+        };
+        let mut cases = Vec::with_capacity(sw.cases.len());
+        for (head, fields, tree) in &sw.cases {
+            let key = match head {
+                Head::Variant(v) => Pat::Ctr(bare(v).to_string()),
+                Head::Product => Pat::Ctr(PRODUCT_CTOR.to_string()),
+                Head::Lit(v) => Pat::Val(v.clone()),
+            };
+            cases.push((key, self.emit_fields(fields, tree, em)?));
+        }
+        let default = match &sw.default {
+            Some((d, tree)) => Some(Box::new(self.binder(em.counts[*d], |s, b| {
+                em.occs[*d] = b;
+                s.emit_tree(tree, em)
+            })?)),
+            None => None,
+        };
+        Ok(Expr::App {
+            func: Box::new(Expr::Mat { cases, default }),
+            arg: Box::new(scrut),
+        })
+    }
+
+    /// A case's branch: a binder per field around its subtree.
+    fn emit_fields(
+        &mut self,
+        fields: &[Occ],
+        tree: &Tree<'a>,
+        em: &mut Emit<'_, 'a>,
+    ) -> Result<Expr, DesugarError> {
+        let Some((&f, rest)) = fields.split_first() else {
+            return self.emit_tree(tree, em);
+        };
+        self.binder(em.counts[f], |s, b| {
+            em.occs[f] = b;
+            s.emit_fields(rest, tree, em)
+        })
+    }
+}
+
+fn count_leaves(tree: &Tree, paths: &mut [usize]) {
+    match tree {
+        Tree::Leaf { arm, .. } => paths[*arm] += 1,
+        Tree::Switch(sw) => {
+            for (_, _, t) in &sw.cases {
+                count_leaves(t, paths);
+            }
+            if let Some((_, t)) = &sw.default {
+                count_leaves(t, paths);
+            }
+        }
+    }
+}
+
+/// A name bound twice in one pattern means its later (rightmost) binding:
+/// unbind the earlier ones.
+fn forget_shadowed<'a>(p: &mut Simple<'a>, seen: &mut Vec<&'a str>) {
+    match p {
+        Simple::Any(name) => {
+            if let Some(n) = *name {
+                if seen.contains(&n) {
+                    *name = None;
+                } else {
+                    seen.push(n);
+                }
+            }
+        }
+        Simple::Ctr(_, subs) => {
+            for sub in subs.iter_mut().rev() {
+                forget_shadowed(sub, seen);
+            }
+        }
+    }
+}
+
+/// `items` with the one at `at` replaced by `with`.
+fn splice<T: Clone>(items: &[T], at: usize, with: Vec<T>) -> Vec<T> {
+    let mut out = items[..at].to_vec();
+    out.extend(with);
+    out.extend_from_slice(&items[at + 1..]);
+    out
+}
+
+fn show_head(lower: &Lower, head: &Head, fields: &[String]) -> String {
+    match head {
+        Head::Variant(name) if fields.is_empty() => name.to_string(),
+        Head::Variant(name) => match lower.fields.get(name) {
+            Some(names) => {
+                let fields: Vec<_> = names
+                    .iter()
+                    .zip(fields)
+                    .map(|(n, f)| format!("{n}: {f}"))
+                    .collect();
+                format!("{name} {{ {} }}", fields.join(", "))
+            }
+            None => format!("{name}({})", fields.join(", ")),
+        },
+        Head::Product => format!("({})", fields.join(", ")),
+        Head::Lit(v) => match v {
+            Value::Int(i) => i.to_string(),
+            Value::Float(x) => x.to_string(),
+            Value::Bool(b) => b.to_string(),
+            Value::Char(c) => format!("{c:?}"),
+            Value::Str(s) => format!("{s:?}"),
+            Value::Bytes(_) => "_".to_string(),
+        },
+    }
+}
+
+fn show_pattern(p: &ast::Pattern) -> String {
+    let list = |ps: &[ast::Pattern]| ps.iter().map(show_pattern).collect::<Vec<_>>().join(", ");
+    match p {
+        ast::Pattern::Identifier(name) => name.to_string(),
+        ast::Pattern::Wildcard => "_".to_string(),
+        ast::Pattern::Literal(l) => match l {
+            ast::Literal::Integer(i) => i.to_string(),
+            ast::Literal::Float(x) => x.to_string(),
+            ast::Literal::Bool(b) => b.to_string(),
+            ast::Literal::String(s) => format!("{s:?}"),
+            ast::Literal::Unit => "()".to_string(),
+        },
+        ast::Pattern::Constructor(name, ps) if ps.is_empty() => name.to_string(),
+        ast::Pattern::Constructor(name, ps) => format!("{name}({})", list(ps)),
+        ast::Pattern::Struct(name, fields) => {
+            let fields: Vec<_> = fields
+                .iter()
+                .map(|(f, p)| format!("{f}: {}", show_pattern(p)))
+                .collect();
+            format!("{name} {{ {} }}", fields.join(", "))
+        }
+        ast::Pattern::Tuple(ps) => format!("({})", list(ps)),
+        ast::Pattern::Typed(p, ast::Type::Identifier(ty)) => {
+            format!("{}: {ty}", show_pattern(p))
+        }
+    }
+}
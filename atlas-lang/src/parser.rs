@@ -1,5 +1,7 @@
+use atlas_core::core::span::SpanLog;
+use atlas_core::error::ParseError;
 use chumsky::extra;
-use chumsky::input::ValueInput;
+use chumsky::input::{MapExtra, ValueInput};
 use chumsky::pratt::*;
 use chumsky::prelude::*;
 use chumsky::span::SimpleSpan;
@@ -7,7 +9,22 @@ use chumsky::span::SimpleSpan;
 use crate::ast::*;
 use crate::lexer::{Lexer, Token};
 
-type ParserError<'tokens, 'src> = extra::Err<Rich<'tokens, Token<'src>>>;
+type ParserError<'tokens, 'src> = extra::Full<Rich<'tokens, Token<'src>>, SpanLog, ()>;
+
+/// Record the span of an expression just built, returning it. Every parser
+/// that builds an [`Expr`] (rather than passing one through) goes through
+/// this, so that the [`SpanLog`] lines up with the tree (see [`ExprSpans`]).
+fn spanned<'tokens, 'src: 'tokens, I>(
+    expr: Expr<'src>,
+    e: &mut MapExtra<'tokens, '_, I, ParserError<'tokens, 'src>>,
+) -> Expr<'src>
+where
+    I: ValueInput<'tokens, Token = Token<'src>, Span = SimpleSpan>,
+{
+    let span = e.span().into_range();
+    e.state().push(span);
+    expr
+}
 
 /// Literal atoms (everything except the `()` unit, which is handled inside the
 /// parenthesised-expression atom).
@@ -23,6 +40,7 @@ where
         Token::True => Literal::Bool(true),
         Token::False => Literal::Bool(false),
     }
+    .labelled("literal")
 }
 
 /// Type expressions. Currently only bare (type-)identifiers, mirroring the
@@ -36,6 +54,7 @@ where
         Token::Identifier(s) => Type::Identifier(s),
         Token::TypeIdentifier(s) => Type::Identifier(s),
     }
+    .labelled("type")
 }
 
 /// Patterns, used by `let`/`fn` bindings and `match` arms.
@@ -48,19 +67,39 @@ where
         let lparen = choice((just(Token::LParen), just(Token::SpacedLParen)));
         let lit = literal().map(Pattern::Literal);
         let wild = just(Token::Underscore).to(Pattern::Wildcard);
-        let ident = select! { Token::Identifier(s) => Pattern::Identifier(s) };
-        // Constructor: `Foo` or `Foo(p, q)`.
+        let ident =
+            select! { Token::Identifier(s) => Pattern::Identifier(s) }.labelled("identifier");
+        // Constructor: `Foo`, `Foo(p, q)`, or `Foo { x: p, y }` (a field
+        // without a pattern binds a name of its own).
+        let field = select! { Token::Identifier(s) => s }
+            .labelled("field name")
+            .then(just(Token::Colon).ignore_then(pattern.clone()).or_not())
+            .map(|(name, p)| (name, p.unwrap_or(Pattern::Identifier(name))));
         let ctor = select! { Token::TypeIdentifier(s) => s }
+            .labelled("constructor")
             .then(
-                pattern
-                    .clone()
-                    .separated_by(just(Token::Comma))
-                    .allow_trailing()
-                    .collect::<Vec<_>>()
-                    .delimited_by(just(Token::LParen), just(Token::RParen))
-                    .or_not(),
+                choice((
+                    pattern
+                        .clone()
+                        .separated_by(just(Token::Comma))
+                        .allow_trailing()
+                        .collect::<Vec<_>>()
+                        .delimited_by(just(Token::LParen), just(Token::RParen))
+                        .map(CtorBody::Tuple),
+                    field
+                        .separated_by(just(Token::Comma))
+                        .allow_trailing()
+                        .collect::<Vec<_>>()
+                        .delimited_by(just(Token::LBrace), just(Token::RBrace))
+                        .map(CtorBody::Struct),
+                ))
+                .or_not(),
             )
-            .map(|(name, args)| Pattern::Constructor(name, args.unwrap_or_default()));
+            .map(|(name, body)| match body {
+                None => Pattern::Constructor(name, Vec::new()),
+                Some(CtorBody::Tuple(args)) => Pattern::Constructor(name, args),
+                Some(CtorBody::Struct(fields)) => Pattern::Struct(name, fields),
+            });
         // Parenthesised: grouping `(p)` or a tuple `(p, q)` / `()`.
         let paren = pattern
             .clone()
@@ -121,7 +160,7 @@ where
             .map(|(decls, value)| ExprBlock { decls, value });
 
         let binding_tail = {
-            let ident = select! { Token::Identifier(s) => s };
+            let ident = select! { Token::Identifier(s) => s }.labelled("identifier");
             let fn_binding = ident
                 .then(pattern().repeated().at_least(1).collect::<Vec<_>>())
                 .then_ignore(just(Token::Equals))
@@ -162,43 +201,54 @@ where
             .then_ignore(newlines.clone())
             .then_ignore(just(Token::In))
             .then(expr.clone())
-            .map(|((first, rest), value)| {
+            .map_with(|((first, rest), value), e| {
                 let mut decls = Vec::with_capacity(rest.len() + 1);
                 decls.push(first);
                 decls.extend(rest);
-                Expr::Block(Box::new(ExprBlock {
+                let block = ExprBlock {
                     decls,
                     value: Some(value),
-                }))
-            });
+                };
+                spanned(Expr::Block(Box::new(block)), e)
+            })
+            .labelled("let expression")
+            .as_context();
 
         // --- ATOMS ---
-        let lit = literal().map(Expr::Literal);
+        let lit = literal().map_with(|lit, e| spanned(Expr::Literal(lit), e));
 
         // `foo` or a `foo::bar::baz` scope path.
         let path = select! { Token::Identifier(s) => s }
+            .labelled("identifier")
             .then(
                 just(Token::ColonColon)
                     .ignore_then(select! { Token::Identifier(s) => s })
                     .repeated()
                     .collect::<Vec<_>>(),
             )
-            .map(|(first, rest)| {
-                if rest.is_empty() {
+            .map_with(|(first, rest), e| {
+                let path = if rest.is_empty() {
                     Expr::Identifier(first)
                 } else {
                     let mut path = Vec::with_capacity(rest.len() + 1);
                     path.push(first);
                     path.extend(rest);
                     Expr::Scope(path)
-                }
+                };
+                spanned(path, e)
             });
 
-        // Constructor: `Foo`, `Foo(a, b)`, or `Foo { x: a, y: b }`.
+        // Constructor: `Foo`, `Foo(a, b)`, or `Foo { x: a, y }` (`y` alone is
+        // short for `y: y`).
         let field = select! { Token::Identifier(s) => s }
-            .then_ignore(just(Token::Colon))
-            .then(expr.clone());
+            .labelled("field name")
+            .then(just(Token::Colon).ignore_then(expr.clone()).or_not())
+            .map_with(|(name, value), e| {
+                let value = value.unwrap_or_else(|| spanned(Expr::Identifier(name), e));
+                (name, value)
+            });
         let ctor = select! { Token::TypeIdentifier(s) => s }
+            .labelled("constructor")
             .then(
                 choice((
                     field
@@ -214,13 +264,13 @@ where
                 ))
                 .or_not(),
             )
-            .map(|(name, body)| {
+            .map_with(|(name, body), e| {
                 let ctor = match body {
                     None => Constructor::Empty(name),
                     Some(CtorBody::Struct(fields)) => Constructor::Struct(name, fields),
                     Some(CtorBody::Tuple(args)) => Constructor::Tuple(name, args),
                 };
-                Expr::Constructor(ctor)
+                spanned(Expr::Constructor(ctor), e)
             });
 
         // Parenthesised: `()` unit, `(e)` grouping, or `(a, b)` tuple.
@@ -237,17 +287,15 @@ where
                     .or_not(),
             )
             .then_ignore(just(Token::RParen))
-            .map(|inner| match inner {
-                None => Expr::Literal(Literal::Unit),
-                Some(((first, rest), trailing)) => {
-                    if rest.is_empty() && trailing.is_none() {
-                        first
-                    } else {
-                        let mut fields = Vec::with_capacity(rest.len() + 1);
-                        fields.push(first);
-                        fields.extend(rest);
-                        Expr::Tuple(Tuple { fields })
-                    }
+            .map_with(|inner, e| match inner {
+                None => spanned(Expr::Literal(Literal::Unit), e),
+                // Only grouping: the content keeps its own span.
+                Some(((first, rest), None)) if rest.is_empty() => first,
+                Some(((first, rest), _)) => {
+                    let mut fields = Vec::with_capacity(rest.len() + 1);
+                    fields.push(first);
+                    fields.extend(rest);
+                    spanned(Expr::Tuple(Tuple { fields }), e)
                 }
             });
         let paren_arg = just(Token::SpacedLParen)
@@ -263,24 +311,22 @@ where
                     .or_not(),
             )
             .then_ignore(just(Token::RParen))
-            .map(|inner| match inner {
-                None => Expr::Literal(Literal::Unit),
-                Some(((first, rest), trailing)) => {
-                    if rest.is_empty() && trailing.is_none() {
-                        first
-                    } else {
-                        let mut fields = Vec::with_capacity(rest.len() + 1);
-                        fields.push(first);
-                        fields.extend(rest);
-                        Expr::Tuple(Tuple { fields })
-                    }
+            .map_with(|inner, e| match inner {
+                None => spanned(Expr::Literal(Literal::Unit), e),
+                // Only grouping: the content keeps its own span.
+                Some(((first, rest), None)) if rest.is_empty() => first,
+                Some(((first, rest), _)) => {
+                    let mut fields = Vec::with_capacity(rest.len() + 1);
+                    fields.push(first);
+                    fields.extend(rest);
+                    spanned(Expr::Tuple(Tuple { fields }), e)
                 }
             });
 
         let list = comma_exprs
             .clone()
             .delimited_by(just(Token::LBracket), just(Token::RBracket))
-            .map(|elems| Expr::List(List { elems }));
+            .map_with(|elems, e| spanned(Expr::List(List { elems }), e));
 
         let if_else = just(Token::If)
             .ignore_then(expr.clone())
@@ -293,13 +339,16 @@ where
                 expr.clone()
                     .delimited_by(just(Token::LBrace), just(Token::RBrace)),
             )
-            .map(|((cond, if_expr), else_expr)| {
-                Expr::IfElse(Box::new(IfElse {
+            .map_with(|((cond, if_expr), else_expr), e| {
+                let if_else = IfElse {
                     cond,
                     if_expr,
                     else_expr,
-                }))
-            });
+                };
+                spanned(Expr::IfElse(Box::new(if_else)), e)
+            })
+            .labelled("if expression")
+            .as_context();
 
         let arm = pattern()
             .then_ignore(just(Token::FatArrow))
@@ -313,7 +362,30 @@ where
                     .collect::<Vec<_>>()
                     .delimited_by(just(Token::LBrace), just(Token::RBrace)),
             )
-            .map(|(scrut, arms)| Expr::Match(Box::new(Match { scrut, arms })));
+            .map_with(|(scrut, arms), e| spanned(Expr::Match(Box::new(Match { scrut, arms })), e))
+            .labelled("match expression")
+            .as_context();
+
+        // `|x, (a, b)| e`, whose body reaches as far as an expression can. Like
+        // a fn's, the body is a block holding just `e`.
+        let closure = just(Token::Pipe)
+            .ignore_then(
+                pattern()
+                    .separated_by(just(Token::Comma))
+                    .at_least(1)
+                    .collect::<Vec<_>>(),
+            )
+            .then_ignore(just(Token::Pipe))
+            .then(expr.clone())
+            .map_with(|(params, value), e| {
+                let body = ExprBlock {
+                    decls: vec![],
+                    value: Some(value),
+                };
+                spanned(Expr::Closure(Box::new(Closure { params, body })), e)
+            })
+            .labelled("closure")
+            .as_context();
 
         let atom_no_block = choice((
             let_expr.clone(),
@@ -332,72 +404,92 @@ where
             if_else,
             match_expr,
             ctor,
-            block.clone().map(|b| Expr::Block(Box::new(b))),
+            block
+                .clone()
+                .map_with(|b, e| spanned(Expr::Block(Box::new(b)), e)),
+            closure,
             paren,
             list,
             path,
         ));
 
         // --- POSTFIX: call / project / index ---
-        let postfix = atom.foldl(
+        let postfix = atom.foldl_with(
             choice((
                 just(Token::Dot)
                     .ignore_then(select! { Token::Identifier(s) => s })
+                    .or(select! { Token::FieldIndex(i) => i })
                     .map(Postfix::Project),
                 expr.clone()
                     .delimited_by(just(Token::LBracket), just(Token::RBracket))
                     .map(Postfix::Index),
             ))
             .repeated(),
-            |lhs, post| match post {
-                Postfix::Project(field) => Expr::Project(Box::new(lhs), field),
-                Postfix::Index(idx) => Expr::Index(Box::new(lhs), Box::new(idx)),
+            |lhs, post, e| {
+                let expr = match post {
+                    Postfix::Project(field) => Expr::Project(Box::new(lhs), field),
+                    Postfix::Index(idx) => Expr::Index(Box::new(lhs), Box::new(idx)),
+                };
+                spanned(expr, e)
             },
         );
 
-        let postfix_arg = atom_no_block.foldl(
+        let postfix_arg = atom_no_block.foldl_with(
             choice((
                 just(Token::Dot)
                     .ignore_then(select! { Token::Identifier(s) => s })
+                    .or(select! { Token::FieldIndex(i) => i })
                     .map(Postfix::Project),
                 expr.clone()
                     .delimited_by(just(Token::LBracket), just(Token::RBracket))
                     .map(Postfix::Index),
             ))
             .repeated(),
-            |lhs, post| match post {
-                Postfix::Project(field) => Expr::Project(Box::new(lhs), field),
-                Postfix::Index(idx) => Expr::Index(Box::new(lhs), Box::new(idx)),
+            |lhs, post, e| {
+                let expr = match post {
+                    Postfix::Project(field) => Expr::Project(Box::new(lhs), field),
+                    Postfix::Index(idx) => Expr::Index(Box::new(lhs), Box::new(idx)),
+                };
+                spanned(expr, e)
             },
         );
 
         let app = postfix
             .clone()
             .then(postfix_arg.repeated().collect::<Vec<_>>())
-            .map(|(func, args)| {
+            .map_with(|(func, args), e| {
                 if args.is_empty() {
                     func
                 } else {
-                    Expr::Call(Box::new(func), args)
+                    spanned(Expr::Call(Box::new(func), args), e)
                 }
             });
 
         // --- PREFIX UNARY + INFIX (pratt precedence) ---
         let infix_op = |prec: u16, tok: Token<'src>, op: InfixOp| {
-            infix(left(prec), just(tok), move |l, _, r, _| Expr::Infix {
-                lhs: Box::new(l),
-                op,
-                rhs: Box::new(r),
+            infix(left(prec), just(tok), move |l, _, r, e| {
+                let infix = Expr::Infix {
+                    lhs: Box::new(l),
+                    op,
+                    rhs: Box::new(r),
+                };
+                spanned(infix, e)
             })
         };
         app.pratt((
-            prefix(10, just(Token::Minus), |_, e, _| Expr::Unary {
-                op: UnaryOp::Neg,
-                expr: Box::new(e),
+            prefix(10, just(Token::Minus), |_, operand, e| {
+                let unary = Expr::Unary {
+                    op: UnaryOp::Neg,
+                    expr: Box::new(operand),
+                };
+                spanned(unary, e)
             }),
-            prefix(10, just(Token::Bang), |_, e, _| Expr::Unary {
-                op: UnaryOp::Not,
-                expr: Box::new(e),
+            prefix(10, just(Token::Bang), |_, operand, e| {
+                let unary = Expr::Unary {
+                    op: UnaryOp::Not,
+                    expr: Box::new(operand),
+                };
+                spanned(unary, e)
             }),
             infix_op(8, Token::Caret, InfixOp::Xor),
             infix_op(7, Token::Star, InfixOp::Mul),
@@ -415,13 +507,18 @@ where
             infix_op(3, Token::Neq, InfixOp::Neq),
             infix_op(2, Token::AndAnd, InfixOp::And),
             infix_op(1, Token::OrOr, InfixOp::Or),
+            infix(left(0), just(Token::PipeGt), |x, _, f, e| {
+                spanned(Expr::Pipe(Box::new(x), Box::new(f)), e)
+            }),
         ))
     })
 }
 
-enum CtorBody<'src> {
-    Struct(Vec<(&'src str, Expr<'src>)>),
-    Tuple(Vec<Expr<'src>>),
+/// The parenthesised or braced part of a constructor, in an expression (`T`
+/// an expression) or a pattern (`T` a pattern).
+enum CtorBody<'src, T> {
+    Struct(Vec<(&'src str, T)>),
+    Tuple(Vec<T>),
 }
 
 /// Declarations, parameterised over the expression parser so the same grammar
@@ -436,8 +533,10 @@ where
 {
     recursive(|declaration| {
         let modifier = just(Token::Pub).to(Modifier::Pub).or_not();
-        let ident = select! { Token::Identifier(s) => s };
-        let type_ident = select! { Token::TypeIdentifier(s) => s };
+        let ident = select! { Token::Identifier(s) => s }.labelled("identifier");
+        // A declaration names an item of its own module, so not a path.
+        let type_ident =
+            select! { Token::TypeIdentifier(s) if !s.contains("::") => s }.labelled("type name");
 
         let let_decl = modifier
             .clone()
@@ -451,7 +550,9 @@ where
                     pattern,
                     value,
                 })
-            });
+            })
+            .labelled("let declaration")
+            .as_context();
 
         let fn_decl = modifier
             .clone()
@@ -469,7 +570,9 @@ where
                         value: Some(value),
                     },
                 })
-            });
+            })
+            .labelled("function declaration")
+            .as_context();
 
         // enum Variants
         let variant = type_ident
@@ -514,7 +617,9 @@ where
                     name,
                     variants,
                 })
-            });
+            })
+            .labelled("enum declaration")
+            .as_context();
 
         let struct_decl = modifier
             .clone()
@@ -535,23 +640,77 @@ where
                     name,
                     entries,
                 })
-            });
+            })
+            .labelled("struct declaration")
+            .as_context();
+
+        let block_of = |value| ExprBlock {
+            decls: vec![],
+            value: Some(value),
+        };
 
-        // Minimal placeholders: the AST only records a name (empty `{}` body).
+        let trait_method = ident
+            .then(pattern().repeated().collect::<Vec<_>>())
+            .then(just(Token::Equals).ignore_then(expr.clone()).or_not())
+            .map(move |((name, args), default)| TraitMethod {
+                name,
+                args,
+                default: default.map(block_of),
+            })
+            .labelled("method signature");
         let trait_decl = modifier
             .clone()
             .then_ignore(just(Token::Trait))
             .then(type_ident)
-            .then_ignore(just(Token::LBrace))
-            .then_ignore(just(Token::RBrace))
-            .map(|(modifier, name)| Declaration::Trait(TraitDecl { modifier, name }));
+            .then(braced_lines(trait_method))
+            .map(|((modifier, name), methods)| {
+                Declaration::Trait(TraitDecl {
+                    modifier,
+                    name,
+                    methods,
+                })
+            })
+            .labelled("trait declaration")
+            .as_context();
+
+        // A function in an impl or `rec` body, which has no modifier of its own.
+        let member_fn = ident
+            .then(pattern().repeated().collect::<Vec<_>>())
+            .then_ignore(just(Token::Equals))
+            .then(expr.clone())
+            .map(move |((name, args), value)| FnDecl {
+                modifier: None,
+                name,
+                args,
+                body: block_of(value),
+            })
+            .labelled("function declaration");
         let impl_decl = modifier
             .clone()
             .then_ignore(just(Token::Impl))
-            .then(type_ident)
-            .then_ignore(just(Token::LBrace))
-            .then_ignore(just(Token::RBrace))
-            .map(|(modifier, name)| Declaration::Impl(ImplDecl { modifier, name }));
+            // The trait may live in another module; the type is any type.
+            .then(select! { Token::TypeIdentifier(s) => s }.labelled("trait name"))
+            .then_ignore(just(Token::For))
+            .then(type_())
+            .then(braced_lines(member_fn.clone()))
+            .map(|(((modifier, trait_name), ty), methods)| {
+                Declaration::Impl(ImplDecl {
+                    modifier,
+                    trait_name,
+                    ty,
+                    methods,
+                })
+            })
+            .labelled("impl declaration")
+            .as_context();
+
+        let rec_decl = modifier
+            .clone()
+            .then_ignore(just(Token::Rec))
+            .then(braced_lines(member_fn))
+            .map(|(modifier, fns)| Declaration::Rec(RecDecl { modifier, fns }))
+            .labelled("rec declaration")
+            .as_context();
 
         let alias_decl = modifier
             .clone()
@@ -560,13 +719,15 @@ where
             .then_ignore(just(Token::Equals))
             .then(type_())
             .then_ignore(just(Token::Semicolon))
-            .map(|((modifier, lhs), rhs)| Declaration::Alias(AliasDecl { modifier, lhs, rhs }));
+            .map(|((modifier, lhs), rhs)| Declaration::Alias(AliasDecl { modifier, lhs, rhs }))
+            .labelled("type alias")
+            .as_context();
 
         let mod_decl = modifier
             .clone()
             .then_ignore(just(Token::Mod))
             .then(ident)
-            .then(
+            .then(choice((
                 just(Token::Newline)
                     .repeated()
                     .ignore_then(
@@ -578,19 +739,24 @@ where
                     )
                     .then_ignore(just(Token::Newline).repeated())
                     .delimited_by(just(Token::LBrace), just(Token::RBrace))
-                    .map(|decls| Module { decls }),
-            )
+                    .map(|decls| Some(Module { decls })),
+                // `mod foo;`: the body lives in `foo.at`.
+                just(Token::Semicolon).to(None),
+            )))
             .map(|((modifier, name), value)| {
                 Declaration::Mod(ModDecl {
                     modifier,
                     name,
                     value,
                 })
-            });
+            })
+            .labelled("module")
+            .as_context();
 
         choice((
             let_decl,
             fn_decl,
+            rec_decl,
             enum_decl,
             struct_decl,
             trait_decl,
@@ -601,6 +767,24 @@ where
     })
 }
 
+/// `{ item \n item .. }`: a trait or impl body, one method per line.
+fn braced_lines<'tokens, 'src: 'tokens, I, T>(
+    item: impl Parser<'tokens, I, T, ParserError<'tokens, 'src>> + Clone,
+) -> impl Parser<'tokens, I, Vec<T>, ParserError<'tokens, 'src>> + Clone
+where
+    I: ValueInput<'tokens, Token = Token<'src>, Span = SimpleSpan>,
+{
+    just(Token::Newline)
+        .repeated()
+        .ignore_then(
+            item.separated_by(just(Token::Newline).repeated().at_least(1))
+                .allow_trailing()
+                .collect::<Vec<_>>(),
+        )
+        .then_ignore(just(Token::Newline).repeated())
+        .delimited_by(just(Token::LBrace), just(Token::RBrace))
+}
+
 enum VariantBody<'src> {
     Struct(Vec<(&'src str, Type<'src>)>),
     Tuple(Vec<Type<'src>>),
@@ -640,44 +824,61 @@ where
         .then_ignore(just(Token::Newline).repeated())
 }
 
-fn join_errs<'a>(errs: impl IntoIterator<Item = Rich<'a, Token<'a>>>) -> String {
-    errs.into_iter()
-        .map(|e| e.to_string())
-        .collect::<Vec<_>>()
-        .join("\n")
+/// Parse a single source expression.
+pub fn parse_expr<'src>(input: &'src str) -> Result<Expr<'src>, ParseError> {
+    parse_expr_spanned(input).map(|(e, _)| e)
 }
 
-/// Parse a single source expression.
-pub fn parse_expr<'src>(input: &'src str) -> Result<Expr<'src>, String> {
+/// Like [`parse_expr`], but also return the spans of the tree's expressions,
+/// for [`ExprSpans`].
+pub fn parse_expr_spanned<'src>(input: &'src str) -> Result<(Expr<'src>, SpanLog), ParseError> {
     let stream = Lexer::new(input).into_stream();
-    just(Token::Newline)
+    let mut log = SpanLog::default();
+    let e = just(Token::Newline)
         .repeated()
         .ignore_then(expr())
         .then_ignore(just(Token::Newline).repeated())
         .then_ignore(end())
-        .parse(stream)
+        .parse_with_state(stream, &mut log)
         .into_result()
-        .map_err(join_errs)
+        .map_err(ParseError::from_rich)?;
+    Ok((e, log))
 }
 
 /// Parse a whole module.
-pub fn parse_module<'src>(input: &'src str) -> Result<Module<'src>, String> {
+pub fn parse_module<'src>(input: &'src str) -> Result<Module<'src>, ParseError> {
+    parse_module_spanned(input).map(|(m, _)| m)
+}
+
+/// Like [`parse_module`], but also return the spans of its expressions.
+pub fn parse_module_spanned<'src>(input: &'src str) -> Result<(Module<'src>, SpanLog), ParseError> {
     let stream = Lexer::new(input).into_stream();
-    module()
+    let mut log = SpanLog::default();
+    let m = module()
         .then_ignore(end())
-        .parse(stream)
+        .parse_with_state(stream, &mut log)
         .into_result()
-        .map_err(join_errs)
+        .map_err(ParseError::from_rich)?;
+    Ok((m, log))
 }
 
 /// Parse a single REPL entry (declaration or expression).
-pub fn parse_repl<'src>(input: &'src str) -> Result<ReplInput<'src>, String> {
+pub fn parse_repl<'src>(input: &'src str) -> Result<ReplInput<'src>, ParseError> {
+    parse_repl_spanned(input).map(|(r, _)| r)
+}
+
+/// Like [`parse_repl`], but also return the spans of its expressions.
+pub fn parse_repl_spanned<'src>(
+    input: &'src str,
+) -> Result<(ReplInput<'src>, SpanLog), ParseError> {
     let stream = Lexer::new(input).into_stream();
-    repl_input()
+    let mut log = SpanLog::default();
+    let r = repl_input()
         .then_ignore(end())
-        .parse(stream)
+        .parse_with_state(stream, &mut log)
         .into_result()
-        .map_err(join_errs)
+        .map_err(ParseError::from_rich)?;
+    Ok((r, log))
 }
 
 #[cfg(test)]
@@ -772,9 +973,186 @@ mod tests {
         assert!(parse_module("struct Point { x: Int, y: Int }").is_ok());
     }
 
+    #[test]
+    fn modules_and_paths() {
+        let m = parse_module("pub mod geo {\npub struct P { x: Int }\n}\nmod util;").unwrap();
+        match &m.decls[..] {
+            [Declaration::Mod(geo), Declaration::Mod(util)] => {
+                assert!(geo.value.as_ref().is_some_and(|m| m.decls.len() == 1));
+                assert!(util.value.is_none());
+            }
+            other => panic!("unexpected: {other:?}"),
+        }
+        assert!(matches!(
+            parse_expr("geo::util::area"),
+            Ok(Expr::Scope(path)) if path == ["geo", "util", "area"]
+        ));
+        assert!(matches!(
+            parse_expr("geo::Circle(1)"),
+            Ok(Expr::Constructor(Constructor::Tuple("geo::Circle", _)))
+        ));
+        let r = parse_expr("match s { geo::P { x } => x }");
+        assert!(
+            matches!(&r, Ok(Expr::Match(m)) if matches!(m.arms[0].pattern, Pattern::Struct("geo::P", _))),
+            "got {r:?}"
+        );
+        // Declared names are not paths.
+        assert!(parse_module("enum geo::Shape { Dot }").is_err());
+    }
+
+    #[test]
+    fn traits_and_impls() {
+        let m = parse_module(
+            "trait Eq {\neq a b\nne a b = !(eq a b)\n}\nimpl geo::Eq for Int {\neq a b = a == b\n}",
+        )
+        .unwrap();
+        match &m.decls[..] {
+            [Declaration::Trait(t), Declaration::Impl(i)] => {
+                assert_eq!(t.name, "Eq");
+                assert!(matches!(
+                    &t.methods[..],
+                    [
+                        TraitMethod {
+                            name: "eq",
+                            default: None,
+                            ..
+                        },
+                        TraitMethod {
+                            name: "ne",
+                            default: Some(_),
+                            ..
+                        },
+                    ]
+                ));
+                assert_eq!(t.methods[1].args.len(), 2);
+                assert_eq!(i.trait_name, "geo::Eq");
+                assert!(matches!(i.ty, Type::Identifier("Int")));
+                assert!(matches!(&i.methods[..], [FnDecl { name: "eq", .. }]));
+            }
+            other => panic!("unexpected: {other:?}"),
+        }
+        assert!(parse_module("trait Empty {}\nimpl Empty for Int {}").is_ok());
+        // An impl's methods need bodies.
+        assert!(parse_module("impl Eq for Int {\neq a b\n}").is_err());
+    }
+
+    #[test]
+    fn rec_groups() {
+        let m = parse_module("pub rec {\neven n = n == 0\nodd n = !(even n)\nones = 1\n}").unwrap();
+        match &m.decls[..] {
+            [Declaration::Rec(r)] => {
+                assert!(matches!(r.modifier, Some(Modifier::Pub)));
+                assert!(matches!(
+                    &r.fns[..],
+                    [
+                        FnDecl { name: "even", .. },
+                        FnDecl { name: "odd", .. },
+                        FnDecl { name: "ones", .. },
+                    ]
+                ));
+                assert!(r.fns[2].args.is_empty());
+            }
+            other => panic!("unexpected: {other:?}"),
+        }
+        // Members have no modifiers of their own.
+        assert!(parse_module("rec {\npub f n = n\n}").is_err());
+    }
+
+    #[test]
+    fn closures_and_pipelines() {
+        let r = parse_expr("|x, (a, b)| x + a");
+        match r {
+            Ok(Expr::Closure(c)) => {
+                assert!(matches!(
+                    &c.params[..],
+                    [Pattern::Identifier("x"), Pattern::Tuple(_)]
+                ));
+                assert!(c.body.decls.is_empty());
+                assert!(matches!(c.body.value, Some(Expr::Infix { .. })));
+            }
+            other => panic!("unexpected: {other:?}"),
+        }
+        // A braced body stays a block expression of its own.
+        let r = parse_expr("|x| {\nlet y = x\ny\n}");
+        assert!(
+            matches!(&r, Ok(Expr::Closure(c))
+                if c.body.decls.is_empty() && matches!(c.body.value, Some(Expr::Block(_)))),
+            "got {r:?}"
+        );
+        // `|>` binds loosest and associates to the left.
+        let r = parse_expr("xs |> map (|x| x) |> len");
+        match r {
+            Ok(Expr::Pipe(x, len)) => {
+                assert!(matches!(*len, Expr::Identifier("len")));
+                match *x {
+                    Expr::Pipe(xs, map) => {
+                        assert!(matches!(*xs, Expr::Identifier("xs")));
+                        assert!(
+                            matches!(&*map, Expr::Call(_, args) if matches!(args[..], [Expr::Closure(_)]))
+                        );
+                    }
+                    other => panic!("unexpected: {other:?}"),
+                }
+            }
+            other => panic!("unexpected: {other:?}"),
+        }
+        let r = parse_expr("a + 1 |> f");
+        assert!(
+            matches!(&r, Ok(Expr::Pipe(x, _)) if matches!(**x, Expr::Infix { .. })),
+            "got {r:?}"
+        );
+        assert!(parse_expr("|| 1").is_err());
+    }
+
     #[test]
     fn match_arms() {
         let r = parse_expr("match x { Foo(a) => a, _ => 0 }");
         assert!(matches!(r, Ok(Expr::Match(_))), "got {r:?}");
     }
+
+    #[test]
+    fn struct_fields_may_be_punned() {
+        let r = parse_expr("P { x, y: 2 }");
+        match r {
+            Ok(Expr::Constructor(Constructor::Struct("P", fields))) => {
+                assert!(matches!(fields[0], ("x", Expr::Identifier("x"))));
+                assert!(matches!(
+                    fields[1],
+                    ("y", Expr::Literal(Literal::Integer(2)))
+                ));
+            }
+            other => panic!("unexpected: {other:?}"),
+        }
+        let r = parse_expr("match s { Circle { r, c: _ } => r }");
+        match r {
+            Ok(Expr::Match(m)) => match &m.arms[0].pattern {
+                Pattern::Struct("Circle", fields) => {
+                    assert!(matches!(fields[0], ("r", Pattern::Identifier("r"))));
+                    assert!(matches!(fields[1], ("c", Pattern::Wildcard)));
+                }
+                other => panic!("unexpected pattern: {other:?}"),
+            },
+            other => panic!("unexpected: {other:?}"),
+        }
+        // Not a field list: a constructor followed by a block.
+        assert!(parse_expr("if x { Red } else { Blue }").is_ok());
+    }
+
+    #[test]
+    fn parse_errors_name_the_declaration_they_are_in() {
+        let src = "struct P { x: Int, y }";
+        let e = parse_module(src).unwrap_err();
+        let [err] = &e.errors[..] else {
+            panic!("{e:?}")
+        };
+        assert_eq!(err.found.as_deref(), Some("}"));
+        assert_eq!(err.expected, ["':'"]);
+        assert_eq!(err.contexts, [("struct declaration".to_string(), 0..20)]);
+        let report = e.render("p.at", src, atlas_core::error::ReportStyle::Plain);
+        assert!(report.contains("p.at:1:22"), "{report}");
+        assert!(
+            report.contains("while parsing this struct declaration"),
+            "{report}"
+        );
+    }
 }
@@ -2,7 +2,7 @@
 
 use std::borrow::Cow;
 
-use atlas_core::extension::{Extensions, Handle, PrimReduce};
+use atlas_core::extension::{Extensions, Handle, PrimError, PrimReduce};
 use atlas_core::vm::exec::{ExecPolicy, Executor};
 use atlas_core::vm::heap::Boxed;
 use atlas_core::vm::term::{PrimId, Term};
@@ -65,6 +65,52 @@ impl WasmExtensions {
             .map_err(|error| error.to_string())?;
         Ok(store)
     }
+
+    /// `%wasm module input`: force both arguments, then compile and run the
+    /// module's `run` export under this extension's limits.
+    async fn invoke<'e, 'h, P: ExecPolicy, X: Extensions>(
+        &self,
+        exec: &Executor<'e, 'h, P, X>,
+        args: Vec<Handle<'h>>,
+    ) -> Result<Handle<'h>, String> {
+        let mut args = args.into_iter();
+        let module = exec
+            .whnf_at(args.next().expect("wasm module argument"))
+            .await;
+        let input = exec
+            .whnf_at(args.next().expect("wasm input argument"))
+            .await;
+        let bytes = match &*module.view() {
+            Term::Box(value) => match exec.heap.value_get(value) {
+                Boxed::Bytes(bytes) => bytes.clone(),
+                _ => return Err("%wasm expects its first argument to be Bytes".to_string()),
+            },
+            _ => return Err("%wasm expects its first argument to be Bytes".to_string()),
+        };
+        let module = Module::new(&self.engine, bytes)
+            .map_err(|error| format!("invalid WebAssembly module: {error}"))?;
+        if module.imports().next().is_some() {
+            return Err("%wasm modules must not import host functionality".to_string());
+        }
+        let mut store = self.store()?;
+        let instance = Instance::new(&mut store, &module, &[])
+            .map_err(|error| format!("failed to instantiate WebAssembly module: {error}"))?;
+        let result = match &*input.view() {
+            Term::Int(value) => instance
+                .get_typed_func::<i64, i64>(&mut store, "run")
+                .map_err(|error| format!("%wasm expects run: (i64) -> i64: {error}"))?
+                .call(&mut store, *value)
+                .map(Term::Int),
+            Term::Float(value) => instance
+                .get_typed_func::<f64, f64>(&mut store, "run")
+                .map_err(|error| format!("%wasm expects run: (f64) -> f64: {error}"))?
+                .call(&mut store, value.into_inner())
+                .map(|value| Term::Float(value.into())),
+            _ => return Err("%wasm input must be an Int or Float".to_string()),
+        }
+        .map_err(|error| format!("WebAssembly run trapped: {error}"))?;
+        Ok(Handle::new(exec.heap.alloc(result), exec.heap))
+    }
 }
 
 impl Default for WasmExtensions {
@@ -95,45 +141,11 @@ impl Extensions for WasmExtensions {
     ) -> PrimReduce<'a, 'h> {
         Box::pin(async move {
             if id.get() != WASM_ID {
-                return Err("unknown atlas-wasm primitive".to_string());
-            }
-            let mut args = args.into_iter();
-            let module = exec
-                .whnf_at(args.next().expect("wasm module argument"))
-                .await;
-            let input = exec
-                .whnf_at(args.next().expect("wasm input argument"))
-                .await;
-            let bytes = match &*module.view() {
-                Term::Box(value) => match exec.heap.value_get(value) {
-                    Boxed::Bytes(bytes) => bytes.clone(),
-                    _ => return Err("%wasm expects its first argument to be Bytes".to_string()),
-                },
-                _ => return Err("%wasm expects its first argument to be Bytes".to_string()),
-            };
-            let module = Module::new(&self.engine, bytes)
-                .map_err(|error| format!("invalid WebAssembly module: {error}"))?;
-            if module.imports().next().is_some() {
-                return Err("%wasm modules must not import host functionality".to_string());
+                return Err(PrimError::new(id, "unknown atlas-wasm primitive"));
             }
-            let mut store = self.store()?;
-            let instance = Instance::new(&mut store, &module, &[])
-                .map_err(|error| format!("failed to instantiate WebAssembly module: {error}"))?;
-            let result = match &*input.view() {
-                Term::Int(value) => instance
-                    .get_typed_func::<i64, i64>(&mut store, "run")
-                    .map_err(|error| format!("%wasm expects run: (i64) -> i64: {error}"))?
-                    .call(&mut store, *value)
-                    .map(Term::Int),
-                Term::Float(value) => instance
-                    .get_typed_func::<f64, f64>(&mut store, "run")
-                    .map_err(|error| format!("%wasm expects run: (f64) -> f64: {error}"))?
-                    .call(&mut store, value.into_inner())
-                    .map(|value| Term::Float(value.into())),
-                _ => return Err("%wasm input must be an Int or Float".to_string()),
-            }
-            .map_err(|error| format!("WebAssembly run trapped: {error}"))?;
-            Ok(Handle::new(exec.heap.alloc(result), exec.heap))
+            self.invoke(exec, args)
+                .await
+                .map_err(|message| PrimError::new(id, message))
         })
     }
 }
@@ -150,7 +162,7 @@ mod tests {
         wat::parse_str(source).unwrap()
     }
 
-    fn run(extension: &WasmExtensions, module: Vec<u8>, input: Value) -> Result<String, String> {
+    fn run(extension: &WasmExtensions, module: Vec<u8>, input: Value) -> Result<String, PrimError> {
         let expr = Expr::App {
             func: Box::new(Expr::App {
                 func: Box::new(Expr::Pri("wasm".to_string())),
@@ -160,7 +172,9 @@ mod tests {
         };
         let heap = Heap::new();
         heap.with(|h| {
-            let root = h.lower(&expr, &|name| extension.resolve(name), &mut |_| None)?;
+            let root = h
+                .lower(&expr, &|name| extension.resolve(name), &mut |_| None)
+                .unwrap();
             let exec = Executor::with_extensions(h, UnlimitedBudget, extension);
             let runtime = tokio::runtime::Builder::new_current_thread()
                 .build()
@@ -201,7 +215,7 @@ mod tests {
             "(module (import \"env\" \"x\" (func)) (func (export \"run\") (param i64) (result i64) local.get 0))",
         );
         let error = run(&WasmExtensions::default(), module, Value::Int(1)).unwrap_err();
-        assert!(error.contains("must not import"));
+        assert!(error.message.contains("must not import"));
     }
 
     #[test]
@@ -210,12 +224,14 @@ mod tests {
         assert!(
             run(&extension, vec![0, 1, 2], Value::Int(1))
                 .unwrap_err()
+                .message
                 .contains("invalid WebAssembly module")
         );
         let trap = wasm("(module (func (export \"run\") (param i64) (result i64) unreachable))");
         assert!(
             run(&extension, trap, Value::Int(1))
                 .unwrap_err()
+                .message
                 .contains("trapped")
         );
         let missing_run =
@@ -223,6 +239,7 @@ mod tests {
         assert!(
             run(&extension, missing_run, Value::Int(1))
                 .unwrap_err()
+                .message
                 .contains("expects run")
         );
     }
@@ -240,6 +257,7 @@ mod tests {
         assert!(
             run(&extension, module, Value::Int(1))
                 .unwrap_err()
+                .message
                 .contains("instantiate")
         );
     }
@@ -37,6 +37,17 @@ APP–LAM and APP–SUP are dual: one eliminates a lambda/application pair, whil
 the other propagates application through a superposition and creates a
 duplication.
 
+#rulebox([MAT–SUP], [
+  A match table $M$ applied to a superposed scrutinee is duplicated with the
+  scrutinee's label, and each copy matches one alternative:
+  $
+    M space (&^ell {a, b})
+    arrow.r_v
+    delta^ell m_0,m_1 := M space "in" space
+      &^ell {m_0 space a, m_1 space b}.
+  $
+])
+
 == Capability dispatch
 
 Rigid values may provide properties and operators through their runtime type
@@ -49,6 +49,29 @@ Full normalization preserves DUP and SUP nodes that cannot interact. Collapsing
 or enumerating superpositions is a separate consumer operation and is not part
 of either WHNF or normalization.
 
+== Collapse
+
+Collapse enumerates the alternatives of a term one normal form at a time.
+Each label $ell$ is a single choice. A branch records the side it took for
+every label on its path, and a later $&^ell {a, b}$ in that branch reduces to
+the recorded side. An undecided superposition reached through the fields of a
+constructor is lifted over it, duplicating the other fields and the type with
+the same label:
+$
+  K(t_1, ..., &^ell {a, b}, ..., t_n)
+  arrow.r
+  &^ell {K(t_1^0, ..., a, ..., t_n^0), K(t_1^1, ..., b, ..., t_n^1)}.
+$
+The head superposition then splits the branch in two. A branch without one is
+normalized and produced as an answer. Lambda bodies are not searched, so a
+superposition under a binder remains in its answer.
+
+Branches are explored breadth-first, so every answer is eventually produced
+even when the search is infinite, or depth-first, left side first. Enumeration
+stops at a limit on the number of answers or when the budget runs out, with
+the branches not yet explored still pending. In the REPL,
+`/collapse [bfs|dfs [<limit>]] <expr>` lists the answers of `expr`.
+
 == Budgets and divergence
 
 A finite reduction policy may stop either relation before a normal form is
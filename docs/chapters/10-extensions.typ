@@ -36,11 +36,73 @@ ownership implement the abstract substitution and erasure rules. The evaluator
 uses a spine to locate the next WHNF interaction and recursively normalizes
 children for full normalization.
 
-Its present `TypeInfo` representation contains lazy product or sum child nodes.
-That representation is an implementation precursor, not the normative
-three-component record defined here. In particular, the complete property and
-operator rows, dependent universes, projections, constraint graph, and static
-type-evaluation mode remain implementation work.
+A `TypeInfo` is the three-component record: a layout (`*`, an atom, a
+product, a sum, a sort, or a dependent product) and property and operator rows, each a list of named
+signatures with an open or closed tail. Field, argument and signature types are
+lazy child nodes. Only signatures are stored, since satisfaction never consults
+an implementation. Structural satisfaction, meet and join are executor
+operations, exposed to programs by the `%type_any`, `%type_prop`, `%type_op`,
+`%type_sat`, `%type_meet` and `%type_join` primitives of `TypeExtensions`. A
+meet or join forces only the entries both sides declare. Row variables are
+represented by the open flag alone, without presence or absence constraints.
+The constraint graph remains implementation work.
+
+The core parser writes sorts as `Prop` and `Type_0`, `Type_1`, ..., and a
+dependent product as `pi (x: A) -> B`, with `pi {x: A} -> B` for grade zero.
+The product's codomain is stored as a lazy lambda over its binder, and
+`typeof` of a type value computes its sort by the sort axioms and PI–PROP /
+PI–TYPE, taking the codomain at a fresh rigid witness. A record is placed in
+$"Type"_0$ without forcing its fields. Conversion is the `===` operator of the
+core: it reduces both operands only as far as the comparison demands, compares
+functions by applying both to one rigid witness, and compares operations stuck
+on a witness by congruence. A comparison that gets stuck leaves the operator
+stuck. A lambda's runtime descriptor is still the `Function` atom rather than a
+synthesized product.
+
+Projections are a `Prj` heap node. Its value is demanded first; the expected
+type is forced only once the value has a head, and the value's descriptor is
+compared against that `TypeInfo` by the satisfaction rules above. A sub-type
+that does not reduce leaves the projection stuck. Because a `TypeInfo` is an
+affine tree with no back-edges, a type unfolded by `fix` is compared only as
+deep as the reduction budget allows.
+
+Static type evaluation is implemented by a `TypeEvaluator` beside the
+executor. It reads a lowered graph without mutating it and assigns types over
+the fragment the core represents: lambdas, applications, duplications,
+superpositions, constructions, matches, operations, and type values. Its
+requirements are solved by subsumption over generalizable metavariables.
+Records keep the tag of an atomic layout as their nominal name, and their rows
+are satisfied entry by entry as at run time. The primitives of
+`TypeExtensions` have signatures, and `%type_any`, `%type_prop` and `%type_op`
+regularize to the record they build; static meets and joins are not computed.
+`%prop name v` is the property access that T–PROPERTY types: it constrains the
+receiver to an open property row and fails at run time, since rows carry no
+implementations. `fix` has its signature, and a type-level `fix` regularizes to
+a recursive type when it meets itself again at the same arguments; recursive
+types are compared coinductively. Other primitives report `unsupported`, and a
+term whose own self-application would need a recursive type is not proven. The
+REPL command `/type` reports the outcome for an expression.
+
+A heap region reachable from a set of roots can be captured as an `Image` and
+written in a versioned binary format, then restored into any heap, any number
+of times. Restoring renumbers every node and cell and gives each label family a
+fresh label, so a restored graph never annihilates with one already present.
+Ownership is what makes this sound: the loader rejects an image in which some
+node has other than one owner. A duplication or binder only half inside the
+region is restored with its missing half erased, which is what the abstract
+rules would do to the copy. Error backtraces and source spans are not kept,
+and primitives are recorded by name for the loading extension set to resolve.
+A `PortableTerm` is the in-memory form of an image of one closed term: a
+heap exports it once and any number of heaps import it, each import a fresh
+copy with its own labels.
+
+Garbage is a term no root reaches, lost without being erased. The executor's
+collector, `/gc` in the REPL, erases it by the ordinary erasure rules, so a
+live duplication or lambda that shares a cell with it sees that half dropped.
+Since every edge except a duplicand's is an owning one, a lost term can only
+reach itself through a duplication whose projections are both lost; the
+collector first swaps such a duplicand out of its cell and erases it on its
+own. Cells that no remaining node holds are freed last.
 
 The machine may specialize DUP–RIGID into separate rules for lambdas,
 applications, operations, types, constructors, and atomic values. It may also